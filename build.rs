@@ -6,4 +6,19 @@ fn main() {
     if target == "wasm32-unknown-unknown" {
         println!("cargo:rustc-cfg=getrandom_backend=\"wasm_js\"");
     }
+
+    // Commit reported by `/healthz`, so deploys can be verified; builds
+    // outside a git checkout report "unknown"
+    let commit = std::process::Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok());
+    if let Some(commit) = commit {
+        println!("cargo:rustc-env=GIT_COMMIT={}", commit.trim());
+    }
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs/heads");
 }
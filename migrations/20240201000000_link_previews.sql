@@ -0,0 +1,2 @@
+-- Link previews attached asynchronously to chat messages (JSON-encoded LinkPreview)
+ALTER TABLE chat_messages ADD COLUMN IF NOT EXISTS link_preview TEXT;
@@ -0,0 +1,2 @@
+-- Moderation marker set by the content filter on flagged messages
+ALTER TABLE chat_messages ADD COLUMN IF NOT EXISTS moderation_flag TEXT;
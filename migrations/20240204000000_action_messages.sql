@@ -0,0 +1,4 @@
+-- Allow `/me` action messages
+ALTER TABLE chat_messages DROP CONSTRAINT IF EXISTS chat_messages_message_type_check;
+ALTER TABLE chat_messages ADD CONSTRAINT chat_messages_message_type_check
+    CHECK (message_type IN ('text', 'image', 'file', 'system', 'action'));
@@ -0,0 +1,15 @@
+-- Periodic per-conversation version checkpoints for reconnect deltas
+CREATE TABLE IF NOT EXISTS conversation_checkpoints (
+    conversation_id UUID NOT NULL REFERENCES conversations(id) ON DELETE CASCADE,
+    -- Messages in the conversation up to and including this checkpoint
+    seq BIGINT NOT NULL,
+    version VARCHAR(255) NOT NULL,
+    -- Messages covered since the previous checkpoint
+    message_ids UUID[] NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (conversation_id, seq)
+);
+
+CREATE INDEX IF NOT EXISTS idx_conversation_checkpoints_created_at ON conversation_checkpoints(conversation_id, created_at);
+CREATE INDEX IF NOT EXISTS idx_chat_messages_conversation_version ON chat_messages(conversation_id, braid_version);
+CREATE INDEX IF NOT EXISTS idx_chat_messages_conversation_created_at ON chat_messages(conversation_id, created_at);
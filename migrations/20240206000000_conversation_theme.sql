@@ -0,0 +1,2 @@
+-- Per-user conversation theme color ("#rrggbb"; NULL uses the global theme)
+ALTER TABLE conversation_settings ADD COLUMN IF NOT EXISTS theme_color VARCHAR(7);
@@ -0,0 +1,22 @@
+-- Full-text search over message bodies, kept current by a trigger
+ALTER TABLE chat_messages ADD COLUMN IF NOT EXISTS search_vector TSVECTOR;
+
+UPDATE chat_messages
+SET search_vector = to_tsvector('english', content)
+WHERE search_vector IS NULL;
+
+CREATE OR REPLACE FUNCTION update_chat_messages_search_vector()
+RETURNS TRIGGER AS $$
+BEGIN
+    NEW.search_vector = to_tsvector('english', COALESCE(NEW.content, ''));
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS update_chat_messages_search_vector ON chat_messages;
+CREATE TRIGGER update_chat_messages_search_vector
+    BEFORE INSERT OR UPDATE OF content ON chat_messages
+    FOR EACH ROW
+    EXECUTE FUNCTION update_chat_messages_search_vector();
+
+CREATE INDEX IF NOT EXISTS idx_chat_messages_search_vector ON chat_messages USING GIN (search_vector);
@@ -0,0 +1,3 @@
+-- Per-user pinned conversations, in a manual order at the top of the sidebar
+ALTER TABLE conversation_settings ADD COLUMN IF NOT EXISTS pinned BOOLEAN NOT NULL DEFAULT false;
+ALTER TABLE conversation_settings ADD COLUMN IF NOT EXISTS pin_order INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+-- Who can send a user friend requests and first messages
+ALTER TABLE users ADD COLUMN IF NOT EXISTS message_privacy VARCHAR(20) NOT NULL DEFAULT 'everyone';
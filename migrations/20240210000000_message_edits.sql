@@ -0,0 +1,4 @@
+-- Message edits and delete tombstones. Deleted messages keep their row (with
+-- the content cleared) so later versions still have their parents.
+ALTER TABLE chat_messages ADD COLUMN IF NOT EXISTS edit_version TEXT;
+ALTER TABLE chat_messages ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
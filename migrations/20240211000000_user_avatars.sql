@@ -0,0 +1,4 @@
+-- Profile pictures. The image lives in attachment storage under
+-- `avatars/{user_id}`; the timestamp versions avatar URLs so clients refetch
+-- after a change.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS avatar_updated_at TIMESTAMPTZ;
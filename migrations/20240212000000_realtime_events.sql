@@ -0,0 +1,13 @@
+-- Realtime events kept for later replay. Only durable event types are
+-- stored here; typing, presence and receipts are refused by the server
+-- (see `EventType::is_ephemeral`).
+CREATE TABLE IF NOT EXISTS realtime_events (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    event_type TEXT NOT NULL,
+    payload JSONB NOT NULL,
+    version TEXT,
+    occurred_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_realtime_events_occurred_at ON realtime_events(occurred_at);
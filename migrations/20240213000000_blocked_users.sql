@@ -0,0 +1,10 @@
+-- Users each user has blocked. Blocked users can't send them friend
+-- requests or direct messages; clients also hide them.
+CREATE TABLE IF NOT EXISTS blocked_users (
+    blocker_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    blocked_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (blocker_id, blocked_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_blocked_users_blocked_id ON blocked_users(blocked_id);
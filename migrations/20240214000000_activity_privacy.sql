@@ -0,0 +1,4 @@
+-- Whether a user shares typing indicators, online status and read receipts
+ALTER TABLE users ADD COLUMN IF NOT EXISTS send_typing BOOLEAN NOT NULL DEFAULT TRUE;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS send_presence BOOLEAN NOT NULL DEFAULT TRUE;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS send_read_receipts BOOLEAN NOT NULL DEFAULT TRUE;
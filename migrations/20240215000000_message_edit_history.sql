@@ -0,0 +1,12 @@
+-- Versions of a message that were replaced by an edit, oldest first by id.
+-- Cleared when the message is deleted so removed content doesn't linger.
+CREATE TABLE IF NOT EXISTS message_edits (
+    id BIGSERIAL PRIMARY KEY,
+    message_id UUID NOT NULL REFERENCES chat_messages(id) ON DELETE CASCADE,
+    version TEXT NOT NULL,
+    content TEXT NOT NULL,
+    written_at TIMESTAMPTZ NOT NULL,
+    replaced_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_message_edits_message_id ON message_edits(message_id, id);
@@ -0,0 +1,24 @@
+-- Server-assigned, per-conversation message order. `last_sequence` is the
+-- counter bumped when a message is stored; clients display by `sequence`.
+ALTER TABLE conversations ADD COLUMN IF NOT EXISTS last_sequence BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE chat_messages ADD COLUMN IF NOT EXISTS sequence BIGINT;
+
+-- Existing messages are numbered in the order they were sent
+UPDATE chat_messages m
+SET sequence = numbered.sequence
+FROM (
+    SELECT id, ROW_NUMBER() OVER (PARTITION BY conversation_id ORDER BY created_at, id) AS sequence
+    FROM chat_messages
+) numbered
+WHERE m.id = numbered.id AND m.sequence IS NULL;
+
+UPDATE conversations c
+SET last_sequence = latest.sequence
+FROM (
+    SELECT conversation_id, MAX(sequence) AS sequence
+    FROM chat_messages
+    GROUP BY conversation_id
+) latest
+WHERE c.id = latest.conversation_id;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_chat_messages_conversation_sequence ON chat_messages(conversation_id, sequence);
@@ -0,0 +1,4 @@
+-- Prefix lookups for the add-friend user search, which matches
+-- case-insensitively on usernames and emails
+CREATE INDEX IF NOT EXISTS idx_users_username_search ON users (LOWER(username) text_pattern_ops);
+CREATE INDEX IF NOT EXISTS idx_users_email_search ON users (LOWER(email) text_pattern_ops);
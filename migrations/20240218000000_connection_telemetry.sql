@@ -0,0 +1,13 @@
+-- Reconnection reports from clients that opted in to connection telemetry.
+-- Reports are anonymous: no user, conversation or address is stored.
+CREATE TABLE IF NOT EXISTS connection_telemetry (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    transport TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    attempts INTEGER NOT NULL,
+    backoff_ms BIGINT NOT NULL,
+    recovered BOOLEAN NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_connection_telemetry_created_at ON connection_telemetry(created_at);
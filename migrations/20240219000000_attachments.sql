@@ -0,0 +1,11 @@
+-- Who may download an attachment: its uploader, and the participants of the
+-- conversation it was shared in. The bytes live in attachment storage under
+-- `attachments/{id}`.
+CREATE TABLE IF NOT EXISTS attachments (
+    id UUID PRIMARY KEY,
+    uploader_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    conversation_id UUID REFERENCES conversations(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_attachments_conversation ON attachments(conversation_id);
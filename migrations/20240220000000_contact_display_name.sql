@@ -0,0 +1,3 @@
+-- The contact queries read a per-contact display name, but the base schema
+-- never created the column.
+ALTER TABLE contacts ADD COLUMN IF NOT EXISTS display_name VARCHAR(255);
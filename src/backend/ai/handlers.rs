@@ -0,0 +1,358 @@
+//! AI HTTP Handlers
+//!
+//! Endpoints for AI actions on messages and conversations. Callers must be
+//! participants of the conversation involved.
+
+use axum::{
+    body::Body,
+    extract::State,
+    http::{header, header::RETRY_AFTER, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::provider::AiError;
+use super::service::AiService;
+use crate::backend::messaging::conversation_cache::ConversationCache;
+use crate::backend::messaging::db::{get_conversation_message, get_message_range};
+use crate::backend::messaging::handlers::extract_user_id;
+use crate::shared::messaging::{
+    display_text, normalize_locale, MessageType, SummarizeConversationRequest, TranslateMessageRequest,
+    TranslateMessageResponse,
+};
+
+/// Most messages a summary covers (the newest of the range)
+const MAX_SUMMARY_MESSAGES: i64 = 200;
+
+/// Translate a message into the caller's locale
+///
+/// POST /api/ai/translate
+///
+/// The translation is returned to the caller only; the message itself is
+/// unchanged.
+pub async fn translate_message(
+    State(db_pool): State<Option<PgPool>>,
+    State(conversations): State<ConversationCache>,
+    State(ai): State<AiService>,
+    headers: HeaderMap,
+    Json(request): Json<TranslateMessageRequest>,
+) -> Result<Json<TranslateMessageResponse>, Response> {
+    let pool = db_pool.as_ref().ok_or_else(|| StatusCode::SERVICE_UNAVAILABLE.into_response())?;
+    let user_id = extract_user_id(&headers).map_err(IntoResponse::into_response)?;
+
+    let target_locale = normalize_locale(&request.target_locale)
+        .ok_or_else(|| StatusCode::BAD_REQUEST.into_response())?;
+
+    require_participant(pool, &conversations, user_id, request.conversation_id).await?;
+
+    let message = get_conversation_message(pool, request.conversation_id, request.message_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to load message {}: {:?}", request.message_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        })?
+        .ok_or_else(|| StatusCode::NOT_FOUND.into_response())?;
+    if message.is_deleted {
+        return Err(StatusCode::GONE.into_response());
+    }
+
+    let translation = ai
+        .translate(user_id, &message.content, &target_locale)
+        .await
+        .map_err(ai_error_response)?;
+
+    Ok(Json(TranslateMessageResponse {
+        message_id: message.id,
+        target_locale,
+        translation,
+    }))
+}
+
+/// Summarize a range of a conversation's messages
+///
+/// POST /api/ai/summarize
+///
+/// The summary streams back as `text/plain` while the provider writes it
+/// and is not stored. An empty range returns `204 No Content` without
+/// using quota.
+pub async fn summarize_conversation(
+    State(db_pool): State<Option<PgPool>>,
+    State(conversations): State<ConversationCache>,
+    State(ai): State<AiService>,
+    headers: HeaderMap,
+    Json(request): Json<SummarizeConversationRequest>,
+) -> Result<Response, Response> {
+    let pool = db_pool.as_ref().ok_or_else(|| StatusCode::SERVICE_UNAVAILABLE.into_response())?;
+    let user_id = extract_user_id(&headers).map_err(IntoResponse::into_response)?;
+
+    require_participant(pool, &conversations, user_id, request.conversation_id).await?;
+
+    let messages = get_message_range(
+        pool,
+        request.conversation_id,
+        request.from_message_id,
+        request.to_message_id,
+        MAX_SUMMARY_MESSAGES,
+    )
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to load messages of {}: {:?}", request.conversation_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR.into_response()
+    })?;
+
+    let lines: Vec<String> = messages
+        .iter()
+        .filter(|(_, message)| !message.content.trim().is_empty())
+        .map(|(sender, message)| match message.message_type {
+            MessageType::Action => format!("* {} {}", sender, display_text(&message.content)),
+            _ => format!("{}: {}", sender, display_text(&message.content)),
+        })
+        .collect();
+    if lines.is_empty() {
+        return Ok(StatusCode::NO_CONTENT.into_response());
+    }
+
+    let summary = ai.summarize(user_id, &lines).await.map_err(ai_error_response)?;
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "text/plain; charset=utf-8")
+        .header(header::CACHE_CONTROL, "no-store")
+        .header(header::X_CONTENT_TYPE_OPTIONS, "nosniff")
+        .body(Body::from_stream(summary))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())
+}
+
+/// `403` unless `user_id` is in the conversation
+async fn require_participant(
+    pool: &PgPool,
+    conversations: &ConversationCache,
+    user_id: Uuid,
+    conversation_id: Uuid,
+) -> Result<(), Response> {
+    let is_participant = conversations
+        .is_participant(pool, user_id, conversation_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to check participant: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        })?;
+    if is_participant {
+        Ok(())
+    } else {
+        Err(StatusCode::FORBIDDEN.into_response())
+    }
+}
+
+/// Map an [`AiError`] to the status clients act on
+fn ai_error_response(error: AiError) -> Response {
+    match error {
+        AiError::QuotaExceeded { retry_after } => {
+            let mut response = StatusCode::TOO_MANY_REQUESTS.into_response();
+            let secs = retry_after.as_secs().max(1);
+            response.headers_mut().insert(RETRY_AFTER, HeaderValue::from(secs));
+            response
+        }
+        AiError::NotConfigured => StatusCode::SERVICE_UNAVAILABLE.into_response(),
+        AiError::InputTooLong(_) => StatusCode::PAYLOAD_TOO_LARGE.into_response(),
+        AiError::Timeout | AiError::EmptyResponse | AiError::Provider(_) => StatusCode::BAD_GATEWAY.into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use chrono::{TimeZone, Utc};
+    use crate::backend::ai::provider::AiFuture;
+    use crate::backend::ai::{AiConfig, AiProvider, SharedAiProvider, StubProvider};
+    use crate::backend::auth::users::User;
+    use crate::backend::messaging::db;
+    use crate::backend::test_db::{auth, TestDatabase};
+    use crate::shared::messaging::ChatMessage;
+
+    /// Provider whose upstream is always down
+    struct FailingProvider;
+
+    impl AiProvider for FailingProvider {
+        fn name(&self) -> &str {
+            "failing"
+        }
+
+        fn complete<'a>(&'a self, _instructions: &'a str, _input: &'a str) -> AiFuture<'a> {
+            Box::pin(async { Err(AiError::Provider("upstream unavailable".to_string())) })
+        }
+    }
+
+    fn ai(provider: SharedAiProvider, daily_quota: usize) -> AiService {
+        AiService::new(provider, AiConfig { daily_quota, ..AiConfig::default() })
+    }
+
+    async fn translate(
+        pool: &PgPool,
+        ai: &AiService,
+        user: &User,
+        message: &ChatMessage,
+        target_locale: &str,
+    ) -> Result<TranslateMessageResponse, Response> {
+        let request = TranslateMessageRequest {
+            conversation_id: message.conversation_id,
+            message_id: message.id,
+            target_locale: target_locale.to_string(),
+        };
+        translate_message(
+            State(Some(pool.clone())),
+            State(ConversationCache::default()),
+            State(ai.clone()),
+            auth(user),
+            Json(request),
+        )
+        .await
+        .map(|Json(response)| response)
+    }
+
+    async fn message(pool: &PgPool, sender: &User, conversation_id: Uuid, content: &str) -> ChatMessage {
+        let message = ChatMessage::new_text(conversation_id, sender.id, content.to_string(), 1);
+        db::store_message(pool, &message).await.unwrap();
+        message
+    }
+
+    async fn summarize(
+        pool: &PgPool,
+        ai: &AiService,
+        user: &User,
+        conversation_id: Uuid,
+        from: Option<&ChatMessage>,
+        to: Option<&ChatMessage>,
+    ) -> Result<Response, Response> {
+        let request = SummarizeConversationRequest {
+            conversation_id,
+            from_message_id: from.map(|m| m.id),
+            to_message_id: to.map(|m| m.id),
+        };
+        summarize_conversation(
+            State(Some(pool.clone())),
+            State(ConversationCache::default()),
+            State(ai.clone()),
+            auth(user),
+            Json(request),
+        )
+        .await
+    }
+
+    async fn body(response: Response) -> String {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    /// Store a message `second` seconds into a fixed minute, so ranges are exact
+    async fn message_at(pool: &PgPool, sender: &User, conversation_id: Uuid, content: &str, second: u32) -> ChatMessage {
+        let mut message = ChatMessage::new_text(conversation_id, sender.id, content.to_string(), second as u64);
+        message.timestamp = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, second).unwrap();
+        db::store_message(pool, &message).await.unwrap();
+        message
+    }
+
+    #[tokio::test]
+    async fn test_translate_with_stub_provider_and_quota() {
+        let db = TestDatabase::new().await;
+        let pool = db.pool().clone();
+        let (alice, bob, stranger) = (db.user().await, db.user().await, db.user().await);
+        let conversation_id = db::create_conversation(&pool, alice.id, bob.id).await.unwrap();
+        let hello = message(&pool, &alice, conversation_id, "hello").await;
+        let bye = message(&pool, &alice, conversation_id, "bye").await;
+        let ai = ai(Arc::new(StubProvider), 1);
+
+        let response = translate(&pool, &ai, &bob, &hello, "de_DE.UTF-8").await.unwrap();
+        assert_eq!(response.translation, "[stub] hello");
+        assert_eq!(response.target_locale, "de-DE");
+        assert_eq!(response.message_id, hello.id);
+
+        // Translating it again is cached and free; anything new is over quota
+        assert!(translate(&pool, &ai, &bob, &hello, "de-DE").await.is_ok());
+        let over_quota = translate(&pool, &ai, &bob, &bye, "de-DE").await.unwrap_err();
+        assert_eq!(over_quota.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(over_quota.headers().contains_key(RETRY_AFTER));
+
+        // Quotas are per user
+        assert!(translate(&pool, &ai, &alice, &bye, "de-DE").await.is_ok());
+
+        // Only participants, valid locales and live messages
+        assert_eq!(translate(&pool, &ai, &stranger, &hello, "de").await.unwrap_err().status(), StatusCode::FORBIDDEN);
+        assert_eq!(translate(&pool, &ai, &alice, &hello, "not a locale").await.unwrap_err().status(), StatusCode::BAD_REQUEST);
+        assert!(db::delete_message(&pool, hello.id).await.unwrap());
+        assert_eq!(translate(&pool, &ai, &alice, &hello, "fr").await.unwrap_err().status(), StatusCode::GONE);
+    }
+
+    #[tokio::test]
+    async fn test_provider_failures_do_not_use_quota() {
+        let db = TestDatabase::new().await;
+        let pool = db.pool().clone();
+        let (alice, bob) = (db.user().await, db.user().await);
+        let conversation_id = db::create_conversation(&pool, alice.id, bob.id).await.unwrap();
+        let hello = message(&pool, &alice, conversation_id, "hello").await;
+
+        let failing = ai(Arc::new(FailingProvider), 1);
+        let error = translate(&pool, &failing, &bob, &hello, "de").await.unwrap_err();
+        assert_eq!(error.status(), StatusCode::BAD_GATEWAY);
+        assert_eq!(failing.remaining_quota(bob.id), 1);
+
+        let disabled = AiService::default();
+        let error = translate(&pool, &disabled, &bob, &hello, "de").await.unwrap_err();
+        assert_eq!(error.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn test_summarize_range_with_stub_provider() {
+        let db = TestDatabase::new().await;
+        let pool = db.pool().clone();
+        let (alice, bob, stranger) = (db.user().await, db.user().await, db.user().await);
+        let conversation_id = db::create_conversation(&pool, alice.id, bob.id).await.unwrap();
+        let read = message_at(&pool, &alice, conversation_id, "old news", 1).await;
+        let lunch = message_at(&pool, &alice, conversation_id, "lunch at noon?", 2).await;
+        let gone = message_at(&pool, &bob, conversation_id, "oops", 3).await;
+        let yes = message_at(&pool, &bob, conversation_id, "yes, the usual place", 4).await;
+        assert!(db::delete_message(&pool, gone.id).await.unwrap());
+        let ai = AiService::new(Arc::new(StubProvider), AiConfig::default());
+
+        let response = summarize(&pool, &ai, &bob, conversation_id, Some(&lunch), Some(&yes)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers()[header::CONTENT_TYPE].to_str().unwrap().starts_with("text/plain"));
+        assert_eq!(
+            body(response).await,
+            format!("[stub] {}: lunch at noon?\n{}: yes, the usual place", alice.username, bob.username)
+        );
+
+        // Open-ended ranges run from the oldest or to the newest
+        let everything = summarize(&pool, &ai, &bob, conversation_id, None, None).await.unwrap();
+        assert!(body(everything).await.contains("old news"));
+        let tail = summarize(&pool, &ai, &bob, conversation_id, Some(&yes), None).await.unwrap();
+        assert!(!body(tail).await.contains(&read.content));
+
+        // Nothing left in the range: no summary and no quota used
+        let empty = summarize(&pool, &ai, &bob, conversation_id, Some(&gone), Some(&gone)).await.unwrap();
+        assert_eq!(empty.status(), StatusCode::NO_CONTENT);
+        assert_eq!(ai.remaining_quota(bob.id), AiConfig::default().daily_quota - 3);
+
+        assert_eq!(
+            summarize(&pool, &ai, &stranger, conversation_id, None, None).await.unwrap_err().status(),
+            StatusCode::FORBIDDEN
+        );
+    }
+
+    #[tokio::test]
+    async fn test_summaries_use_quota() {
+        let db = TestDatabase::new().await;
+        let pool = db.pool().clone();
+        let (alice, bob) = (db.user().await, db.user().await);
+        let conversation_id = db::create_conversation(&pool, alice.id, bob.id).await.unwrap();
+        message_at(&pool, &alice, conversation_id, "hi", 1).await;
+        let ai = AiService::new(Arc::new(StubProvider), AiConfig { daily_quota: 1, ..AiConfig::default() });
+
+        assert!(summarize(&pool, &ai, &bob, conversation_id, None, None).await.is_ok());
+        let over_quota = summarize(&pool, &ai, &bob, conversation_id, None, None).await.unwrap_err();
+        assert_eq!(over_quota.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+}
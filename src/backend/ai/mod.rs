@@ -0,0 +1,20 @@
+//! AI Features
+//!
+//! Optional AI actions on messages, backed by a pluggable model provider:
+//!
+//! - **`provider`** - The [`AiProvider`] trait, a `genai` backed provider
+//!   for hosted models and a deterministic stub for tests
+//! - **`service`** - [`AiService`]: per-user quotas, timeouts and the
+//!   prompts for each action
+//! - **`handlers`** - HTTP endpoints (`POST /api/ai/translate`,
+//!   `POST /api/ai/summarize`)
+//!
+//! Results (translations, conversation summaries) are returned to the
+//! requesting user only and never stored in the conversation.
+
+pub mod provider;
+pub mod service;
+pub mod handlers;
+
+pub use provider::{ai_provider_from_env, AiError, AiProvider, AiStream, SharedAiProvider, StubProvider};
+pub use service::{AiConfig, AiService};
@@ -0,0 +1,189 @@
+//! AI Providers
+//!
+//! [`AiProvider`] runs a single prompt against a model. Providers only talk
+//! to the model; quotas and timeouts are applied by
+//! [`AiService`](super::AiService).
+//!
+//! # Configuration
+//!
+//! - `AI_PROVIDER=stub` - use [`StubProvider`] (local development and tests)
+//! - `AI_MODEL` - model for [`GenaiProvider`], e.g. `gpt-4o-mini` or
+//!   `claude-3-5-haiku-latest`; the provider is picked from the model name
+//!   and reads its usual API key variable (`OPENAI_API_KEY`,
+//!   `ANTHROPIC_API_KEY`, ...)
+//!
+//! With neither set AI features are disabled and report
+//! [`AiError::NotConfigured`].
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures_util::{stream, Stream, StreamExt, TryStreamExt};
+use genai::chat::{ChatMessage as GenaiMessage, ChatRequest, ChatStreamEvent};
+use thiserror::Error;
+
+/// Why an AI request failed
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum AiError {
+    #[error("AI features are not configured")]
+    NotConfigured,
+
+    #[error("AI quota exceeded")]
+    QuotaExceeded { retry_after: Duration },
+
+    #[error("Input too long ({0} characters)")]
+    InputTooLong(usize),
+
+    #[error("Timed out")]
+    Timeout,
+
+    #[error("Provider returned no text")]
+    EmptyResponse,
+
+    #[error("Provider error: {0}")]
+    Provider(String),
+}
+
+/// Future returned by [`AiProvider::complete`]
+pub type AiFuture<'a> = Pin<Box<dyn Future<Output = Result<String, AiError>> + Send + 'a>>;
+
+/// Text chunks returned by [`AiProvider::stream`]
+pub type AiStream<'a> = Pin<Box<dyn Stream<Item = Result<String, AiError>> + Send + 'a>>;
+
+/// A model that turns a prompt into text
+pub trait AiProvider: Send + Sync {
+    /// Short name for logs
+    fn name(&self) -> &str;
+
+    /// Run `input` with `instructions` as the system prompt
+    fn complete<'a>(&'a self, instructions: &'a str, input: &'a str) -> AiFuture<'a>;
+
+    /// Like [`complete`](Self::complete), but yields text as it is generated
+    ///
+    /// Providers that can't stream answer in a single chunk.
+    fn stream<'a>(&'a self, instructions: &'a str, input: &'a str) -> AiStream<'a> {
+        Box::pin(stream::once(self.complete(instructions, input)))
+    }
+}
+
+/// Provider shared through `AppState`
+pub type SharedAiProvider = Arc<dyn AiProvider>;
+
+/// Default provider: AI features are off
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DisabledProvider;
+
+impl AiProvider for DisabledProvider {
+    fn name(&self) -> &str {
+        "disabled"
+    }
+
+    fn complete<'a>(&'a self, _instructions: &'a str, _input: &'a str) -> AiFuture<'a> {
+        Box::pin(async { Err(AiError::NotConfigured) })
+    }
+}
+
+/// Deterministic provider for tests and local development
+///
+/// Answers every prompt with the input prefixed by `[stub]`; streamed
+/// answers arrive one line at a time.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StubProvider;
+
+impl AiProvider for StubProvider {
+    fn name(&self) -> &str {
+        "stub"
+    }
+
+    fn complete<'a>(&'a self, _instructions: &'a str, input: &'a str) -> AiFuture<'a> {
+        Box::pin(async move { Ok(format!("[stub] {}", input)) })
+    }
+
+    fn stream<'a>(&'a self, _instructions: &'a str, input: &'a str) -> AiStream<'a> {
+        let chunks: Vec<_> = std::iter::once("[stub] ".to_string())
+            .chain(input.split_inclusive('\n').map(str::to_string))
+            .map(Ok)
+            .collect();
+        Box::pin(stream::iter(chunks))
+    }
+}
+
+/// Hosted models through the `genai` client
+pub struct GenaiProvider {
+    client: genai::Client,
+    model: String,
+}
+
+impl GenaiProvider {
+    pub fn new(model: impl Into<String>) -> Self {
+        Self {
+            client: genai::Client::default(),
+            model: model.into(),
+        }
+    }
+}
+
+impl AiProvider for GenaiProvider {
+    fn name(&self) -> &str {
+        &self.model
+    }
+
+    fn complete<'a>(&'a self, instructions: &'a str, input: &'a str) -> AiFuture<'a> {
+        Box::pin(async move {
+            let request = ChatRequest::new(vec![
+                GenaiMessage::system(instructions),
+                GenaiMessage::user(input),
+            ]);
+            let response = self
+                .client
+                .exec_chat(&self.model, request, None)
+                .await
+                .map_err(|e| AiError::Provider(e.to_string()))?;
+            response
+                .first_text()
+                .map(|text| text.trim().to_string())
+                .filter(|text| !text.is_empty())
+                .ok_or(AiError::EmptyResponse)
+        })
+    }
+
+    fn stream<'a>(&'a self, instructions: &'a str, input: &'a str) -> AiStream<'a> {
+        let start = async move {
+            let request = ChatRequest::new(vec![
+                GenaiMessage::system(instructions),
+                GenaiMessage::user(input),
+            ]);
+            let response = self
+                .client
+                .exec_chat_stream(&self.model, request, None)
+                .await
+                .map_err(|e| AiError::Provider(e.to_string()))?;
+            Ok::<_, AiError>(response.stream.filter_map(|event| async move {
+                match event {
+                    Ok(ChatStreamEvent::Chunk(chunk)) => Some(Ok(chunk.content)),
+                    Ok(_) => None,
+                    Err(e) => Some(Err(AiError::Provider(e.to_string()))),
+                }
+            }))
+        };
+        Box::pin(stream::once(start).try_flatten())
+    }
+}
+
+/// Build the configured provider from `AI_PROVIDER` and `AI_MODEL`
+pub fn ai_provider_from_env() -> SharedAiProvider {
+    if std::env::var("AI_PROVIDER").is_ok_and(|p| p.eq_ignore_ascii_case("stub")) {
+        tracing::warn!("Using the stub AI provider");
+        return Arc::new(StubProvider);
+    }
+
+    match std::env::var("AI_MODEL") {
+        Ok(model) if !model.trim().is_empty() => {
+            tracing::info!("AI features use model {}", model.trim());
+            Arc::new(GenaiProvider::new(model.trim()))
+        }
+        _ => Arc::new(DisabledProvider),
+    }
+}
@@ -0,0 +1,310 @@
+//! AI Service
+//!
+//! Wraps the configured [`AiProvider`] with the limits every AI action
+//! shares: a rolling per-user daily quota, an input size cap and a timeout.
+//! A request only counts against the quota if the provider answered.
+//! Streamed answers apply the timeout to each chunk.
+//!
+//! # Configuration
+//!
+//! - `AI_DAILY_QUOTA` - AI requests per user per 24 hours (default 50)
+//! - `AI_MAX_INPUT_CHARS` - longest input sent to the provider (default 4000)
+//! - `AI_TIMEOUT_SECS` - provider timeout (default 20)
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use futures_util::{stream, StreamExt};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use uuid::Uuid;
+
+use super::provider::{ai_provider_from_env, AiError, AiStream, DisabledProvider, SharedAiProvider};
+use crate::shared::messaging::display_text;
+
+/// Window for the per-user quota
+const QUOTA_WINDOW: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Maximum number of cached translations
+const MAX_CACHE_ENTRIES: usize = 1024;
+
+/// Limits applied to AI requests
+#[derive(Debug, Clone)]
+pub struct AiConfig {
+    pub daily_quota: usize,
+    pub max_input_chars: usize,
+    pub timeout: Duration,
+}
+
+impl Default for AiConfig {
+    fn default() -> Self {
+        Self {
+            daily_quota: 50,
+            max_input_chars: 4000,
+            timeout: Duration::from_secs(20),
+        }
+    }
+}
+
+impl AiConfig {
+    /// Read limits from `AI_*` environment variables
+    pub fn from_env() -> Self {
+        fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T {
+            std::env::var(name).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+        }
+
+        let defaults = Self::default();
+        Self {
+            daily_quota: env_or("AI_DAILY_QUOTA", defaults.daily_quota),
+            max_input_chars: env_or("AI_MAX_INPUT_CHARS", defaults.max_input_chars),
+            timeout: Duration::from_secs(env_or("AI_TIMEOUT_SECS", defaults.timeout.as_secs())),
+        }
+    }
+}
+
+struct AiInner {
+    provider: SharedAiProvider,
+    config: AiConfig,
+    usage: Mutex<HashMap<Uuid, VecDeque<Instant>>>,
+    /// Translations keyed by (target locale, source text)
+    translations: Mutex<HashMap<(String, String), String>>,
+}
+
+/// Quota-limited access to the AI provider
+#[derive(Clone)]
+pub struct AiService {
+    inner: Arc<AiInner>,
+}
+
+impl AiService {
+    pub fn new(provider: SharedAiProvider, config: AiConfig) -> Self {
+        Self {
+            inner: Arc::new(AiInner {
+                provider,
+                config,
+                usage: Mutex::new(HashMap::new()),
+                translations: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(ai_provider_from_env(), AiConfig::from_env())
+    }
+
+    /// Requests `user_id` has left in the current window
+    pub fn remaining_quota(&self, user_id: Uuid) -> usize {
+        let now = Instant::now();
+        let mut usage = self.inner.usage.lock().unwrap();
+        let window = usage.entry(user_id).or_default();
+        prune(window, now);
+        self.inner.config.daily_quota.saturating_sub(window.len())
+    }
+
+    /// Translate message content into `target_locale`
+    ///
+    /// Mention tokens are rendered as `@username` first so the provider
+    /// never sees user ids. Repeated requests for the same text and locale
+    /// are answered from cache without using quota.
+    pub async fn translate(&self, user_id: Uuid, content: &str, target_locale: &str) -> Result<String, AiError> {
+        let text = display_text(content);
+        let key = (target_locale.to_string(), text);
+        if let Some(cached) = self.inner.translations.lock().unwrap().get(&key) {
+            return Ok(cached.clone());
+        }
+
+        let instructions = format!(
+            "Translate the user's chat message into the language with BCP 47 tag \"{}\". \
+             Keep names, @mentions, URLs, code and emoji unchanged. \
+             Reply with the translation only.",
+            target_locale
+        );
+        let translation = self.run(user_id, &instructions, &key.1).await?;
+
+        let mut cache = self.inner.translations.lock().unwrap();
+        if cache.len() >= MAX_CACHE_ENTRIES {
+            cache.clear();
+        }
+        cache.insert(key, translation.clone());
+        Ok(translation)
+    }
+
+    /// Summarize a conversation transcript, streaming the summary
+    ///
+    /// `lines` are the messages oldest first; if they don't all fit the
+    /// input limit the oldest are left out. Fails before streaming if the
+    /// provider can't start, in which case no quota is used.
+    pub async fn summarize(&self, user_id: Uuid, lines: &[String]) -> Result<AiStream<'static>, AiError> {
+        let mut budget = self.inner.config.max_input_chars;
+        let mut start = lines.len();
+        while start > 0 {
+            let len = lines[start - 1].chars().count() + 1;
+            if len > budget {
+                break;
+            }
+            budget -= len;
+            start -= 1;
+        }
+        if start == lines.len() && !lines.is_empty() {
+            let chars = lines.last().map(|line| line.chars().count()).unwrap_or_default();
+            return Err(AiError::InputTooLong(chars));
+        }
+        let transcript = lines[start..].join("\n");
+
+        let used_at = self.consume_quota(user_id)?;
+
+        let inner = self.inner.clone();
+        let (tx, mut rx) = mpsc::channel(16);
+        tokio::spawn(async move {
+            let instructions = "Summarize this chat transcript for someone catching up on it. \
+                                Lead with decisions, questions and anything addressed to them. \
+                                Be brief and use the transcript's language.";
+            let mut chunks = inner.provider.stream(instructions, &transcript);
+            loop {
+                let chunk = match tokio::time::timeout(inner.config.timeout, chunks.next()).await {
+                    Ok(Some(chunk)) => chunk,
+                    Ok(None) => break,
+                    Err(_) => Err(AiError::Timeout),
+                };
+                let failed = chunk.is_err();
+                if tx.send(chunk).await.is_err() || failed {
+                    break;
+                }
+            }
+        });
+
+        match rx.recv().await.unwrap_or(Err(AiError::EmptyResponse)) {
+            Ok(first) => Ok(Box::pin(stream::once(async move { Ok(first) }).chain(ReceiverStream::new(rx)))),
+            Err(e) => {
+                tracing::warn!("AI provider {} failed: {}", self.inner.provider.name(), e);
+                self.refund_quota(user_id, used_at);
+                Err(e)
+            }
+        }
+    }
+
+    /// Run one prompt for `user_id`, enforcing quota, input size and timeout
+    async fn run(&self, user_id: Uuid, instructions: &str, input: &str) -> Result<String, AiError> {
+        let chars = input.chars().count();
+        if chars > self.inner.config.max_input_chars {
+            return Err(AiError::InputTooLong(chars));
+        }
+
+        let used_at = self.consume_quota(user_id)?;
+
+        let result = tokio::time::timeout(self.inner.config.timeout, self.inner.provider.complete(instructions, input))
+            .await
+            .unwrap_or(Err(AiError::Timeout));
+
+        if let Err(e) = &result {
+            tracing::warn!("AI provider {} failed: {}", self.inner.provider.name(), e);
+            self.refund_quota(user_id, used_at);
+        }
+        result
+    }
+
+    fn consume_quota(&self, user_id: Uuid) -> Result<Instant, AiError> {
+        let now = Instant::now();
+        let mut usage = self.inner.usage.lock().unwrap();
+        let window = usage.entry(user_id).or_default();
+        prune(window, now);
+        if window.len() >= self.inner.config.daily_quota {
+            let retry_after = window
+                .front()
+                .map(|oldest| QUOTA_WINDOW.saturating_sub(now.duration_since(*oldest)))
+                .unwrap_or(QUOTA_WINDOW);
+            return Err(AiError::QuotaExceeded { retry_after });
+        }
+        window.push_back(now);
+        Ok(now)
+    }
+
+    fn refund_quota(&self, user_id: Uuid, used_at: Instant) {
+        let mut usage = self.inner.usage.lock().unwrap();
+        if let Some(window) = usage.get_mut(&user_id) {
+            if let Some(pos) = window.iter().rposition(|t| *t == used_at) {
+                window.remove(pos);
+            }
+        }
+    }
+}
+
+impl Default for AiService {
+    /// AI features turned off
+    fn default() -> Self {
+        Self::new(Arc::new(DisabledProvider), AiConfig::default())
+    }
+}
+
+fn prune(window: &mut VecDeque<Instant>, now: Instant) {
+    while window.front().is_some_and(|t| now.duration_since(*t) > QUOTA_WINDOW) {
+        window.pop_front();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::ai::provider::StubProvider;
+
+    fn service(provider: SharedAiProvider, daily_quota: usize) -> AiService {
+        AiService::new(
+            provider,
+            AiConfig {
+                daily_quota,
+                ..AiConfig::default()
+            },
+        )
+    }
+
+    #[tokio::test]
+    async fn test_quota_is_per_user_and_cached_translations_are_free() {
+        let ai = service(Arc::new(StubProvider), 1);
+        let alice = Uuid::new_v4();
+        let bob = Uuid::new_v4();
+
+        assert_eq!(ai.translate(alice, "hello", "de").await.unwrap(), "[stub] hello");
+        assert_eq!(ai.remaining_quota(alice), 0);
+
+        // Same text and locale comes from cache
+        assert_eq!(ai.translate(alice, "hello", "de").await.unwrap(), "[stub] hello");
+
+        assert!(matches!(ai.translate(alice, "bye", "de").await, Err(AiError::QuotaExceeded { .. })));
+        assert_eq!(ai.translate(bob, "bye", "de").await.unwrap(), "[stub] bye");
+    }
+
+    #[tokio::test]
+    async fn test_summaries_stream_and_drop_the_oldest_lines_to_fit() {
+        let ai = AiService::new(
+            Arc::new(StubProvider),
+            AiConfig {
+                max_input_chars: 23,
+                ..AiConfig::default()
+            },
+        );
+        let alice = Uuid::new_v4();
+        let lines = vec!["amy: first".to_string(), "bob: second".to_string(), "amy: third".to_string()];
+
+        let summary: Vec<_> = ai.summarize(alice, &lines).await.unwrap().collect().await;
+        let summary: String = summary.into_iter().map(Result::unwrap).collect();
+        assert_eq!(summary, "[stub] bob: second\namy: third");
+        assert_eq!(ai.remaining_quota(alice), AiConfig::default().daily_quota - 1);
+
+        let too_long = vec!["x".repeat(24)];
+        assert!(matches!(ai.summarize(alice, &too_long).await, Err(AiError::InputTooLong(24))));
+    }
+
+    #[tokio::test]
+    async fn test_failed_requests_do_not_use_quota() {
+        let ai = service(Arc::new(DisabledProvider), 1);
+        let alice = Uuid::new_v4();
+
+        assert_eq!(ai.translate(alice, "hello", "de").await, Err(AiError::NotConfigured));
+        assert_eq!(ai.remaining_quota(alice), 1);
+
+        let long = "x".repeat(AiConfig::default().max_input_chars + 1);
+        assert!(matches!(ai.translate(alice, &long, "de").await, Err(AiError::InputTooLong(_))));
+        assert_eq!(ai.remaining_quota(alice), 1);
+    }
+}
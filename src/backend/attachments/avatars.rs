@@ -0,0 +1,234 @@
+//! Avatar HTTP Handlers
+//!
+//! - `POST /api/users/avatar` - upload the caller's avatar as a multipart
+//!   `avatar` field; the image type is sniffed from the bytes and must be
+//!   PNG, JPEG, GIF or WebP (`415` otherwise), and uploads over
+//!   [`MAX_AVATAR_BYTES`] are rejected with `413`
+//! - `GET /api/users/{user_id}/avatar` - download a user's avatar
+//!
+//! Avatars are kept in attachment storage under `avatars/{user_id}`, one per
+//! user, and replaced on every upload.
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{multipart::Field, Multipart, Path, State},
+    http::{header, HeaderMap, StatusCode},
+    response::Response,
+    Json,
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::backend::auth::users::set_avatar_updated;
+use crate::backend::messaging::handlers::extract_user_id;
+use crate::shared::messaging::{avatar_url, UploadAvatarResponse};
+
+use super::handlers::PRESIGN_EXPIRY;
+use super::mime::sniff_mime;
+use super::storage::{AttachmentStorage, StorageBackend, StorageError};
+
+/// Largest avatar accepted, in bytes
+pub const MAX_AVATAR_BYTES: usize = 1024 * 1024;
+
+/// Image types accepted as avatars
+const AVATAR_MIME: &[&str] = &["image/png", "image/jpeg", "image/gif", "image/webp"];
+
+/// Storage key for a user's avatar
+pub fn avatar_key(user_id: Uuid) -> String {
+    format!("avatars/{}", user_id)
+}
+
+/// Upload the caller's avatar
+pub async fn upload_avatar(
+    State(db_pool): State<Option<PgPool>>,
+    State(storage): State<AttachmentStorage>,
+    headers: HeaderMap,
+    mut multipart: Multipart,
+) -> Result<Json<UploadAvatarResponse>, StatusCode> {
+    let pool = db_pool.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+    let user_id = extract_user_id(&headers)?;
+
+    let mut field = loop {
+        match multipart.next_field().await.map_err(|_| StatusCode::BAD_REQUEST)? {
+            Some(field) if field.name() == Some("avatar") => break field,
+            Some(_) => continue,
+            None => return Err(StatusCode::BAD_REQUEST),
+        }
+    };
+
+    if field.content_type().is_some_and(|declared| !declared.starts_with("image/")) {
+        return Err(StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+
+    let data = read_capped(&mut field, MAX_AVATAR_BYTES).await?;
+    if data.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let mime_type = sniff_mime(&data)
+        .filter(|mime| AVATAR_MIME.contains(mime))
+        .ok_or_else(|| {
+            tracing::warn!("Rejected avatar upload from user {}: not a supported image", user_id);
+            StatusCode::UNSUPPORTED_MEDIA_TYPE
+        })?;
+    let size = data.len() as u64;
+
+    storage
+        .put(&avatar_key(user_id), data, mime_type)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to store avatar for user {}: {:?}", user_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let updated_at = set_avatar_updated(pool, user_id).await.map_err(|e| {
+        tracing::error!("Failed to record avatar for user {}: {:?}", user_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    tracing::info!("Stored avatar ({} bytes, {}) for user {} via {}", size, mime_type, user_id, storage.kind());
+
+    Ok(Json(UploadAvatarResponse {
+        url: avatar_url(user_id, updated_at.timestamp_millis()),
+        size,
+        mime_type: mime_type.to_string(),
+    }))
+}
+
+/// Download a user's avatar
+///
+/// Avatar URLs are versioned, so responses may be cached.
+pub async fn download_avatar(
+    State(storage): State<AttachmentStorage>,
+    headers: HeaderMap,
+    Path(user_id): Path<Uuid>,
+) -> Result<Response<Body>, StatusCode> {
+    let _requester = extract_user_id(&headers)?;
+    let key = avatar_key(user_id);
+
+    let presigned = storage.presign_url(&key, PRESIGN_EXPIRY).map_err(|e| {
+        tracing::error!("Failed to presign avatar for user {}: {:?}", user_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    if let Some(url) = presigned {
+        return Response::builder()
+            .status(StatusCode::TEMPORARY_REDIRECT)
+            .header(header::LOCATION, url)
+            .body(Body::empty())
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    let object = storage.get(&key).await.map_err(|e| match e {
+        StorageError::NotFound(_) => StatusCode::NOT_FOUND,
+        e => {
+            tracing::error!("Failed to read avatar for user {}: {:?}", user_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    })?;
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, object.content_type)
+        .header(header::CONTENT_LENGTH, object.data.len())
+        .header(header::X_CONTENT_TYPE_OPTIONS, "nosniff")
+        .header(header::CACHE_CONTROL, "private, max-age=86400")
+        .body(Body::from(object.data))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+/// Read a multipart field, failing with `413` once it exceeds `limit` bytes
+async fn read_capped(field: &mut Field<'_>, limit: usize) -> Result<Bytes, StatusCode> {
+    let mut data = Vec::new();
+    while let Some(chunk) = field.chunk().await.map_err(|_| StatusCode::BAD_REQUEST)? {
+        if data.len() + chunk.len() > limit {
+            return Err(StatusCode::PAYLOAD_TOO_LARGE);
+        }
+        data.extend_from_slice(&chunk);
+    }
+    Ok(Bytes::from(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::to_bytes;
+    use axum::extract::FromRequest;
+    use axum::http::Request;
+    use crate::backend::attachments::filesystem::FilesystemStorage;
+    use crate::backend::auth::users::User;
+    use crate::backend::messaging::db;
+    use crate::backend::test_db::{auth, TestDatabase};
+
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR\0\0\0\x01\0\0\0\x01";
+    const BOUNDARY: &str = "avatar-test-boundary";
+
+    async fn multipart(content_type: &str, data: &[u8]) -> Multipart {
+        let mut body = format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"avatar\"; filename=\"me\"\r\nContent-Type: {}\r\n\r\n",
+            BOUNDARY, content_type
+        )
+        .into_bytes();
+        body.extend_from_slice(data);
+        body.extend_from_slice(format!("\r\n--{}--\r\n", BOUNDARY).as_bytes());
+
+        let request = Request::builder()
+            .header(header::CONTENT_TYPE, format!("multipart/form-data; boundary={}", BOUNDARY))
+            .body(Body::from(body))
+            .unwrap();
+        Multipart::from_request(request, &()).await.unwrap()
+    }
+
+    async fn upload(pool: &PgPool, storage: &AttachmentStorage, user: &User, content_type: &str, data: &[u8]) -> Result<String, StatusCode> {
+        let Json(response) = upload_avatar(
+            State(Some(pool.clone())),
+            State(storage.clone()),
+            auth(user),
+            multipart(content_type, data).await,
+        )
+        .await?;
+        Ok(response.url)
+    }
+
+    #[tokio::test]
+    async fn test_avatar_upload_download_and_contacts() {
+        let db = TestDatabase::new().await;
+        let pool = db.pool().clone();
+        let dir = tempfile::tempdir().unwrap();
+        let storage = AttachmentStorage::Filesystem(FilesystemStorage::new(dir.path()));
+        let (alice, bob) = (db.user().await, db.user().await);
+        db::create_contact(&pool, bob.id, alice.id, &alice.username, &alice.email).await.unwrap();
+
+        // No avatar yet: contacts fall back to initials
+        let contacts = db::get_contacts_for_user(&pool, bob.id).await.unwrap();
+        assert_eq!(contacts[0].avatar_url, None);
+        let missing = download_avatar(State(storage.clone()), auth(&bob), Path(alice.id)).await.unwrap_err();
+        assert_eq!(missing, StatusCode::NOT_FOUND);
+
+        // Type comes from the bytes, not the declared content type
+        let not_image = upload(&pool, &storage, &alice, "image/png", b"just some text").await.unwrap_err();
+        assert_eq!(not_image, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        let declared_text = upload(&pool, &storage, &alice, "text/plain", PNG).await.unwrap_err();
+        assert_eq!(declared_text, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        let mut huge = PNG.to_vec();
+        huge.resize(MAX_AVATAR_BYTES + 1, 0);
+        assert_eq!(upload(&pool, &storage, &alice, "image/png", &huge).await.unwrap_err(), StatusCode::PAYLOAD_TOO_LARGE);
+
+        let url = upload(&pool, &storage, &alice, "image/png", PNG).await.unwrap();
+        assert!(url.starts_with(&format!("/api/users/{}/avatar?v=", alice.id)));
+
+        let contacts = db::get_contacts_for_user(&pool, bob.id).await.unwrap();
+        assert_eq!(contacts[0].avatar_url.as_deref(), Some(url.as_str()));
+        let readded = db::create_contact(&pool, bob.id, alice.id, &alice.username, &alice.email).await.unwrap();
+        assert_eq!(readded.avatar_url.as_deref(), Some(url.as_str()));
+
+        let response = download_avatar(State(storage.clone()), auth(&bob), Path(alice.id)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "image/png");
+        assert_eq!(to_bytes(response.into_body(), usize::MAX).await.unwrap(), PNG);
+
+        // Downloads need a signed-in user
+        let anonymous = download_avatar(State(storage.clone()), HeaderMap::new(), Path(alice.id)).await.unwrap_err();
+        assert_eq!(anonymous, StatusCode::UNAUTHORIZED);
+    }
+}
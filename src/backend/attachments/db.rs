@@ -0,0 +1,49 @@
+//! Attachment Records
+//!
+//! Who uploaded each attachment and which conversation it was shared in, so
+//! downloads can be limited to the people who can see it.
+
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+
+/// Uploader and conversation of a stored attachment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AttachmentOwner {
+    pub uploader_id: Uuid,
+    /// `None` until the attachment is shared in a conversation
+    pub conversation_id: Option<Uuid>,
+}
+
+/// Record a newly stored attachment
+pub async fn record_attachment(
+    pool: &PgPool,
+    attachment_id: Uuid,
+    owner: AttachmentOwner,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO attachments (id, uploader_id, conversation_id)
+        VALUES ($1, $2, $3)
+        "#
+    )
+    .bind(attachment_id)
+    .bind(owner.uploader_id)
+    .bind(owner.conversation_id)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Uploader and conversation of an attachment; `None` if it was never recorded
+pub async fn attachment_owner(pool: &PgPool, attachment_id: Uuid) -> Result<Option<AttachmentOwner>, sqlx::Error> {
+    let row = sqlx::query("SELECT uploader_id, conversation_id FROM attachments WHERE id = $1")
+        .bind(attachment_id)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(row.map(|row| AttachmentOwner {
+        uploader_id: row.get("uploader_id"),
+        conversation_id: row.get("conversation_id"),
+    }))
+}
@@ -0,0 +1,123 @@
+//! Local Filesystem Storage
+//!
+//! Stores attachments as files under a root directory, with the content type
+//! in a `<file>.content-type` sidecar. Downloads are served through the
+//! attachment handler, so no presigned URLs are produced.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use bytes::Bytes;
+
+use super::storage::{validate_key, StorageBackend, StorageError, StoredObject};
+
+/// Content type used when no sidecar is present
+const DEFAULT_CONTENT_TYPE: &str = "application/octet-stream";
+
+/// Filesystem-backed attachment storage
+#[derive(Debug, Clone)]
+pub struct FilesystemStorage {
+    root: PathBuf,
+}
+
+impl FilesystemStorage {
+    /// Create a backend rooted at `root` (created lazily on first write)
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn path_for(&self, key: &str) -> Result<PathBuf, StorageError> {
+        validate_key(key)?;
+        Ok(self.root.join(key))
+    }
+
+    fn content_type_path(path: &std::path::Path) -> PathBuf {
+        let mut sidecar = path.as_os_str().to_owned();
+        sidecar.push(".content-type");
+        PathBuf::from(sidecar)
+    }
+}
+
+impl StorageBackend for FilesystemStorage {
+    async fn put(&self, key: &str, data: Bytes, content_type: &str) -> Result<(), StorageError> {
+        let path = self.path_for(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(&path, &data).await?;
+        tokio::fs::write(Self::content_type_path(&path), content_type).await?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<StoredObject, StorageError> {
+        let path = self.path_for(key)?;
+        let data = match tokio::fs::read(&path).await {
+            Ok(data) => Bytes::from(data),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Err(StorageError::NotFound(key.to_string())),
+            Err(e) => return Err(e.into()),
+        };
+        let content_type = tokio::fs::read_to_string(Self::content_type_path(&path))
+            .await
+            .unwrap_or_else(|_| DEFAULT_CONTENT_TYPE.to_string());
+
+        Ok(StoredObject { data, content_type })
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        let path = self.path_for(key)?;
+        let _ = tokio::fs::remove_file(Self::content_type_path(&path)).await;
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn presign_url(&self, key: &str, _expires_in: Duration) -> Result<Option<String>, StorageError> {
+        validate_key(key)?;
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_put_get_delete_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = FilesystemStorage::new(dir.path());
+
+        storage
+            .put("attachments/test.txt", Bytes::from_static(b"hello"), "text/plain")
+            .await
+            .unwrap();
+        let object = storage.get("attachments/test.txt").await.unwrap();
+        assert_eq!(object.data, Bytes::from_static(b"hello"));
+        assert_eq!(object.content_type, "text/plain");
+
+        storage.delete("attachments/test.txt").await.unwrap();
+        assert!(matches!(
+            storage.get("attachments/test.txt").await,
+            Err(StorageError::NotFound(_))
+        ));
+
+        // Deleting a missing object is not an error
+        storage.delete("attachments/test.txt").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_rejects_path_traversal() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = FilesystemStorage::new(dir.path());
+
+        let result = storage.put("../escape", Bytes::from_static(b"x"), "text/plain").await;
+        assert!(matches!(result, Err(StorageError::InvalidKey(_))));
+    }
+
+    #[test]
+    fn test_no_presigned_url() {
+        let storage = FilesystemStorage::new("/tmp/unused");
+        assert_eq!(storage.presign_url("attachments/a", Duration::from_secs(60)).unwrap(), None);
+    }
+}
@@ -0,0 +1,210 @@
+//! Attachment HTTP Handlers
+//!
+//! - `POST /api/attachments?conversation_id=` - upload raw bytes; the stored
+//!   type is sniffed from the content and checked against the MIME allowlist
+//!   (`415` on rejection). The uploader must be in the conversation, if given
+//! - `GET /api/attachments/{attachment_id}` - download; redirects to a presigned
+//!   URL when the backend supports it, otherwise streams the bytes. Only the
+//!   uploader and the conversation's participants may download; anyone else
+//!   gets `404`, as if it didn't exist
+
+use std::time::Duration;
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::Response,
+    Json,
+};
+use serde::Deserialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::backend::messaging::conversation_cache::ConversationCache;
+use crate::backend::messaging::handlers::extract_user_id;
+use crate::shared::messaging::UploadAttachmentResponse;
+
+use super::db::{attachment_owner, record_attachment, AttachmentOwner};
+use super::mime::MimePolicy;
+use super::storage::{AttachmentStorage, StorageBackend, StorageError};
+
+/// Lifetime of presigned download URLs
+pub(crate) const PRESIGN_EXPIRY: Duration = Duration::from_secs(15 * 60);
+
+/// Storage key for an attachment ID
+pub fn attachment_key(attachment_id: Uuid) -> String {
+    format!("attachments/{}", attachment_id)
+}
+
+/// Query parameters for an upload
+#[derive(Debug, Deserialize)]
+pub struct UploadQuery {
+    /// Conversation the attachment is shared in
+    pub conversation_id: Option<Uuid>,
+}
+
+/// Whether `user_id` may download an attachment: its uploader or a
+/// participant in its conversation
+pub(crate) async fn may_download(
+    pool: &PgPool,
+    conversations: &ConversationCache,
+    owner: Option<AttachmentOwner>,
+    user_id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    match owner {
+        None => Ok(false),
+        Some(owner) if owner.uploader_id == user_id => Ok(true),
+        Some(AttachmentOwner { conversation_id: Some(conversation_id), .. }) => {
+            conversations.is_participant(pool, user_id, conversation_id).await
+        }
+        Some(_) => Ok(false),
+    }
+}
+
+/// Upload an attachment
+pub async fn upload_attachment(
+    State(db_pool): State<Option<PgPool>>,
+    State(conversations): State<ConversationCache>,
+    State(storage): State<AttachmentStorage>,
+    headers: HeaderMap,
+    Query(query): Query<UploadQuery>,
+    body: Bytes,
+) -> Result<Json<UploadAttachmentResponse>, StatusCode> {
+    let pool = db_pool.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+    let user_id = extract_user_id(&headers)?;
+
+    if body.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    if let Some(conversation_id) = query.conversation_id {
+        let is_participant = conversations.is_participant(pool, user_id, conversation_id)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        if !is_participant {
+            return Err(StatusCode::FORBIDDEN);
+        }
+    }
+
+    let declared = headers.get(header::CONTENT_TYPE).and_then(|h| h.to_str().ok());
+    let mime_type = MimePolicy::global().validate(declared, &body).map_err(|e| {
+        tracing::warn!("Rejected attachment upload from user {}: {}", user_id, e);
+        StatusCode::UNSUPPORTED_MEDIA_TYPE
+    })?;
+
+    let attachment_id = Uuid::new_v4();
+    let size = body.len() as u64;
+
+    storage
+        .put(&attachment_key(attachment_id), body, &mime_type)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to store attachment {}: {:?}", attachment_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let owner = AttachmentOwner { uploader_id: user_id, conversation_id: query.conversation_id };
+    record_attachment(pool, attachment_id, owner).await.map_err(|e| {
+        tracing::error!("Failed to record attachment {}: {:?}", attachment_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    tracing::info!(
+        "Stored attachment {} ({} bytes, {}) for user {} via {}",
+        attachment_id, size, mime_type, user_id, storage.kind()
+    );
+
+    Ok(Json(UploadAttachmentResponse {
+        id: attachment_id,
+        url: format!("/api/attachments/{}", attachment_id),
+        size,
+        mime_type,
+    }))
+}
+
+/// Download an attachment
+pub async fn download_attachment(
+    State(db_pool): State<Option<PgPool>>,
+    State(conversations): State<ConversationCache>,
+    State(storage): State<AttachmentStorage>,
+    headers: HeaderMap,
+    Path(attachment_id): Path<Uuid>,
+) -> Result<Response<Body>, StatusCode> {
+    let pool = db_pool.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+    let user_id = extract_user_id(&headers)?;
+
+    let owner = attachment_owner(pool, attachment_id).await.map_err(|e| {
+        tracing::error!("Failed to look up attachment {}: {:?}", attachment_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let allowed = may_download(pool, &conversations, owner, user_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if !allowed {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let key = attachment_key(attachment_id);
+
+    let presigned = storage.presign_url(&key, PRESIGN_EXPIRY).map_err(|e| {
+        tracing::error!("Failed to presign attachment {}: {:?}", attachment_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    if let Some(url) = presigned {
+        return Response::builder()
+            .status(StatusCode::TEMPORARY_REDIRECT)
+            .header(header::LOCATION, url)
+            .body(Body::empty())
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    let object = storage.get(&key).await.map_err(|e| match e {
+        StorageError::NotFound(_) => StatusCode::NOT_FOUND,
+        e => {
+            tracing::error!("Failed to read attachment {}: {:?}", attachment_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    })?;
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, object.content_type)
+        .header(header::CONTENT_LENGTH, object.data.len())
+        .header(header::X_CONTENT_TYPE_OPTIONS, "nosniff")
+        .body(Body::from(object.data))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::messaging::conversation_cache::ConversationInfo;
+
+    #[tokio::test]
+    async fn test_non_participant_may_not_download() {
+        // Never queried: the cache answers for the conversation
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .connect_lazy("postgres://nobody@127.0.0.1:1/none")
+            .unwrap();
+        let conversations = ConversationCache::default();
+        let (uploader, member, outsider) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let conversation_id = Uuid::new_v4();
+        conversations.insert(
+            conversation_id,
+            ConversationInfo { created_at: chrono::Utc::now(), participants: vec![uploader, member] },
+        );
+
+        let shared = Some(AttachmentOwner { uploader_id: uploader, conversation_id: Some(conversation_id) });
+        assert!(may_download(&pool, &conversations, shared, uploader).await.unwrap());
+        assert!(may_download(&pool, &conversations, shared, member).await.unwrap());
+        assert!(!may_download(&pool, &conversations, shared, outsider).await.unwrap());
+
+        // Not shared anywhere yet, or never uploaded
+        let unshared = Some(AttachmentOwner { uploader_id: uploader, conversation_id: None });
+        assert!(may_download(&pool, &conversations, unshared, uploader).await.unwrap());
+        assert!(!may_download(&pool, &conversations, unshared, member).await.unwrap());
+        assert!(!may_download(&pool, &conversations, None, uploader).await.unwrap());
+    }
+}
@@ -0,0 +1,237 @@
+//! Attachment MIME Validation
+//!
+//! Determines an upload's real content type by sniffing magic bytes rather
+//! than trusting the client-declared `Content-Type`, then checks it against
+//! a configurable allowlist.
+//!
+//! # Configuration
+//!
+//! - `ATTACHMENT_ALLOWED_MIME` - comma-separated allowlist
+//!   (default [`DEFAULT_ALLOWED_MIME`])
+//!
+//! # Rules
+//!
+//! - The sniffed type always wins over the declared type
+//! - Executables (PE, ELF, Mach-O) are always rejected; a PE file needs the
+//!   `PE\0\0` header its DOS stub points at, not just a leading `MZ`
+//! - Shebang scripts are rejected unless declared as text, in which case
+//!   they are stored as `text/plain` like any other text
+//! - Content that cannot be identified is only accepted as `text/plain`
+//!   when it is valid UTF-8 without NUL bytes
+
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+use thiserror::Error;
+
+/// Allowlist used when `ATTACHMENT_ALLOWED_MIME` is not set
+pub const DEFAULT_ALLOWED_MIME: &[&str] = &[
+    "image/png",
+    "image/jpeg",
+    "image/gif",
+    "image/webp",
+    "application/pdf",
+    "application/zip",
+    "text/plain",
+];
+
+/// Why an upload's content type was rejected
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum MimeError {
+    /// Content is an executable, regardless of declared type
+    #[error("Executable content is not allowed")]
+    Executable,
+
+    /// Content could not be identified
+    #[error("Unrecognized content type")]
+    Unrecognized,
+
+    /// Identified type is not in the allowlist
+    #[error("Content type '{0}' is not allowed")]
+    NotAllowed(String),
+}
+
+/// Type sniffed for `#!` scripts
+const SCRIPT_MIME: &str = "text/x-shellscript";
+
+/// Identify content by its leading bytes
+pub fn sniff_mime(data: &[u8]) -> Option<&'static str> {
+    const SIGNATURES: &[(&[u8], &str)] = &[
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"%PDF-", "application/pdf"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"PK\x05\x06", "application/zip"),
+        (b"\x7fELF", "application/x-executable"),
+        (b"\xfe\xed\xfa\xce", "application/x-mach-binary"),
+        (b"\xfe\xed\xfa\xcf", "application/x-mach-binary"),
+        (b"\xce\xfa\xed\xfe", "application/x-mach-binary"),
+        (b"\xcf\xfa\xed\xfe", "application/x-mach-binary"),
+        (b"#!", SCRIPT_MIME),
+    ];
+
+    if data.len() >= 12 && &data[0..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        return Some("image/webp");
+    }
+    if is_pe(data) {
+        return Some("application/x-msdownload");
+    }
+
+    SIGNATURES
+        .iter()
+        .find(|(magic, _)| data.starts_with(magic))
+        .map(|(_, mime)| *mime)
+}
+
+/// `MZ` DOS stub whose `e_lfanew` (at 0x3C) points at a `PE\0\0` header
+fn is_pe(data: &[u8]) -> bool {
+    let Some(e_lfanew) = data.get(0x3C..0x40) else {
+        return false;
+    };
+    let offset = u32::from_le_bytes([e_lfanew[0], e_lfanew[1], e_lfanew[2], e_lfanew[3]]) as usize;
+    data.starts_with(b"MZ") && offset.checked_add(4).and_then(|end| data.get(offset..end)) == Some(&b"PE\0\0"[..])
+}
+
+fn is_executable(mime: &str) -> bool {
+    matches!(
+        mime,
+        "application/x-msdownload" | "application/x-executable" | "application/x-mach-binary" | "text/x-shellscript"
+    )
+}
+
+/// Configurable MIME allowlist
+#[derive(Debug, Clone)]
+pub struct MimePolicy {
+    allowed: HashSet<String>,
+}
+
+impl MimePolicy {
+    /// Create a policy allowing exactly `allowed`
+    pub fn new<I, S>(allowed: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            allowed: allowed.into_iter().map(|m| m.into().trim().to_ascii_lowercase()).collect(),
+        }
+    }
+
+    /// Read the allowlist from `ATTACHMENT_ALLOWED_MIME`
+    pub fn from_env() -> Self {
+        match std::env::var("ATTACHMENT_ALLOWED_MIME") {
+            Ok(list) if !list.trim().is_empty() => {
+                Self::new(list.split(',').filter(|m| !m.trim().is_empty()).map(str::to_string))
+            }
+            _ => Self::default(),
+        }
+    }
+
+    /// Process-wide policy, read from the environment once
+    pub fn global() -> &'static MimePolicy {
+        static POLICY: OnceLock<MimePolicy> = OnceLock::new();
+        POLICY.get_or_init(MimePolicy::from_env)
+    }
+
+    pub fn is_allowed(&self, mime: &str) -> bool {
+        self.allowed.contains(mime)
+    }
+
+    /// Determine the validated content type for an upload
+    ///
+    /// The stored type comes from the bytes; `declared` only decides whether a
+    /// `#!` script is shared as text, and is otherwise just logged.
+    pub fn validate(&self, declared: Option<&str>, data: &[u8]) -> Result<String, MimeError> {
+        let declared = declared.map(|declared| declared.split(';').next().unwrap_or("").trim());
+        let declared_text = declared.is_some_and(|declared| {
+            declared.get(..5).is_some_and(|prefix| prefix.eq_ignore_ascii_case("text/"))
+        });
+        let sniffed = sniff_mime(data).filter(|mime| !(*mime == SCRIPT_MIME && declared_text));
+
+        let detected = match sniffed {
+            Some(mime) if is_executable(mime) => return Err(MimeError::Executable),
+            Some(mime) => mime,
+            None if !data.contains(&0) && std::str::from_utf8(data).is_ok() => "text/plain",
+            None => return Err(MimeError::Unrecognized),
+        };
+
+        if let Some(declared) = declared {
+            if !declared.eq_ignore_ascii_case(detected) {
+                tracing::warn!("Attachment declared as '{}' but sniffed as '{}'", declared, detected);
+            }
+        }
+
+        if self.is_allowed(detected) {
+            Ok(detected.to_string())
+        } else {
+            Err(MimeError::NotAllowed(detected.to_string()))
+        }
+    }
+}
+
+impl Default for MimePolicy {
+    fn default() -> Self {
+        Self::new(DEFAULT_ALLOWED_MIME.iter().copied())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+
+    #[test]
+    fn test_spoofed_png_uses_real_type() {
+        let policy = MimePolicy::default();
+        assert_eq!(policy.validate(Some("text/plain"), PNG), Ok("image/png".to_string()));
+    }
+
+    #[test]
+    fn test_disallowed_type_is_rejected() {
+        let policy = MimePolicy::new(["text/plain"]);
+        assert_eq!(
+            policy.validate(Some("image/png"), PNG),
+            Err(MimeError::NotAllowed("image/png".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_executable_is_rejected() {
+        let policy = MimePolicy::default();
+        let mut pe = vec![0u8; 0x44];
+        pe[..2].copy_from_slice(b"MZ");
+        pe[0x3C..0x40].copy_from_slice(&0x40u32.to_le_bytes());
+        pe[0x40..].copy_from_slice(b"PE\0\0");
+        assert_eq!(policy.validate(Some("image/png"), &pe), Err(MimeError::Executable));
+        assert_eq!(policy.validate(Some("image/png"), b"#!/bin/sh\nrm -rf /"), Err(MimeError::Executable));
+        assert_eq!(policy.validate(None, b"#!/bin/sh\nrm -rf /"), Err(MimeError::Executable));
+    }
+
+    #[test]
+    fn test_text_that_looks_executable_is_text() {
+        let policy = MimePolicy::default();
+        // No PE header behind the MZ
+        assert_eq!(policy.validate(Some("text/plain"), b"MZ notes"), Ok("text/plain".to_string()));
+        assert_eq!(policy.validate(None, b"MZ notes"), Ok("text/plain".to_string()));
+        // A script shared as text
+        assert_eq!(
+            policy.validate(Some("text/x-sh; charset=utf-8"), b"#!/bin/sh\necho hi"),
+            Ok("text/plain".to_string())
+        );
+    }
+
+    #[test]
+    fn test_plain_text_and_unknown_binary() {
+        let policy = MimePolicy::default();
+        assert_eq!(policy.validate(None, b"hello world"), Ok("text/plain".to_string()));
+        assert_eq!(policy.validate(None, b"\0\x01\x02\x03"), Err(MimeError::Unrecognized));
+    }
+
+    #[test]
+    fn test_sniff_webp() {
+        assert_eq!(sniff_mime(b"RIFF\0\0\0\0WEBPVP8 "), Some("image/webp"));
+    }
+}
@@ -0,0 +1,24 @@
+//! Attachments Module
+//!
+//! Upload/download of message attachments with pluggable storage:
+//!
+//! - `storage` - `StorageBackend` trait and config-selected `AttachmentStorage`
+//! - `filesystem` - local filesystem backend
+//! - `s3` - S3-compatible backend with presigned downloads
+//! - `mime` - magic-byte sniffing and MIME allowlist
+//! - `db` - uploader and conversation of each attachment
+//! - `handlers` - HTTP handlers
+//! - `avatars` - user avatar upload/download, stored alongside attachments
+
+pub mod storage;
+pub mod filesystem;
+pub mod s3;
+pub mod mime;
+pub mod db;
+pub mod handlers;
+pub mod avatars;
+
+pub use storage::{AttachmentStorage, StorageBackend, StorageError, StoredObject};
+pub use mime::{MimePolicy, MimeError};
+pub use handlers::{upload_attachment, download_attachment};
+pub use avatars::{upload_avatar, download_avatar};
@@ -0,0 +1,155 @@
+//! Attachment Storage Backends
+//!
+//! Defines the [`StorageBackend`] trait used by the attachment handlers and
+//! the [`AttachmentStorage`] selector that picks a concrete backend from
+//! configuration.
+//!
+//! # Configuration
+//!
+//! - `ATTACHMENT_STORAGE` - `fs` (default) or `s3`
+//! - `ATTACHMENT_DIR` - root directory for the filesystem backend (default `./data/attachments`)
+//! - `S3_ENDPOINT`, `S3_BUCKET`, `S3_REGION`, `S3_ACCESS_KEY`, `S3_SECRET_KEY` - S3-compatible backend
+
+use std::time::Duration;
+
+use bytes::Bytes;
+use thiserror::Error;
+
+use super::filesystem::FilesystemStorage;
+use super::s3::{S3Config, S3Storage};
+
+/// Errors returned by storage backends
+#[derive(Debug, Error)]
+pub enum StorageError {
+    /// No object stored under the key
+    #[error("Object not found: {0}")]
+    NotFound(String),
+
+    /// Key contains path traversal or other disallowed characters
+    #[error("Invalid storage key: {0}")]
+    InvalidKey(String),
+
+    /// Local filesystem failure
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// Remote backend failure
+    #[error("Storage backend error: {0}")]
+    Backend(String),
+
+    /// Backend configuration is missing or invalid
+    #[error("Storage configuration error: {0}")]
+    Config(String),
+}
+
+/// A stored object and the content type it was stored with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredObject {
+    pub data: Bytes,
+    pub content_type: String,
+}
+
+/// Minimal object storage interface for attachments
+pub trait StorageBackend: Send + Sync {
+    /// Store `data` under `key`, replacing any existing object
+    fn put(&self, key: &str, data: Bytes, content_type: &str) -> impl std::future::Future<Output = Result<(), StorageError>> + Send;
+
+    /// Fetch the object stored under `key`
+    fn get(&self, key: &str) -> impl std::future::Future<Output = Result<StoredObject, StorageError>> + Send;
+
+    /// Delete the object stored under `key` (missing objects are not an error)
+    fn delete(&self, key: &str) -> impl std::future::Future<Output = Result<(), StorageError>> + Send;
+
+    /// URL clients can download from directly, if the backend supports it
+    ///
+    /// Returns `Ok(None)` when downloads must go through the server.
+    fn presign_url(&self, key: &str, expires_in: Duration) -> Result<Option<String>, StorageError>;
+}
+
+/// Validate a storage key: relative, no traversal, no backslashes
+pub fn validate_key(key: &str) -> Result<(), StorageError> {
+    let invalid = key.is_empty()
+        || key.starts_with('/')
+        || key.contains('\\')
+        || key.split('/').any(|segment| segment.is_empty() || segment == "." || segment == "..");
+
+    if invalid {
+        Err(StorageError::InvalidKey(key.to_string()))
+    } else {
+        Ok(())
+    }
+}
+
+/// Storage backend selected from configuration
+#[derive(Clone)]
+pub enum AttachmentStorage {
+    Filesystem(FilesystemStorage),
+    S3(S3Storage),
+}
+
+impl AttachmentStorage {
+    /// Build the configured backend from environment variables
+    pub fn from_env() -> Result<Self, StorageError> {
+        match std::env::var("ATTACHMENT_STORAGE").unwrap_or_else(|_| "fs".to_string()).as_str() {
+            "fs" | "filesystem" => {
+                let root = std::env::var("ATTACHMENT_DIR").unwrap_or_else(|_| "./data/attachments".to_string());
+                Ok(Self::Filesystem(FilesystemStorage::new(root)))
+            }
+            "s3" => Ok(Self::S3(S3Storage::new(S3Config::from_env()?))),
+            other => Err(StorageError::Config(format!("Unknown ATTACHMENT_STORAGE '{}'", other))),
+        }
+    }
+
+    /// Short backend name for logging
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Filesystem(_) => "fs",
+            Self::S3(_) => "s3",
+        }
+    }
+}
+
+impl StorageBackend for AttachmentStorage {
+    async fn put(&self, key: &str, data: Bytes, content_type: &str) -> Result<(), StorageError> {
+        match self {
+            Self::Filesystem(storage) => storage.put(key, data, content_type).await,
+            Self::S3(storage) => storage.put(key, data, content_type).await,
+        }
+    }
+
+    async fn get(&self, key: &str) -> Result<StoredObject, StorageError> {
+        match self {
+            Self::Filesystem(storage) => storage.get(key).await,
+            Self::S3(storage) => storage.get(key).await,
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        match self {
+            Self::Filesystem(storage) => storage.delete(key).await,
+            Self::S3(storage) => storage.delete(key).await,
+        }
+    }
+
+    fn presign_url(&self, key: &str, expires_in: Duration) -> Result<Option<String>, StorageError> {
+        match self {
+            Self::Filesystem(storage) => storage.presign_url(key, expires_in),
+            Self::S3(storage) => storage.presign_url(key, expires_in),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_key() {
+        assert!(validate_key("attachments/abc").is_ok());
+        assert!(validate_key("").is_err());
+        assert!(validate_key("/etc/passwd").is_err());
+        assert!(validate_key("attachments/../secret").is_err());
+        assert!(validate_key("attachments//abc").is_err());
+        assert!(validate_key("a\\b").is_err());
+    }
+}
@@ -11,7 +11,8 @@
 //! ├── types.rs    - Request and response types
 //! ├── signup.rs   - User registration handler
 //! ├── login.rs    - User authentication handler
-//! └── me.rs       - Get current user handler
+//! ├── me.rs       - Get current user handler
+//! └── refresh.rs  - Token refresh handler
 //! ```
 //!
 //! # Handlers
@@ -19,12 +20,14 @@
 //! - **`signup`** - POST /api/auth/signup - User registration
 //! - **`login`** - POST /api/auth/login - User authentication
 //! - **`get_me`** - GET /api/auth/me - Get current user info
+//! - **`refresh`** - POST /api/auth/refresh - Exchange a token for a fresh one
 //!
 //! # Authentication Flow
 //!
 //! 1. **Signup**: User provides email and password → User created → JWT token returned
 //! 2. **Login**: User provides email and password → Credentials verified → JWT token returned
 //! 3. **Get Me**: User provides JWT token → Token verified → User info returned
+//! 4. **Refresh**: User provides JWT token (up to 7 days past expiry) → New JWT token returned
 //!
 //! # Security
 //!
@@ -59,6 +62,9 @@ pub mod login;
 /// Get current user handler
 pub mod me;
 
+/// Token refresh handler
+pub mod refresh;
+
 // Re-export commonly used types
 pub use types::{SignupRequest, LoginRequest, AuthResponse, UserResponse};
 
@@ -69,4 +75,6 @@ pub use signup::signup;
 pub use login::login;
 #[cfg(feature = "ssr")]
 pub use me::get_me;
+#[cfg(feature = "ssr")]
+pub use refresh::refresh;
 
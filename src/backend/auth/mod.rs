@@ -24,7 +24,8 @@
 //!     ├── types.rs    - Request/response types
 //!     ├── signup.rs   - User registration handler
 //!     ├── login.rs    - User authentication handler
-//!     └── me.rs       - Get current user handler
+//!     ├── me.rs       - Get current user handler
+//!     └── refresh.rs  - Token refresh handler
 //! ```
 //!
 //! # Authentication Flow
@@ -32,12 +33,13 @@
 //! 1. **Signup**: User provides email and password → User created → JWT token returned
 //! 2. **Login**: User provides email and password → Credentials verified → JWT token returned
 //! 3. **Get Me**: User provides JWT token → Token verified → User info returned
+//! 4. **Refresh**: User provides JWT token (up to 7 days past expiry) → New JWT token returned
 //!
 //! # Security
 //!
 //! - Passwords are hashed using bcrypt before storage
 //! - JWT tokens are used for stateless authentication
-//! - Tokens expire after 30 days
+//! - Tokens expire after 30 days; clients refresh them via `/api/auth/refresh`
 //! - Invalid credentials return 401 (no information leakage)
 //!
 //! # Example
@@ -67,5 +69,5 @@ pub mod handlers;
 // Re-export commonly used types and handlers
 pub use handlers::types::{SignupRequest, LoginRequest, AuthResponse, UserResponse};
 #[cfg(feature = "ssr")]
-pub use handlers::{signup, login, get_me};
+pub use handlers::{signup, login, get_me, refresh};
 
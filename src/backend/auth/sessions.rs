@@ -28,6 +28,12 @@ pub struct Claims {
 
 
 
+/// Token lifetime (30 days)
+pub const TOKEN_LIFETIME_SECS: u64 = 30 * 24 * 60 * 60;
+
+/// How long after expiry a token can still be exchanged for a fresh one
+pub const REFRESH_GRACE_SECS: u64 = 7 * 24 * 60 * 60;
+
 /// Get JWT secret from environment
 #[cfg(feature = "ssr")]
 fn get_jwt_secret() -> String {
@@ -54,7 +60,7 @@ pub fn create_token(user_id: uuid::Uuid, email: String) -> Result<String, jsonwe
         .as_secs();
     
     // Token expires in 30 days
-    let exp = now + (30 * 24 * 60 * 60);
+    let exp = now + TOKEN_LIFETIME_SECS;
     
     let claims = Claims {
         sub: user_id.to_string(),
@@ -64,10 +70,15 @@ pub fn create_token(user_id: uuid::Uuid, email: String) -> Result<String, jsonwe
         iat: now,
     };
     
+    encode_claims(&claims)
+}
+
+/// Sign a set of claims
+#[cfg(feature = "ssr")]
+fn encode_claims(claims: &Claims) -> Result<String, jsonwebtoken::errors::Error> {
     let secret = get_jwt_secret();
     let key = EncodingKey::from_secret(secret.as_ref());
-    
-    encode(&Header::default(), &claims, &key)
+    encode(&Header::default(), claims, &key)
 }
 
 /// Verify and decode a JWT token
@@ -87,6 +98,28 @@ pub fn verify_token(token: &str) -> Result<Claims, jsonwebtoken::errors::Error>
     Ok(token_data.claims)
 }
 
+/// Verify a token presented for refresh
+/// 
+/// Like [`verify_token`], but also accepts tokens that expired less than
+/// [`REFRESH_GRACE_SECS`] ago so a client that was offline over the expiry
+/// doesn't have to log in again.
+/// 
+/// # Arguments
+/// * `token` - JWT token string
+/// 
+/// # Returns
+/// Decoded claims or error
+#[cfg(feature = "ssr")]
+pub fn verify_token_for_refresh(token: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
+    let secret = get_jwt_secret();
+    let key = DecodingKey::from_secret(secret.as_ref());
+    let mut validation = Validation::default();
+    validation.leeway = REFRESH_GRACE_SECS;
+    
+    let token_data = decode::<Claims>(token, &key, &validation)?;
+    Ok(token_data.claims)
+}
+
 /// Extract user ID from token
 /// 
 /// # Arguments
@@ -159,5 +192,30 @@ mod tests {
         assert_eq!(claims.sub, user_id.to_string());
         assert!(claims.exp > claims.iat);
     }
-}
 
+    fn token_expired_for(secs: u64) -> String {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        let exp = now - secs;
+        encode_claims(&Claims {
+            sub: uuid::Uuid::new_v4().to_string(),
+            email: "test@example.com".to_string(),
+            username: None,
+            exp,
+            iat: exp - TOKEN_LIFETIME_SECS,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_refresh_accepts_recently_expired_token() {
+        let token = token_expired_for(24 * 60 * 60);
+        assert!(verify_token(&token).is_err());
+        assert!(verify_token_for_refresh(&token).is_ok());
+    }
+
+    #[test]
+    fn test_refresh_rejects_token_past_grace() {
+        let token = token_expired_for(REFRESH_GRACE_SECS + 60 * 60);
+        assert!(verify_token_for_refresh(&token).is_err());
+    }
+}
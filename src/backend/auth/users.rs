@@ -253,6 +253,36 @@ pub async fn update_subscription_status_by_customer_id(
     Ok(user)
 }
 
+/// Record that the user's avatar changed
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `user_id` - User ID
+///
+/// # Returns
+/// The new avatar timestamp, which versions the avatar URL
+#[cfg(feature = "ssr")]
+pub async fn set_avatar_updated(
+    pool: &PgPool,
+    user_id: uuid::Uuid,
+) -> Result<DateTime<Utc>, sqlx::Error> {
+    let now = Utc::now();
+
+    sqlx::query(
+        r#"
+        UPDATE users
+        SET avatar_updated_at = $1, updated_at = $1
+        WHERE id = $2
+        "#
+    )
+    .bind(now)
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+
+    Ok(now)
+}
+
 /// Check if user has active subscription
 /// 
 /// # Arguments
@@ -0,0 +1,153 @@
+//! Broadcast Coalescing
+//!
+//! Under rapid sending every PUT would otherwise produce its own broadcast
+//! frame. [`BroadcastCoalescer`] sits in front of the chat broadcast channel
+//! and merges events that arrive within a short window into one
+//! [`MessageEvent`]: the new messages in order, the final version, and the
+//! parents of the first event, so subscribers apply it as a single delta.
+//!
+//! The window starts with the first event of a batch and is never extended,
+//! so no message waits longer than the window before it is broadcast.
+
+use std::time::Duration;
+
+use tokio::sync::{broadcast, mpsc};
+use tokio::time::Instant;
+
+use crate::backend::server::state::MessageEvent;
+
+/// How long to gather events after the first one before broadcasting
+pub const COALESCE_WINDOW: Duration = Duration::from_millis(50);
+
+/// Most events merged into one frame, so a flood can't build huge frames
+pub const MAX_BATCH_EVENTS: usize = 100;
+
+/// Merges chat broadcasts that arrive close together
+///
+/// Cloning is cheap; all clones feed the same background task.
+#[derive(Debug, Clone)]
+pub struct BroadcastCoalescer {
+    tx: mpsc::UnboundedSender<MessageEvent>,
+}
+
+impl BroadcastCoalescer {
+    /// Start the coalescing task in front of `broadcast`
+    ///
+    /// Must be called from within a Tokio runtime. The task ends when every
+    /// clone of the coalescer has been dropped.
+    pub fn spawn(broadcast: broadcast::Sender<MessageEvent>, window: Duration) -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(run(rx, broadcast, window));
+        Self { tx }
+    }
+
+    /// Queue an event for broadcast
+    ///
+    /// Events are broadcast in the order they are queued, so callers that
+    /// need ordering should queue while holding the lock that orders them.
+    pub fn send(&self, event: MessageEvent) {
+        if self.tx.send(event).is_err() {
+            tracing::warn!("[Server] Broadcast coalescer stopped, dropping event");
+        }
+    }
+}
+
+async fn run(
+    mut rx: mpsc::UnboundedReceiver<MessageEvent>,
+    broadcast: broadcast::Sender<MessageEvent>,
+    window: Duration,
+) {
+    while let Some(first) = rx.recv().await {
+        let deadline = Instant::now() + window;
+        let mut batch = vec![first];
+        while batch.len() < MAX_BATCH_EVENTS {
+            match tokio::time::timeout_at(deadline, rx.recv()).await {
+                Ok(Some(event)) => batch.push(event),
+                // Window elapsed, or every sender is gone: flush what we have
+                Ok(None) | Err(_) => break,
+            }
+        }
+
+        let count = batch.len();
+        if let Some(event) = merge_events(batch) {
+            match broadcast.send(event) {
+                Ok(subscribers) => {
+                    tracing::debug!("[Server] Broadcast {} coalesced event(s) to {} subscribers", count, subscribers);
+                }
+                Err(_) => {
+                    // No subscribers yet, that's okay
+                    tracing::debug!("[Server] No subscribers to receive broadcast");
+                }
+            }
+        }
+    }
+}
+
+/// Combine consecutive events into one
+///
+/// Messages keep their order, the version is the last event's and the
+/// parents are the first event's, i.e. what subscribers already have.
+pub fn merge_events(events: Vec<MessageEvent>) -> Option<MessageEvent> {
+    let mut events = events.into_iter();
+    let mut merged = events.next()?;
+    for event in events {
+        merged.messages.extend(event.messages);
+        merged.version = event.version;
+    }
+    Some(merged)
+}
+
+#[cfg(test)]
+#[cfg(feature = "ssr")]
+mod tests {
+    use super::*;
+    use crate::shared::Message;
+
+    fn event(text: &str, version: &str, parent: Option<&str>) -> MessageEvent {
+        MessageEvent {
+            messages: vec![Message::new(text.to_string(), "alice".to_string())],
+            version: version.to_string(),
+            parents: parent.map(str::to_string).into_iter().collect(),
+        }
+    }
+
+    #[test]
+    fn test_merge_events_preserves_order() {
+        let merged = merge_events(vec![
+            event("one", "v1", Some("v0")),
+            event("two", "v2", Some("v1")),
+            event("three", "v3", Some("v2")),
+        ])
+        .unwrap();
+
+        let texts: Vec<&str> = merged.messages.iter().map(|m| m.text.as_str()).collect();
+        assert_eq!(texts, ["one", "two", "three"]);
+        assert_eq!(merged.version, "v3");
+        assert_eq!(merged.parents, ["v0"]);
+
+        assert!(merge_events(Vec::new()).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_rapid_events_become_one_frame() {
+        let (tx, mut rx) = broadcast::channel(16);
+        let coalescer = BroadcastCoalescer::spawn(tx, COALESCE_WINDOW);
+
+        coalescer.send(event("one", "v1", None));
+        coalescer.send(event("two", "v2", Some("v1")));
+        coalescer.send(event("three", "v3", Some("v2")));
+
+        let frame = rx.recv().await.unwrap();
+        assert_eq!(frame.messages.len(), 3);
+        assert_eq!(frame.version, "v3");
+        assert!(frame.parents.is_empty());
+
+        // A later event after the window is its own frame
+        tokio::time::sleep(COALESCE_WINDOW * 2).await;
+        coalescer.send(event("four", "v4", Some("v3")));
+        let frame = rx.recv().await.unwrap();
+        assert_eq!(frame.messages.len(), 1);
+        assert_eq!(frame.parents, ["v3"]);
+        assert!(rx.try_recv().is_err());
+    }
+}
@@ -0,0 +1,101 @@
+//! Braid Version DAG
+//!
+//! A snapshot of a resource's version history for debugging sync issues:
+//! each version with its parents, plus the frontier (versions nothing
+//! builds on yet). Served as JSON by the debug `versions` endpoints and
+//! renderable as Graphviz DOT to see branching and merging.
+
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+/// Version history as adjacency lists
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct VersionDag {
+    /// Each version's parents
+    pub versions: BTreeMap<String, Vec<String>>,
+    /// Versions that aren't a parent of any other version, sorted
+    pub frontier: Vec<String>,
+}
+
+impl VersionDag {
+    /// Build a DAG from `(version, parents)` pairs
+    pub fn from_history<I>(history: I) -> Self
+    where
+        I: IntoIterator<Item = (String, Vec<String>)>,
+    {
+        let versions: BTreeMap<String, Vec<String>> = history.into_iter().collect();
+        let parents: BTreeSet<&String> = versions.values().flatten().collect();
+        let frontier = versions
+            .keys()
+            .filter(|version| !parents.contains(version))
+            .cloned()
+            .collect();
+
+        Self { versions, frontier }
+    }
+
+    /// Render as a Graphviz digraph, edges pointing from parent to child
+    ///
+    /// Frontier versions get a double outline. Parents missing from the
+    /// history are drawn dashed.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph versions {\n    rankdir=LR;\n");
+        for version in self.versions.keys() {
+            let style = if self.frontier.contains(version) { " [peripheries=2]" } else { "" };
+            let _ = writeln!(dot, "    \"{}\"{};", escape(version), style);
+        }
+        let missing: BTreeSet<&String> = self
+            .versions
+            .values()
+            .flatten()
+            .filter(|parent| !self.versions.contains_key(*parent))
+            .collect();
+        for parent in missing {
+            let _ = writeln!(dot, "    \"{}\" [style=dashed];", escape(parent));
+        }
+        for (version, parents) in &self.versions {
+            for parent in parents {
+                let _ = writeln!(dot, "    \"{}\" -> \"{}\";", escape(parent), escape(version));
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+fn escape(id: &str) -> String {
+    id.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dag(edges: &[(&str, &[&str])]) -> VersionDag {
+        VersionDag::from_history(
+            edges
+                .iter()
+                .map(|(version, parents)| (version.to_string(), parents.iter().map(|p| p.to_string()).collect())),
+        )
+    }
+
+    #[test]
+    fn test_frontier_is_versions_without_children() {
+        // v1 branches into v2 and v3; v4 merges them; v5 branches off v3
+        let dag = dag(&[("v1", &[]), ("v2", &["v1"]), ("v3", &["v1"]), ("v4", &["v2", "v3"]), ("v5", &["v3"])]);
+        assert_eq!(dag.frontier, vec!["v4", "v5"]);
+    }
+
+    #[test]
+    fn test_dot_output() {
+        let dag = dag(&[("v2", &["v1"]), ("v\"3", &["v2"])]);
+        let dot = dag.to_dot();
+        assert!(dot.starts_with("digraph versions {"));
+        assert!(dot.contains("\"v1\" [style=dashed];"));
+        assert!(dot.contains("\"v1\" -> \"v2\";"));
+        assert!(dot.contains("\"v2\" -> \"v\\\"3\";"));
+        assert!(dot.contains("\"v\\\"3\" [peripheries=2];"));
+        assert!(dot.ends_with("}\n"));
+    }
+}
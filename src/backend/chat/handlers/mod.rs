@@ -10,6 +10,7 @@
 //! - **`subscription`** - Braid subscription handler (GET /chat with Subscribe header)
 //! - **`put`** - Braid PUT handler (PUT /chat for adding messages)
 //! - **`typing`** - Typing indicator handler (POST /typing)
+//! - **`versions`** - Version DAG debug handler (GET /chat/versions)
 //!
 //! # Module Structure
 //!
@@ -18,7 +19,8 @@
 //! ├── mod.rs          - Module exports and documentation
 //! ├── subscription.rs - Braid subscription handler
 //! ├── put.rs          - Braid PUT handler
-//! └── typing.rs       - Typing indicator handler
+//! ├── typing.rs       - Typing indicator handler
+//! └── versions.rs     - Version DAG debug handler
 //! ```
 //!
 //! # Braid Protocol
@@ -85,6 +87,9 @@ pub mod put;
 /// Typing indicator handler
 pub mod typing;
 
+/// Version DAG debug handler
+pub mod versions;
+
 // Re-export commonly used handlers
 #[cfg(feature = "ssr")]
 pub use subscription::handle_braid_subscription;
@@ -92,4 +97,6 @@ pub use subscription::handle_braid_subscription;
 pub use put::handle_braid_put;
 #[cfg(feature = "ssr")]
 pub use typing::handle_typing_event;
+#[cfg(feature = "ssr")]
+pub use versions::handle_chat_versions;
 
@@ -22,8 +22,9 @@
  * Reference: https://github.com/braid-org/braid-spec/blob/master/draft-toomim-httpbis-braid-http-04.txt
  */
 
-use crate::shared::Message;
-use crate::backend::server::state::AppState;
+use crate::shared::{ErrorCode, Message};
+use crate::backend::error::BackendError;
+use crate::backend::server::state::{AppState, MessageEvent};
 use axum::{
     body::Body,
     extract::State,
@@ -51,9 +52,10 @@ use axum::{
 /// 
 /// The handler validates:
 /// - Message text is not empty
-/// - Message text length is within limits (10,000 characters)
+/// - The request body is within the total message size limit
+/// - Message text length is within limits (`MAX_MESSAGE_LENGTH`, default 10,000 bytes)
 /// - Author name is not empty
-/// - Author name length is within limits (100 characters)
+/// - Author name length is within limits (`MAX_AUTHOR_LENGTH`, default 100 bytes)
 /// - Parent version IDs are valid (if provided)
 /// 
 /// # Arguments
@@ -64,12 +66,16 @@ use axum::{
 /// 
 /// # Returns
 /// 
-/// HTTP response with status code and Version header (Structured Headers format), or an error status code
+/// HTTP response with status code and Version header (Structured Headers format), or a
+/// `BackendError` whose JSON body says which check failed
 /// 
 /// # Errors
 /// 
-/// * `400 Bad Request` - If the request body cannot be parsed as a Message or validation fails
-/// * `401 Unauthorized` - If authentication token is missing or invalid
+/// * `400 Bad Request` - If the request body cannot be parsed as a Message or validation fails,
+///   with code `INVALID_MESSAGE`, `EMPTY_TEXT`, `MESSAGE_TOO_LONG`, `EMPTY_AUTHOR`,
+///   `AUTHOR_TOO_LONG` or `INVALID_PARENTS`
+/// * `401 Unauthorized` - If authentication token is missing or invalid (`UNAUTHORIZED`)
+/// * `413 Payload Too Large` - If the serialized message exceeds `MAX_MESSAGE_BYTES` (`MESSAGE_TOO_LARGE`)
 /// * `500 Internal Server Error` - If state update fails
 /// 
 /// # Example Request
@@ -96,7 +102,9 @@ pub async fn handle_braid_put(
     State(app_state): State<AppState>,
     headers: axum::http::HeaderMap,
     body: axum::body::Bytes,
-) -> Result<Response<Body>, StatusCode> {
+) -> Result<Response<Body>, BackendError> {
+    let _timer = app_state.metrics.put_timer();
+
     // Require authentication for PUT requests
     use crate::backend::auth::sessions::verify_token;
     use axum::http::header::AUTHORIZATION;
@@ -132,40 +140,41 @@ pub async fn handle_braid_put(
     
     tracing::info!("[Server] Authenticated PUT request from user: {}", user_id);
     
+    // Bound the whole message (text plus metadata) before parsing it
+    app_state.message_limits.check_size(body.len()).map_err(|status| {
+        BackendError::coded(status, ErrorCode::MessageTooLarge, "Message exceeds the size limit")
+    })?;
+    
     // Parse message from request body
     // The body should be a JSON-serialized Message
     let message: Message = serde_json::from_slice(&body)
         .map_err(|e| {
             tracing::error!("Failed to parse message from request body: {:?}", e);
-            StatusCode::BAD_REQUEST
+            BackendError::coded(StatusCode::BAD_REQUEST, ErrorCode::InvalidMessage, "Request body is not a valid message")
         })?;
     
     // Validate message content
     // Check that text is not empty and not too long
     if message.text.trim().is_empty() {
         tracing::warn!("[Server] Rejected message with empty text from: {}", message.author);
-        return Err(StatusCode::BAD_REQUEST);
+        return Err(BackendError::coded(StatusCode::BAD_REQUEST, ErrorCode::EmptyText, "Message text is empty"));
     }
     
-    // Limit message length to prevent abuse (e.g., 10000 characters)
-    const MAX_MESSAGE_LENGTH: usize = 10000;
-    if message.text.len() > MAX_MESSAGE_LENGTH {
-        tracing::warn!("[Server] Rejected message that is too long ({} chars) from: {}", message.text.len(), message.author);
-        return Err(StatusCode::BAD_REQUEST);
-    }
+    // Limit message length to prevent abuse
+    app_state.message_limits.check_text(&message.text).map_err(|status| {
+        BackendError::coded(status, ErrorCode::MessageTooLong, "Message text exceeds the length limit")
+    })?;
     
     // Validate author name
     if message.author.trim().is_empty() {
         tracing::warn!("[Server] Rejected message with empty author");
-        return Err(StatusCode::BAD_REQUEST);
+        return Err(BackendError::coded(StatusCode::BAD_REQUEST, ErrorCode::EmptyAuthor, "Author is empty"));
     }
     
     // Limit author name length
-    const MAX_AUTHOR_LENGTH: usize = 100;
-    if message.author.len() > MAX_AUTHOR_LENGTH {
-        tracing::warn!("[Server] Rejected message with author name too long ({} chars)", message.author.len());
-        return Err(StatusCode::BAD_REQUEST);
-    }
+    app_state.message_limits.check_author(&message.author).map_err(|status| {
+        BackendError::coded(status, ErrorCode::AuthorTooLong, "Author name is too long")
+    })?;
     
     tracing::info!("[Server] Received PUT request with message from: {}", message.author);
     
@@ -201,12 +210,12 @@ pub async fn handle_braid_put(
         for parent_version in p.iter() {
             if parent_version.len() > MAX_VERSION_ID_LENGTH {
                 tracing::warn!("[Server] Rejected message with invalid parent version ID (too long): {}", parent_version);
-                return Err(StatusCode::BAD_REQUEST);
+                return Err(BackendError::coded(StatusCode::BAD_REQUEST, ErrorCode::InvalidParents, "Parent version ID is too long"));
             }
             // Additional validation: version IDs should not contain invalid characters
             if parent_version.contains('\n') || parent_version.contains('\r') {
                 tracing::warn!("[Server] Rejected message with invalid parent version ID (contains newline): {}", parent_version);
-                return Err(StatusCode::BAD_REQUEST);
+                return Err(BackendError::coded(StatusCode::BAD_REQUEST, ErrorCode::InvalidParents, "Parent version ID contains a newline"));
             }
         }
         tracing::info!("[Server] Parents header: {:?}", p);
@@ -216,11 +225,24 @@ pub async fn handle_braid_put(
     // This will generate a new version ID and update the version history
     let (version_id, parent_versions) = {
         let mut state_write = app_state.chat_state.write().await;
+        // The version subscribers must already have for the delta to apply
+        let previous_version = state_write.current_version.clone();
         let v_id = state_write.add_message(message.clone(), parents.clone());
         // Get the parent versions that were actually set in state
         let parents = state_write.version_history.get(&v_id)
             .cloned()
             .unwrap_or_default();
+
+        // Broadcast just the new message; subscribers that missed an update
+        // resync with a full snapshot themselves. Queued while the write lock
+        // is held so rapid PUTs reach the coalescer in version order.
+        tracing::info!("[Server] Queueing broadcast of new message with version: {}", v_id);
+        app_state.message_coalescer.send(MessageEvent {
+            messages: vec![message.clone()],
+            version: v_id.clone(),
+            parents: previous_version.into_iter().collect(),
+        });
+
         (v_id, parents)
     };
     
@@ -241,31 +263,9 @@ pub async fn handle_braid_put(
         }
     }
     
+    app_state.metrics.message_sent();
     tracing::info!("[Server] New message added with version: {}", version_id);
     
-    // Get all current messages to broadcast
-    // The client replaces the entire message list, so we need to send all messages
-    let messages_to_broadcast = {
-        let state_read = app_state.chat_state.read().await;
-        state_read.messages.clone()
-    };
-    
-    tracing::info!("[Server] Broadcasting {} messages to all subscribers with version: {}", messages_to_broadcast.len(), version_id);
-    
-    // Broadcast all messages to all SSE subscribers
-    // This immediately notifies all connected clients of the updated state
-    let broadcast_result = app_state.message_broadcast.send((messages_to_broadcast, version_id.clone()));
-    
-    match broadcast_result {
-        Ok(subscriber_count) => {
-            tracing::info!("[Server] Successfully broadcast to {} subscribers", subscriber_count);
-        }
-        Err(e) => {
-            // No subscribers yet, that's okay
-            tracing::debug!("[Server] No subscribers to receive broadcast: {:?}", e);
-        }
-    }
-    
     // Return success response with Version header
     // Version header uses Structured Headers format (RFC 8941) - JSON-stringified string
     // Reference: draft-toomim-httpbis-braid-http-04.txt Section 2.2 (PUT a new version)
@@ -297,13 +297,28 @@ mod tests {
 
     fn create_test_app_state(pool: Option<sqlx::PgPool>) -> AppState {
         use leptos::get_configuration;
+        use crate::backend::chat::coalesce::{BroadcastCoalescer, COALESCE_WINDOW};
         let conf = get_configuration(Some("Cargo.toml")).unwrap();
+        let message_broadcast = tokio::sync::broadcast::channel(1000).0;
         AppState {
             leptos_options: conf.leptos_options,
             db_pool: pool,
             chat_state: std::sync::Arc::new(tokio::sync::RwLock::new(crate::backend::chat::state::ChatState::new())),
             realtime_broadcast: tokio::sync::broadcast::channel(1000).0,
-            message_broadcast: tokio::sync::broadcast::channel(1000).0,
+            message_coalescer: BroadcastCoalescer::spawn(message_broadcast.clone(), COALESCE_WINDOW),
+            message_broadcast,
+            message_limits: crate::backend::messaging::limits::MessageLimits::default(),
+            usage_limits: crate::backend::subscription::usage::UsageLimits::default(),
+            conversation_cache: crate::backend::messaging::conversation_cache::ConversationCache::default(),
+            pool_guard: crate::backend::server::pool::PoolGuard::default(),
+            ai: crate::backend::ai::AiService::default(),
+            user_statuses: crate::backend::messaging::presence::StatusStore::default(),
+            conversation_events: crate::backend::messaging::conversation_events::ConversationEvents::default(),
+            shutdown: crate::backend::server::shutdown::Shutdown::default(),
+            subscription_limit: crate::backend::server::capacity::SubscriptionLimit::default(),
+            connection_telemetry: crate::backend::server::telemetry::ConnectionTelemetry::default(),
+            broadcast_lag: crate::backend::server::lag::BroadcastLag::default(),
+            metrics: crate::backend::server::metrics::ServerMetrics::default(),
         }
     }
 
@@ -329,6 +344,51 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_rapid_puts_coalesce_into_one_frame() {
+        // No database, so the three PUTs land well inside the window
+        let token = create_token(uuid::Uuid::new_v4(), "test@example.com".to_string()).unwrap();
+        let app_state = create_test_app_state(None);
+        let mut rx = app_state.message_broadcast.subscribe();
+
+        for text in ["one", "two", "three"] {
+            let mut headers = HeaderMap::new();
+            headers.insert("authorization", format!("Bearer {}", token).parse().unwrap());
+            headers.insert("content-type", "application/json".parse().unwrap());
+            let message = Message::new(text.to_string(), "test@example.com".to_string());
+            let body = Body::from(serde_json::to_string(&message).unwrap());
+            assert!(handle_braid_put(State(app_state.clone()), headers, body).await.is_ok());
+        }
+
+        let frame = rx.recv().await.unwrap();
+        let texts: Vec<&str> = frame.messages.iter().map(|m| m.text.as_str()).collect();
+        assert_eq!(texts, ["one", "two", "three"]);
+        assert_eq!(Some(&frame.version), app_state.chat_state.read().await.current_version.as_ref());
+        assert!(frame.parents.is_empty());
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_put_message_oversized_metadata() {
+        let token = create_token(uuid::Uuid::new_v4(), "test@example.com".to_string()).unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", format!("Bearer {}", token).parse().unwrap());
+        headers.insert("content-type", "application/json".parse().unwrap());
+
+        let app_state = create_test_app_state(None);
+        let limits = app_state.message_limits;
+
+        // Text is within its limit, but the timestamp pushes the message past the total size
+        let mut message = Message::new("Hello".to_string(), "test@example.com".to_string());
+        message.timestamp = "0".repeat(limits.max_message_bytes);
+        assert!(message.text.len() <= limits.max_text_length);
+
+        let body = Body::from(serde_json::to_string(&message).unwrap());
+        let err = handle_braid_put(State(app_state), headers, body).await.unwrap_err();
+        assert_eq!(err.status_code(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(err.code(), ErrorCode::MessageTooLarge);
+    }
+
     #[tokio::test]
     async fn test_put_message_no_auth() {
         let app_state = create_test_app_state(None);
@@ -336,8 +396,9 @@ mod tests {
         let message = Message::new("Hello".to_string(), "test".to_string());
         let body = Body::from(serde_json::to_string(&message).unwrap());
         
-        let result = handle_braid_put(State(app_state), headers, body).await;
-        assert_eq!(result.unwrap_err(), StatusCode::UNAUTHORIZED);
+        let err = handle_braid_put(State(app_state), headers, body).await.unwrap_err();
+        assert_eq!(err.status_code(), StatusCode::UNAUTHORIZED);
+        assert_eq!(err.code(), ErrorCode::Unauthorized);
     }
 
     #[tokio::test]
@@ -357,9 +418,10 @@ mod tests {
         let body = Body::from(serde_json::to_string(&message).unwrap());
         
         let app_state = create_test_app_state(Some(pool.clone()));
-        let result = handle_braid_put(State(app_state), headers, body).await;
+        let err = handle_braid_put(State(app_state), headers, body).await.unwrap_err();
         
-        assert_eq!(result.unwrap_err(), StatusCode::BAD_REQUEST);
+        assert_eq!(err.status_code(), StatusCode::BAD_REQUEST);
+        assert_eq!(err.code(), ErrorCode::EmptyText);
     }
 }
 
@@ -25,14 +25,25 @@
  * last known version (Section 4.3), and the server will only send
  * updates since that version.
  * 
+ * After the initial snapshot, each update carries only the new message(s)
+ * with `Version` and `Parents` headers. If a subscriber missed an update
+ * (its last version isn't the update's parent), it gets a full snapshot
+ * instead so it can't silently diverge.
+ * 
+ * Clients may send a `Merge-Type:` header (`sync9`, `simpleton` or
+ * `diamond-types`). The negotiated type is echoed in the response and
+ * unsupported values get 406 Not Acceptable. With `diamond-types`, each
+ * update body is a diamond-types patch of just the new messages instead of
+ * the JSON message array.
+ * 
  * All line endings use CRLF (\r\n) per HTTP specification.
  * 
  * Reference: https://github.com/braid-org/braid-spec/blob/master/draft-toomim-httpbis-braid-http-04.txt
  */
 
-use crate::shared::Message;
+use crate::shared::{Message, MergeType, MERGE_TYPE_HEADER};
 #[cfg(feature = "ssr")]
-use crate::backend::server::state::AppState;
+use crate::backend::server::state::{AppState, MessageEvent};
 use axum::{
     body::Body,
     extract::State,
@@ -40,9 +51,16 @@ use axum::{
     response::Response,
 };
 use bytes::Bytes;
+use diamond_types::list::encoding::ENCODE_PATCH;
+use diamond_types::list::ListOpLog;
+use diamond_types::AgentId;
 use futures_util::stream;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc, RwLock};
+use crate::backend::chat::state::ChatState;
+use crate::backend::server::shutdown::Shutdown;
+use crate::backend::server::lag::{BroadcastChannel, BroadcastLag};
 
 /// Helper function to format a Braid update as bytes
 /// 
@@ -66,7 +84,9 @@ use tokio::sync::RwLock;
 /// # Arguments
 /// 
 /// * `version` - Optional version ID to include in Version header
-/// * `messages` - Messages to include in the update body
+/// * `parents` - Version(s) this update applies on top of; empty for snapshots
+/// * `messages` - Messages to include in the update body (the full list for a
+///   snapshot, or just the new ones for a delta)
 /// * `add_trailing_newlines` - Whether to add trailing newlines for subscription streams
 /// 
 /// # Returns
@@ -77,6 +97,7 @@ use tokio::sync::RwLock;
 /// Reference: braid-http-server.js sendUpdate() (lines 703-714)
 pub(crate) fn format_braid_update(
     version: Option<&String>,
+    parents: &[String],
     messages: &[Message],
     add_trailing_newlines: bool,
 ) -> Result<Bytes, StatusCode> {
@@ -87,7 +108,16 @@ pub(crate) fn format_braid_update(
             StatusCode::INTERNAL_SERVER_ERROR
         })?;
     
-    let body_bytes = json_body.as_bytes();
+    Ok(frame_braid_update(version, parents, json_body.as_bytes(), add_trailing_newlines))
+}
+
+/// Frame an already-encoded update body with Version, Parents and Content-Length headers
+pub(crate) fn frame_braid_update(
+    version: Option<&String>,
+    parents: &[String],
+    body_bytes: &[u8],
+    add_trailing_newlines: bool,
+) -> Bytes {
     let content_length = body_bytes.len();
     
     // Build headers using CRLF line endings (\r\n) per spec
@@ -102,6 +132,15 @@ pub(crate) fn format_braid_update(
         let version_header = format!("\"{}\"", ver); // JSON-stringify the version ID
         header_lines.push(format!("Version: {}\r\n", version_header));
     }
+
+    if !parents.is_empty() {
+        let parents_header = parents
+            .iter()
+            .map(|p| format!("\"{}\"", p))
+            .collect::<Vec<_>>()
+            .join(", ");
+        header_lines.push(format!("Parents: {}\r\n", parents_header));
+    }
     
     header_lines.push(format!("Content-Length: {}\r\n", content_length));
     header_lines.push("\r\n".to_string()); // Empty line after headers (CRLF) - separates headers from body
@@ -124,7 +163,279 @@ pub(crate) fn format_braid_update(
         result.extend_from_slice(b"\r\n\r\n"); // Blank line after body to separate from next update
     }
     
-    Ok(Bytes::from(result))
+    Bytes::from(result)
+}
+
+/// Parse the `Merge-Type` request header
+///
+/// Returns `Ok(None)` when the client didn't ask for one, and
+/// `406 Not Acceptable` for merge types the server doesn't support.
+pub(crate) fn negotiate_merge_type(headers: &axum::http::HeaderMap) -> Result<Option<MergeType>, StatusCode> {
+    let Some(value) = headers.get("merge-type") else {
+        return Ok(None);
+    };
+    let value = value.to_str().map_err(|_| StatusCode::NOT_ACCEPTABLE)?;
+    value.parse::<MergeType>().map(Some).map_err(|e| {
+        tracing::warn!("[Server] Rejecting subscription: {}", e);
+        StatusCode::NOT_ACCEPTABLE
+    })
+}
+
+/// Encodes the chat stream as diamond-types patches
+///
+/// Each subscription keeps its own text document with one JSON line per
+/// message; an update carries only the operations added since the last one.
+pub(crate) struct ChatPatchEncoder {
+    oplog: ListOpLog,
+    agent: AgentId,
+    /// Document length in characters
+    len: usize,
+    /// Messages appended so far
+    encoded: usize,
+}
+
+impl ChatPatchEncoder {
+    pub(crate) fn new() -> Self {
+        let mut oplog = ListOpLog::new();
+        let agent = oplog.get_or_create_agent_id("xfmail-server");
+        Self { oplog, agent, len: 0, encoded: 0 }
+    }
+
+    /// Append `messages` and return the patch since the previous call
+    pub(crate) fn encode(&mut self, messages: &[Message]) -> Result<Vec<u8>, StatusCode> {
+        let from = self.oplog.local_frontier();
+        for message in messages {
+            let mut line = serde_json::to_string(message).map_err(|e| {
+                tracing::error!("[Server] Failed to serialize message to JSON: {:?}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+            line.push('\n');
+            self.oplog.add_insert(self.agent, self.len, &line);
+            self.len += line.chars().count();
+            self.encoded += 1;
+        }
+        Ok(self.oplog.encode_from(&ENCODE_PATCH, from.as_ref()))
+    }
+
+    /// Patch bringing the subscriber up to a full snapshot
+    ///
+    /// The message list is append-only, so only the messages past what was
+    /// already encoded are new.
+    pub(crate) fn encode_snapshot(&mut self, all: &[Message]) -> Result<Vec<u8>, StatusCode> {
+        let new = all.get(self.encoded..).unwrap_or_default();
+        self.encode(new)
+    }
+}
+
+/// Format a full snapshot for the negotiated merge type
+fn format_snapshot(
+    patches: &mut Option<ChatPatchEncoder>,
+    version: Option<&String>,
+    messages: &[Message],
+) -> Result<Bytes, StatusCode> {
+    match patches {
+        Some(encoder) => Ok(frame_braid_update(version, &[], &encoder.encode_snapshot(messages)?, true)),
+        None => format_braid_update(version, &[], messages, true),
+    }
+}
+
+/// Format a delta carrying only new messages
+fn format_delta(patches: &mut Option<ChatPatchEncoder>, event: &MessageEvent) -> Result<Bytes, StatusCode> {
+    let version = Some(&event.version);
+    match patches {
+        Some(encoder) => Ok(frame_braid_update(version, &event.parents, &encoder.encode(&event.messages)?, true)),
+        None => format_braid_update(version, &event.parents, &event.messages, true),
+    }
+}
+
+/// Whether a subscriber whose last sent version is `last_version` can apply `event` as a delta
+pub(crate) fn delta_applies(last_version: Option<&String>, event: &MessageEvent) -> bool {
+    match last_version {
+        Some(last) => event.parents.first() == Some(last),
+        None => event.parents.is_empty(),
+    }
+}
+
+/// How often an idle subscription gets a keep-alive blank line
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Sending half of one subscriber's response body
+///
+/// Shared by the update-forwarding and heartbeat tasks. Axum drops the body
+/// when the client disconnects, so a failed send (or the channel closing)
+/// means the client is gone: `connected` is cleared and both tasks stop
+/// rather than lingering until their next send.
+#[derive(Clone)]
+pub(crate) struct SubscriberStream {
+    tx: mpsc::UnboundedSender<Result<Bytes, std::io::Error>>,
+    connected: Arc<RwLock<bool>>,
+}
+
+impl SubscriberStream {
+    /// A stream and the response body it feeds
+    pub(crate) fn open() -> (Self, Body) {
+        // Use std::io::Error as the error type since it implements Into<BoxError>
+        let (tx, rx) = mpsc::unbounded_channel::<Result<Bytes, std::io::Error>>();
+        let body_stream = stream::unfold(rx, |mut receiver| async move {
+            receiver.recv().await.map(|item| (item, receiver))
+        });
+        let subscriber = Self { tx, connected: Arc::new(RwLock::new(true)) };
+        (subscriber, Body::from_stream(body_stream))
+    }
+
+    /// Queue a chunk; `false` if the client is gone
+    async fn send(&self, item: Result<Bytes, std::io::Error>) -> bool {
+        if self.tx.send(item).is_ok() {
+            return true;
+        }
+        self.disconnect().await;
+        false
+    }
+
+    async fn disconnect(&self) {
+        *self.connected.write().await = false;
+    }
+
+    pub(crate) async fn is_connected(&self) -> bool {
+        *self.connected.read().await
+    }
+
+    /// Resolves once the client is gone, whichever task notices first
+    async fn disconnected(&self) {
+        self.tx.closed().await;
+        self.disconnect().await;
+    }
+}
+
+/// Send the initial snapshot, then forward broadcast updates until the
+/// client disconnects or the server shuts down
+async fn forward_updates(
+    subscriber: SubscriberStream,
+    mut broadcast_rx: broadcast::Receiver<MessageEvent>,
+    chat_state: Arc<RwLock<ChatState>>,
+    initial_messages: Vec<Message>,
+    initial_version: Option<String>,
+    merge_type: Option<MergeType>,
+    lag: BroadcastLag,
+    shutdown: Shutdown,
+) {
+    // diamond-types subscribers get patches instead of the full message array
+    let mut patches = merge_type.filter(|m| m.streams_patches()).map(|_| ChatPatchEncoder::new());
+
+    // Send initial snapshot
+    let initial_update = format_snapshot(&mut patches, initial_version.as_ref(), &initial_messages);
+    match initial_update {
+        Ok(bytes) => {
+            tracing::info!("[Server] Sending initial snapshot");
+            if !subscriber.send(Ok(bytes)).await {
+                tracing::warn!("[Server] Failed to send initial snapshot (receiver dropped)");
+                return;
+            }
+        }
+        Err(e) => {
+            tracing::error!("[Server] Failed to format initial snapshot: {:?}", e);
+            let io_err = std::io::Error::new(std::io::ErrorKind::Other, format!("Failed to format update: {:?}", e));
+            subscriber.send(Err(io_err)).await;
+            return;
+        }
+    }
+    
+    // Listen to broadcast channel for new messages
+    // `last_version` is the latest version this subscriber has been sent
+    let mut last_version = initial_version;
+    loop {
+        // Check if still connected
+        if !subscriber.is_connected().await {
+            tracing::info!("[Server] Connection closed, stopping stream");
+            break;
+        }
+        
+        let received = tokio::select! {
+            received = broadcast_rx.recv() => received,
+            _ = subscriber.disconnected() => {
+                tracing::info!("[Server] Client disconnected, stopping stream");
+                break;
+            }
+            _ = shutdown.wait() => {
+                // A last keep-alive, then end the body cleanly so the
+                // client reconnects instead of seeing a reset
+                tracing::info!("[Server] Server shutting down, closing subscription");
+                subscriber.send(Ok(Bytes::from("\r\n"))).await;
+                subscriber.disconnect().await;
+                break;
+            }
+        };
+        
+        match received {
+            Ok(event) => {
+                // Only send if this version is different and we have messages
+                if last_version.as_ref() != Some(&event.version) && !event.messages.is_empty() {
+                    tracing::info!("[Server] Received broadcast: {} new messages with version: {}", event.messages.len(), event.version);
+                    
+                    let (update, sent_version) = if delta_applies(last_version.as_ref(), &event) {
+                        (format_delta(&mut patches, &event), Some(event.version))
+                    } else {
+                        // Missed an update (or raced the initial snapshot): resync
+                        tracing::info!("[Server] Subscriber at {:?} can't apply delta on {:?}, sending snapshot", last_version, event.parents);
+                        let (messages, version) = {
+                            let state_read = chat_state.read().await;
+                            (state_read.messages.clone(), state_read.current_version.clone())
+                        };
+                        (format_snapshot(&mut patches, version.as_ref(), &messages), version)
+                    };
+                    match update {
+                        Ok(bytes) => {
+                            if !subscriber.send(Ok(bytes)).await {
+                                tracing::warn!("[Server] Failed to send update (receiver dropped)");
+                                break;
+                            }
+                        }
+                        Err(e) => {
+                            tracing::error!("[Server] Failed to format update: {:?}", e);
+                            // Continue on error - don't send error to client, just log it
+                        }
+                    }
+                    last_version = sent_version;
+                } else {
+                    tracing::debug!("[Server] Broadcast received but version unchanged or empty, continuing to listen");
+                }
+            }
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                let position = format!("at version {}", last_version.as_deref().unwrap_or("(none)"));
+                lag.record(BroadcastChannel::Message, &position, skipped, broadcast_rx.len());
+                // The next delta won't apply on our last version, so it resyncs with a snapshot
+            }
+            Err(broadcast::error::RecvError::Closed) => {
+                tracing::warn!("[Server] Broadcast channel closed, ending stream");
+                break;
+            }
+        }
+    }
+}
+
+/// Send a keep-alive blank line every `period` until the client
+/// disconnects or the server shuts down
+async fn send_heartbeats(subscriber: SubscriberStream, shutdown: Shutdown, period: Duration) {
+    let mut interval = tokio::time::interval(period);
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {}
+            _ = subscriber.disconnected() => break,
+            // The forwarding task sends the final keep-alive
+            _ = shutdown.wait() => break,
+        }
+        
+        // Check if still connected
+        if !subscriber.is_connected().await {
+            break;
+        }
+        
+        // Send heartbeat using CRLF (\r\n) per spec
+        // Blank lines help keep connections alive and signal to intermediaries
+        if !subscriber.send(Ok(Bytes::from("\r\n"))).await {
+            break;
+        }
+    }
 }
 
 /// Handle Braid subscription request (GET /chat with Subscribe header)
@@ -136,6 +447,9 @@ pub(crate) fn format_braid_update(
 /// - Continues streaming updates as new messages arrive
 /// - Supports reconnection with Parents header for catch-up
 /// - Sends keep-alive heartbeats every 30 seconds (CRLF format)
+/// - Ends with a final keep-alive when the server shuts down, so clients
+///   reconnect from their last version
+/// - Stops forwarding and heartbeats as soon as the client disconnects
 /// 
 /// # Arguments
 /// 
@@ -149,6 +463,7 @@ pub(crate) fn format_braid_update(
 /// # Errors
 /// 
 /// * `400 Bad Request` - If the Subscribe header is missing
+/// * `406 Not Acceptable` - If the Merge-Type header names an unsupported merge type
 /// * `500 Internal Server Error` - If JSON serialization fails
 /// 
 /// # Example Request
@@ -192,6 +507,11 @@ pub async fn handle_braid_subscription(
     }
     
     tracing::info!("[Server] Subscribe header found");
+
+    let merge_type = negotiate_merge_type(&headers)?;
+    if let Some(merge_type) = merge_type {
+        tracing::info!("[Server] Negotiated merge type: {}", merge_type);
+    }
     
     // Get Parents header for reconnection catch-up
     // Parents header uses Structured Headers format (RFC 8941) - comma-separated
@@ -251,128 +571,26 @@ pub async fn handle_braid_subscription(
     
     tracing::info!("[Server] Subscribed to broadcast channel, creating pure Braid stream");
     
-    // Convert initial_version to String for comparison
-    let last_version_str = initial_version.as_ref().map(|s| s.clone()).unwrap_or_else(|| String::new());
+    // Body stream fed by two tasks; either stops both once the client is gone
+    let (subscriber, body) = SubscriberStream::open();
     
-    // Create a channel for sending updates and managing connection state
-    // Use std::io::Error as the error type since it implements Into<BoxError>
-    let (tx, rx) = tokio::sync::mpsc::unbounded_channel::<Result<Bytes, std::io::Error>>();
-    let connected = Arc::new(RwLock::new(true));
-    let connected_clone = connected.clone();
-    
-    // Clone tx for heartbeat task before moving it into the first spawn
-    let tx_heartbeat = tx.clone();
-    
-    // Spawn task to handle the stream
-    let mut broadcast_rx_clone = broadcast_rx;
-    let last_version_clone = last_version_str.clone();
-    let initial_messages_clone = initial_messages.clone();
-    let initial_version_clone = initial_version.clone();
-    
-    tokio::spawn(async move {
-        // Send initial snapshot
-        // add_trailing_newlines=true because this is part of a subscription stream
-        let initial_update = format_braid_update(initial_version_clone.as_ref(), &initial_messages_clone, true);
-        match initial_update {
-            Ok(bytes) => {
-                tracing::info!("[Server] Sending initial snapshot");
-                if tx.send(Ok(bytes)).is_err() {
-                    tracing::warn!("[Server] Failed to send initial snapshot (receiver dropped)");
-                    return;
-                }
-            }
-            Err(e) => {
-                tracing::error!("[Server] Failed to format initial snapshot: {:?}", e);
-                let io_err = std::io::Error::new(std::io::ErrorKind::Other, format!("Failed to format update: {:?}", e));
-                let _ = tx.send(Err(io_err));
-                return;
-            }
-        }
-        
-        // Listen to broadcast channel for new messages
-        let mut last_version = last_version_clone;
-        loop {
-            // Check if still connected
-            {
-                let connected_guard = connected_clone.read().await;
-                if !*connected_guard {
-                    tracing::info!("[Server] Connection closed, stopping stream");
-                    break;
-                }
-            }
-            
-            match broadcast_rx_clone.recv().await {
-                Ok((new_messages, new_version)) => {
-                    // Only send if this version is different and we have messages
-                    if new_version != last_version && !new_messages.is_empty() {
-                        tracing::info!("[Server] Received broadcast: {} new messages with version: {}", new_messages.len(), new_version);
-                        
-                        // add_trailing_newlines=true because this is part of a subscription stream
-                        let update = format_braid_update(Some(&new_version), &new_messages, true);
-                        match update {
-                            Ok(bytes) => {
-                                if tx.send(Ok(bytes)).is_err() {
-                                    tracing::warn!("[Server] Failed to send update (receiver dropped)");
-                                    break;
-                                }
-                            }
-                            Err(e) => {
-                                tracing::error!("[Server] Failed to format update: {:?}", e);
-                                // Continue on error - don't send error to client, just log it
-                            }
-                        }
-                        last_version = new_version;
-                    } else {
-                        tracing::debug!("[Server] Broadcast received but version unchanged or empty, continuing to listen");
-                        last_version = new_version;
-                    }
-                }
-                Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
-                    tracing::warn!("[Server] Broadcast receiver lagged, skipped {} messages. Client may need to reconnect.", skipped);
-                    // Continue looping - we'll catch up on next message
-                }
-                Err(tokio::sync::broadcast::error::RecvError::Closed) => {
-                    tracing::warn!("[Server] Broadcast channel closed, ending stream");
-                    break;
-                }
-            }
-        }
-    });
+    tokio::spawn(forward_updates(
+        subscriber.clone(),
+        broadcast_rx,
+        app_state.chat_state.clone(),
+        initial_messages,
+        initial_version,
+        merge_type,
+        app_state.broadcast_lag.clone(),
+        app_state.shutdown.clone(),
+    ));
     
     // Spawn keep-alive heartbeat task
     // Heartbeats use CRLF (\r\n) per HTTP spec and Braid protocol
     // Blank lines help keep connections alive and signal to intermediaries
     // Reference: draft-toomim-httpbis-braid-http-04.txt Section 4.2 (Sending multiple updates per GET)
     // Reference: braid-http-server.js lines 567-583 (heartbeat implementation)
-    let connected_heartbeat = connected.clone();
-    tokio::spawn(async move {
-        let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(30));
-        loop {
-            interval.tick().await;
-            
-            // Check if still connected
-            {
-                let connected_guard = connected_heartbeat.read().await;
-                if !*connected_guard {
-                    break;
-                }
-            }
-            
-            // Send heartbeat using CRLF (\r\n) per spec
-            // Blank lines help keep connections alive and signal to intermediaries
-            if tx_heartbeat.send(Ok(Bytes::from("\r\n"))).is_err() {
-                break;
-            }
-        }
-    });
-    
-    // Create stream from receiver
-    let body_stream = stream::unfold(rx, |mut receiver| async move {
-        match receiver.recv().await {
-            Some(item) => Some((item, receiver)),
-            None => None,
-        }
-    });
+    tokio::spawn(send_heartbeats(subscriber, app_state.shutdown.clone(), HEARTBEAT_INTERVAL));
     
     // Create custom status code 209 Subscription
     // Note: Axum doesn't have 209 built-in, so we'll use from_u16
@@ -381,14 +599,6 @@ pub async fn handle_braid_subscription(
     
     tracing::info!("[Server] Creating pure Braid stream response with status {}", status);
     
-    // Create response with streaming body
-    let body = Body::from_stream(body_stream);
-    
-    // Handle connection close to update connected state
-    // Note: We can't easily detect when the client disconnects in Axum,
-    // but the stream will naturally end when the receiver is dropped
-    let _connected_for_cleanup = connected;
-    
     // Get Subscribe header value from request (or default to empty string)
     // Per spec section 4.1: "A server implementing Subscribe MUST include a Subscribe header in its response"
     // Reference: draft-toomim-httpbis-braid-http-04.txt Section 4.1, line 783
@@ -396,7 +606,12 @@ pub async fn handle_braid_subscription(
         .and_then(|h| h.to_str().ok())
         .unwrap_or("");
 
-    Ok(Response::builder()
+    let content_type = match merge_type {
+        Some(m) if m.streams_patches() => "application/diamond-types",
+        _ => "application/json",
+    };
+
+    let mut response = Response::builder()
         .status(status)
         // MUST include Subscribe header in response per spec section 4.1
         // Reference: draft-toomim-httpbis-braid-http-04.txt Section 4.1, line 783
@@ -406,11 +621,16 @@ pub async fn handle_braid_subscription(
         // Reference: braid-http-server.js line 525
         .header(axum::http::header::CACHE_CONTROL, "no-cache, no-transform, no-store")
         .header(axum::http::header::CONNECTION, "keep-alive")
-        .header(axum::http::header::CONTENT_TYPE, "application/json")
+        .header(axum::http::header::CONTENT_TYPE, content_type)
         // X-Accel-Buffering: no prevents nginx from buffering the subscription stream
         // This ensures real-time updates are sent immediately
         // Reference: braid-http-server.js line 544
-        .header("X-Accel-Buffering", "no")
+        .header("X-Accel-Buffering", "no");
+    if let Some(merge_type) = merge_type {
+        response = response.header(MERGE_TYPE_HEADER, merge_type.as_str());
+    }
+
+    Ok(response
         .body(body)
         .map_err(|e| {
             tracing::error!("[Server] Failed to build response: {:?}", e);
@@ -418,3 +638,116 @@ pub async fn handle_braid_subscription(
         })?)
 }
 
+#[cfg(test)]
+#[cfg(feature = "ssr")]
+mod tests {
+    use super::*;
+    use axum::http::HeaderMap;
+    use diamond_types::list::ListBranch;
+
+    #[test]
+    fn test_negotiate_merge_type() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(negotiate_merge_type(&headers), Ok(None));
+
+        headers.insert("merge-type", "diamond-types".parse().unwrap());
+        assert_eq!(negotiate_merge_type(&headers), Ok(Some(MergeType::DiamondTypes)));
+
+        headers.insert("merge-type", "\"sync9\"".parse().unwrap());
+        assert_eq!(negotiate_merge_type(&headers), Ok(Some(MergeType::Sync9)));
+
+        headers.insert("merge-type", "antimatter".parse().unwrap());
+        assert_eq!(negotiate_merge_type(&headers), Err(StatusCode::NOT_ACCEPTABLE));
+    }
+
+    fn event(version: &str, parents: &[&str]) -> MessageEvent {
+        MessageEvent {
+            messages: vec![Message::new("hi".to_string(), "alice".to_string())],
+            version: version.to_string(),
+            parents: parents.iter().map(|p| p.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_delta_applies_only_on_last_sent_version() {
+        let v1 = "v1".to_string();
+        assert!(delta_applies(Some(&v1), &event("v2", &["v1"])));
+        assert!(delta_applies(None, &event("v1", &[])));
+
+        // Missed v2, or subscribed before anything existed but missed v1
+        assert!(!delta_applies(Some(&v1), &event("v3", &["v2"])));
+        assert!(!delta_applies(None, &event("v2", &["v1"])));
+    }
+
+    #[test]
+    fn test_delta_update_carries_parents_and_new_message_only() {
+        let delta = event("v2", &["v1"]);
+        let bytes = format_delta(&mut None, &delta).unwrap();
+        let text = String::from_utf8(bytes.to_vec()).unwrap();
+
+        assert!(text.starts_with("Version: \"v2\"\r\nParents: \"v1\"\r\n"));
+        let body = text.split("\r\n\r\n").nth(1).unwrap();
+        let messages: Vec<Message> = serde_json::from_str(body).unwrap();
+        assert_eq!(messages.len(), 1);
+
+        // Snapshots have no Parents
+        let snapshot = format_snapshot(&mut None, Some(&"v2".to_string()), &delta.messages).unwrap();
+        assert!(!String::from_utf8_lossy(&snapshot).contains("Parents:"));
+    }
+
+    #[tokio::test]
+    async fn test_tasks_stop_when_the_client_disconnects() {
+        let (subscriber, body) = SubscriberStream::open();
+        let (_broadcast_tx, broadcast_rx) = broadcast::channel::<MessageEvent>(8);
+        let shutdown = Shutdown::new();
+
+        let forwarder = tokio::spawn(forward_updates(
+            subscriber.clone(),
+            broadcast_rx,
+            Arc::new(RwLock::new(ChatState::new())),
+            Vec::new(),
+            None,
+            None,
+            BroadcastLag::new(),
+            shutdown.clone(),
+        ));
+        // Long enough that only the disconnect can end it
+        let heartbeat = tokio::spawn(send_heartbeats(subscriber.clone(), shutdown, Duration::from_secs(3600)));
+
+        // Let the snapshot go out, then the client goes away
+        tokio::task::yield_now().await;
+        drop(body);
+
+        tokio::time::timeout(Duration::from_secs(1), async {
+            forwarder.await.unwrap();
+            heartbeat.await.unwrap();
+        })
+        .await
+        .expect("subscription tasks outlived the connection");
+        assert!(!subscriber.is_connected().await);
+    }
+
+    #[test]
+    fn test_patches_only_carry_new_messages() {
+        let mut encoder = ChatPatchEncoder::new();
+        let first = Message::new("hello".to_string(), "alice".to_string());
+        let second = Message::new("world".to_string(), "bob".to_string());
+
+        let initial = encoder.encode(std::slice::from_ref(&first)).unwrap();
+        let update = encoder.encode(std::slice::from_ref(&second)).unwrap();
+
+        // A subscriber applying both patches ends up with both messages
+        let mut replica = ListOpLog::new();
+        replica.decode_and_add(&initial).unwrap();
+        replica.decode_and_add(&update).unwrap();
+        let content = ListBranch::new_at_tip(&replica).content().to_string();
+        let lines: Vec<Message> = content.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1].text, "world");
+
+        // The second patch doesn't repeat the first message
+        let update_text = String::from_utf8_lossy(&update);
+        assert!(!update_text.contains("hello"));
+    }
+}
+
@@ -10,7 +10,8 @@
  * 
  * # Event Flow
  * 
- * 1. Client sends typing event (user started/stopped typing)
+ * 1. Client sends typing event (user started/stopped typing, recording audio
+ *    or uploading a file)
  * 2. Server receives event and broadcasts it via real-time event system
  * 3. All subscribers receive the typing event
  * 4. Clients update their UI to show/hide typing indicators
@@ -40,6 +41,8 @@ use axum::{
 /// JSON object with:
 /// - `user`: String - The username of the person typing
 /// - `is_typing`: bool - Whether the user is typing (true) or stopped (false)
+/// - `kind`: optional `"typing"` (default), `"recording_audio"` or `"uploading_file"`
+/// - `conversation_id`: optional conversation UUID
 /// 
 /// # Returns
 /// 
@@ -55,7 +58,7 @@ use axum::{
 /// POST /typing HTTP/1.1
 /// Content-Type: application/json
 /// 
-/// {"user":"Alice","is_typing":true}
+/// {"user":"Alice","is_typing":true,"kind":"recording_audio"}
 /// ```
 /// 
 /// # Example Response
@@ -79,31 +82,24 @@ pub async fn handle_typing_event(
     body: axum::body::Bytes,
 ) -> Result<Response<Body>, StatusCode> {
     use crate::backend::realtime::broadcast::broadcast_event;
-    use crate::shared::RealtimeEvent;
+    use crate::shared::{ActivityEvent, RealtimeEvent};
     
-    // Parse request body
-    #[derive(serde::Deserialize)]
-    struct TypingRequest {
-        /// Username of the person typing
-        user: String,
-        /// Whether the user is typing (true) or stopped (false)
-        is_typing: bool,
-    }
-    
-    let typing_request: TypingRequest = serde_json::from_slice(&body)
+    // Parse request body; `kind` defaults to plain typing
+    let typing_request: ActivityEvent = serde_json::from_slice(&body)
         .map_err(|e| {
             tracing::error!("[Server] Failed to parse typing request: {:?}", e);
             StatusCode::BAD_REQUEST
         })?;
     
     tracing::debug!(
-        "[Server] Received typing event: user={}, is_typing={}",
+        "[Server] Received typing event: user={}, is_typing={}, kind={:?}",
         typing_request.user,
-        typing_request.is_typing
+        typing_request.is_typing,
+        typing_request.kind
     );
     
     // Create typing event and broadcast it
-    let event = RealtimeEvent::typing(typing_request.user, typing_request.is_typing);
+    let event = RealtimeEvent::activity(typing_request);
     broadcast_event(&app_state.realtime_broadcast, event).await;
     
     // Return success response
@@ -0,0 +1,52 @@
+/**
+ * Version DAG Handler
+ *
+ * This module implements the debug endpoint for GET /chat/versions, which
+ * returns the chat's Braid version history so sync issues can be inspected.
+ *
+ * # Response
+ *
+ * JSON adjacency lists (each version with its parents) plus the current
+ * frontier. With `?format=dot` the DAG is returned as Graphviz DOT instead.
+ *
+ * The route is only registered in debug builds or with the
+ * `debug-endpoints` feature.
+ */
+
+use crate::backend::chat::state::ChatState;
+use axum::{
+    extract::{Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+/// Query parameters for the version DAG endpoints
+#[derive(Debug, Default, Deserialize)]
+pub struct VersionsParams {
+    /// `dot` for Graphviz output; JSON otherwise
+    pub format: Option<String>,
+}
+
+impl VersionsParams {
+    /// Whether Graphviz DOT output was asked for
+    pub fn wants_dot(&self) -> bool {
+        self.format.as_deref() == Some("dot")
+    }
+}
+
+/// Handle GET /chat/versions
+pub async fn handle_chat_versions(
+    State(chat_state): State<Arc<RwLock<ChatState>>>,
+    Query(params): Query<VersionsParams>,
+) -> Response {
+    let dag = chat_state.read().await.version_dag();
+    if params.wants_dot() {
+        ([(header::CONTENT_TYPE, "text/vnd.graphviz")], dag.to_dot()).into_response()
+    } else {
+        Json(dag).into_response()
+    }
+}
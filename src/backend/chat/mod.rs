@@ -12,8 +12,10 @@
 //! The chat module is organized into focused submodules:
 //!
 //! - **`state`** - Chat state management (messages, version DAG)
+//! - **`dag`** - Version DAG snapshots for debugging (JSON and Graphviz DOT)
 //! - **`handlers`** - Braid protocol handlers (GET/PUT /chat)
 //! - **`db`** - Database operations for persistence
+//! - **`coalesce`** - Merges rapid broadcasts into single frames
 //!
 //! # Example
 //! //!
@@ -33,6 +35,10 @@
 pub mod state;
 
 
+/// Version DAG snapshots for debugging
+pub mod dag;
+
+
 /// Braid protocol handlers
 pub mod handlers;
 
@@ -42,6 +48,11 @@ pub mod handlers;
 pub mod db;
 
 
+/// Coalescing of chat broadcasts under rapid sending
+#[cfg(feature = "ssr")]
+pub mod coalesce;
+
+
 /// Re-export commonly used types
 pub use state::ChatState;
 pub use handlers::{handle_braid_subscription, handle_braid_put};
\ No newline at end of file
@@ -1,3 +1,4 @@
+use crate::backend::chat::dag::VersionDag;
 use crate::shared::Message;
 use std::collections::HashMap;
 
@@ -35,6 +36,16 @@ impl ChatState {
     pub fn get_messages_since(&self, _parent: Option<&String>) -> Vec<Message> {
         self.messages.clone()
     }
+
+    /// Snapshot of the version history with its current frontier
+    pub fn version_dag(&self) -> VersionDag {
+        VersionDag::from_history(self.version_history.clone())
+    }
+
+    /// Version history as Graphviz DOT, for visualizing branches and merges
+    pub fn dag_to_dot(&self) -> String {
+        self.version_dag().to_dot()
+    }
 }
 
 impl Default for ChatState {
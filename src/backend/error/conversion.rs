@@ -15,10 +15,12 @@
  * Error responses are returned as JSON with the following structure:
  * ```json
  * {
- *   "error": "Error message",
- *   "status": 400
+ *   "code": "MESSAGE_TOO_LONG",
+ *   "message": "Error message"
  * }
  * ```
+ * 
+ * `code` is an `ErrorCode`, which clients map to their own localized text.
  */
 
 use axum::{
@@ -27,6 +29,7 @@ use axum::{
     body::Body,
 };
 use crate::backend::error::types::BackendError;
+use crate::shared::ErrorBody;
 
 impl IntoResponse for BackendError {
     /// Convert a backend error into an HTTP response
@@ -36,9 +39,9 @@ impl IntoResponse for BackendError {
     /// 
     /// # Response Format
     /// 
-    /// The response is a JSON object with:
-    /// - `error`: The error message
-    /// - `status`: The HTTP status code
+    /// The response is a JSON [`ErrorBody`] with:
+    /// - `code`: The machine-readable error code
+    /// - `message`: The error message
     /// 
     /// # Example
     /// 
@@ -53,19 +56,16 @@ impl IntoResponse for BackendError {
     /// ```
     fn into_response(self) -> Response {
         let status = self.status_code();
-        let message = self.message();
-        
-        // Create a JSON error response
-        let body = serde_json::json!({
-            "error": message,
-            "status": status.as_u16(),
-        });
+        let body = ErrorBody {
+            code: self.code(),
+            message: self.message(),
+        };
         
         Response::builder()
             .status(status)
             .header("Content-Type", "application/json")
             .body(Body::from(serde_json::to_string(&body).unwrap_or_else(|_| {
-                format!(r#"{{"error":"{}","status":{}}}"#, message, status.as_u16())
+                format!(r#"{{"code":"INTERNAL","message":"{}"}}"#, status)
             })))
             .unwrap_or_else(|_| {
                 Response::builder()
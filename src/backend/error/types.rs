@@ -9,6 +9,7 @@
  * - `HandlerError` - Errors that occur in HTTP handlers
  * - `StateError` - Errors related to application state management
  * - `ProtocolError` - Braid protocol-specific errors
+ * - `Coded` - Request rejections with a specific `ErrorCode`
  * 
  * # Error Categories
  * 
@@ -36,7 +37,7 @@
 
 use thiserror::Error;
 use axum::http::StatusCode;
-use crate::shared::SharedError;
+use crate::shared::{ErrorCode, SharedError};
 
 /// Backend-specific error types
 /// 
@@ -71,6 +72,20 @@ pub enum BackendError {
         message: String,
     },
     
+    /// Request rejected for a reason the client can tell apart
+    /// 
+    /// Validation failures use this so the response carries an
+    /// `ErrorCode` such as `MESSAGE_TOO_LONG`, not just the status.
+    #[error("{message}")]
+    Coded {
+        /// HTTP status code for this error
+        status: StatusCode,
+        /// Machine-readable reason
+        code: ErrorCode,
+        /// Human-readable error message
+        message: String,
+    },
+    
     /// State management error (e.g., lock acquisition failure)
     /// 
     /// This error occurs when managing application state fails, such as
@@ -128,6 +143,25 @@ impl BackendError {
         }
     }
     
+    /// Create a new error with a specific error code
+    /// 
+    /// # Example
+    /// 
+    /// ```rust
+    /// use braid_site::backend::error::BackendError;
+    /// use braid_site::shared::ErrorCode;
+    /// use axum::http::StatusCode;
+    /// 
+    /// let err = BackendError::coded(StatusCode::BAD_REQUEST, ErrorCode::EmptyText, "Message text is empty");
+    /// ```
+    pub fn coded(status: StatusCode, code: ErrorCode, message: impl Into<String>) -> Self {
+        Self::Coded {
+            status,
+            code,
+            message: message.into(),
+        }
+    }
+    
     /// Create a new state error
     /// 
     /// # Arguments
@@ -174,7 +208,7 @@ impl BackendError {
     /// 
     /// # Status Code Mapping
     /// 
-    /// - `HandlerError` - Uses the status code from the error
+    /// - `HandlerError`, `Coded` - Use the status code from the error
     /// - `StateError` - 500 Internal Server Error
     /// - `ProtocolError` - 400 Bad Request
     /// - `SharedError` - Depends on the shared error type
@@ -182,12 +216,17 @@ impl BackendError {
     pub fn status_code(&self) -> StatusCode {
         match self {
             Self::HandlerError { status, .. } => *status,
+            Self::Coded { status, .. } => *status,
             Self::StateError { .. } => StatusCode::INTERNAL_SERVER_ERROR,
             Self::ProtocolError { .. } => StatusCode::BAD_REQUEST,
             Self::SharedError(err) => match err {
                 SharedError::SerializationError { .. } => StatusCode::INTERNAL_SERVER_ERROR,
                 SharedError::ValidationError { .. } => StatusCode::BAD_REQUEST,
                 SharedError::MessageError { .. } => StatusCode::BAD_REQUEST,
+                SharedError::NetworkError { .. } => StatusCode::BAD_GATEWAY,
+                SharedError::HttpError { status, .. } => {
+                    StatusCode::from_u16(*status).unwrap_or(StatusCode::BAD_GATEWAY)
+                }
             },
             Self::SerializationError(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
@@ -201,12 +240,31 @@ impl BackendError {
     pub fn message(&self) -> String {
         match self {
             Self::HandlerError { message, .. } => message.clone(),
+            Self::Coded { message, .. } => message.clone(),
             Self::StateError { message, .. } => message.clone(),
             Self::ProtocolError { message, .. } => message.clone(),
             Self::SharedError(err) => err.to_string(),
             Self::SerializationError(err) => err.to_string(),
         }
     }
+    
+    /// Get the machine-readable error code
+    /// 
+    /// `Coded` errors return their own code; everything else gets the
+    /// generic code for its status.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Self::Coded { code, .. } => *code,
+            _ => ErrorCode::for_status(self.status_code().as_u16()),
+        }
+    }
+}
+
+/// Bare status codes from handlers and helpers that don't say more
+impl From<StatusCode> for BackendError {
+    fn from(status: StatusCode) -> Self {
+        Self::handler(status, status.canonical_reason().unwrap_or("Error"))
+    }
 }
 
 #[cfg(test)]
@@ -270,6 +328,17 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_error_codes() {
+        let coded = BackendError::coded(StatusCode::BAD_REQUEST, ErrorCode::MessageTooLong, "Too long");
+        assert_eq!(coded.status_code(), StatusCode::BAD_REQUEST);
+        assert_eq!(coded.code(), ErrorCode::MessageTooLong);
+
+        let unauthorized: BackendError = StatusCode::UNAUTHORIZED.into();
+        assert_eq!(unauthorized.code(), ErrorCode::Unauthorized);
+        assert_eq!(BackendError::state("State error").code(), ErrorCode::Internal);
+    }
+
     #[test]
     fn test_error_message() {
         let error = BackendError::handler(StatusCode::BAD_REQUEST, "Test message");
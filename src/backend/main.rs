@@ -27,7 +27,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     tracing::warn!("[STARTUP] Server initialization started");
 
     // Create the Axum app
-    let app = xfmail::backend::server::init::create_app().await;
+    let shutdown = xfmail::backend::server::shutdown::Shutdown::new();
+    let app = xfmail::backend::server::init::create_app(shutdown.clone()).await;
 
     let port = std::env::var("SERVER_PORT")
         .unwrap_or_else(|_| "3000".to_string())
@@ -42,7 +43,24 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let listener = tokio::net::TcpListener::bind(addr).await?;
     eprintln!("[STARTUP] Listening on {}", addr);
     eprintln!("[STARTUP] Client should connect to http://127.0.0.1:{}", port);
-    axum::serve(listener, app).await?;
+
+    // On SIGTERM/Ctrl-C stop accepting connections and tell subscriptions
+    // to close, then give open connections a bounded time to finish
+    let draining = shutdown.clone();
+    let server = axum::serve(listener, app).with_graceful_shutdown(async move {
+        xfmail::backend::server::shutdown::shutdown_signal().await;
+        tracing::warn!("Shutting down, closing open subscriptions");
+        draining.trigger();
+    });
+    let deadline = async {
+        shutdown.wait().await;
+        tokio::time::sleep(xfmail::backend::server::shutdown::DRAIN_TIMEOUT).await;
+    };
+    tokio::select! {
+        result = server => result?,
+        _ = deadline => tracing::warn!("Connections still open after the drain timeout, exiting"),
+    }
+    tracing::warn!("Server stopped");
 
     Ok(())
 }
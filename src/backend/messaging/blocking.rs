@@ -0,0 +1,217 @@
+//! Blocked Users
+//!
+//! Lets a user block others:
+//!
+//! - **Friend requests** from someone the recipient blocked are rejected
+//!   with `403 Forbidden`
+//! - **Direct messages** from someone the other participant blocked are
+//!   rejected with [`BLOCKED_ERROR`]
+//!
+//! Group conversations aren't affected server-side; the blocker's client
+//! hides blocked users' messages instead. Blocking is one-way and silent:
+//! the blocked user isn't told, beyond the rejections above.
+
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::shared::messaging::BlockedUsersResponse;
+use super::handlers::extract_user_id;
+
+pub use crate::shared::messaging::BLOCKED_ERROR;
+
+/// Block `blocked` for `blocker`; blocking twice is a no-op
+pub async fn block_user(pool: &PgPool, blocker: Uuid, blocked: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query("INSERT INTO blocked_users (blocker_id, blocked_id) VALUES ($1, $2) ON CONFLICT DO NOTHING")
+        .bind(blocker)
+        .bind(blocked)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Unblock `blocked` for `blocker`
+pub async fn unblock_user(pool: &PgPool, blocker: Uuid, blocked: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM blocked_users WHERE blocker_id = $1 AND blocked_id = $2")
+        .bind(blocker)
+        .bind(blocked)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Users `blocker` has blocked, most recent first
+pub async fn get_blocked_users(pool: &PgPool, blocker: Uuid) -> Result<Vec<Uuid>, sqlx::Error> {
+    sqlx::query_scalar("SELECT blocked_id FROM blocked_users WHERE blocker_id = $1 ORDER BY created_at DESC")
+        .bind(blocker)
+        .fetch_all(pool)
+        .await
+}
+
+/// Whether `blocker` has blocked `other`
+pub async fn is_blocked(pool: &PgPool, blocker: Uuid, other: Uuid) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM blocked_users WHERE blocker_id = $1 AND blocked_id = $2)")
+        .bind(blocker)
+        .bind(other)
+        .fetch_one(pool)
+        .await
+}
+
+/// Whether `sender` is blocked from posting in a conversation
+///
+/// Only direct conversations are checked: there, the other participant
+/// blocking the sender stops their messages.
+pub async fn blocks_message(pool: &PgPool, sender: Uuid, conversation_id: Uuid) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar(
+        r#"
+        SELECT EXISTS(
+            SELECT 1
+            FROM conversations c
+            JOIN conversation_participants cp ON cp.conversation_id = c.id
+            JOIN blocked_users b ON b.blocker_id = cp.user_id AND b.blocked_id = $2
+            WHERE c.id = $1 AND COALESCE(c.is_direct_message, FALSE)
+        )
+        "#
+    )
+    .bind(conversation_id)
+    .bind(sender)
+    .fetch_one(pool)
+    .await
+}
+
+/// GET /api/users/blocked
+pub async fn list_blocked_users(
+    State(db_pool): State<Option<PgPool>>,
+    headers: HeaderMap,
+) -> Result<Json<BlockedUsersResponse>, StatusCode> {
+    let pool = db_pool.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+    let user_id = extract_user_id(&headers)?;
+
+    let user_ids = get_blocked_users(pool, user_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to list blocked users: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(BlockedUsersResponse { user_ids }))
+}
+
+/// POST /api/users/{user_id}/block
+pub async fn block_user_handler(
+    State(db_pool): State<Option<PgPool>>,
+    Path(blocked_id): Path<Uuid>,
+    headers: HeaderMap,
+) -> Result<StatusCode, StatusCode> {
+    let pool = db_pool.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+    let user_id = extract_user_id(&headers)?;
+    if blocked_id == user_id {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    crate::backend::auth::users::get_user_by_id(pool, blocked_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    block_user(pool, user_id, blocked_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to block {} for {}: {:?}", blocked_id, user_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// DELETE /api/users/{user_id}/block
+pub async fn unblock_user_handler(
+    State(db_pool): State<Option<PgPool>>,
+    Path(blocked_id): Path<Uuid>,
+    headers: HeaderMap,
+) -> Result<StatusCode, StatusCode> {
+    let pool = db_pool.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+    let user_id = extract_user_id(&headers)?;
+
+    unblock_user(pool, user_id, blocked_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to unblock {} for {}: {:?}", blocked_id, user_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::auth::users::User;
+    use crate::backend::messaging::db;
+    use crate::backend::messaging::handlers::send_friend_request;
+    use crate::backend::test_db::{auth, TestDatabase};
+    use crate::shared::messaging::SendFriendRequestRequest;
+
+    async fn friend_request(pool: &PgPool, from: &User, to: &User) -> Result<bool, StatusCode> {
+        let request = SendFriendRequestRequest {
+            to_email: to.email.clone(),
+            to_user_id: Some(to.id),
+            message: None,
+        };
+        send_friend_request(State(Some(pool.clone())), auth(from), Json(request))
+            .await
+            .map(|response| response.0.success)
+    }
+
+    #[tokio::test]
+    async fn test_blocked_users_cannot_request_or_message() {
+        let db = TestDatabase::new().await;
+        let pool = db.pool().clone();
+        let (alice, bob, carol) = (db.user().await, db.user().await, db.user().await);
+
+        let status = block_user_handler(State(Some(pool.clone())), Path(bob.id), auth(&alice)).await.unwrap();
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        // Blocking twice is harmless
+        block_user_handler(State(Some(pool.clone())), Path(bob.id), auth(&alice)).await.unwrap();
+        let blocked = list_blocked_users(State(Some(pool.clone())), auth(&alice)).await.unwrap();
+        assert_eq!(blocked.0.user_ids, vec![bob.id]);
+
+        // Friend requests: one-way
+        assert_eq!(friend_request(&pool, &bob, &alice).await, Err(StatusCode::FORBIDDEN));
+        assert_eq!(friend_request(&pool, &alice, &bob).await, Ok(true));
+
+        // Direct messages are blocked, groups aren't
+        let direct = db::create_conversation(&pool, alice.id, bob.id).await.unwrap();
+        let group = db::create_group_conversation(&pool, alice.id, Some("Team"), &[alice.id, bob.id, carol.id])
+            .await
+            .unwrap();
+        assert!(blocks_message(&pool, bob.id, direct).await.unwrap());
+        assert!(!blocks_message(&pool, alice.id, direct).await.unwrap());
+        assert!(!blocks_message(&pool, bob.id, group).await.unwrap());
+
+        let status = unblock_user_handler(State(Some(pool.clone())), Path(bob.id), auth(&alice)).await.unwrap();
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        assert!(!blocks_message(&pool, bob.id, direct).await.unwrap());
+        assert!(list_blocked_users(State(Some(pool.clone())), auth(&alice)).await.unwrap().0.user_ids.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_block_rejects_self_and_unknown_users() {
+        let db = TestDatabase::new().await;
+        let pool = db.pool().clone();
+        let alice = db.user().await;
+
+        let status = block_user_handler(State(Some(pool.clone())), Path(alice.id), auth(&alice)).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let status = block_user_handler(State(Some(pool.clone())), Path(Uuid::new_v4()), auth(&alice)).await.unwrap_err();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let status = block_user_handler(State(Some(pool)), Path(Uuid::new_v4()), HeaderMap::new()).await.unwrap_err();
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+}
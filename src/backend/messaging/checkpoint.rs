@@ -0,0 +1,329 @@
+//! Conversation Version Checkpoints
+//!
+//! A client reconnecting with a `Parents` header only needs the messages
+//! after that version. Rather than walk the whole history to find them, the
+//! server records a checkpoint every [`CHECKPOINT_INTERVAL`] messages per
+//! conversation: the version reached, the timestamp of the last message and
+//! the ids of the messages covered since the previous checkpoint.
+//!
+//! [`get_messages_since`] jumps to the nearest checkpoint at or before the
+//! client's version and scans forward from there, so the work is bounded by
+//! the checkpoint interval plus the size of the delta. Clients too far behind
+//! (or with a version the server doesn't know) get `None` and fall back to
+//! the usual snapshot.
+
+use std::collections::HashSet;
+
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+
+use crate::backend::messaging::db::chat_message_from_row;
+use crate::shared::messaging::ChatMessage;
+
+/// Messages between checkpoints
+pub const CHECKPOINT_INTERVAL: usize = 100;
+
+/// Most messages sent as a reconnect delta; further behind gets a snapshot
+pub const MAX_DELTA_MESSAGES: usize = 500;
+
+/// Conversation state at a point in its history
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionCheckpoint {
+    pub conversation_id: Uuid,
+    /// Messages in the conversation up to and including this checkpoint
+    pub seq: i64,
+    /// Version of the last message covered
+    pub version: String,
+    /// Messages covered since the previous checkpoint, in order
+    pub message_ids: Vec<Uuid>,
+    /// Timestamp of the last message covered; scans resume from here
+    pub created_at: DateTime<Utc>,
+}
+
+/// Checkpoint covering the next [`CHECKPOINT_INTERVAL`] messages, if there are that many
+///
+/// `pending` are the messages after `previous`, oldest first.
+pub fn next_checkpoint(
+    previous: Option<&VersionCheckpoint>,
+    conversation_id: Uuid,
+    pending: &[ChatMessage],
+) -> Option<VersionCheckpoint> {
+    let covered = pending.get(..CHECKPOINT_INTERVAL)?;
+    let last = covered.last()?;
+    Some(VersionCheckpoint {
+        conversation_id,
+        seq: previous.map_or(0, |p| p.seq) + CHECKPOINT_INTERVAL as i64,
+        version: last.braid_version.clone(),
+        message_ids: covered.iter().map(|m| m.id).collect(),
+        created_at: last.timestamp,
+    })
+}
+
+/// Messages after `version`, from a scan starting at `checkpoint`
+///
+/// `scanned` are the messages from the checkpoint's timestamp onwards (or
+/// from the start without a checkpoint), oldest first. Messages the
+/// checkpoint already covers are skipped. Returns `None` if `version` is
+/// neither the checkpoint's nor in the scan.
+pub fn messages_after(
+    checkpoint: Option<&VersionCheckpoint>,
+    scanned: Vec<ChatMessage>,
+    version: &str,
+) -> Option<Vec<ChatMessage>> {
+    let covered: HashSet<Uuid> = checkpoint
+        .map(|c| c.message_ids.iter().copied().collect())
+        .unwrap_or_default();
+    let mut rest: Vec<ChatMessage> = scanned.into_iter().filter(|m| !covered.contains(&m.id)).collect();
+
+    if checkpoint.is_some_and(|c| c.version == version) {
+        return Some(rest);
+    }
+    let position = rest.iter().position(|m| m.braid_version == version)?;
+    Some(rest.split_off(position + 1))
+}
+
+fn checkpoint_from_row(row: &sqlx::postgres::PgRow) -> VersionCheckpoint {
+    VersionCheckpoint {
+        conversation_id: row.get("conversation_id"),
+        seq: row.get("seq"),
+        version: row.get("version"),
+        message_ids: row.get("message_ids"),
+        created_at: row.get("created_at"),
+    }
+}
+
+/// Newest checkpoint of a conversation, optionally no later than `at`
+async fn latest_checkpoint(
+    pool: &PgPool,
+    conversation_id: Uuid,
+    at: Option<DateTime<Utc>>,
+) -> Result<Option<VersionCheckpoint>, sqlx::Error> {
+    let row = sqlx::query(
+        r#"
+        SELECT conversation_id, seq, version, message_ids, created_at
+        FROM conversation_checkpoints
+        WHERE conversation_id = $1 AND ($2::timestamptz IS NULL OR created_at <= $2)
+        ORDER BY seq DESC
+        LIMIT 1
+        "#
+    )
+    .bind(conversation_id)
+    .bind(at)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.as_ref().map(checkpoint_from_row))
+}
+
+/// Messages from `checkpoint` onwards, oldest first, at most `limit`
+async fn scan_from(
+    pool: &PgPool,
+    conversation_id: Uuid,
+    checkpoint: Option<&VersionCheckpoint>,
+    limit: usize,
+) -> Result<Vec<ChatMessage>, sqlx::Error> {
+    let rows = sqlx::query(
+        r#"
+        SELECT id, conversation_id, sender_id, content, message_type, is_read, is_delivered, crdt_timestamp, braid_version, created_at, link_preview, moderation_flag, edit_version, deleted_at, sequence
+        FROM chat_messages
+        WHERE conversation_id = $1 AND ($2::timestamptz IS NULL OR created_at >= $2)
+        ORDER BY created_at ASC, id ASC
+        LIMIT $3
+        "#
+    )
+    .bind(conversation_id)
+    .bind(checkpoint.map(|c| c.created_at))
+    .bind(limit as i64)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.iter().map(chat_message_from_row).collect())
+}
+
+/// Record a checkpoint if enough messages arrived since the last one
+///
+/// Called after each stored message. Concurrent callers may compute the same
+/// checkpoint; only the first insert wins.
+pub async fn maybe_create_checkpoint(
+    pool: &PgPool,
+    conversation_id: Uuid,
+) -> Result<Option<VersionCheckpoint>, sqlx::Error> {
+    let previous = latest_checkpoint(pool, conversation_id, None).await?;
+    // Messages sharing the previous checkpoint's timestamp are scanned again
+    let scanned = scan_from(pool, conversation_id, previous.as_ref(), CHECKPOINT_INTERVAL * 2).await?;
+    let covered: HashSet<Uuid> = previous
+        .as_ref()
+        .map(|c| c.message_ids.iter().copied().collect())
+        .unwrap_or_default();
+    let pending: Vec<ChatMessage> = scanned.into_iter().filter(|m| !covered.contains(&m.id)).collect();
+
+    let Some(checkpoint) = next_checkpoint(previous.as_ref(), conversation_id, &pending) else {
+        return Ok(None);
+    };
+
+    sqlx::query(
+        r#"
+        INSERT INTO conversation_checkpoints (conversation_id, seq, version, message_ids, created_at)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (conversation_id, seq) DO NOTHING
+        "#
+    )
+    .bind(checkpoint.conversation_id)
+    .bind(checkpoint.seq)
+    .bind(&checkpoint.version)
+    .bind(&checkpoint.message_ids)
+    .bind(checkpoint.created_at)
+    .execute(pool)
+    .await?;
+
+    tracing::debug!(
+        "[MessageSync] Checkpoint {} at version {} for conversation {}",
+        checkpoint.seq,
+        checkpoint.version,
+        conversation_id
+    );
+    Ok(Some(checkpoint))
+}
+
+/// Messages after `version` in a conversation, oldest first
+///
+/// `None` if the version is unknown or the client is more than
+/// [`MAX_DELTA_MESSAGES`] behind; send a snapshot instead.
+pub async fn get_messages_since(
+    pool: &PgPool,
+    conversation_id: Uuid,
+    version: &str,
+) -> Result<Option<Vec<ChatMessage>>, sqlx::Error> {
+    let known_at: Option<DateTime<Utc>> = sqlx::query_scalar(
+        r#"
+        SELECT created_at FROM chat_messages
+        WHERE conversation_id = $1 AND braid_version = $2
+        ORDER BY created_at DESC
+        LIMIT 1
+        "#
+    )
+    .bind(conversation_id)
+    .bind(version)
+    .fetch_optional(pool)
+    .await?;
+    let Some(known_at) = known_at else {
+        return Ok(None);
+    };
+
+    let checkpoint = latest_checkpoint(pool, conversation_id, Some(known_at)).await?;
+    // Room for the rest of the checkpoint's interval before the delta itself
+    let limit = CHECKPOINT_INTERVAL * 2 + MAX_DELTA_MESSAGES;
+    let scanned = scan_from(pool, conversation_id, checkpoint.as_ref(), limit).await?;
+    if scanned.len() >= limit {
+        return Ok(None);
+    }
+
+    Ok(messages_after(checkpoint.as_ref(), scanned, version))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// History of `count` messages one second apart, versions `v1..`
+    fn history(conversation_id: Uuid, count: usize) -> Vec<ChatMessage> {
+        let start = DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z").unwrap().with_timezone(&Utc);
+        (1..=count)
+            .map(|i| {
+                let mut message = ChatMessage::new_text(conversation_id, Uuid::new_v4(), format!("message {}", i), i as u64);
+                message.braid_version = format!("v{}", i);
+                message.timestamp = start + chrono::Duration::seconds(i as i64);
+                message
+            })
+            .collect()
+    }
+
+    /// Checkpoints as `maybe_create_checkpoint` would record them after each message
+    fn checkpoints(conversation_id: Uuid, history: &[ChatMessage]) -> Vec<VersionCheckpoint> {
+        let mut checkpoints: Vec<VersionCheckpoint> = Vec::new();
+        while let Some(checkpoint) = next_checkpoint(
+            checkpoints.last(),
+            conversation_id,
+            &history[checkpoints.len() * CHECKPOINT_INTERVAL..],
+        ) {
+            checkpoints.push(checkpoint);
+        }
+        checkpoints
+    }
+
+    /// What `get_messages_since` does against the database; returns how
+    /// many messages were scanned and the delta
+    fn delta(checkpoints: &[VersionCheckpoint], history: &[ChatMessage], version: &str) -> (usize, Option<Vec<ChatMessage>>) {
+        let known_at = history.iter().find(|m| m.braid_version == version).unwrap().timestamp;
+        let checkpoint = checkpoints.iter().rev().find(|c| c.created_at <= known_at);
+        let scanned: Vec<ChatMessage> = history
+            .iter()
+            .filter(|m| checkpoint.is_none_or(|c| m.timestamp >= c.created_at))
+            .cloned()
+            .collect();
+        (scanned.len(), messages_after(checkpoint, scanned, version))
+    }
+
+    #[test]
+    fn test_checkpoints_every_interval() {
+        let conversation_id = Uuid::new_v4();
+        let history = history(conversation_id, CHECKPOINT_INTERVAL * 2 + 10);
+        let checkpoints = checkpoints(conversation_id, &history);
+
+        assert_eq!(checkpoints.len(), 2);
+        assert_eq!(checkpoints[0].seq, CHECKPOINT_INTERVAL as i64);
+        assert_eq!(checkpoints[0].version, format!("v{}", CHECKPOINT_INTERVAL));
+        assert_eq!(checkpoints[1].seq, CHECKPOINT_INTERVAL as i64 * 2);
+        assert_eq!(checkpoints[1].message_ids.len(), CHECKPOINT_INTERVAL);
+        assert_eq!(checkpoints[1].message_ids[0], history[CHECKPOINT_INTERVAL].id);
+    }
+
+    #[test]
+    fn test_reconnect_delta_scans_from_checkpoint() {
+        let conversation_id = Uuid::new_v4();
+        let history = history(conversation_id, CHECKPOINT_INTERVAL * 3 + 5);
+        let checkpoints = checkpoints(conversation_id, &history);
+
+        // Client last saw a message just past the third checkpoint
+        let version = format!("v{}", CHECKPOINT_INTERVAL * 3 + 2);
+        let (scanned, suffix) = delta(&checkpoints, &history, &version);
+        let suffix = suffix.unwrap();
+
+        // Only the messages since the checkpoint were scanned (the
+        // checkpoint's own last message shares its timestamp)
+        assert_eq!(scanned, 6);
+        let versions: Vec<String> = suffix.into_iter().map(|m| m.braid_version).collect();
+        let expected: Vec<String> = (3..=5).map(|i| format!("v{}", CHECKPOINT_INTERVAL * 3 + i)).collect();
+        assert_eq!(versions, expected);
+    }
+
+    #[test]
+    fn test_reconnect_delta_at_checkpoint_version() {
+        let conversation_id = Uuid::new_v4();
+        let history = history(conversation_id, CHECKPOINT_INTERVAL * 2 + 3);
+        let checkpoints = checkpoints(conversation_id, &history);
+
+        let version = format!("v{}", CHECKPOINT_INTERVAL);
+        let (_, suffix) = delta(&checkpoints, &history, &version);
+        let suffix = suffix.unwrap();
+        assert_eq!(suffix.len(), CHECKPOINT_INTERVAL + 3);
+        assert_eq!(suffix[0].id, history[CHECKPOINT_INTERVAL].id);
+        assert_eq!(suffix.last().unwrap().id, history.last().unwrap().id);
+
+        // Up to date: nothing to send
+        let (_, suffix) = delta(&checkpoints, &history, &format!("v{}", CHECKPOINT_INTERVAL * 2 + 3));
+        assert_eq!(suffix, Some(Vec::new()));
+    }
+
+    #[test]
+    fn test_version_outside_scan_is_unknown() {
+        let conversation_id = Uuid::new_v4();
+        let history = history(conversation_id, CHECKPOINT_INTERVAL + 5);
+        let checkpoints = checkpoints(conversation_id, &history);
+
+        // A scan from the checkpoint can't see versions before it
+        let scanned = history[CHECKPOINT_INTERVAL..].to_vec();
+        assert_eq!(messages_after(checkpoints.first(), scanned, "v3"), None);
+    }
+}
@@ -0,0 +1,207 @@
+//! Content Filter
+//!
+//! Pluggable moderation hook run on every message PUT. A filter can allow,
+//! flag (store with a moderation marker), redact (store with matched terms
+//! masked) or reject a message.
+//!
+//! Filters run on the send hot path, so implementations must be synchronous
+//! and cheap; [`WordlistFilter`] does a single tokenizing pass with hash
+//! lookups.
+//!
+//! # Configuration
+//!
+//! - `CONTENT_FILTER_WORDLIST` - path to a wordlist file; when unset the
+//!   no-op [`NoopFilter`] is used
+//!
+//! # Wordlist Format
+//!
+//! One term per line, matched case-insensitively as a whole word. Lines may
+//! be prefixed with an action (`reject:`, `redact:` or `flag:`); unprefixed
+//! terms are redacted. Blank lines and `#` comments are ignored.
+//!
+//! ```text
+//! # hard block
+//! reject:slur
+//! darn
+//! flag:scam
+//! ```
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// Error code returned to clients when a message is rejected
+pub const CONTENT_REJECTED_ERROR: &str = "content_rejected";
+
+/// Outcome of filtering a message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterDecision {
+    /// Store as-is
+    Allow,
+    /// Store as-is with a moderation marker
+    Flag { reason: String },
+    /// Store the redacted content instead
+    Redact { content: String },
+    /// Do not store; return [`CONTENT_REJECTED_ERROR`]
+    Reject { reason: String },
+}
+
+/// Moderation hook invoked on message PUT
+pub trait ContentFilter: Send + Sync {
+    fn check(&self, content: &str) -> FilterDecision;
+}
+
+/// Filter shared through `AppState`
+pub type SharedContentFilter = Arc<dyn ContentFilter>;
+
+/// Default filter: allows everything
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopFilter;
+
+impl ContentFilter for NoopFilter {
+    fn check(&self, _content: &str) -> FilterDecision {
+        FilterDecision::Allow
+    }
+}
+
+/// Whole-word, case-insensitive wordlist filter
+#[derive(Debug, Clone, Default)]
+pub struct WordlistFilter {
+    reject: HashSet<String>,
+    redact: HashSet<String>,
+    flag: HashSet<String>,
+}
+
+impl WordlistFilter {
+    /// Parse a wordlist (see module docs for the format)
+    pub fn parse(wordlist: &str) -> Self {
+        let mut filter = Self::default();
+        for line in wordlist.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (set, term) = match line.split_once(':') {
+                Some(("reject", term)) => (&mut filter.reject, term),
+                Some(("redact", term)) => (&mut filter.redact, term),
+                Some(("flag", term)) => (&mut filter.flag, term),
+                _ => (&mut filter.redact, line),
+            };
+            let term = term.trim().to_lowercase();
+            if !term.is_empty() {
+                set.insert(term);
+            }
+        }
+        filter
+    }
+
+    /// Load a wordlist file
+    pub fn from_file(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+        Ok(Self::parse(&std::fs::read_to_string(path)?))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.reject.is_empty() && self.redact.is_empty() && self.flag.is_empty()
+    }
+}
+
+impl ContentFilter for WordlistFilter {
+    fn check(&self, content: &str) -> FilterDecision {
+        let mut redact_ranges: Vec<(usize, usize)> = Vec::new();
+        let mut flagged: Option<String> = None;
+
+        for (start, word) in words(content) {
+            let lower = word.to_lowercase();
+            if self.reject.contains(&lower) {
+                return FilterDecision::Reject { reason: format!("blocked term '{}'", lower) };
+            }
+            if self.redact.contains(&lower) {
+                redact_ranges.push((start, start + word.len()));
+            } else if flagged.is_none() && self.flag.contains(&lower) {
+                flagged = Some(format!("flagged term '{}'", lower));
+            }
+        }
+
+        if !redact_ranges.is_empty() {
+            let mut redacted = String::with_capacity(content.len());
+            let mut last = 0;
+            for (start, end) in redact_ranges {
+                redacted.push_str(&content[last..start]);
+                redacted.push_str(&"*".repeat(content[start..end].chars().count()));
+                last = end;
+            }
+            redacted.push_str(&content[last..]);
+            return FilterDecision::Redact { content: redacted };
+        }
+
+        match flagged {
+            Some(reason) => FilterDecision::Flag { reason },
+            None => FilterDecision::Allow,
+        }
+    }
+}
+
+/// Words of `text` with their byte offsets
+fn words(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.split(|c: char| !(c.is_alphanumeric() || c == '\''))
+        .filter(|word| !word.is_empty())
+        .map(move |word| (word.as_ptr() as usize - text.as_ptr() as usize, word))
+}
+
+/// Build the configured filter from `CONTENT_FILTER_WORDLIST`
+pub fn content_filter_from_env() -> SharedContentFilter {
+    let Ok(path) = std::env::var("CONTENT_FILTER_WORDLIST") else {
+        return Arc::new(NoopFilter);
+    };
+
+    match WordlistFilter::from_file(&path) {
+        Ok(filter) => {
+            tracing::info!("Content filter loaded from {}", path);
+            Arc::new(filter)
+        }
+        Err(e) => {
+            tracing::error!("Failed to load content filter wordlist {}: {}", path, e);
+            Arc::new(NoopFilter)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WORDLIST: &str = "# test list\nreject:forbidden\ndarn\nredact:heck\nflag:crypto\n\n";
+
+    #[test]
+    fn test_wordlist_redacts_terms() {
+        let filter = WordlistFilter::parse(WORDLIST);
+        assert_eq!(
+            filter.check("Darn it, what the heck"),
+            FilterDecision::Redact { content: "**** it, what the ****".to_string() }
+        );
+        // Whole words only
+        assert_eq!(filter.check("darned hecklers"), FilterDecision::Allow);
+    }
+
+    #[test]
+    fn test_wordlist_rejects_terms() {
+        let filter = WordlistFilter::parse(WORDLIST);
+        assert!(matches!(
+            filter.check("this is FORBIDDEN, darn"),
+            FilterDecision::Reject { .. }
+        ));
+    }
+
+    #[test]
+    fn test_wordlist_flags_terms() {
+        let filter = WordlistFilter::parse(WORDLIST);
+        assert_eq!(
+            filter.check("buy crypto now"),
+            FilterDecision::Flag { reason: "flagged term 'crypto'".to_string() }
+        );
+        assert_eq!(filter.check("hello"), FilterDecision::Allow);
+    }
+
+    #[test]
+    fn test_noop_filter() {
+        assert_eq!(NoopFilter.check("forbidden"), FilterDecision::Allow);
+    }
+}
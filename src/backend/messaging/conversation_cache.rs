@@ -0,0 +1,270 @@
+//! Conversation Cache
+//!
+//! Size-bounded LRU cache of conversation metadata and participant lists,
+//! so hot conversations don't hit the database for every participant check.
+//!
+//! # Invalidation
+//!
+//! Participant lists guard who may read and post in a conversation, so a
+//! stale list must never outlive a membership change:
+//!
+//! - Membership changes go through [`ConversationCache::add_participant`] and
+//!   [`ConversationCache::remove_participant`], which drop the entry once the
+//!   write has committed
+//! - Every invalidation bumps an epoch; a load that started before it is not
+//!   stored, so a lookup racing a removal can't put the old list back
+//! - Only conversations that exist are cached, so creating one never has a
+//!   stale "no participants" entry to replace
+//!
+//! # Configuration
+//!
+//! - `CONVERSATION_CACHE_SIZE` - conversations kept (default 1024, 0 disables)
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+
+use crate::backend::messaging::db;
+
+/// Default number of cached conversations
+pub const DEFAULT_CAPACITY: usize = 1024;
+
+/// Cached facts about a conversation
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConversationInfo {
+    pub created_at: DateTime<Utc>,
+    pub participants: Vec<Uuid>,
+}
+
+impl ConversationInfo {
+    pub fn has_participant(&self, user_id: Uuid) -> bool {
+        self.participants.contains(&user_id)
+    }
+}
+
+struct CacheInner {
+    capacity: usize,
+    /// Conversation -> (last use, info)
+    entries: HashMap<Uuid, (u64, Arc<ConversationInfo>)>,
+    /// Use counter for LRU ordering
+    clock: u64,
+    /// Bumped on every invalidation
+    epoch: u64,
+}
+
+/// LRU cache of conversation metadata and participants
+#[derive(Clone)]
+pub struct ConversationCache {
+    inner: Arc<Mutex<CacheInner>>,
+}
+
+impl Default for ConversationCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl ConversationCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(CacheInner {
+                capacity,
+                entries: HashMap::new(),
+                clock: 0,
+                epoch: 0,
+            })),
+        }
+    }
+
+    /// Read the size from `CONVERSATION_CACHE_SIZE`
+    pub fn from_env() -> Self {
+        let capacity = std::env::var("CONVERSATION_CACHE_SIZE")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_CAPACITY);
+        Self::new(capacity)
+    }
+
+    /// Metadata and participants of a conversation; `None` if it doesn't exist
+    pub async fn get(&self, pool: &PgPool, conversation_id: Uuid) -> Result<Option<Arc<ConversationInfo>>, sqlx::Error> {
+        if let Some(info) = self.cached(conversation_id) {
+            return Ok(Some(info));
+        }
+
+        let epoch = self.inner.lock().unwrap().epoch;
+        let Some(info) = load(pool, conversation_id).await? else {
+            return Ok(None);
+        };
+        let info = Arc::new(info);
+        self.store(conversation_id, info.clone(), epoch);
+        Ok(Some(info))
+    }
+
+    /// Whether a user is a participant in a conversation
+    pub async fn is_participant(&self, pool: &PgPool, user_id: Uuid, conversation_id: Uuid) -> Result<bool, sqlx::Error> {
+        Ok(self
+            .get(pool, conversation_id)
+            .await?
+            .is_some_and(|info| info.has_participant(user_id)))
+    }
+
+    /// Add a user to a conversation and drop its cached entry
+    pub async fn add_participant(&self, pool: &PgPool, conversation_id: Uuid, user_id: Uuid) -> Result<(), sqlx::Error> {
+        let result = db::add_conversation_participant(pool, conversation_id, user_id).await;
+        self.invalidate(conversation_id);
+        result
+    }
+
+    /// Remove a user from a conversation and drop its cached entry
+    pub async fn remove_participant(&self, pool: &PgPool, conversation_id: Uuid, user_id: Uuid) -> Result<bool, sqlx::Error> {
+        let result = db::remove_conversation_participant(pool, conversation_id, user_id).await;
+        self.invalidate(conversation_id);
+        result
+    }
+
+    /// Seed an entry as if it had been loaded from the database
+    #[cfg(test)]
+    pub(crate) fn insert(&self, conversation_id: Uuid, info: ConversationInfo) {
+        let epoch = self.inner.lock().unwrap().epoch;
+        self.store(conversation_id, Arc::new(info), epoch);
+    }
+
+    /// Drop a conversation's entry after changing it outside this cache
+    pub fn invalidate(&self, conversation_id: Uuid) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.epoch += 1;
+        inner.entries.remove(&conversation_id);
+    }
+
+    /// Number of cached conversations
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn cached(&self, conversation_id: Uuid) -> Option<Arc<ConversationInfo>> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.clock += 1;
+        let clock = inner.clock;
+        let (last_used, info) = inner.entries.get_mut(&conversation_id)?;
+        *last_used = clock;
+        Some(info.clone())
+    }
+
+    /// Store a loaded entry unless something was invalidated since `epoch`
+    fn store(&self, conversation_id: Uuid, info: Arc<ConversationInfo>, epoch: u64) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.epoch != epoch || inner.capacity == 0 {
+            return;
+        }
+        if inner.entries.len() >= inner.capacity && !inner.entries.contains_key(&conversation_id) {
+            if let Some(oldest) = inner.entries.iter().min_by_key(|(_, (t, _))| *t).map(|(k, _)| *k) {
+                inner.entries.remove(&oldest);
+            }
+        }
+        inner.clock += 1;
+        let clock = inner.clock;
+        inner.entries.insert(conversation_id, (clock, info));
+    }
+}
+
+async fn load(pool: &PgPool, conversation_id: Uuid) -> Result<Option<ConversationInfo>, sqlx::Error> {
+    let Some(row) = sqlx::query("SELECT created_at FROM conversations WHERE id = $1")
+        .bind(conversation_id)
+        .fetch_optional(pool)
+        .await?
+    else {
+        return Ok(None);
+    };
+
+    let participants = sqlx::query_scalar("SELECT user_id FROM conversation_participants WHERE conversation_id = $1")
+        .bind(conversation_id)
+        .fetch_all(pool)
+        .await?;
+
+    Ok(Some(ConversationInfo {
+        created_at: row.get("created_at"),
+        participants,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::test_db::TestDatabase;
+
+    fn info(participants: Vec<Uuid>) -> Arc<ConversationInfo> {
+        Arc::new(ConversationInfo { created_at: Utc::now(), participants })
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let cache = ConversationCache::new(2);
+        let (a, b, c) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        cache.store(a, info(vec![]), 0);
+        cache.store(b, info(vec![]), 0);
+
+        // Touching `a` leaves `b` as the oldest
+        assert!(cache.cached(a).is_some());
+        cache.store(c, info(vec![]), 0);
+        assert_eq!(cache.len(), 2);
+        assert!(cache.cached(a).is_some());
+        assert!(cache.cached(b).is_none());
+        assert!(cache.cached(c).is_some());
+    }
+
+    #[test]
+    fn test_load_racing_an_invalidation_is_not_stored() {
+        let cache = ConversationCache::new(4);
+        let conversation_id = Uuid::new_v4();
+        let epoch = cache.inner.lock().unwrap().epoch;
+
+        // Membership changes while the old list is being read
+        cache.invalidate(conversation_id);
+        cache.store(conversation_id, info(vec![Uuid::new_v4()]), epoch);
+        assert!(cache.cached(conversation_id).is_none());
+    }
+
+    #[test]
+    fn test_zero_capacity_disables_caching() {
+        let cache = ConversationCache::new(0);
+        cache.store(Uuid::new_v4(), info(vec![]), 0);
+        assert!(cache.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_membership_change_invalidates_participants() {
+        let db = TestDatabase::new().await;
+        let pool = db.pool().clone();
+        let (alice, bob, carol) = (db.user().await, db.user().await, db.user().await);
+        let conversation_id = db::create_conversation(&pool, alice.id, bob.id).await.unwrap();
+        let cache = ConversationCache::new(16);
+
+        assert!(cache.is_participant(&pool, alice.id, conversation_id).await.unwrap());
+        assert!(!cache.is_participant(&pool, carol.id, conversation_id).await.unwrap());
+        assert_eq!(cache.len(), 1);
+
+        // Writes behind the cache's back aren't seen until invalidated
+        db::add_conversation_participant(&pool, conversation_id, carol.id).await.unwrap();
+        assert!(!cache.is_participant(&pool, carol.id, conversation_id).await.unwrap());
+        cache.invalidate(conversation_id);
+        assert!(cache.is_participant(&pool, carol.id, conversation_id).await.unwrap());
+
+        // Removal through the cache takes effect immediately
+        assert!(cache.remove_participant(&pool, conversation_id, carol.id).await.unwrap());
+        assert!(!cache.is_participant(&pool, carol.id, conversation_id).await.unwrap());
+        cache.add_participant(&pool, conversation_id, carol.id).await.unwrap();
+        let info = cache.get(&pool, conversation_id).await.unwrap().unwrap();
+        assert_eq!(info.participants.len(), 3);
+
+        // Missing conversations aren't cached
+        assert!(!cache.is_participant(&pool, alice.id, Uuid::new_v4()).await.unwrap());
+        assert_eq!(cache.len(), 1);
+    }
+}
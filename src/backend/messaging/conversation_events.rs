@@ -0,0 +1,122 @@
+//! Conversation Events
+//!
+//! Tells participants when a conversation appears, changes or goes away for
+//! them, so clients can update their sidebar live instead of polling
+//! `GET /api/conversations`:
+//!
+//! - **Created** - a new DM after a friend request is accepted, a new group,
+//!   or an existing group someone was just added to
+//! - **Updated** - the conversation's members changed
+//! - **Deleted** - the user left the conversation
+//!
+//! Each event is addressed to one user and carries the conversation as that
+//! user sees it. `GET /sync/conversations` streams the caller's own events
+//! and nobody else's.
+
+use std::convert::Infallible;
+
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::sse::{Event, KeepAlive, Sse},
+};
+use futures_util::stream::{self, Stream, StreamExt};
+use sqlx::PgPool;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use crate::backend::server::shutdown::Shutdown;
+use crate::shared::messaging::Conversation;
+use crate::shared::{ConversationEvent, RealtimeEvent};
+use super::db;
+use super::handlers::extract_user_id;
+
+/// Conversation changes, each addressed to one user
+#[derive(Clone)]
+pub struct ConversationEvents {
+    updates: broadcast::Sender<(Uuid, ConversationEvent)>,
+}
+
+impl Default for ConversationEvents {
+    fn default() -> Self {
+        Self { updates: broadcast::channel(256).0 }
+    }
+}
+
+impl ConversationEvents {
+    /// Receive every user's events; filter by the addressee
+    pub fn subscribe(&self) -> broadcast::Receiver<(Uuid, ConversationEvent)> {
+        self.updates.subscribe()
+    }
+
+    /// Tell `user_ids` about a conversation they're now in
+    pub async fn created(&self, pool: &PgPool, conversation_id: Uuid, user_ids: &[Uuid]) -> Result<(), sqlx::Error> {
+        self.announce(pool, conversation_id, user_ids, |conversation| ConversationEvent::Created { conversation }).await
+    }
+
+    /// Tell `user_ids` a conversation they're in has changed
+    pub async fn updated(&self, pool: &PgPool, conversation_id: Uuid, user_ids: &[Uuid]) -> Result<(), sqlx::Error> {
+        self.announce(pool, conversation_id, user_ids, |conversation| ConversationEvent::Updated { conversation }).await
+    }
+
+    /// Tell a user they're no longer in a conversation
+    pub fn deleted(&self, user_id: Uuid, conversation_id: Uuid) {
+        let _ = self.updates.send((user_id, ConversationEvent::Deleted { conversation_id }));
+    }
+
+    /// Send each user the conversation as they see it
+    async fn announce(
+        &self,
+        pool: &PgPool,
+        conversation_id: Uuid,
+        user_ids: &[Uuid],
+        change: fn(Conversation) -> ConversationEvent,
+    ) -> Result<(), sqlx::Error> {
+        for &user_id in user_ids {
+            let conversation = db::get_conversations_for_user(pool, user_id)
+                .await?
+                .into_iter()
+                .find(|c| c.id == conversation_id);
+            if let Some(conversation) = conversation {
+                let _ = self.updates.send((user_id, change(conversation)));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Stream the caller's conversation changes
+/// GET /sync/conversations
+///
+/// Each change is a `conversation` event whose data is a `RealtimeEvent`
+/// with a [`ConversationEvent`] payload. There's no snapshot; clients load
+/// `GET /api/conversations` first.
+pub async fn handle_conversation_subscription(
+    State(db_pool): State<Option<PgPool>>,
+    State(events): State<ConversationEvents>,
+    State(shutdown): State<Shutdown>,
+    headers: HeaderMap,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, StatusCode> {
+    db_pool.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+    let user_id = extract_user_id(&headers)?;
+
+    let stream = stream::unfold(events.subscribe(), move |mut rx| async move {
+        loop {
+            match rx.recv().await {
+                Ok((recipient, change)) if recipient == user_id => return Some((change, rx)),
+                Ok(_) => continue,
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!("Conversation subscriber {} lagged, skipped {} events", user_id, skipped);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    })
+    .map(|change| {
+        let event = RealtimeEvent::conversation(change);
+        Ok(Event::default().event("conversation").data(serde_json::to_string(&event).unwrap_or_default()))
+    });
+
+    Ok(Sse::new(shutdown.close_sse(stream)).keep_alive(KeepAlive::default()))
+}
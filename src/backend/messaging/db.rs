@@ -6,7 +6,7 @@ use sqlx::{PgPool, Row};
 use uuid::Uuid;
 use chrono::Utc;
 use crate::shared::messaging::{
-    Contact, FriendRequest, FriendRequestStatus,
+    avatar_url, Contact, FriendRequest, FriendRequestStatus,
 };
 
 /// Create a new friend request
@@ -115,19 +115,22 @@ pub async fn get_friend_request_by_id(
 }
 
 /// Accept a friend request
+///
+/// Returns false if the request was already accepted, so a retried accept
+/// can tell it has nothing left to do.
 pub async fn accept_friend_request(
     pool: &PgPool,
     request_id: Uuid,
     user_id: Uuid,
-) -> Result<(), sqlx::Error> {
+) -> Result<bool, sqlx::Error> {
     let now = Utc::now();
 
     // Update the request status
-    sqlx::query(
+    let result = sqlx::query(
         r#"
         UPDATE friend_requests
         SET status = 'accepted', responded_at = $1
-        WHERE id = $2 AND to_user_id = $3
+        WHERE id = $2 AND to_user_id = $3 AND status <> 'accepted'
         "#
     )
     .bind(now)
@@ -136,7 +139,7 @@ pub async fn accept_friend_request(
     .execute(pool)
     .await?;
 
-    Ok(())
+    Ok(result.rows_affected() > 0)
 }
 
 /// Reject a friend request
@@ -164,6 +167,9 @@ pub async fn reject_friend_request(
 }
 
 /// Create a contact entry (called when friend request is accepted)
+///
+/// If the contact already exists (e.g. a retried accept), the existing entry
+/// is kept and returned.
 pub async fn create_contact(
     pool: &PgPool,
     user_id: Uuid,
@@ -171,33 +177,36 @@ pub async fn create_contact(
     username: &str,
     email: &str,
 ) -> Result<Contact, sqlx::Error> {
-    let id = Uuid::new_v4();
     let now = Utc::now();
 
-    sqlx::query(
+    // DO UPDATE rather than DO NOTHING so RETURNING yields the existing row
+    let row = sqlx::query(
         r#"
         INSERT INTO contacts (id, user_id, contact_user_id, username, email, created_at, last_seen, is_online)
         VALUES ($1, $2, $3, $4, $5, $6, $7, false)
+        ON CONFLICT (user_id, contact_user_id)
+        DO UPDATE SET username = EXCLUDED.username, email = EXCLUDED.email
+        RETURNING id, (SELECT avatar_updated_at FROM users WHERE id = $3) AS avatar_updated_at
         "#
     )
-    .bind(id)
+    .bind(Uuid::new_v4())
     .bind(user_id)
     .bind(contact_user_id)
     .bind(username)
     .bind(email)
     .bind(now)
     .bind(now)
-    .execute(pool)
+    .fetch_one(pool)
     .await?;
 
     Ok(Contact {
-        id,
+        id: row.get("id"),
         user_id,
         contact_user_id,
         username: username.to_string(),
         email: email.to_string(),
         display_name: None,
-        avatar_url: None,
+        avatar_url: contact_avatar_url(contact_user_id, &row, None),
         last_seen: now,
         is_online: false,
         created_at: now,
@@ -211,30 +220,44 @@ pub async fn get_contacts_for_user(
 ) -> Result<Vec<Contact>, sqlx::Error> {
     let rows = sqlx::query(
         r#"
-        SELECT id, user_id, contact_user_id, username, email, display_name, avatar_url, last_seen, is_online, created_at
-        FROM contacts
-        WHERE user_id = $1
-        ORDER BY username ASC
+        SELECT c.id, c.user_id, c.contact_user_id, c.username, c.email, c.display_name, c.avatar_url,
+               u.avatar_updated_at, c.last_seen, c.is_online, c.created_at
+        FROM contacts c
+        LEFT JOIN users u ON u.id = c.contact_user_id
+        WHERE c.user_id = $1
+        ORDER BY c.username ASC
         "#
     )
     .bind(user_id)
     .fetch_all(pool)
     .await?;
 
-    Ok(rows.into_iter().map(|row| Contact {
-        id: row.get("id"),
-        user_id: row.get("user_id"),
-        contact_user_id: row.get("contact_user_id"),
-        username: row.get("username"),
-        email: row.get("email"),
-        display_name: row.get("display_name"),
-        avatar_url: row.get("avatar_url"),
-        last_seen: row.get("last_seen"),
-        is_online: row.get("is_online"),
-        created_at: row.get("created_at"),
+    Ok(rows.into_iter().map(|row| {
+        let contact_user_id = row.get("contact_user_id");
+        Contact {
+            id: row.get("id"),
+            user_id: row.get("user_id"),
+            contact_user_id,
+            username: row.get("username"),
+            email: row.get("email"),
+            display_name: row.get("display_name"),
+            avatar_url: contact_avatar_url(contact_user_id, &row, row.get("avatar_url")),
+            last_seen: row.get("last_seen"),
+            is_online: row.get("is_online"),
+            created_at: row.get("created_at"),
+        }
     }).collect())
 }
 
+/// Avatar URL for a contact row with an `avatar_updated_at` column
+///
+/// An uploaded avatar wins over the URL stored on the contact entry.
+fn contact_avatar_url(contact_user_id: Uuid, row: &sqlx::postgres::PgRow, stored: Option<String>) -> Option<String> {
+    row.get::<Option<chrono::DateTime<Utc>>, _>("avatar_updated_at")
+        .map(|updated_at| avatar_url(contact_user_id, updated_at.timestamp_millis()))
+        .or(stored)
+}
+
 /// Delete a contact
 pub async fn delete_contact(
     pool: &PgPool,
@@ -260,38 +283,133 @@ pub async fn create_conversation(
     pool: &PgPool,
     user1_id: Uuid,
     user2_id: Uuid,
+) -> Result<Uuid, sqlx::Error> {
+    create_group_conversation(pool, user1_id, None, &[user1_id, user2_id]).await
+}
+
+/// Create a conversation with any number of participants
+///
+/// `participants` should include the creator. Unnamed two-person
+/// conversations are stored as direct messages.
+pub async fn create_group_conversation(
+    pool: &PgPool,
+    created_by: Uuid,
+    name: Option<&str>,
+    participants: &[Uuid],
 ) -> Result<Uuid, sqlx::Error> {
     let conversation_id = Uuid::new_v4();
     let now = Utc::now();
+    let is_direct_message = name.is_none() && participants.len() == 2;
+
+    let mut tx = pool.begin().await?;
 
-    // Create the conversation
     sqlx::query(
         r#"
-        INSERT INTO conversations (id, created_at, updated_at)
-        VALUES ($1, $2, $3)
+        INSERT INTO conversations (id, created_by, name, is_direct_message, created_at, updated_at)
+        VALUES ($1, $2, $3, $4, $5, $5)
         "#
     )
     .bind(conversation_id)
+    .bind(created_by)
+    .bind(name)
+    .bind(is_direct_message)
     .bind(now)
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO conversation_participants (conversation_id, user_id, joined_at)
+        SELECT $1, user_id, $3 FROM UNNEST($2::uuid[]) AS p(user_id)
+        ON CONFLICT DO NOTHING
+        "#
+    )
+    .bind(conversation_id)
+    .bind(participants)
     .bind(now)
-    .execute(pool)
+    .execute(&mut *tx)
     .await?;
 
-    // Add both participants
+    tx.commit().await?;
+
+    Ok(conversation_id)
+}
+
+/// Add a user to a conversation; does nothing if they're already in it
+///
+/// Callers holding a [`ConversationCache`](super::conversation_cache::ConversationCache)
+/// should use its `add_participant` so cached participant lists stay correct.
+pub async fn add_conversation_participant(
+    pool: &PgPool,
+    conversation_id: Uuid,
+    user_id: Uuid,
+) -> Result<(), sqlx::Error> {
     sqlx::query(
         r#"
         INSERT INTO conversation_participants (conversation_id, user_id, joined_at)
-        VALUES ($1, $2, $3), ($1, $4, $3)
+        VALUES ($1, $2, $3)
+        ON CONFLICT DO NOTHING
+        "#
+    )
+    .bind(conversation_id)
+    .bind(user_id)
+    .bind(Utc::now())
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Remove a user from a conversation
+///
+/// Returns `false` if they weren't in it. Callers holding a
+/// [`ConversationCache`](super::conversation_cache::ConversationCache)
+/// should use its `remove_participant` instead.
+pub async fn remove_conversation_participant(
+    pool: &PgPool,
+    conversation_id: Uuid,
+    user_id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM conversation_participants WHERE conversation_id = $1 AND user_id = $2
         "#
     )
     .bind(conversation_id)
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Find the two-person conversation between two users, if any
+///
+/// Named groups that happen to have two members don't count.
+pub async fn find_direct_conversation(
+    pool: &PgPool,
+    user1_id: Uuid,
+    user2_id: Uuid,
+) -> Result<Option<Uuid>, sqlx::Error> {
+    let row = sqlx::query(
+        r#"
+        SELECT cp.conversation_id
+        FROM conversation_participants cp
+        JOIN conversations c ON c.id = cp.conversation_id
+        WHERE cp.user_id IN ($1, $2) AND c.name IS NULL
+        GROUP BY cp.conversation_id
+        HAVING COUNT(DISTINCT cp.user_id) = 2
+           AND (SELECT COUNT(*) FROM conversation_participants all_cp
+                WHERE all_cp.conversation_id = cp.conversation_id) = 2
+        LIMIT 1
+        "#
+    )
     .bind(user1_id)
-    .bind(now)
     .bind(user2_id)
-    .execute(pool)
+    .fetch_optional(pool)
     .await?;
 
-    Ok(conversation_id)
+    Ok(row.map(|r| r.get("conversation_id")))
 }
 
 /// Get conversations for a user
@@ -301,11 +419,19 @@ pub async fn get_conversations_for_user(
 ) -> Result<Vec<crate::shared::messaging::Conversation>, sqlx::Error> {
     let rows = sqlx::query(
         r#"
-        SELECT c.id, c.created_at, c.updated_at
+        SELECT c.id, c.name, c.created_at, c.updated_at,
+               COALESCE(cs.manually_unread, false) AS manually_unread,
+               cs.theme_color,
+               COALESCE(cs.pinned, false) AS pinned,
+               COALESCE(cs.pin_order, 0) AS pin_order
         FROM conversations c
         INNER JOIN conversation_participants cp ON c.id = cp.conversation_id
+        LEFT JOIN conversation_settings cs
+            ON cs.conversation_id = c.id AND cs.user_id = cp.user_id
         WHERE cp.user_id = $1
-        ORDER BY c.updated_at DESC
+        ORDER BY pinned DESC,
+                 CASE WHEN cs.pinned THEN cs.pin_order END,
+                 c.updated_at DESC
         "#
     )
     .bind(user_id)
@@ -315,50 +441,56 @@ pub async fn get_conversations_for_user(
     let mut conversations = Vec::new();
     for row in rows {
         let conv_id: Uuid = row.get("id");
+        let name: Option<String> = row.get("name");
 
-        // Get participants
+        // Participants with the name this user knows them by, preferring
+        // their contact entry over the account username
         let participant_rows = sqlx::query(
             r#"
-            SELECT user_id FROM conversation_participants WHERE conversation_id = $1
+            SELECT cp.user_id, COALESCE(ct.username, u.username) AS username
+            FROM conversation_participants cp
+            LEFT JOIN users u ON u.id = cp.user_id
+            LEFT JOIN contacts ct ON ct.user_id = $2 AND ct.contact_user_id = cp.user_id
+            WHERE cp.conversation_id = $1
+            ORDER BY cp.joined_at, cp.user_id
             "#
         )
         .bind(conv_id)
+        .bind(user_id)
         .fetch_all(pool)
         .await?;
 
         let participants: Vec<Uuid> = participant_rows.iter().map(|r| r.get("user_id")).collect();
+        let member_usernames: Vec<String> = participant_rows
+            .iter()
+            .filter(|r| r.get::<Uuid, _>("user_id") != user_id)
+            .filter_map(|r| r.get::<Option<String>, _>("username"))
+            .collect();
 
-        // Fetch other user's username (assuming 2-person conversations)
-        let other_username = if participants.len() == 2 {
-            let other_user_id = participants.iter().find(|&&p| p != user_id).unwrap();
-            // Get the username from contacts table
-            sqlx::query(
-                r#"
-                SELECT username FROM contacts WHERE user_id = $1 AND contact_user_id = $2
-                "#
-            )
-            .bind(user_id)
-            .bind(other_user_id)
-            .fetch_optional(pool)
-            .await?
-            .map(|row| row.get::<String, _>("username"))
+        // Only direct chats are titled after "the other" participant
+        let other_username = if name.is_none() && participants.len() == 2 {
+            member_usernames.first().cloned()
         } else {
             None
         };
 
-        // Convert timestamps to RFC3339 strings
-        let updated_at_dt: chrono::DateTime<chrono::Utc> = row.get("updated_at");
-        let created_at_dt: chrono::DateTime<chrono::Utc> = row.get("created_at");
-
         conversations.push(crate::shared::messaging::Conversation {
             id: conv_id,
             participants,
+            name,
             other_username,
+            member_usernames,
             last_message: None,
             last_message_preview: String::new(),
-            last_message_time: Some(updated_at_dt.to_rfc3339()),
+            last_message_time: Some(row.get("updated_at")),
             unread_count: 0,
-            created_at: created_at_dt.to_rfc3339(),
+            manually_unread: row.get("manually_unread"),
+            theme_color: row
+                .get::<Option<String>, _>("theme_color")
+                .and_then(|hex| crate::shared::messaging::ThemeColor::from_hex(&hex)),
+            pinned: row.get("pinned"),
+            pin_order: row.get("pin_order"),
+            created_at: row.get("created_at"),
         });
     }
 
@@ -366,18 +498,25 @@ pub async fn get_conversations_for_user(
 }
 
 /// Store a message in the database
+///
+/// Assigns the message the conversation's next sequence number and returns
+/// it. Bumping the counter and inserting happen in one statement, so
+/// concurrent sends to a conversation get distinct, increasing sequences.
 pub async fn store_message(
     pool: &PgPool,
     message: &crate::shared::messaging::ChatMessage,
-) -> Result<(), sqlx::Error> {
-    // Convert RFC3339 string to chrono for DB
-    let created_at_dt = chrono::DateTime::parse_from_rfc3339(&message.timestamp)
-        .map(|dt| dt.with_timezone(&chrono::Utc))
-        .unwrap_or_else(|_| chrono::Utc::now());
-    sqlx::query(
+) -> Result<i64, sqlx::Error> {
+    let row = sqlx::query(
         r#"
-        INSERT INTO chat_messages (id, conversation_id, sender_id, content, message_type, is_read, is_delivered, crdt_timestamp, braid_version, created_at)
-        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        WITH seq AS (
+            UPDATE conversations
+            SET last_sequence = last_sequence + 1, updated_at = $10
+            WHERE id = $2
+            RETURNING last_sequence
+        )
+        INSERT INTO chat_messages (id, conversation_id, sender_id, content, message_type, is_read, is_delivered, crdt_timestamp, braid_version, created_at, moderation_flag, braid_parents, sequence)
+        SELECT $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, seq.last_sequence FROM seq
+        RETURNING sequence
         "#
     )
     .bind(message.id)
@@ -389,24 +528,160 @@ pub async fn store_message(
     .bind(message.is_delivered)
     .bind(message.crdt_timestamp as i64)
     .bind(&message.braid_version)
-    .bind(created_at_dt)
-    .execute(pool)
+    .bind(message.timestamp)
+    .bind(&message.moderation_flag)
+    .bind(&message.braid_parents)
+    .fetch_one(pool)
     .await?;
 
-    // Update conversation updated_at
+    Ok(row.get("sequence"))
+}
+
+/// Attach a link preview to a stored message
+pub async fn set_message_link_preview(
+    pool: &PgPool,
+    message_id: Uuid,
+    preview: &crate::shared::messaging::LinkPreview,
+) -> Result<(), sqlx::Error> {
+    let json = serde_json::to_string(preview).map_err(|e| sqlx::Error::Encode(Box::new(e)))?;
     sqlx::query(
         r#"
-        UPDATE conversations SET updated_at = $1 WHERE id = $2
+        UPDATE chat_messages SET link_preview = $1, updated_at = NOW() WHERE id = $2
         "#
     )
-    .bind(created_at_dt)
-    .bind(message.conversation_id)
+    .bind(json)
+    .bind(message_id)
     .execute(pool)
     .await?;
 
     Ok(())
 }
 
+/// Get one message in a conversation
+pub async fn get_conversation_message(
+    pool: &PgPool,
+    conversation_id: Uuid,
+    message_id: Uuid,
+) -> Result<Option<crate::shared::messaging::ChatMessage>, sqlx::Error> {
+    let row = sqlx::query(
+        r#"
+        SELECT id, conversation_id, sender_id, content, message_type, is_read, is_delivered, crdt_timestamp, braid_version, created_at, link_preview, moderation_flag, edit_version, deleted_at, sequence
+        FROM chat_messages
+        WHERE id = $1 AND conversation_id = $2
+        "#
+    )
+    .bind(message_id)
+    .bind(conversation_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.as_ref().map(chat_message_from_row))
+}
+
+/// Replace a message's content with an edit
+///
+/// Only applies if the message isn't deleted and its current version is
+/// still `parent_version`; returns `false` otherwise, so two concurrent
+/// edits of the same version can't both win. Clears the link preview,
+/// which belonged to the old content. The replaced version is kept in
+/// `message_edits` for the edit history.
+pub async fn edit_message(
+    pool: &PgPool,
+    message_id: Uuid,
+    parent_version: &str,
+    content: &str,
+    edit_version: &str,
+    moderation_flag: Option<&str>,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        r#"
+        WITH old AS (
+            SELECT id, content, created_at, COALESCE(edit_version, braid_version) AS version
+            FROM chat_messages
+            WHERE id = $1 AND deleted_at IS NULL AND COALESCE(edit_version, braid_version) = $2
+            FOR UPDATE
+        ), updated AS (
+            UPDATE chat_messages m
+            SET content = $3, edit_version = $4, moderation_flag = $5, link_preview = NULL, updated_at = NOW()
+            FROM old
+            WHERE m.id = old.id
+            RETURNING m.id
+        )
+        INSERT INTO message_edits (message_id, version, content, written_at)
+        SELECT old.id, old.version, old.content,
+               COALESCE((SELECT MAX(replaced_at) FROM message_edits e WHERE e.message_id = old.id), old.created_at)
+        FROM old JOIN updated ON updated.id = old.id
+        "#
+    )
+    .bind(message_id)
+    .bind(parent_version)
+    .bind(content)
+    .bind(edit_version)
+    .bind(moderation_flag)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Turn a message into a tombstone
+///
+/// The row is kept with its content cleared, and its edit history is
+/// dropped along with it. Returns `false` if it was already deleted.
+pub async fn delete_message(pool: &PgPool, message_id: Uuid) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let result = sqlx::query(
+        r#"
+        UPDATE chat_messages
+        SET content = '', link_preview = NULL, deleted_at = NOW(), updated_at = NOW()
+        WHERE id = $1 AND deleted_at IS NULL
+        "#
+    )
+    .bind(message_id)
+    .execute(&mut *tx)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Ok(false);
+    }
+
+    sqlx::query("DELETE FROM message_edits WHERE message_id = $1")
+        .bind(message_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    Ok(true)
+}
+
+/// Versions of a message replaced by edits, oldest first
+pub async fn get_edit_history(
+    pool: &PgPool,
+    message_id: Uuid,
+) -> Result<Vec<crate::shared::messaging::MessageEdit>, sqlx::Error> {
+    let rows = sqlx::query(
+        r#"
+        SELECT version, content, written_at, replaced_at
+        FROM message_edits
+        WHERE message_id = $1
+        ORDER BY id ASC
+        "#
+    )
+    .bind(message_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .iter()
+        .map(|row| crate::shared::messaging::MessageEdit {
+            version: row.get("version"),
+            content: row.get("content"),
+            written_at: row.get("written_at"),
+            replaced_at: row.get("replaced_at"),
+        })
+        .collect())
+}
+
 /// Get messages for a conversation
 pub async fn get_messages_for_conversation(
     pool: &PgPool,
@@ -416,10 +691,10 @@ pub async fn get_messages_for_conversation(
 ) -> Result<Vec<crate::shared::messaging::ChatMessage>, sqlx::Error> {
     let rows = sqlx::query(
         r#"
-        SELECT id, conversation_id, sender_id, content, message_type, is_read, is_delivered, crdt_timestamp, braid_version, created_at
+        SELECT id, conversation_id, sender_id, content, message_type, is_read, is_delivered, crdt_timestamp, braid_version, created_at, link_preview, moderation_flag, edit_version, deleted_at, sequence
         FROM chat_messages
         WHERE conversation_id = $1
-        ORDER BY created_at DESC
+        ORDER BY sequence DESC
         LIMIT $2 OFFSET $3
         "#
     )
@@ -429,24 +704,179 @@ pub async fn get_messages_for_conversation(
     .fetch_all(pool)
     .await?;
 
-    Ok(rows.into_iter().map(|row| {
-        let msg_type_str: String = row.get("message_type");
-        let created_at_dt: chrono::DateTime<chrono::Utc> = row.get("created_at");
-        crate::shared::messaging::ChatMessage {
-            id: row.get("id"),
-            conversation_id: row.get("conversation_id"),
-            sender_id: row.get("sender_id"),
-            content: row.get("content"),
-            message_type: crate::shared::messaging::MessageType::from_str(&msg_type_str),
-            timestamp: created_at_dt.to_rfc3339(),
-            is_read: row.get("is_read"),
-            is_delivered: row.get("is_delivered"),
-            crdt_timestamp: row.get::<i64, _>("crdt_timestamp") as u64,
-            braid_version: row.get("braid_version"),
-            braid_parents: vec![],
-            version_vector: crate::shared::messaging::message::VersionVector::default(),
+    Ok(rows.iter().map(chat_message_from_row).collect())
+}
+
+/// Version DAG of a conversation's messages
+///
+/// Each message version points at the parents it was sent with. Only a
+/// message's latest edit is kept, so it is shown as a child of the
+/// original version.
+pub async fn get_version_dag(
+    pool: &PgPool,
+    conversation_id: Uuid,
+) -> Result<crate::backend::chat::dag::VersionDag, sqlx::Error> {
+    let rows = sqlx::query(
+        r#"
+        SELECT braid_version, braid_parents, edit_version
+        FROM chat_messages
+        WHERE conversation_id = $1 AND braid_version IS NOT NULL
+        ORDER BY created_at ASC
+        "#
+    )
+    .bind(conversation_id)
+    .fetch_all(pool)
+    .await?;
+
+    let mut history = Vec::with_capacity(rows.len());
+    for row in rows {
+        let version: String = row.get("braid_version");
+        let parents: Option<Vec<String>> = row.get("braid_parents");
+        if let Some(edit_version) = row.get::<Option<String>, _>("edit_version") {
+            history.push((edit_version, vec![version.clone()]));
         }
-    }).collect())
+        history.push((version, parents.unwrap_or_default()));
+    }
+
+    Ok(crate::backend::chat::dag::VersionDag::from_history(history))
+}
+
+/// Get a page of a conversation's history before a message
+///
+/// Returns up to `limit` messages older than `before` (the newest messages
+/// if `None`), oldest first, and whether older ones remain. Ordering is by
+/// the server-assigned sequence, so messages with the same timestamp
+/// aren't skipped.
+/// Tombstones are included so clients can apply deletes. A `before` that
+/// isn't in the conversation matches nothing.
+pub async fn get_messages_before(
+    pool: &PgPool,
+    conversation_id: Uuid,
+    before: Option<Uuid>,
+    limit: i64,
+) -> Result<(Vec<crate::shared::messaging::ChatMessage>, bool), sqlx::Error> {
+    let rows = sqlx::query(
+        r#"
+        SELECT id, conversation_id, sender_id, content, message_type, is_read, is_delivered, crdt_timestamp, braid_version, created_at, link_preview, moderation_flag, edit_version, deleted_at, sequence
+        FROM chat_messages
+        WHERE conversation_id = $1
+          AND ($2::uuid IS NULL OR sequence < (SELECT sequence FROM chat_messages WHERE id = $2 AND conversation_id = $1))
+        ORDER BY sequence DESC
+        LIMIT $3
+        "#
+    )
+    .bind(conversation_id)
+    .bind(before)
+    .bind(limit + 1)
+    .fetch_all(pool)
+    .await?;
+
+    let has_more = rows.len() as i64 > limit;
+    let mut messages: Vec<_> = rows.iter().take(limit as usize).map(chat_message_from_row).collect();
+    messages.reverse();
+    Ok((messages, has_more))
+}
+
+/// Get a range of a conversation's messages with their senders' usernames
+///
+/// `from`/`to` are message IDs bounding the range (inclusive); `None`
+/// leaves that end open. A bound that isn't in the conversation matches
+/// nothing. Deleted messages are skipped. At most `limit` messages are
+/// returned, the newest of the range, oldest first.
+pub async fn get_message_range(
+    pool: &PgPool,
+    conversation_id: Uuid,
+    from: Option<Uuid>,
+    to: Option<Uuid>,
+    limit: i64,
+) -> Result<Vec<(String, crate::shared::messaging::ChatMessage)>, sqlx::Error> {
+    let rows = sqlx::query(
+        r#"
+        SELECT * FROM (
+            SELECT m.id, m.conversation_id, m.sender_id, m.content, m.message_type, m.is_read, m.is_delivered, m.crdt_timestamp, m.braid_version, m.created_at, m.link_preview, m.moderation_flag, m.edit_version, m.deleted_at, m.sequence,
+                   COALESCE(u.username, 'unknown') AS sender_username
+            FROM chat_messages m
+            LEFT JOIN users u ON u.id = m.sender_id
+            WHERE m.conversation_id = $1
+              AND m.deleted_at IS NULL
+              AND ($2::uuid IS NULL OR m.sequence >= (SELECT sequence FROM chat_messages WHERE id = $2 AND conversation_id = $1))
+              AND ($3::uuid IS NULL OR m.sequence <= (SELECT sequence FROM chat_messages WHERE id = $3 AND conversation_id = $1))
+            ORDER BY m.sequence DESC
+            LIMIT $4
+        ) newest
+        ORDER BY sequence ASC
+        "#
+    )
+    .bind(conversation_id)
+    .bind(from)
+    .bind(to)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.iter().map(|row| (row.get("sender_username"), chat_message_from_row(row))).collect())
+}
+
+/// Full-text search over messages in the user's conversations
+///
+/// `query` is parsed with `websearch_to_tsquery` (quoted phrases, `or`,
+/// `-word`), so any user input is safe. Results are best match first, then
+/// newest first.
+pub async fn search_messages(
+    pool: &PgPool,
+    user_id: Uuid,
+    query: &str,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<crate::shared::messaging::ChatMessage>, sqlx::Error> {
+    let rows = sqlx::query(
+        r#"
+        SELECT m.id, m.conversation_id, m.sender_id, m.content, m.message_type, m.is_read, m.is_delivered, m.crdt_timestamp, m.braid_version, m.created_at, m.link_preview, m.moderation_flag, m.edit_version, m.deleted_at, m.sequence
+        FROM chat_messages m
+        JOIN conversation_participants cp ON cp.conversation_id = m.conversation_id AND cp.user_id = $1
+        CROSS JOIN websearch_to_tsquery('english', $2) AS q
+        WHERE m.search_vector @@ q
+        ORDER BY ts_rank_cd(m.search_vector, q) DESC, m.created_at DESC
+        LIMIT $3 OFFSET $4
+        "#
+    )
+    .bind(user_id)
+    .bind(query)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.iter().map(chat_message_from_row).collect())
+}
+
+/// Build a `ChatMessage` from a `chat_messages` row
+///
+/// The row must have the columns selected by [`get_messages_for_conversation`].
+pub(crate) fn chat_message_from_row(row: &sqlx::postgres::PgRow) -> crate::shared::messaging::ChatMessage {
+    let msg_type_str: String = row.get("message_type");
+    crate::shared::messaging::ChatMessage {
+        id: row.get("id"),
+        conversation_id: row.get("conversation_id"),
+        sender_id: row.get("sender_id"),
+        content: row.get("content"),
+        message_type: crate::shared::messaging::MessageType::from_str(&msg_type_str),
+        timestamp: row.get("created_at"),
+        is_read: row.get("is_read"),
+        is_delivered: row.get("is_delivered"),
+        is_sent: true,
+        crdt_timestamp: row.get::<i64, _>("crdt_timestamp") as u64,
+        braid_version: row.get("braid_version"),
+        braid_parents: vec![],
+        version_vector: crate::shared::messaging::message::VersionVector::default(),
+        link_preview: row
+            .get::<Option<String>, _>("link_preview")
+            .and_then(|json| serde_json::from_str(&json).ok()),
+        moderation_flag: row.get("moderation_flag"),
+        edit_version: row.get("edit_version"),
+        is_deleted: row.get::<Option<chrono::DateTime<chrono::Utc>>, _>("deleted_at").is_some(),
+        sequence: row.get("sequence"),
+    }
 }
 
 /// Mark a message as read
@@ -466,6 +896,157 @@ pub async fn mark_message_read(
     Ok(())
 }
 
+/// Mark a message in a conversation as read
+///
+/// Returns `false` if the message doesn't exist in that conversation.
+pub async fn mark_conversation_message_read(
+    pool: &PgPool,
+    conversation_id: Uuid,
+    message_id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        r#"
+        UPDATE chat_messages SET is_read = true WHERE id = $1 AND conversation_id = $2
+        "#
+    )
+    .bind(message_id)
+    .bind(conversation_id)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Mark a message in a conversation as delivered
+///
+/// Returns `false` if the message doesn't exist in that conversation.
+pub async fn mark_conversation_message_delivered(
+    pool: &PgPool,
+    conversation_id: Uuid,
+    message_id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        r#"
+        UPDATE chat_messages SET is_delivered = true WHERE id = $1 AND conversation_id = $2
+        "#
+    )
+    .bind(message_id)
+    .bind(conversation_id)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Set or clear a user's manual "unread" marker on a conversation
+pub async fn set_conversation_manually_unread(
+    pool: &PgPool,
+    user_id: Uuid,
+    conversation_id: Uuid,
+    unread: bool,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO conversation_settings (conversation_id, user_id, manually_unread, updated_at)
+        VALUES ($1, $2, $3, NOW())
+        ON CONFLICT (conversation_id, user_id)
+        DO UPDATE SET manually_unread = EXCLUDED.manually_unread, updated_at = NOW()
+        "#
+    )
+    .bind(conversation_id)
+    .bind(user_id)
+    .bind(unread)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Set or clear a user's theme color for a conversation
+pub async fn set_conversation_theme_color(
+    pool: &PgPool,
+    user_id: Uuid,
+    conversation_id: Uuid,
+    color: Option<crate::shared::messaging::ThemeColor>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO conversation_settings (conversation_id, user_id, theme_color, updated_at)
+        VALUES ($1, $2, $3, NOW())
+        ON CONFLICT (conversation_id, user_id)
+        DO UPDATE SET theme_color = EXCLUDED.theme_color, updated_at = NOW()
+        "#
+    )
+    .bind(conversation_id)
+    .bind(user_id)
+    .bind(color.map(|c| c.to_hex()))
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Pin or unpin a conversation for a user
+///
+/// A newly pinned conversation goes below the user's other pinned ones.
+pub async fn set_conversation_pinned(
+    pool: &PgPool,
+    user_id: Uuid,
+    conversation_id: Uuid,
+    pinned: bool,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO conversation_settings (conversation_id, user_id, pinned, pin_order, updated_at)
+        VALUES (
+            $1, $2, $3,
+            (SELECT COALESCE(MAX(pin_order) + 1, 0) FROM conversation_settings WHERE user_id = $2 AND pinned),
+            NOW()
+        )
+        ON CONFLICT (conversation_id, user_id)
+        DO UPDATE SET
+            pin_order = CASE
+                WHEN conversation_settings.pinned = EXCLUDED.pinned THEN conversation_settings.pin_order
+                ELSE EXCLUDED.pin_order
+            END,
+            pinned = EXCLUDED.pinned,
+            updated_at = NOW()
+        "#
+    )
+    .bind(conversation_id)
+    .bind(user_id)
+    .bind(pinned)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Reorder a user's pinned conversations
+///
+/// `conversation_ids` is the new order, top first. Conversations that
+/// aren't pinned by the user are ignored.
+pub async fn reorder_pinned_conversations(
+    pool: &PgPool,
+    user_id: Uuid,
+    conversation_ids: &[Uuid],
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        UPDATE conversation_settings cs
+        SET pin_order = (o.position - 1)::INTEGER, updated_at = NOW()
+        FROM UNNEST($2::UUID[]) WITH ORDINALITY AS o(conversation_id, position)
+        WHERE cs.user_id = $1 AND cs.conversation_id = o.conversation_id AND cs.pinned
+        "#
+    )
+    .bind(user_id)
+    .bind(conversation_ids)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
 /// Check if a user is a participant in a conversation
 pub async fn is_user_participant_in_conversation(
     pool: &PgPool,
@@ -488,3 +1069,66 @@ pub async fn is_user_participant_in_conversation(
     Ok(count > 0)
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::test_db::TestDatabase;
+    use crate::shared::messaging::ChatMessage;
+
+    #[tokio::test]
+    async fn test_inserted_message_is_searchable() {
+        let db = TestDatabase::new().await;
+        let pool = db.pool().clone();
+        let alice = db.user().await;
+        let bob = db.user().await;
+        let outsider = db.user().await;
+        let conversation_id = create_conversation(&pool, alice.id, bob.id).await.unwrap();
+
+        let weak = ChatMessage::new_text(conversation_id, alice.id, "Running late, see you soon".to_string(), 1);
+        let strong = ChatMessage::new_text(conversation_id, bob.id, "Still running? Running club runs at six".to_string(), 2);
+        let other = ChatMessage::new_text(conversation_id, bob.id, "Dinner at eight".to_string(), 3);
+        for message in [&weak, &strong, &other] {
+            store_message(&pool, message).await.unwrap();
+        }
+
+        // The trigger indexed the new rows; "run" matches "running" by stem
+        let hits = search_messages(&pool, bob.id, "run", 10, 0).await.unwrap();
+        let ids: Vec<Uuid> = hits.iter().map(|m| m.id).collect();
+        assert_eq!(ids, vec![strong.id, weak.id]);
+
+        // Only participants see the conversation's messages
+        let hits = search_messages(&pool, outsider.id, "run", 10, 0).await.unwrap();
+        assert!(hits.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_edits_and_tombstones() {
+        let db = TestDatabase::new().await;
+        let pool = db.pool().clone();
+        let (alice, bob) = (db.user().await, db.user().await);
+        let conversation_id = create_conversation(&pool, alice.id, bob.id).await.unwrap();
+        let message = ChatMessage::new_text(conversation_id, alice.id, "hello".to_string(), 1);
+        store_message(&pool, &message).await.unwrap();
+        let original = message.current_version().to_string();
+
+        // Two edits of the same version: only the first lands
+        assert!(edit_message(&pool, message.id, &original, "hello!", "edit-1", None).await.unwrap());
+        assert!(!edit_message(&pool, message.id, &original, "hi", "edit-2", None).await.unwrap());
+        let stored = get_conversation_message(&pool, conversation_id, message.id).await.unwrap().unwrap();
+        assert_eq!(stored.content, "hello!");
+        assert_eq!(stored.current_version(), "edit-1");
+        assert!(stored.is_edited());
+
+        // Deleting keeps the row as a tombstone that can't be edited
+        assert!(delete_message(&pool, message.id).await.unwrap());
+        assert!(!delete_message(&pool, message.id).await.unwrap());
+        assert!(!edit_message(&pool, message.id, "edit-1", "back", "edit-3", None).await.unwrap());
+        let stored = get_conversation_message(&pool, conversation_id, message.id).await.unwrap().unwrap();
+        assert!(stored.is_deleted);
+        assert!(stored.content.is_empty());
+
+        // Messages are only found in their own conversation
+        assert!(get_conversation_message(&pool, Uuid::new_v4(), message.id).await.unwrap().is_none());
+    }
+}
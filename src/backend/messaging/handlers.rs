@@ -16,10 +16,13 @@ use crate::shared::messaging::{
     RespondFriendRequestRequest, RespondFriendRequestResponse, ListFriendRequestsResponse,
     ListContactsResponse,
 };
+use super::conversation_cache::ConversationCache;
+use super::conversation_events::ConversationEvents;
 use super::db;
+use super::pagination::PaginationParams;
 
 /// Extract and verify JWT token from headers
-fn extract_user_id(headers: &HeaderMap) -> Result<Uuid, StatusCode> {
+pub(crate) fn extract_user_id(headers: &HeaderMap) -> Result<Uuid, StatusCode> {
     let auth_header = headers.get("authorization")
         .and_then(|h| h.to_str().ok())
         .ok_or(StatusCode::UNAUTHORIZED)?;
@@ -49,10 +52,12 @@ pub async fn send_friend_request(
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
         .ok_or(StatusCode::NOT_FOUND)?;
 
-    // Find the recipient by email
-    let to_user = crate::backend::auth::users::get_user_by_email(pool, &request.to_email)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    // Find the recipient by ID, or by email
+    let to_user = match request.to_user_id {
+        Some(to_user_id) => crate::backend::auth::users::get_user_by_id(pool, to_user_id).await,
+        None => crate::backend::auth::users::get_user_by_email(pool, &request.to_email).await,
+    }
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
     let to_user = match to_user {
         Some(user) => user,
@@ -65,12 +70,33 @@ pub async fn send_friend_request(
         }
     };
 
+    // Blocked senders are turned away outright
+    let blocked = super::blocking::is_blocked(pool, to_user.id, from_user_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if blocked {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    // Respect the recipient's "who can message me" setting
+    let allowed = super::privacy::allows_friend_request(pool, from_user_id, to_user.id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if !allowed {
+        return Ok(Json(SendFriendRequestResponse {
+            success: false,
+            request_id: None,
+            error: Some(super::privacy::PRIVACY_REJECTED_ERROR.to_string()),
+        }));
+    }
+
     // Check if already friends or request pending
     let existing_contact = db::get_contacts_for_user(pool, from_user_id)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
         .into_iter()
-        .find(|c| c.email == request.to_email);
+        .find(|c| c.email == to_user.email);
 
     if existing_contact.is_some() {
         return Ok(Json(SendFriendRequestResponse {
@@ -85,7 +111,7 @@ pub async fn send_friend_request(
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    let has_pending_request = pending_requests.iter().any(|r| r.to_email == request.to_email);
+    let has_pending_request = pending_requests.iter().any(|r| r.to_email == to_user.email);
 
     if has_pending_request {
         return Ok(Json(SendFriendRequestResponse {
@@ -103,7 +129,7 @@ pub async fn send_friend_request(
         &from_user.username,
         &from_user.email,
         &to_user.email,
-        None,
+        request.message.as_deref(),
     )
     .await
     .map_err(|e| {
@@ -145,6 +171,7 @@ pub async fn get_friend_requests(
 /// Respond to a friend request (accept or reject)
 pub async fn respond_to_friend_request(
     State(db_pool): State<Option<PgPool>>,
+    State(events): State<ConversationEvents>,
     headers: HeaderMap,
     Json(request): Json<RespondFriendRequestRequest>,
 ) -> Result<Json<RespondFriendRequestResponse>, StatusCode> {
@@ -163,13 +190,18 @@ pub async fn respond_to_friend_request(
             return Err(StatusCode::FORBIDDEN);
         }
 
-        // Now mark it as accepted
-        db::accept_friend_request(pool, request.request_id, user_id)
+        // Now mark it as accepted. A retried accept (e.g. from the offline
+        // queue) still runs the steps below: they are idempotent, and this
+        // completes an earlier attempt that failed partway through.
+        let newly_accepted = db::accept_friend_request(pool, request.request_id, user_id)
             .await
             .map_err(|e| {
                 tracing::error!("Failed to accept friend request: {:?}", e);
                 StatusCode::INTERNAL_SERVER_ERROR
             })?;
+        if !newly_accepted {
+            tracing::debug!("Friend request {} was already accepted", request.request_id);
+        }
 
         // Get the sender's user info
         let sender = crate::backend::auth::users::get_user_by_id(pool, friend_request.from_user_id)
@@ -211,13 +243,25 @@ pub async fn respond_to_friend_request(
             StatusCode::INTERNAL_SERVER_ERROR
         })?;
 
-        // Create a conversation between them
-        db::create_conversation(pool, user_id, sender.id)
+        // Create a conversation between them, unless there already is one
+        let existing = db::find_direct_conversation(pool, user_id, sender.id)
             .await
             .map_err(|e| {
-                tracing::error!("Failed to create conversation: {:?}", e);
+                tracing::error!("Failed to look up conversation: {:?}", e);
                 StatusCode::INTERNAL_SERVER_ERROR
             })?;
+        if existing.is_none() {
+            let conversation_id = db::create_conversation(pool, user_id, sender.id)
+                .await
+                .map_err(|e| {
+                    tracing::error!("Failed to create conversation: {:?}", e);
+                    StatusCode::INTERNAL_SERVER_ERROR
+                })?;
+
+            if let Err(e) = events.created(pool, conversation_id, &[user_id, sender.id]).await {
+                tracing::warn!("Failed to announce conversation {}: {:?}", conversation_id, e);
+            }
+        }
     } else {
         db::reject_friend_request(pool, request.request_id, user_id)
             .await
@@ -269,18 +313,322 @@ pub async fn get_conversations(
     Ok(Json(crate::shared::messaging::ListConversationsResponse { conversations }))
 }
 
+/// Start a conversation with one or more contacts
+///
+/// Body: `{"participant_ids": [...], "name": "Weekend plans"}`; the caller
+/// is added automatically and `name` may be left out. Everyone added must
+/// be in the caller's contacts. An unnamed chat with a single other person
+/// reuses the existing direct conversation if there is one.
+pub async fn create_conversation(
+    State(db_pool): State<Option<PgPool>>,
+    State(events): State<ConversationEvents>,
+    headers: HeaderMap,
+    Json(request): Json<crate::shared::messaging::CreateConversationRequest>,
+) -> Result<Json<crate::shared::messaging::CreateConversationResponse>, StatusCode> {
+    let pool = db_pool.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+    let user_id = extract_user_id(&headers)?;
+
+    let name = request.name
+        .map(|n| n.trim().to_string())
+        .filter(|n| !n.is_empty());
+
+    let mut others = Vec::new();
+    for id in request.participant_ids {
+        if id != user_id && !others.contains(&id) {
+            others.push(id);
+        }
+    }
+    if others.is_empty() {
+        return Ok(Json(crate::shared::messaging::CreateConversationResponse {
+            success: false,
+            conversation: None,
+            error: Some("Pick at least one other person".to_string()),
+        }));
+    }
+
+    for &other in &others {
+        let is_contact = super::privacy::is_contact(pool, user_id, other)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        if !is_contact {
+            return Err(StatusCode::FORBIDDEN);
+        }
+    }
+
+    let existing = if name.is_none() && others.len() == 1 {
+        db::find_direct_conversation(pool, user_id, others[0])
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to look up conversation: {:?}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?
+    } else {
+        None
+    };
+
+    let conversation_id = match existing {
+        Some(id) => id,
+        None => {
+            let mut participants = vec![user_id];
+            participants.extend(&others);
+            let conversation_id = db::create_group_conversation(pool, user_id, name.as_deref(), &participants)
+                .await
+                .map_err(|e| {
+                    tracing::error!("Failed to create conversation: {:?}", e);
+                    StatusCode::INTERNAL_SERVER_ERROR
+                })?;
+
+            if let Err(e) = events.created(pool, conversation_id, &participants).await {
+                tracing::warn!("Failed to announce conversation {}: {:?}", conversation_id, e);
+            }
+            conversation_id
+        }
+    };
+
+    let conversation = db::get_conversations_for_user(pool, user_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to get conversations: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .into_iter()
+        .find(|c| c.id == conversation_id);
+
+    Ok(Json(crate::shared::messaging::CreateConversationResponse {
+        success: true,
+        conversation,
+        error: None,
+    }))
+}
+
+/// Add one of the caller's contacts to a conversation they're in
+pub async fn add_conversation_participant(
+    State(db_pool): State<Option<PgPool>>,
+    State(conversations): State<ConversationCache>,
+    State(events): State<ConversationEvents>,
+    headers: HeaderMap,
+    axum::extract::Path((conversation_id, member_id)): axum::extract::Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, StatusCode> {
+    let pool = db_pool.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+    let user_id = extract_user_id(&headers)?;
+
+    let is_participant = conversations.is_participant(pool, user_id, conversation_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if !is_participant {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let is_contact = super::privacy::is_contact(pool, user_id, member_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if !is_contact {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    conversations.add_participant(pool, conversation_id, member_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to add conversation participant: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    // The new member sees the conversation appear; everyone else sees it change
+    let others = remaining_participants(&conversations, pool, conversation_id, member_id).await;
+    let announced = match events.created(pool, conversation_id, &[member_id]).await {
+        Ok(()) => events.updated(pool, conversation_id, &others).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = announced {
+        tracing::warn!("Failed to announce conversation {}: {:?}", conversation_id, e);
+    }
+
+    Ok(StatusCode::OK)
+}
+
+/// Remove a participant from a conversation
+///
+/// Users can only remove themselves, i.e. leave the conversation.
+pub async fn remove_conversation_participant(
+    State(db_pool): State<Option<PgPool>>,
+    State(conversations): State<ConversationCache>,
+    State(events): State<ConversationEvents>,
+    headers: HeaderMap,
+    axum::extract::Path((conversation_id, member_id)): axum::extract::Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, StatusCode> {
+    let pool = db_pool.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+    let user_id = extract_user_id(&headers)?;
+
+    if member_id != user_id {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let removed = conversations.remove_participant(pool, conversation_id, member_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to remove conversation participant: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    if !removed {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    events.deleted(member_id, conversation_id);
+    let others = remaining_participants(&conversations, pool, conversation_id, member_id).await;
+    if let Err(e) = events.updated(pool, conversation_id, &others).await {
+        tracing::warn!("Failed to announce conversation {}: {:?}", conversation_id, e);
+    }
+
+    Ok(StatusCode::OK)
+}
+
+/// Everyone in a conversation except `member_id`; empty if it can't be loaded
+async fn remaining_participants(
+    conversations: &ConversationCache,
+    pool: &PgPool,
+    conversation_id: Uuid,
+    member_id: Uuid,
+) -> Vec<Uuid> {
+    match conversations.get(pool, conversation_id).await {
+        Ok(Some(info)) => info.participants.iter().copied().filter(|&id| id != member_id).collect(),
+        Ok(None) => Vec::new(),
+        Err(e) => {
+            tracing::warn!("Failed to load participants of {}: {:?}", conversation_id, e);
+            Vec::new()
+        }
+    }
+}
+
+/// Set or clear the manual "unread" marker on a conversation
+///
+/// Body: `{"unread": true}` to mark, `{"unread": false}` to clear. Clients
+/// clear it when the conversation is opened.
+pub async fn mark_conversation_unread(
+    State(db_pool): State<Option<PgPool>>,
+    headers: HeaderMap,
+    axum::extract::Path(conversation_id): axum::extract::Path<Uuid>,
+    Json(request): Json<crate::shared::messaging::MarkConversationUnreadRequest>,
+) -> Result<StatusCode, StatusCode> {
+    let pool = db_pool.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+    let user_id = extract_user_id(&headers)?;
+
+    let is_participant = db::is_user_participant_in_conversation(pool, user_id, conversation_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if !is_participant {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    db::set_conversation_manually_unread(pool, user_id, conversation_id, request.unread)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to update conversation unread marker: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(StatusCode::OK)
+}
+
+/// Set or clear the user's theme color for a conversation
+///
+/// Body: `{"color": "#1e90ff"}` to set, `{"color": null}` to go back to the
+/// global theme. Only affects the requesting user's view of the chat.
+pub async fn set_conversation_theme(
+    State(db_pool): State<Option<PgPool>>,
+    headers: HeaderMap,
+    axum::extract::Path(conversation_id): axum::extract::Path<Uuid>,
+    Json(request): Json<crate::shared::messaging::SetConversationThemeRequest>,
+) -> Result<StatusCode, StatusCode> {
+    let pool = db_pool.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+    let user_id = extract_user_id(&headers)?;
+
+    let is_participant = db::is_user_participant_in_conversation(pool, user_id, conversation_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if !is_participant {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    db::set_conversation_theme_color(pool, user_id, conversation_id, request.color)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to update conversation theme color: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(StatusCode::OK)
+}
+
+/// Pin or unpin a conversation in the user's sidebar
+///
+/// Body: `{"pinned": true}`. Pinning puts the conversation below the
+/// user's other pinned conversations.
+pub async fn set_conversation_pinned(
+    State(db_pool): State<Option<PgPool>>,
+    headers: HeaderMap,
+    axum::extract::Path(conversation_id): axum::extract::Path<Uuid>,
+    Json(request): Json<crate::shared::messaging::PinConversationRequest>,
+) -> Result<StatusCode, StatusCode> {
+    let pool = db_pool.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+    let user_id = extract_user_id(&headers)?;
+
+    let is_participant = db::is_user_participant_in_conversation(pool, user_id, conversation_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if !is_participant {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    db::set_conversation_pinned(pool, user_id, conversation_id, request.pinned)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to update conversation pin: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(StatusCode::OK)
+}
+
+/// Reorder the user's pinned conversations
+///
+/// Body: `{"conversation_ids": [...]}`, top of the sidebar first.
+pub async fn reorder_pinned_conversations(
+    State(db_pool): State<Option<PgPool>>,
+    headers: HeaderMap,
+    Json(request): Json<crate::shared::messaging::ReorderPinnedConversationsRequest>,
+) -> Result<StatusCode, StatusCode> {
+    let pool = db_pool.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+    let user_id = extract_user_id(&headers)?;
+
+    db::reorder_pinned_conversations(pool, user_id, &request.conversation_ids)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to reorder pinned conversations: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(StatusCode::OK)
+}
+
 /// Get messages for a conversation
 pub async fn get_messages(
     State(db_pool): State<Option<PgPool>>,
+    State(conversations): State<ConversationCache>,
     headers: HeaderMap,
     axum::extract::Path(conversation_id): axum::extract::Path<Uuid>,
-    axum::extract::Query(params): axum::extract::Query<ListMessagesParams>,
+    pagination: PaginationParams,
 ) -> Result<Json<crate::shared::messaging::ListMessagesResponse>, StatusCode> {
     let pool = db_pool.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
     let user_id = extract_user_id(&headers)?;
 
     // Verify user is participant in conversation
-    let is_participant = db::is_user_participant_in_conversation(pool, user_id, conversation_id)
+    let is_participant = conversations.is_participant(pool, user_id, conversation_id)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
@@ -288,8 +636,7 @@ pub async fn get_messages(
         return Err(StatusCode::FORBIDDEN);
     }
 
-    let limit = params.limit.unwrap_or(50) as i64;
-    let offset = params.offset.unwrap_or(0) as i64;
+    let PaginationParams { limit, offset } = pagination;
 
     let messages = db::get_messages_for_conversation(pool, conversation_id, limit, offset)
         .await
@@ -302,14 +649,51 @@ pub async fn get_messages(
     Ok(Json(crate::shared::messaging::ListMessagesResponse {
         messages,
         has_more,
+        limit: limit as u32,
+        offset: offset as u32,
     }))
 }
 
-/// Query parameters for listing messages
+/// Query string for message search
 #[derive(Debug, serde::Deserialize)]
-pub struct ListMessagesParams {
-    pub limit: Option<u32>,
-    pub offset: Option<u32>,
+pub struct SearchMessagesParams {
+    /// Search text (web search syntax: quoted phrases, `or`, `-word`)
+    pub q: String,
+}
+
+/// Search messages across the user's conversations
+///
+/// Uses the Postgres full-text index, best match first. A blank query
+/// returns no messages.
+pub async fn search_messages(
+    State(db_pool): State<Option<PgPool>>,
+    headers: HeaderMap,
+    axum::extract::Query(params): axum::extract::Query<SearchMessagesParams>,
+    pagination: PaginationParams,
+) -> Result<Json<crate::shared::messaging::ListMessagesResponse>, StatusCode> {
+    let pool = db_pool.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+    let user_id = extract_user_id(&headers)?;
+    let PaginationParams { limit, offset } = pagination;
+
+    let query = params.q.trim();
+    let messages = if query.is_empty() {
+        Vec::new()
+    } else {
+        db::search_messages(pool, user_id, query, limit, offset)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to search messages: {:?}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?
+    };
+
+    let has_more = messages.len() as i64 == limit;
+    Ok(Json(crate::shared::messaging::ListMessagesResponse {
+        messages,
+        has_more,
+        limit: limit as u32,
+        offset: offset as u32,
+    }))
 }
 
 /// Mark a message as read
@@ -331,3 +715,251 @@ pub async fn mark_message_read(
     Ok(StatusCode::OK)
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::Path;
+    use crate::backend::auth::users::User;
+    use crate::backend::test_db::{auth, TestDatabase};
+    use crate::shared::messaging::{ChatMessage, Conversation, CreateConversationRequest, PinConversationRequest};
+    use crate::shared::ConversationEvent;
+
+    async fn befriend(pool: &PgPool, owner: &User, other: &User) {
+        db::create_contact(pool, owner.id, other.id, &other.username, &other.email).await.unwrap();
+    }
+
+    async fn create(pool: &PgPool, creator: &User, members: &[&User], name: Option<&str>) -> Result<Conversation, StatusCode> {
+        create_announced(pool, &ConversationEvents::default(), creator, members, name).await
+    }
+
+    async fn create_announced(
+        pool: &PgPool,
+        events: &ConversationEvents,
+        creator: &User,
+        members: &[&User],
+        name: Option<&str>,
+    ) -> Result<Conversation, StatusCode> {
+        let request = CreateConversationRequest {
+            participant_ids: members.iter().map(|u| u.id).collect(),
+            name: name.map(str::to_string),
+        };
+        let Json(response) = create_conversation(
+            State(Some(pool.clone())),
+            State(events.clone()),
+            auth(creator),
+            Json(request),
+        )
+        .await?;
+        assert!(response.success);
+        Ok(response.conversation.unwrap())
+    }
+
+    async fn listed(pool: &PgPool, user: &User, conversation_id: Uuid) -> Option<Conversation> {
+        db::get_conversations_for_user(pool, user.id)
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|c| c.id == conversation_id)
+    }
+
+    async fn pin(pool: &PgPool, user: &User, conversation_id: Uuid) -> StatusCode {
+        set_conversation_pinned(
+            State(Some(pool.clone())),
+            auth(user),
+            Path(conversation_id),
+            Json(PinConversationRequest { pinned: true }),
+        )
+        .await
+        .unwrap_or_else(|status| status)
+    }
+
+    #[tokio::test]
+    async fn test_accepting_twice_creates_one_friendship() {
+        let db = TestDatabase::new().await;
+        let pool = db.pool().clone();
+        let sender = db.user().await;
+        let recipient = db.user().await;
+
+        let request = db::create_friend_request(
+            &pool,
+            sender.id,
+            recipient.id,
+            &sender.username,
+            &sender.email,
+            &recipient.email,
+            None,
+        )
+        .await
+        .unwrap();
+
+        for _ in 0..2 {
+            let Json(response) = respond_to_friend_request(
+                State(Some(pool.clone())),
+                State(ConversationEvents::default()),
+                auth(&recipient),
+                Json(RespondFriendRequestRequest { request_id: request.id, accept: true }),
+            )
+            .await
+            .unwrap();
+            assert!(response.success);
+        }
+
+        let recipient_contacts = db::get_contacts_for_user(&pool, recipient.id).await.unwrap();
+        assert_eq!(recipient_contacts.len(), 1);
+        assert_eq!(recipient_contacts[0].contact_user_id, sender.id);
+        let sender_contacts = db::get_contacts_for_user(&pool, sender.id).await.unwrap();
+        assert_eq!(sender_contacts.len(), 1);
+
+        let conversations = db::get_conversations_for_user(&pool, recipient.id).await.unwrap();
+        assert_eq!(conversations.len(), 1);
+        assert!(conversations[0].has_participant(sender.id));
+    }
+
+    #[tokio::test]
+    async fn test_pinned_conversations_sort_above_recent_ones() {
+        let db = TestDatabase::new().await;
+        let pool = db.pool().clone();
+        let me = db.user().await;
+        let mut conversations = Vec::new();
+        for _ in 0..3 {
+            let friend = db.user().await;
+            conversations.push(db::create_conversation(&pool, me.id, friend.id).await.unwrap());
+        }
+        let (oldest, middle, newest) = (conversations[0], conversations[1], conversations[2]);
+
+        // Recent messages in the later conversations
+        for (i, &conversation_id) in [middle, newest].iter().enumerate() {
+            let message = ChatMessage::new_text(conversation_id, me.id, "hi".to_string(), i as u64 + 1);
+            db::store_message(&pool, &message).await.unwrap();
+        }
+        let order = |list: Vec<Conversation>| list.iter().map(|c| c.id).collect::<Vec<_>>();
+        assert_eq!(order(db::get_conversations_for_user(&pool, me.id).await.unwrap()), vec![newest, middle, oldest]);
+
+        // Pinned first regardless of last-message time, then by recency
+        assert_eq!(pin(&pool, &me, oldest).await, StatusCode::OK);
+        let listed = db::get_conversations_for_user(&pool, me.id).await.unwrap();
+        assert_eq!(order(listed.clone()), vec![oldest, newest, middle]);
+        assert!(listed[0].pinned);
+        assert!(!listed[1].pinned);
+
+        // New pins go below existing ones until reordered
+        assert_eq!(pin(&pool, &me, middle).await, StatusCode::OK);
+        assert_eq!(order(db::get_conversations_for_user(&pool, me.id).await.unwrap()), vec![oldest, middle, newest]);
+        db::reorder_pinned_conversations(&pool, me.id, &[middle, oldest]).await.unwrap();
+        assert_eq!(order(db::get_conversations_for_user(&pool, me.id).await.unwrap()), vec![middle, oldest, newest]);
+
+        // Pins are per user and only for participants
+        let stranger = db.user().await;
+        assert_eq!(pin(&pool, &stranger, oldest).await, StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_group_conversations_are_titled_after_members() {
+        let db = TestDatabase::new().await;
+        let pool = db.pool().clone();
+        let (alice, bob, carol, stranger) = (db.user().await, db.user().await, db.user().await, db.user().await);
+        befriend(&pool, &alice, &bob).await;
+        befriend(&pool, &alice, &carol).await;
+
+        // Only contacts can be added
+        assert_eq!(create(&pool, &alice, &[&bob, &stranger], None).await.unwrap_err(), StatusCode::FORBIDDEN);
+
+        let group = create(&pool, &alice, &[&bob, &carol, &bob], None).await.unwrap();
+        assert_eq!(group.participants.len(), 3);
+        assert!(group.is_group());
+        assert_eq!(group.other_username, None);
+
+        // Bob isn't friends with carol, so he sees her account username
+        let seen_by_bob = listed(&pool, &bob, group.id).await.unwrap();
+        let title = seen_by_bob.display_title();
+        assert!(title.contains(&alice.username) && title.contains(&carol.username));
+
+        let named = create(&pool, &alice, &[&bob], Some("  Weekend plans ")).await.unwrap();
+        assert!(named.is_group());
+        assert_eq!(named.display_title(), "Weekend plans");
+
+        // An unnamed chat with one person reuses the direct conversation
+        let direct = create(&pool, &alice, &[&bob], None).await.unwrap();
+        assert_eq!(create(&pool, &alice, &[&bob], None).await.unwrap().id, direct.id);
+        assert_eq!(direct.other_username.as_deref(), Some(bob.username.as_str()));
+    }
+
+    #[tokio::test]
+    async fn test_participants_can_be_added_and_leave() {
+        let db = TestDatabase::new().await;
+        let pool = db.pool().clone();
+        let cache = ConversationCache::new(16);
+        let (alice, bob, carol, dave) = (db.user().await, db.user().await, db.user().await, db.user().await);
+        befriend(&pool, &alice, &bob).await;
+        befriend(&pool, &alice, &carol).await;
+        befriend(&pool, &alice, &dave).await;
+        let group = create(&pool, &alice, &[&bob, &carol], None).await.unwrap();
+
+        let add = |adder: &User, member: &User| {
+            add_conversation_participant(
+                State(Some(pool.clone())),
+                State(cache.clone()),
+                State(ConversationEvents::default()),
+                auth(adder),
+                Path((group.id, member.id)),
+            )
+        };
+        let remove = |remover: &User, member: &User| {
+            remove_conversation_participant(
+                State(Some(pool.clone())),
+                State(cache.clone()),
+                State(ConversationEvents::default()),
+                auth(remover),
+                Path((group.id, member.id)),
+            )
+        };
+
+        // Bob can't add someone who isn't his contact; alice can
+        assert_eq!(add(&bob, &dave).await, Err(StatusCode::FORBIDDEN));
+        assert_eq!(add(&alice, &dave).await, Ok(StatusCode::OK));
+        assert!(cache.is_participant(&pool, dave.id, group.id).await.unwrap());
+
+        // People can only remove themselves
+        assert_eq!(remove(&bob, &carol).await, Err(StatusCode::FORBIDDEN));
+        assert_eq!(remove(&carol, &carol).await, Ok(StatusCode::OK));
+        assert_eq!(remove(&carol, &carol).await, Err(StatusCode::NOT_FOUND));
+        assert!(!cache.is_participant(&pool, carol.id, group.id).await.unwrap());
+        assert!(listed(&pool, &carol, group.id).await.is_none());
+
+        let members = listed(&pool, &alice, group.id).await.unwrap().participants;
+        assert_eq!(members.len(), 3);
+        assert!(members.contains(&dave.id) && !members.contains(&carol.id));
+    }
+
+    #[tokio::test]
+    async fn test_creating_a_conversation_announces_it_to_both_participants() {
+        let db = TestDatabase::new().await;
+        let pool = db.pool().clone();
+        let events = ConversationEvents::default();
+        let (alice, bob, carol) = (db.user().await, db.user().await, db.user().await);
+        befriend(&pool, &alice, &bob).await;
+        let mut rx = events.subscribe();
+
+        let direct = create_announced(&pool, &events, &alice, &[&bob], None).await.unwrap();
+
+        let mut announced = Vec::new();
+        while let Ok((recipient, event)) = rx.try_recv() {
+            let ConversationEvent::Created { conversation } = event else {
+                panic!("expected a created event, got {:?}", event);
+            };
+            assert_eq!(conversation.id, direct.id);
+            announced.push((recipient, conversation));
+        }
+        assert_eq!(announced.len(), 2);
+        assert!(announced.iter().all(|(recipient, _)| *recipient != carol.id));
+
+        // Each participant gets the conversation as they see it
+        let (_, for_bob) = announced.iter().find(|(recipient, _)| *recipient == bob.id).unwrap();
+        assert_eq!(for_bob.other_username.as_deref(), Some(alice.username.as_str()));
+        assert!(announced.iter().any(|(recipient, _)| *recipient == alice.id));
+
+        // Reusing the existing direct conversation announces nothing
+        create_announced(&pool, &events, &alice, &[&bob], None).await.unwrap();
+        assert!(rx.try_recv().is_err());
+    }
+}
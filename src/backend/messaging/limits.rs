@@ -0,0 +1,202 @@
+//! Message Size Limits
+//!
+//! Three separate bounds apply to every message PUT:
+//!
+//! - **Text length** - the message body; longer text is `400 Bad Request`
+//! - **Author length** - the sender name on `/chat`; longer is `400 Bad Request`
+//! - **Total size** - the whole serialized message (text, metadata and
+//!   attachment references); larger messages are `413 Payload Too Large`.
+//!   This protects the database and broadcast channels from messages whose
+//!   text is fine but whose other fields are huge.
+//!
+//! All three are served to clients from `GET /api/limits` so they can check
+//! before sending.
+//!
+//! # Configuration
+//!
+//! - `MAX_MESSAGE_LENGTH` - text length in bytes (default 10,000)
+//! - `MAX_AUTHOR_LENGTH` - author name length in bytes (default 100)
+//! - `MAX_MESSAGE_BYTES` - total serialized size in bytes (default 64 KiB,
+//!   at most 16 MiB)
+//!
+//! Values must be positive integers and the text and author limits can't
+//! exceed the total size. Anything else is rejected at startup rather than
+//! turning into a limit that refuses every message.
+
+use axum::{extract::State, http::StatusCode, Json};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Default text length limit
+pub const DEFAULT_MAX_TEXT_LENGTH: usize = 10_000;
+
+/// Default author name length limit
+pub const DEFAULT_MAX_AUTHOR_LENGTH: usize = 100;
+
+/// Default total serialized message size limit
+pub const DEFAULT_MAX_MESSAGE_BYTES: usize = 64 * 1024;
+
+/// Highest total size limit that may be configured
+pub const MAX_MESSAGE_BYTES_CEILING: usize = 16 * 1024 * 1024;
+
+/// Misconfigured message limits
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum LimitsError {
+    /// Not a positive integer
+    #[error("{name} must be a positive integer, got '{value}'")]
+    Invalid { name: &'static str, value: String },
+
+    /// Above what the limit may be
+    #[error("{name} is {value}, more than the allowed {max}")]
+    TooLarge { name: &'static str, value: usize, max: usize },
+}
+
+/// Size limits enforced on message PUT
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MessageLimits {
+    /// Longest message text, in bytes
+    pub max_text_length: usize,
+    /// Longest author name, in bytes
+    pub max_author_length: usize,
+    /// Largest serialized message, in bytes
+    pub max_message_bytes: usize,
+}
+
+impl Default for MessageLimits {
+    fn default() -> Self {
+        Self {
+            max_text_length: DEFAULT_MAX_TEXT_LENGTH,
+            max_author_length: DEFAULT_MAX_AUTHOR_LENGTH,
+            max_message_bytes: DEFAULT_MAX_MESSAGE_BYTES,
+        }
+    }
+}
+
+impl MessageLimits {
+    /// Read limits from `MAX_MESSAGE_LENGTH`, `MAX_AUTHOR_LENGTH` and
+    /// `MAX_MESSAGE_BYTES`, defaulting unset ones
+    pub fn from_env() -> Result<Self, LimitsError> {
+        fn env_or(name: &'static str, default: usize) -> Result<usize, LimitsError> {
+            let Ok(value) = std::env::var(name) else {
+                return Ok(default);
+            };
+            value
+                .trim()
+                .parse()
+                .ok()
+                .filter(|v| *v > 0)
+                .ok_or(LimitsError::Invalid { name, value })
+        }
+
+        let defaults = Self::default();
+        let limits = Self {
+            max_text_length: env_or("MAX_MESSAGE_LENGTH", defaults.max_text_length)?,
+            max_author_length: env_or("MAX_AUTHOR_LENGTH", defaults.max_author_length)?,
+            max_message_bytes: env_or("MAX_MESSAGE_BYTES", defaults.max_message_bytes)?,
+        };
+        limits.validate()?;
+        Ok(limits)
+    }
+
+    /// Check the limits are positive and fit inside each other
+    pub fn validate(&self) -> Result<(), LimitsError> {
+        for (name, value) in [
+            ("MAX_MESSAGE_LENGTH", self.max_text_length),
+            ("MAX_AUTHOR_LENGTH", self.max_author_length),
+            ("MAX_MESSAGE_BYTES", self.max_message_bytes),
+        ] {
+            if value == 0 {
+                return Err(LimitsError::Invalid { name, value: value.to_string() });
+            }
+        }
+        if self.max_message_bytes > MAX_MESSAGE_BYTES_CEILING {
+            return Err(LimitsError::TooLarge {
+                name: "MAX_MESSAGE_BYTES",
+                value: self.max_message_bytes,
+                max: MAX_MESSAGE_BYTES_CEILING,
+            });
+        }
+        for (name, value) in [("MAX_MESSAGE_LENGTH", self.max_text_length), ("MAX_AUTHOR_LENGTH", self.max_author_length)] {
+            if value > self.max_message_bytes {
+                return Err(LimitsError::TooLarge { name, value, max: self.max_message_bytes });
+            }
+        }
+        Ok(())
+    }
+
+    /// `400 Bad Request` if the text is too long
+    pub fn check_text(&self, text: &str) -> Result<(), StatusCode> {
+        if text.len() > self.max_text_length {
+            tracing::warn!("[Server] Rejected message text of {} bytes (limit {})", text.len(), self.max_text_length);
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        Ok(())
+    }
+
+    /// `400 Bad Request` if the author name is too long
+    pub fn check_author(&self, author: &str) -> Result<(), StatusCode> {
+        if author.len() > self.max_author_length {
+            tracing::warn!("[Server] Rejected author name of {} bytes (limit {})", author.len(), self.max_author_length);
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        Ok(())
+    }
+
+    /// `413 Payload Too Large` if the serialized message is too big
+    pub fn check_size(&self, serialized_len: usize) -> Result<(), StatusCode> {
+        if serialized_len > self.max_message_bytes {
+            tracing::warn!("[Server] Rejected message of {} bytes (limit {})", serialized_len, self.max_message_bytes);
+            return Err(StatusCode::PAYLOAD_TOO_LARGE);
+        }
+        Ok(())
+    }
+}
+
+/// Serve the message limits
+/// GET /api/limits
+pub async fn get_limits(State(limits): State<MessageLimits>) -> Json<MessageLimits> {
+    Json(limits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limits_are_independent() {
+        let limits = MessageLimits { max_text_length: 10, max_author_length: 5, max_message_bytes: 100 };
+
+        assert!(limits.check_text("short").is_ok());
+        assert_eq!(limits.check_text("far too long text"), Err(StatusCode::BAD_REQUEST));
+        assert!(limits.check_author("alice").is_ok());
+        assert_eq!(limits.check_author("alice2"), Err(StatusCode::BAD_REQUEST));
+
+        assert!(limits.check_size(100).is_ok());
+        assert_eq!(limits.check_size(101), Err(StatusCode::PAYLOAD_TOO_LARGE));
+    }
+
+    #[test]
+    fn test_unreasonable_limits_are_rejected() {
+        assert_eq!(MessageLimits::default().validate(), Ok(()));
+
+        let zero = MessageLimits { max_author_length: 0, ..MessageLimits::default() };
+        assert!(matches!(zero.validate(), Err(LimitsError::Invalid { name: "MAX_AUTHOR_LENGTH", .. })));
+
+        let text_over_total = MessageLimits { max_text_length: 100_000, ..MessageLimits::default() };
+        assert_eq!(
+            text_over_total.validate(),
+            Err(LimitsError::TooLarge { name: "MAX_MESSAGE_LENGTH", value: 100_000, max: DEFAULT_MAX_MESSAGE_BYTES })
+        );
+
+        let huge = MessageLimits { max_message_bytes: usize::MAX, ..MessageLimits::default() };
+        assert!(matches!(huge.validate(), Err(LimitsError::TooLarge { name: "MAX_MESSAGE_BYTES", .. })));
+    }
+
+    #[test]
+    fn test_limits_serialize_for_clients() {
+        let json = serde_json::to_value(MessageLimits::default()).unwrap();
+        assert_eq!(json["max_text_length"], DEFAULT_MAX_TEXT_LENGTH);
+        assert_eq!(json["max_author_length"], DEFAULT_MAX_AUTHOR_LENGTH);
+        assert_eq!(json["max_message_bytes"], DEFAULT_MAX_MESSAGE_BYTES);
+    }
+}
@@ -0,0 +1,570 @@
+//! Link Preview Service
+//!
+//! Fetches OpenGraph metadata for the first URL in a new message and attaches
+//! it to the message asynchronously: the preview is stored and the message is
+//! re-broadcast to subscribers as an edit.
+//!
+//! # SSRF Protection
+//!
+//! - Only `http`/`https` URLs are fetched
+//! - Every hop (including redirects) is resolved up front and rejected if any
+//!   address is private, loopback, link-local, multicast or otherwise
+//!   non-public; the request is then pinned to the checked addresses so DNS
+//!   cannot be rebound between the check and the connect
+//! - Redirects, response size and total time are capped
+//!
+//! # Configuration
+//!
+//! - `LINK_PREVIEW_TIMEOUT_SECS` - total fetch time (default 5)
+//! - `LINK_PREVIEW_MAX_BYTES` - bytes of HTML read (default 262144)
+//! - `LINK_PREVIEW_MAX_REDIRECTS` - redirects followed (default 3)
+//! - `LINK_PREVIEW_RATE_LIMIT` - previews per user per minute (default 10)
+//! - `LINK_PREVIEW_CACHE_TTL_SECS` - how long previews are cached (default 3600)
+
+use std::collections::{HashMap, VecDeque};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use reqwest::Url;
+use sqlx::PgPool;
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::backend::messaging::db::set_message_link_preview;
+use crate::backend::server::state::MessagingBroadcastState;
+use crate::shared::messaging::{extract_urls, ChatMessage, LinkPreview};
+
+/// Maximum number of cached previews
+const MAX_CACHE_ENTRIES: usize = 1024;
+
+/// Window for the per-user rate limit
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+
+/// Longest title/description kept from a page
+const MAX_FIELD_CHARS: usize = 300;
+
+/// Why a preview could not be produced
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum PreviewError {
+    #[error("Invalid URL: {0}")]
+    InvalidUrl(String),
+
+    #[error("Blocked non-public address: {0}")]
+    BlockedAddress(String),
+
+    #[error("Too many redirects")]
+    TooManyRedirects,
+
+    #[error("Timed out")]
+    Timeout,
+
+    #[error("Not an HTML page")]
+    NotHtml,
+
+    #[error("Page has no preview metadata")]
+    NoMetadata,
+
+    #[error("Fetch failed: {0}")]
+    Fetch(String),
+}
+
+/// Limits applied to preview fetches
+#[derive(Debug, Clone)]
+pub struct LinkPreviewConfig {
+    pub timeout: Duration,
+    pub max_body_bytes: usize,
+    pub max_redirects: usize,
+    pub per_user_per_minute: usize,
+    pub cache_ttl: Duration,
+    /// Skip the private-address check (local development and tests only)
+    pub allow_private_networks: bool,
+}
+
+impl Default for LinkPreviewConfig {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(5),
+            max_body_bytes: 256 * 1024,
+            max_redirects: 3,
+            per_user_per_minute: 10,
+            cache_ttl: Duration::from_secs(3600),
+            allow_private_networks: false,
+        }
+    }
+}
+
+impl LinkPreviewConfig {
+    /// Read limits from `LINK_PREVIEW_*` environment variables
+    pub fn from_env() -> Self {
+        fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T {
+            std::env::var(name).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+        }
+
+        let defaults = Self::default();
+        Self {
+            timeout: Duration::from_secs(env_or("LINK_PREVIEW_TIMEOUT_SECS", defaults.timeout.as_secs())),
+            max_body_bytes: env_or("LINK_PREVIEW_MAX_BYTES", defaults.max_body_bytes),
+            max_redirects: env_or("LINK_PREVIEW_MAX_REDIRECTS", defaults.max_redirects),
+            per_user_per_minute: env_or("LINK_PREVIEW_RATE_LIMIT", defaults.per_user_per_minute),
+            cache_ttl: Duration::from_secs(env_or("LINK_PREVIEW_CACHE_TTL_SECS", defaults.cache_ttl.as_secs())),
+            allow_private_networks: false,
+        }
+    }
+}
+
+struct LinkPreviewInner {
+    config: LinkPreviewConfig,
+    cache: Mutex<HashMap<String, (Instant, LinkPreview)>>,
+    recent_requests: Mutex<HashMap<Uuid, VecDeque<Instant>>>,
+}
+
+/// Rate-limited, cached link preview fetcher
+#[derive(Clone)]
+pub struct LinkPreviewService {
+    inner: Arc<LinkPreviewInner>,
+}
+
+impl LinkPreviewService {
+    pub fn new(config: LinkPreviewConfig) -> Self {
+        Self {
+            inner: Arc::new(LinkPreviewInner {
+                config,
+                cache: Mutex::new(HashMap::new()),
+                recent_requests: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(LinkPreviewConfig::from_env())
+    }
+
+    /// Record a preview request for `user_id`; false if over the per-minute limit
+    pub fn check_rate_limit(&self, user_id: Uuid) -> bool {
+        let now = Instant::now();
+        let mut recent = self.inner.recent_requests.lock().unwrap();
+        let window = recent.entry(user_id).or_default();
+        while window.front().is_some_and(|t| now.duration_since(*t) > RATE_LIMIT_WINDOW) {
+            window.pop_front();
+        }
+        if window.len() >= self.inner.config.per_user_per_minute {
+            return false;
+        }
+        window.push_back(now);
+        true
+    }
+
+    fn cached(&self, url: &str) -> Option<LinkPreview> {
+        let cache = self.inner.cache.lock().unwrap();
+        cache
+            .get(url)
+            .filter(|(fetched_at, _)| fetched_at.elapsed() < self.inner.config.cache_ttl)
+            .map(|(_, preview)| preview.clone())
+    }
+
+    fn store(&self, url: &str, preview: &LinkPreview) {
+        let ttl = self.inner.config.cache_ttl;
+        let mut cache = self.inner.cache.lock().unwrap();
+        if cache.len() >= MAX_CACHE_ENTRIES {
+            cache.retain(|_, (fetched_at, _)| fetched_at.elapsed() < ttl);
+            if cache.len() >= MAX_CACHE_ENTRIES {
+                if let Some(oldest) = cache.iter().min_by_key(|(_, (t, _))| *t).map(|(k, _)| k.clone()) {
+                    cache.remove(&oldest);
+                }
+            }
+        }
+        cache.insert(url.to_string(), (Instant::now(), preview.clone()));
+    }
+
+    /// Fetch (or return the cached) preview for `url`
+    pub async fn fetch(&self, url: &str) -> Result<LinkPreview, PreviewError> {
+        if let Some(preview) = self.cached(url) {
+            return Ok(preview);
+        }
+
+        let preview = tokio::time::timeout(self.inner.config.timeout, self.fetch_uncached(url))
+            .await
+            .map_err(|_| PreviewError::Timeout)??;
+
+        self.store(url, &preview);
+        Ok(preview)
+    }
+
+    async fn fetch_uncached(&self, url: &str) -> Result<LinkPreview, PreviewError> {
+        let config = &self.inner.config;
+        let mut current = Url::parse(url).map_err(|e| PreviewError::InvalidUrl(e.to_string()))?;
+
+        for _ in 0..=config.max_redirects {
+            let addrs = self.resolve_checked(&current).await?;
+
+            let mut builder = reqwest::Client::builder()
+                .redirect(reqwest::redirect::Policy::none())
+                .timeout(config.timeout)
+                .user_agent("xfmail-link-preview/1.0");
+            if let Some(domain) = current.host_str().filter(|host| literal_ip(host).is_none()) {
+                builder = builder.resolve_to_addrs(domain, &addrs);
+            }
+            let client = builder.build().map_err(|e| PreviewError::Fetch(e.to_string()))?;
+
+            let mut response = client
+                .get(current.clone())
+                .header(reqwest::header::ACCEPT, "text/html")
+                .send()
+                .await
+                .map_err(|e| PreviewError::Fetch(e.to_string()))?;
+
+            if response.status().is_redirection() {
+                let location = response
+                    .headers()
+                    .get(reqwest::header::LOCATION)
+                    .and_then(|h| h.to_str().ok())
+                    .ok_or_else(|| PreviewError::Fetch("Redirect without Location".to_string()))?;
+                current = current.join(location).map_err(|e| PreviewError::InvalidUrl(e.to_string()))?;
+                continue;
+            }
+            if !response.status().is_success() {
+                return Err(PreviewError::Fetch(format!("HTTP {}", response.status())));
+            }
+
+            let is_html = response
+                .headers()
+                .get(reqwest::header::CONTENT_TYPE)
+                .and_then(|h| h.to_str().ok())
+                .is_some_and(|ct| ct.to_ascii_lowercase().contains("text/html"));
+            if !is_html {
+                return Err(PreviewError::NotHtml);
+            }
+
+            // Metadata lives in <head>; stop reading at the cap instead of failing
+            let mut body = Vec::new();
+            while let Some(chunk) = response.chunk().await.map_err(|e| PreviewError::Fetch(e.to_string()))? {
+                let remaining = config.max_body_bytes - body.len();
+                body.extend_from_slice(&chunk[..chunk.len().min(remaining)]);
+                if body.len() >= config.max_body_bytes {
+                    break;
+                }
+            }
+
+            let html = String::from_utf8_lossy(&body);
+            return parse_open_graph(&html, &current).ok_or(PreviewError::NoMetadata);
+        }
+
+        Err(PreviewError::TooManyRedirects)
+    }
+
+    /// Resolve the URL's host, rejecting non-public addresses
+    async fn resolve_checked(&self, url: &Url) -> Result<Vec<SocketAddr>, PreviewError> {
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(PreviewError::InvalidUrl(format!("Unsupported scheme '{}'", url.scheme())));
+        }
+        let port = url
+            .port_or_known_default()
+            .ok_or_else(|| PreviewError::InvalidUrl("Missing port".to_string()))?;
+
+        let host = url
+            .host_str()
+            .ok_or_else(|| PreviewError::InvalidUrl("Missing host".to_string()))?;
+
+        let addrs: Vec<SocketAddr> = match literal_ip(host) {
+            Some(ip) => vec![SocketAddr::new(ip, port)],
+            None => tokio::net::lookup_host((host, port))
+                .await
+                .map_err(|e| PreviewError::Fetch(format!("DNS lookup failed: {}", e)))?
+                .collect(),
+        };
+
+        if addrs.is_empty() {
+            return Err(PreviewError::Fetch("Host did not resolve".to_string()));
+        }
+        if !self.inner.config.allow_private_networks {
+            if let Some(blocked) = addrs.iter().find(|addr| is_blocked_ip(addr.ip())) {
+                return Err(PreviewError::BlockedAddress(blocked.ip().to_string()));
+            }
+        }
+        Ok(addrs)
+    }
+
+    /// Fetch a preview for the first URL in `message` in the background,
+    /// then store it and re-broadcast the message as an edit
+    pub fn spawn_for_message(&self, pool: PgPool, broadcast_state: MessagingBroadcastState, message: ChatMessage) {
+        let Some(url) = extract_urls(&message.content).into_iter().next() else {
+            return;
+        };
+        if !self.check_rate_limit(message.sender_id) {
+            tracing::debug!("[LinkPreview] Rate limit reached for user {}", message.sender_id);
+            return;
+        }
+
+        let service = self.clone();
+        tokio::spawn(async move {
+            let preview = match service.fetch(&url).await {
+                Ok(preview) if !preview.is_empty() => preview,
+                Ok(_) => return,
+                Err(e) => {
+                    tracing::debug!("[LinkPreview] No preview for {}: {}", url, e);
+                    return;
+                }
+            };
+
+            if let Err(e) = set_message_link_preview(&pool, message.id, &preview).await {
+                tracing::error!("[LinkPreview] Failed to store preview for message {}: {}", message.id, e);
+                return;
+            }
+
+            let conversation_id = message.conversation_id;
+            let mut updated = message;
+            updated.link_preview = Some(preview);
+            broadcast_state.broadcast(conversation_id, updated);
+        });
+    }
+}
+
+/// IP address of a URL host written as a literal (`1.2.3.4` or `[::1]`)
+fn literal_ip(host: &str) -> Option<IpAddr> {
+    host.trim_start_matches('[').trim_end_matches(']').parse().ok()
+}
+
+/// Whether an address must never be fetched (private, loopback, link-local, ...)
+pub fn is_blocked_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => is_blocked_ipv4(v4),
+        IpAddr::V6(v6) => {
+            if let Some(v4) = v6.to_ipv4_mapped() {
+                return is_blocked_ipv4(v4);
+            }
+            let segments = v6.segments();
+            v6.is_loopback()
+                || v6.is_unspecified()
+                || v6.is_multicast()
+                || (segments[0] & 0xfe00) == 0xfc00 // unique local fc00::/7
+                || (segments[0] & 0xffc0) == 0xfe80 // link-local fe80::/10
+                || (segments[0] == 0x2001 && segments[1] == 0x0db8) // documentation
+                || (segments[0] == 0x0064 && segments[1] == 0xff9b) // NAT64
+        }
+    }
+}
+
+fn is_blocked_ipv4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || ip.is_documentation()
+        || a == 0
+        || (a == 100 && (64..128).contains(&b)) // carrier-grade NAT
+        || (a == 192 && b == 0 && ip.octets()[2] == 0) // IETF protocol assignments
+        || (a == 198 && (b == 18 || b == 19)) // benchmarking
+        || a >= 240 // reserved
+}
+
+/// Extract OpenGraph (or plain HTML) preview metadata from a page
+pub fn parse_open_graph(html: &str, page_url: &Url) -> Option<LinkPreview> {
+    let lower = html.to_ascii_lowercase();
+    let mut meta: HashMap<String, String> = HashMap::new();
+
+    let mut pos = 0;
+    while let Some(start) = lower[pos..].find("<meta") {
+        let tag_start = pos + start;
+        let Some(end) = lower[tag_start..].find('>') else { break };
+        let tag = &html[tag_start + 5..tag_start + end];
+        pos = tag_start + end + 1;
+
+        let attrs = parse_attributes(tag);
+        let key = attrs.get("property").or_else(|| attrs.get("name"));
+        if let (Some(key), Some(content)) = (key, attrs.get("content")) {
+            meta.entry(key.to_ascii_lowercase()).or_insert_with(|| content.clone());
+        }
+    }
+
+    let title_tag = lower.find("<title").and_then(|start| {
+        let open_end = start + lower[start..].find('>')? + 1;
+        let close = open_end + lower[open_end..].find("</title")?;
+        Some(decode_entities(html[open_end..close].trim()))
+    });
+
+    let clean = |value: Option<String>| {
+        value
+            .map(|v| v.split_whitespace().collect::<Vec<_>>().join(" "))
+            .filter(|v| !v.is_empty())
+            .map(|v| v.chars().take(MAX_FIELD_CHARS).collect::<String>())
+    };
+
+    let preview = LinkPreview {
+        url: page_url.to_string(),
+        title: clean(meta.get("og:title").cloned().or(title_tag)),
+        description: clean(meta.get("og:description").or_else(|| meta.get("description")).cloned()),
+        image_url: meta
+            .get("og:image")
+            .and_then(|image| page_url.join(image).ok())
+            .filter(|image| matches!(image.scheme(), "http" | "https"))
+            .map(|image| image.to_string()),
+        site_name: clean(meta.get("og:site_name").cloned()),
+    };
+
+    (!preview.is_empty()).then_some(preview)
+}
+
+/// Parse `key="value"`, `key='value'` and `key=value` attributes of a tag
+fn parse_attributes(tag: &str) -> HashMap<String, String> {
+    let mut attrs = HashMap::new();
+    let mut rest = tag.trim_start();
+
+    while !rest.is_empty() {
+        let name_end = rest.find(|c: char| c == '=' || c.is_whitespace() || c == '/').unwrap_or(rest.len());
+        let name = rest[..name_end].to_ascii_lowercase();
+        rest = rest[name_end..].trim_start();
+
+        if let Some(after_eq) = rest.strip_prefix('=') {
+            let after_eq = after_eq.trim_start();
+            let (value, remaining) = match after_eq.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    let inner = &after_eq[1..];
+                    let close = inner.find(quote).unwrap_or(inner.len());
+                    (&inner[..close], inner.get(close + 1..).unwrap_or(""))
+                }
+                _ => {
+                    let end = after_eq.find(char::is_whitespace).unwrap_or(after_eq.len());
+                    (&after_eq[..end], &after_eq[end..])
+                }
+            };
+            if !name.is_empty() {
+                attrs.insert(name, decode_entities(value));
+            }
+            rest = remaining.trim_start();
+        } else {
+            rest = rest.trim_start_matches('/').trim_start();
+        }
+    }
+
+    attrs
+}
+
+fn decode_entities(value: &str) -> String {
+    value
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    const PAGE: &str = r#"<html><head>
+        <title>Fallback title</title>
+        <meta property="og:title" content="Braid &amp; CRDTs">
+        <meta property='og:description' content='Sync without conflicts'>
+        <meta property="og:image" content="/img/cover.png" />
+        <meta property="og:site_name" content="Example">
+        </head><body>hi</body></html>"#;
+
+    /// Serve a single HTTP response on a loopback port
+    async fn serve_once(body: &'static str) -> SocketAddr {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = [0u8; 1024];
+            let _ = socket.read(&mut request).await;
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            let _ = socket.write_all(response.as_bytes()).await;
+        });
+        addr
+    }
+
+    #[tokio::test]
+    async fn test_blocks_private_ip_urls() {
+        let service = LinkPreviewService::new(LinkPreviewConfig::default());
+
+        for url in [
+            "http://127.0.0.1/",
+            "http://10.0.0.5/admin",
+            "http://169.254.169.254/latest/meta-data/",
+            "http://[::1]:8080/",
+            "http://[::ffff:192.168.1.1]/",
+        ] {
+            assert!(
+                matches!(service.fetch(url).await, Err(PreviewError::BlockedAddress(_))),
+                "{} should be blocked",
+                url
+            );
+        }
+        assert!(matches!(service.fetch("file:///etc/passwd").await, Err(PreviewError::InvalidUrl(_))));
+    }
+
+    #[tokio::test]
+    async fn test_blocks_private_ip_even_when_serving() {
+        let addr = serve_once(PAGE).await;
+        let service = LinkPreviewService::new(LinkPreviewConfig::default());
+
+        let result = service.fetch(&format!("http://{}/", addr)).await;
+        assert_eq!(result, Err(PreviewError::BlockedAddress("127.0.0.1".to_string())));
+    }
+
+    #[tokio::test]
+    async fn test_fetches_preview() {
+        let addr = serve_once(PAGE).await;
+        let service = LinkPreviewService::new(LinkPreviewConfig {
+            allow_private_networks: true,
+            ..LinkPreviewConfig::default()
+        });
+        let url = format!("http://{}/post", addr);
+
+        let preview = service.fetch(&url).await.unwrap();
+        assert_eq!(preview.title.as_deref(), Some("Braid & CRDTs"));
+        assert_eq!(preview.description.as_deref(), Some("Sync without conflicts"));
+        assert_eq!(preview.image_url, Some(format!("http://{}/img/cover.png", addr)));
+        assert_eq!(preview.site_name.as_deref(), Some("Example"));
+
+        // Second fetch is served from cache (the test server only answers once)
+        assert_eq!(service.fetch(&url).await.unwrap(), preview);
+    }
+
+    #[test]
+    fn test_parse_public_page() {
+        let url = Url::parse("https://example.com/articles/1").unwrap();
+        let preview = parse_open_graph(PAGE, &url).unwrap();
+
+        assert_eq!(preview.url, "https://example.com/articles/1");
+        assert_eq!(preview.image_url.as_deref(), Some("https://example.com/img/cover.png"));
+
+        let plain = parse_open_graph("<title> Just a title </title>", &url).unwrap();
+        assert_eq!(plain.title.as_deref(), Some("Just a title"));
+        assert!(parse_open_graph("<p>nothing</p>", &url).is_none());
+    }
+
+    #[test]
+    fn test_is_blocked_ip() {
+        assert!(is_blocked_ip("192.168.0.1".parse().unwrap()));
+        assert!(is_blocked_ip("100.64.0.1".parse().unwrap()));
+        assert!(is_blocked_ip("fd00::1".parse().unwrap()));
+        assert!(!is_blocked_ip("93.184.216.34".parse().unwrap()));
+        assert!(!is_blocked_ip("2606:2800:220:1::".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        let service = LinkPreviewService::new(LinkPreviewConfig {
+            per_user_per_minute: 2,
+            ..LinkPreviewConfig::default()
+        });
+        let user = Uuid::new_v4();
+
+        assert!(service.check_rate_limit(user));
+        assert!(service.check_rate_limit(user));
+        assert!(!service.check_rate_limit(user));
+        assert!(service.check_rate_limit(Uuid::new_v4()));
+    }
+}
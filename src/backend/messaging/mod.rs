@@ -4,10 +4,50 @@
 
 pub mod handlers;
 pub mod db;
+pub mod pagination;
+pub mod privacy;
+pub mod blocking;
 #[cfg(feature = "ssr")]
 pub mod message_sync;
+#[cfg(feature = "ssr")]
+pub mod reconnect_guard;
+#[cfg(feature = "ssr")]
+pub mod link_preview;
+#[cfg(feature = "ssr")]
+pub mod content_filter;
+#[cfg(feature = "ssr")]
+pub mod checkpoint;
+#[cfg(feature = "ssr")]
+pub mod limits;
+#[cfg(feature = "ssr")]
+pub mod conversation_cache;
+#[cfg(feature = "ssr")]
+pub mod presence;
+#[cfg(feature = "ssr")]
+pub mod conversation_events;
+#[cfg(feature = "ssr")]
+pub mod user_search;
 
 pub use handlers::*;
+pub use pagination::PaginationParams;
+pub use privacy::{get_privacy_settings, update_privacy_settings, PRIVACY_REJECTED_ERROR};
+pub use blocking::{block_user_handler, list_blocked_users, unblock_user_handler, BLOCKED_ERROR};
 #[cfg(feature = "ssr")]
 pub use message_sync::*;
+#[cfg(feature = "ssr")]
+pub use reconnect_guard::{ReconnectGuard, ReconnectStats};
+#[cfg(feature = "ssr")]
+pub use link_preview::{LinkPreviewService, PreviewError};
+#[cfg(feature = "ssr")]
+pub use content_filter::{ContentFilter, FilterDecision, NoopFilter, SharedContentFilter, WordlistFilter};
+#[cfg(feature = "ssr")]
+pub use limits::{LimitsError, MessageLimits};
+#[cfg(feature = "ssr")]
+pub use conversation_cache::ConversationCache;
+#[cfg(feature = "ssr")]
+pub use presence::{presence_heartbeat, PresenceConfig, StatusStore};
+#[cfg(feature = "ssr")]
+pub use conversation_events::ConversationEvents;
+#[cfg(feature = "ssr")]
+pub use user_search::search_users_handler;
 
@@ -0,0 +1,112 @@
+//! Pagination Extractor
+//!
+//! Centralizes `limit`/`offset` handling for list endpoints so callers
+//! cannot request unbounded result sets.
+//!
+//! # Rules
+//!
+//! - `limit` defaults to [`DEFAULT_LIMIT`] and is clamped to `1..=max`
+//! - `max` comes from `PAGINATION_MAX_LIMIT` (default [`DEFAULT_MAX_LIMIT`])
+//! - negative `offset` values are rejected with `400 Bad Request`
+
+use axum::{
+    extract::{FromRequestParts, Query},
+    http::{request::Parts, StatusCode},
+};
+use serde::Deserialize;
+
+/// Default page size when the caller does not supply `limit`
+pub const DEFAULT_LIMIT: i64 = 50;
+
+/// Default upper bound for `limit`
+pub const DEFAULT_MAX_LIMIT: i64 = 200;
+
+/// Raw query string values before validation
+#[derive(Debug, Deserialize)]
+struct RawPagination {
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+/// Validated pagination parameters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PaginationParams {
+    /// Effective (clamped) page size
+    pub limit: i64,
+    /// Number of rows to skip
+    pub offset: i64,
+}
+
+impl PaginationParams {
+    /// Validate raw values against `max_limit`.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(PaginationParams)` with `limit` clamped to `1..=max_limit`
+    /// - `Err(StatusCode::BAD_REQUEST)` if `offset` is negative
+    pub fn from_raw(limit: Option<i64>, offset: Option<i64>, max_limit: i64) -> Result<Self, StatusCode> {
+        let offset = offset.unwrap_or(0);
+        if offset < 0 {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+
+        let max_limit = max_limit.max(1);
+        let limit = limit.unwrap_or(DEFAULT_LIMIT.min(max_limit)).clamp(1, max_limit);
+
+        Ok(Self { limit, offset })
+    }
+}
+
+/// Read the configured maximum page size from `PAGINATION_MAX_LIMIT`
+pub fn max_limit() -> i64 {
+    std::env::var("PAGINATION_MAX_LIMIT")
+        .ok()
+        .and_then(|v| v.parse::<i64>().ok())
+        .filter(|v| *v > 0)
+        .unwrap_or(DEFAULT_MAX_LIMIT)
+}
+
+impl<S> FromRequestParts<S> for PaginationParams
+where
+    S: Send + Sync,
+{
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Query(raw) = Query::<RawPagination>::from_request_parts(parts, state)
+            .await
+            .map_err(|_| StatusCode::BAD_REQUEST)?;
+
+        Self::from_raw(raw.limit, raw.offset, max_limit())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults() {
+        let params = PaginationParams::from_raw(None, None, DEFAULT_MAX_LIMIT).unwrap();
+        assert_eq!(params, PaginationParams { limit: DEFAULT_LIMIT, offset: 0 });
+    }
+
+    #[test]
+    fn test_over_max_limit_is_clamped() {
+        let params = PaginationParams::from_raw(Some(10_000), Some(5), 200).unwrap();
+        assert_eq!(params.limit, 200);
+        assert_eq!(params.offset, 5);
+    }
+
+    #[test]
+    fn test_zero_limit_is_raised_to_one() {
+        let params = PaginationParams::from_raw(Some(0), None, 200).unwrap();
+        assert_eq!(params.limit, 1);
+    }
+
+    #[test]
+    fn test_negative_offset_is_rejected() {
+        let result = PaginationParams::from_raw(Some(10), Some(-1), 200);
+        assert_eq!(result, Err(StatusCode::BAD_REQUEST));
+    }
+}
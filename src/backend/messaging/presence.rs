@@ -0,0 +1,615 @@
+//! Contact Presence
+//!
+//! Tracks whether users are online from client heartbeats:
+//!
+//! - **Heartbeat** - `POST /api/presence/heartbeat` marks the caller online
+//!   and bumps their `last_seen` on every contact row that points at them
+//! - **Sweep** - a background task marks users offline once their last
+//!   heartbeat is older than the timeout
+//! - **Batch** - `GET /api/presence/batch` reads the current presence of a
+//!   set of contacts, so a client can seed its contact list on login
+//!
+//! Each online/offline change is broadcast as a `presence` realtime event
+//! so subscribers can update their contact list without reloading it.
+//! Repeat heartbeats from a user who is already online broadcast nothing.
+//!
+//! On top of that, users pick a status (online, away, do not disturb) and
+//! optional status text, synced Braid-style over `/sync/presence`: `PUT`
+//! sets the caller's status, `GET` streams the statuses of the caller's
+//! contacts, starting with a snapshot. Statuses live in memory in
+//! [`StatusStore`] and are forgotten when the user goes offline.
+//!
+//! Users who turned off `send_presence` in their privacy settings appear
+//! offline: their heartbeats aren't recorded and their statuses aren't
+//! shared.
+//!
+//! # Configuration
+//!
+//! - `PRESENCE_TIMEOUT_SECS` - seconds without a heartbeat before a user is
+//!   offline (default three heartbeat intervals, 90)
+
+use std::collections::{HashMap, HashSet};
+use std::convert::Infallible;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use axum::{
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    response::sse::{Event, KeepAlive, Sse},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use futures_util::stream::{self, Stream, StreamExt};
+use serde::Deserialize;
+use sqlx::{PgPool, Row};
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use crate::backend::realtime::{broadcast_event, RealtimeEventBroadcast};
+use crate::backend::server::shutdown::Shutdown;
+use crate::shared::messaging::{SetStatusRequest, UserStatus, MAX_PRESENCE_BATCH};
+use crate::shared::{PresenceEvent, RealtimeEvent, PRESENCE_HEARTBEAT_SECS};
+use super::handlers::extract_user_id;
+use super::privacy;
+
+/// How often the sweep looks for missed heartbeats
+const SWEEP_INTERVAL: Duration = Duration::from_secs(15);
+
+/// When a user counts as offline
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PresenceConfig {
+    /// Time without a heartbeat before a user is marked offline
+    pub timeout: Duration,
+}
+
+impl Default for PresenceConfig {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(3 * PRESENCE_HEARTBEAT_SECS),
+        }
+    }
+}
+
+impl PresenceConfig {
+    /// Read the timeout from `PRESENCE_TIMEOUT_SECS`
+    pub fn from_env() -> Self {
+        fn env_or(name: &str, default: u64) -> u64 {
+            std::env::var(name).ok().and_then(|v| v.parse().ok()).filter(|v| *v > 0).unwrap_or(default)
+        }
+
+        let defaults = Self::default();
+        Self {
+            timeout: Duration::from_secs(env_or("PRESENCE_TIMEOUT_SECS", defaults.timeout.as_secs())),
+        }
+    }
+}
+
+/// Users' chosen statuses, and a channel announcing changes to them
+#[derive(Clone)]
+pub struct StatusStore {
+    statuses: Arc<RwLock<HashMap<Uuid, UserStatus>>>,
+    updates: broadcast::Sender<UserStatus>,
+}
+
+impl Default for StatusStore {
+    fn default() -> Self {
+        Self {
+            statuses: Arc::new(RwLock::new(HashMap::new())),
+            updates: broadcast::channel(256).0,
+        }
+    }
+}
+
+impl StatusStore {
+    /// Set a user's status, stamped with the current time, and announce it
+    pub fn set(&self, user_id: Uuid, request: SetStatusRequest) -> UserStatus {
+        let status = stamp(user_id, request);
+        self.statuses.write().unwrap().insert(user_id, status.clone());
+        let _ = self.updates.send(status.clone());
+        status
+    }
+
+    /// Statuses of whichever of `user_ids` have set one
+    pub fn get_many(&self, user_ids: &HashSet<Uuid>) -> Vec<UserStatus> {
+        let statuses = self.statuses.read().unwrap();
+        user_ids.iter().filter_map(|id| statuses.get(id).cloned()).collect()
+    }
+
+    /// Drop a user's status, e.g. once they're offline
+    pub fn forget(&self, user_id: Uuid) {
+        self.statuses.write().unwrap().remove(&user_id);
+    }
+
+    /// Receive status changes as they're set
+    pub fn subscribe(&self) -> broadcast::Receiver<UserStatus> {
+        self.updates.subscribe()
+    }
+}
+
+/// A status as set now
+fn stamp(user_id: Uuid, request: SetStatusRequest) -> UserStatus {
+    UserStatus {
+        user_id,
+        status: request.status,
+        status_text: request.status_text,
+        updated_at: Utc::now().to_rfc3339(),
+    }
+}
+
+/// Users whose statuses `user_id` may see: their contacts and themselves
+pub async fn status_audience(pool: &PgPool, user_id: Uuid) -> Result<HashSet<Uuid>, sqlx::Error> {
+    let contacts: Vec<Uuid> = sqlx::query_scalar("SELECT contact_user_id FROM contacts WHERE user_id = $1")
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(contacts.into_iter().chain(std::iter::once(user_id)).collect())
+}
+
+/// Current presence of whichever of `user_ids` are `user_id`'s contacts
+///
+/// Contacts who don't share presence appear offline with no `last_seen`.
+pub async fn contact_presence(pool: &PgPool, user_id: Uuid, user_ids: &[Uuid]) -> Result<Vec<PresenceEvent>, sqlx::Error> {
+    let rows = sqlx::query(
+        r#"
+        SELECT DISTINCT ON (c.contact_user_id)
+               c.contact_user_id,
+               COALESCE(c.is_online, FALSE) AND u.send_presence AS is_online,
+               CASE WHEN u.send_presence THEN c.last_seen END AS last_seen
+        FROM contacts c
+        JOIN users u ON u.id = c.contact_user_id
+        WHERE c.user_id = $1 AND c.contact_user_id = ANY($2)
+        ORDER BY c.contact_user_id
+        "#
+    )
+    .bind(user_id)
+    .bind(user_ids)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .iter()
+        .map(|row| PresenceEvent {
+            user_id: row.get("contact_user_id"),
+            is_online: row.get("is_online"),
+            last_seen: row
+                .get::<Option<DateTime<Utc>>, _>("last_seen")
+                .map(|t| t.to_rfc3339())
+                .unwrap_or_default(),
+        })
+        .collect())
+}
+
+/// Mark a user online and bump their `last_seen`
+///
+/// Returns the new `last_seen` if this brought them online, `None` if they
+/// were already online (or nobody has them as a contact).
+pub async fn record_heartbeat(pool: &PgPool, user_id: Uuid) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+    let rows = sqlx::query(
+        r#"
+        UPDATE contacts c
+        SET last_seen = NOW(), is_online = TRUE
+        FROM (SELECT id, COALESCE(is_online, FALSE) AS was_online FROM contacts WHERE contact_user_id = $1 FOR UPDATE) old
+        WHERE c.id = old.id
+        RETURNING c.last_seen, old.was_online
+        "#
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    let came_online = rows.iter().any(|row| !row.get::<bool, _>("was_online"));
+    Ok(rows.first().filter(|_| came_online).map(|row| row.get("last_seen")))
+}
+
+/// Mark users offline whose last heartbeat is older than `timeout`
+///
+/// Returns each user that went offline with their last heartbeat.
+pub async fn expire_presence(pool: &PgPool, timeout: Duration) -> Result<Vec<(Uuid, DateTime<Utc>)>, sqlx::Error> {
+    let rows = sqlx::query(
+        r#"
+        UPDATE contacts
+        SET is_online = FALSE
+        WHERE is_online AND last_seen < NOW() - make_interval(secs => $1)
+        RETURNING contact_user_id, last_seen
+        "#
+    )
+    .bind(timeout.as_secs_f64())
+    .fetch_all(pool)
+    .await?;
+
+    let mut expired: Vec<(Uuid, DateTime<Utc>)> = Vec::new();
+    for row in &rows {
+        let user_id: Uuid = row.get("contact_user_id");
+        if !expired.iter().any(|(id, _)| *id == user_id) {
+            expired.push((user_id, row.get("last_seen")));
+        }
+    }
+    Ok(expired)
+}
+
+/// Broadcast one user's presence change
+async fn broadcast_presence(realtime: &RealtimeEventBroadcast, user_id: Uuid, is_online: bool, last_seen: DateTime<Utc>) {
+    let presence = PresenceEvent {
+        user_id,
+        is_online,
+        last_seen: last_seen.to_rfc3339(),
+    };
+    broadcast_event(realtime, RealtimeEvent::presence(presence)).await;
+}
+
+/// Mark expired users offline and tell subscribers; returns how many went offline
+///
+/// Their chosen statuses are forgotten; they send them again on return.
+pub async fn sweep_presence(
+    pool: &PgPool,
+    realtime: &RealtimeEventBroadcast,
+    statuses: &StatusStore,
+    config: PresenceConfig,
+) -> usize {
+    let expired = match expire_presence(pool, config.timeout).await {
+        Ok(expired) => expired,
+        Err(e) => {
+            tracing::error!("Failed to expire presence: {:?}", e);
+            return 0;
+        }
+    };
+    for (user_id, last_seen) in &expired {
+        statuses.forget(*user_id);
+        broadcast_presence(realtime, *user_id, false, *last_seen).await;
+    }
+    expired.len()
+}
+
+/// Run [`sweep_presence`] in the background for the life of the server
+pub fn spawn_presence_sweeper(pool: PgPool, realtime: RealtimeEventBroadcast, statuses: StatusStore, config: PresenceConfig) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SWEEP_INTERVAL);
+        loop {
+            interval.tick().await;
+            let expired = sweep_presence(&pool, &realtime, &statuses, config).await;
+            if expired > 0 {
+                tracing::debug!("Marked {} users offline after missed heartbeats", expired);
+            }
+        }
+    });
+}
+
+/// Record that the caller is online
+/// POST /api/presence/heartbeat
+///
+/// Clients send this every [`PRESENCE_HEARTBEAT_SECS`] while running.
+pub async fn presence_heartbeat(
+    State(db_pool): State<Option<PgPool>>,
+    State(realtime): State<RealtimeEventBroadcast>,
+    headers: HeaderMap,
+) -> Result<StatusCode, StatusCode> {
+    let pool = db_pool.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+    let user_id = extract_user_id(&headers)?;
+
+    if !shares_presence(pool, user_id).await? {
+        return Ok(StatusCode::NO_CONTENT);
+    }
+
+    let came_online = record_heartbeat(pool, user_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to record heartbeat for {}: {:?}", user_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    if let Some(last_seen) = came_online {
+        broadcast_presence(&realtime, user_id, true, last_seen).await;
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Query parameters for a presence batch
+#[derive(Debug, Default, Deserialize)]
+pub struct PresenceBatchParams {
+    /// Comma-separated user IDs
+    #[serde(default)]
+    pub user_ids: String,
+}
+
+/// Current presence of some of the caller's contacts
+/// GET /api/presence/batch?user_ids=
+///
+/// Called once after loading contacts; `presence` events keep them live
+/// from then on. IDs that aren't the caller's contacts are left out. A
+/// malformed ID or more than [`MAX_PRESENCE_BATCH`] IDs is `400 Bad Request`.
+pub async fn presence_batch(
+    State(db_pool): State<Option<PgPool>>,
+    headers: HeaderMap,
+    Query(params): Query<PresenceBatchParams>,
+) -> Result<Json<Vec<PresenceEvent>>, StatusCode> {
+    let pool = db_pool.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+    let user_id = extract_user_id(&headers)?;
+
+    let mut user_ids = params
+        .user_ids
+        .split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map(Uuid::parse_str)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| StatusCode::BAD_REQUEST)?;
+    user_ids.sort_unstable();
+    user_ids.dedup();
+    if user_ids.len() > MAX_PRESENCE_BATCH {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if user_ids.is_empty() {
+        return Ok(Json(Vec::new()));
+    }
+
+    let presence = contact_presence(pool, user_id, &user_ids)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to load presence for {}: {:?}", user_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    Ok(Json(presence))
+}
+
+/// Set the caller's status
+/// PUT /sync/presence
+///
+/// The server stamps the time, so the latest PUT wins everywhere. Unknown
+/// or offline statuses and over-long text are `400 Bad Request`. If the
+/// caller doesn't share presence the status is accepted but not shared.
+pub async fn handle_status_put(
+    State(db_pool): State<Option<PgPool>>,
+    State(statuses): State<StatusStore>,
+    headers: HeaderMap,
+    Json(request): Json<SetStatusRequest>,
+) -> Result<Json<UserStatus>, (StatusCode, String)> {
+    let pool = db_pool.as_ref().ok_or((StatusCode::SERVICE_UNAVAILABLE, String::new()))?;
+    let user_id = extract_user_id(&headers).map_err(|status| (status, String::new()))?;
+    let request = request.normalized().map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+
+    if !shares_presence(pool, user_id).await.map_err(|status| (status, String::new()))? {
+        statuses.forget(user_id);
+        return Ok(Json(stamp(user_id, request)));
+    }
+    Ok(Json(statuses.set(user_id, request)))
+}
+
+/// Whether the user shares their online status and chosen status
+async fn shares_presence(pool: &PgPool, user_id: Uuid) -> Result<bool, StatusCode> {
+    privacy::load_privacy_settings(pool, user_id)
+        .await
+        .map(|settings| settings.send_presence)
+        .map_err(|e| {
+            tracing::error!("Failed to load privacy settings of {}: {:?}", user_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+/// Stream the statuses of the caller's contacts
+/// GET /sync/presence
+///
+/// Starts with the statuses already set, then sends each change as a
+/// `status` event. Contacts added later show up on the next subscription.
+pub async fn handle_presence_subscription(
+    State(db_pool): State<Option<PgPool>>,
+    State(statuses): State<StatusStore>,
+    State(shutdown): State<Shutdown>,
+    headers: HeaderMap,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, StatusCode> {
+    let pool = db_pool.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+    let user_id = extract_user_id(&headers)?;
+
+    let audience = status_audience(pool, user_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to load contacts of {}: {:?}", user_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    // Subscribe before taking the snapshot so nothing falls in between
+    let updates = statuses.subscribe();
+    let snapshot = statuses.get_many(&audience);
+
+    let event = |status: &UserStatus| Ok(Event::default().event("status").data(serde_json::to_string(status).unwrap_or_default()));
+    let live = stream::unfold((updates, audience), move |(mut rx, audience)| async move {
+        loop {
+            match rx.recv().await {
+                Ok(status) if audience.contains(&status.user_id) => return Some((status, (rx, audience))),
+                Ok(_) => continue,
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!("Presence subscriber lagged, skipped {} statuses", skipped);
+                    continue;
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    });
+    let stream = stream::iter(snapshot).chain(live).map(move |status| event(&status));
+
+    Ok(Sse::new(shutdown.close_sse(stream)).keep_alive(KeepAlive::default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Json;
+    use crate::backend::auth::users::User;
+    use crate::backend::messaging::db;
+    use crate::backend::test_db::{auth, TestDatabase};
+    use crate::shared::messaging::PresenceStatus;
+
+    /// Sweeps go over every user in the shared test database, so tests that
+    /// sweep or check who is online take turns
+    static PRESENCE: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+    async fn online_for(pool: &PgPool, owner: &User, contact: &User) -> bool {
+        db::get_contacts_for_user(pool, owner.id)
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|c| c.contact_user_id == contact.id)
+            .unwrap()
+            .is_online
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_and_sweep_broadcast_presence() {
+        let _turn = PRESENCE.lock().await;
+        let db = TestDatabase::new().await;
+        let pool = db.pool().clone();
+        let (alice, bob) = (db.user().await, db.user().await);
+        db::create_contact(&pool, alice.id, bob.id, &bob.username, &bob.email).await.unwrap();
+        let (realtime, mut events) = broadcast::channel::<RealtimeEvent>(16);
+        let statuses = StatusStore::default();
+
+        let status = presence_heartbeat(State(Some(pool.clone())), State(realtime.clone()), auth(&bob)).await.unwrap();
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        assert!(online_for(&pool, &alice, &bob).await);
+        let came_online = PresenceEvent::from_event(&events.try_recv().unwrap()).unwrap();
+        assert_eq!(came_online.user_id, bob.id);
+        assert!(came_online.is_online);
+
+        // Staying online is quiet
+        presence_heartbeat(State(Some(pool.clone())), State(realtime.clone()), auth(&bob)).await.unwrap();
+        assert!(events.try_recv().is_err());
+
+        // Within the timeout nobody goes offline
+        let patient = PresenceConfig { timeout: Duration::from_secs(3600) };
+        sweep_presence(&pool, &realtime, &statuses, patient).await;
+        assert!(online_for(&pool, &alice, &bob).await);
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let strict = PresenceConfig { timeout: Duration::from_millis(10) };
+        assert!(sweep_presence(&pool, &realtime, &statuses, strict).await >= 1);
+        assert!(!online_for(&pool, &alice, &bob).await);
+        let went_offline = std::iter::from_fn(|| events.try_recv().ok())
+            .filter_map(|event| PresenceEvent::from_event(&event))
+            .find(|presence| presence.user_id == bob.id)
+            .unwrap();
+        assert!(!went_offline.is_online);
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_requires_auth() {
+        let db = TestDatabase::new().await;
+        let (realtime, _events) = broadcast::channel::<RealtimeEvent>(16);
+        let status = presence_heartbeat(State(Some(db.pool().clone())), State(realtime), HeaderMap::new())
+            .await
+            .unwrap_err();
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_status_put_is_seen_by_contacts_until_offline() {
+        let _turn = PRESENCE.lock().await;
+        let db = TestDatabase::new().await;
+        let pool = db.pool().clone();
+        let (alice, bob, carol) = (db.user().await, db.user().await, db.user().await);
+        db::create_contact(&pool, alice.id, bob.id, &bob.username, &bob.email).await.unwrap();
+        let statuses = StatusStore::default();
+        let mut updates = statuses.subscribe();
+
+        let request = SetStatusRequest {
+            status: PresenceStatus::Away,
+            status_text: Some("  At lunch ".to_string()),
+        };
+        let Json(set) = handle_status_put(State(Some(pool.clone())), State(statuses.clone()), auth(&bob), Json(request)).await.unwrap();
+        assert_eq!(set.user_id, bob.id);
+        assert_eq!(set.status_text.as_deref(), Some("At lunch"));
+        assert_eq!(updates.try_recv().unwrap(), set);
+
+        // Alice has Bob as a contact, Carol doesn't
+        let alices = status_audience(&pool, alice.id).await.unwrap();
+        assert_eq!(statuses.get_many(&alices), vec![set]);
+        let carols = status_audience(&pool, carol.id).await.unwrap();
+        assert!(statuses.get_many(&carols).is_empty());
+
+        // Going offline forgets the status
+        presence_heartbeat(State(Some(pool.clone())), State(broadcast::channel(16).0), auth(&bob)).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let (realtime, _events) = broadcast::channel::<RealtimeEvent>(16);
+        let strict = PresenceConfig { timeout: Duration::from_millis(10) };
+        assert!(sweep_presence(&pool, &realtime, &statuses, strict).await >= 1);
+        assert!(statuses.get_many(&alices).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_status_put_rejects_offline_and_long_text() {
+        let db = TestDatabase::new().await;
+        let bob = db.user().await;
+        let statuses = StatusStore::default();
+
+        for request in [
+            SetStatusRequest { status: PresenceStatus::Offline, status_text: None },
+            SetStatusRequest { status: PresenceStatus::Online, status_text: Some("x".repeat(141)) },
+        ] {
+            let (status, _) = handle_status_put(State(Some(db.pool().clone())), State(statuses.clone()), auth(&bob), Json(request)).await.unwrap_err();
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+        }
+        let (status, _) = handle_status_put(
+            State(Some(db.pool().clone())),
+            State(statuses),
+            HeaderMap::new(),
+            Json(SetStatusRequest { status: PresenceStatus::Away, status_text: None }),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    fn batch(user_ids: &[Uuid]) -> Query<PresenceBatchParams> {
+        let user_ids = user_ids.iter().map(Uuid::to_string).collect::<Vec<_>>().join(",");
+        Query(PresenceBatchParams { user_ids })
+    }
+
+    #[tokio::test]
+    async fn test_presence_batch_returns_requested_contacts_only() {
+        let _turn = PRESENCE.lock().await;
+        let db = TestDatabase::new().await;
+        let pool = db.pool().clone();
+        let (alice, bob, carol, dave) = (db.user().await, db.user().await, db.user().await, db.user().await);
+        db::create_contact(&pool, alice.id, bob.id, &bob.username, &bob.email).await.unwrap();
+        db::create_contact(&pool, alice.id, carol.id, &carol.username, &carol.email).await.unwrap();
+        db::create_contact(&pool, dave.id, bob.id, &bob.username, &bob.email).await.unwrap();
+        presence_heartbeat(State(Some(pool.clone())), State(broadcast::channel(16).0), auth(&bob)).await.unwrap();
+        presence_heartbeat(State(Some(pool.clone())), State(broadcast::channel(16).0), auth(&dave)).await.unwrap();
+
+        // Dave isn't Alice's contact, so his presence stays hidden even though he's online
+        let Json(mut presence) = presence_batch(State(Some(pool.clone())), auth(&alice), batch(&[bob.id, carol.id, dave.id]))
+            .await
+            .unwrap();
+        presence.sort_by_key(|p| p.user_id != bob.id);
+        assert_eq!(presence.len(), 2);
+        assert_eq!(presence[0].user_id, bob.id);
+        assert!(presence[0].is_online);
+        assert!(!presence[0].last_seen.is_empty());
+        assert_eq!(presence[1].user_id, carol.id);
+        assert!(!presence[1].is_online);
+
+        // Only what was asked for
+        let Json(presence) = presence_batch(State(Some(pool.clone())), auth(&alice), batch(&[carol.id])).await.unwrap();
+        assert_eq!(presence.iter().map(|p| p.user_id).collect::<Vec<_>>(), vec![carol.id]);
+
+        let Json(presence) = presence_batch(State(Some(pool.clone())), auth(&alice), batch(&[])).await.unwrap();
+        assert!(presence.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_presence_batch_rejects_oversized_and_malformed_requests() {
+        let db = TestDatabase::new().await;
+        let pool = db.pool().clone();
+        let alice = db.user().await;
+
+        let too_many: Vec<Uuid> = (0..=MAX_PRESENCE_BATCH).map(|_| Uuid::new_v4()).collect();
+        let status = presence_batch(State(Some(pool.clone())), auth(&alice), batch(&too_many)).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let malformed = Query(PresenceBatchParams { user_ids: "not-a-uuid".to_string() });
+        let status = presence_batch(State(Some(pool.clone())), auth(&alice), malformed).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let status = presence_batch(State(Some(pool.clone())), HeaderMap::new(), batch(&[alice.id])).await.unwrap_err();
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+}
@@ -0,0 +1,337 @@
+//! Message Privacy
+//!
+//! Enforces each user's [`MessagePrivacy`] setting when someone tries to
+//! start a conversation with them:
+//!
+//! - **Friend requests** - checked against the recipient's setting
+//! - **First message** - a message into a conversation with no messages yet
+//!   is checked against every other participant's setting
+//!
+//! Conversations that already have messages are never affected, so
+//! tightening the setting doesn't cut off ongoing chats. Rejections carry
+//! [`PRIVACY_REJECTED_ERROR`] so clients can tell them apart from other
+//! failures.
+//!
+//! It also stores whether the user shares typing indicators, online status
+//! and read receipts. The handlers that relay those drop them when the
+//! sender has turned them off; read receipts are only relayed when both
+//! the reader and the message's sender send them.
+
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+
+use crate::shared::messaging::{MessagePrivacy, PrivacySettings};
+use super::handlers::extract_user_id;
+
+pub use crate::shared::messaging::PRIVACY_REJECTED_ERROR;
+
+/// Get a user's message privacy setting
+pub async fn get_message_privacy(pool: &PgPool, user_id: Uuid) -> Result<MessagePrivacy, sqlx::Error> {
+    let value: Option<String> = sqlx::query_scalar("SELECT message_privacy FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await?;
+
+    Ok(value.as_deref().and_then(MessagePrivacy::from_str).unwrap_or_default())
+}
+
+/// Update a user's message privacy setting
+pub async fn set_message_privacy(
+    pool: &PgPool,
+    user_id: Uuid,
+    privacy: MessagePrivacy,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE users SET message_privacy = $2 WHERE id = $1")
+        .bind(user_id)
+        .bind(privacy.as_str())
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Get all of a user's privacy settings
+///
+/// Unknown users get the defaults.
+pub async fn load_privacy_settings(pool: &PgPool, user_id: Uuid) -> Result<PrivacySettings, sqlx::Error> {
+    let row = sqlx::query(
+        "SELECT message_privacy, send_typing, send_presence, send_read_receipts FROM users WHERE id = $1",
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row
+        .map(|row| PrivacySettings {
+            message_privacy: MessagePrivacy::from_str(row.get::<&str, _>("message_privacy")).unwrap_or_default(),
+            send_typing: row.get("send_typing"),
+            send_presence: row.get("send_presence"),
+            send_read_receipts: row.get("send_read_receipts"),
+        })
+        .unwrap_or_default())
+}
+
+/// Replace all of a user's privacy settings
+pub async fn save_privacy_settings(
+    pool: &PgPool,
+    user_id: Uuid,
+    settings: PrivacySettings,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        UPDATE users
+        SET message_privacy = $2, send_typing = $3, send_presence = $4, send_read_receipts = $5
+        WHERE id = $1
+        "#
+    )
+    .bind(user_id)
+    .bind(settings.message_privacy.as_str())
+    .bind(settings.send_typing)
+    .bind(settings.send_presence)
+    .bind(settings.send_read_receipts)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Whether a read receipt from `reader` should reach `sender`
+///
+/// Both have to send read receipts.
+pub async fn shares_read_receipt(pool: &PgPool, reader: Uuid, sender: Uuid) -> Result<bool, sqlx::Error> {
+    let withheld: bool = sqlx::query_scalar(
+        "SELECT EXISTS(SELECT 1 FROM users WHERE id = ANY($1) AND NOT send_read_receipts)",
+    )
+    .bind(vec![reader, sender])
+    .fetch_one(pool)
+    .await?;
+    Ok(!withheld)
+}
+
+/// Whether `other` is in `owner`'s contacts
+pub async fn is_contact(pool: &PgPool, owner: Uuid, other: Uuid) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM contacts WHERE user_id = $1 AND contact_user_id = $2)")
+        .bind(owner)
+        .bind(other)
+        .fetch_one(pool)
+        .await
+}
+
+/// Whether `to` accepts a friend request from `from`
+pub async fn allows_friend_request(pool: &PgPool, from: Uuid, to: Uuid) -> Result<bool, sqlx::Error> {
+    match get_message_privacy(pool, to).await? {
+        MessagePrivacy::ContactsOnly => is_contact(pool, to, from).await,
+        privacy => Ok(privacy.allows(false)),
+    }
+}
+
+/// Whether `sender` may post in a conversation
+///
+/// Always true once the conversation has messages; otherwise every other
+/// participant must accept the sender.
+pub async fn allows_message(pool: &PgPool, sender: Uuid, conversation_id: Uuid) -> Result<bool, sqlx::Error> {
+    let started: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM chat_messages WHERE conversation_id = $1)")
+        .bind(conversation_id)
+        .fetch_one(pool)
+        .await?;
+    if started {
+        return Ok(true);
+    }
+
+    let rows = sqlx::query(
+        r#"
+        SELECT u.message_privacy,
+               EXISTS(SELECT 1 FROM contacts c WHERE c.user_id = u.id AND c.contact_user_id = $2) AS is_contact
+        FROM conversation_participants cp
+        JOIN users u ON u.id = cp.user_id
+        WHERE cp.conversation_id = $1 AND cp.user_id <> $2
+        "#
+    )
+    .bind(conversation_id)
+    .bind(sender)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.iter().all(|row| {
+        let privacy: String = row.get("message_privacy");
+        MessagePrivacy::from_str(&privacy)
+            .unwrap_or_default()
+            .allows(row.get("is_contact"))
+    }))
+}
+
+/// GET /api/settings/privacy
+pub async fn get_privacy_settings(
+    State(db_pool): State<Option<PgPool>>,
+    headers: HeaderMap,
+) -> Result<Json<PrivacySettings>, StatusCode> {
+    let pool = db_pool.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+    let user_id = extract_user_id(&headers)?;
+
+    let settings = load_privacy_settings(pool, user_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to get privacy settings: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(settings))
+}
+
+/// PUT /api/settings/privacy
+pub async fn update_privacy_settings(
+    State(db_pool): State<Option<PgPool>>,
+    headers: HeaderMap,
+    Json(settings): Json<PrivacySettings>,
+) -> Result<Json<PrivacySettings>, StatusCode> {
+    let pool = db_pool.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+    let user_id = extract_user_id(&headers)?;
+
+    save_privacy_settings(pool, user_id, settings)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to update privacy settings: {:?}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(settings))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::Path;
+    use tokio::sync::broadcast;
+    use crate::backend::auth::users::User;
+    use crate::backend::messaging::conversation_cache::ConversationCache;
+    use crate::backend::messaging::db;
+    use crate::backend::messaging::handlers::send_friend_request;
+    use crate::backend::messaging::message_sync::{handle_conversation_typing, handle_message_read, ConversationTypingRequest};
+    use crate::backend::test_db::{auth, TestDatabase};
+    use crate::shared::messaging::{ChatMessage, SendFriendRequestRequest};
+    use crate::shared::{ActivityKind, RealtimeEvent};
+
+    async fn request_error(pool: &sqlx::PgPool, from: &User, to: &User) -> Option<String> {
+        let request = SendFriendRequestRequest {
+            to_email: to.email.clone(),
+            to_user_id: Some(to.id),
+            message: None,
+        };
+        let response = send_friend_request(State(Some(pool.clone())), auth(from), Json(request))
+            .await
+            .unwrap();
+        response.0.error
+    }
+
+    #[tokio::test]
+    async fn test_contacts_only_rejects_strangers() {
+        let db = TestDatabase::new().await;
+        let pool = db.pool().clone();
+        let me = db.user().await;
+        let friend = db.user().await;
+        let stranger = db.user().await;
+        db::create_contact(&pool, me.id, friend.id, &friend.username, &friend.email).await.unwrap();
+        db::create_contact(&pool, friend.id, me.id, &me.username, &me.email).await.unwrap();
+
+        assert_eq!(get_message_privacy(&pool, me.id).await.unwrap(), MessagePrivacy::Everyone);
+        set_message_privacy(&pool, me.id, MessagePrivacy::ContactsOnly).await.unwrap();
+        assert_eq!(get_message_privacy(&pool, me.id).await.unwrap(), MessagePrivacy::ContactsOnly);
+
+        // Friend requests
+        assert_eq!(request_error(&pool, &stranger, &me).await.as_deref(), Some(PRIVACY_REJECTED_ERROR));
+        assert!(allows_friend_request(&pool, friend.id, me.id).await.unwrap());
+
+        // First messages
+        let with_stranger = db::create_conversation(&pool, me.id, stranger.id).await.unwrap();
+        let with_friend = db::create_conversation(&pool, me.id, friend.id).await.unwrap();
+        assert!(!allows_message(&pool, stranger.id, with_stranger).await.unwrap());
+        assert!(allows_message(&pool, friend.id, with_friend).await.unwrap());
+
+        // Existing conversations carry on
+        let message = ChatMessage::new_text(with_stranger, me.id, "hi".to_string(), 1);
+        db::store_message(&pool, &message).await.unwrap();
+        assert!(allows_message(&pool, stranger.id, with_stranger).await.unwrap());
+
+        // Nobody turns away contacts too
+        set_message_privacy(&pool, me.id, MessagePrivacy::Nobody).await.unwrap();
+        assert!(!allows_friend_request(&pool, friend.id, me.id).await.unwrap());
+        assert!(!allows_message(&pool, friend.id, with_friend).await.unwrap());
+    }
+
+    /// Whether `reader` reading `message` broadcasts a receipt
+    async fn read_receipt_sent(pool: &sqlx::PgPool, reader: &User, message: &ChatMessage) -> bool {
+        let (realtime, mut events) = broadcast::channel::<RealtimeEvent>(16);
+        handle_message_read(
+            State(Some(pool.clone())),
+            State(ConversationCache::default()),
+            State(realtime),
+            Path((message.conversation_id, message.id)),
+            auth(reader),
+        )
+        .await
+        .unwrap();
+        events.try_recv().is_ok()
+    }
+
+    #[tokio::test]
+    async fn test_disabling_read_receipts_stops_sending_and_receiving() {
+        let db = TestDatabase::new().await;
+        let pool = db.pool().clone();
+        let (me, friend) = (db.user().await, db.user().await);
+        let conversation_id = db::create_conversation(&pool, me.id, friend.id).await.unwrap();
+        let mine = ChatMessage::new_text(conversation_id, me.id, "mine".to_string(), 1);
+        let theirs = ChatMessage::new_text(conversation_id, friend.id, "theirs".to_string(), 2);
+        db::store_message(&pool, &mine).await.unwrap();
+        db::store_message(&pool, &theirs).await.unwrap();
+
+        let settings = PrivacySettings { send_read_receipts: false, ..PrivacySettings::default() };
+        save_privacy_settings(&pool, me.id, settings).await.unwrap();
+        assert_eq!(load_privacy_settings(&pool, me.id).await.unwrap(), settings);
+
+        // My reads aren't sent, and my friend's reads of my messages don't reach me
+        assert!(!read_receipt_sent(&pool, &me, &theirs).await);
+        assert!(!read_receipt_sent(&pool, &friend, &mine).await);
+        let stored = db::get_conversation_message(&pool, conversation_id, mine.id).await.unwrap().unwrap();
+        assert!(!stored.is_read);
+
+        // Turning them back on restores both directions
+        save_privacy_settings(&pool, me.id, PrivacySettings::default()).await.unwrap();
+        assert!(read_receipt_sent(&pool, &me, &theirs).await);
+        assert!(read_receipt_sent(&pool, &friend, &mine).await);
+    }
+
+    #[tokio::test]
+    async fn test_typing_is_dropped_when_disabled() {
+        let db = TestDatabase::new().await;
+        let pool = db.pool().clone();
+        let (me, friend) = (db.user().await, db.user().await);
+        let conversation_id = db::create_conversation(&pool, me.id, friend.id).await.unwrap();
+        let settings = PrivacySettings { send_typing: false, ..PrivacySettings::default() };
+        save_privacy_settings(&pool, me.id, settings).await.unwrap();
+
+        for (sender, sent) in [(&me, false), (&friend, true)] {
+            let (realtime, mut events) = broadcast::channel::<RealtimeEvent>(16);
+            let request = ConversationTypingRequest {
+                user: sender.username.clone(),
+                is_typing: true,
+                kind: ActivityKind::Typing,
+            };
+            handle_conversation_typing(
+                State(Some(pool.clone())),
+                State(ConversationCache::default()),
+                State(realtime),
+                Path(conversation_id),
+                auth(sender),
+                Json(request),
+            )
+            .await
+            .unwrap();
+            assert_eq!(events.try_recv().is_ok(), sent);
+        }
+    }
+}
@@ -0,0 +1,214 @@
+//! Reconnect Storm Guard
+//!
+//! After a server restart every client reconnects at roughly the same time
+//! and each subscription replays a message snapshot from the database.
+//! This module smooths that thundering herd:
+//!
+//! - During a startup grace period each subscription waits a small
+//!   randomized delay before loading its snapshot
+//! - Snapshot replays are bounded by a semaphore at all times
+//! - Subscription (reconnect) rate is tracked for diagnostics
+//!
+//! # Configuration
+//!
+//! - `RECONNECT_GRACE_SECS` - length of the startup grace period (default 30)
+//! - `RECONNECT_MAX_JITTER_MS` - max randomized delay during grace (default 2000)
+//! - `RECONNECT_MAX_SNAPSHOTS` - concurrent snapshot replays (default 64)
+
+use std::collections::hash_map::RandomState;
+use std::collections::VecDeque;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Window used to compute the reconnect rate
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Snapshot of reconnect metrics
+#[derive(Debug, Clone, Serialize)]
+pub struct ReconnectStats {
+    /// Subscriptions accepted since startup
+    pub total_subscriptions: u64,
+    /// Subscriptions accepted during the startup grace period
+    pub grace_period_subscriptions: u64,
+    /// Subscriptions accepted in the last 60 seconds
+    pub reconnects_per_minute: usize,
+    /// Whether the server is still in its startup grace period
+    pub in_grace_period: bool,
+}
+
+struct ReconnectGuardInner {
+    started_at: Instant,
+    grace_period: Duration,
+    max_jitter: Duration,
+    snapshot_permits: Arc<Semaphore>,
+    total_subscriptions: AtomicU64,
+    grace_period_subscriptions: AtomicU64,
+    recent: Mutex<VecDeque<Instant>>,
+}
+
+/// Guards subscription snapshot replay against reconnect storms
+#[derive(Clone)]
+pub struct ReconnectGuard {
+    inner: Arc<ReconnectGuardInner>,
+}
+
+impl ReconnectGuard {
+    /// Create a guard with explicit settings
+    pub fn new(grace_period: Duration, max_jitter: Duration, max_concurrent_snapshots: usize) -> Self {
+        Self {
+            inner: Arc::new(ReconnectGuardInner {
+                started_at: Instant::now(),
+                grace_period,
+                max_jitter,
+                snapshot_permits: Arc::new(Semaphore::new(max_concurrent_snapshots.max(1))),
+                total_subscriptions: AtomicU64::new(0),
+                grace_period_subscriptions: AtomicU64::new(0),
+                recent: Mutex::new(VecDeque::new()),
+            }),
+        }
+    }
+
+    /// Create a guard configured from environment variables
+    pub fn from_env() -> Self {
+        fn env_u64(name: &str, default: u64) -> u64 {
+            std::env::var(name).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+        }
+
+        Self::new(
+            Duration::from_secs(env_u64("RECONNECT_GRACE_SECS", 30)),
+            Duration::from_millis(env_u64("RECONNECT_MAX_JITTER_MS", 2000)),
+            env_u64("RECONNECT_MAX_SNAPSHOTS", 64) as usize,
+        )
+    }
+
+    /// Whether the server is still inside its startup grace period
+    pub fn in_grace_period(&self) -> bool {
+        self.inner.started_at.elapsed() < self.inner.grace_period
+    }
+
+    /// Admit a subscription before it replays its snapshot
+    ///
+    /// Records the reconnect, applies a randomized delay during the grace
+    /// period, and waits for a snapshot permit. Hold the returned permit
+    /// while loading the snapshot and drop it before streaming live updates.
+    pub async fn admit(&self) -> Option<OwnedSemaphorePermit> {
+        let in_grace = self.in_grace_period();
+        self.record_subscription(in_grace);
+
+        if in_grace && !self.inner.max_jitter.is_zero() {
+            tokio::time::sleep(random_delay(self.inner.max_jitter)).await;
+        }
+
+        self.inner.snapshot_permits.clone().acquire_owned().await.ok()
+    }
+
+    /// Current reconnect metrics
+    pub fn stats(&self) -> ReconnectStats {
+        let mut recent = self.inner.recent.lock().unwrap();
+        prune(&mut recent, Instant::now());
+
+        ReconnectStats {
+            total_subscriptions: self.inner.total_subscriptions.load(Ordering::Relaxed),
+            grace_period_subscriptions: self.inner.grace_period_subscriptions.load(Ordering::Relaxed),
+            reconnects_per_minute: recent.len(),
+            in_grace_period: self.in_grace_period(),
+        }
+    }
+
+    /// Number of snapshot replays that may start right now
+    pub fn available_snapshot_permits(&self) -> usize {
+        self.inner.snapshot_permits.available_permits()
+    }
+
+    fn record_subscription(&self, in_grace: bool) {
+        self.inner.total_subscriptions.fetch_add(1, Ordering::Relaxed);
+        if in_grace {
+            self.inner.grace_period_subscriptions.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let now = Instant::now();
+        let mut recent = self.inner.recent.lock().unwrap();
+        recent.push_back(now);
+        prune(&mut recent, now);
+    }
+}
+
+impl Default for ReconnectGuard {
+    fn default() -> Self {
+        Self::from_env()
+    }
+}
+
+/// Drop timestamps older than the rate window
+fn prune(recent: &mut VecDeque<Instant>, now: Instant) {
+    while let Some(front) = recent.front() {
+        if now.duration_since(*front) > RATE_WINDOW {
+            recent.pop_front();
+        } else {
+            break;
+        }
+    }
+}
+
+/// Pick a uniformly-ish random delay in `0..max`
+fn random_delay(max: Duration) -> Duration {
+    let max_ms = max.as_millis().max(1) as u64;
+    let random = RandomState::new().build_hasher().finish();
+    Duration::from_millis(random % max_ms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_random_delay_within_bounds() {
+        for _ in 0..100 {
+            assert!(random_delay(Duration::from_millis(50)) < Duration::from_millis(50));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_grace_period_records_reconnects() {
+        let guard = ReconnectGuard::new(Duration::from_secs(60), Duration::ZERO, 4);
+        assert!(guard.in_grace_period());
+
+        drop(guard.admit().await);
+        drop(guard.admit().await);
+
+        let stats = guard.stats();
+        assert_eq!(stats.total_subscriptions, 2);
+        assert_eq!(stats.grace_period_subscriptions, 2);
+        assert_eq!(stats.reconnects_per_minute, 2);
+    }
+
+    #[tokio::test]
+    async fn test_burst_of_subscriptions_is_bounded() {
+        let guard = ReconnectGuard::new(Duration::from_secs(60), Duration::from_millis(20), 8);
+
+        let handles: Vec<_> = (0..500)
+            .map(|_| {
+                let guard = guard.clone();
+                tokio::spawn(async move {
+                    let permit = guard.admit().await;
+                    assert!(permit.is_some());
+                    // Never more than 8 snapshots replay at once
+                    assert!(guard.available_snapshot_permits() < 8);
+                    tokio::task::yield_now().await;
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle.await.unwrap();
+        }
+
+        assert_eq!(guard.available_snapshot_permits(), 8);
+        assert_eq!(guard.stats().total_subscriptions, 500);
+    }
+}
@@ -0,0 +1,177 @@
+//! User Search
+//!
+//! Finds people to add as friends without knowing their exact email.
+//! Usernames match anywhere, best when they start with the query; emails
+//! only match from the start. The caller, their contacts and anyone
+//! blocked in either direction never show up, and only public profiles
+//! are returned.
+
+use axum::{
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+
+use crate::shared::messaging::{avatar_url, is_searchable, SearchUsersResponse, UserProfile, MAX_USER_SEARCH_RESULTS};
+use super::handlers::extract_user_id;
+
+/// Escape `LIKE` wildcards so the query only matches literally
+fn escape_like(query: &str) -> String {
+    let mut escaped = String::with_capacity(query.len());
+    for c in query.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Users `user_id` could add whose username or email matches `query`
+pub async fn search_users(pool: &PgPool, user_id: Uuid, query: &str, limit: i64) -> Result<Vec<UserProfile>, sqlx::Error> {
+    let query = escape_like(&query.trim().to_lowercase());
+
+    let rows = sqlx::query(
+        r#"
+        SELECT u.id, u.username, u.avatar_updated_at
+        FROM users u
+        WHERE u.id <> $1
+          AND (LOWER(u.username) LIKE '%' || $2 || '%' ESCAPE '\'
+               OR LOWER(u.email) LIKE $2 || '%' ESCAPE '\')
+          AND NOT EXISTS (
+              SELECT 1 FROM contacts c WHERE c.user_id = $1 AND c.contact_user_id = u.id
+          )
+          AND NOT EXISTS (
+              SELECT 1 FROM blocked_users b
+              WHERE (b.blocker_id = $1 AND b.blocked_id = u.id)
+                 OR (b.blocker_id = u.id AND b.blocked_id = $1)
+          )
+        ORDER BY LOWER(u.username) LIKE $2 || '%' ESCAPE '\' DESC, LENGTH(u.username), u.username
+        LIMIT $3
+        "#
+    )
+    .bind(user_id)
+    .bind(&query)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .iter()
+        .map(|row| {
+            let id: Uuid = row.get("id");
+            UserProfile {
+                user_id: id,
+                username: row.get("username"),
+                avatar_url: row
+                    .get::<Option<DateTime<Utc>>, _>("avatar_updated_at")
+                    .map(|updated_at| avatar_url(id, updated_at.timestamp_millis())),
+            }
+        })
+        .collect())
+}
+
+/// Query string for a user search
+#[derive(Debug, Default, Deserialize)]
+pub struct SearchUsersParams {
+    /// Start of a username or email, or part of a username
+    #[serde(default)]
+    pub q: String,
+}
+
+/// Find users to add as friends
+/// GET /api/users/search?q=
+///
+/// Returns at most [`MAX_USER_SEARCH_RESULTS`] profiles, best match first.
+/// A query shorter than `MIN_USER_SEARCH_CHARS` returns no one.
+pub async fn search_users_handler(
+    State(db_pool): State<Option<PgPool>>,
+    headers: HeaderMap,
+    Query(params): Query<SearchUsersParams>,
+) -> Result<Json<SearchUsersResponse>, StatusCode> {
+    let pool = db_pool.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+    let user_id = extract_user_id(&headers)?;
+
+    if !is_searchable(&params.q) {
+        return Ok(Json(SearchUsersResponse::default()));
+    }
+
+    let users = search_users(pool, user_id, &params.q, MAX_USER_SEARCH_RESULTS as i64)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to search users for {}: {:?}", user_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Json(SearchUsersResponse { users }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::auth::users::User;
+    use crate::backend::messaging::{blocking, db};
+    use crate::backend::test_db::{auth, TestDatabase};
+
+    async fn search(pool: &PgPool, caller: &User, q: &str) -> Vec<Uuid> {
+        let params = Query(SearchUsersParams { q: q.to_string() });
+        let response = search_users_handler(State(Some(pool.clone())), auth(caller), params).await.unwrap();
+        response.0.users.into_iter().map(|u| u.user_id).collect()
+    }
+
+    #[tokio::test]
+    async fn test_search_matches_usernames_and_email_prefixes() {
+        let db = TestDatabase::new().await;
+        let pool = db.pool().clone();
+        let alice = db.user_named("alice").await;
+        let bob = db.user_named("bob").await;
+
+        // Username prefix, case-insensitive
+        assert!(search(&pool, &alice, "BOB_").await.contains(&bob.id));
+        // Part of a username
+        assert!(search(&pool, &alice, &bob.username[4..]).await.contains(&bob.id));
+        // Email prefix
+        assert!(search(&pool, &alice, &bob.email).await.contains(&bob.id));
+        // Wildcards are matched literally
+        assert!(search(&pool, &alice, "%%").await.is_empty());
+        // Too short to search
+        assert!(search(&pool, &alice, "b").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_search_leaves_out_self_contacts_and_blocked_users() {
+        let db = TestDatabase::new().await;
+        let pool = db.pool().clone();
+        let alice = db.user_named("alice").await;
+        let (bob, carol, dave, erin) = (
+            db.user_named("buddy").await,
+            db.user_named("buddy").await,
+            db.user_named("buddy").await,
+            db.user_named("buddy").await,
+        );
+        db::create_contact(&pool, alice.id, bob.id, &bob.username, &bob.email).await.unwrap();
+        blocking::block_user(&pool, alice.id, carol.id).await.unwrap();
+        blocking::block_user(&pool, dave.id, alice.id).await.unwrap();
+
+        let found = search(&pool, &alice, "buddy_").await;
+        assert!(found.contains(&erin.id));
+        assert!(!found.contains(&bob.id));
+        assert!(!found.contains(&carol.id));
+        assert!(!found.contains(&dave.id));
+        assert!(!search(&pool, &alice, &alice.username).await.contains(&alice.id));
+    }
+
+    #[tokio::test]
+    async fn test_search_requires_authentication() {
+        let db = TestDatabase::new().await;
+        let params = Query(SearchUsersParams { q: "alice".to_string() });
+        let status = search_users_handler(State(Some(db.pool().clone())), HeaderMap::new(), params)
+            .await
+            .unwrap_err();
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+}
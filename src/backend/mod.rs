@@ -30,6 +30,8 @@
 //! - **`realtime`** - Generic real-time event broadcasting system
 //! - **`subscription`** - Usage limit checking and tracking
 //! - **`middleware`** - Request processing middleware
+//! - **`attachments`** - Attachment upload/download with fs or S3 storage
+//! - **`ai`** - AI message actions (translation) behind a pluggable provider
 //! - **`error`** - Backend-specific error types
 //!
 //! # Module Structure
@@ -161,6 +163,18 @@ pub mod collab;
 #[cfg(feature = "ssr")]
 pub mod messaging;
 
+/// Message attachments and storage backends
+#[cfg(feature = "ssr")]
+pub mod attachments;
+
+/// AI providers, quotas and endpoints
+#[cfg(feature = "ssr")]
+pub mod ai;
+
+/// Postgres fixture for handler tests
+#[cfg(all(test, feature = "ssr"))]
+pub(crate) mod test_db;
+
 /// Re-export commonly used types
 #[cfg(feature = "ssr")]
 pub use server::create_app;
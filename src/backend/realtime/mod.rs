@@ -10,6 +10,8 @@
 //!
 //! - **`broadcast`** - Event broadcasting utilities and type definitions
 //! - **`subscription`** - Server-Sent Events subscription handler
+//! - **`persistence`** - Stores durable events as they're broadcast; refuses ephemeral ones
+//! - **`websocket`** - WebSocket alternative to the SSE streams
 //!
 //! # Module Structure
 //!
@@ -17,14 +19,19 @@
 //! realtime/
 //! ├── mod.rs          - Module exports and documentation
 //! ├── broadcast.rs    - Event broadcasting utilities
-//! └── subscription.rs - SSE subscription handler
+//! ├── persistence.rs  - Durable event storage
+//! ├── subscription.rs - SSE subscription handler
+//! └── websocket.rs    - WebSocket handlers (`/ws`, conversation sockets)
 //! ```
 //!
 //! # Real-time System
 //!
 //! The real-time system uses Server-Sent Events (SSE) to provide one-way
 //! communication from server to client. This is simpler than WebSockets
-//! for one-way communication and works well with HTTP/2.
+//! for one-way communication and works well with HTTP/2, so it is the
+//! default. Clients that want a single connection in both directions can
+//! use `GET /ws` instead, which carries the same events and also accepts
+//! typing and receipt updates.
 //!
 //! # Event Types
 //!
@@ -33,8 +40,18 @@
 //! - `Notification` - User notifications
 //! - `Status` - Status updates
 //! - `Typing` - Typing indicators
+//! - `ReadReceipt` - A participant read a message
+//! - `DeliveryReceipt` - A message reached a participant's device
+//! - `Presence` - A user came online or went offline
+//! - `Conversation` - A conversation changed for one user; only sent to
+//!   that user, on `GET /sync/conversations`
 //! - `Custom` - Custom event types
 //!
+//! Only `Message` and `Notification` events are durable; the rest are
+//! ephemeral (`EventType::is_ephemeral`) and are never written to the
+//! database. With a database, every durable event broadcast is stored in
+//! `realtime_events`.
+//!
 //! # Event Filtering
 //!
 //! Clients can filter events by type using the `types` query parameter:
@@ -70,8 +87,18 @@ pub mod broadcast;
 /// Server-Sent Events subscription handler
 pub mod subscription;
 
+/// Durable event storage
+#[cfg(feature = "ssr")]
+pub mod persistence;
+
+/// WebSocket handlers
+#[cfg(feature = "ssr")]
+pub mod websocket;
+
 // Re-export commonly used types and functions
 pub use broadcast::{RealtimeEventBroadcast, broadcast_event};
 #[cfg(feature = "ssr")]
 pub use subscription::handle_realtime_subscription;
+#[cfg(feature = "ssr")]
+pub use websocket::{handle_conversation_socket, handle_realtime_socket};
 
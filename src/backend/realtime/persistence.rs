@@ -0,0 +1,137 @@
+//! Realtime Event Persistence
+//!
+//! Stores durable realtime events (messages, notifications) so they can be
+//! replayed. [`spawn_event_persister`] saves them as they are broadcast.
+//! Ephemeral events - typing, presence, receipts - must never reach the
+//! database: the persister skips them and [`save_event`] refuses them.
+
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::backend::realtime::broadcast::RealtimeEventBroadcast;
+use crate::shared::RealtimeEvent;
+
+/// Fail if `event` is ephemeral and so must not be stored
+pub fn ensure_persistable(event: &RealtimeEvent) -> Result<(), sqlx::Error> {
+    if event.is_ephemeral() {
+        return Err(sqlx::Error::Protocol(format!(
+            "refusing to persist ephemeral {:?} event",
+            event.event_type
+        )));
+    }
+    Ok(())
+}
+
+/// Store a durable realtime event
+///
+/// Ephemeral events are rejected without touching the database.
+pub async fn save_event(pool: &PgPool, event: &RealtimeEvent) -> Result<(), sqlx::Error> {
+    ensure_persistable(event)?;
+
+    let event_type = serde_json::to_value(&event.event_type)
+        .map_err(|e| sqlx::Error::Decode(format!("Failed to encode event type: {}", e).into()))?;
+    let occurred_at = event.timestamp.parse::<DateTime<Utc>>().unwrap_or_else(|_| Utc::now());
+
+    sqlx::query(
+        r#"
+        INSERT INTO realtime_events (event_type, payload, version, occurred_at)
+        VALUES ($1, $2, $3, $4)
+        "#
+    )
+    .bind(event_type.as_str().map(str::to_string).unwrap_or_else(|| event_type.to_string()))
+    .bind(&event.payload)
+    .bind(&event.version)
+    .bind(occurred_at)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Store every durable event broadcast on `realtime` for the life of the server
+///
+/// Subscribes before returning, so nothing broadcast afterwards is missed.
+pub fn spawn_event_persister(pool: PgPool, realtime: &RealtimeEventBroadcast) {
+    let mut events = realtime.subscribe();
+    tokio::spawn(async move {
+        loop {
+            match events.recv().await {
+                Ok(event) if event.is_ephemeral() => {}
+                Ok(event) => {
+                    if let Err(e) = save_event(&pool, &event).await {
+                        tracing::warn!("[Realtime] Failed to persist {:?} event: {:?}", event.event_type, e);
+                    }
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!("[Realtime] Persistence fell behind, {} events not stored", skipped);
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::realtime::broadcast::broadcast_event;
+    use crate::backend::test_db::TestDatabase;
+    use crate::shared::PresenceEvent;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_ephemeral_events_are_not_persistable() {
+        let typing = RealtimeEvent::typing("user1".to_string(), true);
+        assert!(ensure_persistable(&typing).is_err());
+
+        let presence = RealtimeEvent::presence(PresenceEvent {
+            user_id: uuid::Uuid::new_v4(),
+            is_online: true,
+            last_seen: "2024-01-01T12:00:00Z".to_string(),
+        });
+        assert!(ensure_persistable(&presence).is_err());
+
+        let message = RealtimeEvent::message(serde_json::json!({"text": "Hello"}));
+        assert!(ensure_persistable(&message).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_saving_an_ephemeral_event_fails() {
+        // Never connects: the event is refused first
+        let pool = PgPool::connect_lazy("postgres://localhost/unused").unwrap();
+        let result = save_event(&pool, &RealtimeEvent::typing("user1".to_string(), true)).await;
+        assert!(matches!(result, Err(sqlx::Error::Protocol(_))));
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_durable_events_are_stored() {
+        let db = TestDatabase::new().await;
+        let pool = db.pool().clone();
+        let (realtime, _) = tokio::sync::broadcast::channel::<RealtimeEvent>(100);
+        spawn_event_persister(pool.clone(), &realtime);
+
+        let marker = Uuid::new_v4().to_string();
+        broadcast_event(&realtime, RealtimeEvent::typing(marker.clone(), true)).await;
+        broadcast_event(&realtime, RealtimeEvent::message(serde_json::json!({"text": marker}))).await;
+
+        // Events are handled in order, so once the message is in the typing event was skipped
+        let stored = async {
+            loop {
+                let rows: Vec<(String,)> = sqlx::query_as(
+                    "SELECT event_type FROM realtime_events WHERE payload::text LIKE '%' || $1 || '%'",
+                )
+                .bind(&marker)
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+                if !rows.is_empty() {
+                    return rows;
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            }
+        };
+        let rows = tokio::time::timeout(std::time::Duration::from_secs(5), stored).await.unwrap();
+        assert_eq!(rows, vec![("message".to_string(),)]);
+    }
+}
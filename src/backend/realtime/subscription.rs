@@ -22,11 +22,15 @@
  * 
  * - Connections are kept alive using SSE keep-alive mechanism
  * - Clients can reconnect using `Last-Event-ID` header
- * - Lagged events are logged but don't cause connection drops
+ * - Lagged events are logged and counted (see `server::lag`) but don't cause connection drops
+ * - On server shutdown the stream ends with a `: server shutting down`
+ *   comment, and clients reconnect
  */
 
 use crate::shared::EventType;
 use crate::backend::realtime::broadcast::RealtimeEventBroadcast;
+use crate::backend::server::shutdown::Shutdown;
+use crate::backend::server::lag::{BroadcastChannel, BroadcastLag};
 use axum::{
     extract::State,
     http::StatusCode,
@@ -83,6 +87,8 @@ use std::collections::HashMap;
 #[cfg(feature = "ssr")]
 pub async fn handle_realtime_subscription(
     State(broadcast_tx): State<RealtimeEventBroadcast>,
+    State(lag): State<BroadcastLag>,
+    State(shutdown): State<Shutdown>,
     headers: axum::http::HeaderMap,
     query: axum::extract::Query<HashMap<String, String>>,
 ) -> Result<Sse<impl tokio_stream::Stream<Item = Result<Event, axum::Error>>>, StatusCode> {
@@ -95,25 +101,7 @@ pub async fn handle_realtime_subscription(
     }
     
     // Parse event types filter from query parameters
-    let event_types_filter: Option<Vec<EventType>> = query
-        .get("types")
-        .map(|types_str| {
-            types_str
-                .split(',')
-                .map(|s| s.trim())
-                .filter_map(|s| {
-                    match s.to_lowercase().as_str() {
-                        "message" => Some(EventType::Message),
-                        "notification" => Some(EventType::Notification),
-                        "status" => Some(EventType::Status),
-                        "typing" => Some(EventType::Typing),
-                        custom if !custom.is_empty() => Some(EventType::Custom(custom.to_string())),
-                        _ => None,
-                    }
-                })
-                .collect()
-        })
-        .filter(|v: &Vec<_>| !v.is_empty());
+    let event_types_filter = query.get("types").and_then(|types| EventType::parse_list(types));
     
     if let Some(ref types) = event_types_filter {
         tracing::info!("[Realtime] Filtering events by types: {:?}", types);
@@ -133,8 +121,8 @@ pub async fn handle_realtime_subscription(
     // Axum's keep-alive mechanism will automatically inject comment lines (":")
     // to maintain the connection, so we don't need to send empty data events
     let stream = stream::unfold(
-        (broadcast_rx, filter),
-        move |(mut rx, filter)| async move {
+        (broadcast_rx, filter, lag),
+        move |(mut rx, filter, lag)| async move {
             // Loop until we get a meaningful event that passes the filter
             loop {
                 match rx.recv().await {
@@ -159,13 +147,7 @@ pub async fn handle_realtime_subscription(
                         };
                         
                         // Create SSE event with event type as the event name
-                        let event_name = match &event.event_type {
-                            EventType::Message => "message",
-                            EventType::Notification => "notification",
-                            EventType::Status => "status",
-                            EventType::Typing => "typing",
-                            EventType::Custom(name) => name.as_str(),
-                        };
+                        let event_name = event.event_type.name();
                         
                         tracing::info!("[Realtime] Broadcasting event: {} to subscriber", event_name);
                         
@@ -173,10 +155,10 @@ pub async fn handle_realtime_subscription(
                             .event(event_name)
                             .data(event_data);
                         
-                        return Some((Ok(sse_event), (rx, filter)));
+                        return Some((Ok(sse_event), (rx, filter, lag)));
                     }
                     Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
-                        tracing::warn!("[Realtime] Receiver lagged, skipped {} events", skipped);
+                        lag.record(BroadcastChannel::Realtime, &"realtime SSE", skipped, rx.len());
                         // Continue looping - we'll catch up on next event
                         continue;
                     }
@@ -190,7 +172,7 @@ pub async fn handle_realtime_subscription(
     );
     
     // Create SSE response with keep-alive
-    let sse = Sse::new(stream)
+    let sse = Sse::new(shutdown.close_sse(stream))
         .keep_alive(axum::response::sse::KeepAlive::default());
     
     Ok(sse)
@@ -0,0 +1,290 @@
+//! WebSocket Transport
+//!
+//! An alternative to the SSE streams for clients that want one connection
+//! in both directions. Frames are the JSON [`ServerFrame`]s and
+//! [`ClientFrame`]s from `shared::socket`.
+//!
+//! - `GET /ws` carries the same events as `GET /realtime` (filtered by
+//!   `?types=`).
+//! - `GET /sync/conversations/{conversation_id}/ws` carries what
+//!   `GET /sync/conversations/{conversation_id}/messages` does: a snapshot
+//!   (or the delta after a `Parents` header), live messages and the
+//!   conversation's typing and receipt events, narrowed by `?types=`.
+//!
+//! Both accept typing, read and delivered frames, handled exactly like the
+//! matching POST/PUT, and answer `ping` frames with a `pong`. A refused
+//! frame gets an `error` frame back and the connection stays open. On
+//! server shutdown sockets are closed with code 1001 (going away). SSE
+//! remains the default transport.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use axum::{
+    extract::{
+        ws::{close_code, CloseFrame, Message, WebSocket, WebSocketUpgrade},
+        Extension, Path, Query, State,
+    },
+    http::{HeaderMap, StatusCode},
+    response::Response,
+};
+use bytes::Bytes;
+use futures_util::{SinkExt, StreamExt};
+use sqlx::PgPool;
+use tokio::sync::broadcast::{self, error::RecvError};
+use uuid::Uuid;
+
+use crate::backend::messaging::conversation_cache::ConversationCache;
+use crate::backend::messaging::message_sync::{
+    extract_user_id, load_subscription_snapshot, mark_delivered, mark_read, parents_version, send_typing, wants_type,
+    ConversationTypingRequest, SubscriptionParams,
+};
+use crate::backend::messaging::reconnect_guard::ReconnectGuard;
+use crate::backend::realtime::broadcast::RealtimeEventBroadcast;
+use crate::backend::server::capacity::SubscriptionPermit;
+use crate::backend::server::lag::{BroadcastChannel, BroadcastLag};
+use crate::backend::server::shutdown::{Shutdown, SHUTDOWN_COMMENT};
+use crate::backend::server::state::MessagingBroadcastState;
+use crate::shared::messaging::ChatMessage;
+use crate::shared::{ClientFrame, EventType, RealtimeEvent, ServerFrame};
+
+/// How often the server pings an idle socket so proxies keep it open
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+
+/// One authenticated socket and what it is allowed to see
+struct Session {
+    db_pool: Option<PgPool>,
+    conversations: ConversationCache,
+    realtime: RealtimeEventBroadcast,
+    user_id: Uuid,
+    /// Only forward events about this conversation
+    conversation_id: Option<Uuid>,
+    /// Event types to forward; every type if `None`
+    types: Option<Vec<EventType>>,
+}
+
+impl Session {
+    fn wants(&self, event: &RealtimeEvent) -> bool {
+        if !wants_type(self.types.as_deref(), &event.event_type) {
+            return false;
+        }
+        // A conversation's messages come from its own channel
+        match self.conversation_id {
+            Some(conversation_id) => {
+                event.event_type != EventType::Message && event.conversation_id() == Some(conversation_id)
+            }
+            None => true,
+        }
+    }
+
+    /// Apply a client frame; the error frame to send back if it was refused
+    async fn handle(&self, text: &str) -> Option<ServerFrame> {
+        let frame = match serde_json::from_str::<ClientFrame>(text) {
+            Ok(frame) => frame,
+            Err(e) => return Some(ServerFrame::Error { message: format!("invalid frame: {}", e) }),
+        };
+        // Answered from the same loop that forwards broadcasts, so a client
+        // that stops getting pongs knows the connection is stuck
+        if let ClientFrame::Ping { id } = frame {
+            return Some(ServerFrame::Pong { id });
+        }
+        let Some(pool) = self.db_pool.as_ref() else {
+            return Some(ServerFrame::Error { message: StatusCode::SERVICE_UNAVAILABLE.to_string() });
+        };
+
+        let result = match frame {
+            ClientFrame::Typing { conversation_id, user, is_typing, kind } => {
+                let request = ConversationTypingRequest { user, is_typing, kind };
+                send_typing(pool, &self.conversations, &self.realtime, self.user_id, conversation_id, request).await
+            }
+            ClientFrame::Read { conversation_id, message_id } => {
+                mark_read(pool, &self.conversations, &self.realtime, self.user_id, conversation_id, message_id).await
+            }
+            ClientFrame::Delivered { conversation_id, message_id } => {
+                mark_delivered(pool, &self.conversations, &self.realtime, self.user_id, conversation_id, message_id).await
+            }
+            ClientFrame::Ping { .. } => Ok(()),
+        };
+        result.err().map(|status| ServerFrame::Error { message: status.to_string() })
+    }
+}
+
+/// Open a realtime socket
+/// GET /ws?types=
+///
+/// Same events as `GET /realtime`, plus inbound typing and receipt frames.
+#[allow(clippy::too_many_arguments)]
+pub async fn handle_realtime_socket(
+    State(db_pool): State<Option<PgPool>>,
+    State(conversations): State<ConversationCache>,
+    State(realtime): State<RealtimeEventBroadcast>,
+    State(lag): State<BroadcastLag>,
+    State(shutdown): State<Shutdown>,
+    Query(query): Query<HashMap<String, String>>,
+    permit: Option<Extension<SubscriptionPermit>>,
+    headers: HeaderMap,
+    ws: WebSocketUpgrade,
+) -> Result<Response, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+    let types = query.get("types").and_then(|types| EventType::parse_list(types));
+    tracing::info!("[Realtime] WebSocket opened by {} for types {:?}", user_id, types);
+
+    let events = realtime.subscribe();
+    let session = Session { db_pool, conversations, realtime, user_id, conversation_id: None, types };
+    Ok(ws.on_upgrade(move |socket| async move {
+        // The capacity slot is held until the socket closes
+        let _permit = permit;
+        serve(socket, session, lag, shutdown, Vec::new(), None, Some(events)).await
+    }))
+}
+
+/// Open a socket for one conversation's messages
+/// GET /sync/conversations/{conversation_id}/ws?snapshot_limit=&types=
+///
+/// The WebSocket counterpart of the Braid subscription; the snapshot is
+/// loaded (and participation checked) before upgrading, so a 403 is an
+/// ordinary HTTP response.
+#[allow(clippy::too_many_arguments)]
+pub async fn handle_conversation_socket(
+    State(db_pool): State<Option<PgPool>>,
+    State(conversations): State<ConversationCache>,
+    State(broadcast_state): State<MessagingBroadcastState>,
+    State(reconnect_guard): State<ReconnectGuard>,
+    State(realtime): State<RealtimeEventBroadcast>,
+    State(lag): State<BroadcastLag>,
+    State(shutdown): State<Shutdown>,
+    Path(conversation_id): Path<Uuid>,
+    Query(params): Query<SubscriptionParams>,
+    permit: Option<Extension<SubscriptionPermit>>,
+    headers: HeaderMap,
+    ws: WebSocketUpgrade,
+) -> Result<Response, StatusCode> {
+    let user_id = extract_user_id(&headers)?;
+
+    let snapshot = load_subscription_snapshot(
+        db_pool.as_ref(),
+        &conversations,
+        &reconnect_guard,
+        user_id,
+        conversation_id,
+        parents_version(&headers),
+        params.snapshot_limit,
+    )
+    .await?;
+
+    let types = params.event_types();
+    let (snapshot, messages) = if wants_type(types.as_deref(), &EventType::Message) {
+        (snapshot, Some(broadcast_state.get_sender(conversation_id).subscribe()))
+    } else {
+        (Vec::new(), None)
+    };
+    let events = types
+        .as_ref()
+        .is_none_or(|types| types.iter().any(|t| *t != EventType::Message))
+        .then(|| realtime.subscribe());
+    tracing::debug!("[MessageSync] WebSocket opened by {} for conversation {}", user_id, conversation_id);
+
+    let session = Session {
+        db_pool,
+        conversations,
+        realtime,
+        user_id,
+        conversation_id: Some(conversation_id),
+        types,
+    };
+    Ok(ws.on_upgrade(move |socket| async move {
+        let _permit = permit;
+        serve(socket, session, lag, shutdown, snapshot, messages, events).await
+    }))
+}
+
+/// Next item from an optional broadcast; never resolves without one
+async fn recv<T: Clone>(rx: &mut Option<broadcast::Receiver<T>>) -> Result<T, RecvError> {
+    match rx {
+        Some(rx) => rx.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+fn text(frame: &ServerFrame) -> Option<Message> {
+    match serde_json::to_string(frame) {
+        Ok(json) => Some(Message::Text(json.into())),
+        Err(e) => {
+            tracing::error!("[Realtime] Failed to serialize socket frame: {:?}", e);
+            None
+        }
+    }
+}
+
+/// Pump a socket until either side closes it or the server shuts down
+async fn serve(
+    socket: WebSocket,
+    session: Session,
+    lag: BroadcastLag,
+    shutdown: Shutdown,
+    snapshot: Vec<ChatMessage>,
+    mut messages: Option<broadcast::Receiver<ChatMessage>>,
+    mut events: Option<broadcast::Receiver<RealtimeEvent>>,
+) {
+    let (mut sink, mut inbound) = socket.split();
+
+    for message in snapshot {
+        if let Some(frame) = text(&ServerFrame::Message(message)) {
+            if sink.send(frame).await.is_err() {
+                return;
+            }
+        }
+    }
+
+    let mut ping = tokio::time::interval(PING_INTERVAL);
+    ping.tick().await;
+
+    loop {
+        let outgoing = tokio::select! {
+            received = inbound.next() => match received {
+                Some(Ok(Message::Text(frame))) => session.handle(frame.as_str()).await,
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                // Pings are answered by the socket itself
+                Some(Ok(_)) => None,
+            },
+            message = recv(&mut messages) => match message {
+                Ok(message) => Some(ServerFrame::Message(message)),
+                Err(RecvError::Lagged(skipped)) => {
+                    let queued = messages.as_ref().map_or(0, |rx| rx.len());
+                    lag.record(BroadcastChannel::Conversation, &session.user_id, skipped, queued);
+                    None
+                }
+                Err(RecvError::Closed) => break,
+            },
+            event = recv(&mut events) => match event {
+                Ok(event) if session.wants(&event) => Some(ServerFrame::Event(event)),
+                Ok(_) => None,
+                Err(RecvError::Lagged(skipped)) => {
+                    let queued = events.as_ref().map_or(0, |rx| rx.len());
+                    lag.record(BroadcastChannel::Realtime, &session.user_id, skipped, queued);
+                    None
+                }
+                Err(RecvError::Closed) => break,
+            },
+            _ = shutdown.wait() => {
+                let close = CloseFrame { code: close_code::AWAY, reason: SHUTDOWN_COMMENT.into() };
+                let _ = sink.send(Message::Close(Some(close))).await;
+                break;
+            }
+            _ = ping.tick() => {
+                if sink.send(Message::Ping(Bytes::new())).await.is_err() {
+                    break;
+                }
+                None
+            }
+        };
+
+        if let Some(frame) = outgoing.as_ref().and_then(text) {
+            if sink.send(frame).await.is_err() {
+                break;
+            }
+        }
+    }
+
+    tracing::debug!("[Realtime] WebSocket closed for {}", session.user_id);
+}
@@ -11,26 +11,99 @@
  * - `POST /api/auth/signup` - User registration
  * - `POST /api/auth/login` - User login
  * - `GET /api/auth/me` - Get current user info
+ * - `POST /api/auth/refresh` - Exchange a token for a fresh one
  * 
  * ## Usage
  * - `GET /api/usage` - Get usage statistics (requires authentication)
+ *
+ * ## Limits
+ * - `GET /api/limits` - Message text length and total size limits
+ *
+ * ## Metrics
+ * - `GET /api/metrics` - Reconnect, pool, capacity and lag metrics as JSON
+ * - `GET /metrics` - Prometheus text format
+ *
+ * ## Health
+ * - `GET /healthz` - Liveness, with the build version
+ * - `GET /readyz` - Readiness, 503 while the database is unreachable
+ *
+ * ## Telemetry
+ * - `POST /api/telemetry/connection` - Report how a client reconnected (opt-in)
+ *
+ * ## Settings
+ * - `GET/PUT /api/settings/privacy` - Who can message the user
+ *
+ * ## Users
+ * - `POST /api/users/avatar` - Upload the caller's avatar (multipart)
+ * - `GET /api/users/{user_id}/avatar` - Download a user's avatar
+ * - `GET /api/users/search?q=` - Find users to add as friends
+ * - `GET /api/users/blocked` - Users the caller has blocked
+ * - `POST /api/users/{user_id}/block` - Block a user
+ * - `DELETE /api/users/{user_id}/block` - Unblock a user
+ *
+ * ## Messages
+ * - `GET /sync/conversations/{conversation_id}/ws` - Conversation messages over a WebSocket (`snapshot_limit`, `types`)
+ * - `GET /sync/ping` - Heartbeat for clients checking the server still answers
+ * - `GET /sync/conversations/{conversation_id}/messages/history` - Page older messages (`limit`, `before` cursor)
+ * - `GET /sync/conversations/{conversation_id}/messages/since?version=` - Messages missed since a version, for reconnects
+ * - `GET /sync/conversations/{conversation_id}/messages/{message_id}/history` - Versions replaced by edits
+ *
+ * ## Presence
+ * - `POST /api/presence/heartbeat` - Mark the caller online
+ * - `GET /api/presence/batch?user_ids=` - Current presence of some of the caller's contacts
+ * - `GET /sync/presence` - Stream the statuses of the caller's contacts
+ * - `PUT /sync/presence` - Set the caller's status and status text
+ * - `GET /sync/conversations` - Stream conversations created, changed or left for the caller
+ *
+ * ## AI
+ * - `POST /api/ai/translate` - Translate a message for the caller
+ * - `POST /api/ai/summarize` - Stream a summary of a conversation's messages
  */
 
 use axum::Router;
 #[cfg(feature = "ssr")]
 use crate::backend::server::state::AppState;
 #[cfg(feature = "ssr")]
-use crate::backend::auth::{signup, login, get_me};
+use crate::backend::auth::{signup, login, get_me, refresh};
 #[cfg(feature = "ssr")]
 use crate::backend::subscription::api::get_usage_stats;
 #[cfg(feature = "ssr")]
 use crate::backend::messaging::handlers::{
     send_friend_request, get_friend_requests, respond_to_friend_request, get_contacts,
-    get_conversations, get_messages, mark_message_read,
+    get_conversations, get_messages, mark_message_read, mark_conversation_unread,
+    set_conversation_theme, search_messages, set_conversation_pinned, reorder_pinned_conversations,
+    create_conversation, add_conversation_participant, remove_conversation_participant,
 };
 #[cfg(feature = "ssr")]
+use crate::backend::attachments::{upload_attachment, download_attachment, upload_avatar, download_avatar};
+#[cfg(feature = "ssr")]
+use crate::backend::messaging::limits::get_limits;
+#[cfg(feature = "ssr")]
+use crate::backend::server::pool::get_metrics;
+#[cfg(feature = "ssr")]
+use crate::backend::server::metrics::get_prometheus_metrics;
+#[cfg(feature = "ssr")]
+use crate::backend::server::health::{get_healthz, get_readyz};
+#[cfg(feature = "ssr")]
+use crate::backend::server::telemetry::post_connection_telemetry;
+#[cfg(feature = "ssr")]
+use crate::backend::messaging::privacy::{get_privacy_settings, update_privacy_settings};
+#[cfg(feature = "ssr")]
+use crate::backend::messaging::blocking::{block_user_handler, list_blocked_users, unblock_user_handler};
+#[cfg(feature = "ssr")]
+use crate::backend::messaging::user_search::search_users_handler;
+#[cfg(feature = "ssr")]
+use crate::backend::messaging::presence::{presence_heartbeat, presence_batch, handle_presence_subscription, handle_status_put};
+#[cfg(feature = "ssr")]
+use crate::backend::messaging::conversation_events::handle_conversation_subscription;
+#[cfg(feature = "ssr")]
+use crate::backend::ai::handlers::{translate_message, summarize_conversation};
+#[cfg(feature = "ssr")]
+use crate::backend::realtime::websocket::handle_conversation_socket;
+#[cfg(feature = "ssr")]
 use crate::backend::messaging::message_sync::{
-    handle_message_subscription, handle_message_put,
+    handle_message_subscription, handle_message_put, handle_message_delete, handle_message_read, handle_message_delivered,
+    handle_conversation_typing, handle_edit_history, handle_message_history, handle_messages_since, handle_ping,
 };
 
 /// Configure API routes
@@ -41,10 +114,14 @@ use crate::backend::messaging::message_sync::{
 /// - `POST /api/auth/signup` - User registration
 /// - `POST /api/auth/login` - User login
 /// - `GET /api/auth/me` - Get current user info (requires authentication)
+/// - `POST /api/auth/refresh` - Issue a fresh token (requires a valid or recently expired token)
 /// 
 /// ## Usage Routes
 /// - `GET /api/usage` - Get usage statistics (requires authentication)
 /// 
+/// ## Limits Routes
+/// - `GET /api/limits` - Message size limits (public)
+/// 
 /// # Arguments
 /// 
 /// * `router` - The router to add routes to
@@ -78,11 +155,49 @@ pub fn configure_api_routes(router: Router<AppState>) -> Router<AppState> {
             "/api/auth/me",
             axum::routing::get(get_me),
         )
+        .route(
+            "/api/auth/refresh",
+            axum::routing::post(refresh),
+        )
         // Usage statistics endpoint (requires authentication - checked in handler)
         .route(
             "/api/usage",
             axum::routing::get(get_usage_stats),
         )
+        // Message size limits, so clients can check before sending
+        .route(
+            "/api/limits",
+            axum::routing::get(get_limits),
+        )
+        // Reconnect, database pool and subscription capacity metrics
+        .route(
+            "/api/metrics",
+            axum::routing::get(get_metrics),
+        )
+        // The same for Prometheus scrapers
+        .route(
+            "/metrics",
+            axum::routing::get(get_prometheus_metrics),
+        )
+        // Probes for container orchestrators (no authentication)
+        .route(
+            "/healthz",
+            axum::routing::get(get_healthz),
+        )
+        .route(
+            "/readyz",
+            axum::routing::get(get_readyz),
+        )
+        // Opt-in client reconnection reports (requires authentication - checked in handler)
+        .route(
+            "/api/telemetry/connection",
+            axum::routing::post(post_connection_telemetry),
+        )
+        // Privacy settings (requires authentication - checked in handler)
+        .route(
+            "/api/settings/privacy",
+            axum::routing::get(get_privacy_settings).put(update_privacy_settings),
+        )
         // Friend request endpoints
         .route(
             "/api/friends/request",
@@ -104,7 +219,27 @@ pub fn configure_api_routes(router: Router<AppState>) -> Router<AppState> {
         // Conversations endpoints
         .route(
             "/api/conversations",
-            axum::routing::get(get_conversations),
+            axum::routing::get(get_conversations).post(create_conversation),
+        )
+        .route(
+            "/api/conversations/{conversation_id}/participants/{user_id}",
+            axum::routing::put(add_conversation_participant).delete(remove_conversation_participant),
+        )
+        .route(
+            "/api/conversations/{conversation_id}/unread",
+            axum::routing::put(mark_conversation_unread),
+        )
+        .route(
+            "/api/conversations/{conversation_id}/theme",
+            axum::routing::put(set_conversation_theme),
+        )
+        .route(
+            "/api/conversations/{conversation_id}/pin",
+            axum::routing::put(set_conversation_pinned),
+        )
+        .route(
+            "/api/conversations/pinned",
+            axum::routing::put(reorder_pinned_conversations),
         )
         // Messages endpoints
         .route(
@@ -115,14 +250,108 @@ pub fn configure_api_routes(router: Router<AppState>) -> Router<AppState> {
             "/api/messages/{message_id}/read",
             axum::routing::patch(mark_message_read),
         )
+        .route(
+            "/api/messages/search",
+            axum::routing::get(search_messages),
+        )
+        // Attachment endpoints
+        .route(
+            "/api/attachments",
+            axum::routing::post(upload_attachment),
+        )
+        .route(
+            "/api/attachments/{attachment_id}",
+            axum::routing::get(download_attachment),
+        )
+        // Presence endpoints
+        .route(
+            "/api/presence/heartbeat",
+            axum::routing::post(presence_heartbeat),
+        )
+        .route(
+            "/api/presence/batch",
+            axum::routing::get(presence_batch),
+        )
+        // AI endpoints
+        .route(
+            "/api/ai/translate",
+            axum::routing::post(translate_message),
+        )
+        .route(
+            "/api/ai/summarize",
+            axum::routing::post(summarize_conversation),
+        )
+        // Avatar endpoints
+        .route(
+            "/api/users/avatar",
+            axum::routing::post(upload_avatar),
+        )
+        .route(
+            "/api/users/{user_id}/avatar",
+            axum::routing::get(download_avatar),
+        )
+        // User search, for adding friends
+        .route(
+            "/api/users/search",
+            axum::routing::get(search_users_handler),
+        )
+        // Blocking endpoints
+        .route(
+            "/api/users/blocked",
+            axum::routing::get(list_blocked_users),
+        )
+        .route(
+            "/api/users/{user_id}/block",
+            axum::routing::post(block_user_handler).delete(unblock_user_handler),
+        )
         // Message sync endpoints (Braid-HTTP)
         .route(
             "/sync/conversations/{conversation_id}/messages",
             axum::routing::get(handle_message_subscription),
         )
+        .route(
+            "/sync/ping",
+            axum::routing::get(handle_ping),
+        )
+        .route(
+            "/sync/conversations/{conversation_id}/ws",
+            axum::routing::get(handle_conversation_socket),
+        )
+        .route(
+            "/sync/conversations/{conversation_id}/messages/history",
+            axum::routing::get(handle_message_history),
+        )
+        .route(
+            "/sync/conversations/{conversation_id}/messages/since",
+            axum::routing::get(handle_messages_since),
+        )
         .route(
             "/sync/conversations/{conversation_id}/messages/{message_id}",
-            axum::routing::put(handle_message_put),
+            axum::routing::put(handle_message_put).delete(handle_message_delete),
+        )
+        .route(
+            "/sync/conversations/{conversation_id}/messages/{message_id}/history",
+            axum::routing::get(handle_edit_history),
+        )
+        .route(
+            "/sync/conversations/{conversation_id}/messages/{message_id}/read",
+            axum::routing::put(handle_message_read),
+        )
+        .route(
+            "/sync/conversations/{conversation_id}/messages/{message_id}/delivered",
+            axum::routing::put(handle_message_delivered),
+        )
+        .route(
+            "/sync/conversations/{conversation_id}/typing",
+            axum::routing::post(handle_conversation_typing),
+        )
+        .route(
+            "/sync/presence",
+            axum::routing::get(handle_presence_subscription).put(handle_status_put),
+        )
+        .route(
+            "/sync/conversations",
+            axum::routing::get(handle_conversation_subscription),
         )
 }
 
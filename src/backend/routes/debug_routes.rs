@@ -0,0 +1,44 @@
+/**
+ * Debug Route Handlers
+ *
+ * Endpoints for inspecting sync internals during development. They are
+ * only registered in debug builds or when the `debug-endpoints` feature is
+ * enabled, so release servers don't expose them.
+ *
+ * # Routes
+ *
+ * - `GET /chat/versions` - Version DAG of the chat (JSON, or `?format=dot`)
+ * - `GET /sync/conversations/{conversation_id}/versions` - Version DAG of a
+ *   conversation's messages (participants only)
+ */
+
+use axum::Router;
+#[cfg(feature = "ssr")]
+use crate::backend::server::state::AppState;
+#[cfg(feature = "ssr")]
+use crate::backend::chat::handlers::handle_chat_versions;
+#[cfg(feature = "ssr")]
+use crate::backend::messaging::message_sync::handle_conversation_versions;
+
+/// Whether the debug endpoints are served
+pub const DEBUG_ENDPOINTS: bool = cfg!(debug_assertions) || cfg!(feature = "debug-endpoints");
+
+/// Configure debug routes
+///
+/// Returns `router` unchanged unless [`DEBUG_ENDPOINTS`] is set.
+#[cfg(feature = "ssr")]
+pub fn configure_debug_routes(router: Router<AppState>) -> Router<AppState> {
+    if !DEBUG_ENDPOINTS {
+        return router;
+    }
+
+    router
+        .route(
+            "/chat/versions",
+            axum::routing::get(handle_chat_versions),
+        )
+        .route(
+            "/sync/conversations/{conversation_id}/versions",
+            axum::routing::get(handle_conversation_versions),
+        )
+}
@@ -44,6 +44,7 @@
 //! - `POST /api/auth/signup` - User registration
 //! - `POST /api/auth/login` - User login
 //! - `GET /api/auth/me` - Get current user
+//! - `POST /api/auth/refresh` - Refresh a JWT token
 //! - `GET /api/usage` - Usage statistics
 //!
 //! ## Leptos Routes
@@ -58,12 +59,13 @@
 //!
 //! ```rust,no_run
 //! use braid_site::backend::routes::create_router;
+//! use braid_site::backend::server::config::load_cors_settings;
 //! use braid_site::backend::server::state::AppState;
 //! use braid_site::frontend::app::{App, shell};
 //!
 //! # async fn example() {
 //! let app_state = AppState::default();
-//! let router = create_router(app_state, App, shell).await;
+//! let router = create_router(app_state, &load_cors_settings());
 //! # }
 //! ```
 //!
@@ -84,6 +86,9 @@ pub mod chat_routes;
 /// API endpoint handlers
 pub mod api_routes;
 
+/// Debug-only endpoints (version DAG inspection)
+pub mod debug_routes;
+
 // Re-export commonly used functions
 #[cfg(feature = "ssr")]
 pub use router::create_router;
@@ -26,6 +26,10 @@ use crate::backend::server::state::AppState;
 // use crate::backend::routes::chat_routes::configure_chat_routes; // not used currently
 #[cfg(feature = "ssr")]
 use crate::backend::routes::api_routes::configure_api_routes;
+#[cfg(feature = "ssr")]
+use crate::backend::routes::debug_routes::configure_debug_routes;
+#[cfg(feature = "ssr")]
+use crate::backend::server::cors::CorsSettings;
 use tower_http::services::ServeDir;
 
 /// Create the Axum router with all routes configured
@@ -41,6 +45,7 @@ use tower_http::services::ServeDir;
 /// # Arguments
 ///
 /// * `app_state` - Application state containing chat state and services
+/// * `cors` - Which other origins may call the API
 ///
 /// # Returns
 ///
@@ -54,13 +59,17 @@ use tower_http::services::ServeDir;
 /// - `PUT /chat` - Braid PUT for adding messages
 /// - `POST /typing` - Typing indicator events
 /// - `GET /realtime` - Generic real-time event subscription
+/// - `GET /ws` - The same events over a WebSocket, plus typing and receipts
 ///
 /// ## API Routes
 ///
 /// - `POST /api/auth/signup` - User registration
 /// - `POST /api/auth/login` - User login
 /// - `GET /api/auth/me` - Get current user
+/// - `POST /api/auth/refresh` - Refresh a JWT token
 /// - `GET /api/usage` - Usage statistics
+/// - `GET /metrics` - Prometheus metrics
+/// - `GET /healthz`, `GET /readyz` - Liveness and readiness probes
 ///
 /// ## Static Files
 ///
@@ -70,7 +79,7 @@ use tower_http::services::ServeDir;
 ///
 /// The fallback handler returns 404 for unknown routes.
 #[cfg(feature = "ssr")]
-pub fn create_router(app_state: AppState) -> Router<()> {
+pub fn create_router(app_state: AppState, cors: &CorsSettings) -> Router<()> {
     // Start with chat routes
     let router = Router::new()
         .route(
@@ -91,6 +100,13 @@ pub fn create_router(app_state: AppState) -> Router<()> {
                 handle_realtime_subscription
             }),
         )
+        .route(
+            "/ws",
+            axum::routing::get({
+                use crate::backend::realtime::websocket::handle_realtime_socket;
+                handle_realtime_socket
+            }),
+        )
         .route(
             "/typing",
             axum::routing::post({
@@ -114,12 +130,39 @@ pub fn create_router(app_state: AppState) -> Router<()> {
     // Add API routes
     let router = configure_api_routes(router);
 
+    // Add debug routes (debug builds or the `debug-endpoints` feature only)
+    let router = configure_debug_routes(router);
+
     // Add static file serving
     let router = router.nest_service("/static", ServeDir::new("public"));
 
     // Fallback handler for 404
     let router = router.fallback(|| async { "404 Not Found" });
 
+    // Turn requests away with 503 instead of hanging when the database pool is saturated
+    let router = router.layer(axum::middleware::from_fn_with_state(
+        app_state.clone(),
+        crate::backend::server::pool::pool_guard_middleware,
+    ));
+
+    // Turn new subscriptions away with 503 once the global cap is reached
+    let router = router.layer(axum::middleware::from_fn_with_state(
+        app_state.clone(),
+        crate::backend::server::capacity::subscription_limit_middleware,
+    ));
+
+    // Count 401s for `/metrics`
+    let router = router.layer(axum::middleware::from_fn_with_state(
+        app_state.clone(),
+        crate::backend::server::metrics::auth_failure_middleware,
+    ));
+
+    // Outermost, so preflights are answered before any other middleware runs
+    let router = match cors.layer() {
+        Some(cors) => router.layer(cors),
+        None => router,
+    };
+
     // Use AppState as router state
     router.with_state(app_state)
 }
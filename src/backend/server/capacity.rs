@@ -0,0 +1,249 @@
+//! Subscription Capacity
+//!
+//! Every open subscription (SSE stream, Braid `/chat` stream or WebSocket)
+//! holds buffers and a broadcast receiver for as long as it lives, so a
+//! flood of them can run the server out of memory. A global cap bounds the
+//! number open at once across all users. Once it is reached new
+//! subscriptions get `503` with `Retry-After` and clients back off; the ones
+//! already open keep streaming.
+//!
+//! A request counts as a subscription if it is a `GET` with a `Subscribe`
+//! header or a WebSocket upgrade. Its slot is released when the response
+//! body (or the socket) is dropped.
+//!
+//! # Configuration
+//!
+//! - `SUBSCRIPTIONS_MAX_ACTIVE` - subscriptions open at once (default 10000)
+//! - `SUBSCRIPTIONS_RETRY_AFTER_SECS` - `Retry-After` on rejections (default 5)
+
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{header, header::RETRY_AFTER, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use futures_util::StreamExt;
+use serde::Serialize;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Global subscription cap settings
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SubscriptionSettings {
+    /// Subscriptions allowed open at once
+    pub max_active: usize,
+    /// `Retry-After` sent with rejections
+    pub retry_after: Duration,
+}
+
+impl Default for SubscriptionSettings {
+    fn default() -> Self {
+        Self {
+            max_active: 10_000,
+            retry_after: Duration::from_secs(5),
+        }
+    }
+}
+
+impl SubscriptionSettings {
+    /// Read the settings from environment variables
+    pub fn from_env() -> Self {
+        fn env_u64(name: &str) -> Option<u64> {
+            std::env::var(name).ok().and_then(|v| v.parse().ok())
+        }
+
+        let defaults = Self::default();
+        Self {
+            max_active: env_u64("SUBSCRIPTIONS_MAX_ACTIVE")
+                .map(|n| n.max(1) as usize)
+                .unwrap_or(defaults.max_active),
+            retry_after: env_u64("SUBSCRIPTIONS_RETRY_AFTER_SECS")
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.retry_after),
+        }
+    }
+}
+
+/// Snapshot of subscription capacity
+#[derive(Debug, Clone, Serialize)]
+pub struct SubscriptionStats {
+    pub max_active: usize,
+    /// Subscriptions open right now
+    pub active: usize,
+    /// Most subscriptions open at once since startup
+    pub peak_active: usize,
+    /// Subscriptions turned away because the cap was reached
+    pub rejected: u64,
+}
+
+struct SubscriptionLimitInner {
+    settings: SubscriptionSettings,
+    slots: Arc<Semaphore>,
+    peak: AtomicUsize,
+    rejected: AtomicU64,
+}
+
+/// Caps the number of subscriptions open across all users
+#[derive(Clone)]
+pub struct SubscriptionLimit {
+    inner: Arc<SubscriptionLimitInner>,
+}
+
+/// A subscription's slot; released when the last clone is dropped
+///
+/// Added to the request extensions so WebSocket handlers can keep it for
+/// the life of the socket.
+#[derive(Clone)]
+pub struct SubscriptionPermit(#[allow(dead_code)] Arc<OwnedSemaphorePermit>);
+
+impl Default for SubscriptionLimit {
+    fn default() -> Self {
+        Self::new(SubscriptionSettings::default())
+    }
+}
+
+impl SubscriptionLimit {
+    pub fn new(settings: SubscriptionSettings) -> Self {
+        Self {
+            inner: Arc::new(SubscriptionLimitInner {
+                settings,
+                slots: Arc::new(Semaphore::new(settings.max_active.max(1))),
+                peak: AtomicUsize::new(0),
+                rejected: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(SubscriptionSettings::from_env())
+    }
+
+    /// Current and peak usage
+    pub fn stats(&self) -> SubscriptionStats {
+        SubscriptionStats {
+            max_active: self.inner.settings.max_active,
+            active: self.active(),
+            peak_active: self.inner.peak.load(Ordering::Relaxed),
+            rejected: self.inner.rejected.load(Ordering::Relaxed),
+        }
+    }
+
+    fn active(&self) -> usize {
+        self.inner.settings.max_active.saturating_sub(self.inner.slots.available_permits())
+    }
+
+    /// Take a slot, or `None` if the cap is reached
+    fn try_acquire(&self) -> Option<SubscriptionPermit> {
+        let permit = self.inner.slots.clone().try_acquire_owned().ok()?;
+        self.inner.peak.fetch_max(self.active(), Ordering::Relaxed);
+        Some(SubscriptionPermit(Arc::new(permit)))
+    }
+
+    /// `503 Service Unavailable` with `Retry-After`
+    fn unavailable(&self) -> Response {
+        let mut response = StatusCode::SERVICE_UNAVAILABLE.into_response();
+        let secs = self.inner.settings.retry_after.as_secs().max(1);
+        response.headers_mut().insert(RETRY_AFTER, HeaderValue::from(secs));
+        response
+    }
+}
+
+/// Whether `request` opens a subscription
+fn is_subscription(request: &Request) -> bool {
+    let headers = request.headers();
+    let upgrade = headers
+        .get(header::UPGRADE)
+        .and_then(|h| h.to_str().ok())
+        .is_some_and(|h| h.eq_ignore_ascii_case("websocket"));
+    request.method() == Method::GET && (headers.contains_key("subscribe") || upgrade)
+}
+
+/// Middleware applying the [`SubscriptionLimit`] to subscription requests
+pub async fn subscription_limit_middleware(
+    State(limit): State<SubscriptionLimit>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    if !is_subscription(&request) {
+        return next.run(request).await;
+    }
+
+    let Some(permit) = limit.try_acquire() else {
+        limit.inner.rejected.fetch_add(1, Ordering::Relaxed);
+        tracing::warn!(
+            "[Capacity] {} subscriptions open, rejecting {}",
+            limit.inner.settings.max_active,
+            request.uri().path()
+        );
+        return limit.unavailable();
+    };
+    request.extensions_mut().insert(permit.clone());
+
+    let response = next.run(request).await;
+    if !response.status().is_success() {
+        return response;
+    }
+
+    // The slot stays taken while the stream is being sent
+    let (parts, body) = response.into_parts();
+    let body = body.into_data_stream().map(move |chunk| {
+        let _held = &permit;
+        chunk
+    });
+    Response::from_parts(parts, Body::from_stream(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::get, Router};
+    use bytes::Bytes;
+    use futures_util::stream;
+    use tower::ServiceExt;
+
+    fn app(limit: &SubscriptionLimit) -> Router {
+        Router::new()
+            .route(
+                "/stream",
+                get(|| async { Body::from_stream(stream::pending::<Result<Bytes, std::io::Error>>()) }),
+            )
+            .layer(axum::middleware::from_fn_with_state(limit.clone(), subscription_limit_middleware))
+    }
+
+    fn subscribe() -> Request {
+        Request::get("/stream").header("subscribe", "true").body(Body::empty()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_subscription_over_the_cap_is_rejected() {
+        let limit = SubscriptionLimit::new(SubscriptionSettings { max_active: 2, retry_after: Duration::from_secs(7) });
+        let app = app(&limit);
+
+        let first = app.clone().oneshot(subscribe()).await.unwrap();
+        let second = app.clone().oneshot(subscribe()).await.unwrap();
+        assert_eq!(first.status(), StatusCode::OK);
+        assert_eq!(second.status(), StatusCode::OK);
+
+        let third = app.clone().oneshot(subscribe()).await.unwrap();
+        assert_eq!(third.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(third.headers()[RETRY_AFTER], "7");
+
+        // The open streams are still going, and plain requests aren't counted
+        let mut first_body = first.into_body().into_data_stream();
+        assert!(tokio::time::timeout(Duration::from_millis(20), first_body.next()).await.is_err());
+        let plain = Request::get("/stream").body(Body::empty()).unwrap();
+        assert_eq!(app.clone().oneshot(plain).await.unwrap().status(), StatusCode::OK);
+
+        let stats = limit.stats();
+        assert_eq!((stats.active, stats.peak_active, stats.rejected), (2, 2, 1));
+
+        // Closing one frees its slot
+        drop(second);
+        assert_eq!(app.oneshot(subscribe()).await.unwrap().status(), StatusCode::OK);
+        assert_eq!(limit.stats().rejected, 1);
+    }
+}
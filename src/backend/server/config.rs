@@ -2,7 +2,8 @@
  * Server Configuration
  * 
  * This module handles loading and validation of server configuration,
- * focusing on the optional PostgreSQL database connection.
+ * focusing on the optional PostgreSQL database connection, the
+ * message size limits and cross-origin access.
  * 
  * # Configuration Sources
  * 
@@ -13,11 +14,18 @@
  * 
  * Configuration errors are logged but do not prevent server startup.
  * Services that fail to initialize are set to `None` and the server
- * continues without them.
+ * continues without them. Invalid message limits are the exception: a
+ * server that would reject every message refuses to start instead.
  */
 
 #[cfg(feature = "ssr")]
 use sqlx::PgPool;
+#[cfg(feature = "ssr")]
+use crate::backend::server::pool::PoolSettings;
+#[cfg(feature = "ssr")]
+use crate::backend::messaging::limits::MessageLimits;
+#[cfg(feature = "ssr")]
+use crate::backend::server::cors::{CorsOrigins, CorsSettings};
 
 /// Database configuration result
 /// 
@@ -30,7 +38,7 @@ pub type DatabaseConfig = Option<PgPool>;
 /// 
 /// This function:
 /// 1. Reads `DATABASE_URL` from environment
-/// 2. Creates a PostgreSQL connection pool
+/// 2. Creates a PostgreSQL connection pool limited by `settings`
 /// 3. Runs database migrations
 /// 4. Optionally clears the users table (for development)
 /// 
@@ -50,7 +58,7 @@ pub type DatabaseConfig = Option<PgPool>;
 /// ```rust
 /// use braid_site::backend::server::config::load_database;
 /// 
-/// let db_pool = load_database().await;
+/// let db_pool = load_database(&PoolSettings::from_env()).await;
 /// if let Some(pool) = &db_pool {
 ///     // Database is available
 /// } else {
@@ -58,7 +66,7 @@ pub type DatabaseConfig = Option<PgPool>;
 /// }
 /// ```
 #[cfg(feature = "ssr")]
-pub async fn load_database() -> DatabaseConfig {
+pub async fn load_database(settings: &PoolSettings) -> DatabaseConfig {
     let database_url = match std::env::var("DATABASE_URL") {
         Ok(url) => url,
         Err(_) => {
@@ -69,7 +77,7 @@ pub async fn load_database() -> DatabaseConfig {
     
     tracing::info!("Connecting to database...");
     
-    let pool = match PgPool::connect(&database_url).await {
+    let pool = match settings.pool_options().connect(&database_url).await {
         Ok(pool) => pool,
         Err(e) => {
             tracing::error!("Failed to create database connection pool: {:?}", e);
@@ -78,11 +86,15 @@ pub async fn load_database() -> DatabaseConfig {
         }
     };
     
-    tracing::info!("Database connection pool created successfully");
+    tracing::info!(
+        "Database connection pool created successfully (max {} connections, {:?} acquire timeout)",
+        settings.max_connections,
+        settings.acquire_timeout
+    );
     
     // Run migrations
     tracing::info!("Running database migrations...");
-    match sqlx::migrate!().run(&pool).await {
+    match run_migrations(&pool).await {
         Ok(_) => {
             tracing::info!("Database migrations completed successfully");
         }
@@ -96,3 +108,86 @@ pub async fn load_database() -> DatabaseConfig {
     
     Some(pool)
 }
+
+/// Run the embedded migrations
+///
+/// Two released baseline migrations share version 20240105000000
+/// (`comprehensive_schema` and `xfchat_comprehensive_schema`, which differ
+/// only in comments). Given both, sqlx applies the version twice on a fresh
+/// database and reports a checksum mismatch on one that already has it, so
+/// every later migration is skipped. Only one of each version is run: the
+/// one the database recorded, or else the first.
+#[cfg(feature = "ssr")]
+pub async fn run_migrations(pool: &PgPool) -> Result<(), sqlx::migrate::MigrateError> {
+    let mut migrator = sqlx::migrate!();
+
+    // The table doesn't exist before the first run
+    let applied: Vec<(i64, Vec<u8>)> = sqlx::query_as("SELECT version, checksum FROM _sqlx_migrations")
+        .fetch_all(pool)
+        .await
+        .unwrap_or_default();
+
+    let mut migrations: Vec<sqlx::migrate::Migration> = Vec::new();
+    for migration in migrator.iter() {
+        match migrations.iter().position(|kept| kept.version == migration.version) {
+            None => migrations.push(migration.clone()),
+            Some(index) => {
+                let recorded = applied
+                    .iter()
+                    .any(|(version, checksum)| *version == migration.version && checksum[..] == migration.checksum[..]);
+                if recorded {
+                    migrations[index] = migration.clone();
+                }
+            }
+        }
+    }
+    migrator.migrations = std::borrow::Cow::Owned(migrations);
+
+    migrator.run(pool).await
+}
+
+/// Load the message size limits
+/// 
+/// Reads `MAX_MESSAGE_LENGTH`, `MAX_AUTHOR_LENGTH` and `MAX_MESSAGE_BYTES`,
+/// with defaults for unset ones.
+/// 
+/// # Panics
+/// 
+/// If a value is not a positive integer, or the limits don't fit inside
+/// each other (see `MessageLimits::validate`), so a misconfiguration stops
+/// startup rather than silently rejecting messages.
+#[cfg(feature = "ssr")]
+pub fn load_message_limits() -> MessageLimits {
+    match MessageLimits::from_env() {
+        Ok(limits) => {
+            tracing::info!(
+                "Message limits: {} byte text, {} byte author, {} byte message",
+                limits.max_text_length,
+                limits.max_author_length,
+                limits.max_message_bytes
+            );
+            limits
+        }
+        Err(e) => panic!("Invalid message limits: {}", e),
+    }
+}
+
+/// Load the cross-origin settings
+/// 
+/// Reads the `CORS_*` variables (see `server::cors`), defaulting to
+/// same-origin only in release builds and any origin in debug builds.
+/// Invalid origins and header names are logged and skipped.
+#[cfg(feature = "ssr")]
+pub fn load_cors_settings() -> CorsSettings {
+    let settings = CorsSettings::from_env();
+    match &settings.origins {
+        CorsOrigins::SameOrigin => tracing::info!("CORS: same-origin only"),
+        CorsOrigins::Any => tracing::warn!("CORS: any origin allowed (credentials: {})", settings.allow_credentials),
+        CorsOrigins::List(origins) => tracing::info!(
+            "CORS: allowing {} (credentials: {})",
+            origins.join(", "),
+            settings.allow_credentials
+        ),
+    }
+    settings
+}
@@ -0,0 +1,205 @@
+//! Cross-Origin Requests
+//!
+//! Browser clients served from another origin need CORS headers to call the
+//! API and, just as much, to read the Braid headers (`Version`, `Parents`,
+//! ...) on the responses; browsers hide any response header not listed in
+//! `Access-Control-Expose-Headers`. The Braid request and response headers
+//! are therefore always allowed and exposed, whatever else is configured.
+//!
+//! Release builds default to same-origin only (no CORS headers at all);
+//! debug builds allow any origin, with credentials, for local development.
+//!
+//! # Configuration
+//!
+//! - `CORS_ALLOWED_ORIGINS` - comma-separated origins, `*` for any, or
+//!   `same-origin`
+//! - `CORS_ALLOWED_METHODS` - comma-separated methods (default GET, POST,
+//!   PUT, PATCH, DELETE)
+//! - `CORS_ALLOWED_HEADERS` - request headers allowed on top of the Braid
+//!   ones, `Authorization` and `Content-Type`
+//! - `CORS_ALLOW_CREDENTIALS` - `true` to let browsers send cookies and
+//!   `Authorization` cross-origin (default on in debug builds)
+
+use std::time::Duration;
+
+use axum::http::{header, HeaderName, HeaderValue, Method};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+/// Braid request headers clients send
+const BRAID_REQUEST_HEADERS: [&str; 7] = [
+    "subscribe", "version", "parents", "merge-type", "peer", "heartbeats", "patches",
+];
+
+/// Braid response headers clients need to read
+const BRAID_RESPONSE_HEADERS: [&str; 7] = [
+    "subscribe", "version", "parents", "current-version", "merge-type", "patches", "content-range",
+];
+
+/// How long browsers may cache a preflight answer
+const PREFLIGHT_MAX_AGE: Duration = Duration::from_secs(600);
+
+/// Which origins may call the API
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CorsOrigins {
+    /// No cross-origin access
+    SameOrigin,
+    /// Any origin (echoed back, so credentials still work)
+    Any,
+    List(Vec<String>),
+}
+
+/// Cross-origin settings
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CorsSettings {
+    pub origins: CorsOrigins,
+    pub methods: Vec<Method>,
+    /// Request headers allowed beyond the Braid ones
+    pub headers: Vec<String>,
+    pub allow_credentials: bool,
+}
+
+impl Default for CorsSettings {
+    fn default() -> Self {
+        let debug = cfg!(debug_assertions);
+        Self {
+            origins: if debug { CorsOrigins::Any } else { CorsOrigins::SameOrigin },
+            methods: vec![Method::GET, Method::POST, Method::PUT, Method::PATCH, Method::DELETE],
+            headers: Vec::new(),
+            allow_credentials: debug,
+        }
+    }
+}
+
+impl CorsSettings {
+    /// Read the settings from environment variables
+    pub fn from_env() -> Self {
+        fn env_list(name: &str) -> Option<Vec<String>> {
+            std::env::var(name).ok().map(|v| {
+                v.split(',')
+                    .map(|item| item.trim().to_string())
+                    .filter(|item| !item.is_empty())
+                    .collect()
+            })
+        }
+
+        let defaults = Self::default();
+        Self {
+            origins: env_list("CORS_ALLOWED_ORIGINS")
+                .map(|origins| match origins.as_slice() {
+                    [] => CorsOrigins::SameOrigin,
+                    [only] if only == "same-origin" => CorsOrigins::SameOrigin,
+                    [only] if only == "*" => CorsOrigins::Any,
+                    _ => CorsOrigins::List(origins),
+                })
+                .unwrap_or(defaults.origins),
+            methods: env_list("CORS_ALLOWED_METHODS")
+                .map(|methods| {
+                    methods
+                        .iter()
+                        .filter_map(|m| m.to_ascii_uppercase().parse().ok())
+                        .collect()
+                })
+                .unwrap_or(defaults.methods),
+            headers: env_list("CORS_ALLOWED_HEADERS").unwrap_or(defaults.headers),
+            allow_credentials: std::env::var("CORS_ALLOW_CREDENTIALS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.allow_credentials),
+        }
+    }
+
+    /// The layer to apply, or `None` when only same-origin requests are allowed
+    pub fn layer(&self) -> Option<CorsLayer> {
+        let origins = match &self.origins {
+            CorsOrigins::SameOrigin => return None,
+            CorsOrigins::Any => AllowOrigin::mirror_request(),
+            CorsOrigins::List(origins) => AllowOrigin::list(origins.iter().filter_map(|origin| {
+                HeaderValue::from_str(origin)
+                    .map_err(|_| tracing::warn!("[CORS] Ignoring invalid origin: {:?}", origin))
+                    .ok()
+            })),
+        };
+
+        // Listed explicitly: wildcards aren't allowed alongside credentials
+        let allowed_headers = [header::AUTHORIZATION, header::CONTENT_TYPE, header::ACCEPT, header::CACHE_CONTROL]
+            .into_iter()
+            .chain(BRAID_REQUEST_HEADERS.into_iter().map(HeaderName::from_static))
+            .chain(self.headers.iter().filter_map(|name| {
+                HeaderName::try_from(name.as_str())
+                    .map_err(|_| tracing::warn!("[CORS] Ignoring invalid header name: {:?}", name))
+                    .ok()
+            }))
+            .collect::<Vec<_>>();
+        let exposed_headers = BRAID_RESPONSE_HEADERS
+            .into_iter()
+            .map(HeaderName::from_static)
+            .chain([header::RETRY_AFTER])
+            .collect::<Vec<_>>();
+
+        Some(
+            CorsLayer::new()
+                .allow_origin(origins)
+                .allow_methods(self.methods.clone())
+                .allow_headers(allowed_headers)
+                .expose_headers(exposed_headers)
+                .allow_credentials(self.allow_credentials)
+                .max_age(PREFLIGHT_MAX_AGE),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, extract::Request, http::StatusCode, routing::put, Router};
+    use tower::ServiceExt;
+
+    fn app(settings: &CorsSettings) -> Router {
+        Router::new()
+            .route("/chat", put(|| async { ([("version", "\"v1\"")], "") }))
+            .layer(settings.layer().unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_braid_headers_are_allowed_and_exposed() {
+        let settings = CorsSettings {
+            origins: CorsOrigins::List(vec!["https://app.example.com".to_string()]),
+            allow_credentials: true,
+            ..CorsSettings::default()
+        };
+        let app = app(&settings);
+
+        let preflight = Request::builder()
+            .method(Method::OPTIONS)
+            .uri("/chat")
+            .header("origin", "https://app.example.com")
+            .header("access-control-request-method", "PUT")
+            .header("access-control-request-headers", "authorization, parents, merge-type")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(preflight).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let headers = response.headers();
+        assert_eq!(headers["access-control-allow-origin"], "https://app.example.com");
+        assert_eq!(headers["access-control-allow-credentials"], "true");
+        let allowed = headers["access-control-allow-headers"].to_str().unwrap();
+        assert!(["authorization", "subscribe", "version", "parents", "merge-type"].iter().all(|h| allowed.contains(h)));
+
+        let request = Request::put("/chat").header("origin", "https://app.example.com").body(Body::empty()).unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let exposed = response.headers()["access-control-expose-headers"].to_str().unwrap();
+        assert!(["version", "parents", "current-version", "merge-type"].iter().all(|h| exposed.contains(h)));
+
+        // Origins not on the list get no CORS headers
+        let request = Request::put("/chat").header("origin", "https://evil.example.com").body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert!(!response.headers().contains_key("access-control-allow-origin"));
+    }
+
+    #[test]
+    fn test_same_origin_adds_no_layer() {
+        let settings = CorsSettings { origins: CorsOrigins::SameOrigin, ..CorsSettings::default() };
+        assert!(settings.layer().is_none());
+        assert!(CorsSettings { origins: CorsOrigins::Any, ..settings }.layer().is_some());
+    }
+}
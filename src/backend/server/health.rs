@@ -0,0 +1,120 @@
+//! Health Probes
+//!
+//! Unauthenticated endpoints for container orchestrators:
+//!
+//! - `GET /healthz` (liveness) answers `200` whenever the process can serve
+//!   requests at all, with the version and commit it was built from so a
+//!   deploy can be checked
+//! - `GET /readyz` (readiness) answers `503` while the database doesn't
+//!   answer a `SELECT 1` or the server is shutting down, so traffic goes to
+//!   other instances until it is back
+//!
+//! Both are cheap enough to poll every few seconds.
+
+use std::time::Duration;
+
+use axum::{extract::State, http::StatusCode, Json};
+use serde::Serialize;
+use sqlx::PgPool;
+
+use crate::backend::server::shutdown::Shutdown;
+
+/// Longest the readiness check waits on the database
+const READY_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Commit the server was built from, set by `build.rs`
+const GIT_COMMIT: &str = match option_env!("GIT_COMMIT") {
+    Some(commit) => commit,
+    None => "unknown",
+};
+
+#[derive(Debug, Serialize)]
+pub struct Health {
+    pub status: &'static str,
+    pub version: &'static str,
+    pub commit: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Readiness {
+    pub status: &'static str,
+    /// `ok`, `unreachable`, or `disabled` when running without a database
+    pub database: &'static str,
+}
+
+/// Liveness probe
+/// GET /healthz
+pub async fn get_healthz() -> Json<Health> {
+    Json(Health {
+        status: "ok",
+        version: env!("CARGO_PKG_VERSION"),
+        commit: GIT_COMMIT,
+    })
+}
+
+/// Readiness probe
+/// GET /readyz
+pub async fn get_readyz(
+    State(db_pool): State<Option<PgPool>>,
+    State(shutdown): State<Shutdown>,
+) -> (StatusCode, Json<Readiness>) {
+    let database = match &db_pool {
+        Some(pool) => {
+            let ping = sqlx::query("SELECT 1").execute(pool);
+            match tokio::time::timeout(READY_TIMEOUT, ping).await {
+                Ok(Ok(_)) => "ok",
+                Ok(Err(e)) => {
+                    tracing::warn!("[HEALTH] Database check failed: {:?}", e);
+                    "unreachable"
+                }
+                Err(_) => {
+                    tracing::warn!("[HEALTH] Database check timed out");
+                    "unreachable"
+                }
+            }
+        }
+        None => "disabled",
+    };
+
+    let (code, status) = if shutdown.is_triggered() {
+        (StatusCode::SERVICE_UNAVAILABLE, "shutting_down")
+    } else if database == "unreachable" {
+        (StatusCode::SERVICE_UNAVAILABLE, "unavailable")
+    } else {
+        (StatusCode::OK, "ready")
+    };
+    (code, Json(Readiness { status, database }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_probes_without_database() {
+        let Json(health) = get_healthz().await;
+        assert_eq!((health.status, health.version), ("ok", env!("CARGO_PKG_VERSION")));
+        assert!(!health.commit.is_empty());
+
+        let shutdown = Shutdown::new();
+        let (code, Json(ready)) = get_readyz(State(None), State(shutdown.clone())).await;
+        assert_eq!((code, ready.status, ready.database), (StatusCode::OK, "ready", "disabled"));
+
+        // Draining instances drop out of rotation
+        shutdown.trigger();
+        let (code, Json(ready)) = get_readyz(State(None), State(shutdown)).await;
+        assert_eq!((code, ready.status), (StatusCode::SERVICE_UNAVAILABLE, "shutting_down"));
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_database_is_not_ready() {
+        // Nothing listens on port 1; the lazy pool only fails once queried
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .acquire_timeout(Duration::from_millis(200))
+            .connect_lazy("postgres://nobody@127.0.0.1:1/none")
+            .unwrap();
+
+        let (code, Json(ready)) = get_readyz(State(Some(pool)), State(Shutdown::new())).await;
+        assert_eq!((code, ready.database), (StatusCode::SERVICE_UNAVAILABLE, "unreachable"));
+    }
+}
@@ -35,7 +35,9 @@ use crate::backend::routes::router::create_router;
 #[cfg(feature = "ssr")]
 use crate::backend::server::state::{AppState, MessageEvent};
 #[cfg(feature = "ssr")]
-use crate::backend::server::config::load_database;
+use crate::backend::server::config::{load_cors_settings, load_database, load_message_limits};
+#[cfg(feature = "ssr")]
+use crate::backend::server::shutdown::Shutdown;
 
 /// Create and configure the Axum application
 ///
@@ -44,6 +46,10 @@ use crate::backend::server::config::load_database;
 /// - Database connection pool (if configured)
 /// - Route configuration
 ///
+/// # Arguments
+///
+/// * `shutdown` - Triggered by the caller to close open subscriptions
+///
 /// # Returns
 ///
 /// Configured Axum Router ready to serve requests
@@ -62,10 +68,14 @@ use crate::backend::server::config::load_database;
 /// - Missing database: Server continues without database features
 /// - Migration failures: Logged but don't prevent startup
 /// - State restoration failures: Logged but don't prevent startup
+/// - Invalid message limits: Panics, see `load_message_limits`
 #[cfg(feature = "ssr")]
-pub async fn create_app() -> Router<()> {
+pub async fn create_app(shutdown: Shutdown) -> Router<()> {
     tracing::info!("Initializing XFCollab backend server");
 
+    // Checked first so a misconfigured server stops before doing anything
+    let message_limits = load_message_limits();
+
     // Step 1: Create shared chat state
     // This stores messages and version history in memory
     // In a production app, this would be a database connection
@@ -76,48 +86,109 @@ pub async fn create_app() -> Router<()> {
     let collab_state = Arc::new(RwLock::new(CollabState::new()));
 
     // Step 2: Create broadcast channels
-    // Capacity of 1000 should be more than enough for a chat app
-    let (message_broadcast, _) = broadcast::channel::<MessageEvent>(1000);
+    // Subscribers more than this many events behind skip ahead (see `lag`)
+    let broadcast_capacity = crate::backend::server::lag::capacity_from_env();
+    tracing::info!("Broadcast channel capacity: {}", broadcast_capacity);
+    let (message_broadcast, _) = broadcast::channel::<MessageEvent>(broadcast_capacity);
+
+    // Rapid PUTs are merged into one frame before reaching subscribers
+    let message_coalescer = crate::backend::chat::coalesce::BroadcastCoalescer::spawn(
+        message_broadcast.clone(),
+        crate::backend::chat::coalesce::COALESCE_WINDOW,
+    );
 
     // Create generic real-time event broadcast channel
     // This can handle any type of real-time event: messages, notifications, status updates, etc.
-    let (realtime_broadcast, _) = broadcast::channel::<crate::shared::RealtimeEvent>(1000);
+    let (realtime_broadcast, _) = broadcast::channel::<crate::shared::RealtimeEvent>(broadcast_capacity);
 
     tracing::info!("Chat state and broadcast channels initialized");
 
     // Step 3: Load optional services
-    let db_pool = load_database().await;
+    let pool_settings = crate::backend::server::pool::PoolSettings::from_env();
+    let db_pool = load_database(&pool_settings).await;
 
     // Step 4: Restore chat state from database if available
     if let Some(pool) = &db_pool {
         restore_chat_state(pool, &chat_state).await;
     }
 
+    // Step 4.5: Select attachment storage backend
+    let attachment_storage = match crate::backend::attachments::AttachmentStorage::from_env() {
+        Ok(storage) => storage,
+        Err(e) => {
+            tracing::error!("Invalid attachment storage configuration: {}", e);
+            tracing::warn!("Falling back to filesystem attachment storage");
+            crate::backend::attachments::AttachmentStorage::Filesystem(
+                crate::backend::attachments::filesystem::FilesystemStorage::new("./data/attachments"),
+            )
+        }
+    };
+    tracing::info!("Attachment storage backend: {}", attachment_storage.kind());
+
     // Step 5: Create app state
     let app_state = AppState {
         chat_state,
         collab_state,
         message_broadcast,
+        message_coalescer,
         realtime_broadcast,
         db_pool,
         messaging_broadcast: crate::backend::server::state::MessagingBroadcastState::new(),
         messaging_crdt: crate::backend::server::state::MessagingCrdtState::new(),
+        reconnect_guard: crate::backend::messaging::reconnect_guard::ReconnectGuard::from_env(),
+        attachment_storage,
+        link_previews: crate::backend::messaging::link_preview::LinkPreviewService::from_env(),
+        content_filter: crate::backend::messaging::content_filter::content_filter_from_env(),
+        message_limits,
+        usage_limits: crate::backend::subscription::usage::UsageLimits::from_env(),
+        conversation_cache: crate::backend::messaging::conversation_cache::ConversationCache::from_env(),
+        pool_guard: crate::backend::server::pool::PoolGuard::new(pool_settings),
+        ai: crate::backend::ai::AiService::from_env(),
+        user_statuses: crate::backend::messaging::presence::StatusStore::default(),
+        conversation_events: crate::backend::messaging::conversation_events::ConversationEvents::default(),
+        shutdown,
+        subscription_limit: crate::backend::server::capacity::SubscriptionLimit::from_env(),
+        connection_telemetry: crate::backend::server::telemetry::ConnectionTelemetry::default(),
+        broadcast_lag: crate::backend::server::lag::BroadcastLag::default(),
+        metrics: crate::backend::server::metrics::ServerMetrics::default(),
     };
 
     // Step 6: Create router with all routes
-    let app = create_router(app_state.clone());
+    let app = create_router(app_state.clone(), &load_cors_settings());
 
     // Step 7: Start periodic cleanup task for broadcast channels
     let cleanup_state = app_state.messaging_broadcast.clone();
+    let reconnect_guard = app_state.reconnect_guard.clone();
+    let pool_guard = app_state.pool_guard.clone();
+    let stats_pool = app_state.db_pool.clone();
     tokio::spawn(async move {
         let mut interval = tokio::time::interval(std::time::Duration::from_secs(300)); // 5 minutes
         loop {
             interval.tick().await;
             cleanup_state.cleanup_inactive_channels();
             tracing::debug!("Cleaned up inactive messaging broadcast channels");
+            tracing::info!("Reconnect stats: {:?}", reconnect_guard.stats());
+            if let Some(pool) = &stats_pool {
+                tracing::info!("Database pool stats: {:?}", pool_guard.stats(pool));
+            }
         }
     });
 
+    // Step 8: Mark users offline when their heartbeats stop
+    if let Some(pool) = &app_state.db_pool {
+        crate::backend::messaging::presence::spawn_presence_sweeper(
+            pool.clone(),
+            app_state.realtime_broadcast.clone(),
+            app_state.user_statuses.clone(),
+            crate::backend::messaging::presence::PresenceConfig::from_env(),
+        );
+    }
+
+    // Step 9: Keep durable realtime events for replay
+    if let Some(pool) = &app_state.db_pool {
+        crate::backend::realtime::persistence::spawn_event_persister(pool.clone(), &app_state.realtime_broadcast);
+    }
+
     tracing::info!("Router configured with periodic cleanup task");
 
     app
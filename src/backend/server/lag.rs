@@ -0,0 +1,120 @@
+//! Broadcast Lag
+//!
+//! Subscribers read the server's broadcast channels at their own pace. One
+//! that falls more than the channel's capacity behind misses the oldest
+//! events (`RecvError::Lagged`), so lags are counted per channel for
+//! `/api/metrics`, and each is logged with how far behind the subscriber
+//! still is. Lags that keep showing up mean `BROADCAST_CAPACITY` is too
+//! small for the traffic.
+
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+
+/// Events the message and realtime channels hold for slow subscribers
+pub const DEFAULT_BROADCAST_CAPACITY: usize = 1000;
+
+/// Channel capacity from `BROADCAST_CAPACITY`, or the default
+pub fn capacity_from_env() -> usize {
+    std::env::var("BROADCAST_CAPACITY")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .map(|n| n.max(1))
+        .unwrap_or(DEFAULT_BROADCAST_CAPACITY)
+}
+
+/// Which broadcast a subscriber fell behind on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BroadcastChannel {
+    /// Chat updates (`AppState::message_broadcast`)
+    Message,
+    /// Typing, receipts and other realtime events
+    Realtime,
+    /// A messaging conversation's new messages
+    Conversation,
+}
+
+impl BroadcastChannel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Message => "message",
+            Self::Realtime => "realtime",
+            Self::Conversation => "conversation",
+        }
+    }
+}
+
+/// Lags on one channel since startup
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ChannelLag {
+    /// Times a subscriber fell behind
+    pub lags: u64,
+    /// Events skipped across all of them
+    pub skipped: u64,
+    /// Most events one lag skipped
+    pub max_skipped: u64,
+}
+
+/// Counts subscribers falling behind on broadcast channels
+#[derive(Clone, Default)]
+pub struct BroadcastLag {
+    stats: Arc<Mutex<BTreeMap<BroadcastChannel, ChannelLag>>>,
+}
+
+impl BroadcastLag {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count a lag on `channel` and log where the subscriber is
+    ///
+    /// `skipped` is from `RecvError::Lagged`; `queued` is what the
+    /// subscriber still has to read (`Receiver::len`) after skipping, i.e.
+    /// how far behind the newest event it is.
+    pub fn record(&self, channel: BroadcastChannel, subscriber: &dyn Display, skipped: u64, queued: usize) {
+        tracing::warn!(
+            "[Broadcast] {:?} subscriber {} lagged, skipped {} events, {} still queued",
+            channel, subscriber, skipped, queued
+        );
+        let Ok(mut stats) = self.stats.lock() else {
+            return;
+        };
+        let lag = stats.entry(channel).or_default();
+        lag.lags += 1;
+        lag.skipped = lag.skipped.saturating_add(skipped);
+        lag.max_skipped = lag.max_skipped.max(skipped);
+    }
+
+    pub fn stats(&self) -> BTreeMap<BroadcastChannel, ChannelLag> {
+        self.stats.lock().map(|stats| stats.clone()).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::broadcast::{self, error::RecvError};
+
+    #[tokio::test]
+    async fn test_lagging_subscriber_is_counted() {
+        let lag = BroadcastLag::new();
+        let (tx, mut rx) = broadcast::channel(4);
+        for n in 0..10 {
+            tx.send(n).unwrap();
+        }
+
+        let Err(RecvError::Lagged(skipped)) = rx.recv().await else {
+            panic!("expected the subscriber to lag");
+        };
+        lag.record(BroadcastChannel::Realtime, &"test", skipped, rx.len());
+        assert_eq!((skipped, rx.len()), (6, 4));
+
+        lag.record(BroadcastChannel::Realtime, &"test", 2, 0);
+        let stats = lag.stats();
+        assert_eq!(stats[&BroadcastChannel::Realtime], ChannelLag { lags: 2, skipped: 8, max_skipped: 6 });
+        assert!(!stats.contains_key(&BroadcastChannel::Message));
+    }
+}
@@ -0,0 +1,235 @@
+//! Prometheus Metrics
+//!
+//! `GET /metrics` in the Prometheus text format, for scrapers that can't read
+//! the JSON at `/api/metrics`. Counters live in a small lock-free registry
+//! ([`ServerMetrics`]) that handlers update where the event happens; gauges
+//! are read from the components that already track them when scraped:
+//!
+//! - `xfmail_braid_subscriptions_active` - open subscriptions ([`SubscriptionLimit`])
+//! - `xfmail_messages_sent_total` - new messages stored by `PUT /chat` and the
+//!   messaging PUT
+//! - `xfmail_put_duration_seconds` - histogram of those PUTs' latency
+//! - `xfmail_auth_failures_total` - requests answered `401 Unauthorized`
+//! - `xfmail_db_pool_connections_in_use` - only when running with a database
+//! - `xfmail_broadcast_lags_total` / `xfmail_broadcast_skipped_events_total` -
+//!   subscribers falling behind, per channel ([`BroadcastLag`])
+
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::{Request, State},
+    http::{header::CONTENT_TYPE, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use sqlx::PgPool;
+
+use crate::backend::server::capacity::SubscriptionLimit;
+use crate::backend::server::lag::BroadcastLag;
+
+/// Upper bounds of the PUT latency buckets, in seconds
+const PUT_BUCKETS: [f64; 10] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0];
+
+/// Content type of the Prometheus text exposition format
+const TEXT_FORMAT: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Fixed-bucket latency histogram
+struct Histogram {
+    /// Observations at or below each of `PUT_BUCKETS`, not cumulative
+    buckets: [AtomicU64; PUT_BUCKETS.len()],
+    count: AtomicU64,
+    sum_micros: AtomicU64,
+}
+
+impl Histogram {
+    fn new() -> Self {
+        Self {
+            buckets: std::array::from_fn(|_| AtomicU64::new(0)),
+            count: AtomicU64::new(0),
+            sum_micros: AtomicU64::new(0),
+        }
+    }
+
+    fn observe(&self, elapsed: Duration) {
+        let secs = elapsed.as_secs_f64();
+        if let Some(bucket) = PUT_BUCKETS.iter().position(|bound| secs <= *bound) {
+            self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_micros.fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    fn render(&self, out: &mut String, name: &str, help: &str) {
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        let _ = writeln!(out, "# TYPE {} histogram", name);
+        let mut cumulative = 0;
+        for (bound, bucket) in PUT_BUCKETS.iter().zip(&self.buckets) {
+            cumulative += bucket.load(Ordering::Relaxed);
+            let _ = writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, bound, cumulative);
+        }
+        let count = self.count.load(Ordering::Relaxed);
+        let _ = writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}", name, count);
+        let sum = self.sum_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0;
+        let _ = writeln!(out, "{}_sum {}", name, sum);
+        let _ = writeln!(out, "{}_count {}", name, count);
+    }
+}
+
+struct ServerMetricsInner {
+    messages_sent: AtomicU64,
+    auth_failures: AtomicU64,
+    put_duration: Histogram,
+}
+
+/// Counters and histograms updated by handlers
+#[derive(Clone)]
+pub struct ServerMetrics {
+    inner: Arc<ServerMetricsInner>,
+}
+
+impl Default for ServerMetrics {
+    fn default() -> Self {
+        Self {
+            inner: Arc::new(ServerMetricsInner {
+                messages_sent: AtomicU64::new(0),
+                auth_failures: AtomicU64::new(0),
+                put_duration: Histogram::new(),
+            }),
+        }
+    }
+}
+
+impl ServerMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count a new message stored
+    pub fn message_sent(&self) {
+        self.inner.messages_sent.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count a request turned away as unauthenticated
+    pub fn auth_failure(&self) {
+        self.inner.auth_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Time a message PUT; the latency is recorded when the timer is dropped,
+    /// so every way out of the handler is counted
+    pub fn put_timer(&self) -> PutTimer {
+        PutTimer { metrics: self.clone(), started: Instant::now() }
+    }
+
+    /// The registry plus the scraped gauges, in the Prometheus text format
+    pub fn render(&self, subscriptions: &SubscriptionLimit, pool: Option<&PgPool>, lag: &BroadcastLag) -> String {
+        let mut out = String::new();
+
+        gauge(&mut out, "xfmail_braid_subscriptions_active", "Braid subscriptions open right now", subscriptions.stats().active as u64);
+        counter(&mut out, "xfmail_messages_sent_total", "New messages stored", self.inner.messages_sent.load(Ordering::Relaxed));
+        self.inner.put_duration.render(&mut out, "xfmail_put_duration_seconds", "Time taken to handle message PUTs");
+        counter(&mut out, "xfmail_auth_failures_total", "Requests answered 401 Unauthorized", self.inner.auth_failures.load(Ordering::Relaxed));
+
+        if let Some(pool) = pool {
+            let in_use = pool.size().saturating_sub(pool.num_idle() as u32);
+            gauge(&mut out, "xfmail_db_pool_connections_in_use", "Database connections checked out of the pool", u64::from(in_use));
+        }
+
+        let lag = lag.stats();
+        let _ = writeln!(out, "# HELP xfmail_broadcast_lags_total Times a subscriber fell behind a broadcast channel");
+        let _ = writeln!(out, "# TYPE xfmail_broadcast_lags_total counter");
+        for (channel, stats) in &lag {
+            let _ = writeln!(out, "xfmail_broadcast_lags_total{{channel=\"{}\"}} {}", channel.as_str(), stats.lags);
+        }
+        let _ = writeln!(out, "# HELP xfmail_broadcast_skipped_events_total Events lagging subscribers skipped");
+        let _ = writeln!(out, "# TYPE xfmail_broadcast_skipped_events_total counter");
+        for (channel, stats) in &lag {
+            let _ = writeln!(out, "xfmail_broadcast_skipped_events_total{{channel=\"{}\"}} {}", channel.as_str(), stats.skipped);
+        }
+
+        out
+    }
+}
+
+fn counter(out: &mut String, name: &str, help: &str, value: u64) {
+    let _ = writeln!(out, "# HELP {} {}\n# TYPE {} counter\n{} {}", name, help, name, name, value);
+}
+
+fn gauge(out: &mut String, name: &str, help: &str, value: u64) {
+    let _ = writeln!(out, "# HELP {} {}\n# TYPE {} gauge\n{} {}", name, help, name, name, value);
+}
+
+/// Records a PUT's latency when dropped
+pub struct PutTimer {
+    metrics: ServerMetrics,
+    started: Instant,
+}
+
+impl Drop for PutTimer {
+    fn drop(&mut self) {
+        self.metrics.inner.put_duration.observe(self.started.elapsed());
+    }
+}
+
+/// Middleware counting `401 Unauthorized` responses as auth failures
+///
+/// Handlers check tokens themselves, so the response status is the one
+/// place every failed check shows up.
+pub async fn auth_failure_middleware(
+    State(metrics): State<ServerMetrics>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let response = next.run(request).await;
+    if response.status() == StatusCode::UNAUTHORIZED {
+        metrics.auth_failure();
+    }
+    response
+}
+
+/// GET /metrics
+pub async fn get_prometheus_metrics(
+    State(metrics): State<ServerMetrics>,
+    State(subscription_limit): State<SubscriptionLimit>,
+    State(db_pool): State<Option<PgPool>>,
+    State(broadcast_lag): State<BroadcastLag>,
+) -> Response {
+    let body = metrics.render(&subscription_limit, db_pool.as_ref(), &broadcast_lag);
+    ([(CONTENT_TYPE, TEXT_FORMAT)], body).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::server::lag::BroadcastChannel;
+
+    #[test]
+    fn test_render_prometheus_text() {
+        let metrics = ServerMetrics::new();
+        metrics.message_sent();
+        metrics.message_sent();
+        metrics.auth_failure();
+        metrics.inner.put_duration.observe(Duration::from_millis(3));
+        metrics.inner.put_duration.observe(Duration::from_millis(300));
+        drop(metrics.put_timer());
+
+        let lag = BroadcastLag::new();
+        lag.record(BroadcastChannel::Conversation, &"test", 5, 0);
+
+        let text = metrics.render(&SubscriptionLimit::default(), None, &lag);
+        assert!(text.contains("# TYPE xfmail_braid_subscriptions_active gauge\nxfmail_braid_subscriptions_active 0\n"));
+        assert!(text.contains("xfmail_messages_sent_total 2\n"));
+        assert!(text.contains("xfmail_auth_failures_total 1\n"));
+        assert!(text.contains("xfmail_put_duration_seconds_bucket{le=\"0.005\"} 2\n"));
+        assert!(text.contains("xfmail_put_duration_seconds_bucket{le=\"0.25\"} 2\n"));
+        assert!(text.contains("xfmail_put_duration_seconds_bucket{le=\"0.5\"} 3\n"));
+        assert!(text.contains("xfmail_put_duration_seconds_bucket{le=\"+Inf\"} 3\n"));
+        assert!(text.contains("xfmail_put_duration_seconds_count 3\n"));
+        assert!(text.contains("xfmail_broadcast_lags_total{channel=\"conversation\"} 1\n"));
+        assert!(text.contains("xfmail_broadcast_skipped_events_total{channel=\"conversation\"} 5\n"));
+        // No database, no pool gauge
+        assert!(!text.contains("xfmail_db_pool_connections_in_use"));
+    }
+}
@@ -11,6 +11,14 @@
 //! - **`state`** - Application state structure and `FromRef` implementations
 //! - **`config`** - Configuration loading and validation
 //! - **`init`** - Server initialization and app creation
+//! - **`pool`** - Database pool limits, load shedding and metrics
+//! - **`shutdown`** - SIGTERM handling and draining open subscriptions
+//! - **`capacity`** - Global cap on open subscriptions
+//! - **`telemetry`** - Reconnection reports from clients
+//! - **`lag`** - Broadcast capacity and subscribers falling behind
+//! - **`metrics`** - Prometheus metrics at `/metrics`
+//! - **`health`** - Liveness and readiness probes
+//! - **`cors`** - Cross-origin access for browser clients
 //!
 //! # Module Structure
 //!
@@ -19,7 +27,15 @@
 //! ├── mod.rs          - Module exports and documentation
 //! ├── state.rs        - AppState and FromRef implementations
 //! ├── config.rs       - Configuration loading (database, Stripe)
-//! └── init.rs         - Server initialization and app creation
+//! ├── init.rs         - Server initialization and app creation
+//! ├── pool.rs         - Database pool limits and metrics
+//! ├── shutdown.rs     - Graceful shutdown signal
+//! ├── capacity.rs     - Global subscription cap
+//! ├── telemetry.rs    - Client connection telemetry
+//! ├── lag.rs          - Broadcast capacity and lag metrics
+//! ├── metrics.rs      - Prometheus registry and `/metrics`
+//! ├── health.rs       - `/healthz` and `/readyz`
+//! └── cors.rs         - CORS settings and layer
 //! ```
 //!
 //! # State Management
@@ -70,9 +86,42 @@ pub mod config;
 /// Server initialization
 pub mod init;
 
+/// Database pool limits and metrics
+pub mod pool;
+
+/// Graceful shutdown
+#[cfg(feature = "ssr")]
+pub mod shutdown;
+
+/// Global subscription cap
+#[cfg(feature = "ssr")]
+pub mod capacity;
+
+/// Client connection telemetry
+#[cfg(feature = "ssr")]
+pub mod telemetry;
+
+/// Broadcast capacity and lag metrics
+#[cfg(feature = "ssr")]
+pub mod lag;
+
+/// Prometheus metrics
+#[cfg(feature = "ssr")]
+pub mod metrics;
+
+/// Health probes
+#[cfg(feature = "ssr")]
+pub mod health;
+
+/// Cross-origin access
+#[cfg(feature = "ssr")]
+pub mod cors;
+
 // Re-export commonly used types
 #[cfg(feature = "ssr")]
 pub use state::{AppState, MessageEvent};
 #[cfg(feature = "ssr")]
 pub use init::create_app;
+#[cfg(feature = "ssr")]
+pub use shutdown::Shutdown;
 
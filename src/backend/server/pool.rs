@@ -0,0 +1,346 @@
+//! Database Pool Limits
+//!
+//! Keeps the server responsive when the Postgres pool runs out of
+//! connections, e.g. during a reconnect storm:
+//!
+//! - The pool size and how long a query may wait for a connection are
+//!   configurable; queries wait in the pool's own queue up to that timeout
+//! - While the pool is saturated at most `DATABASE_MAX_QUEUE` requests may be
+//!   in flight beyond the pool size; further ones are turned away at once
+//! - A request that fails after waiting out the acquire timeout on a
+//!   saturated pool is answered `503` instead of `500`
+//!
+//! Both rejections carry `Retry-After`, so clients back off and retry
+//! instead of hanging or giving up.
+//!
+//! # Configuration
+//!
+//! - `DATABASE_MAX_CONNECTIONS` - pool size (default 10)
+//! - `DATABASE_ACQUIRE_TIMEOUT_MS` - longest wait for a connection (default 3000)
+//! - `DATABASE_MAX_QUEUE` - requests allowed beyond the pool size while it is
+//!   saturated (default 4x the pool size)
+//! - `DATABASE_RETRY_AFTER_SECS` - `Retry-After` on rejections (default 1)
+
+#[cfg(feature = "ssr")]
+use std::sync::atomic::{AtomicU64, Ordering};
+#[cfg(feature = "ssr")]
+use std::collections::BTreeMap;
+#[cfg(feature = "ssr")]
+use std::sync::Arc;
+#[cfg(feature = "ssr")]
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "ssr")]
+use axum::{
+    extract::{Request, State},
+    http::{header::RETRY_AFTER, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+#[cfg(feature = "ssr")]
+use serde::Serialize;
+#[cfg(feature = "ssr")]
+use sqlx::postgres::PgPoolOptions;
+#[cfg(feature = "ssr")]
+use sqlx::PgPool;
+#[cfg(feature = "ssr")]
+use tokio::sync::Semaphore;
+
+#[cfg(feature = "ssr")]
+use crate::backend::messaging::reconnect_guard::{ReconnectGuard, ReconnectStats};
+#[cfg(feature = "ssr")]
+use crate::backend::server::capacity::{SubscriptionLimit, SubscriptionStats};
+#[cfg(feature = "ssr")]
+use crate::backend::server::telemetry::{ConnectionTelemetry, ConnectionTelemetryStats};
+#[cfg(feature = "ssr")]
+use crate::backend::server::lag::{BroadcastChannel, BroadcastLag, ChannelLag};
+
+/// Pool size and queueing settings
+#[cfg(feature = "ssr")]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PoolSettings {
+    /// Connections the pool may open
+    pub max_connections: u32,
+    /// Longest a query waits for a free connection
+    pub acquire_timeout: Duration,
+    /// Requests allowed in flight beyond `max_connections` while saturated
+    pub max_queue: usize,
+    /// `Retry-After` sent with rejections
+    pub retry_after: Duration,
+}
+
+#[cfg(feature = "ssr")]
+impl Default for PoolSettings {
+    fn default() -> Self {
+        Self {
+            max_connections: 10,
+            acquire_timeout: Duration::from_secs(3),
+            max_queue: 40,
+            retry_after: Duration::from_secs(1),
+        }
+    }
+}
+
+#[cfg(feature = "ssr")]
+impl PoolSettings {
+    /// Read the settings from environment variables
+    pub fn from_env() -> Self {
+        fn env_u64(name: &str) -> Option<u64> {
+            std::env::var(name).ok().and_then(|v| v.parse().ok())
+        }
+
+        let defaults = Self::default();
+        let max_connections = env_u64("DATABASE_MAX_CONNECTIONS")
+            .map(|n| n.max(1) as u32)
+            .unwrap_or(defaults.max_connections);
+        Self {
+            max_connections,
+            acquire_timeout: env_u64("DATABASE_ACQUIRE_TIMEOUT_MS")
+                .map(Duration::from_millis)
+                .unwrap_or(defaults.acquire_timeout),
+            max_queue: env_u64("DATABASE_MAX_QUEUE")
+                .map(|n| n as usize)
+                .unwrap_or(max_connections as usize * 4),
+            retry_after: env_u64("DATABASE_RETRY_AFTER_SECS")
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.retry_after),
+        }
+    }
+
+    /// Pool options with these limits
+    pub fn pool_options(&self) -> PgPoolOptions {
+        PgPoolOptions::new()
+            .max_connections(self.max_connections)
+            .acquire_timeout(self.acquire_timeout)
+    }
+}
+
+/// Snapshot of pool utilization
+#[cfg(feature = "ssr")]
+#[derive(Debug, Clone, Serialize)]
+pub struct PoolStats {
+    pub max_connections: u32,
+    /// Connections currently open
+    pub open_connections: u32,
+    /// Open connections not in use
+    pub idle_connections: usize,
+    /// Share of `max_connections` in use, 0.0 to 1.0
+    pub utilization: f64,
+    /// Requests being handled right now
+    pub requests_in_flight: usize,
+    /// Requests turned away because the queue was full
+    pub rejected_requests: u64,
+    /// Requests that timed out waiting for a connection
+    pub timed_out_requests: u64,
+}
+
+#[cfg(feature = "ssr")]
+struct PoolGuardInner {
+    settings: PoolSettings,
+    /// One permit per request allowed in flight while the pool is saturated
+    slots: Arc<Semaphore>,
+    rejected: AtomicU64,
+    timed_out: AtomicU64,
+}
+
+/// Sheds load when the database pool is saturated
+#[cfg(feature = "ssr")]
+#[derive(Clone)]
+pub struct PoolGuard {
+    inner: Arc<PoolGuardInner>,
+}
+
+#[cfg(feature = "ssr")]
+impl Default for PoolGuard {
+    fn default() -> Self {
+        Self::new(PoolSettings::default())
+    }
+}
+
+#[cfg(feature = "ssr")]
+impl PoolGuard {
+    pub fn new(settings: PoolSettings) -> Self {
+        let slots = settings.max_connections as usize + settings.max_queue;
+        Self {
+            inner: Arc::new(PoolGuardInner {
+                settings,
+                slots: Arc::new(Semaphore::new(slots.max(1))),
+                rejected: AtomicU64::new(0),
+                timed_out: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    pub fn settings(&self) -> &PoolSettings {
+        &self.inner.settings
+    }
+
+    /// Current utilization of `pool`
+    pub fn stats(&self, pool: &PgPool) -> PoolStats {
+        let max_connections = self.inner.settings.max_connections;
+        let open_connections = pool.size();
+        let idle_connections = pool.num_idle();
+        let in_use = open_connections.saturating_sub(idle_connections as u32);
+        let slots = max_connections as usize + self.inner.settings.max_queue;
+
+        PoolStats {
+            max_connections,
+            open_connections,
+            idle_connections,
+            utilization: in_use as f64 / max_connections.max(1) as f64,
+            requests_in_flight: slots.saturating_sub(self.inner.slots.available_permits()),
+            rejected_requests: self.inner.rejected.load(Ordering::Relaxed),
+            timed_out_requests: self.inner.timed_out.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Whether every connection the pool may open is in use
+    fn is_saturated(&self, pool: &PgPool) -> bool {
+        pool.size() >= self.inner.settings.max_connections && pool.num_idle() == 0
+    }
+
+    /// `503 Service Unavailable` with `Retry-After`
+    fn unavailable(&self) -> Response {
+        let mut response = StatusCode::SERVICE_UNAVAILABLE.into_response();
+        let secs = self.inner.settings.retry_after.as_secs().max(1);
+        response.headers_mut().insert(RETRY_AFTER, HeaderValue::from(secs));
+        response
+    }
+}
+
+/// Middleware applying the [`PoolGuard`] to every request
+///
+/// Does nothing when the server runs without a database.
+#[cfg(feature = "ssr")]
+pub async fn pool_guard_middleware(
+    State(guard): State<PoolGuard>,
+    State(db_pool): State<Option<PgPool>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(pool) = db_pool else {
+        return next.run(request).await;
+    };
+
+    // Requests only count against the queue while the pool is saturated
+    let permit = guard.inner.slots.clone().try_acquire_owned().ok();
+    if permit.is_none() && guard.is_saturated(&pool) {
+        guard.inner.rejected.fetch_add(1, Ordering::Relaxed);
+        tracing::warn!("[POOL] Queue full, rejecting {} {}", request.method(), request.uri().path());
+        return guard.unavailable();
+    }
+
+    let started = Instant::now();
+    let response = next.run(request).await;
+    drop(permit);
+
+    // Handlers report a pool timeout as a plain 500; a failure that took the
+    // whole acquire timeout on a saturated pool is one
+    if response.status() == StatusCode::INTERNAL_SERVER_ERROR
+        && started.elapsed() >= guard.inner.settings.acquire_timeout
+        && guard.is_saturated(&pool)
+    {
+        guard.inner.timed_out.fetch_add(1, Ordering::Relaxed);
+        tracing::warn!("[POOL] Request timed out waiting for a database connection");
+        return guard.unavailable();
+    }
+
+    response
+}
+
+/// Server metrics
+#[cfg(feature = "ssr")]
+#[derive(Debug, Clone, Serialize)]
+pub struct Metrics {
+    pub reconnects: ReconnectStats,
+    /// `None` when running without a database
+    pub pool: Option<PoolStats>,
+    pub subscriptions: SubscriptionStats,
+    /// Reconnections reported by clients
+    pub connection_telemetry: ConnectionTelemetryStats,
+    /// Subscribers that fell behind, per broadcast channel
+    pub broadcast_lag: BTreeMap<BroadcastChannel, ChannelLag>,
+}
+
+/// GET /api/metrics
+#[cfg(feature = "ssr")]
+pub async fn get_metrics(
+    State(reconnect_guard): State<ReconnectGuard>,
+    State(guard): State<PoolGuard>,
+    State(db_pool): State<Option<PgPool>>,
+    State(subscription_limit): State<SubscriptionLimit>,
+    State(connection_telemetry): State<ConnectionTelemetry>,
+    State(broadcast_lag): State<BroadcastLag>,
+) -> Json<Metrics> {
+    Json(Metrics {
+        reconnects: reconnect_guard.stats(),
+        pool: db_pool.as_ref().map(|pool| guard.stats(pool)),
+        subscriptions: subscription_limit.stats(),
+        connection_telemetry: connection_telemetry.stats(),
+        broadcast_lag: broadcast_lag.stats(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, extract::FromRef, routing::get, Router};
+    use tower::ServiceExt;
+    use crate::backend::test_db::TestDatabase;
+
+    #[derive(Clone)]
+    struct TestState {
+        guard: PoolGuard,
+        pool: Option<PgPool>,
+    }
+
+    impl FromRef<TestState> for PoolGuard {
+        fn from_ref(state: &TestState) -> Self {
+            state.guard.clone()
+        }
+    }
+
+    impl FromRef<TestState> for Option<PgPool> {
+        fn from_ref(state: &TestState) -> Self {
+            state.pool.clone()
+        }
+    }
+
+    async fn query(State(pool): State<Option<PgPool>>) -> StatusCode {
+        match sqlx::query("SELECT 1").execute(pool.as_ref().unwrap()).await {
+            Ok(_) => StatusCode::OK,
+            Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_saturated_pool_returns_503() {
+        let settings = PoolSettings {
+            max_connections: 1,
+            acquire_timeout: Duration::from_millis(200),
+            max_queue: 0,
+            retry_after: Duration::from_secs(2),
+        };
+        let pool = settings.pool_options().connect(&TestDatabase::url()).await.unwrap();
+        let state = TestState { guard: PoolGuard::new(settings), pool: Some(pool.clone()) };
+        let app = Router::new()
+            .route("/query", get(query))
+            .layer(axum::middleware::from_fn_with_state(state.clone(), pool_guard_middleware))
+            .with_state(state.clone());
+        let request = || Request::get("/query").body(Body::empty()).unwrap();
+
+        // Hold the only connection so the handler has to wait for it
+        let held = pool.acquire().await.unwrap();
+        assert_eq!(state.guard.stats(&pool).utilization, 1.0);
+
+        let response = app.clone().oneshot(request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()[RETRY_AFTER], "2");
+        assert_eq!(state.guard.stats(&pool).timed_out_requests, 1);
+
+        // Once the connection is back requests go through again
+        drop(held);
+        assert_eq!(app.oneshot(request()).await.unwrap().status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,122 @@
+//! Graceful Shutdown
+//!
+//! On SIGTERM or Ctrl-C the server stops accepting connections and waits
+//! for open ones to finish. Subscriptions never finish by themselves, so
+//! they watch [`Shutdown`] and end their streams when it fires, with a last
+//! keep-alive first so the client sees a clean close rather than a reset.
+//! Clients then reconnect, to a replacement instance during a deploy, and
+//! resume from the last version they saw.
+
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::response::sse::Event;
+use futures_util::stream::{self, Stream, StreamExt};
+use tokio::sync::watch;
+
+/// How long open connections get to close before the server exits anyway
+pub const DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Comment sent as the last line of an SSE subscription closed by shutdown
+pub const SHUTDOWN_COMMENT: &str = "server shutting down";
+
+/// Shared shutdown flag that subscriptions can wait on
+#[derive(Clone, Debug)]
+pub struct Shutdown {
+    tx: Arc<watch::Sender<bool>>,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self { tx: Arc::new(watch::channel(false).0) }
+    }
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tell every subscription to close
+    pub fn trigger(&self) {
+        self.tx.send_replace(true);
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        *self.tx.borrow()
+    }
+
+    /// Resolves once [`Self::trigger`] has been called, even if already
+    pub fn wait(&self) -> impl Future<Output = ()> + Send + 'static {
+        let mut rx = self.tx.subscribe();
+        async move {
+            let _ = rx.wait_for(|triggered| *triggered).await;
+        }
+    }
+
+    /// End an SSE stream on shutdown, closing with [`SHUTDOWN_COMMENT`]
+    pub fn close_sse<S, E>(&self, events: S) -> impl Stream<Item = Result<Event, E>> + Send + 'static
+    where
+        S: Stream<Item = Result<Event, E>> + Send + 'static,
+        E: Send + 'static,
+    {
+        let closing = self.clone();
+        events.take_until(self.wait()).chain(
+            stream::once(async move { closing.is_triggered() })
+                .filter_map(|triggered| async move { triggered.then(|| Ok(Event::default().comment(SHUTDOWN_COMMENT))) }),
+        )
+    }
+}
+
+/// Resolves on Ctrl-C or, on Unix, SIGTERM
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!("Failed to listen for Ctrl-C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                tracing::error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => tracing::warn!("Received Ctrl-C"),
+        _ = terminate => tracing::warn!("Received SIGTERM"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_sse_stream_ends_with_a_comment_on_shutdown() {
+        let shutdown = Shutdown::new();
+        let events = stream::iter([Ok::<_, std::convert::Infallible>(Event::default().data("a"))]).chain(stream::pending());
+        let mut closed = Box::pin(shutdown.close_sse(events));
+
+        assert!(closed.next().await.is_some());
+        shutdown.trigger();
+        let last = closed.next().await.unwrap().unwrap();
+        assert_eq!(format!("{:?}", last), format!("{:?}", Event::default().comment(SHUTDOWN_COMMENT)));
+        assert!(closed.next().await.is_none());
+
+        // Late subscribers see the shutdown straight away
+        shutdown.wait().await;
+    }
+}
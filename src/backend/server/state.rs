@@ -52,6 +52,8 @@ use crate::shared::Message;
 #[cfg(feature = "ssr")]
 use crate::backend::chat::state::ChatState;
 #[cfg(feature = "ssr")]
+use crate::backend::chat::coalesce::BroadcastCoalescer;
+#[cfg(feature = "ssr")]
 use crate::backend::collab::state::CollabState;
 #[cfg(feature = "ssr")]
 use crate::backend::realtime::broadcast::RealtimeEventBroadcast;
@@ -63,17 +65,55 @@ use std::collections::HashMap;
 use uuid::Uuid;
 #[cfg(feature = "ssr")]
 use crate::shared::messaging::ChatMessage;
+#[cfg(feature = "ssr")]
+use crate::backend::messaging::reconnect_guard::ReconnectGuard;
+#[cfg(feature = "ssr")]
+use crate::backend::attachments::AttachmentStorage;
+#[cfg(feature = "ssr")]
+use crate::backend::messaging::link_preview::LinkPreviewService;
+#[cfg(feature = "ssr")]
+use crate::backend::messaging::content_filter::SharedContentFilter;
+#[cfg(feature = "ssr")]
+use crate::backend::messaging::limits::MessageLimits;
+#[cfg(feature = "ssr")]
+use crate::backend::subscription::usage::UsageLimits;
+#[cfg(feature = "ssr")]
+use crate::backend::messaging::conversation_cache::ConversationCache;
+#[cfg(feature = "ssr")]
+use crate::backend::messaging::conversation_events::ConversationEvents;
+#[cfg(feature = "ssr")]
+use crate::backend::ai::AiService;
+#[cfg(feature = "ssr")]
+use crate::backend::messaging::presence::StatusStore;
+#[cfg(feature = "ssr")]
+use crate::backend::server::pool::PoolGuard;
+#[cfg(feature = "ssr")]
+use crate::backend::server::shutdown::Shutdown;
+#[cfg(feature = "ssr")]
+use crate::backend::server::capacity::SubscriptionLimit;
+#[cfg(feature = "ssr")]
+use crate::backend::server::telemetry::ConnectionTelemetry;
+#[cfg(feature = "ssr")]
+use crate::backend::server::lag::BroadcastLag;
+#[cfg(feature = "ssr")]
+use crate::backend::server::metrics::ServerMetrics;
 
 /// Message broadcast event
 ///
-/// This type represents a single broadcast event containing:
-/// - A vector of all current messages
-/// - The version ID associated with this update
+/// Carries only the newly added message(s) as a delta:
+/// - `messages` - the messages added in this version
+/// - `version` - the new version ID
+/// - `parents` - the version the delta applies on top of (empty for the first)
 ///
-/// When a new message is added, all subscribers receive this event
-/// with the complete message list and the new version ID.
+/// Subscribers whose last sent version matches `parents` forward the delta;
+/// anyone who fell behind resyncs with a full snapshot instead.
 #[cfg(feature = "ssr")]
-pub type MessageEvent = (Vec<Message>, String);
+#[derive(Debug, Clone)]
+pub struct MessageEvent {
+    pub messages: Vec<Message>,
+    pub version: String,
+    pub parents: Vec<String>,
+}
 
 /// Broadcast state for messaging conversations
 ///
@@ -197,6 +237,10 @@ pub struct AppState {
     /// This is a chat-specific broadcast channel. When a new message is added,
     /// it's broadcast to all subscribers via this channel.
     pub message_broadcast: broadcast::Sender<MessageEvent>,
+
+    /// Front of `message_broadcast` that merges events arriving within a
+    /// short window into one frame; PUT handlers send through this
+    pub message_coalescer: BroadcastCoalescer,
     
     /// Generic real-time event broadcast channel
     /// 
@@ -224,6 +268,56 @@ pub struct AppState {
     /// Manages per-conversation CRDT state for conflict-free message synchronization.
     /// Each conversation maintains its own MessageCrdt instance.
     pub messaging_crdt: MessagingCrdtState,
+
+    /// Reconnect storm guard for conversation subscriptions
+    ///
+    /// Staggers snapshot replay after a restart and tracks reconnect rate.
+    pub reconnect_guard: ReconnectGuard,
+
+    /// Attachment storage backend (filesystem or S3, selected by config)
+    pub attachment_storage: AttachmentStorage,
+
+    /// Rate-limited link preview fetcher for URLs in new messages
+    pub link_previews: LinkPreviewService,
+
+    /// Moderation hook run on message PUT (no-op unless configured)
+    pub content_filter: SharedContentFilter,
+
+    /// Text length and total size limits for message PUT
+    pub message_limits: MessageLimits,
+
+    /// Per-user daily quotas, e.g. messages sent
+    pub usage_limits: UsageLimits,
+
+    /// LRU cache of conversation metadata and participant lists
+    pub conversation_cache: ConversationCache,
+
+    /// Sheds load when the database pool is saturated
+    pub pool_guard: PoolGuard,
+
+    /// AI provider with per-user quotas (disabled unless configured)
+    pub ai: AiService,
+
+    /// Users' chosen statuses, synced over `/sync/presence`
+    pub user_statuses: StatusStore,
+
+    /// Per-user conversation changes, streamed over `/sync/conversations`
+    pub conversation_events: ConversationEvents,
+
+    /// Fired on SIGTERM/Ctrl-C so open subscriptions close cleanly
+    pub shutdown: Shutdown,
+
+    /// Caps subscriptions open at once across all users
+    pub subscription_limit: SubscriptionLimit,
+
+    /// Reconnection reports from clients that opted in
+    pub connection_telemetry: ConnectionTelemetry,
+
+    /// Subscribers that fell behind on the broadcast channels
+    pub broadcast_lag: BroadcastLag,
+
+    /// Counters and histograms exported at `/metrics`
+    pub metrics: ServerMetrics,
 }
 
 
@@ -278,7 +372,7 @@ impl FromRef<AppState> for Arc<RwLock<CollabState>> {
 /// use braid_site::backend::server::state::MessageEvent;
 /// 
 /// async fn handler(State(tx): State<broadcast::Sender<MessageEvent>>) {
-///     tx.send((messages, version)).ok();
+///     tx.send(MessageEvent { messages, version, parents }).ok();
 /// }
 /// ```
 impl FromRef<AppState> for broadcast::Sender<MessageEvent> {
@@ -320,3 +414,177 @@ impl FromRef<AppState> for MessagingBroadcastState {
     }
 }
 
+#[cfg(feature = "ssr")]
+/// Implement FromRef for ReconnectGuard
+///
+/// This allows subscription handlers to extract the reconnect guard
+/// directly from `AppState`.
+impl FromRef<AppState> for ReconnectGuard {
+    fn from_ref(app_state: &AppState) -> Self {
+        app_state.reconnect_guard.clone()
+    }
+}
+
+#[cfg(feature = "ssr")]
+/// Implement FromRef for AttachmentStorage
+///
+/// This allows attachment handlers to extract the storage backend
+/// directly from `AppState`.
+impl FromRef<AppState> for AttachmentStorage {
+    fn from_ref(app_state: &AppState) -> Self {
+        app_state.attachment_storage.clone()
+    }
+}
+
+#[cfg(feature = "ssr")]
+/// Implement FromRef for LinkPreviewService
+///
+/// This allows the message PUT handler to extract the preview fetcher
+/// directly from `AppState`.
+impl FromRef<AppState> for LinkPreviewService {
+    fn from_ref(app_state: &AppState) -> Self {
+        app_state.link_previews.clone()
+    }
+}
+
+#[cfg(feature = "ssr")]
+/// Implement FromRef for SharedContentFilter
+///
+/// This allows the message PUT handler to extract the content filter
+/// directly from `AppState`.
+impl FromRef<AppState> for SharedContentFilter {
+    fn from_ref(app_state: &AppState) -> Self {
+        app_state.content_filter.clone()
+    }
+}
+
+#[cfg(feature = "ssr")]
+/// Implement FromRef for MessageLimits
+///
+/// This allows message PUT handlers and `/api/limits` to extract the
+/// limits directly from `AppState`.
+impl FromRef<AppState> for MessageLimits {
+    fn from_ref(app_state: &AppState) -> Self {
+        app_state.message_limits
+    }
+}
+
+#[cfg(feature = "ssr")]
+/// Implement FromRef for UsageLimits
+///
+/// This allows the message PUT handler to extract the quotas directly
+/// from `AppState`.
+impl FromRef<AppState> for UsageLimits {
+    fn from_ref(app_state: &AppState) -> Self {
+        app_state.usage_limits
+    }
+}
+
+#[cfg(feature = "ssr")]
+/// Implement FromRef for ConversationCache
+///
+/// This allows handlers that check conversation membership to extract the
+/// cache directly from `AppState`.
+impl FromRef<AppState> for ConversationCache {
+    fn from_ref(app_state: &AppState) -> Self {
+        app_state.conversation_cache.clone()
+    }
+}
+
+#[cfg(feature = "ssr")]
+/// Implement FromRef for AiService
+///
+/// This allows the AI handlers to extract the provider and its quotas
+/// directly from `AppState`.
+impl FromRef<AppState> for AiService {
+    fn from_ref(app_state: &AppState) -> Self {
+        app_state.ai.clone()
+    }
+}
+
+#[cfg(feature = "ssr")]
+/// Implement FromRef for StatusStore
+///
+/// This allows the presence sync handlers to read and set statuses
+/// directly from `AppState`.
+impl FromRef<AppState> for StatusStore {
+    fn from_ref(app_state: &AppState) -> Self {
+        app_state.user_statuses.clone()
+    }
+}
+
+#[cfg(feature = "ssr")]
+/// Implement FromRef for ConversationEvents
+///
+/// This allows the conversation handlers to announce changes and the
+/// `/sync/conversations` stream to subscribe to them.
+impl FromRef<AppState> for ConversationEvents {
+    fn from_ref(app_state: &AppState) -> Self {
+        app_state.conversation_events.clone()
+    }
+}
+
+#[cfg(feature = "ssr")]
+/// Implement FromRef for PoolGuard
+///
+/// This allows the pool guard middleware and `/api/metrics` to extract the
+/// guard directly from `AppState`.
+impl FromRef<AppState> for PoolGuard {
+    fn from_ref(app_state: &AppState) -> Self {
+        app_state.pool_guard.clone()
+    }
+}
+
+#[cfg(feature = "ssr")]
+/// Implement FromRef for Shutdown
+///
+/// This allows subscription handlers to end their streams when the server
+/// is shutting down.
+impl FromRef<AppState> for Shutdown {
+    fn from_ref(app_state: &AppState) -> Self {
+        app_state.shutdown.clone()
+    }
+}
+
+#[cfg(feature = "ssr")]
+/// Implement FromRef for SubscriptionLimit
+///
+/// This allows the capacity middleware and `/api/metrics` to extract the
+/// subscription cap directly from `AppState`.
+impl FromRef<AppState> for SubscriptionLimit {
+    fn from_ref(app_state: &AppState) -> Self {
+        app_state.subscription_limit.clone()
+    }
+}
+
+#[cfg(feature = "ssr")]
+/// Implement FromRef for ConnectionTelemetry
+///
+/// This allows the telemetry endpoint and `/api/metrics` to extract the
+/// client connection reports directly from `AppState`.
+impl FromRef<AppState> for ConnectionTelemetry {
+    fn from_ref(app_state: &AppState) -> Self {
+        app_state.connection_telemetry.clone()
+    }
+}
+
+#[cfg(feature = "ssr")]
+/// Implement FromRef for BroadcastLag
+///
+/// This allows subscription handlers to count lagging subscribers and
+/// `/api/metrics` to report them.
+impl FromRef<AppState> for BroadcastLag {
+    fn from_ref(app_state: &AppState) -> Self {
+        app_state.broadcast_lag.clone()
+    }
+}
+
+#[cfg(feature = "ssr")]
+/// Implement FromRef for ServerMetrics
+///
+/// This allows handlers to count the events exported at `/metrics`.
+impl FromRef<AppState> for ServerMetrics {
+    fn from_ref(app_state: &AppState) -> Self {
+        app_state.metrics.clone()
+    }
+}
@@ -0,0 +1,157 @@
+//! Connection Telemetry
+//!
+//! Receives the opt-in [`ConnectionReport`]s clients send after recovering
+//! from (or giving up on) a dropped subscription. Reports are counted for
+//! `/api/metrics` and, with a database, stored in `connection_telemetry`
+//! for later analysis, so the clients' "will reconnect" warnings can be
+//! lined up with the server's reconnect and capacity metrics.
+//!
+//! Reporting requires a signed-in client, to keep out junk, but the caller
+//! is not recorded: only the report itself is kept.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use serde::Serialize;
+use sqlx::PgPool;
+
+use crate::backend::messaging::handlers::extract_user_id;
+use crate::shared::{ConnectionReport, DisconnectReason};
+
+/// Totals over the reports received since startup
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ConnectionTelemetryStats {
+    pub reports: u64,
+    /// Reports from clients that gave up
+    pub gave_up: u64,
+    /// Reconnect attempts across all reports
+    pub attempts: u64,
+    /// Time spent backing off across all reports
+    pub backoff_ms: u64,
+    pub by_reason: BTreeMap<DisconnectReason, u64>,
+}
+
+/// Aggregates connection reports from clients
+#[derive(Clone, Default)]
+pub struct ConnectionTelemetry {
+    stats: Arc<Mutex<ConnectionTelemetryStats>>,
+}
+
+impl ConnectionTelemetry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count a report
+    pub fn record(&self, report: &ConnectionReport) {
+        let Ok(mut stats) = self.stats.lock() else {
+            return;
+        };
+        stats.reports += 1;
+        stats.gave_up += u64::from(!report.recovered);
+        stats.attempts += u64::from(report.attempts);
+        stats.backoff_ms = stats.backoff_ms.saturating_add(report.backoff_ms);
+        *stats.by_reason.entry(report.reason).or_default() += 1;
+    }
+
+    pub fn stats(&self) -> ConnectionTelemetryStats {
+        self.stats.lock().map(|stats| stats.clone()).unwrap_or_default()
+    }
+}
+
+/// Keep a report for analysis
+async fn store_report(pool: &PgPool, report: &ConnectionReport) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO connection_telemetry (transport, reason, attempts, backoff_ms, recovered)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+    )
+    .bind(report.transport.as_str())
+    .bind(report.reason.as_str())
+    .bind(report.attempts as i32)
+    .bind(report.backoff_ms as i64)
+    .bind(report.recovered)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Record a client's reconnection report
+/// POST /api/telemetry/connection
+///
+/// Answers `202 Accepted`; a report that fails to store is still counted.
+pub async fn post_connection_telemetry(
+    State(telemetry): State<ConnectionTelemetry>,
+    State(db_pool): State<Option<PgPool>>,
+    headers: HeaderMap,
+    Json(report): Json<ConnectionReport>,
+) -> Result<StatusCode, StatusCode> {
+    extract_user_id(&headers)?;
+    if !report.is_plausible() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    telemetry.record(&report);
+    tracing::debug!(
+        "[Telemetry] {:?} outage over {:?}: {} attempts, {} ms backoff, recovered: {}",
+        report.reason, report.transport, report.attempts, report.backoff_ms, report.recovered
+    );
+
+    if let Some(pool) = &db_pool {
+        if let Err(e) = store_report(pool, &report).await {
+            tracing::error!("[Telemetry] Failed to store connection report: {:?}", e);
+        }
+    }
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::auth::sessions::create_token;
+    use crate::shared::ConnectionTransport;
+
+    fn report(recovered: bool) -> ConnectionReport {
+        ConnectionReport {
+            transport: ConnectionTransport::Sse,
+            reason: DisconnectReason::Stalled,
+            attempts: 3,
+            backoff_ms: 7_000,
+            recovered,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_report_is_accepted_and_counted() {
+        let telemetry = ConnectionTelemetry::new();
+        let token = create_token(uuid::Uuid::new_v4(), "test@example.com".to_string()).unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", format!("Bearer {}", token).parse().unwrap());
+
+        for recovered in [true, false] {
+            let status = post_connection_telemetry(State(telemetry.clone()), State(None), headers.clone(), Json(report(recovered)))
+                .await
+                .unwrap();
+            assert_eq!(status, StatusCode::ACCEPTED);
+        }
+
+        let stats = telemetry.stats();
+        assert_eq!((stats.reports, stats.gave_up, stats.attempts, stats.backoff_ms), (2, 1, 6, 14_000));
+        assert_eq!(stats.by_reason[&DisconnectReason::Stalled], 2);
+
+        // Anonymous and implausible reports aren't counted
+        let anonymous = post_connection_telemetry(State(telemetry.clone()), State(None), HeaderMap::new(), Json(report(true))).await;
+        assert_eq!(anonymous, Err(StatusCode::UNAUTHORIZED));
+        let bogus = ConnectionReport { attempts: u32::MAX, ..report(true) };
+        let bogus = post_connection_telemetry(State(telemetry.clone()), State(None), headers, Json(bogus)).await;
+        assert_eq!(bogus, Err(StatusCode::BAD_REQUEST));
+        assert_eq!(telemetry.stats().reports, 2);
+    }
+}
@@ -0,0 +1,193 @@
+//! Usage Limits
+//!
+//! Per-user daily quotas, counted in the `usage_tracking` table for each
+//! UTC day. A message is counted just before it is stored, by the same
+//! statement that checks the quota, so concurrent sends can't go over it.
+//! If storing fails the count is given back, so sends that are refused for
+//! any other reason don't use quota. Edits and deletes never count.
+//!
+//! # Configuration
+//!
+//! - `MESSAGES_PER_DAY` - new messages a user may send per UTC day
+//!   (default 1000)
+
+use chrono::{DateTime, Duration, Utc};
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+
+use crate::shared::messaging::{UsageLimitExceeded, MESSAGE_QUOTA_ERROR};
+
+/// `usage_tracking.metric_type` for messages sent
+pub const MESSAGES_SENT_METRIC: &str = "messages_sent";
+
+/// Default daily message quota
+pub const DEFAULT_MESSAGES_PER_DAY: i64 = 1000;
+
+/// Quotas enforced per user
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UsageLimits {
+    /// New messages per UTC day
+    pub messages_per_day: i64,
+}
+
+impl Default for UsageLimits {
+    fn default() -> Self {
+        Self { messages_per_day: DEFAULT_MESSAGES_PER_DAY }
+    }
+}
+
+impl UsageLimits {
+    /// Read quotas from `MESSAGES_PER_DAY`
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            messages_per_day: std::env::var("MESSAGES_PER_DAY")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|v| *v > 0)
+                .unwrap_or(defaults.messages_per_day),
+        }
+    }
+
+    /// `Err` describing the limit if a user who has sent `used` messages
+    /// today may not send another
+    pub fn check_messages(&self, used: i64, now: DateTime<Utc>) -> Result<(), UsageLimitExceeded> {
+        if used < self.messages_per_day {
+            return Ok(());
+        }
+        Err(self.messages_exceeded(used, now))
+    }
+
+    /// The limit a user who has sent `used` messages today ran into
+    pub fn messages_exceeded(&self, used: i64, now: DateTime<Utc>) -> UsageLimitExceeded {
+        UsageLimitExceeded {
+            error: MESSAGE_QUOTA_ERROR.to_string(),
+            limit: self.messages_per_day,
+            used,
+            resets_at: next_reset(now),
+        }
+    }
+}
+
+/// Start of the next UTC day, when daily quotas reset
+pub fn next_reset(now: DateTime<Utc>) -> DateTime<Utc> {
+    (now.date_naive() + Duration::days(1))
+        .and_hms_opt(0, 0, 0)
+        .map(|midnight| midnight.and_utc())
+        .unwrap_or(now)
+}
+
+/// Messages the user has sent today (UTC)
+pub async fn messages_sent_today(pool: &PgPool, user_id: Uuid) -> Result<i64, sqlx::Error> {
+    let row = sqlx::query(
+        r#"
+        SELECT COALESCE(SUM(count), 0)::BIGINT AS used
+        FROM usage_tracking
+        WHERE user_id = $1 AND metric_type = $2 AND period_start = (NOW() AT TIME ZONE 'UTC')::date
+        "#
+    )
+    .bind(user_id)
+    .bind(MESSAGES_SENT_METRIC)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(row.get("used"))
+}
+
+/// Count one more message sent today (UTC), unless `limit` are already counted
+///
+/// Returns whether it was counted. The check and the count are one
+/// statement, so concurrent sends can't both take the last of the quota.
+pub async fn record_message_sent(pool: &PgPool, user_id: Uuid, limit: i64) -> Result<bool, sqlx::Error> {
+    let counted = sqlx::query(
+        r#"
+        INSERT INTO usage_tracking (user_id, metric_type, count, period_start, period_end)
+        VALUES ($1, $2, 1, (NOW() AT TIME ZONE 'UTC')::date, (NOW() AT TIME ZONE 'UTC')::date)
+        ON CONFLICT (user_id, metric_type, period_start)
+        DO UPDATE SET count = usage_tracking.count + 1
+        WHERE usage_tracking.count < $3
+        RETURNING count
+        "#
+    )
+    .bind(user_id)
+    .bind(MESSAGES_SENT_METRIC)
+    .bind(limit)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(counted.is_some())
+}
+
+/// Give back a message counted by [`record_message_sent`] that wasn't stored
+pub async fn undo_message_sent(pool: &PgPool, user_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        UPDATE usage_tracking SET count = GREATEST(count - 1, 0)
+        WHERE user_id = $1 AND metric_type = $2 AND period_start = (NOW() AT TIME ZONE 'UTC')::date
+        "#
+    )
+    .bind(user_id)
+    .bind(MESSAGES_SENT_METRIC)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::test_db::TestDatabase;
+
+    #[test]
+    fn test_check_messages_reports_when_quota_resets() {
+        let limits = UsageLimits { messages_per_day: 3 };
+        let now = DateTime::parse_from_rfc3339("2024-05-01T22:15:00Z").unwrap().with_timezone(&Utc);
+
+        assert!(limits.check_messages(2, now).is_ok());
+
+        let exceeded = limits.check_messages(3, now).unwrap_err();
+        assert_eq!(exceeded.error, MESSAGE_QUOTA_ERROR);
+        assert_eq!((exceeded.limit, exceeded.used), (3, 3));
+        assert_eq!(serde_json::to_value(&exceeded).unwrap()["resets_at"], "2024-05-02T00:00:00+00:00");
+    }
+
+    #[tokio::test]
+    async fn test_message_quota_counts_per_user() {
+        let db = TestDatabase::new().await;
+        let pool = db.pool().clone();
+        let (alice, bob) = (db.user().await, db.user().await);
+        let limit = 2;
+
+        assert_eq!(messages_sent_today(&pool, alice.id).await.unwrap(), 0);
+        assert!(record_message_sent(&pool, alice.id, limit).await.unwrap());
+        assert!(record_message_sent(&pool, alice.id, limit).await.unwrap());
+        assert!(!record_message_sent(&pool, alice.id, limit).await.unwrap());
+        assert_eq!(messages_sent_today(&pool, alice.id).await.unwrap(), 2);
+
+        // A message that wasn't stored gives its place back
+        undo_message_sent(&pool, alice.id).await.unwrap();
+        assert_eq!(messages_sent_today(&pool, alice.id).await.unwrap(), 1);
+        assert!(record_message_sent(&pool, alice.id, limit).await.unwrap());
+
+        // Other users keep their own quota
+        assert_eq!(messages_sent_today(&pool, bob.id).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_sends_cannot_exceed_quota() {
+        let db = TestDatabase::new().await;
+        let pool = db.pool().clone();
+        let alice = db.user().await;
+        let limit = 3;
+
+        let sends = (0..10).map(|_| {
+            let pool = pool.clone();
+            tokio::spawn(async move { record_message_sent(&pool, alice.id, limit).await.unwrap() })
+        });
+        let counted = futures_util::future::join_all(sends).await.into_iter().filter(|counted| *counted.as_ref().unwrap()).count();
+
+        assert_eq!(counted, 3);
+        assert_eq!(messages_sent_today(&pool, alice.id).await.unwrap(), limit);
+    }
+}
@@ -0,0 +1,527 @@
+//! # API Client
+//!
+//! Typed client for the backend's REST endpoints. It owns the shared
+//! `reqwest::Client`, adds credentials to every authenticated request and
+//! turns failures into [`SharedError`]s:
+//!
+//! - transport failures become `NetworkError`
+//! - non-2xx responses become `HttpError` with the status code and a
+//!   message, localized from the body's error code when it has one
+//! - undecodable bodies become `SerializationError`
+//!
+//! Credentials are the JWT from [`Config`], or `X-Dev-User-Id` when
+//! `DEV_AUTH_BYPASS=1` and `DEV_USER_ID` are set and there is no token.
+//!
+//! ## Usage
+//!
+//! ```rust,no_run
+//! use xfmail::egui_app::api_client::ApiClient;
+//! use xfmail::egui_app::config::Config;
+//!
+//! let api = ApiClient::new(Config::new());
+//! let contacts = api.contacts().await?;
+//! ```
+//!
+//! Methods are async; UI code that runs them on a background thread can use
+//! [`ApiClient::block_on`].
+
+use std::future::Future;
+
+use futures_util::StreamExt;
+use reqwest::{Client, RequestBuilder, Response};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::egui_app::config::{locale_from_env, Config};
+use crate::egui_app::error_text::http_message;
+use crate::egui_app::types::{AuthResponse, LoginRequest, SignupRequest, UserInfo, UserResponse};
+use crate::shared::error::SharedError;
+use crate::shared::PresenceEvent;
+use crate::shared::messaging::{
+    Contact, Conversation, FriendRequest, ListContactsResponse, ListConversationsResponse,
+    EditHistoryResponse, ListFriendRequestsResponse, ListMessagesResponse, MarkConversationUnreadRequest, MessageHistoryPage,
+    RespondFriendRequestRequest, RespondFriendRequestResponse, SendFriendRequestRequest,
+    SendFriendRequestResponse, SetConversationThemeRequest, ThemeColor, PinConversationRequest,
+    ReorderPinnedConversationsRequest, PrivacySettings,
+    CreateConversationRequest, CreateConversationResponse, TranslateMessageRequest, TranslateMessageResponse,
+    SummarizeConversationRequest, SetStatusRequest, UserStatus, BlockedUsersResponse,
+    SearchUsersResponse, UserProfile,
+};
+
+/// Result type for API calls
+pub type ApiResult<T> = Result<T, SharedError>;
+
+/// Add JWT or dev-bypass credentials to a request
+///
+/// Fails with a client-side `401` if there are none, without sending anything.
+pub fn authorize(config: &Config, request: RequestBuilder) -> ApiResult<RequestBuilder> {
+    let (name, value) = auth_header(config)?;
+    Ok(request.header(name, value))
+}
+
+/// The header [`authorize`] adds, for connections not made with reqwest
+pub fn auth_header(config: &Config) -> ApiResult<(&'static str, String)> {
+    if let Some(token) = config.get_token() {
+        return Ok(("Authorization", format!("Bearer {}", token)));
+    }
+    match (config.dev_auth_bypass(), config.dev_user_id()) {
+        (true, Some(uid)) => Ok(("X-Dev-User-Id", uid.to_string())),
+        _ => Err(SharedError::http(401, "Not authenticated")),
+    }
+}
+
+/// Send a request, mapping transport errors and non-2xx statuses
+pub async fn send(request: RequestBuilder) -> ApiResult<Response> {
+    let response = request
+        .send()
+        .await
+        .map_err(|e| SharedError::network(e.to_string()))?;
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let text = response.text().await.unwrap_or_default();
+    let message = http_message(status, &text, &locale_from_env());
+    Err(SharedError::http(status.as_u16(), message))
+}
+
+/// Typed client for the backend API
+#[derive(Debug, Clone)]
+pub struct ApiClient {
+    client: Client,
+    config: Config,
+}
+
+impl ApiClient {
+    pub fn new(config: Config) -> Self {
+        Self::with_client(Client::new(), config)
+    }
+
+    /// Use an existing `reqwest::Client` (e.g. to share its connection pool)
+    pub fn with_client(client: Client, config: Config) -> Self {
+        Self { client, config }
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Run an API call to completion on a fresh runtime
+    ///
+    /// For UI code that calls the API from a plain background thread.
+    pub fn block_on<T>(future: impl Future<Output = ApiResult<T>>) -> ApiResult<T> {
+        let rt = tokio::runtime::Runtime::new()
+            .map_err(|e| SharedError::network(format!("Failed to create runtime: {}", e)))?;
+        rt.block_on(future)
+    }
+
+    /// Add this client's credentials to a request
+    pub fn authorize(&self, request: RequestBuilder) -> ApiResult<RequestBuilder> {
+        authorize(&self.config, request)
+    }
+
+    async fn json<T: DeserializeOwned>(response: Response) -> ApiResult<T> {
+        response
+            .json()
+            .await
+            .map_err(|e| SharedError::serialization(format!("Failed to parse response: {}", e)))
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> ApiResult<T> {
+        let request = self.authorize(self.client.get(self.config.api_url(path)))?;
+        Self::json(send(request).await?).await
+    }
+
+    async fn post<B: Serialize, T: DeserializeOwned>(&self, path: &str, body: &B) -> ApiResult<T> {
+        let request = self.authorize(self.client.post(self.config.api_url(path)))?;
+        Self::json(send(request.json(body)).await?).await
+    }
+
+    async fn put<B: Serialize>(&self, path: &str, body: &B) -> ApiResult<()> {
+        let request = self.authorize(self.client.put(self.config.api_url(path)))?;
+        send(request.json(body)).await.map(|_| ())
+    }
+
+    async fn delete(&self, path: &str) -> ApiResult<()> {
+        let request = self.authorize(self.client.delete(self.config.api_url(path)))?;
+        send(request).await.map(|_| ())
+    }
+
+    // Auth
+
+    /// `POST /api/auth/login`
+    pub async fn login(&self, username: String, password: String) -> ApiResult<AuthResponse> {
+        let request = self.client.post(self.config.api_url("/api/auth/login"));
+        Self::json(send(request.json(&LoginRequest { username, password })).await?).await
+    }
+
+    /// `POST /api/auth/signup`
+    pub async fn signup(&self, username: String, email: String, password: String) -> ApiResult<AuthResponse> {
+        let request = self.client.post(self.config.api_url("/api/auth/signup"));
+        Self::json(send(request.json(&SignupRequest { username, email, password })).await?).await
+    }
+
+    /// `POST /api/auth/refresh`; needs a real token, not dev bypass
+    pub async fn refresh_token(&self) -> ApiResult<AuthResponse> {
+        let token = self.config.get_token().ok_or_else(|| SharedError::http(401, "Not logged in"))?;
+        let request = self
+            .client
+            .post(self.config.api_url("/api/auth/refresh"))
+            .header("Authorization", format!("Bearer {}", token));
+        Self::json(send(request).await?).await
+    }
+
+    /// `GET /api/auth/me`
+    pub async fn me(&self) -> ApiResult<UserInfo> {
+        self.get::<UserResponse>("/api/auth/me").await.map(UserInfo::from)
+    }
+
+    // Contacts
+
+    /// `GET /api/contacts`
+    pub async fn contacts(&self) -> ApiResult<Vec<Contact>> {
+        Ok(self.get::<ListContactsResponse>("/api/contacts").await?.contacts)
+    }
+
+    // Friend requests
+
+    /// `POST /api/friends/request`
+    pub async fn send_friend_request(&self, request: &SendFriendRequestRequest) -> ApiResult<SendFriendRequestResponse> {
+        self.post("/api/friends/request", request).await
+    }
+
+    /// `GET /api/friends/requests`
+    pub async fn friend_requests(&self) -> ApiResult<Vec<FriendRequest>> {
+        Ok(self.get::<ListFriendRequestsResponse>("/api/friends/requests").await?.requests)
+    }
+
+    /// `POST /api/friends/respond`
+    pub async fn respond_to_friend_request(&self, request_id: Uuid, accept: bool) -> ApiResult<RespondFriendRequestResponse> {
+        self.post("/api/friends/respond", &RespondFriendRequestRequest { request_id, accept }).await
+    }
+
+    // Conversations
+
+    /// `GET /api/conversations`
+    pub async fn conversations(&self) -> ApiResult<Vec<Conversation>> {
+        Ok(self.get::<ListConversationsResponse>("/api/conversations").await?.conversations)
+    }
+
+    /// `POST /api/conversations`
+    pub async fn create_conversation(&self, participant_ids: Vec<Uuid>, name: Option<String>) -> ApiResult<CreateConversationResponse> {
+        self.post("/api/conversations", &CreateConversationRequest { participant_ids, name }).await
+    }
+
+    /// `PUT /api/conversations/{id}/participants/{user_id}`
+    pub async fn add_conversation_participant(&self, conversation_id: Uuid, user_id: Uuid) -> ApiResult<()> {
+        self.put(
+            &format!("/api/conversations/{}/participants/{}", conversation_id, user_id),
+            &(),
+        )
+        .await
+    }
+
+    /// `DELETE /api/conversations/{id}/participants/{user_id}`
+    pub async fn remove_conversation_participant(&self, conversation_id: Uuid, user_id: Uuid) -> ApiResult<()> {
+        self.delete(&format!("/api/conversations/{}/participants/{}", conversation_id, user_id))
+            .await
+    }
+
+    /// `PUT /api/conversations/{id}/unread`
+    pub async fn set_conversation_unread(&self, conversation_id: Uuid, unread: bool) -> ApiResult<()> {
+        self.put(
+            &format!("/api/conversations/{}/unread", conversation_id),
+            &MarkConversationUnreadRequest { unread },
+        )
+        .await
+    }
+
+    /// `PUT /api/conversations/{id}/theme`
+    pub async fn set_conversation_theme(&self, conversation_id: Uuid, color: Option<ThemeColor>) -> ApiResult<()> {
+        self.put(
+            &format!("/api/conversations/{}/theme", conversation_id),
+            &SetConversationThemeRequest { color },
+        )
+        .await
+    }
+
+    /// `PUT /api/conversations/{id}/pin`
+    pub async fn set_conversation_pinned(&self, conversation_id: Uuid, pinned: bool) -> ApiResult<()> {
+        self.put(
+            &format!("/api/conversations/{}/pin", conversation_id),
+            &PinConversationRequest { pinned },
+        )
+        .await
+    }
+
+    /// `PUT /api/conversations/pinned`, top of the sidebar first
+    pub async fn reorder_pinned_conversations(&self, conversation_ids: Vec<Uuid>) -> ApiResult<()> {
+        self.put("/api/conversations/pinned", &ReorderPinnedConversationsRequest { conversation_ids })
+            .await
+    }
+
+    // Settings
+
+    /// `GET /api/settings/privacy`
+    pub async fn privacy_settings(&self) -> ApiResult<PrivacySettings> {
+        self.get("/api/settings/privacy").await
+    }
+
+    /// `PUT /api/settings/privacy`
+    pub async fn set_privacy_settings(&self, settings: PrivacySettings) -> ApiResult<()> {
+        self.put("/api/settings/privacy", &settings).await
+    }
+
+    // Messages
+
+    /// `GET /api/conversations/{id}/messages`, newest first
+    pub async fn messages(&self, conversation_id: Uuid, limit: u32, offset: u32) -> ApiResult<ListMessagesResponse> {
+        self.get(&format!(
+            "/api/conversations/{}/messages?limit={}&offset={}",
+            conversation_id, limit, offset
+        ))
+        .await
+    }
+
+    /// `GET /sync/conversations/{id}/messages/history`, oldest first
+    ///
+    /// Pass the previous page's `next_cursor` as `before` to page further back.
+    pub async fn message_history(
+        &self,
+        conversation_id: Uuid,
+        limit: u32,
+        before: Option<Uuid>,
+    ) -> ApiResult<MessageHistoryPage> {
+        let url = self.config.api_url(&format!("/sync/conversations/{}/messages/history", conversation_id));
+        let mut query = vec![("limit", limit.to_string())];
+        if let Some(before) = before {
+            query.push(("before", before.to_string()));
+        }
+        Self::json(send(self.authorize(self.client.get(url).query(&query))?).await?).await
+    }
+
+    /// `GET /sync/conversations/{id}/messages/{id}/history`, versions an edit replaced
+    pub async fn edit_history(&self, conversation_id: Uuid, message_id: Uuid) -> ApiResult<EditHistoryResponse> {
+        let url = self.config.api_url(&format!(
+            "/sync/conversations/{}/messages/{}/history",
+            conversation_id, message_id
+        ));
+        Self::json(send(self.authorize(self.client.get(url))?).await?).await
+    }
+
+    /// `GET /api/messages/search`, best match first
+    pub async fn search_messages(&self, query: &str, limit: u32, offset: u32) -> ApiResult<ListMessagesResponse> {
+        let url = self.config.api_url("/api/messages/search");
+        let request = self.client.get(url).query(&[
+            ("q", query.to_string()),
+            ("limit", limit.to_string()),
+            ("offset", offset.to_string()),
+        ]);
+        Self::json(send(self.authorize(request)?).await?).await
+    }
+
+    /// `PATCH /api/messages/{id}/read`
+    pub async fn mark_message_read(&self, message_id: Uuid) -> ApiResult<()> {
+        let url = self.config.api_url(&format!("/api/messages/{}/read", message_id));
+        send(self.authorize(self.client.patch(url))?).await.map(|_| ())
+    }
+
+    /// `GET /api/users/search`, best match first
+    pub async fn search_users(&self, query: &str) -> ApiResult<Vec<UserProfile>> {
+        let url = self.config.api_url("/api/users/search");
+        let request = self.client.get(url).query(&[("q", query)]);
+        let response: SearchUsersResponse = Self::json(send(self.authorize(request)?).await?).await?;
+        Ok(response.users)
+    }
+
+    // Blocking
+
+    /// `GET /api/users/blocked`
+    pub async fn blocked_users(&self) -> ApiResult<Vec<Uuid>> {
+        let response: BlockedUsersResponse = self.get("/api/users/blocked").await?;
+        Ok(response.user_ids)
+    }
+
+    /// `POST /api/users/{id}/block`
+    pub async fn block_user(&self, user_id: Uuid) -> ApiResult<()> {
+        let url = self.config.api_url(&format!("/api/users/{}/block", user_id));
+        send(self.authorize(self.client.post(url))?).await.map(|_| ())
+    }
+
+    /// `DELETE /api/users/{id}/block`
+    pub async fn unblock_user(&self, user_id: Uuid) -> ApiResult<()> {
+        self.delete(&format!("/api/users/{}/block", user_id)).await
+    }
+
+    // Presence
+
+    /// `POST /api/presence/heartbeat`
+    pub async fn presence_heartbeat(&self) -> ApiResult<()> {
+        let request = self.authorize(self.client.post(self.config.api_url("/api/presence/heartbeat")))?;
+        send(request).await.map(|_| ())
+    }
+
+    /// `GET /api/presence/batch`, for at most `MAX_PRESENCE_BATCH` users
+    pub async fn presence_batch(&self, user_ids: &[Uuid]) -> ApiResult<Vec<PresenceEvent>> {
+        let user_ids = user_ids.iter().map(Uuid::to_string).collect::<Vec<_>>().join(",");
+        let url = self.config.api_url("/api/presence/batch");
+        Self::json(send(self.authorize(self.client.get(url).query(&[("user_ids", user_ids)]))?).await?).await
+    }
+
+    /// `PUT /sync/presence`
+    pub async fn set_status(&self, request: &SetStatusRequest) -> ApiResult<UserStatus> {
+        let builder = self.authorize(self.client.put(self.config.api_url("/sync/presence")))?;
+        Self::json(send(builder.json(request)).await?).await
+    }
+
+    // AI
+
+    /// `POST /api/ai/translate`
+    pub async fn translate_message(&self, request: &TranslateMessageRequest) -> ApiResult<TranslateMessageResponse> {
+        self.post("/api/ai/translate", request).await
+    }
+
+    /// `POST /api/ai/summarize`, handing the summary to `on_text` as it streams in
+    ///
+    /// Returns `false` if the range had nothing to summarize.
+    pub async fn summarize_conversation(
+        &self,
+        request: &SummarizeConversationRequest,
+        mut on_text: impl FnMut(&str),
+    ) -> ApiResult<bool> {
+        let builder = self.authorize(self.client.post(self.config.api_url("/api/ai/summarize")))?;
+        let response = send(builder.json(request)).await?;
+        if response.status() == reqwest::StatusCode::NO_CONTENT {
+            return Ok(false);
+        }
+
+        let mut body = response.bytes_stream();
+        let mut pending = Vec::new();
+        while let Some(chunk) = body.next().await {
+            let chunk = chunk.map_err(|e| SharedError::network(e.to_string()))?;
+            pending.extend_from_slice(&chunk);
+            // A chunk can end mid-character; hold the incomplete tail back
+            let valid = match std::str::from_utf8(&pending) {
+                Ok(text) => text.len(),
+                Err(e) => e.valid_up_to(),
+            };
+            if valid > 0 {
+                on_text(std::str::from_utf8(&pending[..valid]).unwrap_or_default());
+                pending.drain(..valid);
+            }
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::config::AppConfig;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    fn config(server_url: String, token: Option<&str>) -> Config {
+        let mut config = Config::with_builder(AppConfig::builder().server_url(server_url)).unwrap();
+        config.set_token(token.map(str::to_string));
+        config
+    }
+
+    fn json_response(status: &str, body: &str) -> String {
+        format!(
+            "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        )
+    }
+
+    /// Serve `responses` in order, returning the raw requests received
+    async fn serve(responses: Vec<String>) -> (String, tokio::task::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let handle = tokio::spawn(async move {
+            let mut requests = Vec::new();
+            for response in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = vec![0u8; 8192];
+                let n = socket.read(&mut buf).await.unwrap();
+                requests.push(String::from_utf8_lossy(&buf[..n]).to_string());
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+            requests
+        });
+        (url, handle)
+    }
+
+    #[tokio::test]
+    async fn test_conversations_sends_token_and_parses() {
+        let conversation = Conversation::new_direct(Uuid::new_v4(), Uuid::new_v4());
+        let body = serde_json::to_string(&ListConversationsResponse { conversations: vec![conversation.clone()] }).unwrap();
+        let (url, server) = serve(vec![json_response("200 OK", &body)]).await;
+        let api = ApiClient::new(config(url, Some("token")));
+
+        let conversations = api.conversations().await.unwrap();
+        assert_eq!(conversations, vec![conversation]);
+
+        let requests = server.await.unwrap();
+        assert!(requests[0].starts_with("GET /api/conversations "));
+        assert!(requests[0].to_lowercase().contains("authorization: bearer token"));
+    }
+
+    #[tokio::test]
+    async fn test_error_status_maps_to_http_error() {
+        let (url, _server) = serve(vec![json_response("409 Conflict", "Already friends")]).await;
+        let api = ApiClient::new(config(url, Some("token")));
+        let request = SendFriendRequestRequest {
+            to_email: "friend@example.com".to_string(),
+            to_user_id: None,
+            message: None,
+        };
+
+        let error = api.send_friend_request(&request).await.unwrap_err();
+        assert_eq!(error.status(), Some(409));
+        assert!(error.to_string().contains("Already friends"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn test_summaries_stream_text_and_report_empty_ranges() {
+        let text = "[stub] amy: grüße";
+        let streamed = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            text.len(),
+            text
+        );
+        let empty = "HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n".to_string();
+        let (url, server) = serve(vec![streamed, empty]).await;
+        let api = ApiClient::new(config(url, Some("token")));
+        let request = SummarizeConversationRequest {
+            conversation_id: Uuid::new_v4(),
+            from_message_id: None,
+            to_message_id: None,
+        };
+
+        let mut summary = String::new();
+        assert!(api.summarize_conversation(&request, |chunk| summary.push_str(chunk)).await.unwrap());
+        assert_eq!(summary, text);
+
+        let mut called = false;
+        assert!(!api.summarize_conversation(&request, |_| called = true).await.unwrap());
+        assert!(!called);
+
+        let requests = server.await.unwrap();
+        assert!(requests[0].starts_with("POST /api/ai/summarize "));
+    }
+
+    #[tokio::test]
+    async fn test_missing_credentials_fail_without_a_request() {
+        let api = ApiClient::new(config("http://127.0.0.1:9".to_string(), None));
+        let error = api.contacts().await.unwrap_err();
+        assert_eq!(error.status(), Some(401));
+    }
+}
@@ -0,0 +1,293 @@
+//! App Lock
+//!
+//! Locks the UI behind a PIN while keeping the session: the token stays in
+//! memory and background sync keeps running, but content is hidden until the
+//! PIN is entered again. The PIN is stored as a bcrypt hash alongside the
+//! lock settings in the platform config directory
+//! (`<config>/xfmail/app_lock.json`).
+//!
+//! OS biometric unlock isn't wired up yet; only the PIN is accepted.
+//!
+//! After [`FREE_ATTEMPTS`] incorrect PINs, each further one locks out
+//! unlocking for a while, starting at 30 seconds and doubling up to 15
+//! minutes. The count is kept until the app is unlocked.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+/// Allowed PIN lengths (digits only)
+pub const PIN_LENGTH: std::ops::RangeInclusive<usize> = 4..=8;
+
+/// Incorrect PINs allowed before unlocking is delayed
+pub const FREE_ATTEMPTS: u32 = 5;
+
+/// Lockout after the first incorrect PIN past [`FREE_ATTEMPTS`]
+const BASE_LOCKOUT: Duration = Duration::from_secs(30);
+
+/// Longest lockout
+const MAX_LOCKOUT: Duration = Duration::from_secs(15 * 60);
+
+/// Errors from setting or entering the PIN
+#[derive(Debug, thiserror::Error)]
+pub enum LockError {
+    #[error("PIN must be {}-{} digits", PIN_LENGTH.start(), PIN_LENGTH.end())]
+    InvalidPin,
+    #[error("Incorrect PIN")]
+    IncorrectPin,
+    #[error("Too many incorrect PINs, try again in {}s", .0.as_secs_f32().ceil())]
+    LockedOut(Duration),
+    #[error("No PIN has been set")]
+    NoPin,
+    #[error("Failed to hash PIN: {0}")]
+    Hash(#[from] bcrypt::BcryptError),
+    #[error("Failed to save lock settings: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Persisted lock settings
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+struct LockSettings {
+    /// bcrypt hash of the PIN
+    pin_hash: Option<String>,
+    /// Lock automatically when the window is minimized
+    auto_lock_on_minimize: bool,
+}
+
+/// PIN lock state
+#[derive(Debug)]
+pub struct AppLock {
+    settings: LockSettings,
+    path: PathBuf,
+    hash_cost: u32,
+    locked: bool,
+    failed_attempts: u32,
+    /// No PIN is checked before this
+    locked_out_until: Option<Instant>,
+}
+
+impl Default for AppLock {
+    fn default() -> Self {
+        Self::load()
+    }
+}
+
+impl AppLock {
+    /// Default location of the settings file
+    pub fn default_path() -> PathBuf {
+        let mut path = dirs::config_dir().unwrap_or_else(std::env::temp_dir);
+        path.push("xfmail");
+        path.push("app_lock.json");
+        path
+    }
+
+    /// Load from the default location
+    pub fn load() -> Self {
+        Self::load_from(&Self::default_path())
+    }
+
+    /// Load from `path`; a missing or unreadable file means no PIN
+    pub fn load_from(path: &Path) -> Self {
+        let settings = std::fs::read_to_string(path)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+        Self {
+            settings,
+            path: path.to_path_buf(),
+            hash_cost: bcrypt::DEFAULT_COST,
+            locked: false,
+            failed_attempts: 0,
+            locked_out_until: None,
+        }
+    }
+
+    /// Use a different bcrypt cost for new PINs
+    pub fn with_hash_cost(mut self, cost: u32) -> Self {
+        self.hash_cost = cost;
+        self
+    }
+
+    fn save(&self) -> Result<(), LockError> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(&self.settings)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        std::fs::write(&self.path, json)?;
+        Ok(())
+    }
+
+    /// Whether a PIN has been set
+    pub fn has_pin(&self) -> bool {
+        self.settings.pin_hash.is_some()
+    }
+
+    /// Set (or replace) the PIN
+    pub fn set_pin(&mut self, pin: &str) -> Result<(), LockError> {
+        if !PIN_LENGTH.contains(&pin.len()) || !pin.chars().all(|c| c.is_ascii_digit()) {
+            return Err(LockError::InvalidPin);
+        }
+        self.settings.pin_hash = Some(bcrypt::hash(pin, self.hash_cost)?);
+        self.save()
+    }
+
+    /// Remove the PIN, which also disables locking
+    pub fn clear_pin(&mut self) -> Result<(), LockError> {
+        self.settings.pin_hash = None;
+        self.locked = false;
+        self.save()
+    }
+
+    /// Whether the app locks itself when minimized
+    pub fn auto_lock_on_minimize(&self) -> bool {
+        self.settings.auto_lock_on_minimize
+    }
+
+    /// Toggle locking on minimize
+    pub fn set_auto_lock_on_minimize(&mut self, enabled: bool) -> Result<(), LockError> {
+        self.settings.auto_lock_on_minimize = enabled;
+        self.save()
+    }
+
+    /// Whether content is currently hidden
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    /// Failed unlock attempts since the last lock
+    pub fn failed_attempts(&self) -> u32 {
+        self.failed_attempts
+    }
+
+    /// Lock the app
+    ///
+    /// Locking again while locked (e.g. minimizing the lock screen) keeps
+    /// the failed attempts and any lockout.
+    pub fn lock(&mut self) -> Result<(), LockError> {
+        if !self.has_pin() {
+            return Err(LockError::NoPin);
+        }
+        if !self.locked {
+            self.locked = true;
+            self.failed_attempts = 0;
+            self.locked_out_until = None;
+        }
+        Ok(())
+    }
+
+    /// Unlock with `pin`
+    pub fn unlock(&mut self, pin: &str) -> Result<(), LockError> {
+        self.unlock_at(pin, Instant::now())
+    }
+
+    fn unlock_at(&mut self, pin: &str, now: Instant) -> Result<(), LockError> {
+        let hash = self.settings.pin_hash.as_deref().ok_or(LockError::NoPin)?;
+        if let Some(until) = self.locked_out_until.filter(|until| *until > now) {
+            return Err(LockError::LockedOut(until - now));
+        }
+        if bcrypt::verify(pin, hash)? {
+            self.reset();
+            Ok(())
+        } else {
+            self.failed_attempts += 1;
+            self.locked_out_until = lockout_after(self.failed_attempts).map(|lockout| now + lockout);
+            Err(LockError::IncorrectPin)
+        }
+    }
+
+    /// Drop the lock without a PIN (used on logout)
+    pub fn reset(&mut self) {
+        self.locked = false;
+        self.failed_attempts = 0;
+        self.locked_out_until = None;
+    }
+}
+
+/// How long unlocking is refused after `failed_attempts` incorrect PINs
+fn lockout_after(failed_attempts: u32) -> Option<Duration> {
+    let over = failed_attempts.checked_sub(FREE_ATTEMPTS + 1)?;
+    Some(BASE_LOCKOUT.saturating_mul(1 << over.min(16)).min(MAX_LOCKOUT))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app_lock(dir: &tempfile::TempDir) -> AppLock {
+        AppLock::load_from(&dir.path().join("app_lock.json")).with_hash_cost(4)
+    }
+
+    #[test]
+    fn test_unlock_with_correct_and_incorrect_pin() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut lock = app_lock(&dir);
+        assert!(matches!(lock.lock(), Err(LockError::NoPin)));
+
+        lock.set_pin("4821").unwrap();
+        lock.lock().unwrap();
+        assert!(lock.is_locked());
+
+        assert!(matches!(lock.unlock("1111"), Err(LockError::IncorrectPin)));
+        assert!(matches!(lock.unlock(""), Err(LockError::IncorrectPin)));
+        assert!(lock.is_locked());
+        assert_eq!(lock.failed_attempts(), 2);
+
+        lock.unlock("4821").unwrap();
+        assert!(!lock.is_locked());
+        assert_eq!(lock.failed_attempts(), 0);
+    }
+
+    #[test]
+    fn test_repeated_incorrect_pins_lock_out_for_longer_each_time() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut lock = app_lock(&dir);
+        lock.set_pin("4821").unwrap();
+        lock.lock().unwrap();
+        let start = Instant::now();
+
+        for _ in 0..=FREE_ATTEMPTS {
+            assert!(matches!(lock.unlock_at("1111", start), Err(LockError::IncorrectPin)));
+        }
+        // Even the right PIN is refused until the lockout ends
+        let later = start + Duration::from_secs(10);
+        assert!(matches!(lock.unlock_at("4821", later), Err(LockError::LockedOut(left)) if left == Duration::from_secs(20)));
+        assert!(lock.is_locked());
+
+        // The next lockout is twice as long, and locking again doesn't clear it
+        let later = start + BASE_LOCKOUT;
+        assert!(matches!(lock.unlock_at("1111", later), Err(LockError::IncorrectPin)));
+        lock.lock().unwrap();
+        assert!(matches!(lock.unlock_at("4821", later + BASE_LOCKOUT), Err(LockError::LockedOut(_))));
+        assert_eq!(lock.failed_attempts(), FREE_ATTEMPTS + 2);
+
+        lock.unlock_at("4821", later + BASE_LOCKOUT * 2).unwrap();
+        assert!(!lock.is_locked());
+        assert_eq!(lock.failed_attempts(), 0);
+        assert_eq!(lockout_after(FREE_ATTEMPTS), None);
+        assert_eq!(lockout_after(100), Some(MAX_LOCKOUT));
+    }
+
+    #[test]
+    fn test_pin_is_stored_hashed() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut lock = app_lock(&dir);
+        assert!(matches!(lock.set_pin("12a4"), Err(LockError::InvalidPin)));
+        assert!(matches!(lock.set_pin("123"), Err(LockError::InvalidPin)));
+
+        lock.set_pin("482193").unwrap();
+        lock.set_auto_lock_on_minimize(true).unwrap();
+
+        let stored = std::fs::read_to_string(dir.path().join("app_lock.json")).unwrap();
+        assert!(!stored.contains("482193"));
+
+        // A fresh load keeps the PIN and settings but starts unlocked
+        let mut reloaded = app_lock(&dir);
+        assert!(reloaded.has_pin());
+        assert!(reloaded.auto_lock_on_minimize());
+        assert!(!reloaded.is_locked());
+        reloaded.lock().unwrap();
+        reloaded.unlock("482193").unwrap();
+    }
+}
@@ -1,13 +1,18 @@
 /**
  * Authentication Module
  * 
- * Handles authentication UI and HTTP client functions for login/signup.
+ * Handles authentication state and blocking login/signup helpers built on
+ * `ApiClient`.
  */
 
+use crate::egui_app::api_client::ApiClient;
 use crate::egui_app::config::Config;
-use crate::egui_app::types::{AuthResponse, UserInfo, LoginRequest, SignupRequest, UserResponse};
-use reqwest::Client;
-use tokio::runtime::Runtime;
+use crate::egui_app::types::{AuthResponse, UserInfo};
+use base64::Engine;
+use chrono::{DateTime, Utc};
+
+/// Refresh the token once it is this close to expiring
+pub const REFRESH_WINDOW_DAYS: i64 = 3;
 
 /// Authentication state
 #[derive(Debug, Clone)]
@@ -49,35 +54,8 @@ pub fn login(
     username: String,
     password: String,
 ) -> Result<AuthResponse, String> {
-    let client = Client::new();
-    let url = config.api_url("/api/auth/login");
-
-    let request = LoginRequest { username, password };
-
-    // Create a runtime for async execution
-    let rt = Runtime::new().map_err(|e| format!("Failed to create runtime: {}", e))?;
-
-    rt.block_on(async {
-        let response = client
-            .post(&url)
-            .json(&request)
-            .send()
-            .await
-            .map_err(|e| format!("Network error: {}", e))?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await.unwrap_or_else(|_| status.to_string());
-            return Err(format!("Login failed: {} - {}", status, error_text));
-        }
-
-        let auth_response: AuthResponse = response
-            .json()
-            .await
-            .map_err(|e| format!("Failed to parse response: {}", e))?;
-
-        Ok(auth_response)
-    })
+    let api = ApiClient::new(config.clone());
+    ApiClient::block_on(api.login(username, password)).map_err(|e| format!("Login failed: {}", e))
 }
 
 /// Signup new user with username, email, and password
@@ -87,66 +65,47 @@ pub fn signup(
     email: String,
     password: String,
 ) -> Result<AuthResponse, String> {
-    let client = Client::new();
-    let url = config.api_url("/api/auth/signup");
-
-    let request = SignupRequest { username, email, password };
-
-    // Create a runtime for async execution
-    let rt = Runtime::new().map_err(|e| format!("Failed to create runtime: {}", e))?;
-
-    rt.block_on(async {
-        let response = client
-            .post(&url)
-            .json(&request)
-            .send()
-            .await
-            .map_err(|e| format!("Network error: {}", e))?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await.unwrap_or_else(|_| status.to_string());
-            return Err(format!("Signup failed: {} - {}", status, error_text));
-        }
+    let api = ApiClient::new(config.clone());
+    ApiClient::block_on(api.signup(username, email, password)).map_err(|e| format!("Signup failed: {}", e))
+}
 
-        let auth_response: AuthResponse = response
-            .json()
-            .await
-            .map_err(|e| format!("Failed to parse response: {}", e))?;
+/// Exchange the current token for a fresh one
+///
+/// The server accepts tokens up to 7 days past expiry, so this also recovers
+/// a session that lapsed while the app was closed.
+pub fn refresh_token(config: &Config) -> Result<AuthResponse, String> {
+    let api = ApiClient::new(config.clone());
+    ApiClient::block_on(api.refresh_token()).map_err(|e| format!("Refresh failed: {}", e))
+}
+
+/// Expiry (`exp` claim) of a JWT, read without verifying the signature
+pub fn token_expiry(token: &str) -> Option<DateTime<Utc>> {
+    #[derive(serde::Deserialize)]
+    struct Expiry {
+        exp: i64,
+    }
+
+    let payload = token.split('.').nth(1)?;
+    let json = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(payload.trim_end_matches('='))
+        .ok()?;
+    let claims: Expiry = serde_json::from_slice(&json).ok()?;
+    DateTime::from_timestamp(claims.exp, 0)
+}
 
-        Ok(auth_response)
-    })
+/// Whether `token` expires within [`REFRESH_WINDOW_DAYS`] of `now`
+///
+/// Tokens without a readable expiry (e.g. dev bypass) are never refreshed.
+pub fn token_needs_refresh(token: &str, now: DateTime<Utc>) -> bool {
+    token_expiry(token).is_some_and(|exp| exp - now <= chrono::Duration::days(REFRESH_WINDOW_DAYS))
 }
 
 /// Get current user info with token
 pub fn get_me(config: &Config, token: &str) -> Result<UserInfo, String> {
-    let client = Client::new();
-    let url = config.api_url("/api/auth/me");
-    
-    // Create a runtime for async execution
-    let rt = Runtime::new().map_err(|e| format!("Failed to create runtime: {}", e))?;
-    
-    rt.block_on(async {
-        let response = client
-            .get(&url)
-            .header("Authorization", format!("Bearer {}", token))
-            .send()
-            .await
-            .map_err(|e| format!("Network error: {}", e))?;
-        
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await.unwrap_or_else(|_| status.to_string());
-            return Err(format!("Get user failed: {} - {}", status, error_text));
-        }
-        
-        let user_response: UserResponse = response
-            .json()
-            .await
-            .map_err(|e| format!("Failed to parse response: {}", e))?;
-        
-        Ok(UserInfo::from(user_response))
-    })
+    let mut config = config.clone();
+    config.set_token(Some(token.to_string()));
+    let api = ApiClient::new(config);
+    ApiClient::block_on(api.me()).map_err(|e| format!("Get user failed: {}", e))
 }
 
 #[cfg(test)]
@@ -179,6 +138,29 @@ mod tests {
         assert!(state.error.is_none());
     }
 
+    fn jwt_expiring_at(exp: DateTime<Utc>) -> String {
+        let encode = |json: String| base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(json);
+        format!(
+            "{}.{}.signature",
+            encode(r#"{"alg":"HS256","typ":"JWT"}"#.to_string()),
+            encode(format!(r#"{{"sub":"user","exp":{}}}"#, exp.timestamp()))
+        )
+    }
+
+    #[test]
+    fn test_token_needs_refresh_near_expiry() {
+        let now = Utc::now();
+        let fresh = jwt_expiring_at(now + chrono::Duration::days(20));
+        let expiring = jwt_expiring_at(now + chrono::Duration::days(2));
+        let expired = jwt_expiring_at(now - chrono::Duration::days(1));
+
+        assert_eq!(token_expiry(&fresh).map(|t| t.timestamp()), Some((now + chrono::Duration::days(20)).timestamp()));
+        assert!(!token_needs_refresh(&fresh, now));
+        assert!(token_needs_refresh(&expiring, now));
+        assert!(token_needs_refresh(&expired, now));
+        assert!(!token_needs_refresh("dev-token", now));
+    }
+
     #[test]
     fn test_auth_state_set_error() {
         let mut state = AuthState::new();
@@ -0,0 +1,145 @@
+//! Unread Badge
+//!
+//! Mirrors the total unread count onto the taskbar/dock badge and the window
+//! title, and clears both once everything is read.
+//!
+//! # Platforms
+//!
+//! - **Linux**: Unity `LauncherEntry` D-Bus signal (via `gdbus`), honoured by
+//!   GNOME/Ubuntu docks, KDE and Plank. Requires the `xfmail.desktop` entry.
+//! - **Others**: no native badge yet; [`NoopBadge`] is used and only the
+//!   window title shows the count.
+
+/// Desktop entry the Linux launcher badge is attached to
+pub const DESKTOP_ENTRY: &str = "xfmail.desktop";
+
+/// Sets the OS badge; a count of zero clears it
+pub trait BadgeBackend: Send {
+    fn set_count(&mut self, count: u32);
+}
+
+/// Fallback for platforms without a badge API
+#[derive(Debug, Default)]
+pub struct NoopBadge;
+
+impl BadgeBackend for NoopBadge {
+    fn set_count(&mut self, _count: u32) {}
+}
+
+/// Unity launcher API badge (Linux)
+#[cfg(target_os = "linux")]
+#[derive(Debug, Default)]
+pub struct LauncherBadge;
+
+#[cfg(target_os = "linux")]
+impl BadgeBackend for LauncherBadge {
+    fn set_count(&mut self, count: u32) {
+        let properties = format!(
+            "{{'count': <int64 {}>, 'count-visible': <{}>}}",
+            count,
+            count > 0
+        );
+        let app_uri = format!("application://{}", DESKTOP_ENTRY);
+
+        // Fire and forget; a missing gdbus or dock just means no badge
+        std::thread::spawn(move || {
+            let result = std::process::Command::new("gdbus")
+                .args([
+                    "emit",
+                    "--session",
+                    "--object-path",
+                    "/com/xfmail/launcher",
+                    "--signal",
+                    "com.canonical.Unity.LauncherEntry.Update",
+                    &app_uri,
+                    &properties,
+                ])
+                .status();
+            if let Err(e) = result {
+                tracing::debug!("Failed to update launcher badge: {}", e);
+            }
+        });
+    }
+}
+
+/// Badge backend for the current platform
+pub fn platform_backend() -> Box<dyn BadgeBackend> {
+    #[cfg(target_os = "linux")]
+    {
+        Box::new(LauncherBadge)
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        Box::new(NoopBadge)
+    }
+}
+
+/// Window title with the unread count, e.g. "XFChat - Messaging (3)"
+pub fn window_title(base: &str, unread: u32) -> String {
+    if unread == 0 {
+        base.to_string()
+    } else {
+        format!("{} ({})", base, unread)
+    }
+}
+
+/// Pushes the aggregate unread count to a [`BadgeBackend`] when it changes
+pub struct UnreadBadge {
+    backend: Box<dyn BadgeBackend>,
+    last: Option<u32>,
+}
+
+impl Default for UnreadBadge {
+    fn default() -> Self {
+        Self::new(platform_backend())
+    }
+}
+
+impl UnreadBadge {
+    pub fn new(backend: Box<dyn BadgeBackend>) -> Self {
+        Self { backend, last: None }
+    }
+
+    /// Record the current total; returns whether it changed (and the badge was updated)
+    pub fn update(&mut self, total: u32) -> bool {
+        if self.last == Some(total) {
+            return false;
+        }
+        self.last = Some(total);
+        self.backend.set_count(total);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default, Clone)]
+    struct RecordingBadge(Arc<Mutex<Vec<u32>>>);
+
+    impl BadgeBackend for RecordingBadge {
+        fn set_count(&mut self, count: u32) {
+            self.0.lock().unwrap().push(count);
+        }
+    }
+
+    #[test]
+    fn test_backend_called_only_when_total_changes() {
+        let recorder = RecordingBadge::default();
+        let mut badge = UnreadBadge::new(Box::new(recorder.clone()));
+
+        for total in [3, 3, 5, 5, 0, 0] {
+            badge.update(total);
+        }
+
+        assert_eq!(*recorder.0.lock().unwrap(), vec![3, 5, 0]);
+    }
+
+    #[test]
+    fn test_window_title() {
+        assert_eq!(window_title("XFChat", 0), "XFChat");
+        assert_eq!(window_title("XFChat", 12), "XFChat (12)");
+    }
+}
@@ -6,6 +6,7 @@
  */
 
 use crate::egui_app::config::Config;
+use crate::egui_app::error_text::http_message;
 use crate::shared::Message;
 use reqwest::Client;
 use tokio::runtime::Runtime;
@@ -87,9 +88,8 @@ impl BraidClient {
             
             if !response.status().is_success() {
                 let status = response.status();
-                let error_text = response.text().await
-                .unwrap_or_else(|_| status.to_string());
-                return Err(format!("PUT failed: {} - {}", status, error_text));
+                let error_text = response.text().await.unwrap_or_default();
+                return Err(http_message(status, &error_text, self.config.locale()));
             }
 
             // Extract version from Version header (Structured Headers format)
@@ -1,8 +1,68 @@
+use chrono::{DateTime, Days, FixedOffset, NaiveTime, TimeZone, Utc};
+
 use crate::shared::config::{AppConfig, AppConfigBuilder, ConfigError};
+use crate::shared::messaging::normalize_locale;
 
 /// Default server URL
 const DEFAULT_SERVER_URL: &str = "http://127.0.0.1:3000";
 
+/// Messages replayed when subscribing to a conversation
+const DEFAULT_SNAPSHOT_LIMIT: u32 = 50;
+
+/// Snapshot size in low data mode
+const LOW_DATA_SNAPSHOT_LIMIT: u32 = 15;
+
+/// Sync/reconnect intervals are multiplied by this in low data mode
+const LOW_DATA_INTERVAL_FACTOR: u32 = 4;
+
+/// Inactivity timeout from `XFMAIL_INACTIVITY_TIMEOUT_MINS` (unset or 0 disables it)
+fn inactivity_timeout_from_env() -> Option<std::time::Duration> {
+    std::env::var("XFMAIL_INACTIVITY_TIMEOUT_MINS")
+        .ok()
+        .and_then(|mins| mins.trim().parse::<u64>().ok())
+        .filter(|mins| *mins > 0)
+        .map(|mins| std::time::Duration::from_secs(mins * 60))
+}
+
+/// Display locale from `LC_ALL`, `LC_MESSAGES` or `LANG` (defaults to `en`)
+pub(crate) fn locale_from_env() -> String {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find_map(|value| normalize_locale(&value))
+        .unwrap_or_else(|| "en".to_string())
+}
+
+/// Daily do-not-disturb window in local time
+///
+/// `start` after `end` spans midnight (e.g. 22:00–08:00); equal times mean
+/// the schedule never applies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DndSchedule {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl Default for DndSchedule {
+    fn default() -> Self {
+        Self {
+            start: NaiveTime::from_hms_opt(22, 0, 0).expect("valid time"),
+            end: NaiveTime::from_hms_opt(8, 0, 0).expect("valid time"),
+        }
+    }
+}
+
+impl DndSchedule {
+    /// Whether `time` falls inside the window (start inclusive, end exclusive)
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
 /// Application configuration wrapper.
 #[derive(Debug, Clone)]
 pub struct Config {
@@ -10,6 +70,17 @@ pub struct Config {
     token: Option<String>,
     dev_auth_bypass: bool,
     dev_user_id: Option<String>,
+    onboarding_completed: bool,
+    low_data_mode: bool,
+    connection_telemetry: bool,
+    notifications_enabled: bool,
+    notification_sound: bool,
+    do_not_disturb: bool,
+    dnd_until: Option<DateTime<Utc>>,
+    dnd_schedule: Option<DndSchedule>,
+    timezone: FixedOffset,
+    inactivity_timeout: Option<std::time::Duration>,
+    locale: String,
 }
 
 impl Default for Config {
@@ -22,7 +93,25 @@ impl Default for Config {
             .expect("default app config is valid");
         let dev_auth_bypass = std::env::var("DEV_AUTH_BYPASS").unwrap_or_default() == "1";
         let dev_user_id = std::env::var("DEV_USER_ID").ok();
-        Self { app, token: None, dev_auth_bypass, dev_user_id }
+        let low_data_mode = std::env::var("XFMAIL_LOW_DATA_MODE").unwrap_or_default() == "1";
+        let connection_telemetry = std::env::var("XFMAIL_CONNECTION_TELEMETRY").unwrap_or_default() == "1";
+        Self {
+            app,
+            token: None,
+            dev_auth_bypass,
+            dev_user_id,
+            onboarding_completed: false,
+            low_data_mode,
+            connection_telemetry,
+            notifications_enabled: true,
+            notification_sound: true,
+            do_not_disturb: false,
+            dnd_until: None,
+            dnd_schedule: None,
+            timezone: *chrono::Local::now().offset(),
+            inactivity_timeout: inactivity_timeout_from_env(),
+            locale: locale_from_env(),
+        }
     }
 }
 
@@ -36,7 +125,25 @@ impl Config {
         let app = builder.build()?;
         let dev_auth_bypass = std::env::var("DEV_AUTH_BYPASS").unwrap_or_default() == "1";
         let dev_user_id = std::env::var("DEV_USER_ID").ok();
-        Ok(Self { app, token: None, dev_auth_bypass, dev_user_id })
+        let low_data_mode = std::env::var("XFMAIL_LOW_DATA_MODE").unwrap_or_default() == "1";
+        let connection_telemetry = std::env::var("XFMAIL_CONNECTION_TELEMETRY").unwrap_or_default() == "1";
+        Ok(Self {
+            app,
+            token: None,
+            dev_auth_bypass,
+            dev_user_id,
+            onboarding_completed: false,
+            low_data_mode,
+            connection_telemetry,
+            notifications_enabled: true,
+            notification_sound: true,
+            do_not_disturb: false,
+            dnd_until: None,
+            dnd_schedule: None,
+            timezone: *chrono::Local::now().offset(),
+            inactivity_timeout: inactivity_timeout_from_env(),
+            locale: locale_from_env(),
+        })
     }
 
     /// Set the JWT token
@@ -72,6 +179,155 @@ impl Config {
     pub fn dev_user_id(&self) -> Option<&str> {
         self.dev_user_id.as_deref()
     }
+
+    /// Whether the first-run onboarding flow has been completed
+    pub fn onboarding_completed(&self) -> bool {
+        self.onboarding_completed
+    }
+
+    /// Mark the first-run onboarding flow as completed (or reset it)
+    pub fn set_onboarding_completed(&mut self, completed: bool) {
+        self.onboarding_completed = completed;
+    }
+
+    /// Whether low data mode is on (no media fetches, smaller snapshots, slower sync)
+    pub fn low_data_mode(&self) -> bool {
+        self.low_data_mode
+    }
+
+    /// Toggle low data mode
+    pub fn set_low_data_mode(&mut self, enabled: bool) {
+        self.low_data_mode = enabled;
+    }
+
+    /// Whether reconnection reports are sent to the server (opt-in, off by default)
+    pub fn connection_telemetry(&self) -> bool {
+        self.connection_telemetry
+    }
+
+    /// Opt in to or out of reconnection reports
+    pub fn set_connection_telemetry(&mut self, enabled: bool) {
+        self.connection_telemetry = enabled;
+    }
+
+    /// Whether avatars, images, thumbnails and preview images may be downloaded
+    pub fn media_fetch_enabled(&self) -> bool {
+        !self.low_data_mode
+    }
+
+    /// Number of messages to request when subscribing to a conversation
+    pub fn snapshot_limit(&self) -> u32 {
+        if self.low_data_mode { LOW_DATA_SNAPSHOT_LIMIT } else { DEFAULT_SNAPSHOT_LIMIT }
+    }
+
+    /// Whether desktop notifications are shown for new messages
+    pub fn notifications_enabled(&self) -> bool {
+        self.notifications_enabled
+    }
+
+    /// Toggle desktop notifications
+    pub fn set_notifications_enabled(&mut self, enabled: bool) {
+        self.notifications_enabled = enabled;
+    }
+
+    /// Whether notifications play a sound
+    pub fn notification_sound(&self) -> bool {
+        self.notification_sound
+    }
+
+    /// Toggle the notification sound
+    pub fn set_notification_sound(&mut self, enabled: bool) {
+        self.notification_sound = enabled;
+    }
+
+    /// Whether do-not-disturb is on (no notifications or sounds)
+    pub fn do_not_disturb(&self) -> bool {
+        self.do_not_disturb
+    }
+
+    /// Toggle do-not-disturb
+    pub fn set_do_not_disturb(&mut self, enabled: bool) {
+        self.do_not_disturb = enabled;
+        if !enabled {
+            self.dnd_until = None;
+        }
+    }
+
+    /// End of a temporary do-not-disturb period, if one is set
+    pub fn dnd_until(&self) -> Option<DateTime<Utc>> {
+        self.dnd_until
+    }
+
+    /// Silence notifications until `until`
+    pub fn set_dnd_until(&mut self, until: Option<DateTime<Utc>>) {
+        self.dnd_until = until;
+    }
+
+    /// Silence notifications until local midnight after `now`
+    pub fn dnd_until_tomorrow(&mut self, now: DateTime<Utc>) {
+        let tomorrow = now.with_timezone(&self.timezone).date_naive() + Days::new(1);
+        let midnight = self
+            .timezone
+            .from_local_datetime(&tomorrow.and_time(NaiveTime::MIN))
+            .single()
+            .map(|t| t.with_timezone(&Utc));
+        self.dnd_until = midnight;
+    }
+
+    /// Daily do-not-disturb window, if enabled
+    pub fn dnd_schedule(&self) -> Option<DndSchedule> {
+        self.dnd_schedule
+    }
+
+    /// Enable (`Some`) or disable (`None`) the daily do-not-disturb window
+    pub fn set_dnd_schedule(&mut self, schedule: Option<DndSchedule>) {
+        self.dnd_schedule = schedule;
+    }
+
+    /// Timezone the do-not-disturb schedule is evaluated in (defaults to the system's)
+    pub fn timezone(&self) -> FixedOffset {
+        self.timezone
+    }
+
+    pub fn set_timezone(&mut self, timezone: FixedOffset) {
+        self.timezone = timezone;
+    }
+
+    /// Whether notifications are silenced at `now` (manual toggle, temporary, or scheduled)
+    pub fn do_not_disturb_at(&self, now: DateTime<Utc>) -> bool {
+        if self.do_not_disturb || self.dnd_until.is_some_and(|until| now < until) {
+            return true;
+        }
+        self.dnd_schedule
+            .is_some_and(|schedule| schedule.contains(now.with_timezone(&self.timezone).time()))
+    }
+
+    /// Idle time after which the session is logged out, if enabled
+    pub fn inactivity_timeout(&self) -> Option<std::time::Duration> {
+        self.inactivity_timeout
+    }
+
+    /// Enable (or disable with `None`) auto-logout after inactivity
+    pub fn set_inactivity_timeout(&mut self, timeout: Option<std::time::Duration>) {
+        self.inactivity_timeout = timeout;
+    }
+
+    /// Language messages are translated into, as a BCP 47 tag
+    pub fn locale(&self) -> &str {
+        &self.locale
+    }
+
+    /// Set the translation language; ignored unless it is a valid locale
+    pub fn set_locale(&mut self, locale: &str) {
+        if let Some(locale) = normalize_locale(locale) {
+            self.locale = locale;
+        }
+    }
+
+    /// Scale a sync or reconnect interval for the current data mode
+    pub fn sync_interval(&self, base: std::time::Duration) -> std::time::Duration {
+        if self.low_data_mode { base * LOW_DATA_INTERVAL_FACTOR } else { base }
+    }
 }
 
 #[cfg(test)]
@@ -106,4 +362,58 @@ mod tests {
         let url = config.api_url("/api/auth/login");
         assert_eq!(url, "http://127.0.0.1:3000/api/auth/login");
     }
+
+    #[test]
+    fn test_onboarding_flag() {
+        let mut config = Config::new();
+        assert!(!config.onboarding_completed());
+        config.set_onboarding_completed(true);
+        assert!(config.onboarding_completed());
+    }
+
+    #[test]
+    fn test_low_data_mode() {
+        let mut config = Config::new();
+        config.set_low_data_mode(false);
+        let base = std::time::Duration::from_secs(1);
+        assert!(config.media_fetch_enabled());
+        assert_eq!(config.sync_interval(base), base);
+
+        config.set_low_data_mode(true);
+        assert!(!config.media_fetch_enabled());
+        assert!(config.snapshot_limit() < DEFAULT_SNAPSHOT_LIMIT);
+        assert_eq!(config.sync_interval(base), base * LOW_DATA_INTERVAL_FACTOR);
+    }
+
+    #[test]
+    fn test_dnd_schedule_in_configured_timezone() {
+        let mut config = Config::new();
+        config.set_dnd_schedule(Some(DndSchedule::default()));
+        config.set_timezone(FixedOffset::east_opt(2 * 3600).unwrap());
+
+        // 21:30 UTC is 23:30 local: inside 22:00–08:00
+        let late = Utc.with_ymd_and_hms(2024, 3, 1, 21, 30, 0).unwrap();
+        assert!(config.do_not_disturb_at(late));
+        // 10:00 UTC is 12:00 local: outside
+        let noon = Utc.with_ymd_and_hms(2024, 3, 1, 10, 0, 0).unwrap();
+        assert!(!config.do_not_disturb_at(noon));
+        // 05:59 UTC is 07:59 local: still inside
+        assert!(config.do_not_disturb_at(Utc.with_ymd_and_hms(2024, 3, 1, 5, 59, 0).unwrap()));
+    }
+
+    #[test]
+    fn test_dnd_until_tomorrow() {
+        let mut config = Config::new();
+        config.set_timezone(FixedOffset::east_opt(0).unwrap());
+        let now = Utc.with_ymd_and_hms(2024, 3, 1, 15, 0, 0).unwrap();
+
+        config.dnd_until_tomorrow(now);
+        assert_eq!(config.dnd_until(), Some(Utc.with_ymd_and_hms(2024, 3, 2, 0, 0, 0).unwrap()));
+        assert!(config.do_not_disturb_at(now));
+        assert!(!config.do_not_disturb_at(Utc.with_ymd_and_hms(2024, 3, 2, 0, 0, 1).unwrap()));
+
+        config.dnd_until_tomorrow(now);
+        config.set_do_not_disturb(false);
+        assert!(!config.do_not_disturb_at(now));
+    }
 }
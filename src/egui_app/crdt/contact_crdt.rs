@@ -97,8 +97,7 @@ impl ContactCrdt {
 
         // Record operation
         let op_id = self.operations.len() as u64 + 1;
-        let data = serde_json::to_vec(&ContactOperation { user_id, status })
-            .unwrap_or_default();
+        let data = OperationMeta::encode_data(&ContactOperation { user_id, status });
 
         let operation = OperationMeta {
             id: op_id,
@@ -138,7 +137,7 @@ impl CrdtState for ContactCrdt {
     }
 
     fn apply_operation(&mut self, op: &OperationMeta) -> Result<(), String> {
-        if let Ok(contact_op) = serde_json::from_slice::<ContactOperation>(&op.data) {
+        if let Ok(contact_op) = op.decode_data::<ContactOperation>() {
             self.update_relationship(contact_op.user_id, contact_op.status);
         }
         Ok(())
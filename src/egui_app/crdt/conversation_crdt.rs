@@ -33,7 +33,7 @@
 //! conversation.merge(&remote_state);
 //! ```
 
-use crate::egui_app::crdt::{CrdtState, MergeResult, OperationMeta, OperationType};
+use crate::egui_app::crdt::{CrdtState, MergeResult, OperationMeta, OperationType, Resolution};
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use uuid::Uuid;
@@ -147,8 +147,7 @@ impl ConversationCrdt {
         self.participants.active.insert(user_id);
 
         // Create operation metadata
-        let data = serde_json::to_vec(&ParticipantOperation::Add { user_id })
-            .unwrap_or_default();
+        let data = OperationMeta::encode_data(&ParticipantOperation::Add { user_id });
 
         let operation = OperationMeta {
             id: op_id,
@@ -174,8 +173,7 @@ impl ConversationCrdt {
         self.participants.active.remove(&user_id);
 
         // Create operation metadata
-        let data = serde_json::to_vec(&ParticipantOperation::Remove { user_id })
-            .unwrap_or_default();
+        let data = OperationMeta::encode_data(&ParticipantOperation::Remove { user_id });
 
         let operation = OperationMeta {
             id: op_id,
@@ -195,10 +193,9 @@ impl ConversationCrdt {
         self.metadata.updated_at = chrono::Utc::now().to_rfc3339();
 
         let op_id = self.next_operation_id();
-        let data = serde_json::to_vec(&MetadataOperation::UpdateName {
+        let data = OperationMeta::encode_data(&MetadataOperation::UpdateName {
             name: name.to_string(),
-        })
-        .unwrap_or_default();
+        });
 
         let operation = OperationMeta {
             id: op_id,
@@ -222,6 +219,38 @@ impl ConversationCrdt {
         }
     }
 
+    /// Settle a conflict reported by [`CrdtState::merge`]
+    ///
+    /// `remote_data` is the conflict's remote payload. Keeping theirs adopts
+    /// the remote state; keeping both applies every remote operation we can
+    /// and skips the rest.
+    pub fn resolve_conflict(&mut self, remote_data: &[u8], resolution: Resolution) -> Result<(), String> {
+        let remote: ConversationCrdt = serde_json::from_slice(remote_data)
+            .map_err(|e| format!("Unreadable remote conversation: {}", e))?;
+        if remote.conversation_id != self.conversation_id {
+            return Err("Conflict is for a different conversation".to_string());
+        }
+
+        match resolution {
+            Resolution::KeepMine => {}
+            Resolution::KeepTheirs => {
+                *self = Self { agent_id: self.agent_id, ..remote };
+            }
+            Resolution::KeepBoth => {
+                for op in &remote.operations {
+                    if self.operations.iter().any(|existing| existing.id == op.id) {
+                        continue;
+                    }
+                    if let Err(e) = self.apply_operation(op) {
+                        tracing::warn!("Skipping conversation operation {}: {}", op.id, e);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     /// Generate next operation ID
     fn next_operation_id(&self) -> u64 {
         // Simple counter-based ID generation
@@ -278,8 +307,8 @@ impl CrdtState for ConversationCrdt {
 
     fn apply_operation(&mut self, op: &OperationMeta) -> Result<(), String> {
         match op.op_type {
-            OperationType::Add => {
-                if let Ok(part_op) = serde_json::from_slice::<ParticipantOperation>(&op.data) {
+            OperationType::Add | OperationType::Remove => {
+                if let Ok(part_op) = op.decode_data::<ParticipantOperation>() {
                     match part_op {
                         ParticipantOperation::Add { user_id } => {
                             self.participants.active.insert(user_id);
@@ -292,11 +321,8 @@ impl CrdtState for ConversationCrdt {
                     }
                 }
             }
-            OperationType::Remove => {
-                // Handle remove operations
-            }
             OperationType::Update => {
-                if let Ok(meta_op) = serde_json::from_slice::<MetadataOperation>(&op.data) {
+                if let Ok(meta_op) = op.decode_data::<MetadataOperation>() {
                     match meta_op {
                         MetadataOperation::UpdateName { name } => {
                             self.metadata.name = Some(name);
@@ -413,4 +439,24 @@ mod tests {
         let result = conversation1.merge(&conversation2);
         assert_eq!(result, MergeResult::LocalUpdated);
     }
+
+    #[test]
+    fn test_merge_applies_remote_removal() {
+        let agent = Agent::new();
+        let (user1, user2, user3) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let mut local = ConversationCrdt::from_conversation(
+            agent.id(),
+            Uuid::new_v4(),
+            vec![user1, user2, user3],
+            None,
+        );
+        let mut remote = local.clone();
+
+        remote.remove_participant(user3);
+        assert_eq!(local.merge(&remote), MergeResult::RemoteMerged);
+
+        let participants = local.participants();
+        assert_eq!(participants.len(), 2);
+        assert!(!participants.contains(&user3));
+    }
 }
\ No newline at end of file
@@ -2,8 +2,31 @@
 //!
 //! Conflict-free replicated data type for message ordering and delivery status.
 //! Implements the Braid protocol with version vectors for causal ordering and conflict resolution.
-
-use crate::egui_app::crdt::{CrdtState, MergeResult, OperationMeta};
+//!
+//! Edits get their own version and name the version they were made from, so
+//! an edit made from a version that has since been edited again is caught as
+//! concurrent and reported as a conflict. Deletes keep the entry as a
+//! tombstone so causal history stays intact.
+//!
+//! ## Compaction
+//!
+//! Every status update, edit and delete stays in the operation log so other
+//! replicas can catch up on it. [`MessageCrdt::compact`] drops the ones
+//! every known agent has already applied: their effects live on in the
+//! message entries and delivery statuses, which are the snapshot a merge
+//! hands over. It is safe to drop an operation once it's at or below the
+//! *stable checkpoint*, the pointwise minimum of the operation frontiers
+//! acknowledged by every agent we know of (message authors, operation
+//! authors and anyone we've merged with). An agent that hasn't told us its
+//! frontier holds the checkpoint at zero, as does never having merged. Frontiers are learned during
+//! merges and count every operation up to them as applied, which holds
+//! because operations travel in full logs or `operations_since` batches.
+//! A replica below the checkpoint, e.g. one we didn't know about, can't be
+//! served a delta any more and needs a full merge instead; see
+//! [`MessageCrdt::can_serve_delta`].
+
+use crate::egui_app::crdt::{CrdtState, MergeResult, OperationMeta, OperationType, Resolution};
+use crate::shared::messaging::ChatMessage;
 use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, HashMap, HashSet};
 use uuid::Uuid;
@@ -57,6 +80,28 @@ impl VersionVector {
         !self.dominates(other) && !other.dominates(self)
     }
 
+    /// Clock value recorded for an agent, 0 if none
+    pub fn get(&self, agent_id: u64) -> u64 {
+        self.versions.get(&agent_id).copied().unwrap_or(0)
+    }
+
+    /// Raise an agent's clock value to at least `version`
+    pub fn observe(&mut self, agent_id: u64, version: u64) {
+        let current = self.versions.entry(agent_id).or_insert(0);
+        *current = (*current).max(version);
+    }
+
+    /// Pointwise minimum: what both vectors have reached
+    pub fn meet(&self, other: &VersionVector) -> VersionVector {
+        let versions = self
+            .versions
+            .iter()
+            .map(|(agent_id, version)| (*agent_id, (*version).min(other.get(*agent_id))))
+            .filter(|(_, version)| *version > 0)
+            .collect();
+        Self { versions }
+    }
+
     /// Get the maximum version across all agents
     pub fn max_version(&self) -> u64 {
         self.versions.values().max().unwrap_or(&0).clone()
@@ -82,6 +127,31 @@ pub struct MessageCrdt {
     operations: Vec<OperationMeta>,
     /// Pending messages for offline sync
     pending_messages: Vec<MessageEntry>,
+    /// Highest operation ID per agent known to be applied everywhere;
+    /// operations up to here may have been compacted away
+    #[serde(default)]
+    compacted: VersionVector,
+    /// Operation frontier each other agent last showed us in a merge
+    #[serde(default)]
+    acknowledged: HashMap<u64, VersionVector>,
+}
+
+/// When [`MessageCrdt::compact`] trims the operation log
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompactionPolicy {
+    /// Leave the log alone until it holds more operations than this
+    pub min_operations: usize,
+    /// Always keep this many of the newest operations, even if stable
+    pub retain_recent: usize,
+}
+
+impl Default for CompactionPolicy {
+    fn default() -> Self {
+        Self {
+            min_operations: 500,
+            retain_recent: 100,
+        }
+    }
 }
 
 /// Message entry with full CRDT metadata
@@ -108,14 +178,33 @@ pub struct MessageEntry {
     /// Creation timestamp
     pub created_at: chrono::DateTime<chrono::Utc>,
     /// Delivery status
+    #[serde(default = "sent_by_default")]
+    pub is_sent: bool,
     pub is_delivered: bool,
     pub is_read: bool,
+    /// Version of the latest edit, if the message has been edited
+    #[serde(default)]
+    pub edit_version: Option<String>,
+    /// Tombstone: the message was deleted and its content cleared
+    #[serde(default)]
+    pub is_deleted: bool,
+}
+
+impl MessageEntry {
+    /// Version of the current content: the latest edit, or the original
+    pub fn current_version(&self) -> &str {
+        self.edit_version.as_deref().unwrap_or(&self.braid_version)
+    }
 }
 
 /// Message delivery status
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// Ordered from least to most advanced; status only ever moves forward.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum MessageStatus {
-    /// Message sent but not delivered
+    /// Message created locally, not yet accepted by the server
+    Pending,
+    /// Message persisted and broadcast by the server, not yet delivered
     Sent,
     /// Message delivered to recipient
     Delivered,
@@ -135,9 +224,16 @@ impl MessageCrdt {
             delivery_status: HashMap::new(),
             operations: Vec::new(),
             pending_messages: Vec::new(),
+            compacted: VersionVector::new(),
+            acknowledged: HashMap::new(),
         }
     }
 
+    /// Conversation this CRDT manages
+    pub fn conversation_id(&self) -> Uuid {
+        self.conversation_id
+    }
+
     /// Create a new message with proper CRDT versioning
     pub fn create_message(
         &mut self,
@@ -168,13 +264,18 @@ impl MessageCrdt {
             braid_version: braid_version.clone(),
             braid_parents,
             created_at: chrono::Utc::now(),
+            is_sent: false,
             is_delivered: false,
             is_read: false,
+            edit_version: None,
+            is_deleted: false,
         };
 
         // Update our version vector
         self.version_vector = new_version_vector;
 
+        self.delivery_status.insert(message.id, MessageStatus::Pending);
+
         // Add to pending messages (will be synced when online)
         self.pending_messages.push(message.clone());
 
@@ -182,6 +283,9 @@ impl MessageCrdt {
     }
 
     /// Add a received message from another client
+    ///
+    /// Records a `Delivered` status update, which reaches the sender's CRDT
+    /// through the normal merge.
     pub fn add_received_message(&mut self, message: MessageEntry) {
         // Update our version vector with the received message's vector
         self.version_vector.merge(&message.version_vector);
@@ -190,7 +294,11 @@ impl MessageCrdt {
         self.lamport_clock = self.lamport_clock.max(message.lamport_timestamp) + 1;
 
         // Store message using braid version as key
+        let message_id = message.id;
+        self.delivery_status.entry(message_id).or_insert(status_of(&message));
         self.messages.insert(message.braid_version.clone(), message);
+
+        self.mark_delivered(message_id);
     }
 
     /// Get messages in causal order (sorted by version vector)
@@ -207,20 +315,215 @@ impl MessageCrdt {
         messages
     }
 
+    /// Mark message as accepted by the server
+    pub fn mark_sent(&mut self, message_id: Uuid) {
+        self.update_status(message_id, MessageStatus::Sent);
+    }
+
     /// Mark message as delivered
     pub fn mark_delivered(&mut self, message_id: Uuid) {
-        if let Some(message) = self.messages.values_mut().find(|m| m.id == message_id) {
-            message.is_delivered = true;
-        }
-        self.delivery_status.insert(message_id, MessageStatus::Delivered);
+        self.update_status(message_id, MessageStatus::Delivered);
     }
 
     /// Mark message as read
     pub fn mark_read(&mut self, message_id: Uuid) {
-        if let Some(message) = self.messages.values_mut().find(|m| m.id == message_id) {
-            message.is_read = true;
+        self.update_status(message_id, MessageStatus::Read);
+    }
+
+    /// Edit one of our messages, giving the edit its own version
+    ///
+    /// Returns the edited entry, or `None` if the message is unknown or
+    /// deleted.
+    pub fn edit_message(&mut self, message_id: Uuid, content: String) -> Option<MessageEntry> {
+        let entry = self.messages.values_mut().find(|m| m.id == message_id && !m.is_deleted)?;
+        self.lamport_clock += 1;
+        let edit_version = format!("edit-{}-{}", self.agent_id, self.lamport_clock);
+        let parent = entry.current_version().to_string();
+        entry.content = content.clone();
+        entry.edit_version = Some(edit_version.clone());
+        let edited = entry.clone();
+
+        self.record_operation(OperationType::Update, &MessageOperation::Edit {
+            message_id,
+            content,
+            edit_version,
+            parent,
+        });
+        Some(edited)
+    }
+
+    /// Delete a message, keeping it as a tombstone
+    ///
+    /// Returns whether anything changed.
+    pub fn delete_message(&mut self, message_id: Uuid) -> bool {
+        if !self.tombstone(message_id) {
+            return false;
+        }
+        self.lamport_clock += 1;
+        self.record_operation(OperationType::Remove, &MessageOperation::Delete { message_id });
+        true
+    }
+
+    /// Mark a message deleted and clear its content; returns whether it changed
+    fn tombstone(&mut self, message_id: Uuid) -> bool {
+        let mut changed = false;
+        let messages = self.messages.values_mut().chain(self.pending_messages.iter_mut());
+        for message in messages.filter(|m| m.id == message_id && !m.is_deleted) {
+            message.is_deleted = true;
+            message.content.clear();
+            changed = true;
+        }
+        changed
+    }
+
+    /// Apply a remote edit; returns `false` if it was made concurrently with ours
+    ///
+    /// An edit made from our current version fast-forwards to it. One made
+    /// from any other version raced an edit we already have and is left for
+    /// the user to settle. Tombstones win over edits.
+    fn apply_edit(&mut self, message_id: Uuid, content: String, edit_version: String, parent: &str) -> bool {
+        let Some(entry) = self.messages.values_mut().find(|m| m.id == message_id) else {
+            return true;
+        };
+        if entry.is_deleted || entry.edit_version.as_deref() == Some(&edit_version) {
+            return true;
         }
-        self.delivery_status.insert(message_id, MessageStatus::Read);
+        if entry.current_version() != parent {
+            return false;
+        }
+        entry.content = content;
+        entry.edit_version = Some(edit_version);
+        true
+    }
+
+    /// Current delivery status of a message
+    pub fn message_status(&self, message_id: Uuid) -> Option<MessageStatus> {
+        self.delivery_status.get(&message_id).copied()
+    }
+
+    /// Advance a message's status and record the change for other replicas
+    fn update_status(&mut self, message_id: Uuid, status: MessageStatus) {
+        if !self.advance_status(message_id, status) {
+            return;
+        }
+        self.lamport_clock += 1;
+        let operation = MessageOperation::UpdateStatus {
+            message_id,
+            is_sent: status >= MessageStatus::Sent,
+            is_delivered: status >= MessageStatus::Delivered,
+            is_read: status >= MessageStatus::Read,
+        };
+        self.record_operation(OperationType::Update, &operation);
+    }
+
+    /// Record a local operation at the current clock for other replicas
+    fn record_operation(&mut self, op_type: OperationType, operation: &MessageOperation) {
+        self.operations.push(OperationMeta {
+            id: self.lamport_clock,
+            agent_id: self.agent_id,
+            timestamp: self.lamport_clock,
+            op_type,
+            data: OperationMeta::encode_data(operation),
+        });
+    }
+
+    /// Move a message's status forward; returns whether it changed
+    ///
+    /// A status that isn't ahead of the current one is ignored, so replicas
+    /// converge on the most advanced status whatever order updates arrive in.
+    fn advance_status(&mut self, message_id: Uuid, status: MessageStatus) -> bool {
+        if self.delivery_status.get(&message_id).is_some_and(|current| status <= *current) {
+            return false;
+        }
+        self.delivery_status.insert(message_id, status);
+        let messages = self.messages.values_mut().chain(self.pending_messages.iter_mut());
+        for message in messages.filter(|m| m.id == message_id) {
+            message.is_sent = status >= MessageStatus::Sent;
+            message.is_delivered = status >= MessageStatus::Delivered;
+            message.is_read = status >= MessageStatus::Read;
+        }
+        true
+    }
+
+    /// Whether we already have an operation (operations are unique per agent)
+    ///
+    /// Compacted operations count as had.
+    fn has_operation(&self, op: &OperationMeta) -> bool {
+        op.id <= self.compacted.get(op.agent_id)
+            || self.operations.iter().any(|o| o.agent_id == op.agent_id && o.id == op.id)
+    }
+
+    /// Highest operation ID we've applied from each agent
+    pub fn operation_frontier(&self) -> VersionVector {
+        let mut frontier = self.compacted.clone();
+        for op in &self.operations {
+            frontier.observe(op.agent_id, op.id);
+        }
+        frontier
+    }
+
+    /// Operations up to here have been applied by every agent we know of
+    ///
+    /// Agents who haven't acknowledged a frontier yet hold it at zero, and
+    /// so does having never merged with anyone: our operations may not
+    /// have reached a single other replica.
+    pub fn stable_checkpoint(&self) -> VersionVector {
+        if self.acknowledged.is_empty() {
+            return VersionVector::new();
+        }
+        let mut agents: HashSet<u64> = self.acknowledged.keys().copied().collect();
+        agents.extend(self.version_vector.versions.keys().copied());
+        agents.extend(self.operations.iter().map(|op| op.agent_id));
+        agents.remove(&self.agent_id);
+
+        let empty = VersionVector::new();
+        agents.iter().fold(self.operation_frontier(), |checkpoint, agent_id| {
+            checkpoint.meet(self.acknowledged.get(agent_id).unwrap_or(&empty))
+        })
+    }
+
+    /// Drop operations at or below the stable checkpoint
+    ///
+    /// Returns how many were dropped. See the module docs for why this is
+    /// safe.
+    pub fn compact(&mut self, policy: &CompactionPolicy) -> usize {
+        if self.operations.len() <= policy.min_operations {
+            return 0;
+        }
+        let stable = self.stable_checkpoint();
+        let keep_from = self.operations.len().saturating_sub(policy.retain_recent);
+        let before = self.operations.len();
+
+        let mut index = 0;
+        self.operations.retain(|op| {
+            index += 1;
+            index > keep_from || op.id > stable.get(op.agent_id)
+        });
+        self.compacted.merge(&stable);
+        before - self.operations.len()
+    }
+
+    /// Whether `operations_since` still holds everything a replica at
+    /// `frontier` is missing, or it needs a full merge
+    pub fn can_serve_delta(&self, frontier: &VersionVector) -> bool {
+        frontier.dominates(&self.compacted)
+    }
+
+    /// Remember how far another replica, and everyone it knows of, has got
+    fn learn_frontiers(&mut self, other: &MessageCrdt) {
+        let reported = other
+            .acknowledged
+            .iter()
+            .map(|(agent_id, frontier)| (*agent_id, frontier.clone()))
+            .chain(std::iter::once((other.agent_id, other.operation_frontier())));
+        for (agent_id, frontier) in reported {
+            if agent_id != self.agent_id {
+                self.acknowledged.entry(agent_id).or_default().merge(&frontier);
+            }
+        }
+        // Everything the other side compacted is applied everywhere, our
+        // copy of its messages included
+        self.compacted.merge(&other.compacted);
     }
 
     /// Get pending messages for sync
@@ -255,6 +558,97 @@ impl MessageCrdt {
             .cloned()
             .collect()
     }
+
+    /// Group messages that were created concurrently
+    ///
+    /// Two messages are concurrent when neither version vector dominates the
+    /// other, i.e. their senders hadn't seen each other's message yet. Groups
+    /// are the connected sets of such messages, so a chain of overlapping
+    /// concurrent messages forms one group. Messages with a causal order
+    /// relative to everything else are left out. Groups and their members
+    /// are in Lamport order.
+    pub fn concurrent_groups(&self) -> Vec<Vec<Uuid>> {
+        let messages = self.get_messages_chronological();
+        let mut grouped = vec![false; messages.len()];
+        let mut groups = Vec::new();
+
+        for start in 0..messages.len() {
+            if grouped[start] {
+                continue;
+            }
+            grouped[start] = true;
+            let mut members = vec![start];
+            let mut next = 0;
+            while next < members.len() {
+                let vector = &messages[members[next]].version_vector;
+                next += 1;
+                for other in 0..messages.len() {
+                    if !grouped[other] && vector.concurrent(&messages[other].version_vector) {
+                        grouped[other] = true;
+                        members.push(other);
+                    }
+                }
+            }
+            if members.len() > 1 {
+                members.sort_unstable();
+                groups.push(members.into_iter().map(|i| messages[i].id).collect());
+            }
+        }
+
+        groups
+    }
+}
+
+impl MessageCrdt {
+    /// Settle a content conflict reported by [`CrdtState::merge`]
+    ///
+    /// `local_data` and `remote_data` are the conflict's payloads. Keeping
+    /// theirs replaces our content; keeping both adds their version as a new
+    /// message next to ours, queued for sync like one we'd written. Returns
+    /// each conflicting message's id with the entry that now holds the
+    /// remote content (nothing when keeping ours).
+    pub fn resolve_conflict(
+        &mut self,
+        local_data: &[u8],
+        remote_data: &[u8],
+        resolution: Resolution,
+    ) -> Result<Vec<(Uuid, MessageEntry)>, String> {
+        let conflicts = conflicting_entries(local_data, remote_data)?;
+        if conflicts.iter().any(|(_, ours, _)| ours.conversation_id != self.conversation_id) {
+            return Err("Conflict is for a different conversation".to_string());
+        }
+
+        let mut resolved = Vec::new();
+        for (version, ours, theirs) in conflicts {
+            match resolution {
+                Resolution::KeepMine => {}
+                Resolution::KeepTheirs => {
+                    if let Some(entry) = self.messages.get_mut(&version) {
+                        entry.content = theirs.content;
+                        entry.edit_version = theirs.edit_version;
+                        resolved.push((ours.id, entry.clone()));
+                    }
+                }
+                Resolution::KeepBoth => {
+                    self.lamport_clock += 1;
+                    let copy = MessageEntry {
+                        id: Uuid::new_v4(),
+                        version_vector: self.version_vector.increment(self.agent_id),
+                        braid_version: format!("msg-{}-{}", self.agent_id, self.lamport_clock),
+                        braid_parents: vec![version],
+                        edit_version: None,
+                        ..theirs
+                    };
+                    self.delivery_status.insert(copy.id, status_of(&copy));
+                    self.messages.insert(copy.braid_version.clone(), copy.clone());
+                    self.pending_messages.push(copy.clone());
+                    resolved.push((ours.id, copy));
+                }
+            }
+        }
+
+        Ok(resolved)
+    }
 }
 
 impl CrdtState for MessageCrdt {
@@ -284,45 +678,41 @@ impl CrdtState for MessageCrdt {
                 // We don't have this message, add it
                 self.messages.insert(version_key.clone(), remote_message.clone());
                 has_remote_changes = true;
+            }
+            // Tombstones win, whatever either side did to the content
+            if remote_message.is_deleted && self.tombstone(remote_message.id) {
+                has_remote_changes = true;
+            }
+            // Take the more advanced delivery status
+            let remote_status = other
+                .message_status(remote_message.id)
+                .unwrap_or_else(|| status_of(remote_message));
+            if self.advance_status(remote_message.id, remote_status) {
+                has_remote_changes = true;
+            }
+        }
 
-                // Update delivery status
-                self.delivery_status.insert(
-                    remote_message.id,
-                    if remote_message.is_read {
-                        MessageStatus::Read
-                    } else if remote_message.is_delivered {
-                        MessageStatus::Delivered
-                    } else {
-                        MessageStatus::Sent
-                    }
-                );
-            } else {
-                // We have this message, merge delivery status
-                if let Some(local_message) = self.messages.get_mut(version_key) {
-                    let local_status = self.delivery_status.get(&local_message.id);
-                    let remote_status = if remote_message.is_read {
-                        MessageStatus::Read
-                    } else if remote_message.is_delivered {
-                        MessageStatus::Delivered
-                    } else {
-                        MessageStatus::Sent
-                    };
-
-                    // Merge delivery status (take the more advanced status)
-                    match (local_status, &remote_status) {
-                        (Some(MessageStatus::Sent), MessageStatus::Delivered) |
-                        (Some(MessageStatus::Sent), MessageStatus::Read) |
-                        (Some(MessageStatus::Delivered), MessageStatus::Read) => {
-                            self.delivery_status.insert(local_message.id, remote_status);
-                            local_message.is_delivered = remote_message.is_delivered;
-                            local_message.is_read = remote_message.is_read;
-                            has_remote_changes = true;
-                        }
-                        _ => {}
+        // Apply status updates, edits and deletes from the remote that we haven't seen
+        let mut concurrent_edits = HashSet::new();
+        for op in &other.operations {
+            if !self.has_operation(op) {
+                if let Ok(MessageOperation::Edit { message_id, content, edit_version, parent }) = op.decode_data() {
+                    if !self.apply_edit(message_id, content, edit_version, &parent) {
+                        concurrent_edits.insert(message_id);
                     }
+                    self.lamport_clock = self.lamport_clock.max(op.timestamp);
+                    self.operations.push(op.clone());
+                } else if let Err(e) = self.apply_operation(op) {
+                    tracing::warn!("Skipping unreadable message operation {}: {}", op.id, e);
                 }
+                has_remote_changes = true;
             }
         }
+        // Any operations the remote hasn't seen are ours
+        if self.operations.iter().any(|op| !other.has_operation(op)) {
+            has_local_changes = true;
+        }
+        self.learn_frontiers(other);
 
         // Check if we have messages that remote doesn't have
         for version_key in self.messages.keys() {
@@ -341,6 +731,19 @@ impl CrdtState for MessageCrdt {
             let local_msg = self.messages.get(version_key).unwrap();
             let remote_msg = other.messages.get(version_key).unwrap();
 
+            if local_msg.is_deleted || remote_msg.is_deleted {
+                continue;
+            }
+
+            // Edits racing each other conflict; an edit one side hasn't seen yet doesn't
+            if concurrent_edits.contains(&local_msg.id) {
+                conflicts.push(format!("Message {} was edited concurrently", local_msg.id));
+                continue;
+            }
+            if local_msg.edit_version != remote_msg.edit_version {
+                continue;
+            }
+
             // Check for content conflicts (same ID but different content - shouldn't happen in practice)
             if local_msg.content != remote_msg.content {
                 conflicts.push(format!("Message {} has content conflict", local_msg.id));
@@ -372,9 +775,27 @@ impl CrdtState for MessageCrdt {
             .collect()
     }
 
-    fn apply_operation(&mut self, _op: &OperationMeta) -> Result<(), String> {
-        // For now, operations are handled through direct message merging
-        // In a full implementation, this would apply individual operations
+    fn apply_operation(&mut self, op: &OperationMeta) -> Result<(), String> {
+        match op.decode_data::<MessageOperation>()? {
+            MessageOperation::UpdateStatus { message_id, is_sent, is_delivered, is_read } => {
+                self.advance_status(message_id, MessageStatus::from_flags(is_sent, is_delivered, is_read));
+            }
+            MessageOperation::Edit { message_id, content, edit_version, parent } => {
+                if !self.apply_edit(message_id, content, edit_version, &parent) {
+                    tracing::info!("Concurrent edit of message {} left for a merge to report", message_id);
+                }
+            }
+            MessageOperation::Delete { message_id } => {
+                self.tombstone(message_id);
+            }
+            // Messages themselves are merged directly
+            MessageOperation::Add { .. } => {}
+        }
+
+        self.lamport_clock = self.lamport_clock.max(op.timestamp);
+        if !self.has_operation(op) {
+            self.operations.push(op.clone());
+        }
         Ok(())
     }
 
@@ -387,6 +808,59 @@ impl CrdtState for MessageCrdt {
     }
 }
 
+/// The messages a [`MergeResult::Conflict`] from [`MessageCrdt`] is about
+///
+/// Decodes the conflict's payloads and pairs up the versions whose content
+/// differs, as `(version, ours, theirs)`.
+pub fn conflicting_entries(
+    local_data: &[u8],
+    remote_data: &[u8],
+) -> Result<Vec<(String, MessageEntry, MessageEntry)>, String> {
+    let local: BTreeMap<String, MessageEntry> =
+        serde_json::from_slice(local_data).map_err(|e| format!("Unreadable local messages: {}", e))?;
+    let mut remote: BTreeMap<String, MessageEntry> =
+        serde_json::from_slice(remote_data).map_err(|e| format!("Unreadable remote messages: {}", e))?;
+
+    Ok(local
+        .into_iter()
+        .filter_map(|(version, ours)| {
+            let theirs = remote.remove(&version)?;
+            (ours.content != theirs.content).then_some((version, ours, theirs))
+        })
+        .collect())
+}
+
+impl MessageStatus {
+    /// Status from a message's flags; the most advanced one set wins
+    pub fn from_flags(is_sent: bool, is_delivered: bool, is_read: bool) -> Self {
+        if is_read {
+            MessageStatus::Read
+        } else if is_delivered {
+            MessageStatus::Delivered
+        } else if is_sent {
+            MessageStatus::Sent
+        } else {
+            MessageStatus::Pending
+        }
+    }
+}
+
+impl From<&ChatMessage> for MessageStatus {
+    fn from(message: &ChatMessage) -> Self {
+        MessageStatus::from_flags(message.is_sent, message.is_delivered, message.is_read)
+    }
+}
+
+/// Delivery status recorded on a message entry
+fn status_of(message: &MessageEntry) -> MessageStatus {
+    MessageStatus::from_flags(message.is_sent, message.is_delivered, message.is_read)
+}
+
+/// Entries and operations from before the pending stage were all sent
+fn sent_by_default() -> bool {
+    true
+}
+
 /// Message operations for CRDT synchronization
 #[derive(Debug, Clone, Serialize, Deserialize)]
 enum MessageOperation {
@@ -404,9 +878,24 @@ enum MessageOperation {
     /// Update delivery status
     UpdateStatus {
         message_id: Uuid,
+        #[serde(default = "sent_by_default")]
+        is_sent: bool,
         is_delivered: bool,
         is_read: bool,
     },
+    /// Replace a message's content
+    Edit {
+        message_id: Uuid,
+        content: String,
+        /// Version of this edit
+        edit_version: String,
+        /// Version the edit was made from
+        parent: String,
+    },
+    /// Delete a message, leaving a tombstone
+    Delete {
+        message_id: Uuid,
+    },
 }
 
 #[cfg(test)]
@@ -478,4 +967,226 @@ mod tests {
         assert!(matches!(result, MergeResult::BothMerged));
         assert_eq!(crdt1.messages.len(), 1);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_resolve_content_conflict() {
+        let conversation_id = Uuid::new_v4();
+        let mut sender = MessageCrdt::new(conversation_id, 1);
+        let message = sender.create_message("Original".to_string(), "text".to_string(), Uuid::new_v4());
+        let edited = MessageEntry { content: "Edited".to_string(), ..message.clone() };
+
+        let conflicted = |resolution| {
+            let mut ours = MessageCrdt::new(conversation_id, 2);
+            let mut theirs = MessageCrdt::new(conversation_id, 3);
+            ours.add_received_message(message.clone());
+            theirs.add_received_message(edited.clone());
+            let MergeResult::Conflict { local_data, remote_data, .. } = ours.merge(&theirs) else {
+                panic!("expected a conflict");
+            };
+            let resolved = ours.resolve_conflict(&local_data, &remote_data, resolution).unwrap();
+            (ours, resolved)
+        };
+
+        let (ours, resolved) = conflicted(Resolution::KeepMine);
+        assert!(resolved.is_empty());
+        assert_eq!(ours.get_messages()[0].content, "Original");
+
+        let (ours, resolved) = conflicted(Resolution::KeepTheirs);
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(resolved[0].1.id, message.id);
+        assert_eq!(ours.get_messages().len(), 1);
+        assert_eq!(ours.get_messages()[0].content, "Edited");
+
+        let (ours, resolved) = conflicted(Resolution::KeepBoth);
+        assert_eq!(resolved[0].0, message.id);
+        assert_ne!(resolved[0].1.id, message.id);
+        let contents: Vec<_> = ours.get_messages_chronological().iter().map(|m| m.content.clone()).collect();
+        assert_eq!(contents.len(), 2);
+        assert!(contents.contains(&"Original".to_string()) && contents.contains(&"Edited".to_string()));
+        assert_eq!(ours.pending_messages().len(), 1);
+    }
+
+    #[test]
+    fn test_concurrent_groups_after_independent_sends() {
+        let conversation_id = Uuid::new_v4();
+        let mut crdt1 = MessageCrdt::new(conversation_id, 1);
+        let mut crdt2 = MessageCrdt::new(conversation_id, 2);
+
+        // Each agent sends without having seen the other's message
+        let message1 = crdt1.create_message("From 1".to_string(), "text".to_string(), Uuid::new_v4());
+        let message2 = crdt2.create_message("From 2".to_string(), "text".to_string(), Uuid::new_v4());
+        crdt1.add_received_message(message1.clone());
+        crdt2.add_received_message(message2.clone());
+        assert!(crdt1.concurrent_groups().is_empty());
+
+        assert_eq!(crdt1.missing_versions(&crdt2), vec![message2.braid_version.clone()]);
+        crdt1.merge(&crdt2);
+
+        let groups = crdt1.concurrent_groups();
+        assert_eq!(groups.len(), 1);
+        let group: HashSet<Uuid> = groups[0].iter().copied().collect();
+        assert_eq!(group, HashSet::from([message1.id, message2.id]));
+
+        // A reply written after the merge follows both and stays ungrouped
+        let reply = crdt1.create_message("Reply".to_string(), "text".to_string(), Uuid::new_v4());
+        crdt1.add_received_message(reply.clone());
+        let groups = crdt1.concurrent_groups();
+        assert_eq!(groups.len(), 1);
+        assert!(!groups[0].contains(&reply.id));
+    }
+
+    #[test]
+    fn test_delivery_status_flows_back_to_sender() {
+        let conversation_id = Uuid::new_v4();
+        let mut sender = MessageCrdt::new(conversation_id, 1);
+        let mut recipient = MessageCrdt::new(conversation_id, 2);
+
+        let message = sender.create_message("Hello".to_string(), "text".to_string(), Uuid::new_v4());
+        assert_eq!(sender.message_status(message.id), Some(MessageStatus::Pending));
+
+        // Receiving records a Delivered update for the sender
+        recipient.add_received_message(message.clone());
+        assert_eq!(recipient.message_status(message.id), Some(MessageStatus::Delivered));
+        assert_eq!(recipient.operations_since(0).len(), 1);
+
+        sender.merge(&recipient);
+        assert_eq!(sender.message_status(message.id), Some(MessageStatus::Delivered));
+        assert!(sender.pending_messages()[0].is_delivered);
+
+        // Read overtakes Delivered, and a stale replica can't undo it
+        let stale = recipient.clone();
+        recipient.mark_read(message.id);
+        sender.merge(&recipient);
+        assert_eq!(sender.message_status(message.id), Some(MessageStatus::Read));
+        sender.merge(&stale);
+        assert_eq!(sender.message_status(message.id), Some(MessageStatus::Read));
+        assert!(sender.get_messages().iter().all(|m| m.is_read));
+
+        // Operations replay the same way outside a full merge
+        let mut replica = MessageCrdt::new(conversation_id, 3);
+        for op in recipient.operations_since(0) {
+            replica.apply_operation(&op).unwrap();
+        }
+        assert_eq!(replica.message_status(message.id), Some(MessageStatus::Read));
+    }
+
+    #[test]
+    fn test_accepted_message_is_sent_before_delivery() {
+        let conversation_id = Uuid::new_v4();
+        let mut sender = MessageCrdt::new(conversation_id, 1);
+        let mut recipient = MessageCrdt::new(conversation_id, 2);
+
+        let message = sender.create_message("Hello".to_string(), "text".to_string(), Uuid::new_v4());
+        assert_eq!(sender.message_status(message.id), Some(MessageStatus::Pending));
+        assert!(!sender.pending_messages()[0].is_sent);
+
+        // The server's ack for the PUT is the first tick, with no delivery yet
+        sender.mark_sent(message.id);
+        assert_eq!(sender.message_status(message.id), Some(MessageStatus::Sent));
+        assert!(sender.pending_messages()[0].is_sent);
+        assert!(!sender.pending_messages()[0].is_delivered);
+
+        let mut replica = MessageCrdt::new(conversation_id, 3);
+        for op in sender.operations_since(0) {
+            replica.apply_operation(&op).unwrap();
+        }
+        assert_eq!(replica.message_status(message.id), Some(MessageStatus::Sent));
+
+        // Only the recipient's ack moves it on to Delivered
+        recipient.add_received_message(message.clone());
+        sender.merge(&recipient);
+        assert_eq!(sender.message_status(message.id), Some(MessageStatus::Delivered));
+
+        // Chat messages map onto the same stages
+        let mut chat = ChatMessage::new_text(conversation_id, Uuid::new_v4(), "Hi".to_string(), 1);
+        assert_eq!(MessageStatus::from(&chat), MessageStatus::Pending);
+        chat.is_sent = true;
+        assert_eq!(MessageStatus::from(&chat), MessageStatus::Sent);
+        chat.is_delivered = true;
+        assert_eq!(MessageStatus::from(&chat), MessageStatus::Delivered);
+    }
+
+    #[test]
+    fn test_edits_and_deletes_replicate() {
+        let conversation_id = Uuid::new_v4();
+        let mut sender = MessageCrdt::new(conversation_id, 1);
+        let message = sender.create_message("Hello".to_string(), "text".to_string(), Uuid::new_v4());
+        sender.add_received_message(message.clone());
+        let mut other = MessageCrdt::new(conversation_id, 2);
+        other.add_received_message(message.clone());
+
+        // An edit from the version the other side has fast-forwards
+        let edited = sender.edit_message(message.id, "Hello!".to_string()).unwrap();
+        assert_ne!(edited.current_version(), message.braid_version);
+        other.merge(&sender);
+        assert_eq!(other.get_messages()[0].content, "Hello!");
+        assert_eq!(other.get_messages()[0].edit_version, edited.edit_version);
+
+        // Both editing the same version is reported and can be settled
+        let mut racing = other.clone();
+        sender.edit_message(message.id, "Hi".to_string()).unwrap();
+        racing.edit_message(message.id, "Hey".to_string()).unwrap();
+        let MergeResult::Conflict { local_data, remote_data, .. } = racing.merge(&sender) else {
+            panic!("expected a conflict");
+        };
+        racing.resolve_conflict(&local_data, &remote_data, Resolution::KeepTheirs).unwrap();
+        assert_eq!(racing.get_messages()[0].content, "Hi");
+        assert_eq!(racing.get_messages()[0].current_version(), sender.get_messages()[0].current_version());
+
+        // A delete leaves a tombstone that later edits can't revive
+        assert!(sender.delete_message(message.id));
+        assert!(sender.edit_message(message.id, "Back".to_string()).is_none());
+        other.merge(&sender);
+        assert_eq!(other.get_messages().len(), 1);
+        assert!(other.get_messages()[0].is_deleted);
+        assert!(other.get_messages()[0].content.is_empty());
+    }
+
+    #[test]
+    fn test_compaction_keeps_merges_working_at_the_checkpoint() {
+        let conversation_id = Uuid::new_v4();
+        let mut sender = MessageCrdt::new(conversation_id, 1);
+        let mut recipient = MessageCrdt::new(conversation_id, 2);
+        let everything = CompactionPolicy { min_operations: 0, retain_recent: 0 };
+
+        let mut messages = Vec::new();
+        for text in ["One", "Two", "Three"] {
+            let message = sender.create_message(text.to_string(), "text".to_string(), Uuid::new_v4());
+            sender.add_received_message(message.clone());
+            sender.mark_sent(message.id);
+            recipient.add_received_message(message.clone());
+            messages.push(message);
+        }
+        let edited = sender.edit_message(messages[0].id, "One!".to_string()).unwrap();
+
+        // Nothing is stable until the recipient has shown how far it got
+        assert_eq!(sender.compact(&everything), 0);
+        recipient.merge(&sender);
+        sender.merge(&recipient);
+        let before = sender.operations.len();
+        assert_eq!(sender.compact(&everything), before);
+        assert!(sender.operations.is_empty());
+        assert!(sender.can_serve_delta(&recipient.operation_frontier()));
+        assert!(!sender.can_serve_delta(&VersionVector::new()));
+
+        // The recipient still has the old operations; replaying them is harmless
+        recipient.mark_read(messages[1].id);
+        assert!(!matches!(sender.merge(&recipient), MergeResult::Conflict { .. }));
+        assert_eq!(sender.message_status(messages[1].id), Some(MessageStatus::Read));
+        assert_eq!(sender.operations.len(), 1);
+
+        // And new edits still fast-forward in both directions
+        let reedited = sender.edit_message(messages[0].id, "One!!".to_string()).unwrap();
+        assert!(!matches!(recipient.merge(&sender), MergeResult::Conflict { .. }));
+        let entry = recipient.get_messages().into_iter().find(|m| m.id == messages[0].id).unwrap();
+        assert_eq!(entry.content, "One!!");
+        assert_ne!(reedited.edit_version, edited.edit_version);
+
+        // A message from an agent we've never merged with holds the checkpoint back
+        let mut stranger = MessageCrdt::new(conversation_id, 3);
+        let message = stranger.create_message("Hi".to_string(), "text".to_string(), Uuid::new_v4());
+        sender.add_received_message(message);
+        assert_eq!(sender.stable_checkpoint().max_version(), 0);
+        assert_eq!(sender.compact(&everything), 0);
+    }
+}
@@ -65,7 +65,7 @@ pub use agent::Agent;
 pub use conversation_crdt::ConversationCrdt;
 pub use contact_crdt::ContactCrdt;
 pub use message_crdt::MessageCrdt;
-pub use user_state_crdt::UserStateCrdt;
+pub use user_state_crdt::{PresenceStatus, UserStateCrdt};
 // Re-export merger type. `MergeResult` is defined in this module below.
 pub use merger::Merger;
 pub use serializer::{CrdtSerializer, SerializedState};
@@ -98,6 +98,18 @@ pub struct OperationMeta {
     pub data: Vec<u8>,
 }
 
+impl OperationMeta {
+    /// Encode an operation payload in the compact binary format
+    pub fn encode_data<T: Serialize>(payload: &T) -> Vec<u8> {
+        serializer::encode_binary(payload).unwrap_or_default()
+    }
+
+    /// Decode the payload, whether binary or (older) JSON
+    pub fn decode_data<T: serde::de::DeserializeOwned>(&self) -> Result<T, String> {
+        serializer::decode(&self.data)
+    }
+}
+
 /// CRDT state interface
 pub trait CrdtState: Clone + Send + Sync {
     /// Merge this state with another state
@@ -138,6 +150,17 @@ pub enum MergeResult {
     },
 }
 
+/// The user's answer to a [`MergeResult::Conflict`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    /// Keep the local version and drop the remote one
+    KeepMine,
+    /// Replace the local version with the remote one
+    KeepTheirs,
+    /// Keep the local version and add the remote one alongside it
+    KeepBoth,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -2,11 +2,66 @@
 //!
 //! Efficient serialization and deserialization of CRDT states for storage and network transfer.
 //! Provides compression and versioning support for CRDT data.
+//!
+//! ## Wire format
+//!
+//! [`CrdtSerializer::serialize_binary`] and [`CrdtSerializer::encode`] prefix
+//! the payload with a one-byte format tag so the reader doesn't need to know
+//! how it was written:
+//!
+//! - `0x01`: JSON
+//! - `0x02`: bincode (varint integers), much smaller for large conversations
+//!
+//! Untagged JSON written before the tag existed starts with `{`, `[` or `"`
+//! and is still decoded.
 
 use crate::egui_app::crdt::{CrdtState, OperationMeta};
+use bincode::Options;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 // use std::io::{Read, Write};
 
+/// Format tag for JSON payloads
+pub const TAG_JSON: u8 = 0x01;
+/// Format tag for bincode payloads
+pub const TAG_BINARY: u8 = 0x02;
+
+fn bincode_options() -> impl Options {
+    bincode::DefaultOptions::new()
+}
+
+/// Encode a value as tagged bincode
+pub fn encode_binary<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, String> {
+    let mut data = vec![TAG_BINARY];
+    bincode_options()
+        .serialize_into(&mut data, value)
+        .map_err(|e| format!("Binary serialization failed: {}", e))?;
+    Ok(data)
+}
+
+/// Encode a value as tagged JSON
+pub fn encode_json<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, String> {
+    let mut data = vec![TAG_JSON];
+    serde_json::to_writer(&mut data, value)
+        .map_err(|e| format!("JSON serialization failed: {}", e))?;
+    Ok(data)
+}
+
+/// Decode a tagged (or legacy untagged JSON) payload
+pub fn decode<T: DeserializeOwned>(data: &[u8]) -> Result<T, String> {
+    match data.first() {
+        Some(&TAG_BINARY) => bincode_options()
+            .deserialize(&data[1..])
+            .map_err(|e| format!("Binary deserialization failed: {}", e)),
+        Some(&TAG_JSON) => serde_json::from_slice(&data[1..])
+            .map_err(|e| format!("JSON deserialization failed: {}", e)),
+        Some(b'{' | b'[' | b'"') => serde_json::from_slice(data)
+            .map_err(|e| format!("JSON deserialization failed: {}", e)),
+        Some(tag) => Err(format!("Unknown serialization format tag: {:#04x}", tag)),
+        None => Err("Empty payload".to_string()),
+    }
+}
+
 /// CRDT serialization/deserialization manager
 #[derive(Debug)]
 pub struct CrdtSerializer {
@@ -72,17 +127,7 @@ impl CrdtSerializer {
         let timestamp = chrono::Utc::now().to_rfc3339();
 
         // Serialize the CRDT data
-        let data = match self.format {
-            SerializationFormat::Json => {
-                serde_json::to_vec(crdt)
-                    .map_err(|e| format!("JSON serialization failed: {}", e))?
-            }
-            // Fallback to JSON to avoid external deps when Bincode/MessagePack are selected
-            SerializationFormat::Bincode | SerializationFormat::MessagePack => {
-                serde_json::to_vec(crdt)
-                    .map_err(|e| format!("JSON serialization failed: {}", e))?
-            }
-        };
+        let data = self.encode(crdt)?;
 
         let original_size = data.len();
         let (compressed_data, compressed) = if self.compression {
@@ -116,18 +161,8 @@ impl CrdtSerializer {
             state.data.clone()
         };
 
-        // Deserialize based on format
-        match self.format {
-            SerializationFormat::Json => {
-                serde_json::from_slice(&data)
-                    .map_err(|e| format!("JSON deserialization failed: {}", e))
-            }
-            // Fallback to JSON when other formats are requested
-            SerializationFormat::Bincode | SerializationFormat::MessagePack => {
-                serde_json::from_slice(&data)
-                    .map_err(|e| format!("JSON deserialization failed: {}", e))
-            }
-        }
+        // The format tag says how the data was written
+        decode(&data)
     }
 
     /// Serialize operation metadata
@@ -135,17 +170,7 @@ impl CrdtSerializer {
         &self,
         operations: &[OperationMeta],
     ) -> Result<Vec<u8>, String> {
-        match self.format {
-            SerializationFormat::Json => {
-                serde_json::to_vec(operations)
-                    .map_err(|e| format!("JSON serialization failed: {}", e))
-            }
-            // Fallback to JSON
-            SerializationFormat::Bincode | SerializationFormat::MessagePack => {
-                serde_json::to_vec(operations)
-                    .map_err(|e| format!("JSON serialization failed: {}", e))
-            }
-        }
+        self.encode(operations)
     }
 
     /// Deserialize operation metadata
@@ -153,19 +178,28 @@ impl CrdtSerializer {
         &self,
         data: &[u8],
     ) -> Result<Vec<OperationMeta>, String> {
+        decode(data)
+    }
+
+    /// Encode a value in this serializer's format, with a format tag
+    pub fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>, String> {
         match self.format {
-            SerializationFormat::Json => {
-                serde_json::from_slice(data)
-                    .map_err(|e| format!("JSON deserialization failed: {}", e))
-            }
-            // Fallback to JSON
-            SerializationFormat::Bincode | SerializationFormat::MessagePack => {
-                serde_json::from_slice(data)
-                    .map_err(|e| format!("JSON deserialization failed: {}", e))
-            }
+            SerializationFormat::Bincode => encode_binary(value),
+            // MessagePack falls back to JSON to avoid another dependency
+            SerializationFormat::Json | SerializationFormat::MessagePack => encode_json(value),
         }
     }
 
+    /// Serialize a value in the compact binary format, regardless of settings
+    pub fn serialize_binary<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>, String> {
+        encode_binary(value)
+    }
+
+    /// Deserialize a value written by [`Self::serialize_binary`] or [`Self::encode`]
+    pub fn deserialize_binary<T: DeserializeOwned>(&self, data: &[u8]) -> Result<T, String> {
+        decode(data)
+    }
+
     /// Compress data using LZ4
     fn compress(&self, data: &[u8]) -> Result<Vec<u8>, String> {
         // Simple compression - in real implementation, use lz4 or similar
@@ -220,7 +254,8 @@ impl Default for CrdtSerializer {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::egui_app::crdt::{Agent, ConversationCrdt};
+    use crate::egui_app::crdt::{Agent, ConversationCrdt, MessageCrdt};
+    use uuid::Uuid;
 
     #[test]
     fn test_serializer_creation() {
@@ -263,4 +298,56 @@ mod tests {
         assert_eq!(stats.compressed_size, 5);
         assert_eq!(stats.compression_ratio, 1.0);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_binary_roundtrip_conversation_crdt() {
+        let serializer = CrdtSerializer::new();
+        let agent = Agent::new();
+        let mut crdt = ConversationCrdt::new(agent.id());
+        let user = Uuid::new_v4();
+        crdt.add_participant(user);
+        crdt.update_name("Project Discussion");
+
+        let data = serializer.serialize_binary(&crdt).expect("Serialization failed");
+        assert_eq!(data[0], TAG_BINARY);
+        assert!(data.len() < serde_json::to_vec(&crdt).unwrap().len());
+
+        let decoded: ConversationCrdt = serializer.deserialize_binary(&data).expect("Deserialization failed");
+        assert_eq!(decoded.conversation_id(), crdt.conversation_id());
+        assert_eq!(decoded.version(), crdt.version());
+        assert_eq!(decoded.name(), Some("Project Discussion"));
+        assert!(decoded.participants().contains(&user));
+    }
+
+    #[test]
+    fn test_binary_roundtrip_message_crdt() {
+        let serializer = CrdtSerializer::with_settings(false, SerializationFormat::Bincode);
+        let mut crdt = MessageCrdt::new(Uuid::new_v4(), 1);
+        for i in 0..20 {
+            let message = crdt.create_message(format!("Message {}", i), "text".to_string(), Uuid::new_v4());
+            crdt.add_received_message(message);
+        }
+
+        let state = serializer.serialize_crdt(&crdt, "message").expect("Serialization failed");
+        assert_eq!(state.data[0], TAG_BINARY);
+        let json_state = CrdtSerializer::new().serialize_crdt(&crdt, "message").unwrap();
+        assert!(state.data.len() < json_state.data.len());
+
+        // Either serializer reads either format
+        for state in [&state, &json_state] {
+            let decoded: MessageCrdt = CrdtSerializer::new().deserialize_crdt(state).expect("Deserialization failed");
+            assert_eq!(decoded.lamport_clock(), crdt.lamport_clock());
+            let contents: Vec<_> = decoded.get_messages_chronological().iter().map(|m| m.content.clone()).collect();
+            let expected: Vec<_> = crdt.get_messages_chronological().iter().map(|m| m.content.clone()).collect();
+            assert_eq!(contents, expected);
+        }
+    }
+
+    #[test]
+    fn test_decode_legacy_json_and_unknown_tag() {
+        let legacy = serde_json::to_vec(&vec![1u64, 2, 3]).unwrap();
+        assert_eq!(decode::<Vec<u64>>(&legacy).unwrap(), vec![1, 2, 3]);
+        assert!(decode::<Vec<u64>>(&[0x7f, 1, 2]).is_err());
+        assert!(decode::<Vec<u64>>(&[]).is_err());
+    }
+}
@@ -1,9 +1,16 @@
 //! # User State CRDT
 //!
 //! Conflict-free replicated data type for user presence and status management.
-//! Tracks online/offline status, last seen times, and user activity.
+//! Tracks each user's chosen status (online, away, do not disturb) and status
+//! text, whether they're connected, and when their status last changed.
+//!
+//! Statuses are last-write-wins per user: a remote status replaces ours only
+//! if it was set later. Connection changes from the server's heartbeat
+//! tracking don't count as writes, so they never hide a newer status.
 
 use crate::egui_app::crdt::{CrdtState, MergeResult, OperationMeta, OperationType};
+pub use crate::shared::messaging::PresenceStatus;
+use crate::shared::messaging::UserStatus;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use uuid::Uuid;
@@ -28,23 +35,27 @@ pub struct UserPresence {
     user_id: Uuid,
     /// Current status
     status: PresenceStatus,
-    /// Last seen timestamp
+    /// When the status was last set (RFC3339); empty if never
     last_seen: String,
-    /// Current activity
-    activity: Option<String>,
+    /// Status text, e.g. "In a meeting"
+    status_text: Option<String>,
 }
 
-/// User presence status
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
-pub enum PresenceStatus {
-    /// User is online
-    Online,
-    /// User is away
-    Away,
-    /// User is offline
-    Offline,
-    /// User is busy
-    Busy,
+impl UserPresence {
+    /// Current status
+    pub fn status(&self) -> &PresenceStatus {
+        &self.status
+    }
+
+    /// When the status was last set (RFC3339)
+    pub fn last_seen(&self) -> &str {
+        &self.last_seen
+    }
+
+    /// Status text, if the user set one
+    pub fn status_text(&self) -> Option<&str> {
+        self.status_text.as_deref()
+    }
 }
 
 impl UserStateCrdt {
@@ -59,25 +70,21 @@ impl UserStateCrdt {
     }
 
     /// Update user presence
-    pub fn update_presence(&mut self, user_id: Uuid, status: PresenceStatus, activity: Option<String>) {
+    pub fn update_presence(&mut self, user_id: Uuid, status: PresenceStatus, status_text: Option<String>) {
         let presence = self.presence.entry(user_id).or_insert(UserPresence {
             user_id,
             status: PresenceStatus::Offline,
-            last_seen: chrono::Utc::now().to_rfc3339(),
-            activity: None,
+            last_seen: String::new(),
+            status_text: None,
         });
 
-        let status_clone = status.clone();
-        let activity_clone = activity.clone();
-
-        presence.status = status_clone;
+        presence.status = status;
         presence.last_seen = chrono::Utc::now().to_rfc3339();
-        presence.activity = activity.clone();
+        presence.status_text = status_text.clone();
 
         // Record operation
         let op_id = self.operations.len() as u64 + 1;
-        let data = serde_json::to_vec(&PresenceOperation { user_id, status, activity: activity_clone })
-            .unwrap_or_default();
+        let data = OperationMeta::encode_data(&PresenceOperation { user_id, status, status_text });
 
         let operation = OperationMeta {
             id: op_id,
@@ -91,16 +98,76 @@ impl UserStateCrdt {
         self.version += 1;
     }
 
+    /// Record that a user connected or disconnected
+    ///
+    /// Disconnecting shows them offline; reconnecting brings back `Online`
+    /// until they send their status again.
+    pub fn set_connected(&mut self, user_id: Uuid, connected: bool) {
+        let presence = self.presence.entry(user_id).or_insert(UserPresence {
+            user_id,
+            status: PresenceStatus::Offline,
+            last_seen: String::new(),
+            status_text: None,
+        });
+        if !connected {
+            presence.status = PresenceStatus::Offline;
+        } else if presence.status == PresenceStatus::Offline {
+            presence.status = PresenceStatus::Online;
+        }
+    }
+
+    /// Apply a status from the server if it's newer than ours; returns whether it was
+    pub fn merge_status(&mut self, status: &UserStatus) -> bool {
+        if self
+            .presence
+            .get(&status.user_id)
+            .is_some_and(|local| local.last_seen.as_str() >= status.updated_at.as_str())
+        {
+            return false;
+        }
+        self.presence.insert(
+            status.user_id,
+            UserPresence {
+                user_id: status.user_id,
+                status: status.status,
+                last_seen: status.updated_at.clone(),
+                status_text: status.status_text.clone(),
+            },
+        );
+        self.version += 1;
+        true
+    }
+
+    /// A user's status in wire form, e.g. to send our own to the server
+    pub fn user_status(&self, user_id: &Uuid) -> Option<UserStatus> {
+        self.presence.get(user_id).map(|presence| UserStatus {
+            user_id: presence.user_id,
+            status: presence.status,
+            status_text: presence.status_text.clone(),
+            updated_at: presence.last_seen.clone(),
+        })
+    }
+
     /// Get user presence
     pub fn get_presence(&self, user_id: &Uuid) -> Option<&UserPresence> {
         self.presence.get(user_id)
     }
 
+    /// A user's status; `Offline` if unknown
+    pub fn status(&self, user_id: &Uuid) -> PresenceStatus {
+        self.presence.get(user_id).map_or(PresenceStatus::Offline, |p| p.status)
+    }
+
+    /// Whether a user is known to be connected
+    pub fn is_online(&self, user_id: &Uuid) -> bool {
+        self.status(user_id) != PresenceStatus::Offline
+    }
+
     /// Get all online users
     pub fn get_online_users(&self) -> Vec<Uuid> {
         self.presence
             .iter()
-            .filter(|(_, presence)| presence.status == PresenceStatus::Online)
+            .filter(|(_, presence)| presence.status != PresenceStatus::Offline)
             .map(|(user_id, _)| *user_id)
             .collect()
     }
@@ -144,8 +211,8 @@ impl CrdtState for UserStateCrdt {
     }
 
     fn apply_operation(&mut self, op: &OperationMeta) -> Result<(), String> {
-        if let Ok(presence_op) = serde_json::from_slice::<PresenceOperation>(&op.data) {
-            self.update_presence(presence_op.user_id, presence_op.status, presence_op.activity);
+        if let Ok(presence_op) = op.decode_data::<PresenceOperation>() {
+            self.update_presence(presence_op.user_id, presence_op.status, presence_op.status_text);
         }
         Ok(())
     }
@@ -163,5 +230,51 @@ impl CrdtState for UserStateCrdt {
 struct PresenceOperation {
     user_id: Uuid,
     status: PresenceStatus,
-    activity: Option<String>,
-}
\ No newline at end of file
+    status_text: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(user_id: Uuid, status: PresenceStatus, text: Option<&str>, at: &str) -> UserStatus {
+        UserStatus {
+            user_id,
+            status,
+            status_text: text.map(str::to_string),
+            updated_at: at.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_newer_status_wins_and_connection_changes_do_not_count() {
+        let mut crdt = UserStateCrdt::new(1);
+        let user = Uuid::new_v4();
+
+        assert!(crdt.merge_status(&status(user, PresenceStatus::Away, Some("lunch"), "2024-01-01T12:00:00+00:00")));
+        assert!(!crdt.merge_status(&status(user, PresenceStatus::Online, None, "2024-01-01T11:00:00+00:00")));
+        assert_eq!(crdt.status(&user), PresenceStatus::Away);
+        assert_eq!(crdt.get_presence(&user).unwrap().status_text(), Some("lunch"));
+
+        crdt.set_connected(user, false);
+        assert!(!crdt.is_online(&user));
+        crdt.set_connected(user, true);
+        assert_eq!(crdt.status(&user), PresenceStatus::Online);
+
+        assert!(crdt.merge_status(&status(user, PresenceStatus::DoNotDisturb, None, "2024-01-01T13:00:00+00:00")));
+        assert_eq!(crdt.user_status(&user).unwrap().status, PresenceStatus::DoNotDisturb);
+    }
+
+    #[test]
+    fn test_merge_replicas() {
+        let user = Uuid::new_v4();
+        let mut ours = UserStateCrdt::new(1);
+        let mut theirs = UserStateCrdt::new(2);
+        ours.merge_status(&status(user, PresenceStatus::Away, None, "2024-01-01T12:00:00+00:00"));
+        theirs.merge_status(&status(user, PresenceStatus::DoNotDisturb, None, "2024-01-01T12:30:00+00:00"));
+
+        assert_eq!(ours.merge(&theirs), MergeResult::BothMerged);
+        assert_eq!(ours.status(&user), PresenceStatus::DoNotDisturb);
+        assert_eq!(theirs.merge(&ours), MergeResult::Identical);
+    }
+}
@@ -0,0 +1,273 @@
+//! Deep Links
+//!
+//! Parses and builds links to conversations and messages so they can be
+//! shared ("Copy link") and opened by the native app.
+//!
+//! # Formats
+//!
+//! - `xfmail://conversation/{conversation_id}`
+//! - `xfmail://conversation/{conversation_id}/message/{message_id}`
+//! - `https://{DEEP_LINK_HTTPS_HOST}/conversation/{conversation_id}[/message/{message_id}]`
+//!   (fallback for clients without the scheme handler registered)
+//!
+//! # OS Registration
+//!
+//! With the `deep-links` feature, [`register_url_handler`] registers the
+//! current executable as the handler for the `xfmail://` scheme. The app
+//! receives the link as its first command-line argument.
+
+use std::fmt;
+
+use uuid::Uuid;
+
+/// Custom URL scheme handled by the native app
+pub const DEEP_LINK_SCHEME: &str = "xfmail";
+
+/// Host used for https fallback links
+pub const DEEP_LINK_HTTPS_HOST: &str = "xfmail.app";
+
+/// A parsed link to a conversation, optionally to a specific message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeepLink {
+    pub conversation_id: Uuid,
+    pub message_id: Option<Uuid>,
+}
+
+/// Why a deep link could not be parsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeepLinkError {
+    /// Not a URL at all
+    InvalidUrl,
+    /// Scheme or host is not one we handle
+    UnsupportedScheme(String),
+    /// Path does not match `conversation/{id}[/message/{id}]`
+    InvalidPath,
+    /// An ID segment is not a UUID
+    InvalidId(String),
+}
+
+impl fmt::Display for DeepLinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeepLinkError::InvalidUrl => write!(f, "Not a valid link"),
+            DeepLinkError::UnsupportedScheme(scheme) => write!(f, "Unsupported link: {}", scheme),
+            DeepLinkError::InvalidPath => write!(f, "Link does not point to a conversation"),
+            DeepLinkError::InvalidId(id) => write!(f, "Invalid ID in link: {}", id),
+        }
+    }
+}
+
+impl std::error::Error for DeepLinkError {}
+
+/// Outcome of navigating to a deep link
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NavigationResult {
+    /// Conversation opened (and message targeted, if any)
+    Navigated,
+    /// Not logged in or conversations not loaded yet; the link is retried later
+    Deferred,
+    /// The user is not a participant in the linked conversation
+    NotParticipant,
+    /// The linked message no longer exists (deleted) or is unavailable
+    MessageUnavailable,
+}
+
+impl DeepLink {
+    /// Link to a conversation
+    pub fn conversation(conversation_id: Uuid) -> Self {
+        Self { conversation_id, message_id: None }
+    }
+
+    /// Link to a message within a conversation
+    pub fn message(conversation_id: Uuid, message_id: Uuid) -> Self {
+        Self { conversation_id, message_id: Some(message_id) }
+    }
+
+    /// Parse an `xfmail://` or https fallback link
+    pub fn parse(link: &str) -> Result<Self, DeepLinkError> {
+        let url = reqwest::Url::parse(link.trim()).map_err(|_| DeepLinkError::InvalidUrl)?;
+
+        // For `xfmail://conversation/...` the URL parser treats `conversation` as the host
+        let segments: Vec<String> = match url.scheme() {
+            DEEP_LINK_SCHEME => {
+                let host = url.host_str().unwrap_or_default();
+                std::iter::once(host.to_string())
+                    .chain(url.path_segments().into_iter().flatten().map(str::to_string))
+                    .filter(|s| !s.is_empty())
+                    .collect()
+            }
+            "https" if url.host_str() == Some(DEEP_LINK_HTTPS_HOST) => url
+                .path_segments()
+                .into_iter()
+                .flatten()
+                .filter(|s| !s.is_empty())
+                .map(str::to_string)
+                .collect(),
+            other => {
+                let target = url.host_str().map(|h| format!("{}://{}", other, h)).unwrap_or_else(|| other.to_string());
+                return Err(DeepLinkError::UnsupportedScheme(target));
+            }
+        };
+
+        let parse_id = |s: &str| Uuid::parse_str(s).map_err(|_| DeepLinkError::InvalidId(s.to_string()));
+
+        match segments.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+            ["conversation", conversation_id] => Ok(Self::conversation(parse_id(conversation_id)?)),
+            ["conversation", conversation_id, "message", message_id] => {
+                Ok(Self::message(parse_id(conversation_id)?, parse_id(message_id)?))
+            }
+            _ => Err(DeepLinkError::InvalidPath),
+        }
+    }
+
+    fn path(&self) -> String {
+        match self.message_id {
+            Some(message_id) => format!("conversation/{}/message/{}", self.conversation_id, message_id),
+            None => format!("conversation/{}", self.conversation_id),
+        }
+    }
+
+    /// https fallback form of this link
+    pub fn to_https_url(&self) -> String {
+        format!("https://{}/{}", DEEP_LINK_HTTPS_HOST, self.path())
+    }
+}
+
+impl fmt::Display for DeepLink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}://{}", DEEP_LINK_SCHEME, self.path())
+    }
+}
+
+impl std::str::FromStr for DeepLink {
+    type Err = DeepLinkError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+/// Find a deep link among command-line arguments (as passed by the OS handler)
+pub fn from_args<I: IntoIterator<Item = String>>(args: I) -> Option<DeepLink> {
+    args.into_iter().skip(1).find_map(|arg| DeepLink::parse(&arg).ok())
+}
+
+/// Register the current executable as the `xfmail://` handler
+#[cfg(feature = "deep-links")]
+pub fn register_url_handler() -> Result<(), String> {
+    let exe = std::env::current_exe().map_err(|e| format!("Failed to locate executable: {}", e))?;
+
+    #[cfg(target_os = "linux")]
+    {
+        let applications = dirs::data_dir()
+            .ok_or("No data directory")?
+            .join("applications");
+        std::fs::create_dir_all(&applications).map_err(|e| e.to_string())?;
+
+        let desktop_file = applications.join("xfmail-url-handler.desktop");
+        let contents = format!(
+            "[Desktop Entry]\nType=Application\nName=XFMail\nExec=\"{}\" %u\nNoDisplay=true\nMimeType=x-scheme-handler/{};\n",
+            exe.display(),
+            DEEP_LINK_SCHEME
+        );
+        std::fs::write(&desktop_file, contents).map_err(|e| e.to_string())?;
+
+        let status = std::process::Command::new("xdg-mime")
+            .args(["default", "xfmail-url-handler.desktop", &format!("x-scheme-handler/{}", DEEP_LINK_SCHEME)])
+            .status()
+            .map_err(|e| format!("Failed to run xdg-mime: {}", e))?;
+        if !status.success() {
+            return Err(format!("xdg-mime exited with {}", status));
+        }
+        Ok(())
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        let key = format!("HKCU\\Software\\Classes\\{}", DEEP_LINK_SCHEME);
+        let command_key = format!("{}\\shell\\open\\command", key);
+        let command = format!("\"{}\" \"%1\"", exe.display());
+        let entries: [&[&str]; 3] = [
+            &["add", &key, "/ve", "/d", "URL:XFMail", "/f"],
+            &["add", &key, "/v", "URL Protocol", "/d", "", "/f"],
+            &["add", &command_key, "/ve", "/d", &command, "/f"],
+        ];
+        for args in entries {
+            let status = std::process::Command::new("reg")
+                .args(args)
+                .status()
+                .map_err(|e| format!("Failed to run reg: {}", e))?;
+            if !status.success() {
+                return Err(format!("reg exited with {}", status));
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "windows")))]
+    {
+        let _ = exe;
+        Err("URL scheme registration requires CFBundleURLTypes in the app bundle on this platform".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_message_link() {
+        let conversation_id = Uuid::new_v4();
+        let message_id = Uuid::new_v4();
+        let link = format!("xfmail://conversation/{}/message/{}", conversation_id, message_id);
+
+        assert_eq!(DeepLink::parse(&link), Ok(DeepLink::message(conversation_id, message_id)));
+    }
+
+    #[test]
+    fn test_parse_conversation_and_https_links() {
+        let conversation_id = Uuid::new_v4();
+        let link = DeepLink::conversation(conversation_id);
+
+        assert_eq!(DeepLink::parse(&link.to_string()), Ok(link));
+        assert_eq!(DeepLink::parse(&link.to_https_url()), Ok(link));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let link = DeepLink::message(Uuid::new_v4(), Uuid::new_v4());
+        assert_eq!(link.to_string().parse::<DeepLink>(), Ok(link));
+        assert_eq!(DeepLink::parse(&link.to_https_url()), Ok(link));
+    }
+
+    #[test]
+    fn test_parse_invalid_links() {
+        let id = Uuid::new_v4();
+
+        assert_eq!(DeepLink::parse("not a link"), Err(DeepLinkError::InvalidUrl));
+        assert!(matches!(
+            DeepLink::parse(&format!("https://evil.example/conversation/{}", id)),
+            Err(DeepLinkError::UnsupportedScheme(_))
+        ));
+        assert_eq!(
+            DeepLink::parse(&format!("xfmail://contact/{}", id)),
+            Err(DeepLinkError::InvalidPath)
+        );
+        assert_eq!(
+            DeepLink::parse(&format!("xfmail://conversation/{}/message", id)),
+            Err(DeepLinkError::InvalidPath)
+        );
+        assert_eq!(
+            DeepLink::parse("xfmail://conversation/not-a-uuid"),
+            Err(DeepLinkError::InvalidId("not-a-uuid".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_from_args() {
+        let link = DeepLink::conversation(Uuid::new_v4());
+        let args = vec!["egui_app".to_string(), "--verbose".to_string(), link.to_string()];
+        assert_eq!(from_args(args), Some(link));
+        assert_eq!(from_args(vec!["egui_app".to_string()]), None);
+    }
+}
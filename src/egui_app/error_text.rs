@@ -0,0 +1,87 @@
+//! # Error Text
+//!
+//! User-facing text for the [`ErrorCode`]s the backend puts in error
+//! responses. The server's `message` is English and meant for logs; the UI
+//! shows the text here instead, in the display locale where there is a
+//! translation and in English otherwise.
+
+use crate::shared::{ErrorBody, ErrorCode};
+
+/// Localized text for `code`, or `None` for codes with no specific text
+pub fn describe(code: ErrorCode, locale: &str) -> Option<&'static str> {
+    let language = locale.split('-').next().unwrap_or_default().to_ascii_lowercase();
+    let text = match (language.as_str(), code) {
+        (_, ErrorCode::BadRequest | ErrorCode::Internal | ErrorCode::Unknown) => return None,
+
+        ("de", ErrorCode::InvalidMessage) => "Die Nachricht konnte nicht gelesen werden.",
+        ("de", ErrorCode::EmptyText) => "Die Nachricht ist leer.",
+        ("de", ErrorCode::MessageTooLong) => "Die Nachricht ist zu lang.",
+        ("de", ErrorCode::MessageTooLarge) => "Die Nachricht ist zu groß zum Senden.",
+        ("de", ErrorCode::EmptyAuthor) => "Der Absendername fehlt.",
+        ("de", ErrorCode::AuthorTooLong) => "Der Absendername ist zu lang.",
+        ("de", ErrorCode::InvalidParents) => "Die Unterhaltung ist nicht synchron. Bitte erneut versuchen.",
+        ("de", ErrorCode::Unauthorized) => "Sitzung abgelaufen. Bitte erneut anmelden.",
+
+        ("fr", ErrorCode::InvalidMessage) => "Le message est illisible.",
+        ("fr", ErrorCode::EmptyText) => "Le message est vide.",
+        ("fr", ErrorCode::MessageTooLong) => "Le message est trop long.",
+        ("fr", ErrorCode::MessageTooLarge) => "Le message est trop volumineux pour être envoyé.",
+        ("fr", ErrorCode::EmptyAuthor) => "Le nom de l'expéditeur est manquant.",
+        ("fr", ErrorCode::AuthorTooLong) => "Le nom de l'expéditeur est trop long.",
+        ("fr", ErrorCode::InvalidParents) => "La conversation n'est pas synchronisée. Veuillez réessayer.",
+        ("fr", ErrorCode::Unauthorized) => "Votre session a expiré. Veuillez vous reconnecter.",
+
+        ("es", ErrorCode::InvalidMessage) => "No se pudo leer el mensaje.",
+        ("es", ErrorCode::EmptyText) => "El mensaje está vacío.",
+        ("es", ErrorCode::MessageTooLong) => "El mensaje es demasiado largo.",
+        ("es", ErrorCode::MessageTooLarge) => "El mensaje es demasiado grande para enviarlo.",
+        ("es", ErrorCode::EmptyAuthor) => "Falta el nombre del remitente.",
+        ("es", ErrorCode::AuthorTooLong) => "El nombre del remitente es demasiado largo.",
+        ("es", ErrorCode::InvalidParents) => "La conversación no está sincronizada. Inténtalo de nuevo.",
+        ("es", ErrorCode::Unauthorized) => "Tu sesión ha caducado. Inicia sesión de nuevo.",
+
+        (_, ErrorCode::InvalidMessage) => "The message couldn't be read.",
+        (_, ErrorCode::EmptyText) => "The message is empty.",
+        (_, ErrorCode::MessageTooLong) => "The message is too long.",
+        (_, ErrorCode::MessageTooLarge) => "The message is too large to send.",
+        (_, ErrorCode::EmptyAuthor) => "The sender name is missing.",
+        (_, ErrorCode::AuthorTooLong) => "The sender name is too long.",
+        (_, ErrorCode::InvalidParents) => "The conversation is out of sync. Please try again.",
+        (_, ErrorCode::Unauthorized) => "Your session has expired. Please sign in again.",
+    };
+    Some(text)
+}
+
+/// Text to show for a failed response with `status` and `body`
+///
+/// Uses the localized text for the body's error code when there is one,
+/// falling back to the raw body and then the status reason.
+pub fn http_message(status: reqwest::StatusCode, body: &str, locale: &str) -> String {
+    if let Ok(error) = serde_json::from_str::<ErrorBody>(body) {
+        return describe(error.code, locale).map(str::to_string).unwrap_or(error.message);
+    }
+    if body.trim().is_empty() {
+        status.canonical_reason().unwrap_or("Request failed").to_string()
+    } else {
+        body.trim().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::StatusCode;
+
+    #[test]
+    fn test_codes_are_localized() {
+        let body = r#"{"code":"MESSAGE_TOO_LONG","message":"Message text exceeds the length limit"}"#;
+        assert_eq!(http_message(StatusCode::BAD_REQUEST, body, "de-DE"), "Die Nachricht ist zu lang.");
+        assert_eq!(http_message(StatusCode::BAD_REQUEST, body, "ja"), "The message is too long.");
+
+        // Generic codes keep the server's message, other bodies pass through
+        let body = r#"{"code":"INTERNAL","message":"State error"}"#;
+        assert_eq!(http_message(StatusCode::INTERNAL_SERVER_ERROR, body, "en"), "State error");
+        assert_eq!(http_message(StatusCode::BAD_GATEWAY, " upstream down\n", "en"), "upstream down");
+        assert_eq!(http_message(StatusCode::NOT_FOUND, "", "en"), "Not Found");
+    }
+}
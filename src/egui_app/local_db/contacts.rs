@@ -49,7 +49,7 @@ impl LocalDatabase {
         .bind(&contact.username)
         .bind(&contact.email)
         .bind(&contact.display_name)
-        .bind(&contact.created_at)
+        .bind(contact.created_at.to_rfc3339())
         .bind(chrono::Utc::now().to_rfc3339())
         .bind(true) // Mark as needing sync
         .execute(&self.pool)
@@ -181,15 +181,9 @@ impl LocalDatabase {
             email: row.try_get("email")?,
             display_name: row.try_get("display_name")?,
             avatar_url: None, // TODO: Add avatar support
-            #[cfg(feature = "ssr")]
             last_seen: chrono::Utc::now(), // TODO: Store last_seen
-            #[cfg(not(feature = "ssr"))]
-            last_seen: chrono::Utc::now().to_rfc3339(), // TODO: Store last_seen
             is_online: false, // TODO: Add online status
-            #[cfg(feature = "ssr")]
             created_at: chrono::Utc::now(), // TODO: Store created_at
-            #[cfg(not(feature = "ssr"))]
-            created_at: chrono::Utc::now().to_rfc3339(), // TODO: Store created_at
         })
     }
 }
@@ -200,7 +194,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_store_and_retrieve_contact() {
-        let db = LocalDatabase::new().await.unwrap();
+        let (_dir, db) = LocalDatabase::open_temp().await;
 
         let contact = Contact {
             id: Uuid::new_v4(),
@@ -210,9 +204,9 @@ mod tests {
             email: "test@example.com".to_string(),
             display_name: Some("Test User".to_string()),
             avatar_url: None,
-            last_seen: chrono::Utc::now().to_rfc3339(),
+            last_seen: chrono::Utc::now(),
             is_online: false,
-            created_at: chrono::Utc::now().to_rfc3339(),
+            created_at: chrono::Utc::now(),
         };
 
         // Store contact
@@ -229,7 +223,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_search_contacts() {
-        let db = LocalDatabase::new().await.unwrap();
+        let (_dir, db) = LocalDatabase::open_temp().await;
 
         let contact1 = Contact {
             id: Uuid::new_v4(),
@@ -239,9 +233,9 @@ mod tests {
             email: "alice@example.com".to_string(),
             display_name: Some("Alice Smith".to_string()),
             avatar_url: None,
-            last_seen: chrono::Utc::now().to_rfc3339(),
+            last_seen: chrono::Utc::now(),
             is_online: false,
-            created_at: chrono::Utc::now().to_rfc3339(),
+            created_at: chrono::Utc::now(),
         };
 
         let contact2 = Contact {
@@ -252,9 +246,9 @@ mod tests {
             email: "bob@example.com".to_string(),
             display_name: Some("Bob Johnson".to_string()),
             avatar_url: None,
-            last_seen: chrono::Utc::now().to_rfc3339(),
+            last_seen: chrono::Utc::now(),
             is_online: false,
-            created_at: chrono::Utc::now().to_rfc3339(),
+            created_at: chrono::Utc::now(),
         };
 
         db.store_contact(&contact1).await.unwrap();
@@ -27,7 +27,7 @@
 //! let participants = db.get_conversation_participants(&conversation_id).unwrap();
 //! ```
 
-use crate::shared::messaging::Conversation;
+use crate::shared::messaging::{Conversation, ThemeColor};
 use crate::egui_app::local_db::LocalDatabase;
 use sqlx::{Result as SqlxResult, Row};
 use uuid::Uuid;
@@ -41,11 +41,12 @@ impl LocalDatabase {
         // Store conversation
         sqlx::query(
             "INSERT OR REPLACE INTO conversations (
-                id, created_at, updated_at, needs_sync
-            ) VALUES (?, ?, ?, ?)",
+                id, name, created_at, updated_at, needs_sync
+            ) VALUES (?, ?, ?, ?, ?)",
         )
         .bind(conversation.id.to_string())
-        .bind(&conversation.created_at)
+        .bind(&conversation.name)
+        .bind(conversation.created_at.to_rfc3339())
         .bind(chrono::Utc::now().to_rfc3339())
         .bind(true) // Mark as needing sync
         .execute(&self.pool)
@@ -74,11 +75,15 @@ impl LocalDatabase {
     pub async fn get_conversations(&self, current_user_id: Option<&Uuid>) -> Result<Vec<Conversation>> {
         if let Some(user_id) = current_user_id {
             let rows = sqlx::query(
-                "SELECT DISTINCT c.id, c.created_at
+                "SELECT DISTINCT c.id, c.name, c.created_at, c.updated_at, s.manually_unread, s.theme_color,
+                        s.pinned, s.pin_order
                  FROM conversations c
                  INNER JOIN conversation_participants cp ON c.id = cp.conversation_id
+                 LEFT JOIN conversation_settings s ON s.conversation_id = c.id
                  WHERE cp.user_id = ?
-                 ORDER BY c.updated_at DESC"
+                 ORDER BY COALESCE(s.pinned, 0) DESC,
+                          CASE WHEN s.pinned THEN s.pin_order END,
+                          c.updated_at DESC"
             )
             .bind(user_id.to_string())
             .fetch_all(&self.pool)
@@ -100,9 +105,11 @@ impl LocalDatabase {
     /// Get a conversation by ID
     pub async fn get_conversation(&self, conversation_id: &Uuid) -> Result<Option<Conversation>> {
         let row = sqlx::query(
-            "SELECT id, name, conversation_type, created_by, created_at, updated_at
-             FROM conversations
-             WHERE id = ?"
+            "SELECT c.id, c.name, c.conversation_type, c.created_by, c.created_at, c.updated_at,
+                    s.manually_unread, s.theme_color, s.pinned, s.pin_order
+             FROM conversations c
+             LEFT JOIN conversation_settings s ON s.conversation_id = c.id
+             WHERE c.id = ?"
         )
         .bind(conversation_id.to_string())
         .fetch_optional(&self.pool)
@@ -131,6 +138,84 @@ impl LocalDatabase {
         Ok(participants)
     }
 
+    /// Set or clear the manual "unread" marker on a conversation
+    pub async fn set_conversation_manually_unread(&self, conversation_id: &Uuid, unread: bool) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO conversation_settings (conversation_id, manually_unread, updated_at, needs_sync)
+             VALUES (?, ?, ?, 1)
+             ON CONFLICT(conversation_id) DO UPDATE SET
+                manually_unread = excluded.manually_unread,
+                updated_at = excluded.updated_at,
+                needs_sync = 1",
+        )
+        .bind(conversation_id.to_string())
+        .bind(unread)
+        .bind(chrono::Utc::now().to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Set or clear the user's theme color for a conversation
+    pub async fn set_conversation_theme_color(&self, conversation_id: &Uuid, color: Option<ThemeColor>) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO conversation_settings (conversation_id, theme_color, updated_at, needs_sync)
+             VALUES (?, ?, ?, 1)
+             ON CONFLICT(conversation_id) DO UPDATE SET
+                theme_color = excluded.theme_color,
+                updated_at = excluded.updated_at,
+                needs_sync = 1",
+        )
+        .bind(conversation_id.to_string())
+        .bind(color.map(|c| c.to_hex()))
+        .bind(chrono::Utc::now().to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Pin or unpin a conversation; a new pin goes below the existing ones
+    pub async fn set_conversation_pinned(&self, conversation_id: &Uuid, pinned: bool) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO conversation_settings (conversation_id, pinned, pin_order, updated_at, needs_sync)
+             VALUES (?1, ?2, (SELECT COALESCE(MAX(pin_order) + 1, 0) FROM conversation_settings WHERE pinned), ?3, 1)
+             ON CONFLICT(conversation_id) DO UPDATE SET
+                pin_order = CASE WHEN pinned = excluded.pinned THEN pin_order ELSE excluded.pin_order END,
+                pinned = excluded.pinned,
+                updated_at = excluded.updated_at,
+                needs_sync = 1",
+        )
+        .bind(conversation_id.to_string())
+        .bind(pinned)
+        .bind(chrono::Utc::now().to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Reorder pinned conversations, top first; unpinned ones are ignored
+    pub async fn reorder_pinned_conversations(&self, conversation_ids: &[Uuid]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        let now = chrono::Utc::now().to_rfc3339();
+        for (position, conversation_id) in conversation_ids.iter().enumerate() {
+            sqlx::query(
+                "UPDATE conversation_settings SET pin_order = ?, updated_at = ?, needs_sync = 1
+                 WHERE conversation_id = ? AND pinned",
+            )
+            .bind(position as i64)
+            .bind(&now)
+            .bind(conversation_id.to_string())
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(())
+    }
+
     /// Mark conversation as synced
     pub async fn mark_conversation_synced(&self, conversation_id: &Uuid) -> Result<()> {
         sqlx::query(
@@ -177,11 +262,21 @@ impl LocalDatabase {
         Ok(Conversation {
             id: conversation_id,
             participants: Vec::new(), // Will be populated by caller if needed
+            name: row.try_get::<Option<String>, _>("name").ok().flatten(),
             other_username: None, // TODO: Calculate from participants
+            member_usernames: Vec::new(),
             last_message: None, // TODO: Get from messages
             last_message_preview: String::new(), // TODO: Calculate from last message
             last_message_time: None, // TODO: Get from last message
             unread_count: 0, // TODO: Calculate unread count
+            manually_unread: row.try_get::<Option<bool>, _>("manually_unread").ok().flatten().unwrap_or(false),
+            theme_color: row
+                .try_get::<Option<String>, _>("theme_color")
+                .ok()
+                .flatten()
+                .and_then(|hex| ThemeColor::from_hex(&hex)),
+            pinned: row.try_get::<Option<bool>, _>("pinned").ok().flatten().unwrap_or(false),
+            pin_order: row.try_get::<Option<i32>, _>("pin_order").ok().flatten().unwrap_or(0),
             created_at: row.try_get("created_at")?,
         })
     }
@@ -193,17 +288,23 @@ mod tests {
 
     #[tokio::test]
     async fn test_store_and_retrieve_conversation() {
-        let db = LocalDatabase::new().await.unwrap();
+        let (_dir, db) = LocalDatabase::open_temp().await;
 
         let conversation = Conversation {
             id: Uuid::new_v4(),
             participants: vec![Uuid::new_v4(), Uuid::new_v4()],
+            name: None,
             other_username: None,
+            member_usernames: Vec::new(),
             last_message: None,
             last_message_preview: String::new(),
             last_message_time: None,
             unread_count: 0,
-            created_at: chrono::Utc::now().to_rfc3339(),
+            manually_unread: false,
+            theme_color: None,
+            pinned: false,
+            pin_order: 0,
+            created_at: chrono::Utc::now(),
         };
 
         // Store conversation
@@ -216,9 +317,81 @@ mod tests {
         assert_eq!(retrieved.participants.len(), conversation.participants.len());
     }
 
+    #[tokio::test]
+    async fn test_manually_unread_round_trip() {
+        let (_dir, db) = LocalDatabase::open_temp().await;
+        let conversation = Conversation::new_direct(Uuid::new_v4(), Uuid::new_v4());
+        db.store_conversation(&conversation).await.unwrap();
+
+        db.set_conversation_manually_unread(&conversation.id, true).await.unwrap();
+        let retrieved = db.get_conversation(&conversation.id).await.unwrap().unwrap();
+        assert!(retrieved.manually_unread);
+        assert_eq!(retrieved.unread_count, 0);
+        assert!(retrieved.unread_badge().is_some());
+
+        db.set_conversation_manually_unread(&conversation.id, false).await.unwrap();
+        let retrieved = db.get_conversation(&conversation.id).await.unwrap().unwrap();
+        assert!(!retrieved.manually_unread);
+    }
+
+    #[tokio::test]
+    async fn test_theme_color_round_trip() {
+        let (_dir, db) = LocalDatabase::open_temp().await;
+        let user_id = Uuid::new_v4();
+        let conversation = Conversation::new_direct(user_id, Uuid::new_v4());
+        db.store_conversation(&conversation).await.unwrap();
+        assert_eq!(db.get_conversation(&conversation.id).await.unwrap().unwrap().theme_color, None);
+
+        let color = ThemeColor::new(0x2e, 0x8b, 0x57);
+        db.set_conversation_theme_color(&conversation.id, Some(color)).await.unwrap();
+        // Independent of the unread marker stored in the same row
+        db.set_conversation_manually_unread(&conversation.id, true).await.unwrap();
+        let retrieved = db.get_conversation(&conversation.id).await.unwrap().unwrap();
+        assert_eq!(retrieved.theme_color, Some(color));
+        let listed = db.get_conversations(Some(&user_id)).await.unwrap();
+        assert_eq!(listed[0].theme_color, Some(color));
+
+        db.set_conversation_theme_color(&conversation.id, None).await.unwrap();
+        let retrieved = db.get_conversation(&conversation.id).await.unwrap().unwrap();
+        assert_eq!(retrieved.theme_color, None);
+        assert!(retrieved.manually_unread);
+    }
+
+    async fn listed(db: &LocalDatabase, user_id: Uuid) -> Vec<Uuid> {
+        db.get_conversations(Some(&user_id)).await.unwrap().iter().map(|c| c.id).collect()
+    }
+
+    #[tokio::test]
+    async fn test_pinned_conversations_list_first() {
+        let (_dir, db) = LocalDatabase::open_temp().await;
+        let user_id = Uuid::new_v4();
+        let mut ids = Vec::new();
+        for _ in 0..3 {
+            let conversation = Conversation::new_direct(user_id, Uuid::new_v4());
+            db.store_conversation(&conversation).await.unwrap();
+            ids.push(conversation.id);
+            // Distinct updated_at values, newest last
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        }
+        assert_eq!(listed(&db, user_id).await, vec![ids[2], ids[1], ids[0]]);
+
+        db.set_conversation_pinned(&ids[0], true).await.unwrap();
+        db.set_conversation_pinned(&ids[1], true).await.unwrap();
+        assert_eq!(listed(&db, user_id).await, vec![ids[0], ids[1], ids[2]]);
+        let pinned = db.get_conversation(&ids[1]).await.unwrap().unwrap();
+        assert!(pinned.pinned);
+        assert_eq!(pinned.pin_order, 1);
+
+        db.reorder_pinned_conversations(&[ids[1], ids[0]]).await.unwrap();
+        assert_eq!(listed(&db, user_id).await, vec![ids[1], ids[0], ids[2]]);
+
+        db.set_conversation_pinned(&ids[1], false).await.unwrap();
+        assert_eq!(listed(&db, user_id).await, vec![ids[0], ids[2], ids[1]]);
+    }
+
     #[tokio::test]
     async fn test_get_conversations_for_user() {
-        let db = LocalDatabase::new().await.unwrap();
+        let (_dir, db) = LocalDatabase::open_temp().await;
 
         let user_id = Uuid::new_v4();
         let other_user_id = Uuid::new_v4();
@@ -226,23 +399,35 @@ mod tests {
         let conversation1 = Conversation {
             id: Uuid::new_v4(),
             participants: vec![user_id, other_user_id],
+            name: None,
             other_username: None,
+            member_usernames: Vec::new(),
             last_message: None,
             last_message_preview: String::new(),
             last_message_time: None,
             unread_count: 0,
-            created_at: chrono::Utc::now().to_rfc3339(),
+            manually_unread: false,
+            theme_color: None,
+            pinned: false,
+            pin_order: 0,
+            created_at: chrono::Utc::now(),
         };
 
         let conversation2 = Conversation {
             id: Uuid::new_v4(),
             participants: vec![user_id, Uuid::new_v4()],
+            name: None,
             other_username: None,
+            member_usernames: Vec::new(),
             last_message: None,
             last_message_preview: String::new(),
             last_message_time: None,
             unread_count: 0,
-            created_at: chrono::Utc::now().to_rfc3339(),
+            manually_unread: false,
+            theme_color: None,
+            pinned: false,
+            pin_order: 0,
+            created_at: chrono::Utc::now(),
         };
 
         db.store_conversation(&conversation1).await.unwrap();
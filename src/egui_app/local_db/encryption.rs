@@ -0,0 +1,212 @@
+//! # Local Database Encryption
+//!
+//! With the `encrypted-local-db` feature the local database is a SQLCipher
+//! file, so message bodies and everything else in it are encrypted at rest.
+//! The feature swaps the bundled SQLite for SQLCipher, which is why it is off
+//! by default.
+//!
+//! ## Key
+//!
+//! [`DatabaseKey::load`] picks the key:
+//! - `XFMAIL_DB_KEY`, a passphrase SQLCipher stretches with PBKDF2 (for
+//!   headless setups without a keystore)
+//! - otherwise a random 256-bit key kept in the OS keystore (Keychain,
+//!   Credential Manager or the Secret Service), created on first use
+//!
+//! ## Migration
+//!
+//! A plaintext database found at the path is copied into a new encrypted
+//! file with `sqlcipher_export`, which then replaces it. Plaintext backups
+//! (`<db>.bak` and pre-migration snapshots) are removed afterwards; they
+//! could not be opened with the key anyway.
+
+use std::fmt;
+use std::path::Path;
+
+use sqlx::sqlite::{SqliteConnectOptions, SqliteConnection};
+use sqlx::{ConnectOptions, Connection};
+
+use super::{remove_wal_files, with_suffix, LocalDatabase, Result};
+
+/// Environment variable holding a passphrase to use instead of the keystore
+pub const DB_KEY_ENV: &str = "XFMAIL_DB_KEY";
+
+/// Keystore service and account the generated key is stored under
+const KEYSTORE_SERVICE: &str = "xfmail";
+const KEYSTORE_ACCOUNT: &str = "local-db-key";
+
+/// First bytes of every unencrypted SQLite file
+const PLAINTEXT_HEADER: &[u8; 16] = b"SQLite format 3\0";
+
+/// Key for a SQLCipher database, kept as its `PRAGMA key` value
+#[derive(Clone, PartialEq, Eq)]
+pub struct DatabaseKey(String);
+
+impl fmt::Debug for DatabaseKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("DatabaseKey(..)")
+    }
+}
+
+impl DatabaseKey {
+    /// Passphrase; SQLCipher derives the actual key from it
+    pub fn from_passphrase(passphrase: &str) -> Self {
+        Self(format!("'{}'", passphrase.replace('\'', "''")))
+    }
+
+    /// Raw 256-bit key, used as is
+    pub fn from_raw(key: &[u8; 32]) -> Self {
+        Self(format!("\"x'{}'\"", hex::encode(key)))
+    }
+
+    /// Key from [`DB_KEY_ENV`], or from the OS keystore
+    pub fn load() -> Result<Self> {
+        if let Some(passphrase) = std::env::var(DB_KEY_ENV).ok().filter(|p| !p.is_empty()) {
+            return Ok(Self::from_passphrase(&passphrase));
+        }
+
+        let entry = keyring::Entry::new(KEYSTORE_SERVICE, KEYSTORE_ACCOUNT).map_err(keystore_error)?;
+        let stored = match entry.get_password() {
+            Ok(stored) => stored,
+            Err(keyring::Error::NoEntry) => {
+                let mut key = [0u8; 32];
+                getrandom::fill(&mut key).map_err(|e| keystore_error(format!("no randomness for a new key: {}", e)))?;
+                let stored = hex::encode(key);
+                entry.set_password(&stored).map_err(keystore_error)?;
+                tracing::info!("Created local database key in the OS keystore");
+                stored
+            }
+            Err(e) => return Err(keystore_error(e)),
+        };
+
+        let key: [u8; 32] = hex::decode(&stored)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| keystore_error("stored local database key is malformed"))?;
+        Ok(Self::from_raw(&key))
+    }
+
+    /// Value for `PRAGMA key` and `ATTACH ... KEY`
+    pub(crate) fn pragma(&self) -> &str {
+        &self.0
+    }
+}
+
+fn keystore_error(e: impl fmt::Display) -> sqlx::Error {
+    sqlx::Error::Io(std::io::Error::other(format!(
+        "Local database key unavailable ({}); set {} to use a passphrase instead",
+        e, DB_KEY_ENV
+    )))
+}
+
+/// Whether `db_path` is an existing unencrypted SQLite file
+pub fn is_plaintext(db_path: &Path) -> bool {
+    use std::io::Read;
+
+    let mut header = [0u8; 16];
+    std::fs::File::open(db_path)
+        .and_then(|mut file| file.read_exact(&mut header))
+        .is_ok_and(|_| &header == PLAINTEXT_HEADER)
+}
+
+/// Encrypt a plaintext database in place; `false` if there was nothing to do
+pub(crate) async fn encrypt_plaintext(db_path: &Path, key: &str) -> Result<bool> {
+    if !is_plaintext(db_path) {
+        return Ok(false);
+    }
+    tracing::warn!("Encrypting plaintext local database {}", db_path.display());
+
+    let encrypted = with_suffix(db_path, ".encrypting");
+    let _ = std::fs::remove_file(&encrypted);
+
+    let mut conn: SqliteConnection = SqliteConnectOptions::new().filename(db_path).connect().await?;
+    let exported = async {
+        sqlx::query(&format!("ATTACH DATABASE ? AS encrypted KEY {}", key))
+            .bind(encrypted.to_string_lossy().to_string())
+            .execute(&mut conn)
+            .await?;
+        sqlx::query("SELECT sqlcipher_export('encrypted')").execute(&mut conn).await?;
+        sqlx::query("DETACH DATABASE encrypted").execute(&mut conn).await?;
+        Ok::<_, sqlx::Error>(())
+    }
+    .await;
+    conn.close().await?;
+    if let Err(e) = exported {
+        let _ = std::fs::remove_file(&encrypted);
+        return Err(e);
+    }
+
+    // The WAL belongs to the plaintext file and was folded into the export
+    remove_wal_files(db_path);
+    std::fs::rename(&encrypted, db_path)?;
+
+    for backup in std::iter::once(LocalDatabase::backup_path(db_path)).chain(LocalDatabase::migration_backups(db_path)) {
+        if backup.exists() {
+            if let Err(e) = std::fs::remove_file(&backup) {
+                tracing::warn!("Failed to remove plaintext backup {}: {}", backup.display(), e);
+            }
+        }
+    }
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn count_tables(db_path: &Path, key: Option<&DatabaseKey>) -> Result<i64> {
+        let mut options = SqliteConnectOptions::new().filename(db_path);
+        if let Some(key) = key {
+            options = options.pragma("key", key.pragma().to_string());
+        }
+        let mut conn = options.connect().await?;
+        let (count,): (i64,) = sqlx::query_as("SELECT count(*) FROM sqlite_master").fetch_one(&mut conn).await?;
+        conn.close().await?;
+        Ok(count)
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_db_cant_be_read_without_the_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("local.db");
+        let key = DatabaseKey::from_passphrase("correct horse");
+
+        let db = LocalDatabase::new_encrypted_at(&path, &key).await.unwrap();
+        db.pool().close().await;
+        assert!(!is_plaintext(&path));
+
+        assert!(count_tables(&path, None).await.is_err());
+        assert!(count_tables(&path, Some(&DatabaseKey::from_passphrase("battery staple"))).await.is_err());
+        assert!(count_tables(&path, Some(&key)).await.unwrap() > 0);
+
+        // A wrong key is an error, not a corrupt file to recover from
+        assert!(LocalDatabase::new_encrypted_at(&path, &DatabaseKey::from_raw(&[7; 32])).await.is_err());
+        assert!(count_tables(&path, Some(&key)).await.unwrap() > 0);
+    }
+
+    #[tokio::test]
+    async fn test_plaintext_db_is_encrypted_on_open() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("local.db");
+        {
+            let db = LocalDatabase::open(&path, None).await.unwrap();
+            sqlx::query("CREATE TABLE marker (text TEXT)").execute(db.pool()).await.unwrap();
+            sqlx::query("INSERT INTO marker VALUES ('secret message')").execute(db.pool()).await.unwrap();
+            db.pool().close().await;
+        }
+        std::fs::copy(&path, LocalDatabase::backup_path(&path)).unwrap();
+        assert!(is_plaintext(&path));
+
+        let key = DatabaseKey::from_raw(&[1; 32]);
+        let db = LocalDatabase::new_encrypted_at(&path, &key).await.unwrap();
+        let (text,): (String,) = sqlx::query_as("SELECT text FROM marker").fetch_one(db.pool()).await.unwrap();
+        assert_eq!(text, "secret message");
+        db.pool().close().await;
+
+        assert!(!is_plaintext(&path));
+        assert!(!LocalDatabase::backup_path(&path).exists());
+        let bytes = std::fs::read(&path).unwrap();
+        assert!(!bytes.windows(b"secret message".len()).any(|w| w == b"secret message"));
+    }
+}
@@ -0,0 +1,167 @@
+//! # Conversation Export
+//!
+//! Writes a conversation's locally stored history out as JSON or CSV for
+//! the user to keep. Deleted messages are left out. Timestamps are RFC3339
+//! and senders are shown by their contact name where we have one.
+//!
+//! ## Usage
+//!
+//! ```rust,no_run
+//! use xfmail::egui_app::local_db::{export::ExportFormat, LocalDatabase};
+//!
+//! let db = LocalDatabase::new().await.unwrap();
+//! let csv = db.export_conversation(&conversation_id, ExportFormat::Csv).await.unwrap();
+//! std::fs::write("chat.csv", csv).unwrap();
+//! ```
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+use sqlx::Row;
+use uuid::Uuid;
+
+use crate::egui_app::local_db::{LocalDatabase, Result};
+use crate::shared::messaging::ChatMessage;
+
+/// File format for [`LocalDatabase::export_conversation`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// An array of messages with their metadata
+    Json,
+    /// `timestamp,sender,content` rows under a header
+    Csv,
+}
+
+impl ExportFormat {
+    /// File extension, without the dot
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Csv => "csv",
+        }
+    }
+}
+
+/// One message in a JSON export
+#[derive(Debug, Serialize)]
+struct ExportedMessage<'a> {
+    id: Uuid,
+    timestamp: String,
+    sender_id: Uuid,
+    sender: &'a str,
+    content: &'a str,
+    message_type: String,
+    edited: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sequence: Option<i64>,
+}
+
+impl LocalDatabase {
+    /// Export a conversation's messages, oldest first
+    pub async fn export_conversation(&self, conversation_id: &Uuid, format: ExportFormat) -> Result<String> {
+        let messages = self.get_conversation_messages(conversation_id, None).await?;
+        let names = self.contact_names().await?;
+
+        let rows: Vec<(String, &str, &ChatMessage)> = messages
+            .iter()
+            .filter(|m| !m.is_deleted)
+            .map(|m| {
+                let sender = names.get(&m.sender_id).map(String::as_str).unwrap_or("You");
+                (m.timestamp.to_rfc3339(), sender, m)
+            })
+            .collect();
+
+        match format {
+            ExportFormat::Json => {
+                let exported: Vec<_> = rows
+                    .iter()
+                    .map(|(timestamp, sender, m)| ExportedMessage {
+                        id: m.id,
+                        timestamp: timestamp.clone(),
+                        sender_id: m.sender_id,
+                        sender,
+                        content: &m.content,
+                        message_type: m.message_type.to_string(),
+                        edited: m.edit_version.is_some(),
+                        sequence: m.sequence,
+                    })
+                    .collect();
+                serde_json::to_string_pretty(&exported).map_err(|e| sqlx::Error::Decode(Box::new(e)))
+            }
+            ExportFormat::Csv => {
+                let mut csv = String::from("timestamp,sender,content\r\n");
+                for (timestamp, sender, m) in &rows {
+                    csv.push_str(&format!("{},{},{}\r\n", csv_field(timestamp), csv_field(sender), csv_field(&m.content)));
+                }
+                Ok(csv)
+            }
+        }
+    }
+
+    /// Contacts' display names (or usernames) by user id
+    async fn contact_names(&self) -> Result<HashMap<Uuid, String>> {
+        let rows = sqlx::query("SELECT contact_user_id, username, display_name FROM contacts")
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows
+            .iter()
+            .filter_map(|row| {
+                let user_id = Uuid::parse_str(&row.get::<String, _>("contact_user_id")).ok()?;
+                let name = row.get::<Option<String>, _>("display_name").unwrap_or_else(|| row.get("username"));
+                Some((user_id, name))
+            })
+            .collect())
+    }
+}
+
+/// Quote a CSV field (RFC 4180) if it holds a comma, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::messaging::timestamp;
+
+    #[test]
+    fn test_csv_field_quotes_only_when_needed() {
+        assert_eq!(csv_field("plain text"), "plain text");
+        assert_eq!(csv_field("a, b"), "\"a, b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+    }
+
+    #[tokio::test]
+    async fn test_export_conversation_as_csv_and_json() {
+        let (_dir, db) = LocalDatabase::open_temp().await;
+        let conversation_id = Uuid::new_v4();
+        let sender_id = Uuid::new_v4();
+
+        let mut first = ChatMessage::new_text(conversation_id, sender_id, "hello, \"world\"\nbye".to_string(), 1);
+        first.timestamp = timestamp::parse("2024-03-01T10:00:00Z").unwrap();
+        let mut deleted = ChatMessage::new_text(conversation_id, sender_id, String::new(), 2);
+        deleted.is_deleted = true;
+        db.store_message(&first).await.unwrap();
+        db.store_message(&deleted).await.unwrap();
+
+        let csv = db.export_conversation(&conversation_id, ExportFormat::Csv).await.unwrap();
+        assert_eq!(
+            csv,
+            "timestamp,sender,content\r\n2024-03-01T10:00:00+00:00,You,\"hello, \"\"world\"\"\nbye\"\r\n"
+        );
+
+        let json = db.export_conversation(&conversation_id, ExportFormat::Json).await.unwrap();
+        let exported: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let exported = exported.as_array().unwrap();
+        assert_eq!(exported.len(), 1);
+        assert_eq!(exported[0]["id"], first.id.to_string());
+        assert_eq!(exported[0]["content"], first.content);
+        assert_eq!(exported[0]["timestamp"], "2024-03-01T10:00:00+00:00");
+    }
+}
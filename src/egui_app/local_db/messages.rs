@@ -24,18 +24,54 @@
 //! // Get recent messages
 //! let messages = db.get_conversation_messages(&conversation_id, Some(50)).await.unwrap();
 //!
+//! // Page back through history, newest first
+//! let page = db.get_conversation_messages_before(&conversation_id, None, 50).await.unwrap();
+//! if page.has_more {
+//!     let cursor = MessageCursor::of(&page.messages[0]);
+//!     let older = db.get_conversation_messages_before(&conversation_id, Some(&cursor), 50).await.unwrap();
+//! }
+//!
 //! // Mark as read
 //! db.mark_message_read(&message_id).await.unwrap();
 //! ```
 
-use crate::shared::messaging::ChatMessage;
+use crate::shared::messaging::{timestamp, ChatMessage};
+use crate::egui_app::crdt::message_crdt::MessageStatus;
 use crate::egui_app::local_db::LocalDatabase;
+use chrono::{DateTime, Utc};
 use sqlx::{Result as SqlxResult, Row};
 use uuid::Uuid;
 
 /// Result type alias for message operations
 pub type Result<T> = SqlxResult<T>;
 
+/// Position in a conversation's history to page back from
+///
+/// Messages are ordered by their own `timestamp` (when they were created,
+/// not when they were stored locally) with the ID as a tiebreaker, so the
+/// cursor stays put when newer messages arrive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MessageCursor {
+    pub timestamp: DateTime<Utc>,
+    pub id: Uuid,
+}
+
+impl MessageCursor {
+    /// Cursor at a message; pages start just before it
+    pub fn of(message: &ChatMessage) -> Self {
+        Self { timestamp: message.timestamp, id: message.id }
+    }
+}
+
+/// One page of a conversation's history
+#[derive(Debug, Clone, PartialEq)]
+pub struct MessagePage {
+    /// Oldest first, like `get_conversation_messages`
+    pub messages: Vec<ChatMessage>,
+    /// Whether there are older messages before this page
+    pub has_more: bool,
+}
+
 impl LocalDatabase {
     /// Store a message locally
     ///
@@ -46,25 +82,52 @@ impl LocalDatabase {
             .unwrap_or_else(|_| "[]".to_string());
 
         sqlx::query(
-            "INSERT OR REPLACE INTO messages (
+            // An upsert rather than INSERT OR REPLACE: REPLACE deletes the old
+            // row without firing delete triggers, which would leave stale
+            // entries in the `messages_fts` search index
+            "INSERT INTO messages (
                 id, conversation_id, sender_id, content, message_type,
                 timestamp, is_read, is_delivered, crdt_timestamp,
                 braid_version, braid_parents, delivery_status,
+                edit_version, is_deleted, sequence,
                 created_at, updated_at, needs_sync
-            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(id) DO UPDATE SET
+                conversation_id = excluded.conversation_id,
+                sender_id = excluded.sender_id,
+                content = excluded.content,
+                message_type = excluded.message_type,
+                timestamp = excluded.timestamp,
+                is_read = excluded.is_read,
+                is_delivered = excluded.is_delivered,
+                crdt_timestamp = excluded.crdt_timestamp,
+                braid_version = excluded.braid_version,
+                braid_parents = excluded.braid_parents,
+                delivery_status = excluded.delivery_status,
+                edit_version = excluded.edit_version,
+                is_deleted = excluded.is_deleted,
+                -- A local copy (e.g. an edit made offline) mustn't forget
+                -- the sequence the server already gave the message
+                sequence = COALESCE(excluded.sequence, messages.sequence),
+                created_at = excluded.created_at,
+                updated_at = excluded.updated_at,
+                needs_sync = excluded.needs_sync",
         )
         .bind(message.id.to_string())
         .bind(message.conversation_id.to_string())
         .bind(message.sender_id.to_string())
         .bind(&message.content)
         .bind(message.message_type.to_string())
-        .bind(&message.timestamp)
+        .bind(message.timestamp.to_rfc3339())
         .bind(message.is_read)
         .bind(message.is_delivered)
         .bind(message.crdt_timestamp as i64)
         .bind(&message.braid_version)
         .bind(braid_parents_json)
-        .bind("sent") // Default delivery status
+        .bind(delivery_status(message))
+        .bind(&message.edit_version)
+        .bind(message.is_deleted)
+        .bind(message.sequence)
         .bind(chrono::Utc::now().to_rfc3339())
         .bind(chrono::Utc::now().to_rfc3339())
         .bind(true) // Mark as needing sync
@@ -75,16 +138,20 @@ impl LocalDatabase {
     }
 
     /// Get messages for a conversation
+    ///
+    /// In display order: by server sequence, with messages the server hasn't
+    /// sequenced yet last, in CRDT order.
     pub async fn get_conversation_messages(&self, conversation_id: &Uuid, limit: Option<i32>) -> Result<Vec<ChatMessage>> {
         let limit_clause = limit.map(|l| format!("LIMIT {}", l)).unwrap_or_default();
 
         let query = format!(
             "SELECT id, conversation_id, sender_id, content, message_type,
                     timestamp, is_read, is_delivered, crdt_timestamp,
-                    braid_version, braid_parents
+                    braid_version, braid_parents, delivery_status,
+                    edit_version, is_deleted, sequence
              FROM messages
              WHERE conversation_id = ?
-             ORDER BY crdt_timestamp ASC
+             ORDER BY sequence IS NULL, sequence ASC, crdt_timestamp ASC
              {}",
             limit_clause
         );
@@ -102,12 +169,58 @@ impl LocalDatabase {
         Ok(messages)
     }
 
+    /// Get up to `limit` messages older than `before`, for scrolling back
+    ///
+    /// With no cursor this is the newest page. Pass `MessageCursor::of` the
+    /// oldest message shown to get the page before it.
+    pub async fn get_conversation_messages_before(
+        &self,
+        conversation_id: &Uuid,
+        before: Option<&MessageCursor>,
+        limit: u32,
+    ) -> Result<MessagePage> {
+        let (before_timestamp, before_id) = match before {
+            Some(cursor) => (Some(cursor.timestamp.to_rfc3339()), Some(cursor.id.to_string())),
+            None => (None, None),
+        };
+
+        // One extra row tells us whether there's another page
+        let rows = sqlx::query(
+            "SELECT id, conversation_id, sender_id, content, message_type,
+                    timestamp, is_read, is_delivered, crdt_timestamp,
+                    braid_version, braid_parents, delivery_status,
+                    edit_version, is_deleted, sequence
+             FROM messages
+             WHERE conversation_id = ?1
+               AND (?2 IS NULL OR timestamp < ?2 OR (timestamp = ?2 AND id < ?3))
+             ORDER BY timestamp DESC, id DESC
+             LIMIT ?4",
+        )
+        .bind(conversation_id.to_string())
+        .bind(before_timestamp)
+        .bind(before_id)
+        .bind(limit as i64 + 1)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let has_more = rows.len() > limit as usize;
+        let mut messages = rows
+            .iter()
+            .take(limit as usize)
+            .map(|row| self.row_to_message(row))
+            .collect::<Result<Vec<_>>>()?;
+        messages.reverse();
+
+        Ok(MessagePage { messages, has_more })
+    }
+
     /// Get a single message by ID
     pub async fn get_message(&self, message_id: &Uuid) -> Result<Option<ChatMessage>> {
         let row = sqlx::query(
             "SELECT id, conversation_id, sender_id, content, message_type,
                     timestamp, is_read, is_delivered, crdt_timestamp,
-                    braid_version, braid_parents
+                    braid_version, braid_parents, delivery_status,
+                    edit_version, is_deleted, sequence
              FROM messages
              WHERE id = ?"
         )
@@ -152,7 +265,8 @@ impl LocalDatabase {
         let rows = sqlx::query(
             "SELECT id, conversation_id, sender_id, content, message_type,
                     timestamp, is_read, is_delivered, crdt_timestamp,
-                    braid_version, braid_parents
+                    braid_version, braid_parents, delivery_status,
+                    edit_version, is_deleted, sequence
              FROM messages
              WHERE needs_sync = 1
              ORDER BY created_at ASC"
@@ -207,17 +321,32 @@ impl LocalDatabase {
             sender_id: Uuid::parse_str(&row.try_get::<String, _>("sender_id")?).unwrap_or_default(),
             content: row.try_get("content")?,
             message_type: crate::shared::messaging::MessageType::from_str(&row.try_get::<String, _>("message_type")?),
-            timestamp: row.try_get("timestamp")?,
+            timestamp: timestamp::parse(&row.try_get::<String, _>("timestamp")?).unwrap_or_default(),
             is_read: row.try_get("is_read")?,
             is_delivered: row.try_get("is_delivered")?,
+            is_sent: matches!(row.try_get::<String, _>("delivery_status")?.as_str(), "sent" | "delivered"),
             crdt_timestamp: row.try_get("crdt_timestamp")?,
             braid_version: row.try_get("braid_version")?,
             braid_parents,
             version_vector: crate::shared::messaging::message::VersionVector::default(),
+            link_preview: None,
+            moderation_flag: None,
+            edit_version: row.try_get("edit_version")?,
+            is_deleted: row.try_get("is_deleted")?,
+            sequence: row.try_get("sequence")?,
         })
     }
 }
 
+/// `delivery_status` column value for a message
+fn delivery_status(message: &ChatMessage) -> &'static str {
+    match MessageStatus::from(message) {
+        MessageStatus::Pending => "sending",
+        MessageStatus::Sent => "sent",
+        MessageStatus::Delivered | MessageStatus::Read => "delivered",
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -225,7 +354,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_store_and_retrieve_message() {
-        let db = LocalDatabase::new().await.unwrap();
+        let (_dir, db) = LocalDatabase::open_temp().await;
 
         let message = ChatMessage {
             id: Uuid::new_v4(),
@@ -233,13 +362,19 @@ mod tests {
             sender_id: Uuid::new_v4(),
             content: "Test message".to_string(),
             message_type: MessageType::Text,
-            timestamp: chrono::Utc::now().to_rfc3339(),
+            timestamp: chrono::Utc::now(),
             is_read: false,
             is_delivered: false,
+            is_sent: true,
             crdt_timestamp: 12345,
             braid_version: "v1".to_string(),
             braid_parents: vec![],
             version_vector: crate::shared::messaging::message::VersionVector::default(),
+            link_preview: None,
+            moderation_flag: None,
+            edit_version: None,
+            is_deleted: false,
+            sequence: None,
         };
 
         // Store message
@@ -251,5 +386,75 @@ mod tests {
         assert_eq!(retrieved.id, message.id);
         assert_eq!(retrieved.content, message.content);
         assert_eq!(retrieved.crdt_timestamp, message.crdt_timestamp);
+        assert!(retrieved.is_sent);
+    }
+
+    #[tokio::test]
+    async fn test_paging_back_is_stable_across_inserts() {
+        let (_dir, db) = LocalDatabase::open_temp().await;
+        let conversation_id = Uuid::new_v4();
+        let sender_id = Uuid::new_v4();
+
+        // Five messages, the middle three sharing a timestamp
+        let timestamps = [
+            "2024-03-01T10:00:00+00:00",
+            "2024-03-01T10:01:00+00:00",
+            "2024-03-01T10:01:00+00:00",
+            "2024-03-01T10:01:00+00:00",
+            "2024-03-01T10:02:00+00:00",
+        ];
+        let mut stored = Vec::new();
+        for (i, at) in timestamps.iter().enumerate() {
+            let mut message = ChatMessage::new_text(conversation_id, sender_id, format!("message {}", i), i as u64);
+            message.timestamp = timestamp::parse(at).unwrap();
+            db.store_message(&message).await.unwrap();
+            stored.push(message);
+        }
+        stored.sort_by_key(|m| (m.timestamp, m.id));
+        let ids = |messages: &[ChatMessage]| messages.iter().map(|m| m.id).collect::<Vec<_>>();
+
+        let newest = db.get_conversation_messages_before(&conversation_id, None, 2).await.unwrap();
+        assert_eq!(ids(&newest.messages), ids(&stored[3..]));
+        assert!(newest.has_more);
+
+        // A new message arriving doesn't shift the next page
+        let mut incoming = ChatMessage::new_text(conversation_id, sender_id, "new".to_string(), 99);
+        incoming.timestamp = timestamp::parse("2024-03-01T10:03:00+00:00").unwrap();
+        db.store_message(&incoming).await.unwrap();
+
+        let cursor = MessageCursor::of(&newest.messages[0]);
+        let middle = db.get_conversation_messages_before(&conversation_id, Some(&cursor), 2).await.unwrap();
+        assert_eq!(ids(&middle.messages), ids(&stored[1..3]));
+        assert!(middle.has_more);
+
+        let cursor = MessageCursor::of(&middle.messages[0]);
+        let oldest = db.get_conversation_messages_before(&conversation_id, Some(&cursor), 2).await.unwrap();
+        assert_eq!(ids(&oldest.messages), ids(&stored[..1]));
+        assert!(!oldest.has_more);
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_conversation_messages_follow_server_sequence() {
+        let (_dir, db) = LocalDatabase::open_temp().await;
+        let conversation_id = Uuid::new_v4();
+        let sender_id = Uuid::new_v4();
+
+        // CRDT order disagrees with the order the server accepted them in
+        let mut first = ChatMessage::new_text(conversation_id, sender_id, "first".to_string(), 9);
+        first.sequence = Some(1);
+        let mut second = ChatMessage::new_text(conversation_id, sender_id, "second".to_string(), 3);
+        second.sequence = Some(2);
+        let pending = ChatMessage::new_text(conversation_id, sender_id, "pending".to_string(), 1);
+        for message in [&pending, &second, &first] {
+            db.store_message(message).await.unwrap();
+        }
+
+        // Restoring the message without a sequence keeps the server's one
+        db.store_message(&ChatMessage { sequence: None, ..second.clone() }).await.unwrap();
+
+        let messages = db.get_conversation_messages(&conversation_id, None).await.unwrap();
+        let ids: Vec<_> = messages.iter().map(|m| m.id).collect();
+        assert_eq!(ids, vec![first.id, second.id, pending.id]);
+        assert_eq!(messages[1].sequence, Some(2));
+    }
+}
@@ -14,12 +14,24 @@
 //!
 //! ## Key Components
 //!
-//! - `LocalDatabase`: Main database connection and schema management
+//! - `LocalDatabase`: Main database connection and schema management; opens
+//!   `<data_dir>/xfmail/local.db`, `$XFMAIL_DB_PATH`, or an explicit path via
+//!   `LocalDatabase::new_at`
+//! - `LocalDatabase::integrity_check`: Startup integrity check with automatic
+//!   recovery from `<db>.bak` (or an empty database to re-import into)
+//! - `MIGRATIONS`: versioned schema changes, each applied in its own
+//!   transaction and recorded in `schema_migrations`
+//! - Migrations snapshot the database first and restore it if they fail; the
+//!   newest `MAX_MIGRATION_BACKUPS` snapshots are kept
 //! - `schema.rs`: Database schema definitions and migrations
 //! - `messages.rs`: Message storage and retrieval operations
 //! - `contacts.rs`: Contact management operations
 //! - `conversations.rs`: Conversation handling operations
 //! - `sync.rs`: Synchronization metadata and offline queue management
+//! - `search.rs`: Full-text search over message bodies (SQLite FTS5)
+//! - `export.rs`: JSON/CSV export of a conversation's history
+//! - `encryption.rs`: SQLCipher encryption at rest (`encrypted-local-db`
+//!   feature), keyed from the OS keystore or `XFMAIL_DB_KEY`
 //!
 //! ## Usage
 //!
@@ -42,13 +54,107 @@ pub mod messages;
 pub mod contacts;
 pub mod conversations;
 pub mod sync;
+pub mod search;
+pub mod export;
+#[cfg(feature = "encrypted-local-db")]
+pub mod encryption;
 
+use sqlx::sqlite::SqliteConnectOptions;
 use sqlx::{SqlitePool, Result as SqlxResult};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 /// Result type for local database operations
 pub type Result<T> = SqlxResult<T>;
 
+/// Environment variable that overrides the default database path
+pub const DB_PATH_ENV: &str = "XFMAIL_DB_PATH";
+
+/// Number of pre-migration backups kept next to the database
+pub const MAX_MIGRATION_BACKUPS: usize = 3;
+
+/// Marker in pre-migration backup file names (`local.db.pre-migration-<timestamp>-v<version>`)
+const MIGRATION_BACKUP_INFIX: &str = ".pre-migration-";
+
+/// A schema migration applied on top of `schema.sql`
+#[derive(Debug, Clone, Copy)]
+struct Migration {
+    version: u32,
+    /// SQL run to apply the migration; may hold several statements
+    up: &'static str,
+}
+
+/// Local schema migrations, in order
+///
+/// Migration 1 is the initial schema from `schema.sql`. To change the schema,
+/// append a migration with the next version; each one runs in its own
+/// transaction and is recorded in `schema_migrations`.
+const MIGRATIONS: &[Migration] = &[
+    Migration { version: 1, up: "" },
+    // Status and priority for the persisted offline `OperationQueue`
+    Migration {
+        version: 2,
+        up: "ALTER TABLE offline_queue ADD COLUMN status TEXT NOT NULL DEFAULT 'Pending';
+              ALTER TABLE offline_queue ADD COLUMN priority INTEGER NOT NULL DEFAULT 1;",
+    },
+    // Full-text index over message bodies, kept in sync by triggers
+    Migration {
+        version: 3,
+        up: "CREATE VIRTUAL TABLE IF NOT EXISTS messages_fts USING fts5(
+                  content, content='messages', content_rowid='rowid'
+              );
+              CREATE TRIGGER IF NOT EXISTS messages_fts_insert AFTER INSERT ON messages BEGIN
+                  INSERT INTO messages_fts(rowid, content) VALUES (new.rowid, new.content);
+              END;
+              CREATE TRIGGER IF NOT EXISTS messages_fts_delete AFTER DELETE ON messages BEGIN
+                  INSERT INTO messages_fts(messages_fts, rowid, content) VALUES ('delete', old.rowid, old.content);
+              END;
+              CREATE TRIGGER IF NOT EXISTS messages_fts_update AFTER UPDATE OF content ON messages BEGIN
+                  INSERT INTO messages_fts(messages_fts, rowid, content) VALUES ('delete', old.rowid, old.content);
+                  INSERT INTO messages_fts(rowid, content) VALUES (new.rowid, new.content);
+              END;
+              INSERT INTO messages_fts(messages_fts) VALUES ('rebuild');",
+    },
+    // Per-conversation theme color ("#rrggbb"; NULL uses the global theme)
+    Migration {
+        version: 4,
+        up: "ALTER TABLE conversation_settings ADD COLUMN theme_color TEXT;",
+    },
+    // Paging back through a conversation by (timestamp, id)
+    Migration {
+        version: 5,
+        up: "CREATE INDEX IF NOT EXISTS idx_messages_conversation_timestamp_id
+                  ON messages(conversation_id, timestamp, id);",
+    },
+    // Pinned conversations and their manual order
+    Migration {
+        version: 6,
+        up: "ALTER TABLE conversation_settings ADD COLUMN pinned INTEGER NOT NULL DEFAULT 0;
+             ALTER TABLE conversation_settings ADD COLUMN pin_order INTEGER NOT NULL DEFAULT 0;",
+    },
+    // Message edits and delete tombstones
+    Migration {
+        version: 7,
+        up: "ALTER TABLE messages ADD COLUMN edit_version TEXT;
+             ALTER TABLE messages ADD COLUMN is_deleted INTEGER NOT NULL DEFAULT 0;",
+    },
+    // Offline operations that used up their retries, kept for manual retry
+    Migration {
+        version: 8,
+        up: "CREATE TABLE IF NOT EXISTS dead_letter (
+                 id TEXT PRIMARY KEY,
+                 operation_type TEXT NOT NULL,
+                 data TEXT NOT NULL,
+                 last_error TEXT,
+                 attempts INTEGER NOT NULL,
+                 failed_at TEXT NOT NULL
+             );",
+    },
+    Migration {
+        version: 9,
+        up: "ALTER TABLE messages ADD COLUMN sequence INTEGER;",
+    },
+];
+
 /// Local database connection manager
 ///
 /// Manages the SQLite database connection pool and provides high-level operations
@@ -56,26 +162,148 @@ pub type Result<T> = SqlxResult<T>;
 #[derive(Debug)]
 pub struct LocalDatabase {
     pool: SqlitePool,
+    path: PathBuf,
+    recovery: RecoveryOutcome,
+    /// SQLCipher `PRAGMA key` value; `None` for a plaintext database
+    key: Option<String>,
+}
+
+/// Result of `PRAGMA integrity_check`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IntegrityStatus {
+    /// Database passed the check
+    Ok,
+    /// Problems reported by SQLite
+    Corrupt(Vec<String>),
+}
+
+/// What happened to the database file when it was opened
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecoveryOutcome {
+    /// Integrity check passed; no recovery needed
+    Healthy,
+    /// Corrupt file replaced with the backup at this path
+    RestoredFromBackup(PathBuf),
+    /// Corrupt file replaced with an empty database; local cache must be
+    /// re-imported from the server
+    Recreated,
 }
 
 impl LocalDatabase {
     /// Open or create local database
     ///
     /// Creates the database file if it doesn't exist and initializes the schema.
-    /// Uses WAL mode for better concurrency and performance.
+    /// Uses WAL mode for better concurrency and performance. A corrupt file is
+    /// recovered instead of failing; see [`LocalDatabase::recovery_outcome`].
+    ///
+    /// The file lives in the platform data directory unless
+    /// [`DB_PATH_ENV`] points elsewhere. With the `encrypted-local-db`
+    /// feature it is encrypted with the key from `DatabaseKey::load`.
     pub async fn new() -> Result<Self> {
-        let db_path = Self::get_db_path();
+        let key = Self::default_key()?;
+        Self::open(&Self::get_db_path(), key.as_deref()).await
+    }
 
+    /// Open or create the database at an explicit path
+    ///
+    /// Lets several instances run side by side, and tests use their own files.
+    pub async fn new_at(path: impl AsRef<Path>) -> Result<Self> {
+        let key = Self::default_key()?;
+        Self::open(path.as_ref(), key.as_deref()).await
+    }
+
+    /// Open or create an encrypted database at `path` with an explicit key
+    ///
+    /// A plaintext database already there is encrypted first.
+    #[cfg(feature = "encrypted-local-db")]
+    pub async fn new_encrypted_at(path: impl AsRef<Path>, key: &encryption::DatabaseKey) -> Result<Self> {
+        Self::open(path.as_ref(), Some(key.pragma())).await
+    }
+
+    /// Key for [`Self::new`] and [`Self::new_at`]; none without encryption
+    fn default_key() -> Result<Option<String>> {
+        #[cfg(feature = "encrypted-local-db")]
+        return encryption::DatabaseKey::load().map(|key| Some(key.pragma().to_string()));
+        #[cfg(not(feature = "encrypted-local-db"))]
+        Ok(None)
+    }
+
+    /// Open the database at `db_path`, recovering it if the integrity check fails
+    pub(crate) async fn open(db_path: &Path, key: Option<&str>) -> Result<Self> {
         // Ensure directory exists
-        if let Some(parent) = Path::new(&db_path).parent() {
-            std::fs::create_dir_all(parent)?;
+        if let Some(parent) = db_path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).map_err(|e| {
+                sqlx::Error::Io(std::io::Error::new(
+                    e.kind(),
+                    format!("Failed to create local database directory {}: {}", parent.display(), e),
+                ))
+            })?;
+        }
+
+        #[cfg(feature = "encrypted-local-db")]
+        if let Some(key) = key {
+            encryption::encrypt_plaintext(db_path, key).await?;
         }
 
-        // Create connection URL
-        let database_url = format!("sqlite:{}", db_path);
+        let db = match Self::connect(db_path, key).await {
+            Ok(db) => db,
+            // To SQLite a wrong key looks just like a corrupt file; keep the
+            // file rather than recovering over data the right key could open
+            Err(e) if key.is_some() && is_not_a_database(&e) => {
+                tracing::error!("Local database {} could not be decrypted: {}", db_path.display(), e);
+                return Err(e);
+            }
+            Err(e) if is_corruption_error(&e) => {
+                tracing::error!("Local database {} could not be opened: {}", db_path.display(), e);
+                return Self::recover(db_path, key).await;
+            }
+            Err(e) => return Err(e),
+        };
+
+        match db.integrity_check().await {
+            Ok(IntegrityStatus::Ok) => Ok(db),
+            Ok(IntegrityStatus::Corrupt(problems)) => {
+                tracing::error!("Local database integrity check failed: {:?}", problems);
+                db.pool.close().await;
+                Self::recover(db_path, key).await
+            }
+            Err(e) if is_corruption_error(&e) => {
+                tracing::error!("Local database integrity check errored: {}", e);
+                db.pool.close().await;
+                Self::recover(db_path, key).await
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Connect, configure and initialize the schema without checking integrity
+    async fn connect(db_path: &Path, key: Option<&str>) -> Result<Self> {
+        let mut db = Self {
+            pool: Self::connect_pool(db_path, key).await?,
+            path: db_path.to_path_buf(),
+            recovery: RecoveryOutcome::Healthy,
+            key: key.map(str::to_string),
+        };
+
+        // Initialize schema
+        db.init_schema().await?;
+
+        Ok(db)
+    }
+
+    /// Create the connection pool with WAL mode and other optimizations
+    ///
+    /// With a `key` every connection unlocks the database with it first.
+    async fn connect_pool(db_path: &Path, key: Option<&str>) -> Result<SqlitePool> {
+        let mut options = SqliteConnectOptions::new()
+            .filename(db_path)
+            .create_if_missing(true);
+        if let Some(key) = key {
+            options = options.pragma("key", key.to_string());
+        }
 
         // Create connection pool
-        let pool = SqlitePool::connect(&database_url).await?;
+        let pool = SqlitePool::connect_with(options).await?;
 
         // Enable WAL mode and other optimizations
         sqlx::query("PRAGMA journal_mode=WAL").execute(&pool).await?;
@@ -84,32 +312,176 @@ impl LocalDatabase {
         sqlx::query("PRAGMA foreign_keys=ON").execute(&pool).await?;
         sqlx::query("PRAGMA temp_store=MEMORY").execute(&pool).await?;
 
-        let db = Self { pool };
+        Ok(pool)
+    }
+
+    /// Replace a corrupt database with its backup, or with an empty one
+    ///
+    /// The corrupt file is kept next to the database as
+    /// `local.db.corrupt-<timestamp>` for diagnostics.
+    async fn recover(db_path: &Path, key: Option<&str>) -> Result<Self> {
+        if db_path.exists() {
+            let quarantine = with_suffix(db_path, &format!(".corrupt-{}", chrono::Utc::now().format("%Y%m%d%H%M%S")));
+            std::fs::rename(db_path, &quarantine)?;
+            tracing::warn!("Moved corrupt local database to {}", quarantine.display());
+        }
+        remove_wal_files(db_path);
 
-        // Initialize schema
-        db.init_schema().await?;
+        for backup in Self::backup_candidates(db_path) {
+            std::fs::copy(&backup, db_path)?;
+            match Self::connect(db_path, key).await {
+                Ok(mut db) => {
+                    if matches!(db.integrity_check().await, Ok(IntegrityStatus::Ok)) {
+                        tracing::info!("Restored local database from {}", backup.display());
+                        db.recovery = RecoveryOutcome::RestoredFromBackup(backup);
+                        return Ok(db);
+                    }
+                    tracing::warn!("Backup {} is also corrupt", backup.display());
+                    db.pool.close().await;
+                }
+                Err(e) => tracing::warn!("Backup {} is unusable: {}", backup.display(), e),
+            }
+            let _ = std::fs::remove_file(db_path);
+            remove_wal_files(db_path);
+        }
 
+        tracing::warn!("Recreated empty local database; cached data will be re-imported from the server");
+        let mut db = Self::connect(db_path, key).await?;
+        db.recovery = RecoveryOutcome::Recreated;
         Ok(db)
     }
 
+    /// Run `PRAGMA integrity_check`
+    ///
+    /// Cheap enough to run at startup and periodically while the app is idle.
+    pub async fn integrity_check(&self) -> Result<IntegrityStatus> {
+        let rows: Vec<(String,)> = sqlx::query_as("PRAGMA integrity_check")
+            .fetch_all(&self.pool)
+            .await?;
+
+        let problems: Vec<String> = rows.into_iter().map(|(row,)| row).collect();
+        if problems.len() == 1 && problems[0] == "ok" {
+            Ok(IntegrityStatus::Ok)
+        } else {
+            Ok(IntegrityStatus::Corrupt(problems))
+        }
+    }
+
+    /// How the database was recovered when it was opened
+    ///
+    /// `Recreated` means the caller should run the "clear local cache and
+    /// re-import" flow.
+    pub fn recovery_outcome(&self) -> &RecoveryOutcome {
+        &self.recovery
+    }
+
+    /// Path of the database file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Backup file used for recovery (`<db>.bak`)
+    pub fn backup_path(db_path: &Path) -> PathBuf {
+        with_suffix(db_path, ".bak")
+    }
+
+    /// Pre-migration backups of `db_path`, newest first
+    pub fn migration_backups(db_path: &Path) -> Vec<PathBuf> {
+        let Some(parent) = db_path.parent() else {
+            return Vec::new();
+        };
+        let prefix = format!(
+            "{}{}",
+            db_path.file_name().unwrap_or_default().to_string_lossy(),
+            MIGRATION_BACKUP_INFIX
+        );
+
+        let mut backups: Vec<PathBuf> = std::fs::read_dir(parent)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .filter(|entry| entry.file_name().to_string_lossy().starts_with(&prefix))
+                    .map(|entry| entry.path())
+                    .collect()
+            })
+            .unwrap_or_default();
+        // Names embed a fixed-width timestamp, so lexical order is chronological
+        backups.sort();
+        backups.reverse();
+        backups
+    }
+
+    /// Backups to try when recovering, most preferred first
+    fn backup_candidates(db_path: &Path) -> Vec<PathBuf> {
+        let explicit = Self::backup_path(db_path);
+        explicit
+            .exists()
+            .then_some(explicit)
+            .into_iter()
+            .chain(Self::migration_backups(db_path))
+            .collect()
+    }
+
+    /// Snapshot the database before applying migrations from `from_version`
+    ///
+    /// Uses `VACUUM INTO` so the copy is consistent even with a live WAL, then
+    /// rotates old snapshots so only the newest [`MAX_MIGRATION_BACKUPS`] remain.
+    async fn backup_before_migration(&self, from_version: u32) -> Result<PathBuf> {
+        let backup = with_suffix(
+            &self.path,
+            &format!(
+                "{}{}-v{}",
+                MIGRATION_BACKUP_INFIX,
+                chrono::Utc::now().format("%Y%m%dT%H%M%S%.3f"),
+                from_version
+            ),
+        );
+        sqlx::query("VACUUM INTO ?")
+            .bind(backup.to_string_lossy().to_string())
+            .execute(&self.pool)
+            .await?;
+
+        for stale in Self::migration_backups(&self.path).into_iter().skip(MAX_MIGRATION_BACKUPS) {
+            if let Err(e) = std::fs::remove_file(&stale) {
+                tracing::warn!("Failed to remove old backup {}: {}", stale.display(), e);
+            }
+        }
+
+        Ok(backup)
+    }
+
+    /// Put `backup` back in place of the database file and reconnect
+    async fn restore_backup(&mut self, backup: &Path) -> Result<()> {
+        self.pool.close().await;
+        remove_wal_files(&self.path);
+        std::fs::copy(backup, &self.path)?;
+        self.pool = Self::connect_pool(&self.path, self.key.as_deref()).await?;
+        Ok(())
+    }
+
     /// Get database file path
     ///
-    /// Returns the platform-specific path for the local database file.
-    /// Uses the system's data directory when available.
-    fn get_db_path() -> String {
+    /// Returns [`DB_PATH_ENV`] if set, otherwise the platform-specific path
+    /// for the local database file. Uses the system's data directory when
+    /// available.
+    fn get_db_path() -> PathBuf {
+        if let Some(path) = std::env::var_os(DB_PATH_ENV).filter(|p| !p.is_empty()) {
+            return PathBuf::from(path);
+        }
+
         // Use platform-specific data directory
         let mut path = dirs::data_dir()
             .unwrap_or_else(|| std::env::temp_dir());
 
         path.push("xfmail");
         path.push("local.db");
-        path.to_string_lossy().to_string()
+        path
     }
 
     /// Initialize database schema
     ///
     /// Creates all necessary tables and runs any pending migrations.
-    async fn init_schema(&self) -> Result<()> {
+    async fn init_schema(&mut self) -> Result<()> {
         // Create tables
         sqlx::query(include_str!("schema.sql"))
             .execute(&self.pool)
@@ -124,7 +496,12 @@ impl LocalDatabase {
     /// Run database migrations
     ///
     /// Checks the current schema version and applies any pending migrations.
-    async fn run_migrations(&self) -> Result<()> {
+    async fn run_migrations(&mut self) -> Result<()> {
+        self.run_migrations_from(MIGRATIONS).await
+    }
+
+    /// Apply pending `migrations`, restoring a pre-migration backup on failure
+    async fn run_migrations_from(&mut self, migrations: &[Migration]) -> Result<()> {
         // Create migrations table if it doesn't exist
         sqlx::query(
             "CREATE TABLE IF NOT EXISTS schema_migrations (
@@ -136,31 +513,59 @@ impl LocalDatabase {
         .await?;
 
         // Get current version
-        let current_version: (i32,) = sqlx::query_as(
+        let current_version: (u32,) = sqlx::query_as(
             "SELECT COALESCE(MAX(version), 0) FROM schema_migrations",
         )
         .fetch_one(&self.pool)
         .await
         .unwrap_or((0,));
 
-        // Apply migrations
-        if current_version.0 < 1 {
-            self.apply_migration_1().await?;
+        let pending: Vec<&Migration> = migrations
+            .iter()
+            .filter(|m| m.version > current_version.0)
+            .collect();
+        if pending.is_empty() {
+            return Ok(());
+        }
+
+        // A fresh database has nothing worth backing up
+        let backup = if current_version.0 > 0 {
+            Some(self.backup_before_migration(current_version.0).await?)
+        } else {
+            None
+        };
+
+        for migration in pending {
+            if let Err(e) = self.apply_migration(migration).await {
+                tracing::error!("Local database migration {} failed: {}", migration.version, e);
+                if let Some(backup) = &backup {
+                    self.restore_backup(backup).await?;
+                    tracing::warn!("Restored local database from {}", backup.display());
+                }
+                return Err(e);
+            }
         }
 
         Ok(())
     }
 
-    /// Migration 1: Initial schema
+    /// Apply one migration and record it in `schema_migrations`
     ///
-    /// Sets up the initial database schema and marks migration as applied.
-    async fn apply_migration_1(&self) -> Result<()> {
+    /// Runs in a transaction, so a migration that fails part way leaves
+    /// neither its changes nor its `schema_migrations` row behind.
+    async fn apply_migration(&self, migration: &Migration) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        if !migration.up.is_empty() {
+            sqlx::query(migration.up).execute(&mut *tx).await?;
+        }
         sqlx::query(
-            "INSERT INTO schema_migrations (version, applied_at) VALUES (1, ?)",
+            "INSERT INTO schema_migrations (version, applied_at) VALUES (?, ?)",
         )
+        .bind(migration.version)
         .bind(chrono::Utc::now().to_rfc3339())
-        .execute(&self.pool)
+        .execute(&mut *tx)
         .await?;
+        tx.commit().await?;
         Ok(())
     }
 
@@ -209,46 +614,105 @@ impl LocalDatabase {
 
     /// Clean up old data
     ///
-    /// Removes old messages and failed operations to manage storage space.
-    pub async fn cleanup(&self, days_old: i32) -> Result<CleanupStats> {
-        let cutoff_date = chrono::Utc::now() - chrono::Duration::days(days_old as i64);
+    /// Removes messages and failed operations as `policy` allows, to manage
+    /// storage space. Age limits apply first, then the per-conversation cap.
+    pub async fn cleanup(&self, policy: &CleanupPolicy) -> Result<CleanupStats> {
+        let mut tx = self.pool.begin().await?;
+        let mut stats = CleanupStats::default();
 
-        // Get count before deletion for old messages
-        let old_messages_before: (i64,) = sqlx::query_as(
-            "SELECT COUNT(*) FROM messages WHERE created_at < ? AND is_read = 1",
-        )
-        .bind(cutoff_date.to_rfc3339())
-        .fetch_one(&self.pool)
-        .await?;
+        if let Some(days) = policy.max_message_age_days {
+            let cutoff_date = chrono::Utc::now() - chrono::Duration::days(days as i64);
+            let result = sqlx::query(
+                "DELETE FROM messages WHERE created_at < ? AND (is_read = 1 OR ? = 0)",
+            )
+            .bind(cutoff_date.to_rfc3339())
+            .bind(policy.keep_unread)
+            .execute(&mut *tx)
+            .await?;
+            stats.old_messages_removed = result.rows_affected();
+        }
 
-        // Delete old messages
-        sqlx::query(
-            "DELETE FROM messages WHERE created_at < ? AND is_read = 1",
-        )
-        .bind(cutoff_date.to_rfc3339())
-        .execute(&self.pool)
-        .await?;
+        if let Some(max) = policy.max_messages_per_conversation {
+            // Unread messages past the cap still count towards it, so a
+            // conversation can go over when they are kept
+            let result = sqlx::query(
+                "DELETE FROM messages WHERE id IN (
+                    SELECT id FROM (
+                        SELECT id, is_read, ROW_NUMBER() OVER (
+                            PARTITION BY conversation_id ORDER BY timestamp DESC, id DESC
+                        ) AS newest_rank
+                        FROM messages
+                    )
+                    WHERE newest_rank > ? AND (is_read = 1 OR ? = 0)
+                )",
+            )
+            .bind(max as i64)
+            .bind(policy.keep_unread)
+            .execute(&mut *tx)
+            .await?;
+            stats.excess_messages_removed = result.rows_affected();
+        }
 
-        // Get count before deletion for failed operations
-        let failed_ops_before: (i64,) = sqlx::query_as(
-            "SELECT COUNT(*) FROM offline_queue WHERE created_at < ? AND retry_count > 5",
-        )
-        .bind(cutoff_date.to_rfc3339())
-        .fetch_one(&self.pool)
-        .await?;
+        if let Some(days) = policy.max_failed_op_age_days {
+            let cutoff_date = chrono::Utc::now() - chrono::Duration::days(days as i64);
+            let result = sqlx::query(
+                "DELETE FROM offline_queue WHERE created_at < ? AND retry_count > ?",
+            )
+            .bind(cutoff_date.to_rfc3339())
+            .bind(FAILED_OPERATION_RETRIES)
+            .execute(&mut *tx)
+            .await?;
+            stats.failed_operations_removed = result.rows_affected();
+        }
 
-        // Delete failed operations
-        sqlx::query(
-            "DELETE FROM offline_queue WHERE created_at < ? AND retry_count > 5",
-        )
-        .bind(cutoff_date.to_rfc3339())
-        .execute(&self.pool)
-        .await?;
+        tx.commit().await?;
+        Ok(stats)
+    }
+}
 
-        Ok(CleanupStats {
-            old_messages_removed: old_messages_before.0 as u64,
-            failed_operations_removed: failed_ops_before.0 as u64,
-        })
+#[cfg(test)]
+impl LocalDatabase {
+    /// Fresh plaintext database in a temporary directory, removed when the guard drops
+    pub(crate) async fn open_temp() -> (tempfile::TempDir, Self) {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Self::open(&dir.path().join("local.db"), None).await.unwrap();
+        (dir, db)
+    }
+}
+
+/// `path` with `suffix` appended to the file name
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Remove SQLite's `-wal` and `-shm` side files
+fn remove_wal_files(db_path: &Path) {
+    for suffix in ["-wal", "-shm"] {
+        let _ = std::fs::remove_file(with_suffix(db_path, suffix));
+    }
+}
+
+/// Whether an error is `SQLITE_NOTADB`, e.g. an encrypted file opened with the wrong key
+fn is_not_a_database(error: &sqlx::Error) -> bool {
+    match error {
+        sqlx::Error::Database(db_error) => {
+            db_error.code().as_deref() == Some("26") || db_error.message().contains("not a database")
+        }
+        _ => false,
+    }
+}
+
+/// Whether an error means the file is corrupt (`SQLITE_CORRUPT`, `SQLITE_NOTADB`)
+fn is_corruption_error(error: &sqlx::Error) -> bool {
+    match error {
+        sqlx::Error::Database(db_error) => {
+            matches!(db_error.code().as_deref(), Some("11") | Some("26"))
+                || db_error.message().contains("malformed")
+                || db_error.message().contains("not a database")
+        }
+        _ => false,
     }
 }
 
@@ -265,32 +729,318 @@ pub struct DatabaseStats {
     pub pending_operations: u64,
 }
 
+/// Retries after which a queued operation counts as failed for cleanup
+const FAILED_OPERATION_RETRIES: i64 = 5;
+
+/// What `LocalDatabase::cleanup` removes
+///
+/// `None` turns a limit off. The default removes read messages after 30
+/// days and failed operations after 7, with no per-conversation cap.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CleanupPolicy {
+    /// Remove messages stored longer ago than this
+    pub max_message_age_days: Option<u32>,
+    /// Never remove unread messages, whatever the other limits say
+    pub keep_unread: bool,
+    /// Keep only each conversation's newest messages, regardless of age
+    pub max_messages_per_conversation: Option<u32>,
+    /// Remove failed operations queued longer ago than this
+    pub max_failed_op_age_days: Option<u32>,
+}
+
+impl Default for CleanupPolicy {
+    fn default() -> Self {
+        Self {
+            max_message_age_days: Some(30),
+            keep_unread: true,
+            max_messages_per_conversation: None,
+            max_failed_op_age_days: Some(7),
+        }
+    }
+}
+
 /// Cleanup operation statistics
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct CleanupStats {
-    /// Number of old messages removed
+    /// Messages removed for being older than `max_message_age_days`
     pub old_messages_removed: u64,
-    /// Number of failed operations removed
+    /// Messages removed for being past `max_messages_per_conversation`
+    pub excess_messages_removed: u64,
+    /// Failed operations removed
     pub failed_operations_removed: u64,
 }
 
+impl CleanupStats {
+    /// Messages removed under any limit
+    pub fn messages_removed(&self) -> u64 {
+        self.old_messages_removed + self.excess_messages_removed
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::shared::messaging::ChatMessage;
+    use std::collections::HashSet;
+    use uuid::Uuid;
 
     #[tokio::test]
     async fn test_database_creation() {
-        let db = LocalDatabase::new().await;
-        assert!(db.is_ok());
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("local.db");
+        let db = LocalDatabase::new_at(&path).await.unwrap();
+        assert_eq!(db.path(), path);
+        assert!(path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_unwritable_directory_is_a_clear_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let blocker = dir.path().join("not-a-dir");
+        std::fs::write(&blocker, b"").unwrap();
+
+        let err = LocalDatabase::new_at(blocker.join("local.db")).await.unwrap_err();
+        assert!(err.to_string().contains("Failed to create local database directory"));
     }
 
     #[tokio::test]
     async fn test_database_stats() {
-        let db = LocalDatabase::new().await.unwrap();
+        let (_dir, db) = LocalDatabase::open_temp().await;
         let stats = db.get_stats().await.unwrap();
         assert_eq!(stats.message_count, 0); // Should be empty initially
         assert_eq!(stats.contact_count, 0);
         assert_eq!(stats.conversation_count, 0);
         assert_eq!(stats.pending_operations, 0);
     }
+
+    /// Store a message in `conversation_id` sent and stored `days_ago`
+    async fn seed_message(db: &LocalDatabase, conversation_id: Uuid, days_ago: i64, is_read: bool) -> Uuid {
+        let at = chrono::Utc::now() - chrono::Duration::days(days_ago);
+        let mut message = ChatMessage::new_text(conversation_id, Uuid::new_v4(), format!("{} days ago", days_ago), 0);
+        message.timestamp = at;
+        message.is_read = is_read;
+        db.store_message(&message).await.unwrap();
+        sqlx::query("UPDATE messages SET created_at = ? WHERE id = ?")
+            .bind(at.to_rfc3339())
+            .bind(message.id.to_string())
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        message.id
+    }
+
+    async fn message_ids(db: &LocalDatabase) -> HashSet<Uuid> {
+        let rows: Vec<(String,)> = sqlx::query_as("SELECT id FROM messages").fetch_all(&db.pool).await.unwrap();
+        rows.iter().map(|(id,)| Uuid::parse_str(id).unwrap()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_cleanup_by_age_keeps_unread() {
+        let (_dir, db) = LocalDatabase::open_temp().await;
+        let conversation_id = Uuid::new_v4();
+        seed_message(&db, conversation_id, 40, true).await;
+        let old_unread = seed_message(&db, conversation_id, 40, false).await;
+        let recent_read = seed_message(&db, conversation_id, 1, true).await;
+
+        let stats = db.cleanup(&CleanupPolicy::default()).await.unwrap();
+        assert_eq!(stats.old_messages_removed, 1);
+        assert_eq!(stats.excess_messages_removed, 0);
+        assert_eq!(message_ids(&db).await, HashSet::from([old_unread, recent_read]));
+
+        // Without keep_unread, age alone decides
+        let policy = CleanupPolicy { keep_unread: false, ..CleanupPolicy::default() };
+        let stats = db.cleanup(&policy).await.unwrap();
+        assert_eq!(stats.messages_removed(), 1);
+        assert_eq!(message_ids(&db).await, HashSet::from([recent_read]));
+    }
+
+    #[tokio::test]
+    async fn test_cleanup_caps_each_conversation() {
+        let (_dir, db) = LocalDatabase::open_temp().await;
+        let (busy, quiet) = (Uuid::new_v4(), Uuid::new_v4());
+        // Busy: oldest first, the second oldest unread
+        let mut busy_ids = Vec::new();
+        for (days_ago, is_read) in [(5, true), (4, false), (3, true), (2, true), (1, true)] {
+            busy_ids.push(seed_message(&db, busy, days_ago, is_read).await);
+        }
+        let quiet_id = seed_message(&db, quiet, 10, true).await;
+
+        let policy = CleanupPolicy {
+            max_message_age_days: None,
+            keep_unread: true,
+            max_messages_per_conversation: Some(2),
+            max_failed_op_age_days: None,
+        };
+        let stats = db.cleanup(&policy).await.unwrap();
+
+        // The two newest survive, plus the unread one past the cap; the
+        // quiet conversation is under its cap however old it is
+        assert_eq!(stats.excess_messages_removed, 2);
+        assert_eq!(stats.old_messages_removed, 0);
+        assert_eq!(
+            message_ids(&db).await,
+            HashSet::from([busy_ids[1], busy_ids[3], busy_ids[4], quiet_id])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cleanup_removes_old_failed_operations() {
+        let (_dir, db) = LocalDatabase::open_temp().await;
+        let old = (chrono::Utc::now() - chrono::Duration::days(10)).to_rfc3339();
+        for (id, created_at, retry_count) in [("failed", &old, 6), ("retrying", &old, 2)] {
+            sqlx::query(
+                "INSERT INTO offline_queue (id, operation_type, data, created_at, retry_count) VALUES (?, 'message_send', '{}', ?, ?)",
+            )
+            .bind(id)
+            .bind(created_at)
+            .bind(retry_count)
+            .execute(&db.pool)
+            .await
+            .unwrap();
+        }
+
+        let stats = db.cleanup(&CleanupPolicy::default()).await.unwrap();
+        assert_eq!(stats.failed_operations_removed, 1);
+        let rows: Vec<(String,)> = sqlx::query_as("SELECT id FROM offline_queue").fetch_all(&db.pool).await.unwrap();
+        assert_eq!(rows, vec![("retrying".to_string(),)]);
+    }
+
+    /// Overwrite a database file with garbage, as a torn write might
+    fn corrupt(path: &Path) {
+        std::fs::write(path, vec![0xAB; 8192]).unwrap();
+        remove_wal_files(path);
+    }
+
+    #[tokio::test]
+    async fn test_integrity_check_passes_for_healthy_db() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = LocalDatabase::open(&dir.path().join("local.db"), None).await.unwrap();
+
+        assert_eq!(db.integrity_check().await.unwrap(), IntegrityStatus::Ok);
+        assert_eq!(db.recovery_outcome(), &RecoveryOutcome::Healthy);
+    }
+
+    #[tokio::test]
+    async fn test_corruption_recreates_db() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("local.db");
+        LocalDatabase::open(&path, None).await.unwrap().pool.close().await;
+
+        corrupt(&path);
+
+        let db = LocalDatabase::open(&path, None).await.unwrap();
+        assert_eq!(db.recovery_outcome(), &RecoveryOutcome::Recreated);
+        assert_eq!(db.integrity_check().await.unwrap(), IntegrityStatus::Ok);
+        assert_eq!(db.get_stats().await.unwrap().message_count, 0);
+
+        // The corrupt file is kept for diagnostics
+        let quarantined = std::fs::read_dir(dir.path())
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .any(|entry| entry.file_name().to_string_lossy().contains(".corrupt-"));
+        assert!(quarantined);
+    }
+
+    #[tokio::test]
+    async fn test_corruption_restores_from_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("local.db");
+        let db = LocalDatabase::open(&path, None).await.unwrap();
+        sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)").execute(db.pool()).await.unwrap();
+        db.pool.close().await;
+        std::fs::copy(&path, LocalDatabase::backup_path(&path)).unwrap();
+
+        corrupt(&path);
+
+        let db = LocalDatabase::open(&path, None).await.unwrap();
+        assert_eq!(
+            db.recovery_outcome(),
+            &RecoveryOutcome::RestoredFromBackup(LocalDatabase::backup_path(&path))
+        );
+        assert_eq!(db.integrity_check().await.unwrap(), IntegrityStatus::Ok);
+    }
+
+    #[tokio::test]
+    async fn test_failed_migration_restores_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("local.db");
+        let mut db = LocalDatabase::open(&path, None).await.unwrap();
+        sqlx::query("CREATE TABLE keep_me (value TEXT)").execute(db.pool()).await.unwrap();
+        sqlx::query("INSERT INTO keep_me VALUES ('before')").execute(db.pool()).await.unwrap();
+
+        // Partially applies (the CREATE succeeds) before failing
+        let latest = MIGRATIONS.last().unwrap().version;
+        let failing = [Migration {
+            version: latest + 1,
+            up: "CREATE TABLE half_done (x INTEGER); INSERT INTO no_such_table VALUES (1);",
+        }];
+        assert!(db.run_migrations_from(&failing).await.is_err());
+
+        let half_done: (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM sqlite_master WHERE name = 'half_done'",
+        )
+        .fetch_one(db.pool())
+        .await
+        .unwrap();
+        assert_eq!(half_done.0, 0);
+
+        let kept: (String,) = sqlx::query_as("SELECT value FROM keep_me")
+            .fetch_one(db.pool())
+            .await
+            .unwrap();
+        assert_eq!(kept.0, "before");
+
+        let version: (u32,) = sqlx::query_as("SELECT MAX(version) FROM schema_migrations")
+            .fetch_one(db.pool())
+            .await
+            .unwrap();
+        assert_eq!(version.0, latest);
+        assert_eq!(LocalDatabase::migration_backups(&path).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_failed_migration_rolls_back_its_transaction() {
+        let (_dir, db) = LocalDatabase::open_temp().await;
+        let latest = MIGRATIONS.last().unwrap().version;
+
+        let failing = Migration {
+            version: latest + 1,
+            up: "ALTER TABLE messages ADD COLUMN half_done TEXT; INSERT INTO no_such_table VALUES (1);",
+        };
+        assert!(db.apply_migration(&failing).await.is_err());
+
+        let columns: Vec<(String,)> = sqlx::query_as("SELECT name FROM pragma_table_info('messages')")
+            .fetch_all(db.pool())
+            .await
+            .unwrap();
+        assert!(!columns.iter().any(|(name,)| name == "half_done"));
+
+        let version: (u32,) = sqlx::query_as("SELECT MAX(version) FROM schema_migrations")
+            .fetch_one(db.pool())
+            .await
+            .unwrap();
+        assert_eq!(version.0, latest);
+
+        // Every known migration has been applied in order
+        let applied: Vec<(u32,)> = sqlx::query_as("SELECT version FROM schema_migrations ORDER BY version")
+            .fetch_all(db.pool())
+            .await
+            .unwrap();
+        let expected: Vec<u32> = MIGRATIONS.iter().map(|m| m.version).collect();
+        assert_eq!(applied.into_iter().map(|(v,)| v).collect::<Vec<_>>(), expected);
+    }
+
+    #[tokio::test]
+    async fn test_migration_backups_are_rotated() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("local.db");
+        let db = LocalDatabase::open(&path, None).await.unwrap();
+
+        for _ in 0..MAX_MIGRATION_BACKUPS + 2 {
+            db.backup_before_migration(1).await.unwrap();
+            tokio::time::sleep(std::time::Duration::from_millis(2)).await;
+        }
+
+        assert_eq!(LocalDatabase::migration_backups(&path).len(), MAX_MIGRATION_BACKUPS);
+    }
 }
\ No newline at end of file
@@ -63,6 +63,16 @@ CREATE TABLE IF NOT EXISTS conversation_participants (
     FOREIGN KEY (user_id) REFERENCES users(id)
 );
 
+-- Per-conversation settings for the local user
+CREATE TABLE IF NOT EXISTS conversation_settings (
+    conversation_id TEXT PRIMARY KEY,
+    manually_unread BOOLEAN NOT NULL DEFAULT 0,
+    updated_at TEXT NOT NULL,
+    -- Sync metadata
+    needs_sync BOOLEAN DEFAULT 0,
+    FOREIGN KEY (conversation_id) REFERENCES conversations(id) ON DELETE CASCADE
+);
+
 -- Messages table
 CREATE TABLE IF NOT EXISTS messages (
     id TEXT PRIMARY KEY,
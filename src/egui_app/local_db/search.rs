@@ -0,0 +1,229 @@
+//! # Local Message Search
+//!
+//! Full-text search across every locally stored message, backed by the
+//! `messages_fts` FTS5 table. Triggers on `messages` keep the index in sync
+//! on insert, update and delete (see migration 3 in `mod.rs`).
+//!
+//! ## Usage
+//!
+//! ```rust,no_run
+//! use xfmail::egui_app::local_db::LocalDatabase;
+//!
+//! let db = LocalDatabase::new().await.unwrap();
+//! for hit in db.search_messages("lunch friday", 20).await.unwrap() {
+//!     println!("{}: {}", hit.conversation_id, hit.plain_snippet());
+//! }
+//! ```
+
+use crate::egui_app::local_db::LocalDatabase;
+use sqlx::{Result as SqlxResult, Row};
+use uuid::Uuid;
+
+/// Result type alias for search operations
+pub type Result<T> = SqlxResult<T>;
+
+/// Marks the start of a matched term in [`MessageSearchHit::snippet`]
+pub const MATCH_START: char = '\u{2}';
+
+/// Marks the end of a matched term in [`MessageSearchHit::snippet`]
+pub const MATCH_END: char = '\u{3}';
+
+/// Words of context shown around the match
+const SNIPPET_TOKENS: i32 = 12;
+
+/// A message matching a search
+#[derive(Debug, Clone, PartialEq)]
+pub struct MessageSearchHit {
+    pub conversation_id: Uuid,
+    pub message_id: Uuid,
+    /// Excerpt around the match, with matched terms between
+    /// [`MATCH_START`] and [`MATCH_END`]
+    pub snippet: String,
+    /// BM25 relevance; lower is a better match
+    pub rank: f64,
+}
+
+impl MessageSearchHit {
+    /// Snippet split into `(text, is_match)` runs for rendering
+    pub fn snippet_parts(&self) -> Vec<(&str, bool)> {
+        let mut parts = Vec::new();
+        let mut rest = self.snippet.as_str();
+        while let Some(start) = rest.find(MATCH_START) {
+            if start > 0 {
+                parts.push((&rest[..start], false));
+            }
+            let matched = &rest[start + MATCH_START.len_utf8()..];
+            let end = matched.find(MATCH_END).unwrap_or(matched.len());
+            parts.push((&matched[..end], true));
+            rest = matched.get(end + MATCH_END.len_utf8()..).unwrap_or("");
+        }
+        if !rest.is_empty() {
+            parts.push((rest, false));
+        }
+        parts
+    }
+
+    /// Snippet without match markers
+    pub fn plain_snippet(&self) -> String {
+        self.snippet.replace([MATCH_START, MATCH_END], "")
+    }
+}
+
+/// Turn what the user typed into an FTS5 query
+///
+/// Each word becomes a quoted prefix term, so punctuation can't be parsed as
+/// FTS5 syntax and partially typed words still match. All words must match.
+fn fts_query(input: &str) -> String {
+    input
+        .split_whitespace()
+        .map(|word| format!("\"{}\"*", word.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+impl LocalDatabase {
+    /// Search message bodies across all conversations
+    ///
+    /// Returns at most `limit` hits, best match first. An empty query
+    /// returns nothing.
+    pub async fn search_messages(&self, query: &str, limit: usize) -> Result<Vec<MessageSearchHit>> {
+        let fts_query = fts_query(query);
+        if fts_query.is_empty() || limit == 0 {
+            return Ok(Vec::new());
+        }
+
+        let rows = sqlx::query(
+            "SELECT m.id, m.conversation_id,
+                    snippet(messages_fts, 0, ?, ?, '…', ?) AS snippet,
+                    bm25(messages_fts) AS score
+             FROM messages_fts
+             JOIN messages m ON m.rowid = messages_fts.rowid
+             WHERE messages_fts MATCH ?
+             ORDER BY score
+             LIMIT ?",
+        )
+        .bind(MATCH_START.to_string())
+        .bind(MATCH_END.to_string())
+        .bind(SNIPPET_TOKENS)
+        .bind(&fts_query)
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(MessageSearchHit {
+                    conversation_id: Uuid::parse_str(&row.try_get::<String, _>("conversation_id")?).unwrap_or_default(),
+                    message_id: Uuid::parse_str(&row.try_get::<String, _>("id")?).unwrap_or_default(),
+                    snippet: row.try_get("snippet")?,
+                    rank: row.try_get("score")?,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::messaging::ChatMessage;
+
+    /// Insert the user and conversation a message needs for its foreign keys
+    async fn conversation(db: &LocalDatabase, user_id: Uuid) -> Uuid {
+        let now = chrono::Utc::now().to_rfc3339();
+        sqlx::query("INSERT OR IGNORE INTO users (id, username, email, created_at, updated_at) VALUES (?, ?, ?, ?, ?)")
+            .bind(user_id.to_string())
+            .bind(format!("user-{}", user_id))
+            .bind(format!("{}@example.com", user_id))
+            .bind(&now)
+            .bind(&now)
+            .execute(db.pool())
+            .await
+            .unwrap();
+
+        let conversation_id = Uuid::new_v4();
+        sqlx::query("INSERT INTO conversations (id, created_by, created_at, updated_at) VALUES (?, ?, ?, ?)")
+            .bind(conversation_id.to_string())
+            .bind(user_id.to_string())
+            .bind(&now)
+            .bind(&now)
+            .execute(db.pool())
+            .await
+            .unwrap();
+        conversation_id
+    }
+
+    #[test]
+    fn test_fts_query_escapes_syntax() {
+        assert_eq!(fts_query("lunch fri"), "\"lunch\"* \"fri\"*");
+        assert_eq!(fts_query("say \"hi\" -now"), "\"say\"* \"\"\"hi\"\"\"* \"-now\"*");
+        assert_eq!(fts_query("   "), "");
+    }
+
+    #[test]
+    fn test_snippet_parts() {
+        let hit = MessageSearchHit {
+            conversation_id: Uuid::nil(),
+            message_id: Uuid::nil(),
+            snippet: format!("…see you at {}lunch{} on {}Friday{}", MATCH_START, MATCH_END, MATCH_START, MATCH_END),
+            rank: -1.0,
+        };
+        assert_eq!(
+            hit.snippet_parts(),
+            [("…see you at ", false), ("lunch", true), (" on ", false), ("Friday", true)]
+        );
+        assert_eq!(hit.plain_snippet(), "…see you at lunch on Friday");
+    }
+
+    #[tokio::test]
+    async fn test_search_across_conversations() {
+        let (_dir, db) = LocalDatabase::open_temp().await;
+        let user_id = Uuid::new_v4();
+        let first = conversation(&db, user_id).await;
+        let second = conversation(&db, user_id).await;
+
+        let lunch = ChatMessage::new_text(first, user_id, "Lunch on Friday?".to_string(), 1);
+        let reply = ChatMessage::new_text(second, user_id, "Friday lunch works, see you at noon".to_string(), 2);
+        let other = ChatMessage::new_text(second, user_id, "Unrelated message".to_string(), 3);
+        for message in [&lunch, &reply, &other] {
+            db.store_message(message).await.unwrap();
+        }
+
+        let hits = db.search_messages("lunch fri", 10).await.unwrap();
+        let mut found: Vec<(Uuid, Uuid)> = hits.iter().map(|h| (h.conversation_id, h.message_id)).collect();
+        found.sort();
+        let mut expected = vec![(first, lunch.id), (second, reply.id)];
+        expected.sort();
+        assert_eq!(found, expected);
+        assert!(hits.iter().all(|h| h.snippet_parts().iter().any(|(text, is_match)| *is_match && text.eq_ignore_ascii_case("lunch"))));
+        assert!(hits[0].rank <= hits[1].rank);
+
+        assert_eq!(db.search_messages("lunch", 1).await.unwrap().len(), 1);
+        assert!(db.search_messages("", 10).await.unwrap().is_empty());
+        assert!(db.search_messages("\"unbalanced", 10).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_index_follows_updates_and_deletes() {
+        let (_dir, db) = LocalDatabase::open_temp().await;
+        let user_id = Uuid::new_v4();
+        let conversation_id = conversation(&db, user_id).await;
+
+        let mut message = ChatMessage::new_text(conversation_id, user_id, "quarterly report draft".to_string(), 1);
+        db.store_message(&message).await.unwrap();
+        assert_eq!(db.search_messages("report", 10).await.unwrap().len(), 1);
+
+        // Storing the same message again (e.g. after an edit) reindexes it
+        message.content = "final budget".to_string();
+        db.store_message(&message).await.unwrap();
+        assert!(db.search_messages("report", 10).await.unwrap().is_empty());
+        assert_eq!(db.search_messages("budget", 10).await.unwrap().len(), 1);
+
+        sqlx::query("DELETE FROM messages WHERE id = ?")
+            .bind(message.id.to_string())
+            .execute(db.pool())
+            .await
+            .unwrap();
+        assert!(db.search_messages("budget", 10).await.unwrap().is_empty());
+    }
+}
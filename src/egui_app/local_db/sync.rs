@@ -222,7 +222,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_offline_queue_operations() {
-        let db = LocalDatabase::new().await.unwrap();
+        let (_dir, db) = LocalDatabase::open_temp().await;
 
         let operation = SyncOperation::SendMessage {
             conversation_id: Uuid::new_v4(),
@@ -247,7 +247,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_sync_metadata() {
-        let db = LocalDatabase::new().await.unwrap();
+        let (_dir, db) = LocalDatabase::open_temp().await;
 
         // Set metadata
         db.set_sync_metadata("test_key", "test_value").await.unwrap();
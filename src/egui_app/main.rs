@@ -5,7 +5,28 @@
  * It implements eframe::App and provides the UI for authentication and demo selection.
  */
 use eframe::egui;
-use xfmail::egui_app::{AppState, views};
+use xfmail::egui_app::{deep_link, AppState, views};
+use xfmail::egui_app::badge::{self, UnreadBadge};
+use xfmail::egui_app::state::OnboardingState;
+use xfmail::egui_app::window_state::WindowState;
+
+/// Native window title
+const WINDOW_TITLE: &str = "XFChat - Messaging";
+
+/// Whether the user interacted with the window this frame
+fn user_interacted(input: &egui::InputState) -> bool {
+    input.pointer.delta() != egui::Vec2::ZERO
+        || input.events.iter().any(|event| {
+            matches!(
+                event,
+                egui::Event::Key { .. }
+                    | egui::Event::Text(_)
+                    | egui::Event::PointerButton { .. }
+                    | egui::Event::MouseWheel { .. }
+                    | egui::Event::Touch { .. }
+            )
+        })
+}
 
 /// Configure custom font (Roboto Condensed Black)
 fn setup_custom_fonts(ctx: &egui::Context) {
@@ -37,18 +58,35 @@ fn setup_custom_fonts(ctx: &egui::Context) {
 }
 
 fn main() -> Result<(), eframe::Error> {
+    #[cfg(feature = "deep-links")]
+    if let Err(e) = deep_link::register_url_handler() {
+        eprintln!("Failed to register xfmail:// handler: {}", e);
+    }
+
+    // The OS passes the clicked link as an argument
+    let initial_link = deep_link::from_args(std::env::args());
+
+    let window_state = WindowState::load();
+    let onboarding = OnboardingState::load();
+
     let options = eframe::NativeOptions {
-        viewport: egui::ViewportBuilder::default()
-            .with_inner_size([1200.0, 800.0])
-            .with_min_inner_size([800.0, 600.0]),
+        viewport: window_state.viewport_builder(),
         ..Default::default()
     };
     eframe::run_native(
-        "XFChat - Messaging",
+        WINDOW_TITLE,
         options,
         Box::new(|cc| {
             setup_custom_fonts(&cc.egui_ctx);
-            Ok(Box::new(BraidApp::default()))
+            egui_extras::install_image_loaders(&cc.egui_ctx);
+            let mut app = BraidApp::default();
+            app.state.restore_onboarding(&onboarding);
+            app.state.messaging_state.restore_conversation_id = window_state.last_conversation_id;
+            app.window_state = window_state;
+            if let Some(link) = initial_link {
+                app.state.open_deep_link(link);
+            }
+            Ok(Box::new(app))
         }),
     )
 }
@@ -56,12 +94,18 @@ fn main() -> Result<(), eframe::Error> {
 /// Main application state
 struct BraidApp {
     state: AppState,
+    /// Geometry and last conversation, saved on exit
+    window_state: WindowState,
+    /// Taskbar/dock unread badge
+    unread_badge: UnreadBadge,
 }
 
 impl Default for BraidApp {
     fn default() -> Self {
         Self {
             state: AppState::new(),
+            window_state: WindowState::default(),
+            unread_badge: UnreadBadge::default(),
         }
     }
 }
@@ -69,11 +113,53 @@ impl Default for BraidApp {
 impl eframe::App for BraidApp {
     fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
         self.state.check_auth_result();
+        self.state.check_sync();
+        self.state.check_onboarding();
+        self.state.check_diagnostics_result();
 
-        views::render_top_bar(ctx, &mut self.state, frame);
+        let active = ctx.input(user_interacted);
+        self.state.check_inactivity(active, std::time::Instant::now());
 
-        views::render_main_panel(ctx, &mut self.state);
+        // Coming back from the background reconnects without waiting out backoffs
+        let focused = ctx.input(|i| i.viewport().focused.unwrap_or(true));
+        self.state.messaging_state.update_focus(focused);
+
+        let minimized = ctx.input(|i| i.viewport().minimized == Some(true));
+        let lock = &self.state.app_lock;
+        if minimized && lock.auto_lock_on_minimize() && lock.has_pin() && !lock.is_locked() {
+            self.state.lock_app();
+        }
+
+        if self.state.app_lock.is_locked() {
+            views::lock_view::render(ctx, &mut self.state);
+        } else {
+            views::render_top_bar(ctx, &mut self.state, frame);
+            views::render_main_panel(ctx, &mut self.state);
+            views::debug_view::render_queue_panel(ctx, &mut self.state);
+            views::render_onboarding_tips(ctx, &mut self.state);
+        }
+
+        let unread = self.state.messaging_state.total_unread();
+        if self.unread_badge.update(unread) {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Title(badge::window_title(WINDOW_TITLE, unread)));
+        }
+
+        ctx.input(|i| self.window_state.capture(i.viewport()));
+        if let Some(id) = self.state.messaging_state.selected_conversation_id {
+            self.window_state.last_conversation_id = Some(id);
+        }
 
         ctx.request_repaint();
     }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        // Don't leave a stale count on the dock
+        self.unread_badge.update(0);
+        if let Err(e) = self.window_state.save() {
+            eprintln!("Failed to save window state: {}", e);
+        }
+        if let Err(e) = self.state.onboarding_state().save() {
+            eprintln!("Failed to save onboarding state: {}", e);
+        }
+    }
 }
@@ -0,0 +1,221 @@
+//! Media Loader
+//!
+//! Downloads avatars, images, thumbnails and link-preview images in the
+//! background and caches the bytes by URL. In low data mode nothing is
+//! fetched and views render placeholders instead.
+//!
+//! Server-relative URLs (`/api/users/{id}/avatar`) are fetched from the
+//! configured server with the user's token.
+
+use std::collections::HashMap;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Arc;
+
+use crate::egui_app::config::Config;
+
+/// What a piece of media is used for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MediaKind {
+    Avatar,
+    Image,
+    Thumbnail,
+    LinkPreviewImage,
+}
+
+/// Load state of a media URL
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MediaState {
+    /// Not fetched (low data mode); render a placeholder
+    Placeholder,
+    /// Download in progress
+    Loading,
+    /// Downloaded bytes
+    Ready(Arc<[u8]>),
+    /// Download failed
+    Failed(String),
+}
+
+/// Fetches media bytes (HTTP in the app, stubbed in tests)
+pub trait MediaFetcher: Send + Sync {
+    /// Download `url`, sending `token` as a bearer token if given
+    fn fetch(&self, url: &str, token: Option<&str>) -> Result<Vec<u8>, String>;
+}
+
+/// Blocking HTTP fetcher, run on a background thread
+pub struct HttpMediaFetcher {
+    client: reqwest::blocking::Client,
+}
+
+impl HttpMediaFetcher {
+    pub fn new() -> Self {
+        Self { client: reqwest::blocking::Client::new() }
+    }
+}
+
+impl Default for HttpMediaFetcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MediaFetcher for HttpMediaFetcher {
+    fn fetch(&self, url: &str, token: Option<&str>) -> Result<Vec<u8>, String> {
+        let mut request = self.client.get(url);
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+        let response = request.send().map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("HTTP {}", response.status()));
+        }
+        response.bytes().map(|b| b.to_vec()).map_err(|e| e.to_string())
+    }
+}
+
+/// Background media loader with a per-URL cache
+pub struct MediaLoader {
+    fetcher: Arc<dyn MediaFetcher>,
+    cache: HashMap<String, MediaState>,
+    result_tx: Sender<(String, MediaState)>,
+    result_rx: Receiver<(String, MediaState)>,
+}
+
+impl MediaLoader {
+    pub fn new(fetcher: Arc<dyn MediaFetcher>) -> Self {
+        let (result_tx, result_rx) = channel();
+        Self { fetcher, cache: HashMap::new(), result_tx, result_rx }
+    }
+
+    /// Current state of `url`, starting a download if needed
+    ///
+    /// In low data mode this never fetches and returns [`MediaState::Placeholder`].
+    pub fn request(&mut self, url: &str, kind: MediaKind, config: &Config) -> MediaState {
+        self.poll();
+
+        if let Some(state) = self.cache.get(url) {
+            return state.clone();
+        }
+        if !config.media_fetch_enabled() {
+            tracing::trace!("Low data mode: skipping {:?} fetch for {}", kind, url);
+            return MediaState::Placeholder;
+        }
+
+        self.cache.insert(url.to_string(), MediaState::Loading);
+        let fetcher = self.fetcher.clone();
+        let tx = self.result_tx.clone();
+        let key = url.to_string();
+        // Our own endpoints need the full URL and credentials
+        let (url, token) = if url.starts_with('/') {
+            (config.api_url(url), config.get_token().cloned())
+        } else {
+            (key.clone(), None)
+        };
+        std::thread::spawn(move || {
+            let state = match fetcher.fetch(&url, token.as_deref()) {
+                Ok(bytes) => MediaState::Ready(bytes.into()),
+                Err(e) => MediaState::Failed(e),
+            };
+            let _ = tx.send((key, state));
+        });
+        MediaState::Loading
+    }
+
+    /// Apply finished downloads to the cache
+    pub fn poll(&mut self) {
+        while let Ok((url, state)) = self.result_rx.try_recv() {
+            self.cache.insert(url, state);
+        }
+    }
+}
+
+impl Default for MediaLoader {
+    fn default() -> Self {
+        Self::new(Arc::new(HttpMediaFetcher::new()))
+    }
+}
+
+impl std::fmt::Debug for MediaLoader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MediaLoader").field("cached", &self.cache.len()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::{Duration, Instant};
+
+    #[derive(Default)]
+    struct CountingFetcher {
+        calls: AtomicUsize,
+        last_request: std::sync::Mutex<Option<(String, Option<String>)>>,
+    }
+
+    impl MediaFetcher for CountingFetcher {
+        fn fetch(&self, url: &str, token: Option<&str>) -> Result<Vec<u8>, String> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            *self.last_request.lock().unwrap() = Some((url.to_string(), token.map(str::to_string)));
+            Ok(vec![1, 2, 3])
+        }
+    }
+
+    #[test]
+    fn test_low_data_mode_suppresses_fetches() {
+        let fetcher = Arc::new(CountingFetcher::default());
+        let mut loader = MediaLoader::new(fetcher.clone());
+        let mut config = Config::new();
+        config.set_low_data_mode(true);
+
+        for kind in [MediaKind::Avatar, MediaKind::Image, MediaKind::Thumbnail, MediaKind::LinkPreviewImage] {
+            assert_eq!(loader.request("https://example.com/a.png", kind, &config), MediaState::Placeholder);
+        }
+
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(fetcher.calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_fetches_and_caches_when_enabled() {
+        let fetcher = Arc::new(CountingFetcher::default());
+        let mut loader = MediaLoader::new(fetcher.clone());
+        let mut config = Config::new();
+        config.set_low_data_mode(false);
+
+        let url = "https://example.com/avatar.png";
+        assert_eq!(loader.request(url, MediaKind::Avatar, &config), MediaState::Loading);
+
+        let deadline = Instant::now() + Duration::from_secs(2);
+        let state = loop {
+            match loader.request(url, MediaKind::Avatar, &config) {
+                MediaState::Loading if Instant::now() < deadline => std::thread::sleep(Duration::from_millis(5)),
+                state => break state,
+            }
+        };
+
+        assert_eq!(state, MediaState::Ready(vec![1, 2, 3].into()));
+        assert_eq!(fetcher.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_server_relative_urls_use_server_and_token() {
+        let fetcher = Arc::new(CountingFetcher::default());
+        let mut loader = MediaLoader::new(fetcher.clone());
+        let mut config = Config::new();
+        config.set_low_data_mode(false);
+        config.set_token(Some("secret".to_string()));
+
+        let url = "/api/users/42/avatar?v=1";
+        loader.request(url, MediaKind::Avatar, &config);
+
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while loader.request(url, MediaKind::Avatar, &config) == MediaState::Loading && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+
+        assert_eq!(
+            *fetcher.last_request.lock().unwrap(),
+            Some((config.api_url(url), Some("secret".to_string())))
+        );
+    }
+}
@@ -0,0 +1,206 @@
+//! Composer Activity
+//!
+//! Tracks what the local user is doing in the composer (typing, recording
+//! audio, uploading a file) and decides when to tell the server, plus the
+//! remote participants' activity shown above the input bar.
+//!
+//! Outgoing events are debounced: a start event is sent when the activity
+//! begins or changes kind, refreshed every [`ACTIVITY_REFRESH`] while it
+//! continues, and a single stop event is sent when it ends. Remote activity
+//! is tracked per sender and expires after the TTL the server attaches
+//! (falling back to [`ACTIVITY_TTL`]) without a refresh, so a lost stop
+//! event never leaves a stale indicator.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use uuid::Uuid;
+
+use crate::shared::{ActivityEvent, ActivityKind};
+
+/// How often an ongoing activity is re-sent
+pub const ACTIVITY_REFRESH: Duration = Duration::from_secs(3);
+
+/// How long a remote activity is shown without a refresh, unless the event
+/// carries its own TTL
+pub const ACTIVITY_TTL: Duration = Duration::from_secs(5);
+
+/// Activity implied by the current composer state, if any
+///
+/// Recording and uploading take precedence over a non-empty draft.
+pub fn current_activity(recording_audio: bool, uploading_file: bool, draft: &str) -> Option<ActivityKind> {
+    if recording_audio {
+        Some(ActivityKind::RecordingAudio)
+    } else if uploading_file {
+        Some(ActivityKind::UploadingFile)
+    } else if !draft.trim().is_empty() {
+        Some(ActivityKind::Typing)
+    } else {
+        None
+    }
+}
+
+/// Debounces the local user's outgoing activity events
+#[derive(Debug, Default)]
+pub struct ActivityTracker {
+    last_sent: Option<(ActivityKind, Instant)>,
+}
+
+impl ActivityTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Event to send for `current` at `now`, as `(kind, is_active)`
+    pub fn update(&mut self, current: Option<ActivityKind>, now: Instant) -> Option<(ActivityKind, bool)> {
+        match (current, self.last_sent) {
+            (Some(kind), Some((sent, at))) if kind == sent && now.duration_since(at) < ACTIVITY_REFRESH => None,
+            (Some(kind), _) => {
+                self.last_sent = Some((kind, now));
+                Some((kind, true))
+            }
+            (None, Some((sent, _))) => {
+                self.last_sent = None;
+                Some((sent, false))
+            }
+            (None, None) => None,
+        }
+    }
+}
+
+/// A remote participant's live activity
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TypingUser {
+    name: String,
+    kind: ActivityKind,
+    expires: Instant,
+}
+
+/// Other participants' activity, expiring after its TTL
+#[derive(Debug, Default)]
+pub struct RemoteActivity {
+    typing_users: HashMap<Uuid, TypingUser>,
+}
+
+impl RemoteActivity {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a received activity event; events without a sender are ignored
+    pub fn apply(&mut self, event: ActivityEvent, now: Instant) {
+        let Some(sender_id) = event.sender_id else {
+            return;
+        };
+        if event.is_typing {
+            let ttl = event.ttl_secs.map_or(ACTIVITY_TTL, Duration::from_secs);
+            let user = TypingUser { name: event.user, kind: event.kind, expires: now + ttl };
+            self.typing_users.insert(sender_id, user);
+        } else {
+            self.typing_users.remove(&sender_id);
+        }
+    }
+
+    /// Forget everything (e.g. when switching conversations)
+    pub fn clear(&mut self) {
+        self.typing_users.clear();
+    }
+
+    /// Indicator text for the live activities, e.g. "Alice is recording audio…"
+    pub fn indicator(&mut self, now: Instant) -> Option<String> {
+        self.typing_users.retain(|_, user| user.expires > now);
+
+        let mut lines: Vec<String> = self
+            .typing_users
+            .values()
+            .map(|user| user.kind.indicator(&user.name))
+            .collect();
+        lines.sort();
+
+        if lines.is_empty() {
+            None
+        } else {
+            Some(lines.join(", "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(sender_id: Uuid, user: &str, kind: ActivityKind, is_typing: bool) -> ActivityEvent {
+        ActivityEvent {
+            user: user.to_string(),
+            is_typing,
+            kind,
+            conversation_id: None,
+            sender_id: Some(sender_id),
+            ttl_secs: None,
+        }
+    }
+
+    #[test]
+    fn test_current_activity_precedence() {
+        assert_eq!(current_activity(false, false, "  "), None);
+        assert_eq!(current_activity(false, false, "hi"), Some(ActivityKind::Typing));
+        assert_eq!(current_activity(false, true, "hi"), Some(ActivityKind::UploadingFile));
+        assert_eq!(current_activity(true, true, "hi"), Some(ActivityKind::RecordingAudio));
+    }
+
+    #[test]
+    fn test_tracker_debounces_and_stops() {
+        let mut tracker = ActivityTracker::new();
+        let start = Instant::now();
+
+        assert_eq!(tracker.update(Some(ActivityKind::Typing), start), Some((ActivityKind::Typing, true)));
+        assert_eq!(tracker.update(Some(ActivityKind::Typing), start + Duration::from_secs(1)), None);
+        assert_eq!(
+            tracker.update(Some(ActivityKind::Typing), start + ACTIVITY_REFRESH),
+            Some((ActivityKind::Typing, true))
+        );
+
+        // A kind change is sent immediately
+        let later = start + ACTIVITY_REFRESH + Duration::from_millis(10);
+        assert_eq!(
+            tracker.update(Some(ActivityKind::RecordingAudio), later),
+            Some((ActivityKind::RecordingAudio, true))
+        );
+
+        assert_eq!(tracker.update(None, later), Some((ActivityKind::RecordingAudio, false)));
+        assert_eq!(tracker.update(None, later), None);
+    }
+
+    #[test]
+    fn test_remote_activity_renders_and_expires() {
+        let mut remote = RemoteActivity::new();
+        let now = Instant::now();
+        let (alice, bob) = (Uuid::new_v4(), Uuid::new_v4());
+
+        remote.apply(event(alice, "Alice", ActivityKind::RecordingAudio, true), now);
+        assert_eq!(remote.indicator(now).as_deref(), Some("Alice is recording audio…"));
+
+        remote.apply(event(alice, "Alice", ActivityKind::RecordingAudio, false), now);
+        assert_eq!(remote.indicator(now), None);
+
+        remote.apply(event(bob, "Bob", ActivityKind::UploadingFile, true), now);
+        assert_eq!(remote.indicator(now + ACTIVITY_TTL), None);
+    }
+
+    #[test]
+    fn test_remote_activity_uses_event_ttl_and_needs_sender() {
+        let mut remote = RemoteActivity::new();
+        let now = Instant::now();
+
+        let mut typing = event(Uuid::new_v4(), "Alice", ActivityKind::Typing, true);
+        typing.ttl_secs = Some(2);
+        remote.apply(typing, now);
+        assert_eq!(remote.indicator(now + Duration::from_secs(1)).as_deref(), Some("Alice is typing…"));
+        assert_eq!(remote.indicator(now + Duration::from_secs(2)), None);
+
+        let mut anonymous = event(Uuid::new_v4(), "Bob", ActivityKind::Typing, true);
+        anonymous.sender_id = None;
+        remote.apply(anonymous, now);
+        assert_eq!(remote.indicator(now), None);
+    }
+}
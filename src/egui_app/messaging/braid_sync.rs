@@ -1,79 +1,594 @@
 //! Braid Message Sync Client
 //!
 //! This module implements the Braid-HTTP client for real-time message synchronization.
+//! The conversation subscription can instead use the server's WebSocket
+//! (see [`Transport`]), which also carries typing and receipts.
 
+use crate::egui_app::api_client;
 use crate::egui_app::config::Config;
-use crate::shared::messaging::ChatMessage;
+use crate::shared::error::SharedError;
+use crate::shared::messaging::{ChatMessage, MessageType, UsageLimitExceeded, UserStatus};
+use crate::shared::{
+    ActivityEvent, ActivityKind, ClientFrame, ConversationEvent, DeliveryReceiptEvent, PresenceEvent, ReadReceiptEvent,
+    ConnectionReport, ConnectionTransport, DisconnectReason, RealtimeEvent, ServerFrame,
+};
 use reqwest::Client;
 use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use std::thread;
 use tokio::runtime::Runtime;
-use futures_util::StreamExt;
+use tokio::sync::Notify;
+use futures_util::{SinkExt, Stream, StreamExt};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::UnboundedSender;
+use tokio_tungstenite::tungstenite::{self, client::IntoClientRequest, http::HeaderValue};
 use uuid::Uuid;
 
+/// How the conversation subscription reaches the server
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Transport {
+    /// Braid-HTTP over Server-Sent Events; typing and receipts are separate requests
+    #[default]
+    Sse,
+    /// `/sync/conversations/{id}/ws`; typing and receipts go over the socket
+    /// while it is open
+    WebSocket,
+}
+
+/// Sender for frames to the open conversation socket, if there is one
+type SocketFrames = Arc<Mutex<Option<UnboundedSender<ClientFrame>>>>;
+
+/// Reconnect backoff for the message subscription
+///
+/// Delays double from `base_delay` up to `max_delay`, and each sleep is
+/// spread by ±`jitter` so clients dropped together don't reconnect in
+/// lockstep. After `max_attempts` consecutive failures the subscription
+/// reports a permanent [`SubscriptionStatus::Error`] and stops.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReconnectPolicy {
+    /// Delay before the first reconnect
+    pub base_delay: Duration,
+    /// Upper bound for the doubled delay
+    pub max_delay: Duration,
+    /// Fraction of the delay to randomize by (0.2 = ±20%)
+    pub jitter: f64,
+    /// Consecutive failed attempts before giving up
+    pub max_attempts: u32,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+            jitter: 0.2,
+            max_attempts: 10,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// `delay` spread by the jitter fraction; `unit` is a random value in [0, 1)
+    pub fn jittered(&self, delay: Duration, unit: f64) -> Duration {
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        let factor = 1.0 + jitter * (2.0 * unit.clamp(0.0, 1.0) - 1.0);
+        delay.mul_f64(factor)
+    }
+}
+
+/// Client-side check that a connected server is still answering
+///
+/// A wedged server can keep a subscription open without sending anything,
+/// which looks the same as a quiet conversation. Every `interval` the
+/// client pings it (a `ping` frame on a socket, `GET /sync/ping` next to
+/// an SSE stream); a ping not answered within `timeout` is missed, and
+/// after `max_missed` in a row the subscription reports
+/// [`SubscriptionStatus::Stalled`] and reconnects, resuming from the last
+/// version seen. Off unless set on [`MessageSyncClient::heartbeat`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeartbeatPolicy {
+    pub interval: Duration,
+    pub timeout: Duration,
+    /// Consecutive unanswered pings before the server counts as stalled
+    pub max_missed: u32,
+}
+
+impl Default for HeartbeatPolicy {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(30),
+            timeout: Duration::from_secs(10),
+            max_missed: 2,
+        }
+    }
+}
+
+impl HeartbeatPolicy {
+    /// Policy pinging every `XFMAIL_HEARTBEAT_SECS` (unset or 0 disables it)
+    pub fn from_env() -> Option<Self> {
+        std::env::var("XFMAIL_HEARTBEAT_SECS")
+            .ok()
+            .and_then(|secs| secs.trim().parse::<u64>().ok())
+            .filter(|secs| *secs > 0)
+            .map(|secs| Self { interval: Duration::from_secs(secs), ..Self::default() })
+    }
+}
+
+/// Resolves once `max_missed` pings in a row go unanswered; never without a policy
+async fn until_stalled<F, Fut>(policy: Option<HeartbeatPolicy>, mut ping: F)
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = bool>,
+{
+    let Some(policy) = policy else {
+        return std::future::pending().await;
+    };
+    let mut missed = 0;
+    loop {
+        tokio::time::sleep(policy.interval).await;
+        let answered = tokio::time::timeout(policy.timeout, ping()).await.unwrap_or(false);
+        if answered {
+            missed = 0;
+            continue;
+        }
+        missed += 1;
+        tracing::debug!("Missed heartbeat {} of {}", missed, policy.max_missed);
+        if missed >= policy.max_missed {
+            return;
+        }
+    }
+}
+
+/// Timeout for sending a reconnection report
+const TELEMETRY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// An outage being retried, kept for connection telemetry
+#[derive(Debug)]
+struct Outage {
+    reason: DisconnectReason,
+    attempts: u32,
+    waited: Duration,
+}
+
+/// Per-subscription backoff state
+#[derive(Debug)]
+struct Backoff {
+    policy: ReconnectPolicy,
+    delay: Duration,
+    attempts: u32,
+    /// Since the first failure; outlives [`Self::reset`] until the
+    /// connection is back or given up on
+    outage: Option<Outage>,
+}
+
+impl Backoff {
+    fn new(policy: ReconnectPolicy) -> Self {
+        Self { policy, delay: policy.base_delay, attempts: 0, outage: None }
+    }
+
+    /// Call after a successful connection
+    fn reset(&mut self) {
+        self.delay = self.policy.base_delay;
+        self.attempts = 0;
+    }
+
+    /// Sleep for `delay`, starting over from the base delay if woken early
+    async fn sleep(&mut self, delay: Duration, wake: &Notify) {
+        let started = Instant::now();
+        let woken = sleep_or_wake(delay, wake).await;
+        if let Some(outage) = &mut self.outage {
+            outage.waited += started.elapsed();
+        }
+        if woken {
+            self.reset();
+        }
+    }
+
+    /// [`Self::next_delay`] after a failure, remembered for telemetry
+    fn failed(&mut self, reason: DisconnectReason) -> Option<Duration> {
+        let delay = self.next_delay();
+        let outage = self.outage.get_or_insert(Outage { reason, attempts: 0, waited: Duration::ZERO });
+        outage.attempts += u32::from(delay.is_some());
+        delay
+    }
+
+    /// Report on the outage that just ended, if there was one
+    fn end_outage(&mut self, transport: ConnectionTransport, recovered: bool) -> Option<ConnectionReport> {
+        let outage = self.outage.take()?;
+        Some(ConnectionReport {
+            transport,
+            reason: outage.reason,
+            attempts: outage.attempts,
+            backoff_ms: u64::try_from(outage.waited.as_millis()).unwrap_or(u64::MAX),
+            recovered,
+        })
+    }
+
+    /// Jittered delay before the next attempt, or `None` once attempts are exhausted
+    fn next_delay(&mut self) -> Option<Duration> {
+        if self.attempts >= self.policy.max_attempts {
+            return None;
+        }
+        self.attempts += 1;
+        let delay = self.policy.jittered(self.delay, random_unit());
+        self.delay = std::cmp::min(self.delay * 2, self.policy.max_delay);
+        Some(delay)
+    }
+}
+
+/// Send a reconnection report if the user opted in; failures are only logged
+async fn report_connection(config: Config, report: ConnectionReport) {
+    if !config.connection_telemetry() {
+        return;
+    }
+    let request = config.api_url("/api/telemetry/connection");
+    let Ok(request) = api_client::authorize(&config, Client::new().post(request)) else {
+        return;
+    };
+    if let Err(e) = api_client::send(request.json(&report).timeout(TELEMETRY_TIMEOUT)).await {
+        tracing::debug!("Connection report not sent: {}", e);
+    }
+}
+
+/// What a subscription needs to retry after a failure or give up on it
+struct Retry<'a> {
+    transport: ConnectionTransport,
+    config: &'a Config,
+    conversation_id: Uuid,
+    status_sender: &'a Sender<SubscriptionStatus>,
+    wake: &'a Notify,
+}
+
+impl Retry<'_> {
+    /// Wait out the backoff after a failure; false once attempts are exhausted,
+    /// after reporting the outage as given up
+    async fn after_failure(&self, backoff: &mut Backoff, reason: DisconnectReason) -> bool {
+        let Some(delay) = backoff.failed(reason) else {
+            tracing::error!("Giving up on conversation {} after {} reconnect attempts", self.conversation_id, backoff.attempts);
+            let _ = self.status_sender.send(SubscriptionStatus::Error(format!(
+                "permanent failure: gave up after {} reconnect attempts",
+                backoff.attempts
+            )));
+            if let Some(report) = backoff.end_outage(self.transport, false) {
+                report_connection(self.config.clone(), report).await;
+            }
+            return false;
+        };
+        let _ = self.status_sender.send(SubscriptionStatus::Retrying);
+        backoff.sleep(delay, self.wake).await;
+        true
+    }
+}
+
+/// Messages after `version`, read from the server's database
+///
+/// The replay a reconnect starts with can miss messages the server's
+/// broadcast dropped while we lagged behind; this fills the gap. `None` if
+/// the server can't tell (e.g. the version is too old), in which case the
+/// subscription's snapshot is all there is.
+async fn fetch_missed(config: &Config, client: &Client, conversation_id: Uuid, version: &str) -> Option<Vec<ChatMessage>> {
+    let url = config.api_url(&format!("/sync/conversations/{}/messages/since", conversation_id));
+    let request = api_client::authorize(config, client.get(&url).query(&[("version", version)])).ok()?;
+    match api_client::send(request).await {
+        Ok(response) => response.json().await.ok(),
+        Err(e) => {
+            tracing::warn!("Couldn't catch up on conversation {} since {}: {}", conversation_id, version, e);
+            None
+        }
+    }
+}
+
+/// Catch up on what a reconnect may have missed before reading the live stream
+///
+/// Returns `false` if the UI has gone away.
+async fn catch_up(
+    config: &Config,
+    client: &Client,
+    conversation_id: Uuid,
+    resume_from: Option<&str>,
+    current_version: &Mutex<Option<String>>,
+    message_sender: &Sender<ChatMessage>,
+) -> bool {
+    let Some(version) = resume_from else {
+        return true;
+    };
+    let missed = fetch_missed(config, client, conversation_id, version).await.unwrap_or_default();
+    if !missed.is_empty() {
+        tracing::info!("[BRAID] Caught up on {} messages in conversation {}", missed.len(), conversation_id);
+    }
+    for msg in missed {
+        record_version(current_version, &msg);
+        if message_sender.send(msg).is_err() {
+            return false;
+        }
+    }
+    true
+}
+
+/// Random value in [0, 1)
+fn random_unit() -> f64 {
+    (Uuid::new_v4().as_u128() >> 75) as f64 / (1u64 << 53) as f64
+}
+
+/// Wait out a reconnect delay; returns `true` if [`MessageSyncClient::reconnect_now`] cut it short
+async fn sleep_or_wake(delay: Duration, wake: &Notify) -> bool {
+    tokio::select! {
+        _ = tokio::time::sleep(delay) => false,
+        _ = wake.notified() => true,
+    }
+}
+
+/// What reading the next chunk of an SSE stream produced
+enum StreamRead<T> {
+    Item(T),
+    /// The server closed the stream
+    End,
+    /// [`MessageSyncClient::reconnect_now`] asked for a fresh connection
+    Woken,
+}
+
+/// Read the next chunk of an SSE stream unless woken first
+///
+/// A backgrounded app's connection can stay open but dead, so a wake-up
+/// abandons it rather than waiting for a read that never completes.
+async fn read_or_wake<S: Stream + Unpin>(stream: &mut S, wake: &Notify) -> StreamRead<S::Item> {
+    tokio::select! {
+        item = stream.next() => item.map_or(StreamRead::End, StreamRead::Item),
+        _ = wake.notified() => StreamRead::Woken,
+    }
+}
+
+/// A conversation subscription's thread and the flag that stops it
+#[derive(Debug)]
+struct ConversationSubscription {
+    thread: thread::JoinHandle<()>,
+    /// Set to stop this subscription's thread, and only it
+    stopped: Arc<AtomicBool>,
+    /// Wakes this thread out of its backoff or an idle read
+    wake: Arc<Notify>,
+    /// Filled in by the thread while its socket is open
+    frames: SocketFrames,
+}
+
+impl ConversationSubscription {
+    /// Stop the thread, waking it so an idle stream doesn't keep it around
+    fn stop(&self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        self.wake.notify_waiters();
+    }
+
+    fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+}
+
 /// Message sync client for Braid-HTTP
 #[derive(Debug)]
 pub struct MessageSyncClient {
     config: Config,
+    /// Backoff used when the subscription drops
+    pub reconnect: ReconnectPolicy,
+    /// Used for the next conversation subscription
+    pub transport: Transport,
+    /// Detects a server that stopped answering; off when `None`
+    pub heartbeat: Option<HeartbeatPolicy>,
     client: Client,
-    current_version: Option<String>,
-    subscription_thread: Option<thread::JoinHandle<()>>,
+    /// Latest version seen for the subscribed conversation, shared with the
+    /// subscription thread so reconnects resume from it
+    current_version: Arc<Mutex<Option<String>>>,
+    subscribed_conversation_id: Option<Uuid>,
+    subscription: Option<ConversationSubscription>,
+    /// Set when the client is dropped (e.g. on logout) so the user stream
+    /// threads stop instead of reconnecting with a stale token
+    stopped: Arc<AtomicBool>,
+    /// Set when the server rejected our token; the conversation is only
+    /// resubscribed once [`Self::set_token`] brings a refreshed one
+    auth_rejected: Arc<AtomicBool>,
+    /// Wakes the user stream threads out of their backoff, see [`Self::reconnect_now`]
+    wake: Arc<Notify>,
     message_sender: Sender<ChatMessage>,
     message_receiver: Receiver<ChatMessage>,
     status_sender: Sender<SubscriptionStatus>,
     status_receiver: Receiver<SubscriptionStatus>,
+    activity_thread: Option<thread::JoinHandle<()>>,
+    activity_sender: Sender<ActivityEvent>,
+    activity_receiver: Receiver<ActivityEvent>,
+    receipt_sender: Sender<ReadReceiptEvent>,
+    receipt_receiver: Receiver<ReadReceiptEvent>,
+    delivery_sender: Sender<DeliveryReceiptEvent>,
+    delivery_receiver: Receiver<DeliveryReceiptEvent>,
+    presence_sender: Sender<PresenceEvent>,
+    presence_receiver: Receiver<PresenceEvent>,
+    status_thread: Option<thread::JoinHandle<()>>,
+    user_status_sender: Sender<UserStatus>,
+    user_status_receiver: Receiver<UserStatus>,
+    conversation_thread: Option<thread::JoinHandle<()>>,
+    conversation_sender: Sender<ConversationEvent>,
+    conversation_receiver: Receiver<ConversationEvent>,
 }
 
 impl Default for MessageSyncClient {
     fn default() -> Self {
         let (message_tx, message_rx) = mpsc::channel();
         let (status_tx, status_rx) = mpsc::channel();
+        let (activity_tx, activity_rx) = mpsc::channel();
+        let (receipt_tx, receipt_rx) = mpsc::channel();
+        let (delivery_tx, delivery_rx) = mpsc::channel();
+        let (presence_tx, presence_rx) = mpsc::channel();
+        let (user_status_tx, user_status_rx) = mpsc::channel();
+        let (conversation_tx, conversation_rx) = mpsc::channel();
         Self {
             config: Config::default(),
+            reconnect: ReconnectPolicy::default(),
+            transport: Transport::default(),
+            heartbeat: None,
             client: Client::new(),
-            current_version: None,
-            subscription_thread: None,
+            current_version: Arc::new(Mutex::new(None)),
+            subscribed_conversation_id: None,
+            subscription: None,
+            stopped: Arc::new(AtomicBool::new(false)),
+            auth_rejected: Arc::new(AtomicBool::new(false)),
+            wake: Arc::new(Notify::new()),
             message_sender: message_tx,
             message_receiver: message_rx,
             status_sender: status_tx,
             status_receiver: status_rx,
+            activity_thread: None,
+            activity_sender: activity_tx,
+            activity_receiver: activity_rx,
+            receipt_sender: receipt_tx,
+            receipt_receiver: receipt_rx,
+            delivery_sender: delivery_tx,
+            delivery_receiver: delivery_rx,
+            presence_sender: presence_tx,
+            presence_receiver: presence_rx,
+            status_thread: None,
+            user_status_sender: user_status_tx,
+            user_status_receiver: user_status_rx,
+            conversation_thread: None,
+            conversation_sender: conversation_tx,
+            conversation_receiver: conversation_rx,
         }
     }
 }
 
 impl MessageSyncClient {
-    pub fn new(config: Config) -> Self {
+    pub fn new(config: Config, reconnect: ReconnectPolicy) -> Self {
         let (message_tx, message_rx) = mpsc::channel();
         let (status_tx, status_rx) = mpsc::channel();
+        let (activity_tx, activity_rx) = mpsc::channel();
+        let (receipt_tx, receipt_rx) = mpsc::channel();
+        let (delivery_tx, delivery_rx) = mpsc::channel();
+        let (presence_tx, presence_rx) = mpsc::channel();
+        let (user_status_tx, user_status_rx) = mpsc::channel();
+        let (conversation_tx, conversation_rx) = mpsc::channel();
         Self {
             config,
+            reconnect,
+            transport: Transport::default(),
+            heartbeat: None,
             client: Client::new(),
-            current_version: None,
-            subscription_thread: None,
+            current_version: Arc::new(Mutex::new(None)),
+            subscribed_conversation_id: None,
+            subscription: None,
+            stopped: Arc::new(AtomicBool::new(false)),
+            auth_rejected: Arc::new(AtomicBool::new(false)),
+            wake: Arc::new(Notify::new()),
             message_sender: message_tx,
             message_receiver: message_rx,
             status_sender: status_tx,
             status_receiver: status_rx,
+            activity_thread: None,
+            activity_sender: activity_tx,
+            activity_receiver: activity_rx,
+            receipt_sender: receipt_tx,
+            receipt_receiver: receipt_rx,
+            delivery_sender: delivery_tx,
+            delivery_receiver: delivery_rx,
+            presence_sender: presence_tx,
+            presence_receiver: presence_rx,
+            status_thread: None,
+            user_status_sender: user_status_tx,
+            user_status_receiver: user_status_rx,
+            conversation_thread: None,
+            conversation_sender: conversation_tx,
+            conversation_receiver: conversation_rx,
+        }
+    }
+
+    /// Use a refreshed token for requests and subsequent subscriptions
+    ///
+    /// A conversation subscription the server turned away for its token is
+    /// started again with the new one.
+    pub fn set_token(&mut self, token: Option<String>) {
+        let has_token = token.is_some();
+        self.config.set_token(token);
+        if has_token && self.auth_rejected.swap(false, Ordering::Relaxed) {
+            if let Some(conversation_id) = self.subscribed_conversation_id {
+                self.subscribe_to_conversation(conversation_id);
+            }
         }
     }
 
     /// Subscribe to a conversation's message stream
     pub fn subscribe_to_conversation(&mut self, conversation_id: Uuid) {
-        // Stop existing subscription if any
-        if self.subscription_thread.is_some() {
-            // Join the old thread (it will terminate when we create a new one)
-            self.subscription_thread = None;
+        // Stop the old thread before starting its replacement
+        if let Some(old) = self.subscription.take() {
+            old.stop();
+        }
+
+        // A version from another conversation means nothing here; the old
+        // thread keeps its own handle so it can't overwrite the new one
+        if self.subscribed_conversation_id != Some(conversation_id) {
+            self.current_version = Arc::new(Mutex::new(None));
+            self.subscribed_conversation_id = Some(conversation_id);
         }
 
         let config = self.config.clone();
+        let reconnect = self.reconnect;
+        let current_version = Arc::clone(&self.current_version);
+        let stopped = Arc::new(AtomicBool::new(false));
+        let thread_stopped = Arc::clone(&stopped);
+        let auth_rejected = Arc::clone(&self.auth_rejected);
+        let wake = Arc::new(Notify::new());
+        let thread_wake = Arc::clone(&wake);
         let message_sender = self.message_sender.clone();
         let status_sender = self.status_sender.clone();
+        let frames: SocketFrames = Arc::new(Mutex::new(None));
+        let socket_frames = Arc::clone(&frames);
 
-        let thread = std::thread::spawn(move || {
-            subscribe_to_stream(config, conversation_id, message_sender, status_sender);
+        let transport = self.transport;
+        let heartbeat = self.heartbeat;
+        let thread = std::thread::spawn(move || match transport {
+            Transport::Sse => subscribe_to_stream(
+                config,
+                reconnect,
+                heartbeat,
+                conversation_id,
+                current_version,
+                thread_stopped,
+                auth_rejected,
+                thread_wake,
+                message_sender,
+                status_sender,
+            ),
+            Transport::WebSocket => subscribe_to_socket(
+                config,
+                reconnect,
+                heartbeat,
+                conversation_id,
+                current_version,
+                thread_stopped,
+                auth_rejected,
+                thread_wake,
+                socket_frames,
+                message_sender,
+                status_sender,
+            ),
         });
 
-        self.subscription_thread = Some(thread);
+        self.subscription = Some(ConversationSubscription { thread, stopped, wake, frames });
+    }
+
+    /// Reconnect every subscription now instead of waiting out its backoff
+    ///
+    /// For when the app regains focus after the OS may have suspended the
+    /// network: open streams are dropped and reopened (resuming from the
+    /// last version seen), backoffs start over, and a conversation
+    /// subscription that gave up is started again. One turned away for its
+    /// token waits for [`Self::set_token`] instead.
+    pub fn reconnect_now(&mut self) {
+        self.wake.notify_waiters();
+        // Only the active conversation; stopped ones are left to exit
+        if let Some(ref subscription) = self.subscription {
+            subscription.wake.notify_waiters();
+        }
+        let finished = self.subscription.as_ref().is_some_and(ConversationSubscription::is_finished)
+            && !self.auth_rejected.load(Ordering::Relaxed);
+        if let (true, Some(conversation_id)) = (finished, self.subscribed_conversation_id) {
+            self.subscribe_to_conversation(conversation_id);
+        }
     }
 
     /// Send a message via PUT
@@ -81,39 +596,322 @@ impl MessageSyncClient {
         &mut self,
         conversation_id: Uuid,
         content: String,
+        message_type: &MessageType,
         parents: Option<Vec<String>>,
-    ) -> Result<(Uuid, String), String> {
-        tracing::info!("[BRAID] Client sending message: conversation={}, content_preview='{}...'",
-                      conversation_id, &content[..content.len().min(50)]);
+    ) -> Result<(Uuid, String), SendError> {
         let message_id = Uuid::new_v4();
+        self.sender()
+            .send(conversation_id, message_id, &content, message_type, parents)
+            .map(|version| (message_id, version))
+    }
+
+    /// Handle for sending messages from another thread
+    pub fn sender(&self) -> MessageSender {
+        MessageSender {
+            config: self.config.clone(),
+            client: self.client.clone(),
+            current_version: Arc::clone(&self.current_version),
+        }
+    }
+
+    /// Edit one of our messages via PUT to its existing id
+    ///
+    /// `parent_version` is the version being edited; the server refuses the
+    /// edit if the message has changed since. Returns the edit's version.
+    pub fn edit_message(
+        &self,
+        conversation_id: Uuid,
+        message_id: Uuid,
+        content: String,
+        parent_version: &str,
+    ) -> Result<String, String> {
         let url = self.config.api_url(&format!(
             "/sync/conversations/{}/messages/{}",
             conversation_id, message_id
         ));
-        // Prefer real JWT when available; otherwise, allow dev bypass if configured
-        let token_opt = self.config.get_token().cloned();
-        let dev_bypass = self.config.dev_auth_bypass();
-        let dev_user_id = self.config.dev_user_id().map(|s| s.to_string());
-        if token_opt.is_none() && !dev_bypass {
-            return Err("Not authenticated".to_string());
-        }
+        let request = api_client::authorize(&self.config, self.client.put(&url))
+            .map_err(|e| e.to_string())?
+            .header("Parents", format!("\"{}\"", parent_version))
+            .json(&serde_json::json!({ "content": content }));
 
         let rt = Runtime::new().map_err(|e| format!("Failed to create runtime: {}", e))?;
+        rt.block_on(async {
+            let response = api_client::send(request).await.map_err(|e| match e.status() {
+                Some(409) => "The message changed since you started editing it".to_string(),
+                Some(410) => "The message was deleted".to_string(),
+                Some(403) => "You can only edit your own messages".to_string(),
+                _ => format!("Edit failed: {}", e),
+            })?;
+
+            Ok(response
+                .headers()
+                .get("Version")
+                .and_then(|h| h.to_str().ok())
+                .unwrap_or("")
+                .trim_matches('"')
+                .to_string())
+        })
+    }
+
+    /// Delete one of our messages via DELETE; the server keeps a tombstone
+    pub fn delete_message(&self, conversation_id: Uuid, message_id: Uuid) -> Result<(), String> {
+        let url = self.config.api_url(&format!(
+            "/sync/conversations/{}/messages/{}",
+            conversation_id, message_id
+        ));
+        let request = api_client::authorize(&self.config, self.client.delete(&url))
+            .map_err(|e| e.to_string())?;
 
+        let rt = Runtime::new().map_err(|e| format!("Failed to create runtime: {}", e))?;
         rt.block_on(async {
-            let mut request = self
-                .client
-                .put(&url)
-                .header("Content-Type", "application/json");
+            api_client::send(request).await.map_err(|e| match e.status() {
+                Some(403) => "You can only delete your own messages".to_string(),
+                _ => format!("Delete failed: {}", e),
+            })?;
+            Ok(())
+        })
+    }
+
+    /// Get current version
+    pub fn get_current_version(&self) -> Option<String> {
+        self.current_version.lock().ok().and_then(|v| v.clone())
+    }
+
+    /// Check for new messages (non-blocking)
+    pub fn poll_messages(&self) -> Vec<ChatMessage> {
+        let mut messages = Vec::new();
+        while let Ok(msg) = self.message_receiver.try_recv() {
+            messages.push(msg);
+        }
+        messages
+    }
+
+    /// Poll latest subscription status update (non-blocking)
+    pub fn poll_status(&self) -> Option<SubscriptionStatus> {
+        self.status_receiver.try_recv().ok()
+    }
+
+    /// Send a frame over the conversation socket; `false` if none is open
+    fn send_frame(&self, frame: ClientFrame) -> bool {
+        if self.transport != Transport::WebSocket {
+            return false;
+        }
+        let frames = self
+            .subscription
+            .as_ref()
+            .and_then(|subscription| subscription.frames.lock().ok()?.clone());
+        frames.is_some_and(|frames| frames.send(frame).is_ok())
+    }
+
+    /// Report the local user's composer activity in a conversation
+    /// (fire-and-forget POST /sync/conversations/{id}/typing, or a socket frame)
+    pub fn send_activity(&self, conversation_id: Uuid, user: String, kind: ActivityKind, is_active: bool) {
+        let frame = ClientFrame::Typing { conversation_id, user: user.clone(), is_typing: is_active, kind };
+        if self.send_frame(frame) {
+            return;
+        }
+
+        let url = self.config.api_url(&format!("/sync/conversations/{}/typing", conversation_id));
+        let body = serde_json::json!({ "user": user, "is_typing": is_active, "kind": kind });
+        let request = match api_client::authorize(&self.config, self.client.post(&url)) {
+            Ok(request) => request.json(&body),
+            Err(e) => {
+                tracing::debug!("Not sending activity event: {}", e);
+                return;
+            }
+        };
 
-            if let Some(token) = token_opt.as_ref() {
-                request = request.header("Authorization", format!("Bearer {}", token));
-            } else if dev_bypass {
-                if let Some(uid) = dev_user_id.as_ref() {
-                    request = request.header("X-Dev-User-Id", uid);
+        thread::spawn(move || {
+            let rt = match Runtime::new() {
+                Ok(rt) => rt,
+                Err(e) => {
+                    tracing::warn!("Failed to create runtime for activity event: {}", e);
+                    return;
+                }
+            };
+            rt.block_on(async {
+                if let Err(e) = request.send().await {
+                    tracing::debug!("Failed to send activity event: {}", e);
                 }
+            });
+        });
+    }
+
+    /// Tell the server a message was read (fire-and-forget PUT .../read)
+    ///
+    /// The server broadcasts a read receipt so the sender sees it as read.
+    pub fn mark_read(&self, conversation_id: Uuid, message_id: Uuid) {
+        self.send_receipt(conversation_id, message_id, "read");
+    }
+
+    /// Tell the server a message reached this device (fire-and-forget PUT .../delivered)
+    ///
+    /// The server broadcasts a delivery receipt so the sender sees the second tick.
+    pub fn mark_delivered(&self, conversation_id: Uuid, message_id: Uuid) {
+        self.send_receipt(conversation_id, message_id, "delivered");
+    }
+
+    fn send_receipt(&self, conversation_id: Uuid, message_id: Uuid, kind: &'static str) {
+        let frame = match kind {
+            "read" => ClientFrame::Read { conversation_id, message_id },
+            _ => ClientFrame::Delivered { conversation_id, message_id },
+        };
+        if self.send_frame(frame) {
+            return;
+        }
+
+        let url = self.config.api_url(&format!(
+            "/sync/conversations/{}/messages/{}/{}",
+            conversation_id, message_id, kind
+        ));
+        let request = match api_client::authorize(&self.config, self.client.put(&url)) {
+            Ok(request) => request,
+            Err(e) => {
+                tracing::debug!("Not sending {} receipt for {}: {}", kind, message_id, e);
+                return;
             }
+        };
+
+        thread::spawn(move || {
+            let rt = match Runtime::new() {
+                Ok(rt) => rt,
+                Err(e) => {
+                    tracing::warn!("Failed to create runtime for {} receipt: {}", kind, e);
+                    return;
+                }
+            };
+            rt.block_on(async {
+                match request.send().await {
+                    Ok(resp) if !resp.status().is_success() => {
+                        tracing::debug!("{} receipt for {} failed with status: {}", kind, message_id, resp.status());
+                    }
+                    Err(e) => tracing::debug!("Failed to send {} receipt for {}: {}", kind, message_id, e),
+                    Ok(_) => {}
+                }
+            });
+        });
+    }
+
+    /// Start listening for other users' activity events, receipts and presence (once)
+    pub fn subscribe_to_activity(&mut self) {
+        if self.activity_thread.is_some() {
+            return;
+        }
+        let config = self.config.clone();
+        let activity_sender = self.activity_sender.clone();
+        let receipt_sender = self.receipt_sender.clone();
+        let delivery_sender = self.delivery_sender.clone();
+        let presence_sender = self.presence_sender.clone();
+        let wake = Arc::clone(&self.wake);
+        self.activity_thread = Some(thread::spawn(move || {
+            subscribe_to_activity_stream(config, wake, activity_sender, receipt_sender, delivery_sender, presence_sender);
+        }));
+    }
+
+    /// Check for activity events (non-blocking)
+    pub fn poll_activity(&self) -> Vec<ActivityEvent> {
+        self.activity_receiver.try_iter().collect()
+    }
 
+    /// Check for read receipts (non-blocking)
+    pub fn poll_read_receipts(&self) -> Vec<ReadReceiptEvent> {
+        self.receipt_receiver.try_iter().collect()
+    }
+
+    /// Check for delivery receipts (non-blocking)
+    pub fn poll_delivery_receipts(&self) -> Vec<DeliveryReceiptEvent> {
+        self.delivery_receiver.try_iter().collect()
+    }
+
+    /// Check for contacts coming online or going offline (non-blocking)
+    pub fn poll_presence(&self) -> Vec<PresenceEvent> {
+        self.presence_receiver.try_iter().collect()
+    }
+
+    /// Start listening for contacts' chosen statuses on `/sync/presence` (once)
+    pub fn subscribe_to_statuses(&mut self) {
+        if self.status_thread.is_some() {
+            return;
+        }
+        let config = self.config.clone();
+        let stopped = self.stopped.clone();
+        let wake = Arc::clone(&self.wake);
+        let sender = self.user_status_sender.clone();
+        self.status_thread = Some(thread::spawn(move || {
+            subscribe_to_user_stream(config, "/sync/presence", stopped, wake, sender, |data| serde_json::from_str(data).ok());
+        }));
+    }
+
+    /// Check for contacts' status changes (non-blocking)
+    pub fn poll_statuses(&self) -> Vec<UserStatus> {
+        self.user_status_receiver.try_iter().collect()
+    }
+
+    /// Start listening for conversations created, changed or left on `/sync/conversations` (once)
+    pub fn subscribe_to_conversation_events(&mut self) {
+        if self.conversation_thread.is_some() {
+            return;
+        }
+        let config = self.config.clone();
+        let stopped = self.stopped.clone();
+        let wake = Arc::clone(&self.wake);
+        let sender = self.conversation_sender.clone();
+        self.conversation_thread = Some(thread::spawn(move || {
+            subscribe_to_user_stream(config, "/sync/conversations", stopped, wake, sender, |data| {
+                ConversationEvent::from_event(&serde_json::from_str::<RealtimeEvent>(data).ok()?)
+            });
+        }));
+    }
+
+    /// Check for conversation changes (non-blocking)
+    pub fn poll_conversation_events(&self) -> Vec<ConversationEvent> {
+        self.conversation_receiver.try_iter().collect()
+    }
+}
+
+impl Drop for MessageSyncClient {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        if let Some(ref subscription) = self.subscription {
+            subscription.stop();
+        }
+    }
+}
+
+/// Sends messages for a [`MessageSyncClient`] without borrowing it
+///
+/// Lets the UI show a message straight away and PUT it in the background.
+#[derive(Debug, Clone)]
+pub struct MessageSender {
+    config: Config,
+    client: Client,
+    current_version: Arc<Mutex<Option<String>>>,
+}
+
+impl MessageSender {
+    /// PUT a message under an id the caller picked, returning its version
+    pub fn send(
+        &self,
+        conversation_id: Uuid,
+        message_id: Uuid,
+        content: &str,
+        message_type: &MessageType,
+        parents: Option<Vec<String>>,
+    ) -> Result<String, SendError> {
+        tracing::info!("[BRAID] Client sending message: conversation={}, content_preview='{}...'",
+                      conversation_id, &content[..content.len().min(50)]);
+        let url = self.config.api_url(&format!(
+            "/sync/conversations/{}/messages/{}",
+            conversation_id, message_id
+        ));
+        // Real JWT when available; otherwise dev bypass if configured
+        let mut request = api_client::authorize(&self.config, self.client.put(&url))
+            .map_err(|e| SendError::Failed(e.to_string()))?
+            .header("Content-Type", "application/json");
+
+        let rt = Runtime::new().map_err(|e| SendError::Failed(format!("Failed to create runtime: {}", e)))?;
+
+        rt.block_on(async {
             // Add Parents header if provided (Structured Headers format)
             if let Some(ref parents_vec) = parents {
                 let parents_header = parents_vec
@@ -126,23 +924,12 @@ impl MessageSyncClient {
 
             let body = serde_json::json!({
                 "content": content,
-                "message_type": "text"
+                "message_type": message_type.to_string()
             });
 
-            let response = request
-                .json(&body)
-                .send()
+            let response = api_client::send(request.json(&body))
                 .await
-                .map_err(|e| format!("Network error: {}", e))?;
-
-            if !response.status().is_success() {
-                let status = response.status();
-                let error_text = response
-                    .text()
-                    .await
-                    .unwrap_or_else(|_| status.to_string());
-                return Err(format!("PUT failed: {} - {}", status, error_text));
-            }
+                .map_err(SendError::from_response)?;
 
             // Extract version from Version header
             let version_header = response
@@ -160,28 +947,44 @@ impl MessageSyncClient {
                 .trim_matches('"')
                 .to_string();
 
-            self.current_version = Some(version.clone());
-            Ok((message_id, version))
+            if let Ok(mut current) = self.current_version.lock() {
+                *current = Some(version.clone());
+            }
+            Ok(version)
         })
     }
+}
 
-    /// Get current version
-    pub fn get_current_version(&self) -> Option<&String> {
-        self.current_version.as_ref()
-    }
+/// Why a message PUT failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SendError {
+    /// The sender's daily quota is used up; resending won't work until it resets
+    LimitReached(UsageLimitExceeded),
+    /// Network, auth or server failure; worth queueing to try again
+    Failed(String),
+}
 
-    /// Check for new messages (non-blocking)
-    pub fn poll_messages(&self) -> Vec<ChatMessage> {
-        let mut messages = Vec::new();
-        while let Ok(msg) = self.message_receiver.try_recv() {
-            messages.push(msg);
+impl SendError {
+    /// Classify a failed PUT, reading the quota details from a 402/429 body
+    fn from_response(error: SharedError) -> Self {
+        if let SharedError::HttpError { status: 402 | 429, message } = &error {
+            if let Ok(exceeded) = serde_json::from_str(message) {
+                return Self::LimitReached(exceeded);
+            }
         }
-        messages
+        Self::Failed(format!("PUT failed: {}", error))
     }
+}
 
-    /// Poll latest subscription status update (non-blocking)
-    pub fn poll_status(&self) -> Option<SubscriptionStatus> {
-        self.status_receiver.try_recv().ok()
+impl std::fmt::Display for SendError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::LimitReached(exceeded) => {
+                let resets_at = exceeded.resets_at.with_timezone(&chrono::Local).format("%H:%M");
+                write!(f, "You've reached your limit of {} messages today. You can send again at {}.", exceeded.limit, resets_at)
+            }
+            Self::Failed(message) => f.write_str(message),
+        }
     }
 }
 
@@ -191,14 +994,27 @@ pub enum SubscriptionStatus {
     Connecting,
     Connected,
     Retrying,
+    /// The server rejected our token (401); the app should refresh it, and
+    /// the subscription resumes once the new token is set
+    Unauthorized,
+    /// The connection looked open but heartbeats went unanswered; followed
+    /// by a reconnect
+    Stalled,
     Error(String),
     Disconnected,
 }
 
 /// Subscribe to SSE stream for a conversation
+#[allow(clippy::too_many_arguments)]
 fn subscribe_to_stream(
     config: crate::egui_app::config::Config,
+    reconnect: ReconnectPolicy,
+    heartbeat: Option<HeartbeatPolicy>,
     conversation_id: Uuid,
+    current_version: Arc<Mutex<Option<String>>>,
+    stopped: Arc<AtomicBool>,
+    auth_rejected: Arc<AtomicBool>,
+    wake: Arc<Notify>,
     message_sender: Sender<ChatMessage>,
     status_sender: Sender<SubscriptionStatus>,
 ) {
@@ -211,41 +1027,49 @@ fn subscribe_to_stream(
     };
 
     rt.block_on(async {
-        let mut reconnect_delay = std::time::Duration::from_millis(1000);
-        const MAX_RECONNECT_DELAY: std::time::Duration = std::time::Duration::from_secs(30);
+        // Low data mode stretches reconnect intervals
+        let mut backoff = Backoff::new(ReconnectPolicy {
+            base_delay: config.sync_interval(reconnect.base_delay),
+            max_delay: config.sync_interval(reconnect.max_delay),
+            ..reconnect
+        });
+        let retry = Retry {
+            transport: ConnectionTransport::Sse,
+            config: &config,
+            conversation_id,
+            status_sender: &status_sender,
+            wake: &wake,
+        };
 
         loop {
-            let url = config.api_url(&format!(
-                "/sync/conversations/{}/messages",
-                conversation_id
-            ));
-
-            let token_opt = config.get_token().cloned();
-            let dev_bypass = config.dev_auth_bypass();
-            let dev_user_id = config.dev_user_id().map(|s| s.to_string());
-            
-            println!("[CLIENT-SUB] Attempting subscription: {}", url);
-            println!("[CLIENT-SUB] Token: {}, Dev bypass: {}, Dev user: {:?}", token_opt.is_some(), dev_bypass, dev_user_id);
-            
-            if token_opt.is_none() && !dev_bypass {
-                println!("[CLIENT-SUB] ERROR: No token and no dev bypass!");
-                tracing::error!("No authentication token available and DEV_AUTH_BYPASS disabled");
+            if stopped.load(Ordering::Relaxed) {
+                tracing::info!("Message sync stopped for conversation {}", conversation_id);
                 break;
             }
 
+            // Activity and receipts come from the activity stream
+            let url = config.api_url(&format!(
+                "/sync/conversations/{}/messages?snapshot_limit={}&types=message",
+                conversation_id,
+                config.snapshot_limit()
+            ));
+
             let client = Client::new();
 
-            let mut req = client.get(&url).header("Subscribe", "true");
-            if let Some(token) = token_opt.as_ref() {
-                req = req.header("Authorization", format!("Bearer {}", token));
-            } else if dev_bypass {
-                if let Some(uid) = dev_user_id.as_ref() {
-                    req = req.header("X-Dev-User-Id", uid);
-                    println!("[CLIENT-SUB] Using X-Dev-User-Id: {}", uid);
+            let mut req = match api_client::authorize(&config, client.get(&url).header("Subscribe", "true")) {
+                Ok(req) => req,
+                Err(e) => {
+                    tracing::error!("Not subscribing to conversation {}: {}", conversation_id, e);
+                    break;
                 }
+            };
+
+            // Resume from the last version we saw instead of replaying history
+            let resume_from = current_version.lock().ok().and_then(|v| v.clone());
+            if let Some(version) = resume_from.as_deref() {
+                req = req.header("Parents", parents_header(version));
             }
 
-            println!("[CLIENT-SUB] Sending request to: {}", url);
             tracing::info!("[BRAID] Subscribing to SSE: {}", url);
             // Report connecting
             let _ = status_sender.send(SubscriptionStatus::Connecting);
@@ -253,47 +1077,95 @@ fn subscribe_to_stream(
                 .await
             {
                 Ok(resp) => {
-                    println!("[CLIENT-SUB] Response received: {}", resp.status());
+                    tracing::debug!("[BRAID] Subscription response: {}", resp.status());
                     resp
                 }
                 Err(e) => {
-                    println!("[CLIENT-SUB] Request failed: {}", e);
                     tracing::warn!("Failed to subscribe to message stream (will retry): {}", e);
                     let _ = status_sender.send(SubscriptionStatus::Error(format!("network: {}", e)));
-                    let _ = status_sender.send(SubscriptionStatus::Retrying);
-                    tokio::time::sleep(reconnect_delay).await;
-                    reconnect_delay = std::cmp::min(reconnect_delay * 2, MAX_RECONNECT_DELAY);
+                    if !retry.after_failure(&mut backoff, DisconnectReason::Network).await {
+                        break;
+                    }
                     continue;
                 }
             };
 
+            // Retrying can't fix a rejected token or a conversation we're not in
+            match response.status() {
+                reqwest::StatusCode::UNAUTHORIZED => {
+                    tracing::warn!("Subscription to {} rejected our token; waiting for a refresh", conversation_id);
+                    auth_rejected.store(true, Ordering::Relaxed);
+                    let _ = status_sender.send(SubscriptionStatus::Unauthorized);
+                    break;
+                }
+                reqwest::StatusCode::FORBIDDEN => {
+                    tracing::error!("Not allowed to subscribe to conversation {}", conversation_id);
+                    let _ = status_sender.send(SubscriptionStatus::Error(
+                        "permanent failure: not a participant".to_string(),
+                    ));
+                    break;
+                }
+                _ => {}
+            }
+
             if !response.status().is_success() {
-                println!("[CLIENT-SUB] ERROR: Subscription failed with status: {}", response.status());
                 tracing::error!(
                     "Subscription failed with status: {} (will retry)",
                     response.status()
                 );
                 let _ = status_sender.send(SubscriptionStatus::Error(format!("http: {}", response.status())));
-                let _ = status_sender.send(SubscriptionStatus::Retrying);
-                tokio::time::sleep(reconnect_delay).await;
-                reconnect_delay = std::cmp::min(reconnect_delay * 2, MAX_RECONNECT_DELAY);
+                if !retry.after_failure(&mut backoff, DisconnectReason::Http).await {
+                    break;
+                }
                 continue;
             }
             
-            println!("[CLIENT-SUB] Connected successfully!");
-
             tracing::info!("[BRAID] SSE subscription established for conversation {}", conversation_id);
             let _ = status_sender.send(SubscriptionStatus::Connected);
 
-            // Reset reconnect delay on successful connection
-            reconnect_delay = std::time::Duration::from_millis(1000);
+            // Reset backoff on successful connection
+            backoff.reset();
+            if let Some(report) = backoff.end_outage(ConnectionTransport::Sse, true) {
+                tokio::spawn(report_connection(config.clone(), report));
+            }
+            if !catch_up(&config, &client, conversation_id, resume_from.as_deref(), &current_version, &message_sender).await {
+                return;
+            }
 
             // Read SSE stream as bytes stream
             let mut stream = response.bytes_stream();
             let mut buffer = String::new();
             let mut connection_active = true;
+            let mut woken = false;
+            let mut stalled = false;
 
-            while let Some(chunk_result) = stream.next().await {
+            // The stream can't be pinged, so ask the server something cheap
+            let ping_url = config.api_url("/sync/ping");
+            let stall = until_stalled(heartbeat, || {
+                let ping = client.get(&ping_url).send();
+                async move { ping.await.is_ok_and(|resp| resp.status().is_success()) }
+            });
+            tokio::pin!(stall);
+
+            loop {
+                let read = tokio::select! {
+                    read = read_or_wake(&mut stream, &wake) => read,
+                    _ = &mut stall => {
+                        stalled = true;
+                        break;
+                    }
+                };
+                let chunk_result = match read {
+                    StreamRead::Item(chunk_result) => chunk_result,
+                    StreamRead::End => break,
+                    StreamRead::Woken => {
+                        woken = true;
+                        break;
+                    }
+                };
+                if stopped.load(Ordering::Relaxed) {
+                    return;
+                }
                 match chunk_result {
                     Ok(chunk) => {
                         // Convert bytes to string
@@ -324,6 +1196,7 @@ fn subscribe_to_stream(
                                 // Try to parse as JSON
                                 if let Ok(msg) = serde_json::from_str::<ChatMessage>(data_content) {
                                     tracing::debug!("Received message via SSE: {:?}", msg.id);
+                                    record_version(&current_version, &msg);
                                     if let Err(e) = message_sender.send(msg) {
                                         tracing::error!("Failed to send message to channel: {}", e);
                                         return;
@@ -339,6 +1212,7 @@ fn subscribe_to_stream(
                                 match serde_json::from_str::<ChatMessage>(&line) {
                                     Ok(msg) => {
                                         tracing::debug!("Received JSON line message: {:?}", msg.id);
+                                        record_version(&current_version, &msg);
                                         if let Err(e) = message_sender.send(msg) {
                                             tracing::error!("Failed to send message to channel: {}", e);
                                             return;
@@ -361,17 +1235,778 @@ fn subscribe_to_stream(
                 }
             }
 
-            if connection_active {
+            if woken {
+                tracing::info!("Reconnecting conversation {} on request", conversation_id);
+                backoff.reset();
+            } else if stalled {
+                tracing::warn!("Server stopped answering heartbeats for conversation {}, will reconnect", conversation_id);
+                let _ = status_sender.send(SubscriptionStatus::Stalled);
+                if !retry.after_failure(&mut backoff, DisconnectReason::Stalled).await {
+                    break;
+                }
+            } else if connection_active {
                 tracing::info!("Message stream closed normally for conversation {}", conversation_id);
                 let _ = status_sender.send(SubscriptionStatus::Disconnected);
                 break; // Normal closure, don't reconnect
             } else {
                 tracing::warn!("Message stream connection lost for conversation {}, will reconnect", conversation_id);
-                let _ = status_sender.send(SubscriptionStatus::Retrying);
-                tokio::time::sleep(reconnect_delay).await;
-                reconnect_delay = std::cmp::min(reconnect_delay * 2, MAX_RECONNECT_DELAY);
+                if !retry.after_failure(&mut backoff, DisconnectReason::Stream).await {
+                    break;
+                }
+            }
+        }
+    });
+}
+
+/// Subscribe to a conversation over its WebSocket
+///
+/// Behaves like [`subscribe_to_stream`]: the same backoff and statuses,
+/// resuming from the last version seen. While connected, `socket_frames`
+/// holds a sender for outgoing typing and receipt frames.
+#[allow(clippy::too_many_arguments)]
+fn subscribe_to_socket(
+    config: Config,
+    reconnect: ReconnectPolicy,
+    heartbeat: Option<HeartbeatPolicy>,
+    conversation_id: Uuid,
+    current_version: Arc<Mutex<Option<String>>>,
+    stopped: Arc<AtomicBool>,
+    auth_rejected: Arc<AtomicBool>,
+    wake: Arc<Notify>,
+    socket_frames: SocketFrames,
+    message_sender: Sender<ChatMessage>,
+    status_sender: Sender<SubscriptionStatus>,
+) {
+    let rt = match Runtime::new() {
+        Ok(rt) => rt,
+        Err(e) => {
+            tracing::error!("Failed to create runtime for message socket: {}", e);
+            return;
+        }
+    };
+
+    rt.block_on(async {
+        let mut backoff = Backoff::new(ReconnectPolicy {
+            base_delay: config.sync_interval(reconnect.base_delay),
+            max_delay: config.sync_interval(reconnect.max_delay),
+            ..reconnect
+        });
+        let retry = Retry {
+            transport: ConnectionTransport::WebSocket,
+            config: &config,
+            conversation_id,
+            status_sender: &status_sender,
+            wake: &wake,
+        };
+
+        while !stopped.load(Ordering::Relaxed) {
+            let url = socket_url(&config.api_url(&format!(
+                "/sync/conversations/{}/ws?snapshot_limit={}&types=message",
+                conversation_id,
+                config.snapshot_limit()
+            )));
+            let mut request = match url.as_str().into_client_request() {
+                Ok(request) => request,
+                Err(e) => {
+                    tracing::error!("Invalid socket URL {}: {}", url, e);
+                    let _ = status_sender.send(SubscriptionStatus::Error(format!("permanent failure: {}", e)));
+                    break;
+                }
+            };
+            match api_client::auth_header(&config) {
+                Ok((name, value)) => {
+                    if let Ok(value) = HeaderValue::from_str(&value) {
+                        request.headers_mut().insert(name, value);
+                    }
+                }
+                Err(e) => {
+                    tracing::error!("Not opening socket for conversation {}: {}", conversation_id, e);
+                    break;
+                }
+            }
+            // Resume from the last version we saw instead of replaying history
+            let resume_from = current_version.lock().ok().and_then(|v| v.clone());
+            if let Some(Ok(parents)) = resume_from.as_deref().map(|version| HeaderValue::from_str(&parents_header(version))) {
+                request.headers_mut().insert("Parents", parents);
+            }
+
+            tracing::info!("[BRAID] Opening socket: {}", url);
+            let _ = status_sender.send(SubscriptionStatus::Connecting);
+            let socket = match tokio_tungstenite::connect_async(request).await {
+                Ok((socket, _)) => socket,
+                // Retrying can't fix a rejected token or a conversation we're not in
+                Err(tungstenite::Error::Http(response)) if response.status() == 401 => {
+                    tracing::warn!("Socket for {} rejected our token; waiting for a refresh", conversation_id);
+                    auth_rejected.store(true, Ordering::Relaxed);
+                    let _ = status_sender.send(SubscriptionStatus::Unauthorized);
+                    break;
+                }
+                Err(tungstenite::Error::Http(response)) if response.status() == 403 => {
+                    tracing::error!("Not allowed to subscribe to conversation {}", conversation_id);
+                    let _ = status_sender.send(SubscriptionStatus::Error(
+                        "permanent failure: not a participant".to_string(),
+                    ));
+                    break;
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to open message socket (will retry): {}", e);
+                    let reason = match e {
+                        tungstenite::Error::Http(_) => DisconnectReason::Http,
+                        _ => DisconnectReason::Network,
+                    };
+                    let _ = status_sender.send(SubscriptionStatus::Error(format!("network: {}", e)));
+                    if !retry.after_failure(&mut backoff, reason).await {
+                        break;
+                    }
+                    continue;
+                }
+            };
+
+            tracing::info!("[BRAID] Socket open for conversation {}", conversation_id);
+            let _ = status_sender.send(SubscriptionStatus::Connected);
+            backoff.reset();
+            if let Some(report) = backoff.end_outage(ConnectionTransport::WebSocket, true) {
+                tokio::spawn(report_connection(config.clone(), report));
+            }
+            let client = Client::new();
+            if !catch_up(&config, &client, conversation_id, resume_from.as_deref(), &current_version, &message_sender).await {
+                return;
+            }
+
+            let (mut sink, mut stream) = socket.split();
+            let (frames_tx, mut frames_rx) = tokio::sync::mpsc::unbounded_channel();
+            if let Ok(mut frames) = socket_frames.lock() {
+                *frames = Some(frames_tx.clone());
+            }
+
+            // Pings go through the same loop as messages, so a wedged
+            // server can't answer them
+            let (pong_tx, pong_rx) = tokio::sync::watch::channel(0u64);
+            let mut ping_id = 0u64;
+            let stall = until_stalled(heartbeat, || {
+                ping_id += 1;
+                let id = ping_id;
+                let sent = frames_tx.send(ClientFrame::Ping { id }).is_ok();
+                let mut pongs = pong_rx.clone();
+                async move {
+                    let answered = sent && pongs.wait_for(|&last| last >= id).await.is_ok();
+                    answered
+                }
+            });
+            tokio::pin!(stall);
+
+            let mut connection_active = true;
+            let mut woken = false;
+            let mut stalled = false;
+            loop {
+                tokio::select! {
+                    _ = &mut stall => {
+                        stalled = true;
+                        break;
+                    }
+                    received = read_or_wake(&mut stream, &wake) => match received {
+                        StreamRead::Item(Ok(tungstenite::Message::Text(text))) => {
+                            match serde_json::from_str::<ServerFrame>(text.as_str()) {
+                                Ok(ServerFrame::Message(msg)) => {
+                                    record_version(&current_version, &msg);
+                                    if message_sender.send(msg).is_err() {
+                                        return;
+                                    }
+                                }
+                                Ok(ServerFrame::Pong { id }) => {
+                                    pong_tx.send_replace(id);
+                                }
+                                Ok(ServerFrame::Error { message }) => {
+                                    tracing::debug!("Server refused a socket frame: {}", message);
+                                }
+                                // Activity and receipts come from the activity stream
+                                Ok(ServerFrame::Event(_)) => {}
+                                Err(e) => tracing::warn!("Failed to parse socket frame: {}", e),
+                            }
+                        }
+                        StreamRead::Item(Ok(tungstenite::Message::Close(_))) | StreamRead::End => break,
+                        StreamRead::Item(Ok(_)) => {}
+                        StreamRead::Item(Err(e)) => {
+                            tracing::error!("Error reading from message socket: {}", e);
+                            connection_active = false;
+                            let _ = status_sender.send(SubscriptionStatus::Error(format!("stream: {}", e)));
+                            break;
+                        }
+                        StreamRead::Woken => {
+                            woken = true;
+                            break;
+                        }
+                    },
+                    Some(frame) = frames_rx.recv() => {
+                        let Ok(json) = serde_json::to_string(&frame) else { continue };
+                        if let Err(e) = sink.send(tungstenite::Message::Text(json.into())).await {
+                            tracing::error!("Error writing to message socket: {}", e);
+                            connection_active = false;
+                            let _ = status_sender.send(SubscriptionStatus::Error(format!("stream: {}", e)));
+                            break;
+                        }
+                    }
+                }
+                if stopped.load(Ordering::Relaxed) {
+                    return;
+                }
+            }
+
+            // Typing and receipts fall back to HTTP until the socket is back
+            if let Ok(mut frames) = socket_frames.lock() {
+                *frames = None;
+            }
+
+            if woken {
+                tracing::info!("Reconnecting conversation {} on request", conversation_id);
+                backoff.reset();
+            } else if stalled {
+                tracing::warn!("Server stopped answering pings for conversation {}, will reconnect", conversation_id);
+                let _ = status_sender.send(SubscriptionStatus::Stalled);
+                if !retry.after_failure(&mut backoff, DisconnectReason::Stalled).await {
+                    break;
+                }
+            } else if connection_active {
+                tracing::info!("Message socket closed normally for conversation {}", conversation_id);
+                let _ = status_sender.send(SubscriptionStatus::Disconnected);
+                break;
+            } else {
+                tracing::warn!("Message socket lost for conversation {}, will reconnect", conversation_id);
+                if !retry.after_failure(&mut backoff, DisconnectReason::Stream).await {
+                    break;
+                }
+            }
+        }
+    });
+}
+
+/// `ws://`/`wss://` form of an API URL
+fn socket_url(api_url: &str) -> String {
+    if let Some(rest) = api_url.strip_prefix("https://") {
+        format!("wss://{}", rest)
+    } else if let Some(rest) = api_url.strip_prefix("http://") {
+        format!("ws://{}", rest)
+    } else {
+        api_url.to_string()
+    }
+}
+
+/// `Parents` header value for a single version (Structured Headers string)
+fn parents_header(version: &str) -> String {
+    format!("\"{}\"", version.trim_matches('"'))
+}
+
+/// Remember the version of a streamed message for the next reconnect
+fn record_version(current_version: &Mutex<Option<String>>, msg: &ChatMessage) {
+    if msg.braid_version.is_empty() {
+        return;
+    }
+    if let Ok(mut current) = current_version.lock() {
+        *current = Some(msg.braid_version.clone());
+    }
+}
+
+/// Subscribe to typing/activity events, read/delivery receipts and presence on the realtime stream
+fn subscribe_to_activity_stream(
+    config: Config,
+    wake: Arc<Notify>,
+    activity_sender: Sender<ActivityEvent>,
+    receipt_sender: Sender<ReadReceiptEvent>,
+    delivery_sender: Sender<DeliveryReceiptEvent>,
+    presence_sender: Sender<PresenceEvent>,
+) {
+    let rt = match Runtime::new() {
+        Ok(rt) => rt,
+        Err(e) => {
+            tracing::error!("Failed to create runtime for activity subscription: {}", e);
+            return;
+        }
+    };
+
+    rt.block_on(async {
+        let client = Client::new();
+        let reconnect_delay = config.sync_interval(std::time::Duration::from_secs(5));
+
+        loop {
+            let url = config.api_url("/realtime?types=typing,read_receipt,delivery_receipt,presence");
+            let response = match client.get(&url).header("Subscribe", "true").send().await {
+                Ok(resp) if resp.status().is_success() => resp,
+                Ok(resp) => {
+                    tracing::debug!("Activity subscription failed with status: {}", resp.status());
+                    sleep_or_wake(reconnect_delay, &wake).await;
+                    continue;
+                }
+                Err(e) => {
+                    tracing::debug!("Activity subscription failed: {}", e);
+                    sleep_or_wake(reconnect_delay, &wake).await;
+                    continue;
+                }
+            };
+
+            let mut stream = response.bytes_stream();
+            let mut buffer = String::new();
+            let mut woken = false;
+            loop {
+                let chunk = match read_or_wake(&mut stream, &wake).await {
+                    StreamRead::Item(Ok(chunk)) => chunk,
+                    StreamRead::Item(Err(_)) | StreamRead::End => break,
+                    StreamRead::Woken => {
+                        woken = true;
+                        break;
+                    }
+                };
+                buffer.push_str(&String::from_utf8_lossy(&chunk));
+                while let Some(newline_pos) = buffer.find('\n') {
+                    let line = buffer[..newline_pos].trim_end_matches('\r').to_string();
+                    buffer = buffer[newline_pos + 1..].to_string();
+
+                    let Some(data) = line.strip_prefix("data: ") else { continue };
+                    let Ok(event) = serde_json::from_str::<RealtimeEvent>(data) else { continue };
+                    let delivered = if let Some(activity) = ActivityEvent::from_event(&event) {
+                        activity_sender.send(activity).is_ok()
+                    } else if let Some(receipt) = ReadReceiptEvent::from_event(&event) {
+                        receipt_sender.send(receipt).is_ok()
+                    } else if let Some(receipt) = DeliveryReceiptEvent::from_event(&event) {
+                        delivery_sender.send(receipt).is_ok()
+                    } else if let Some(presence) = PresenceEvent::from_event(&event) {
+                        presence_sender.send(presence).is_ok()
+                    } else {
+                        true
+                    };
+                    if !delivered {
+                        return;
+                    }
+                }
+            }
+
+            if !woken {
+                sleep_or_wake(reconnect_delay, &wake).await;
             }
         }
     });
 }
 
+/// Subscribe to one of the caller's own streams (`/sync/presence`,
+/// `/sync/conversations`), passing each `data:` line through `parse`
+fn subscribe_to_user_stream<T>(
+    config: Config,
+    path: &str,
+    stopped: Arc<AtomicBool>,
+    wake: Arc<Notify>,
+    sender: Sender<T>,
+    parse: fn(&str) -> Option<T>,
+) {
+    let rt = match Runtime::new() {
+        Ok(rt) => rt,
+        Err(e) => {
+            tracing::error!("Failed to create runtime for {} subscription: {}", path, e);
+            return;
+        }
+    };
+
+    rt.block_on(async {
+        let client = Client::new();
+        let reconnect_delay = config.sync_interval(std::time::Duration::from_secs(5));
+
+        while !stopped.load(Ordering::Relaxed) {
+            let url = config.api_url(path);
+            let req = match api_client::authorize(&config, client.get(&url).header("Subscribe", "true")) {
+                Ok(req) => req,
+                Err(e) => {
+                    tracing::debug!("Not subscribing to {}: {}", path, e);
+                    return;
+                }
+            };
+            let response = match req.send().await {
+                Ok(resp) if resp.status().is_success() => resp,
+                Ok(resp) => {
+                    tracing::debug!("{} subscription failed with status: {}", path, resp.status());
+                    sleep_or_wake(reconnect_delay, &wake).await;
+                    continue;
+                }
+                Err(e) => {
+                    tracing::debug!("{} subscription failed: {}", path, e);
+                    sleep_or_wake(reconnect_delay, &wake).await;
+                    continue;
+                }
+            };
+
+            let mut stream = response.bytes_stream();
+            let mut buffer = String::new();
+            let mut woken = false;
+            loop {
+                let chunk = match read_or_wake(&mut stream, &wake).await {
+                    StreamRead::Item(Ok(chunk)) => chunk,
+                    StreamRead::Item(Err(_)) | StreamRead::End => break,
+                    StreamRead::Woken => {
+                        woken = true;
+                        break;
+                    }
+                };
+                if stopped.load(Ordering::Relaxed) {
+                    return;
+                }
+                buffer.push_str(&String::from_utf8_lossy(&chunk));
+                while let Some(newline_pos) = buffer.find('\n') {
+                    let line = buffer[..newline_pos].trim_end_matches('\r').to_string();
+                    buffer = buffer[newline_pos + 1..].to_string();
+
+                    let Some(data) = line.strip_prefix("data: ") else { continue };
+                    let Some(item) = parse(data) else { continue };
+                    if sender.send(item).is_err() {
+                        return;
+                    }
+                }
+            }
+
+            if !woken {
+                sleep_or_wake(reconnect_delay, &wake).await;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::config::AppConfig;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Serve `responses` in order, returning the raw requests received
+    async fn serve(responses: Vec<String>) -> (String, tokio::task::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let handle = tokio::spawn(async move {
+            let mut requests = Vec::new();
+            for response in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = vec![0u8; 8192];
+                let n = socket.read(&mut buf).await.unwrap();
+                requests.push(String::from_utf8_lossy(&buf[..n]).to_string());
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+            requests
+        });
+        (url, handle)
+    }
+
+    /// Statuses reported until `last` arrives (or a few seconds pass)
+    async fn statuses_until(client: &MessageSyncClient, last: SubscriptionStatus) -> Vec<SubscriptionStatus> {
+        let mut statuses = Vec::new();
+        for _ in 0..300 {
+            while let Some(status) = client.poll_status() {
+                let done = status == last;
+                statuses.push(status);
+                if done {
+                    return statuses;
+                }
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        statuses
+    }
+
+    #[test]
+    fn test_jitter_stays_within_bounds() {
+        let policy = ReconnectPolicy::default();
+        let delay = Duration::from_secs(10);
+        assert_eq!(policy.jittered(delay, 0.0), Duration::from_secs(8));
+        assert_eq!(policy.jittered(delay, 0.5), delay);
+        assert!(policy.jittered(delay, 0.999_999) <= Duration::from_secs(12));
+
+        for _ in 0..100 {
+            let unit = random_unit();
+            assert!((0.0..1.0).contains(&unit));
+        }
+    }
+
+    #[test]
+    fn test_parents_header_tracks_streamed_versions() {
+        let current_version = Mutex::new(None);
+        let mut msg = ChatMessage::new_text(Uuid::new_v4(), Uuid::new_v4(), "hi".to_string(), 0);
+
+        msg.braid_version = "v1".to_string();
+        record_version(&current_version, &msg);
+        msg.braid_version = String::new();
+        record_version(&current_version, &msg);
+
+        let version = current_version.lock().unwrap().clone().unwrap();
+        assert_eq!(version, "v1");
+        assert_eq!(parents_header(&version), "\"v1\"");
+        assert_eq!(parents_header("\"v2\""), "\"v2\"");
+    }
+
+    #[test]
+    fn test_socket_url_swaps_scheme() {
+        assert_eq!(socket_url("http://localhost:3000/ws"), "ws://localhost:3000/ws");
+        assert_eq!(socket_url("https://chat.example.com/ws"), "wss://chat.example.com/ws");
+    }
+
+    #[test]
+    fn test_frames_need_an_open_socket() {
+        let mut client = MessageSyncClient::default();
+        client.transport = Transport::WebSocket;
+        let conversation_id = Uuid::new_v4();
+        let read = ClientFrame::Read { conversation_id, message_id: Uuid::new_v4() };
+        assert!(!client.send_frame(read.clone()));
+
+        // No token, so the thread gives up without opening a socket
+        client.subscribe_to_conversation(conversation_id);
+        assert!(!client.send_frame(read.clone()));
+
+        let (frames, mut received) = tokio::sync::mpsc::unbounded_channel();
+        *client.subscription.as_ref().unwrap().frames.lock().unwrap() = Some(frames);
+        assert!(client.send_frame(read.clone()));
+        assert_eq!(received.try_recv().unwrap(), read);
+
+        client.transport = Transport::Sse;
+        assert!(!client.send_frame(read));
+    }
+
+    #[test]
+    fn test_backoff_doubles_caps_and_gives_up() {
+        let policy = ReconnectPolicy {
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(4),
+            jitter: 0.0,
+            max_attempts: 4,
+        };
+        let mut backoff = Backoff::new(policy);
+
+        let delays: Vec<_> = std::iter::from_fn(|| backoff.next_delay()).collect();
+        assert_eq!(delays, [1, 2, 4, 4].map(Duration::from_secs));
+        assert_eq!(backoff.next_delay(), None);
+
+        backoff.reset();
+        assert_eq!(backoff.next_delay(), Some(Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn test_outage_is_reported_once_connected_again() {
+        let mut backoff = Backoff::new(ReconnectPolicy { jitter: 0.0, ..ReconnectPolicy::default() });
+        assert_eq!(backoff.end_outage(ConnectionTransport::Sse, true), None);
+
+        backoff.failed(DisconnectReason::Stalled);
+        backoff.failed(DisconnectReason::Network);
+        backoff.reset();
+        let report = backoff.end_outage(ConnectionTransport::Sse, true).unwrap();
+        assert_eq!((report.reason, report.attempts, report.recovered), (DisconnectReason::Stalled, 2, true));
+        assert_eq!(backoff.end_outage(ConnectionTransport::Sse, true), None);
+    }
+    #[test]
+    fn test_wake_cuts_backoff_short_and_starts_it_over() {
+        let policy = ReconnectPolicy { jitter: 0.0, ..ReconnectPolicy::default() };
+        let mut backoff = Backoff::new(policy);
+        let delay = (0..4).filter_map(|_| backoff.next_delay()).last().unwrap();
+        assert_eq!(delay, Duration::from_secs(8));
+
+        let wake = Arc::new(Notify::new());
+        let waker = Arc::clone(&wake);
+        let started = std::time::Instant::now();
+        Runtime::new().unwrap().block_on(async {
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                waker.notify_waiters();
+            });
+            backoff.sleep(Duration::from_secs(60), &wake).await;
+        });
+
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(backoff.attempts, 0);
+        assert_eq!(backoff.next_delay(), Some(policy.base_delay));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_unauthorized_waits_for_refreshed_token() {
+        let message = ChatMessage::new_text(Uuid::new_v4(), Uuid::new_v4(), "hi".to_string(), 0);
+        let unauthorized = "HTTP/1.1 401 Unauthorized\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string();
+        let stream = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nConnection: close\r\n\r\ndata: {}\n\n",
+            serde_json::to_string(&message).unwrap()
+        );
+        let (url, server) = serve(vec![unauthorized, stream]).await;
+        let mut config = Config::with_builder(AppConfig::builder().server_url(url)).unwrap();
+        config.set_token(Some("expired".to_string()));
+        let policy = ReconnectPolicy { base_delay: Duration::from_millis(10), ..ReconnectPolicy::default() };
+        let mut client = MessageSyncClient::new(config, policy);
+
+        // A 401 stops the subscription instead of backing off and retrying
+        client.subscribe_to_conversation(message.conversation_id);
+        let statuses = statuses_until(&client, SubscriptionStatus::Unauthorized).await;
+        assert_eq!(statuses, vec![SubscriptionStatus::Connecting, SubscriptionStatus::Unauthorized]);
+        for _ in 0..100 {
+            if client.subscription.as_ref().is_some_and(ConversationSubscription::is_finished) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(client.subscription.as_ref().unwrap().is_finished());
+
+        // Waking doesn't retry with the rejected token either
+        client.reconnect_now();
+        assert!(client.subscription.as_ref().unwrap().is_finished());
+
+        // The refreshed token resubscribes
+        client.set_token(Some("refreshed".to_string()));
+        let statuses = statuses_until(&client, SubscriptionStatus::Disconnected).await;
+        assert!(statuses.contains(&SubscriptionStatus::Connected));
+        assert_eq!(client.poll_messages().into_iter().map(|m| m.id).collect::<Vec<_>>(), vec![message.id]);
+
+        let requests = server.await.unwrap();
+        assert!(requests[0].to_lowercase().contains("authorization: bearer expired"));
+        assert!(requests[1].to_lowercase().contains("authorization: bearer refreshed"));
+    }
+
+    #[test]
+    fn test_resubscribing_stops_the_old_thread() {
+        let mut client = MessageSyncClient::default();
+        client.subscribe_to_conversation(Uuid::new_v4());
+        let old = Arc::clone(&client.subscription.as_ref().unwrap().stopped);
+
+        client.subscribe_to_conversation(Uuid::new_v4());
+        assert!(old.load(Ordering::Relaxed));
+        let current = Arc::clone(&client.subscription.as_ref().unwrap().stopped);
+        assert!(!current.load(Ordering::Relaxed));
+
+        drop(client);
+        assert!(current.load(Ordering::Relaxed));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_idle_old_subscription_exits_on_resubscribe() {
+        // Opens every stream, then never sends on it
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut held = Vec::new();
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = vec![0u8; 8192];
+                let _ = socket.read(&mut buf).await;
+                let _ = socket.write_all(b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\n\r\n").await;
+                held.push(socket);
+            }
+        });
+        let mut config = Config::with_builder(AppConfig::builder().server_url(url)).unwrap();
+        config.set_token(Some("token".to_string()));
+        let mut client = MessageSyncClient::new(config, ReconnectPolicy::default());
+
+        client.subscribe_to_conversation(Uuid::new_v4());
+        statuses_until(&client, SubscriptionStatus::Connected).await;
+        let old = Arc::clone(&client.subscription.as_ref().unwrap().stopped);
+
+        client.subscribe_to_conversation(Uuid::new_v4());
+        statuses_until(&client, SubscriptionStatus::Connected).await;
+        let current = Arc::clone(&client.subscription.as_ref().unwrap().stopped);
+
+        // The thread's own handle on the flag goes away when it exits
+        for _ in 0..100 {
+            if Arc::strong_count(&old) == 1 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(Arc::strong_count(&old), 1);
+
+        // Reconnecting only touches the active subscription
+        client.reconnect_now();
+        let statuses = statuses_until(&client, SubscriptionStatus::Connected).await;
+        assert_eq!(statuses, vec![SubscriptionStatus::Connecting, SubscriptionStatus::Connected]);
+        // Still held by the client, its thread and this test
+        assert_eq!(Arc::strong_count(&current), 3);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_missed_heartbeats_report_a_stalled_server() {
+        // Opens the stream, then never sends on it or answers anything else
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut held = Vec::new();
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = vec![0u8; 8192];
+                let n = socket.read(&mut buf).await.unwrap_or(0);
+                if String::from_utf8_lossy(&buf[..n]).contains("/messages") {
+                    let _ = socket.write_all(b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\n\r\n").await;
+                }
+                held.push(socket);
+            }
+        });
+        let mut config = Config::with_builder(AppConfig::builder().server_url(url)).unwrap();
+        config.set_token(Some("token".to_string()));
+        let mut client = MessageSyncClient::new(config, ReconnectPolicy::default());
+        client.heartbeat = Some(HeartbeatPolicy {
+            interval: Duration::from_millis(50),
+            timeout: Duration::from_millis(50),
+            max_missed: 2,
+        });
+
+        client.subscribe_to_conversation(Uuid::new_v4());
+        let statuses = statuses_until(&client, SubscriptionStatus::Stalled).await;
+        assert_eq!(
+            statuses,
+            vec![SubscriptionStatus::Connecting, SubscriptionStatus::Connected, SubscriptionStatus::Stalled]
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_send_over_quota_is_limit_reached() {
+        let exceeded = UsageLimitExceeded {
+            error: crate::shared::messaging::MESSAGE_QUOTA_ERROR.to_string(),
+            limit: 1000,
+            used: 1000,
+            resets_at: crate::shared::messaging::timestamp::parse("2024-05-02T00:00:00+00:00").unwrap(),
+        };
+        let body = serde_json::to_string(&exceeded).unwrap();
+        let too_many = format!(
+            "HTTP/1.1 429 Too Many Requests\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        let unavailable = "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string();
+        let (url, _server) = serve(vec![too_many, unavailable]).await;
+        let mut config = Config::with_builder(AppConfig::builder().server_url(url)).unwrap();
+        config.set_token(Some("token".to_string()));
+        let mut client = MessageSyncClient::new(config, ReconnectPolicy::default());
+
+        let results = tokio::task::spawn_blocking(move || {
+            let conversation_id = Uuid::new_v4();
+            let mut send = || client.send_message(conversation_id, "hi".to_string(), &MessageType::Text, None);
+            (send(), send())
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(results.0, Err(SendError::LimitReached(exceeded)));
+        assert!(matches!(results.1, Err(SendError::Failed(_))));
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_catches_up_on_missed_messages() {
+        let conversation_id = Uuid::new_v4();
+        let mut missed = ChatMessage::new_text(conversation_id, Uuid::new_v4(), "missed".to_string(), 2);
+        missed.braid_version = "v2".to_string();
+        let body = serde_json::to_string(&vec![missed.clone()]).unwrap();
+        let ok = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        let (url, server) = serve(vec![ok]).await;
+        let mut config = Config::with_builder(AppConfig::builder().server_url(url)).unwrap();
+        config.set_token(Some("token".to_string()));
+        let client = Client::new();
+        let current_version = Mutex::new(Some("v1".to_string()));
+        let (sender, receiver) = mpsc::channel();
+
+        // A first connection has nothing to catch up on
+        assert!(catch_up(&config, &client, conversation_id, None, &current_version, &sender).await);
+        assert!(receiver.try_recv().is_err());
+
+        assert!(catch_up(&config, &client, conversation_id, Some("v1"), &current_version, &sender).await);
+        assert_eq!(receiver.try_recv().unwrap().id, missed.id);
+        assert_eq!(current_version.lock().unwrap().as_deref(), Some("v2"));
+        let requests = server.await.unwrap();
+        assert!(requests[0].starts_with(&format!("GET /sync/conversations/{}/messages/since?version=v1 ", conversation_id)));
+    }
+}
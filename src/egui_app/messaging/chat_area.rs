@@ -47,6 +47,13 @@ fn render_active_chat(ui: &mut egui::Ui, state: &mut MessagingState) {
             },
         );
         
+        // "Alice is recording audio…" above the input bar
+        if let Some(indicator) = state.remote_activity.indicator(std::time::Instant::now()) {
+            ui.label(egui::RichText::new(indicator).italics().color(colors::TEXT_SECONDARY));
+            // Repaint without input so the indicator disappears when it expires
+            ui.ctx().request_repaint_after(std::time::Duration::from_secs(1));
+        }
+
         // Input bar at bottom
         input_bar::render(ui, state, true); // TODO: Pass actual online status
     });
@@ -0,0 +1,205 @@
+//! Slash Commands
+//!
+//! A draft starting with `/` is a command: `/name args`. The composer shows
+//! the matching commands while the name is being typed, and on send the
+//! command turns the draft into the message that is actually sent, e.g.
+//! `/me waves` becomes an [`MessageType::Action`] with content `waves`.
+//!
+//! Commands live in a [`CommandRegistry`]; [`CommandRegistry::default`] has
+//! the built-ins and more can be added with [`CommandRegistry::register`].
+//! Start a message with `//` to send a literal leading `/`.
+
+use crate::shared::messaging::MessageType;
+
+/// Message produced by a command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandOutput {
+    pub content: String,
+    pub message_type: MessageType,
+}
+
+impl CommandOutput {
+    /// Plain text message
+    pub fn text(content: impl Into<String>) -> Self {
+        Self { content: content.into(), message_type: MessageType::Text }
+    }
+}
+
+/// A command available in the composer
+#[derive(Debug, Clone, Copy)]
+pub struct SlashCommand {
+    /// Name without the slash
+    pub name: &'static str,
+    /// Argument hint shown in the menu, e.g. `<action>`
+    pub usage: &'static str,
+    pub description: &'static str,
+    /// Turn the arguments into a message, or explain why they're invalid
+    pub run: fn(&str) -> Result<CommandOutput, String>,
+}
+
+/// `/name args` split from a draft; `None` if the draft isn't a command
+pub fn parse_command(input: &str) -> Option<(&str, &str)> {
+    let rest = input.trim_start().strip_prefix('/')?;
+    if rest.starts_with('/') {
+        return None;
+    }
+    let name_end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+    Some((&rest[..name_end], rest[name_end..].trim()))
+}
+
+/// Command name being typed, while the cursor is still on it
+///
+/// Used to decide when to show the command menu.
+pub fn typing_command_name(input: &str) -> Option<&str> {
+    let (name, _) = parse_command(input)?;
+    let typed = input.trim_start();
+    (typed.len() == name.len() + 1).then_some(name)
+}
+
+/// Known commands
+#[derive(Debug, Clone)]
+pub struct CommandRegistry {
+    commands: Vec<SlashCommand>,
+}
+
+impl Default for CommandRegistry {
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        registry.register(SlashCommand {
+            name: "me",
+            usage: "<action>",
+            description: "Send an action, e.g. \"* Alice waves\"",
+            run: me,
+        });
+        registry.register(SlashCommand {
+            name: "shrug",
+            usage: "[message]",
+            description: "Append ¯\\_(ツ)_/¯ to your message",
+            run: shrug,
+        });
+        registry.register(SlashCommand {
+            name: "giphy",
+            usage: "<search>",
+            description: "Share a GIPHY search link",
+            run: giphy,
+        });
+        registry
+    }
+}
+
+impl CommandRegistry {
+    /// Registry without the built-in commands
+    pub fn empty() -> Self {
+        Self { commands: Vec::new() }
+    }
+
+    /// Add a command, replacing any existing one with the same name
+    pub fn register(&mut self, command: SlashCommand) {
+        self.commands.retain(|c| c.name != command.name);
+        self.commands.push(command);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&SlashCommand> {
+        self.commands.iter().find(|c| c.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Commands whose name starts with `prefix`, sorted by name
+    pub fn matching(&self, prefix: &str) -> Vec<&SlashCommand> {
+        let prefix = prefix.to_lowercase();
+        let mut matches: Vec<&SlashCommand> = self.commands.iter().filter(|c| c.name.starts_with(&prefix)).collect();
+        matches.sort_by_key(|c| c.name);
+        matches
+    }
+
+    /// Turn a draft into the message to send
+    ///
+    /// Drafts that aren't commands are sent as text (with a leading `//`
+    /// unescaped to `/`); unknown commands and bad arguments are errors.
+    pub fn apply(&self, input: &str) -> Result<CommandOutput, String> {
+        let Some((name, args)) = parse_command(input) else {
+            let text = input.trim();
+            return Ok(CommandOutput::text(text.strip_prefix('/').filter(|t| t.starts_with('/')).unwrap_or(text)));
+        };
+        let command = self.get(name).ok_or_else(|| format!("Unknown command /{}", name))?;
+        (command.run)(args)
+    }
+}
+
+/// `/me waves` → action "waves"
+fn me(args: &str) -> Result<CommandOutput, String> {
+    if args.is_empty() {
+        return Err("Usage: /me <action>".to_string());
+    }
+    Ok(CommandOutput { content: args.to_string(), message_type: MessageType::Action })
+}
+
+fn shrug(args: &str) -> Result<CommandOutput, String> {
+    const SHRUG: &str = "¯\\_(ツ)_/¯";
+    Ok(CommandOutput::text(if args.is_empty() { SHRUG.to_string() } else { format!("{} {}", args, SHRUG) }))
+}
+
+/// There's no GIPHY API key to pick a GIF with, so this shares a search
+/// link and lets the link preview show it
+fn giphy(args: &str) -> Result<CommandOutput, String> {
+    let terms: Vec<String> = args
+        .split_whitespace()
+        .map(|word| word.chars().filter(|c| c.is_alphanumeric()).collect::<String>().to_lowercase())
+        .filter(|word| !word.is_empty())
+        .collect();
+    if terms.is_empty() {
+        return Err("Usage: /giphy <search>".to_string());
+    }
+    Ok(CommandOutput::text(format!("https://giphy.com/search/{}", terms.join("-"))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(parse_command("/me waves hello"), Some(("me", "waves hello")));
+        assert_eq!(parse_command("  /shrug"), Some(("shrug", "")));
+        assert_eq!(parse_command("/"), Some(("", "")));
+        assert_eq!(parse_command("hello /me"), None);
+        assert_eq!(parse_command("//not a command"), None);
+
+        assert_eq!(typing_command_name("/sh"), Some("sh"));
+        assert_eq!(typing_command_name("/"), Some(""));
+        assert_eq!(typing_command_name("/shrug ok"), None);
+    }
+
+    #[test]
+    fn test_me_becomes_action() {
+        let registry = CommandRegistry::default();
+        assert_eq!(
+            registry.apply("/me waves at everyone"),
+            Ok(CommandOutput { content: "waves at everyone".to_string(), message_type: MessageType::Action })
+        );
+        assert_eq!(registry.apply("/ME waves").unwrap().message_type, MessageType::Action);
+        assert!(registry.apply("/me").is_err());
+    }
+
+    #[test]
+    fn test_registry() {
+        let mut registry = CommandRegistry::default();
+        assert_eq!(registry.apply("/shrug fine").unwrap().content, "fine ¯\\_(ツ)_/¯");
+        assert_eq!(registry.apply("/giphy Happy cat!").unwrap().content, "https://giphy.com/search/happy-cat");
+        assert_eq!(registry.apply("/nope"), Err("Unknown command /nope".to_string()));
+        assert_eq!(registry.apply("  hello  "), Ok(CommandOutput::text("hello")));
+        assert_eq!(registry.apply("//shrug"), Ok(CommandOutput::text("/shrug")));
+
+        let names: Vec<&str> = registry.matching("").iter().map(|c| c.name).collect();
+        assert_eq!(names, ["giphy", "me", "shrug"]);
+
+        registry.register(SlashCommand {
+            name: "tableflip",
+            usage: "",
+            description: "(╯°□°)╯︵ ┻━┻",
+            run: |_| Ok(CommandOutput::text("(╯°□°)╯︵ ┻━┻")),
+        });
+        let names: Vec<&str> = registry.matching("t").iter().map(|c| c.name).collect();
+        assert_eq!(names, ["tableflip"]);
+        assert_eq!(registry.apply("/tableflip").unwrap().content, "(╯°□°)╯︵ ┻━┻");
+    }
+}
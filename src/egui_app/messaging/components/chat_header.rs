@@ -1,11 +1,15 @@
 //! Chat Header Component
 //!
-//! Displays the header of the chat area with contact info and actions.
+//! Displays the header of the chat area with contact info and actions. The
+//! header uses the conversation's theme color if the user picked one in the
+//! chat menu.
 
 use eframe::egui;
 use crate::egui_app::messaging::state::MessagingState;
 use crate::egui_app::theme::colors;
 use crate::egui_app::messaging::braid_sync::SubscriptionStatus;
+use crate::egui_app::local_db::export::ExportFormat;
+use crate::shared::messaging::ThemeColor;
 
 /// Render the chat header
 pub fn render(ui: &mut egui::Ui, state: &mut MessagingState) {
@@ -14,19 +18,51 @@ pub fn render(ui: &mut egui::Ui, state: &mut MessagingState) {
         None => return,
     };
     
-    // Find the other participant's contact info
+    let conversation_id = conversation.id;
+    let current_color = conversation.theme_color;
+    let theme = state.conversation_theme(conversation_id);
+
+    // Groups show their title; direct chats the other participant's contact info
+    let group = conversation.is_group()
+        .then(|| (conversation.display_title(), conversation.participants.len()));
     let other_contact = state.contacts.iter()
-        .find(|c| conversation.participants.contains(&c.contact_user_id));
+        .find(|c| conversation.participants.contains(&c.contact_user_id))
+        .cloned();
+    // Suggested file name for exports
+    let export_name = group.as_ref().map(|(title, _)| title.clone())
+        .or_else(|| other_contact.as_ref().map(|c| c.username.clone()))
+        .unwrap_or_else(|| "conversation".to_string())
+        .replace(['/', '\\'], "_");
+    // Contacts who could be added to the group
+    let addable: Vec<_> = state.contacts.iter()
+        .filter(|c| !conversation.participants.contains(&c.contact_user_id))
+        .map(|c| (c.contact_user_id, c.display_name.clone().unwrap_or_else(|| c.username.clone())))
+        .collect();
     
     egui::Frame::new()
-        .fill(colors::CHAT_HEADER_BG)
+        .fill(theme.header_bg)
         .inner_margin(egui::Margin::same(12))
         .show(ui, |ui| {
             ui.set_min_width(ui.available_width());
             
             ui.horizontal(|ui| {
                 // Avatar
-                if let Some(contact) = other_contact {
+                if let Some((title, members)) = &group {
+                    egui::Frame::new()
+                        .fill(colors::ACCENT)
+                        .corner_radius(egui::CornerRadius::same(18))
+                        .inner_margin(egui::Margin::same(8))
+                        .show(ui, |ui| {
+                            ui.label(egui::RichText::new("👥").color(egui::Color32::WHITE).strong());
+                        });
+
+                    ui.add_space(8.0);
+
+                    ui.vertical(|ui| {
+                        ui.label(egui::RichText::new(title).strong().size(16.0));
+                        ui.colored_label(colors::TEXT_SECONDARY, format!("{} members", members));
+                    });
+                } else if let Some(contact) = &other_contact {
                     let avatar_text = contact.username.chars().next()
                         .map(|c| c.to_uppercase().to_string())
                         .unwrap_or_else(|| "?".to_string());
@@ -87,6 +123,8 @@ pub fn render(ui: &mut egui::Ui, state: &mut MessagingState) {
                         Some(SubscriptionStatus::Connected) => ("Connected", egui::Color32::from_rgb(22, 163, 74)),
                         Some(SubscriptionStatus::Retrying) => ("Retrying", egui::Color32::from_rgb(234, 179, 8)),
                         Some(SubscriptionStatus::Connecting) => ("Connecting", egui::Color32::from_rgb(59, 130, 246)),
+                        Some(SubscriptionStatus::Unauthorized) => ("Signing in", egui::Color32::from_rgb(234, 179, 8)),
+                        Some(SubscriptionStatus::Stalled) => ("Stalled", egui::Color32::from_rgb(234, 179, 8)),
                         Some(SubscriptionStatus::Error(_)) => ("Error", egui::Color32::from_rgb(220, 38, 38)),
                         Some(SubscriptionStatus::Disconnected) => ("Disconnected", egui::Color32::from_rgb(107, 114, 128)),
                         None => ("—", egui::Color32::from_rgb(107, 114, 128)),
@@ -111,18 +149,66 @@ pub fn render(ui: &mut egui::Ui, state: &mut MessagingState) {
                             .show(ui.ctx(), |ui| {
                                 ui.set_min_width(150.0);
                                 ui.vertical(|ui| {
+                                    if group.is_some() {
+                                        ui.menu_button("Add People", |ui| {
+                                            if addable.is_empty() {
+                                                ui.label("All your contacts are in this group");
+                                            }
+                                            for (user_id, name) in &addable {
+                                                if ui.button(name).clicked() {
+                                                    state.add_participant(conversation_id, *user_id);
+                                                    ui.close();
+                                                }
+                                            }
+                                        });
+                                        if ui.button("Leave Group").clicked() {
+                                            state.show_chat_header_menu = false;
+                                            state.leave_conversation(conversation_id);
+                                        }
+                                        ui.separator();
+                                    }
+                                    if ui.button("Reload messages").clicked() {
+                                        state.show_chat_header_menu = false;
+                                        state.request_resync(conversation_id);
+                                    }
+                                    if ui.button("Summarize unread").clicked() {
+                                        state.show_chat_header_menu = false;
+                                        state.request_unread_summary(conversation_id);
+                                    }
+                                    ui.menu_button("Export Conversation", |ui| {
+                                        for (label, format) in [("JSON…", ExportFormat::Json), ("CSV…", ExportFormat::Csv)] {
+                                            if ui.button(label).clicked() {
+                                                ui.close();
+                                                state.show_chat_header_menu = false;
+                                                let path = rfd::FileDialog::new()
+                                                    .set_file_name(format!("{}.{}", export_name, format.extension()))
+                                                    .add_filter(label.trim_end_matches('…'), &[format.extension()])
+                                                    .save_file();
+                                                if let Some(path) = path {
+                                                    state.export_conversation(conversation_id, format, path);
+                                                }
+                                            }
+                                        }
+                                    });
                                     if ui.button("View Profile").clicked() {
                                         state.show_chat_header_menu = false;
                                         // TODO: Implement profile view
                                     }
-                                    if ui.button("Block User").clicked() {
-                                        state.show_chat_header_menu = false;
-                                        // TODO: Implement block functionality
+                                    if let Some(contact) = other_contact.as_ref().filter(|_| group.is_none()) {
+                                        if ui.button("Block User").clicked() {
+                                            state.show_chat_header_menu = false;
+                                            state.block_user(contact.contact_user_id);
+                                        }
                                     }
                                     if ui.button("Clear Chat").clicked() {
                                         state.show_chat_header_menu = false;
                                         // TODO: Implement clear chat functionality
                                     }
+
+                                    ui.separator();
+                                    if let Some(color) = render_color_picker(ui, current_color) {
+                                        state.set_theme_color(conversation_id, color);
+                                    }
                                 });
                             });
                         if !open {
@@ -134,3 +220,29 @@ pub fn render(ui: &mut egui::Ui, state: &mut MessagingState) {
         });
 }
 
+/// Swatches for the conversation's theme color
+///
+/// Returns the new color if one was picked; `Some(None)` resets to the
+/// global theme.
+fn render_color_picker(ui: &mut egui::Ui, current: Option<ThemeColor>) -> Option<Option<ThemeColor>> {
+    let mut picked = None;
+
+    ui.label("Chat Color");
+    ui.horizontal_wrapped(|ui| {
+        for (name, color) in colors::CONVERSATION_PRESETS {
+            let (rect, response) = ui.allocate_exact_size(egui::vec2(20.0, 20.0), egui::Sense::click());
+            ui.painter().circle_filled(rect.center(), 8.0, egui::Color32::from_rgb(color.r, color.g, color.b));
+            if current == Some(*color) {
+                ui.painter().circle_stroke(rect.center(), 9.5, egui::Stroke::new(2.0, colors::TEXT_PRIMARY));
+            }
+            if response.on_hover_text(*name).clicked() {
+                picked = Some(Some(*color));
+            }
+        }
+    });
+    if ui.add_enabled(current.is_some(), egui::Button::new("Default Color")).clicked() {
+        picked = Some(None);
+    }
+
+    picked
+}
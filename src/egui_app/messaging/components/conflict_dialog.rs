@@ -0,0 +1,89 @@
+//! Conflict Dialog Component
+//!
+//! Modal shown when a CRDT merge reports a conflict it can't settle on its
+//! own. Shows our version and the remote one side by side and asks which to
+//! keep.
+
+use eframe::egui;
+use crate::egui_app::crdt::{message_crdt, MergeResult, Resolution};
+use crate::egui_app::theme::colors;
+
+/// Render the dialog for `conflict`; returns the user's choice, if made
+///
+/// `remaining` is the number of conflicts waiting, including this one.
+pub fn render(ctx: &egui::Context, conflict: &MergeResult, remaining: usize) -> Option<Resolution> {
+    let MergeResult::Conflict { description, local_data, remote_data } = conflict else {
+        return None;
+    };
+    let (mine, theirs) = sides(local_data, remote_data);
+    let mut choice = None;
+
+    egui::Window::new("Resolve Conflict")
+        .collapsible(false)
+        .resizable(true)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(ctx, |ui| {
+            ui.set_min_width(480.0);
+
+            ui.label(description);
+            if remaining > 1 {
+                ui.colored_label(colors::TEXT_SECONDARY, format!("{} more after this one", remaining - 1));
+            }
+            ui.add_space(8.0);
+
+            ui.columns(2, |columns| {
+                side(&mut columns[0], "Mine", &mine);
+                side(&mut columns[1], "Theirs", &theirs);
+            });
+            ui.add_space(8.0);
+
+            ui.horizontal(|ui| {
+                if ui.button("Keep Mine").clicked() {
+                    choice = Some(Resolution::KeepMine);
+                }
+                if ui.button("Keep Theirs").clicked() {
+                    choice = Some(Resolution::KeepTheirs);
+                }
+                if ui.button("Keep Both").clicked() {
+                    choice = Some(Resolution::KeepBoth);
+                }
+            });
+        });
+
+    choice
+}
+
+fn side(ui: &mut egui::Ui, title: &str, text: &str) {
+    ui.strong(title);
+    egui::ScrollArea::vertical()
+        .id_salt(title)
+        .max_height(240.0)
+        .show(ui, |ui| {
+            ui.label(egui::RichText::new(text).monospace());
+        });
+}
+
+/// Readable text for both sides of a conflict
+///
+/// Message conflicts show just the messages that differ; anything else is
+/// shown as pretty-printed JSON when it decodes, or its size when it doesn't.
+fn sides(local_data: &[u8], remote_data: &[u8]) -> (String, String) {
+    if let Ok(entries) = message_crdt::conflicting_entries(local_data, remote_data) {
+        let (mine, theirs): (Vec<_>, Vec<_>) = entries
+            .into_iter()
+            .map(|(_, ours, theirs)| (ours.content, theirs.content))
+            .unzip();
+        return (mine.join("\n\n"), theirs.join("\n\n"));
+    }
+    (raw(local_data), raw(remote_data))
+}
+
+fn raw(data: &[u8]) -> String {
+    if data.is_empty() {
+        return "(nothing)".to_string();
+    }
+    serde_json::from_slice::<serde_json::Value>(data)
+        .ok()
+        .and_then(|value| serde_json::to_string_pretty(&value).ok())
+        .unwrap_or_else(|| format!("({} bytes)", data.len()))
+}
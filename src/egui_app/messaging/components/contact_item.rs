@@ -1,20 +1,58 @@
 //! Contact Item Component
 //!
-//! A single contact item in the contact list showing username, last message preview, and time.
+//! A single contact item in the contact list showing avatar, username, last
+//! message preview, and time. Pinned items can be dragged onto each other to
+//! reorder them.
 
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
 use eframe::egui;
-use crate::shared::messaging::{Contact, ChatMessage};
+use uuid::Uuid;
+use crate::shared::messaging::{display_text, Contact, ChatMessage, PresenceStatus};
+use crate::egui_app::notifications::NotificationLevel;
 use crate::egui_app::theme::colors;
 
+/// What the user did with a contact item this frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContactItemAction {
+    None,
+    Open,
+    MarkUnread,
+    SetNotificationLevel(NotificationLevel),
+    /// Pin if unpinned, unpin if pinned
+    TogglePin,
+    /// This pinned conversation was dropped onto this item
+    DropPinned(Uuid),
+}
+
+/// Drag payload for reordering pinned conversations
+struct PinnedDrag(Uuid);
+
+/// Size of the avatar circle
+const AVATAR_SIZE: f32 = 40.0;
+
 /// Render a single contact item
-/// Returns true if the item was clicked
+///
+/// `badge` is the unread badge text (a count, or a dot for manually-unread).
+/// `pinned` is the conversation ID if it is pinned, which makes the item
+/// draggable onto other pinned items. `avatar` is the downloaded avatar
+/// image and its URL; without it the contact's initial is shown. Contacts
+/// who are online get a dot on their avatar in their status's colour, with
+/// their status text on hover.
+#[allow(clippy::too_many_arguments)]
 pub fn render(
     ui: &mut egui::Ui,
     contact: &Contact,
+    avatar: Option<(&str, Arc<[u8]>)>,
+    status: (PresenceStatus, Option<&str>),
     last_message: Option<&ChatMessage>,
     is_selected: bool,
-) -> bool {
-    let mut clicked = false;
+    badge: Option<&str>,
+    notification_level: NotificationLevel,
+    pinned: Option<Uuid>,
+) -> ContactItemAction {
+    let mut action = ContactItemAction::None;
 
     // Background color based on selection
     let bg_color = if is_selected {
@@ -30,18 +68,7 @@ pub fn render(
             ui.set_min_width(ui.available_width());
 
             ui.horizontal(|ui| {
-                // Avatar placeholder (first letter of username)
-                let avatar_text = contact.username.chars().next()
-                    .map(|c| c.to_uppercase().to_string())
-                    .unwrap_or_else(|| "?".to_string());
-
-                egui::Frame::new()
-                    .fill(colors::ACCENT)
-                    .corner_radius(egui::CornerRadius::same(20))
-                    .inner_margin(egui::Margin::same(10))
-                    .show(ui, |ui| {
-                        ui.label(egui::RichText::new(avatar_text).color(egui::Color32::WHITE).strong());
-                    });
+                render_avatar(ui, contact, avatar, status);
 
                 ui.add_space(8.0);
 
@@ -52,6 +79,12 @@ pub fn render(
                         let display_name = contact.display_name.as_ref()
                             .unwrap_or(&contact.username);
                         ui.label(egui::RichText::new(display_name).strong());
+                        if notification_level == NotificationLevel::Muted {
+                            ui.colored_label(colors::TEXT_SECONDARY, "🔕");
+                        }
+                        if pinned.is_some() {
+                            ui.colored_label(colors::TEXT_SECONDARY, "📌");
+                        }
 
                         // Time of last message
                         if let Some(msg) = last_message {
@@ -62,21 +95,68 @@ pub fn render(
                         }
                     });
 
-                    // Last message preview
-                    if let Some(msg) = last_message {
-                        let preview = truncate_message(&msg.content, 40);
-                        ui.colored_label(colors::TEXT_SECONDARY, preview);
-                    } else {
-                        ui.colored_label(colors::TEXT_SECONDARY, "No messages yet");
-                    }
+                    ui.horizontal(|ui| {
+                        // Last message preview
+                        if let Some(msg) = last_message {
+                            let preview = truncate_message(&display_text(&msg.content), 40);
+                            ui.colored_label(colors::TEXT_SECONDARY, preview);
+                        } else {
+                            ui.colored_label(colors::TEXT_SECONDARY, "No messages yet");
+                        }
+
+                        // Unread badge
+                        if let Some(badge) = badge {
+                            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                                egui::Frame::new()
+                                    .fill(colors::ACCENT)
+                                    .corner_radius(egui::CornerRadius::same(8))
+                                    .inner_margin(egui::Margin::symmetric(6, 1))
+                                    .show(ui, |ui| {
+                                        ui.label(egui::RichText::new(badge).color(egui::Color32::WHITE).small().strong());
+                                    });
+                            });
+                        }
+                    });
                 });
             });
         });
 
     // Check if the entire frame was clicked
-    if response.response.interact(egui::Sense::click()).clicked() {
-        clicked = true;
+    let sense = if pinned.is_some() { egui::Sense::click_and_drag() } else { egui::Sense::click() };
+    let item_response = response.response.interact(sense);
+    if item_response.clicked() {
+        action = ContactItemAction::Open;
     }
+    if let Some(conversation_id) = pinned {
+        item_response.dnd_set_drag_payload(PinnedDrag(conversation_id));
+        if item_response.dnd_hover_payload::<PinnedDrag>().is_some_and(|drag| drag.0 != conversation_id) {
+            let rect = item_response.rect;
+            ui.painter().hline(rect.x_range(), rect.top(), egui::Stroke::new(2.0, colors::ACCENT));
+        }
+        if let Some(drag) = item_response.dnd_release_payload::<PinnedDrag>() {
+            if drag.0 != conversation_id {
+                action = ContactItemAction::DropPinned(drag.0);
+            }
+        }
+    }
+    item_response.context_menu(|ui| {
+        if ui.button("Mark as unread").clicked() {
+            action = ContactItemAction::MarkUnread;
+            ui.close();
+        }
+        if ui.button(if pinned.is_some() { "Unpin" } else { "Pin to top" }).clicked() {
+            action = ContactItemAction::TogglePin;
+            ui.close();
+        }
+        ui.menu_button("Notifications", |ui| {
+            for level in NotificationLevel::ALL {
+                if ui.radio(notification_level == level, level.label()).clicked() {
+                    action = ContactItemAction::SetNotificationLevel(level);
+                    ui.close();
+                }
+            }
+        });
+    });
 
     // Add hover effect
     if response.response.hovered() && !is_selected {
@@ -87,23 +167,49 @@ pub fn render(
         );
     }
 
-    clicked
+    action
 }
 
-/// Format timestamp for display (RFC3339 string -> HH:MM)
-fn format_time(timestamp: &str) -> String {
-    // Try to parse the timestamp, otherwise just return a shortened version
-    // The timestamp is in ISO 8601 format
-    if timestamp.len() >= 16 {
-        // Extract time portion (HH:MM)
-        if let Some(t_pos) = timestamp.find('T') {
-            let time_part = &timestamp[t_pos + 1..];
-            if time_part.len() >= 5 {
-                return time_part[..5].to_string();
-            }
-        }
+/// Render the contact's avatar, or their initial while it's unavailable
+fn render_avatar(ui: &mut egui::Ui, contact: &Contact, avatar: Option<(&str, Arc<[u8]>)>, status: (PresenceStatus, Option<&str>)) {
+    let rect = if let Some((url, bytes)) = avatar {
+        ui.add(
+            egui::Image::from_bytes(format!("bytes:/{}", url), bytes)
+                .fit_to_exact_size(egui::vec2(AVATAR_SIZE, AVATAR_SIZE))
+                .corner_radius(egui::CornerRadius::same((AVATAR_SIZE / 2.0) as u8)),
+        )
+        .rect
+    } else {
+        let (rect, _) = ui.allocate_exact_size(egui::vec2(AVATAR_SIZE, AVATAR_SIZE), egui::Sense::hover());
+        ui.painter().circle_filled(rect.center(), AVATAR_SIZE / 2.0, colors::ACCENT);
+        ui.painter().text(
+            rect.center(),
+            egui::Align2::CENTER_CENTER,
+            contact.avatar_initial(),
+            egui::FontId::proportional(18.0),
+            egui::Color32::WHITE,
+        );
+        rect
+    };
+
+    let (status, status_text) = status;
+    if status != PresenceStatus::Offline {
+        // Ringed in the list background so it stands out on the avatar
+        let center = rect.right_bottom() - egui::vec2(5.0, 5.0);
+        ui.painter().circle_filled(center, 6.0, colors::CHAT_LIST_BG);
+        ui.painter().circle_filled(center, 4.5, colors::status_color(status));
+        let hover = match status_text {
+            Some(text) => format!("{}: {}", status.label(), text),
+            None => status.label().to_string(),
+        };
+        ui.interact(rect, ui.id().with(("status", contact.contact_user_id)), egui::Sense::hover())
+            .on_hover_text(hover);
     }
-    timestamp.to_string()
+}
+
+/// Format timestamp for display (HH:MM)
+fn format_time(timestamp: &DateTime<Utc>) -> String {
+    timestamp.format("%H:%M").to_string()
 }
 
 /// Truncate message for preview
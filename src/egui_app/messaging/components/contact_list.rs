@@ -1,20 +1,26 @@
 //! Contact List Component
 //!
-//! Displays the list of contacts with their last message preview.
+//! Displays group conversations, then the list of contacts with their last
+//! message preview.
 
+use chrono::Utc;
 use eframe::egui;
 use uuid::Uuid;
+use crate::egui_app::config::Config;
+use crate::egui_app::media::{MediaKind, MediaState};
 use crate::egui_app::messaging::state::MessagingState;
+use crate::shared::messaging::display_text;
 use crate::egui_app::theme::colors;
-use super::contact_item;
-
-#[cfg(feature = "ssr")]
-use chrono::Utc;
+use super::contact_item::{self, ContactItemAction};
 
 /// Render the contact list
-pub fn render(ui: &mut egui::Ui, state: &mut MessagingState) {
+///
+/// Avatars are downloaded in the background (not in low data mode).
+pub fn render(ui: &mut egui::Ui, state: &mut MessagingState, config: &Config) {
     tracing::debug!("[BRAID] Rendering contact list, contacts: {}, conversations: {}", state.contacts.len(), state.conversations.len());
 
+    render_groups(ui, state);
+
     // Collect contact data first to avoid borrow issues
     let contact_data: Vec<_> = {
         let filtered_contacts = state.filtered_contacts();
@@ -24,32 +30,34 @@ pub fn render(ui: &mut egui::Ui, state: &mut MessagingState) {
             Vec::new()
         } else {
             filtered_contacts.iter().map(|contact| {
-                let is_selected = state.selected_conversation_id
-                    .map(|id| {
-                        state.conversations.get(&id)
-                            .map(|conv| conv.participants.contains(&contact.contact_user_id))
-                            .unwrap_or(false)
-                    })
-                    .unwrap_or(false);
-
-                // Find the conversation for this contact
-                let conversation_id = state.conversations.values()
-                    .find(|conv| conv.participants.contains(&contact.contact_user_id))
-                    .map(|conv| conv.id);
+                // Find the one-to-one conversation for this contact
+                let conversation_id = state.direct_conversation_id(contact.contact_user_id);
+                let is_selected = conversation_id.is_some() && state.selected_conversation_id == conversation_id;
+
+                let badge = conversation_id.and_then(|id| state.unread_badge(id));
+                let notification_level = conversation_id
+                    .map(|id| state.notification_level(id))
+                    .unwrap_or_default();
+                let pinned = conversation_id
+                    .filter(|id| state.conversations.get(id).is_some_and(|c| c.pinned));
 
                 let last_message_content = conversation_id
                     .and_then(|id| state.messages.get(&id))
                     .and_then(|msgs| msgs.last())
-                    .map(|msg| (msg.content.clone(), msg.timestamp.clone()));
+                    .map(|msg| (display_text(&msg.content), msg.timestamp));
 
                 (
                     contact.contact_user_id,
                     contact.username.clone(),
                     contact.email.clone(),
                     contact.display_name.clone(),
+                    contact.avatar_url.clone(),
                     is_selected,
                     conversation_id,
                     last_message_content,
+                    badge,
+                    notification_level,
+                    pinned,
                 )
             }).collect()
         }
@@ -59,10 +67,19 @@ pub fn render(ui: &mut egui::Ui, state: &mut MessagingState) {
         render_empty_state(ui, state);
     } else {
         let mut selected_conv: Option<Uuid> = None;
+        let mut marked_unread: Option<Uuid> = None;
+        let mut level_change = None;
+        let mut pin_toggle: Option<Uuid> = None;
+        let mut pin_move = None;
+
+        for (contact_user_id, username, email, display_name, avatar_url, is_selected, conversation_id, last_message, badge, notification_level, pinned) in contact_data {
+            let avatar = avatar_url.as_deref().and_then(|url| match state.media.request(url, MediaKind::Avatar, config) {
+                MediaState::Ready(bytes) => Some((url, bytes)),
+                _ => None,
+            });
+            let status = state.contact_status(contact_user_id);
 
-        for (contact_user_id, username, email, display_name, is_selected, conversation_id, last_message) in contact_data {
             // Create a temporary contact for rendering
-            #[cfg(feature = "ssr")]
             let contact = crate::shared::messaging::Contact {
                 id: Uuid::new_v4(), // Placeholder
                 user_id: Uuid::new_v4(), // Placeholder
@@ -76,20 +93,6 @@ pub fn render(ui: &mut egui::Ui, state: &mut MessagingState) {
                 created_at: Utc::now(),
             };
 
-            #[cfg(not(feature = "ssr"))]
-            let contact = crate::shared::messaging::Contact {
-                id: Uuid::new_v4(), // Placeholder
-                user_id: Uuid::new_v4(), // Placeholder
-                contact_user_id,
-                username,
-                email,
-                display_name,
-                avatar_url: None,
-                last_seen: String::new(),
-                is_online: false,
-                created_at: String::new(),
-            };
-
             // Create a temporary message for rendering if we have one
             let temp_message = last_message.map(|(content, timestamp)| {
                 crate::shared::messaging::ChatMessage {
@@ -101,18 +104,31 @@ pub fn render(ui: &mut egui::Ui, state: &mut MessagingState) {
                     timestamp,
                     is_read: false,
                     is_delivered: true,
+                    is_sent: true,
                     crdt_timestamp: 0,
                     braid_version: String::new(),
                     braid_parents: Vec::new(),
                     version_vector: crate::shared::messaging::message::VersionVector::default(),
+                    link_preview: None,
+                    moderation_flag: None,
+                    edit_version: None,
+                    is_deleted: false,
+                    sequence: None,
                 }
             });
 
-            if contact_item::render(ui, &contact, temp_message.as_ref(), is_selected) {
+            match contact_item::render(ui, &contact, avatar, status, temp_message.as_ref(), is_selected, badge.as_deref(), notification_level, pinned) {
                 // Contact was clicked - select the conversation
-                if let Some(conv_id) = conversation_id {
-                    selected_conv = Some(conv_id);
+                ContactItemAction::Open => selected_conv = conversation_id,
+                ContactItemAction::MarkUnread => marked_unread = conversation_id,
+                ContactItemAction::SetNotificationLevel(level) => {
+                    level_change = conversation_id.map(|id| (id, level));
                 }
+                ContactItemAction::TogglePin => pin_toggle = conversation_id,
+                ContactItemAction::DropPinned(dragged) => {
+                    pin_move = conversation_id.map(|target| (dragged, target));
+                }
+                ContactItemAction::None => {}
             }
         }
 
@@ -120,6 +136,67 @@ pub fn render(ui: &mut egui::Ui, state: &mut MessagingState) {
         if let Some(conv_id) = selected_conv {
             state.select_conversation(conv_id);
         }
+        if let Some(conv_id) = marked_unread {
+            state.set_manually_unread(conv_id, true);
+        }
+        if let Some((conv_id, level)) = level_change {
+            state.set_notification_level(conv_id, level);
+        }
+        if let Some(conv_id) = pin_toggle {
+            let pinned = state.conversations.get(&conv_id).is_some_and(|c| c.pinned);
+            state.set_pinned(conv_id, !pinned);
+        }
+        if let Some((dragged, target)) = pin_move {
+            state.move_pinned(dragged, target);
+        }
+    }
+}
+
+/// Render group conversations above the contacts
+fn render_groups(ui: &mut egui::Ui, state: &mut MessagingState) {
+    let groups: Vec<_> = state.filtered_groups()
+        .iter()
+        .map(|group| {
+            let title = match state.unread_badge(group.id) {
+                Some(badge) => format!("👥 {}  ({})", group.display_title(), badge),
+                None => format!("👥 {}", group.display_title()),
+            };
+            (group.id, title, group.pinned)
+        })
+        .collect();
+
+    let mut selected = None;
+    let mut pin_toggle = None;
+    let mut left = None;
+    for (conversation_id, title, pinned) in groups {
+        let is_selected = state.selected_conversation_id == Some(conversation_id);
+        let response = ui.add_sized(
+            [ui.available_width(), 32.0],
+            egui::Button::selectable(is_selected, title),
+        );
+        if response.clicked() {
+            selected = Some(conversation_id);
+        }
+        response.context_menu(|ui| {
+            if ui.button(if pinned { "Unpin" } else { "Pin" }).clicked() {
+                pin_toggle = Some((conversation_id, !pinned));
+                ui.close();
+            }
+            if ui.button("Leave group").clicked() {
+                left = Some(conversation_id);
+                ui.close();
+            }
+        });
+    }
+
+    if let Some(conversation_id) = selected {
+        state.select_conversation(conversation_id);
+    }
+    if let Some((conversation_id, pinned)) = pin_toggle {
+        state.set_pinned(conversation_id, pinned);
+    }
+    if let Some(conversation_id) = left {
+        state.leave_conversation(conversation_id);
     }
 }
 
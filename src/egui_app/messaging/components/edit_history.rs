@@ -0,0 +1,56 @@
+//! Edit History Component
+//!
+//! Popup listing the versions an edited message went through, oldest first,
+//! ending with what it says now. Opened by clicking "(edited)" on a bubble.
+
+use eframe::egui;
+use crate::egui_app::messaging::state::{EditHistory, EditHistoryStatus};
+use crate::egui_app::theme::colors;
+use crate::shared::messaging::ChatMessage;
+
+/// Render the popup for `history`; returns `false` once it's closed
+///
+/// `current` is the message as we have it now, shown as the last version.
+pub fn render(ctx: &egui::Context, history: &EditHistory, current: Option<&ChatMessage>) -> bool {
+    let mut open = true;
+    egui::Window::new("Edit History")
+        .open(&mut open)
+        .collapsible(false)
+        .resizable(true)
+        .default_size(egui::vec2(360.0, 280.0))
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(ctx, |ui| {
+            egui::ScrollArea::vertical().show(ui, |ui| match &history.status {
+                EditHistoryStatus::Loading => {
+                    ui.spinner();
+                }
+                EditHistoryStatus::Failed(e) => {
+                    ui.colored_label(egui::Color32::RED, e);
+                }
+                EditHistoryStatus::Loaded(edits) => {
+                    if edits.is_empty() {
+                        ui.label(egui::RichText::new("No earlier versions").weak());
+                    }
+                    for edit in edits {
+                        version(ui, &edit.content, &format!("Written {}", format_timestamp(&edit.written_at)));
+                    }
+                    if let Some(message) = current {
+                        let written = edits.last().map_or(&message.timestamp, |e| &e.replaced_at);
+                        version(ui, &message.content, &format!("Current, since {}", format_timestamp(written)));
+                    }
+                }
+            });
+        });
+    open
+}
+
+fn version(ui: &mut egui::Ui, content: &str, caption: &str) {
+    ui.colored_label(colors::TEXT_SECONDARY, caption);
+    ui.label(content);
+    ui.separator();
+}
+
+/// Format a timestamp as local date and time
+fn format_timestamp(timestamp: &chrono::DateTime<chrono::Utc>) -> String {
+    timestamp.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string()
+}
@@ -162,6 +162,10 @@ fn process_action(action: FriendRequestAction, state: &mut MessagingState, confi
             if state.pending_accept_request.is_some() {
                 return;
             }
+            if !state.is_online {
+                state.queue_accept_request(request_id);
+                return;
+            }
 
             let config_clone = config.clone();
             let (tx, rx) = channel();
@@ -1,8 +1,18 @@
 //! Input Bar Component
 //!
 //! The message input bar at the bottom of the chat area.
+//!
+//! Typing `@` opens a mention popup above the input, and a leading `/`
+//! opens the slash-command menu: ↑/↓ move the highlight, Enter or Tab
+//! inserts the selection, Escape closes the popup.
+//!
+//! While one of our messages is being edited, a row above the input says so
+//! and sending saves the edit instead of posting a new message.
 
 use eframe::egui;
+use egui::text::{CCursor, CCursorRange};
+use crate::egui_app::messaging::commands::{self, CommandOutput};
+use crate::egui_app::messaging::mentions::{self, MentionCandidate};
 use crate::egui_app::messaging::state::MessagingState;
 use crate::egui_app::theme::colors;
 
@@ -13,6 +23,15 @@ pub fn render(ui: &mut egui::Ui, state: &mut MessagingState, is_online: bool) {
         .inner_margin(egui::Margin::symmetric(12, 8))
         .show(ui, |ui| {
             ui.set_min_width(ui.available_width());
+
+            if state.editing_message_id.is_some() {
+                ui.horizontal(|ui| {
+                    ui.colored_label(colors::TEXT_SECONDARY, "✏ Editing message");
+                    if ui.small_button("✕").on_hover_text("Cancel editing").clicked() {
+                        state.cancel_editing();
+                    }
+                });
+            }
             
             ui.horizontal(|ui| {
                 // Attachment button
@@ -27,12 +46,34 @@ pub fn render(ui: &mut egui::Ui, state: &mut MessagingState, is_online: bool) {
                     "You're offline - message will be sent when online"
                 };
 
-                let response = ui.add(
-                    egui::TextEdit::singleline(&mut state.message_input)
-                        .hint_text(hint_text)
-                        .desired_width(ui.available_width() - 80.0)
-                );
-                
+                let input_id = ui.id().with("message_input");
+                let candidates = state
+                    .selected_conversation_id
+                    .map(|id| state.mention_candidates(id))
+                    .unwrap_or_default();
+
+                // Popup keys are taken before the text edit sees them
+                handle_mention_keys(ui, state, input_id, &candidates);
+                if ui.memory(|m| m.has_focus(input_id)) {
+                    handle_command_keys(ui, state, input_id);
+                }
+
+                let output = egui::TextEdit::singleline(&mut state.message_input)
+                    .id(input_id)
+                    .hint_text(hint_text)
+                    .desired_width(ui.available_width() - 80.0)
+                    .show(ui);
+                let response = output.response;
+
+                update_mention_query(state, response.has_focus(), output.cursor_range.map(|r| r.primary.index));
+                render_mention_popup(ui, state, input_id, response.rect, &candidates);
+                if commands::typing_command_name(&state.message_input).is_none() {
+                    state.command_menu_dismissed = false;
+                }
+                if response.has_focus() {
+                    render_command_menu(ui, state, input_id, response.rect);
+                }
+
                 // Send on Enter
                 let enter_pressed = ui.input(|i| i.key_pressed(egui::Key::Enter));
                 if response.lost_focus() && enter_pressed {
@@ -53,14 +94,230 @@ pub fn render(ui: &mut egui::Ui, state: &mut MessagingState, is_online: bool) {
         });
 }
 
+/// Popup navigation: ↑/↓ to move, Enter/Tab to insert, Escape to close
+fn handle_mention_keys(ui: &mut egui::Ui, state: &mut MessagingState, input_id: egui::Id, candidates: &[MentionCandidate]) {
+    let Some(query) = &state.mention_query else {
+        return;
+    };
+    let matches = mentions::match_candidates(&query.query, candidates);
+    if matches.is_empty() {
+        return;
+    }
+    let count = matches.len();
+    let mut selected = state.mention_selected.min(count - 1);
+
+    let (down, up, accept, dismiss) = ui.input_mut(|i| {
+        (
+            i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown),
+            i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp),
+            i.consume_key(egui::Modifiers::NONE, egui::Key::Enter)
+                || i.consume_key(egui::Modifiers::NONE, egui::Key::Tab),
+            i.consume_key(egui::Modifiers::NONE, egui::Key::Escape),
+        )
+    });
+    if down {
+        selected = (selected + 1) % count;
+    }
+    if up {
+        selected = (selected + count - 1) % count;
+    }
+    state.mention_selected = selected;
+
+    if accept {
+        apply_mention(ui.ctx(), state, input_id, &matches[selected]);
+    } else if dismiss {
+        state.mention_dismissed = state.mention_query.take().map(|q| q.start);
+    }
+}
+
+/// Track the `@query` at the caret
+fn update_mention_query(state: &mut MessagingState, focused: bool, cursor: Option<usize>) {
+    let query = cursor
+        .filter(|_| focused)
+        .and_then(|cursor| mentions::active_query(&state.message_input, cursor));
+
+    match &query {
+        Some(q) if state.mention_dismissed == Some(q.start) => {
+            state.mention_query = None;
+            return;
+        }
+        Some(_) => {}
+        None => state.mention_dismissed = None,
+    }
+    if query.as_ref().map(|q| q.start) != state.mention_query.as_ref().map(|q| q.start) {
+        state.mention_selected = 0;
+    }
+    state.mention_query = query;
+}
+
+/// List of matching participants just above the input
+fn render_mention_popup(
+    ui: &mut egui::Ui,
+    state: &mut MessagingState,
+    input_id: egui::Id,
+    input_rect: egui::Rect,
+    candidates: &[MentionCandidate],
+) {
+    let Some(query) = &state.mention_query else {
+        return;
+    };
+    let matches = mentions::match_candidates(&query.query, candidates);
+    if matches.is_empty() {
+        return;
+    }
+
+    let mut clicked = None;
+    egui::Area::new(input_id.with("mention_popup"))
+        .order(egui::Order::Foreground)
+        .pivot(egui::Align2::LEFT_BOTTOM)
+        .fixed_pos(input_rect.left_top() - egui::vec2(0.0, 4.0))
+        .show(ui.ctx(), |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                ui.set_min_width(input_rect.width().min(240.0));
+                for (i, candidate) in matches.iter().enumerate() {
+                    if ui.selectable_label(i == state.mention_selected, candidate.label()).clicked() {
+                        clicked = Some(candidate.clone());
+                    }
+                }
+            });
+        });
+
+    if let Some(candidate) = clicked {
+        apply_mention(ui.ctx(), state, input_id, &candidate);
+    }
+}
+
+/// Insert the chosen mention and put the caret after it
+fn apply_mention(ctx: &egui::Context, state: &mut MessagingState, input_id: egui::Id, candidate: &MentionCandidate) {
+    let Some(query) = state.mention_query.take() else {
+        return;
+    };
+    let cursor = query.start + 1 + query.query.chars().count();
+    let (text, new_cursor) = mentions::insert_mention(&state.message_input, &query, cursor, candidate);
+    state.message_input = text;
+    state.mention_selected = 0;
+    if !state.composer_mentions.contains(candidate) {
+        state.composer_mentions.push(candidate.clone());
+    }
+
+    set_caret(ctx, input_id, new_cursor);
+}
+
+/// Move the input's caret to character `index` and keep it focused
+fn set_caret(ctx: &egui::Context, input_id: egui::Id, index: usize) {
+    let mut edit_state = egui::TextEdit::load_state(ctx, input_id).unwrap_or_default();
+    edit_state.cursor.set_char_range(Some(CCursorRange::one(CCursor::new(index))));
+    edit_state.store(ctx, input_id);
+    ctx.memory_mut(|m| m.request_focus(input_id));
+    ctx.request_repaint();
+}
+
+/// Names of the commands matching the name being typed, unless the menu was dismissed
+fn command_matches(state: &MessagingState) -> Vec<&'static str> {
+    if state.command_menu_dismissed {
+        return Vec::new();
+    }
+    commands::typing_command_name(&state.message_input)
+        .map(|name| state.commands.matching(name).iter().map(|c| c.name).collect())
+        .unwrap_or_default()
+}
+
+/// Command menu navigation: ↑/↓ to move, Tab (or Enter on a partial name)
+/// to complete, Escape to close
+fn handle_command_keys(ui: &mut egui::Ui, state: &mut MessagingState, input_id: egui::Id) {
+    let matches = command_matches(state);
+    if matches.is_empty() {
+        return;
+    }
+    let count = matches.len();
+    let mut selected = state.command_selected.min(count - 1);
+
+    // Enter on a fully typed name sends instead of completing
+    let typed = commands::typing_command_name(&state.message_input).unwrap_or_default();
+    let complete_on_enter = !matches.iter().any(|name| name.eq_ignore_ascii_case(typed));
+
+    let (down, up, accept, dismiss) = ui.input_mut(|i| {
+        (
+            i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown),
+            i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp),
+            i.consume_key(egui::Modifiers::NONE, egui::Key::Tab)
+                || (complete_on_enter && i.consume_key(egui::Modifiers::NONE, egui::Key::Enter)),
+            i.consume_key(egui::Modifiers::NONE, egui::Key::Escape),
+        )
+    });
+    if down {
+        selected = (selected + 1) % count;
+    }
+    if up {
+        selected = (selected + count - 1) % count;
+    }
+    state.command_selected = selected;
+
+    if accept {
+        complete_command(ui.ctx(), state, input_id, matches[selected]);
+    } else if dismiss {
+        state.command_menu_dismissed = true;
+    }
+}
+
+/// Commands matching the draft, just above the input
+fn render_command_menu(ui: &mut egui::Ui, state: &mut MessagingState, input_id: egui::Id, input_rect: egui::Rect) {
+    let matches = command_matches(state);
+    if matches.is_empty() {
+        return;
+    }
+
+    let mut clicked = None;
+    egui::Area::new(input_id.with("command_menu"))
+        .order(egui::Order::Foreground)
+        .pivot(egui::Align2::LEFT_BOTTOM)
+        .fixed_pos(input_rect.left_top() - egui::vec2(0.0, 4.0))
+        .show(ui.ctx(), |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                ui.set_min_width(input_rect.width().min(320.0));
+                for (i, name) in matches.iter().enumerate() {
+                    let Some(command) = state.commands.get(name) else { continue };
+                    let label = format!("/{} {} — {}", command.name, command.usage, command.description);
+                    if ui.selectable_label(i == state.command_selected, label).clicked() {
+                        clicked = Some(*name);
+                    }
+                }
+            });
+        });
+
+    if let Some(name) = clicked {
+        complete_command(ui.ctx(), state, input_id, name);
+    }
+}
+
+/// Replace the draft with `/name ` ready for arguments
+fn complete_command(ctx: &egui::Context, state: &mut MessagingState, input_id: egui::Id, name: &str) {
+    state.message_input = format!("/{} ", name);
+    state.command_selected = 0;
+    set_caret(ctx, input_id, state.message_input.chars().count());
+}
+
 /// Send the current message
 fn send_message(state: &mut MessagingState, is_online: bool) {
     tracing::info!("[BRAID] send_message called with content length: {}, is_online: {}", state.message_input.len(), is_online);
-    let content = state.message_input.trim().to_string();
-    if content.is_empty() {
+    if let Some(message_id) = state.editing_message_id {
+        save_edit(state, message_id, is_online);
+        return;
+    }
+    let draft = mentions::serialize_mentions(state.message_input.trim(), &state.composer_mentions);
+    if draft.is_empty() {
         tracing::info!("[BRAID] Message content is empty, not sending");
         return;
     }
+
+    // Slash commands rewrite the draft (e.g. `/me waves` becomes an action)
+    let CommandOutput { content, message_type } = match state.commands.apply(&draft) {
+        Ok(output) => output,
+        Err(e) => {
+            state.ui_error = Some(e);
+            return;
+        }
+    };
     tracing::info!("[BRAID] Sending message: '{}'", content);
 
     // Get the selected conversation
@@ -101,78 +358,91 @@ fn send_message(state: &mut MessagingState, is_online: bool) {
             state.last_subscribed_conversation_id = Some(conversation_id);
         }
 
-        match message_sync_client.send_message(conversation_id, content.clone(), None) {
-            Ok((message_id, version)) => {
-                tracing::info!("[BRAID] Message sent successfully: id={}, version={}", message_id, version);
-                // Create a new message and add it to the conversation
-                let new_message = crate::shared::messaging::ChatMessage {
-                    id: message_id,
-                    conversation_id,
-                    sender_id: state.current_user_id.unwrap_or_else(|| uuid::Uuid::nil()),
-                    content,
-                    message_type: crate::shared::messaging::MessageType::Text,
-                    #[cfg(feature = "ssr")]
-                    timestamp: chrono::Utc::now().to_rfc3339(),
-                    #[cfg(not(feature = "ssr"))]
-                    timestamp: chrono::Utc::now().to_rfc3339(),
-                    is_read: false,
-                    is_delivered: true,
-                    crdt_timestamp: 0, // TODO: Implement proper CRDT timestamp
-                    braid_version: version,
-                    braid_parents: Vec::new(),
-                    version_vector: crate::shared::messaging::message::VersionVector::default(),
-                };
-
-                // Add to messages map
-                state.messages.entry(conversation_id)
-                    .or_insert_with(Vec::new)
-                    .push(new_message);
-
-                // Clear input
-                state.message_input.clear();
-            }
-            Err(e) => {
-                // Network error - queue for later
-                tracing::warn!("[BRAID] Failed to send message online, queuing for offline: {}", e);
-                // Surface common auth/network issues to the UI so users see why nothing happens
-                if e.contains("Not authenticated") || e.contains("401") || e.contains("UNAUTHORIZED") {
-                    state.ui_error = Some("You are not authenticated. Please login in this window.".to_string());
-                } else if e.contains("FORBIDDEN") || e.contains("403") {
-                    state.ui_error = Some("You are not a participant in this conversation.".to_string());
-                } else {
-                    state.ui_error = Some(format!("Failed to send message: {}", e));
-                }
-                queue_message_offline(state, conversation_id, content);
-            }
-        }
+        // Shown as pending straight away; the PUT happens in the background
+        let message_id = state.send_message(conversation_id, content, message_type);
+        tracing::info!("[BRAID] Message {} queued to send", message_id);
+        state.message_input.clear();
+        state.composer_mentions.clear();
     } else {
         // Offline: Queue for later sending
-        queue_message_offline(state, conversation_id, content);
+        queue_message_offline(state, conversation_id, content, message_type);
     }
 
     // Reset sending state
     state.is_sending_message = false;
 }
 
+/// Save the composer's text as an edit of one of our messages
+///
+/// Edits name the version they were made from, so they need the server
+/// there to check it and aren't queued offline.
+fn save_edit(state: &mut MessagingState, message_id: uuid::Uuid, is_online: bool) {
+    let content = mentions::serialize_mentions(state.message_input.trim(), &state.composer_mentions);
+    let Some(conversation_id) = state.selected_conversation_id else {
+        return;
+    };
+    if content.is_empty() {
+        return;
+    }
+    if !is_online {
+        state.ui_error = Some("You're offline - reconnect to save the edit".to_string());
+        return;
+    }
+
+    let parent = state
+        .messages
+        .get(&conversation_id)
+        .and_then(|messages| messages.iter().find(|m| m.id == message_id))
+        .map(|m| m.current_version().to_string());
+    let Some(parent) = parent else {
+        state.cancel_editing();
+        return;
+    };
+    let Some(client) = state.message_sync_client.as_ref() else {
+        tracing::error!("[BRAID] No message sync client available!");
+        return;
+    };
+
+    match client.edit_message(conversation_id, message_id, content.clone(), &parent) {
+        Ok(version) => {
+            tracing::info!("[BRAID] Message {} edited, version={}", message_id, version);
+            state.apply_message_edit(conversation_id, message_id, content, version);
+            state.cancel_editing();
+        }
+        Err(e) => {
+            tracing::warn!("[BRAID] Failed to edit message {}: {}", message_id, e);
+            state.ui_error = Some(e);
+        }
+    }
+}
+
 /// Queue a message for offline sending
-fn queue_message_offline(state: &mut MessagingState, conversation_id: uuid::Uuid, content: String) {
+fn queue_message_offline(
+    state: &mut MessagingState,
+    conversation_id: uuid::Uuid,
+    content: String,
+    message_type: crate::shared::messaging::MessageType,
+) {
     // Create a message for offline queuing
     let offline_message = crate::shared::messaging::ChatMessage {
         id: uuid::Uuid::new_v4(),
         conversation_id,
         sender_id: state.current_user_id.unwrap_or_else(|| uuid::Uuid::nil()),
         content: content.clone(),
-        message_type: crate::shared::messaging::MessageType::Text,
-        #[cfg(feature = "ssr")]
-        timestamp: chrono::Utc::now().to_rfc3339(),
-        #[cfg(not(feature = "ssr"))]
-        timestamp: chrono::Utc::now().to_rfc3339(),
+        message_type,
+        timestamp: chrono::Utc::now(),
         is_read: false,
         is_delivered: false, // Mark as not delivered yet
+        is_sent: false,
         crdt_timestamp: 0,
         braid_version: "pending".to_string(),
         braid_parents: Vec::new(),
         version_vector: crate::shared::messaging::message::VersionVector::default(),
+        link_preview: None,
+        moderation_flag: None,
+        edit_version: None,
+        is_deleted: false,
+        sequence: None,
     };
 
     // Add to offline queue
@@ -185,6 +455,7 @@ fn queue_message_offline(state: &mut MessagingState, conversation_id: uuid::Uuid
 
     // Clear input
     state.message_input.clear();
+    state.composer_mentions.clear();
 
     tracing::info!("[BRAID] Message queued for offline sending: {}", content);
 }
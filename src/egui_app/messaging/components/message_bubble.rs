@@ -1,20 +1,55 @@
 //! Message Bubble Component
 //!
-//! Displays a single message bubble with content and timestamp.
+//! Displays a single message bubble with content and timestamp. Edited
+//! messages are marked "(edited)" and deleted ones show "(deleted)" in place
+//! of their content; clicking "(edited)" opens the edit history. A requested
+//! translation is shown under the content.
 
+use chrono::{DateTime, Utc};
 use eframe::egui;
-use crate::shared::messaging::ChatMessage;
-use crate::egui_app::theme::colors;
+use crate::shared::messaging::{display_text, ChatMessage, LinkPreview, MessageType};
+use crate::egui_app::crdt::message_crdt::MessageStatus;
+use crate::egui_app::deep_link::DeepLink;
+use crate::egui_app::messaging::state::Translation;
+use crate::egui_app::theme::colors::{self, ConversationTheme};
 
-/// Render a message bubble
-pub fn render(ui: &mut egui::Ui, message: &ChatMessage, is_own_message: bool) {
+/// Something the user picked from a bubble's context menu
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BubbleAction {
+    Edit,
+    Delete,
+    Translate,
+    ShowEditHistory,
+    RetrySend,
+}
+
+/// Render a message bubble, returning the bubble's response and any action
+///
+/// In low data mode media is replaced with placeholders. `sender_name` is
+/// used for `/me` actions ("* Alice waves"). Bubble fills come from the
+/// conversation's `theme`. Our own messages can be edited or deleted from
+/// the context menu; any message can be translated. Read ticks only show
+/// with `show_read`, i.e. while we send read receipts ourselves. Our
+/// messages that failed to send (`send_failed`) offer a retry instead.
+pub fn render(
+    ui: &mut egui::Ui,
+    message: &ChatMessage,
+    sender_name: &str,
+    is_own_message: bool,
+    low_data_mode: bool,
+    theme: &ConversationTheme,
+    translation: Option<&Translation>,
+    show_read: bool,
+    send_failed: bool,
+) -> (egui::Response, Option<BubbleAction>) {
     let (bg_color, text_color, align) = if is_own_message {
-        (colors::BUBBLE_OUTGOING, colors::TEXT_PRIMARY, egui::Align::RIGHT)
+        (theme.bubble_outgoing, colors::TEXT_PRIMARY, egui::Align::RIGHT)
     } else {
-        (colors::BUBBLE_INCOMING, colors::TEXT_PRIMARY, egui::Align::LEFT)
+        (theme.bubble_incoming, colors::TEXT_PRIMARY, egui::Align::LEFT)
     };
 
-    ui.with_layout(egui::Layout::top_down(align), |ui| {
+    let mut action = None;
+    let response = ui.with_layout(egui::Layout::top_down(align), |ui| {
         // Limit bubble width
         let max_width = ui.available_width() * 0.7;
 
@@ -33,43 +68,157 @@ pub fn render(ui: &mut egui::Ui, message: &ChatMessage, is_own_message: bool) {
                     .inner_margin(egui::Margin::symmetric(12, 8))
                     .show(ui, |ui| {
                         // Message content
-                        ui.label(egui::RichText::new(&message.content).color(text_color));
+                        if message.is_deleted {
+                            ui.label(egui::RichText::new("(deleted)").italics().color(colors::TEXT_SECONDARY));
+                        } else if message.message_type == MessageType::Action {
+                            ui.label(
+                                egui::RichText::new(format!("* {} {}", sender_name, display_text(&message.content)))
+                                    .italics()
+                                    .color(text_color),
+                            );
+                        } else {
+                            ui.label(egui::RichText::new(display_text(&message.content)).color(text_color));
+                        }
+
+                        // Translation (requested from the context menu)
+                        if let Some(translation) = translation.filter(|_| !message.is_deleted) {
+                            render_translation(ui, translation);
+                        }
+
+                        // Link preview (attached by the server after sending)
+                        if let Some(preview) = message.link_preview.as_ref().filter(|_| !message.is_deleted) {
+                            render_link_preview(ui, preview, low_data_mode);
+                        }
 
                         // Timestamp and status
                         ui.horizontal(|ui| {
                             let time_str = format_time(&message.timestamp);
                             ui.colored_label(colors::TEXT_SECONDARY, time_str);
+                            if message.is_edited() && !message.is_deleted {
+                                let edited = ui
+                                    .add(
+                                        egui::Label::new(egui::RichText::new("(edited)").color(colors::TEXT_SECONDARY))
+                                            .sense(egui::Sense::click()),
+                                    )
+                                    .on_hover_text("Show edit history");
+                                if edited.clicked() {
+                                    action = Some(BubbleAction::ShowEditHistory);
+                                }
+                            }
 
-                            if is_own_message {
+                            if is_own_message && send_failed {
+                                ui.colored_label(colors::ERROR, "⚠").on_hover_text("Not sent");
+                                if ui.small_button("Retry").clicked() {
+                                    action = Some(BubbleAction::RetrySend);
+                                }
+                            } else if is_own_message {
                                 // Delivery status
-                                let status_icon = if message.is_delivered {
-                                    "✓✓"
-                                } else {
-                                    "✓"
+                                let (status_icon, hover) = match MessageStatus::from(message) {
+                                    MessageStatus::Pending => ("🕓", "Sending"),
+                                    MessageStatus::Sent => ("✓", "Sent"),
+                                    MessageStatus::Delivered => ("✓✓", "Delivered"),
+                                    MessageStatus::Read if !show_read => ("✓✓", "Delivered"),
+                                    MessageStatus::Read => ("✓✓", "Read"),
                                 };
                                 ui.colored_label(
-                                    if message.is_read { colors::ACCENT } else { colors::TEXT_SECONDARY },
+                                    if message.is_read && show_read { colors::ACCENT } else { colors::TEXT_SECONDARY },
                                     status_icon,
-                                );
+                                )
+                                .on_hover_text(hover);
                             }
                         });
-                    });
+                    })
+                    .response
             },
-        );
+        )
+        .inner
+    })
+    .inner
+    .interact(egui::Sense::click());
+
+    response.context_menu(|ui| {
+        if is_own_message && !message.is_deleted {
+            if ui.button("Edit").clicked() {
+                action = Some(BubbleAction::Edit);
+                ui.close();
+            }
+            if ui.button("Delete").clicked() {
+                action = Some(BubbleAction::Delete);
+                ui.close();
+            }
+            ui.separator();
+        }
+        let can_translate = !message.is_deleted && !matches!(translation, Some(Translation::Pending | Translation::Done(_)));
+        if can_translate && ui.button("Translate").clicked() {
+            action = Some(BubbleAction::Translate);
+            ui.close();
+        }
+        if message.is_edited() && !message.is_deleted && ui.button("Edit history").clicked() {
+            action = Some(BubbleAction::ShowEditHistory);
+            ui.close();
+        }
+        if ui.button("Copy link").clicked() {
+            let link = DeepLink::message(message.conversation_id, message.id);
+            ui.ctx().copy_text(link.to_string());
+            ui.close();
+        }
+        if ui.button("Copy web link").clicked() {
+            let link = DeepLink::message(message.conversation_id, message.id);
+            ui.ctx().copy_text(link.to_https_url());
+            ui.close();
+        }
     });
 
     ui.add_space(4.0);
+    (response, action)
 }
 
-/// Format timestamp string to display time (HH:MM)
-fn format_time(timestamp: &str) -> String {
-    // Timestamp format: "2024-01-15T10:30:00Z"
-    if let Some(t_pos) = timestamp.find('T') {
-        let time_part = &timestamp[t_pos + 1..];
-        if time_part.len() >= 5 {
-            return time_part[..5].to_string();
+/// Render a message's translation below its content
+fn render_translation(ui: &mut egui::Ui, translation: &Translation) {
+    match translation {
+        Translation::Pending => {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.colored_label(colors::TEXT_SECONDARY, "Translating…");
+            });
+        }
+        Translation::Done(text) => {
+            ui.label(egui::RichText::new(format!("🌐 {}", text)).italics().color(colors::TEXT_SECONDARY));
+        }
+        Translation::Failed(error) => {
+            ui.colored_label(colors::ERROR, format!("🌐 {}", error));
         }
     }
-    timestamp.to_string()
+}
+
+/// Render a link preview card inside a bubble
+fn render_link_preview(ui: &mut egui::Ui, preview: &LinkPreview, low_data_mode: bool) {
+    egui::Frame::new()
+        .stroke(egui::Stroke::new(1.0, colors::BUBBLE_BORDER))
+        .corner_radius(egui::CornerRadius::same(6))
+        .inner_margin(egui::Margin::symmetric(8, 6))
+        .show(ui, |ui| {
+            if let Some(site_name) = &preview.site_name {
+                ui.colored_label(colors::TEXT_SECONDARY, egui::RichText::new(site_name).small());
+            }
+            if let Some(title) = &preview.title {
+                ui.hyperlink_to(egui::RichText::new(title).strong(), &preview.url);
+            }
+            if let Some(description) = &preview.description {
+                ui.colored_label(colors::TEXT_SECONDARY, description);
+            }
+            if let Some(image_url) = &preview.image_url {
+                if low_data_mode {
+                    ui.colored_label(colors::TEXT_SECONDARY, "🖼 Image hidden (low data mode)");
+                } else {
+                    ui.hyperlink_to("🖼 View image", image_url);
+                }
+            }
+        });
+}
+
+/// Format timestamp to display time (HH:MM)
+fn format_time(timestamp: &DateTime<Utc>) -> String {
+    timestamp.format("%H:%M").to_string()
 }
 
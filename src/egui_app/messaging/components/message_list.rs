@@ -1,34 +1,69 @@
 //! Message List Component
 //!
-//! Displays the list of messages in a conversation.
+//! Displays the list of messages in a conversation. Scrolling to the top
+//! loads older messages from the local database.
 
+use chrono::{DateTime, Utc};
 use eframe::egui;
 use crate::egui_app::messaging::state::MessagingState;
 use crate::egui_app::theme::colors;
-use super::message_bubble;
+use super::message_bubble::{self, BubbleAction};
+use uuid::Uuid;
+
+/// Distance from the top, in points, at which older messages start loading
+const LOAD_OLDER_THRESHOLD: f32 = 40.0;
+
+/// Distance from the bottom, in points, that still counts as at the newest message
+const AT_BOTTOM_THRESHOLD: f32 = 8.0;
 
 /// Render the message list
-pub fn render(ui: &mut egui::Ui, state: &MessagingState) {
+pub fn render(ui: &mut egui::Ui, state: &mut MessagingState) {
+    // A page of older messages arrived; keep the previous top message in view
+    if let Some((conversation_id, older)) = state.history.poll() {
+        let previous_top = state.prepend_older_messages(conversation_id, older);
+        if state.selected_conversation_id == Some(conversation_id) && state.scroll_to_message_id.is_none() {
+            state.scroll_to_message_id = previous_top;
+        }
+    }
+
+    let Some(conversation_id) = state.selected_conversation_id else {
+        return;
+    };
+    let loading_older = state.history.is_loading(conversation_id);
+    if loading_older {
+        ui.ctx().request_repaint_after(std::time::Duration::from_millis(50));
+    }
+
     let messages = match state.selected_messages() {
         Some(msgs) => msgs,
         None => return,
     };
 
     let current_user_id = state.current_user_id;
-
-    egui::ScrollArea::vertical()
+    let low_data_mode = state.low_data_mode;
+    let theme = state.conversation_theme(conversation_id);
+    let scroll_target = state.scroll_to_message_id;
+    let mut scrolled = false;
+    let mut top_visible: Option<Uuid> = None;
+    let mut action: Option<(Uuid, BubbleAction)> = None;
+
+    let output = egui::ScrollArea::vertical()
         .auto_shrink([false, false])
-        .stick_to_bottom(true)
+        .stick_to_bottom(scroll_target.is_none())
         .show(ui, |ui| {
             ui.add_space(8.0);
 
+            if loading_older {
+                ui.vertical_centered(|ui| ui.spinner());
+            }
+
             if messages.is_empty() {
                 render_empty_state(ui);
             } else {
                 let mut last_date: Option<String> = None;
 
                 for message in messages {
-                    // Date separator - extract date from timestamp string
+                    // Date separator - extract date from timestamp
                     let message_date = extract_date(&message.timestamp);
                     if last_date.as_ref().map(|d| d != &message_date).unwrap_or(true) {
                         render_date_separator(ui, &message_date);
@@ -40,12 +75,84 @@ pub fn render(ui: &mut egui::Ui, state: &MessagingState) {
                         .map(|id| id == message.sender_id)
                         .unwrap_or(false);
 
-                    message_bubble::render(ui, message, is_own_message);
+                    let sender_name = if is_own_message {
+                        state.current_username.clone().unwrap_or_else(|| "You".to_string())
+                    } else {
+                        state.sender_name(message.sender_id)
+                    };
+                    let (response, bubble_action) = message_bubble::render(
+                        ui,
+                        message,
+                        &sender_name,
+                        is_own_message,
+                        low_data_mode,
+                        &theme,
+                        state.translations.get(&message.id),
+                        state.sends_read_receipts(),
+                        state.failed_sends.contains(&message.id),
+                    );
+                    if let Some(bubble_action) = bubble_action {
+                        action = Some((message.id, bubble_action));
+                    }
+                    if scroll_target == Some(message.id) {
+                        // A resync returns to the same spot; deep links center
+                        let align = if state.scroll_anchor == scroll_target { egui::Align::Min } else { egui::Align::Center };
+                        response.scroll_to_me(Some(align));
+                        scrolled = true;
+                    }
+                    if top_visible.is_none() && response.rect.bottom() > ui.clip_rect().top() {
+                        top_visible = Some(message.id);
+                    }
                 }
             }
 
             ui.add_space(8.0);
         });
+
+    match action {
+        Some((message_id, BubbleAction::Edit)) => state.start_editing(conversation_id, message_id),
+        Some((message_id, BubbleAction::Delete)) => delete_message(state, conversation_id, message_id),
+        Some((message_id, BubbleAction::Translate)) => state.request_translation(conversation_id, message_id),
+        Some((message_id, BubbleAction::ShowEditHistory)) => state.show_edit_history(conversation_id, message_id),
+        Some((message_id, BubbleAction::RetrySend)) => state.retry_send(conversation_id, message_id),
+        None => {}
+    }
+
+    // Deep link target reached; resume following new messages
+    if scrolled {
+        state.scroll_to_message_id = None;
+    }
+
+    // Remember where the user is reading, unless they're at the newest message
+    if scroll_target.is_none() {
+        let bottom = output.state.offset.y + output.inner_rect.height();
+        let at_bottom = bottom >= output.content_size.y - AT_BOTTOM_THRESHOLD;
+        state.scroll_anchor = if at_bottom { None } else { top_visible };
+    }
+
+    // Scrolled to the top: load the page before the oldest message shown
+    if scroll_target.is_none() && output.state.offset.y <= LOAD_OLDER_THRESHOLD {
+        let oldest = state.selected_messages().and_then(|m| m.first()).cloned();
+        state.history.load_before(conversation_id, oldest.as_ref());
+    }
+}
+
+/// Delete one of our messages on the server, then tombstone our copy
+fn delete_message(state: &mut MessagingState, conversation_id: Uuid, message_id: Uuid) {
+    if !state.is_online {
+        state.ui_error = Some("You're offline - reconnect to delete messages".to_string());
+        return;
+    }
+    let Some(client) = state.message_sync_client.as_ref() else {
+        return;
+    };
+    match client.delete_message(conversation_id, message_id) {
+        Ok(()) => state.apply_message_delete(conversation_id, message_id),
+        Err(e) => {
+            tracing::warn!("[BRAID] Failed to delete message {}: {}", message_id, e);
+            state.ui_error = Some(e);
+        }
+    }
 }
 
 /// Render empty state when no messages
@@ -58,14 +165,9 @@ fn render_empty_state(ui: &mut egui::Ui) {
     });
 }
 
-/// Extract date portion from ISO 8601 timestamp string
-fn extract_date(timestamp: &str) -> String {
-    // Timestamp format: "2024-01-15T10:30:00Z"
-    if let Some(t_pos) = timestamp.find('T') {
-        timestamp[..t_pos].to_string()
-    } else {
-        timestamp.to_string()
-    }
+/// Date portion of a timestamp (YYYY-MM-DD)
+fn extract_date(timestamp: &DateTime<Utc>) -> String {
+    timestamp.format("%Y-%m-%d").to_string()
 }
 
 /// Render a date separator
@@ -10,4 +10,6 @@ pub mod chat_header;
 pub mod message_list;
 pub mod message_bubble;
 pub mod input_bar;
+pub mod conflict_dialog;
 
+pub mod edit_history;
@@ -1,9 +1,15 @@
 //! Search Bar Component
 //!
-//! A search bar for filtering contacts by email or username.
+//! A search bar for filtering contacts by email or username. The same query
+//! searches message bodies in the local database; matches are listed under
+//! the contacts by [`render_message_results`].
 
 use eframe::egui;
+use eframe::egui::text::{LayoutJob, TextFormat};
+use crate::egui_app::deep_link::DeepLink;
 use crate::egui_app::messaging::state::MessagingState;
+use crate::egui_app::theme::colors;
+use crate::shared::messaging::display_text;
 
 /// Render the search bar
 pub fn render(ui: &mut egui::Ui, state: &mut MessagingState) {
@@ -16,7 +22,7 @@ pub fn render(ui: &mut egui::Ui, state: &mut MessagingState) {
         // Search input
         let _response = ui.add(
             egui::TextEdit::singleline(&mut state.search_query)
-                .hint_text("Search contacts...")
+                .hint_text("Search contacts and messages...")
                 .desired_width(ui.available_width() - 40.0)
         );
 
@@ -29,5 +35,88 @@ pub fn render(ui: &mut egui::Ui, state: &mut MessagingState) {
 
         ui.add_space(8.0);
     });
+
+    state.message_search.set_query(&state.search_query);
+    if state.message_search.poll() {
+        ui.ctx().request_repaint_after(std::time::Duration::from_millis(50));
+    }
 }
 
+/// Render messages matching the search query, if any
+///
+/// Clicking a result opens its conversation scrolled to the message.
+pub fn render_message_results(ui: &mut egui::Ui, state: &mut MessagingState) {
+    if state.search_query.trim().is_empty() {
+        return;
+    }
+
+    ui.add_space(8.0);
+    ui.horizontal(|ui| {
+        ui.add_space(12.0);
+        ui.colored_label(colors::TEXT_SECONDARY, egui::RichText::new("Messages").small().strong());
+        if state.message_search.is_searching() {
+            ui.spinner();
+        }
+    });
+
+    if let Some(error) = &state.message_search.error {
+        ui.horizontal(|ui| {
+            ui.add_space(12.0);
+            ui.colored_label(colors::ERROR, error);
+        });
+        return;
+    }
+
+    if state.message_search.results.is_empty() {
+        if !state.message_search.is_searching() {
+            ui.horizontal(|ui| {
+                ui.add_space(12.0);
+                ui.colored_label(colors::TEXT_SECONDARY, "No matching messages");
+            });
+        }
+        return;
+    }
+
+    let mut open = None;
+    for hit in &state.message_search.results {
+        let title = state
+            .conversations
+            .get(&hit.conversation_id)
+            .map(|c| c.display_title())
+            .unwrap_or_else(|| "Conversation".to_string());
+
+        let mut snippet = LayoutJob::default();
+        for (text, is_match) in hit.snippet_parts() {
+            let format = if is_match {
+                TextFormat {
+                    color: colors::CHAT_ITEM_TEXT,
+                    background: colors::ACTIVE_CHAT_STRIP,
+                    ..Default::default()
+                }
+            } else {
+                TextFormat { color: colors::ICONS, ..Default::default() }
+            };
+            snippet.append(&display_text(text), 0.0, format);
+        }
+
+        let response = ui
+            .horizontal(|ui| {
+                ui.add_space(12.0);
+                ui.vertical(|ui| {
+                    ui.colored_label(colors::CHAT_ITEM_TEXT, egui::RichText::new(title).strong());
+                    ui.label(snippet);
+                });
+            })
+            .response
+            .interact(egui::Sense::click())
+            .on_hover_cursor(egui::CursorIcon::PointingHand);
+        if response.clicked() {
+            open = Some(DeepLink { conversation_id: hit.conversation_id, message_id: Some(hit.message_id) });
+        }
+        ui.add_space(4.0);
+    }
+
+    if let Some(link) = open {
+        state.navigate_to_message(link);
+    }
+}
@@ -1,107 +1,69 @@
 //! Friend Request API Client
 //!
-//! This module provides async functions for interacting with the friend request API.
+//! Blocking wrappers around [`ApiClient`] for the messaging UI, which calls
+//! the API from background threads and shows errors as text.
 
+use crate::egui_app::api_client::ApiClient;
 use crate::egui_app::config::Config;
+use crate::shared::error::SharedError;
+use crate::shared::PresenceEvent;
 use crate::shared::messaging::{
-    Contact, Conversation, FriendRequest, ListContactsResponse, ListConversationsResponse,
-    ListFriendRequestsResponse, RespondFriendRequestRequest, RespondFriendRequestResponse,
-    SendFriendRequestRequest, SendFriendRequestResponse,
+    ChatMessage, Contact, Conversation, FriendRequest, MessageEdit, PrivacySettings, RespondFriendRequestResponse,
+    SendFriendRequestRequest, SendFriendRequestResponse, SummarizeConversationRequest, ThemeColor,
+    TranslateMessageRequest, SetStatusRequest, UserProfile, UserStatus,
+    MAX_PRESENCE_BATCH, PRIVACY_REJECTED_ERROR,
 };
-use reqwest::Client;
-use tokio::runtime::Runtime;
 use uuid::Uuid;
 
 /// Friend API client
 pub struct FriendApiClient {
-    config: Config,
-    client: Client,
+    api: ApiClient,
+}
+
+/// Describe an API error for display
+fn describe(error: SharedError) -> String {
+    match error {
+        SharedError::HttpError { message, .. } if message == "Not authenticated" => message,
+        SharedError::NetworkError { message } => format!("Network error: {}", message),
+        SharedError::SerializationError { message } => message,
+        other => format!("Request failed: {}", other),
+    }
 }
 
 impl FriendApiClient {
     pub fn new(config: Config) -> Self {
         Self {
-            config,
-            client: Client::new(),
+            api: ApiClient::new(config),
         }
     }
 
-    /// Send a friend request to a user by email
-    pub fn send_friend_request(&self, to_email: &str) -> Result<SendFriendRequestResponse, String> {
-        let url = self.config.api_url("/api/friends/request");
-        let token = self.config.get_token().ok_or("Not authenticated")?;
-
-        let rt = Runtime::new().map_err(|e| format!("Failed to create runtime: {}", e))?;
-
-        rt.block_on(async {
-            let request = SendFriendRequestRequest {
-                to_email: to_email.to_string(),
-            };
-
-            let response = self
-                .client
-                .post(&url)
-                .header("Authorization", format!("Bearer {}", token))
-                .header("Content-Type", "application/json")
-                .json(&request)
-                .send()
-                .await
-                .map_err(|e| format!("Network error: {}", e))?;
-
-            if !response.status().is_success() {
-                let status = response.status();
-                let error_text = response
-                    .text()
-                    .await
-                    .unwrap_or_else(|_| status.to_string());
-                
-                let friendly_error = match status.as_u16() {
-                    409 => "Friend request already sent or already friends".to_string(),
-                    404 => "User not found".to_string(),
-                    _ => format!("Request failed: {} - {}", status, error_text),
-                };
-                return Err(friendly_error);
-            }
+    /// Send a friend request to a user by email, or by ID when picked from a search
+    pub fn send_friend_request(&self, to_email: &str, to_user_id: Option<Uuid>) -> Result<SendFriendRequestResponse, String> {
+        let request = SendFriendRequestRequest {
+            to_email: to_email.to_string(),
+            to_user_id,
+            message: None,
+        };
+
+        let mut response = ApiClient::block_on(self.api.send_friend_request(&request)).map_err(|e| match e.status() {
+            Some(409) => "Friend request already sent or already friends".to_string(),
+            Some(404) => "User not found".to_string(),
+            _ => describe(e),
+        })?;
+        if response.error.as_deref() == Some(PRIVACY_REJECTED_ERROR) {
+            response.error = Some("This user isn't accepting friend requests from you".to_string());
+        }
+        Ok(response)
+    }
 
-            response
-                .json::<SendFriendRequestResponse>()
-                .await
-                .map_err(|e| format!("Failed to parse response: {}", e))
-        })
+    /// Find users to add by username or email
+    pub fn search_users(&self, query: &str) -> Result<Vec<UserProfile>, String> {
+        ApiClient::block_on(self.api.search_users(query)).map_err(describe)
     }
 
     /// Get pending friend requests for the current user
     pub fn get_pending_requests(&self) -> Result<Vec<FriendRequest>, String> {
-        let url = self.config.api_url("/api/friends/requests");
-        let token = self.config.get_token().ok_or("Not authenticated")?;
-
-        let rt = Runtime::new().map_err(|e| format!("Failed to create runtime: {}", e))?;
-
-        rt.block_on(async {
-            let response = self
-                .client
-                .get(&url)
-                .header("Authorization", format!("Bearer {}", token))
-                .send()
-                .await
-                .map_err(|e| format!("Network error: {}", e))?;
-
-            if !response.status().is_success() {
-                let status = response.status();
-                let error_text = response
-                    .text()
-                    .await
-                    .unwrap_or_else(|_| status.to_string());
-                return Err(format!("Request failed: {} - {}", status, error_text));
-            }
-
-            let list_response = response
-                .json::<ListFriendRequestsResponse>()
-                .await
-                .map_err(|e| format!("Failed to parse response: {}", e))?;
-
-            Ok(list_response.requests)
-        })
+        ApiClient::block_on(self.api.friend_requests()).map_err(describe)
     }
 
     /// Respond to a friend request (accept or reject)
@@ -110,106 +72,178 @@ impl FriendApiClient {
         request_id: Uuid,
         accept: bool,
     ) -> Result<RespondFriendRequestResponse, String> {
-        let url = self.config.api_url("/api/friends/respond");
-        let token = self.config.get_token().ok_or("Not authenticated")?;
-
-        let rt = Runtime::new().map_err(|e| format!("Failed to create runtime: {}", e))?;
-
-        rt.block_on(async {
-            let request = RespondFriendRequestRequest { request_id, accept };
-
-            let response = self
-                .client
-                .post(&url)
-                .header("Authorization", format!("Bearer {}", token))
-                .header("Content-Type", "application/json")
-                .json(&request)
-                .send()
-                .await
-                .map_err(|e| format!("Network error: {}", e))?;
-
-            if !response.status().is_success() {
-                let status = response.status();
-                let error_text = response
-                    .text()
-                    .await
-                    .unwrap_or_else(|_| status.to_string());
-                return Err(format!("Request failed: {} - {}", status, error_text));
-            }
-
-            response
-                .json::<RespondFriendRequestResponse>()
-                .await
-                .map_err(|e| format!("Failed to parse response: {}", e))
-        })
+        ApiClient::block_on(self.api.respond_to_friend_request(request_id, accept)).map_err(describe)
     }
 
     /// Get contacts for the current user
     pub fn get_contacts(&self) -> Result<Vec<Contact>, String> {
-        let url = self.config.api_url("/api/contacts");
-        let token = self.config.get_token().ok_or("Not authenticated")?;
-
-        let rt = Runtime::new().map_err(|e| format!("Failed to create runtime: {}", e))?;
-
-        rt.block_on(async {
-            let response = self
-                .client
-                .get(&url)
-                .header("Authorization", format!("Bearer {}", token))
-                .send()
-                .await
-                .map_err(|e| format!("Network error: {}", e))?;
-
-            if !response.status().is_success() {
-                let status = response.status();
-                let error_text = response
-                    .text()
-                    .await
-                    .unwrap_or_else(|_| status.to_string());
-                return Err(format!("Request failed: {} - {}", status, error_text));
+        ApiClient::block_on(self.api.contacts()).map_err(describe)
+    }
+
+    /// Get conversations for the current user
+    pub fn get_conversations(&self) -> Result<Vec<Conversation>, String> {
+        ApiClient::block_on(self.api.conversations()).map_err(describe)
+    }
+
+    /// Start a conversation with the given contacts
+    ///
+    /// Returns the new conversation, or the existing direct chat when
+    /// starting an unnamed one with a single contact.
+    pub fn create_conversation(&self, participant_ids: Vec<Uuid>, name: Option<String>) -> Result<Conversation, String> {
+        let response = ApiClient::block_on(self.api.create_conversation(participant_ids, name)).map_err(|e| match e.status() {
+            Some(403) => "You can only add your contacts to a conversation".to_string(),
+            _ => describe(e),
+        })?;
+        response
+            .conversation
+            .ok_or_else(|| response.error.unwrap_or_else(|| "Failed to create conversation".to_string()))
+    }
+
+    /// Add a contact to a conversation
+    pub fn add_conversation_participant(&self, conversation_id: Uuid, user_id: Uuid) -> Result<(), String> {
+        ApiClient::block_on(self.api.add_conversation_participant(conversation_id, user_id)).map_err(describe)
+    }
+
+    /// Remove a participant from a conversation; only works for the user themselves
+    pub fn remove_conversation_participant(&self, conversation_id: Uuid, user_id: Uuid) -> Result<(), String> {
+        ApiClient::block_on(self.api.remove_conversation_participant(conversation_id, user_id)).map_err(describe)
+    }
+
+    /// Set or clear the manual "unread" marker on a conversation
+    pub fn set_conversation_unread(&self, conversation_id: Uuid, unread: bool) -> Result<(), String> {
+        ApiClient::block_on(self.api.set_conversation_unread(conversation_id, unread)).map_err(describe)
+    }
+
+    /// Set or clear the user's theme color for a conversation
+    pub fn set_conversation_theme(&self, conversation_id: Uuid, color: Option<ThemeColor>) -> Result<(), String> {
+        ApiClient::block_on(self.api.set_conversation_theme(conversation_id, color)).map_err(describe)
+    }
+
+    /// Pin or unpin a conversation in the sidebar
+    pub fn set_conversation_pinned(&self, conversation_id: Uuid, pinned: bool) -> Result<(), String> {
+        ApiClient::block_on(self.api.set_conversation_pinned(conversation_id, pinned)).map_err(describe)
+    }
+
+    /// Save the order of the pinned conversations, top first
+    pub fn reorder_pinned_conversations(&self, conversation_ids: Vec<Uuid>) -> Result<(), String> {
+        ApiClient::block_on(self.api.reorder_pinned_conversations(conversation_ids)).map_err(describe)
+    }
+
+    /// Get who can message the user and which activity they share
+    pub fn get_privacy_settings(&self) -> Result<PrivacySettings, String> {
+        ApiClient::block_on(self.api.privacy_settings()).map_err(describe)
+    }
+
+    /// Save who can message the user and which activity they share
+    pub fn set_privacy_settings(&self, settings: PrivacySettings) -> Result<(), String> {
+        ApiClient::block_on(self.api.set_privacy_settings(settings)).map_err(describe)
+    }
+
+    /// Users we've blocked
+    pub fn get_blocked_users(&self) -> Result<Vec<Uuid>, String> {
+        ApiClient::block_on(self.api.blocked_users()).map_err(describe)
+    }
+
+    /// Block or unblock a user
+    pub fn set_user_blocked(&self, user_id: Uuid, blocked: bool) -> Result<(), String> {
+        let result = if blocked {
+            ApiClient::block_on(self.api.block_user(user_id))
+        } else {
+            ApiClient::block_on(self.api.unblock_user(user_id))
+        };
+        result.map_err(describe)
+    }
+
+    /// Tell the server we're online; returns whether it could be reached at all
+    pub fn check_connectivity(&self) -> bool {
+        match ApiClient::block_on(self.api.presence_heartbeat()) {
+            Ok(()) => true,
+            Err(SharedError::NetworkError { message }) => {
+                tracing::debug!("Server unreachable: {}", message);
+                false
             }
+            Err(e) => {
+                tracing::debug!("Failed to send presence heartbeat: {}", e);
+                true
+            }
+        }
+    }
 
-            let list_response = response
-                .json::<ListContactsResponse>()
-                .await
-                .map_err(|e| format!("Failed to parse response: {}", e))?;
+    /// Current presence of `user_ids`, asked for in batches the server accepts
+    pub fn get_presence(&self, user_ids: &[Uuid]) -> Result<Vec<PresenceEvent>, String> {
+        let mut presence = Vec::with_capacity(user_ids.len());
+        for batch in user_ids.chunks(MAX_PRESENCE_BATCH) {
+            presence.extend(ApiClient::block_on(self.api.presence_batch(batch)).map_err(describe)?);
+        }
+        Ok(presence)
+    }
 
-            Ok(list_response.contacts)
+    /// Set our status and status text for contacts to see
+    pub fn set_status(&self, request: &SetStatusRequest) -> Result<UserStatus, String> {
+        ApiClient::block_on(self.api.set_status(request)).map_err(|error| match error {
+            SharedError::HttpError { status: 400, message } => message,
+            other => describe(other),
         })
     }
 
-    /// Get conversations for the current user
-    pub fn get_conversations(&self) -> Result<Vec<Conversation>, String> {
-        let url = self.config.api_url("/api/conversations");
-        let token = self.config.get_token().ok_or("Not authenticated")?;
-
-        let rt = Runtime::new().map_err(|e| format!("Failed to create runtime: {}", e))?;
-
-        rt.block_on(async {
-            let response = self
-                .client
-                .get(&url)
-                .header("Authorization", format!("Bearer {}", token))
-                .send()
-                .await
-                .map_err(|e| format!("Network error: {}", e))?;
-
-            if !response.status().is_success() {
-                let status = response.status();
-                let error_text = response
-                    .text()
-                    .await
-                    .unwrap_or_else(|_| status.to_string());
-                return Err(format!("Request failed: {} - {}", status, error_text));
-            }
+    /// Translate a message into `target_locale`
+    pub fn translate_message(&self, conversation_id: Uuid, message_id: Uuid, target_locale: &str) -> Result<String, String> {
+        let request = TranslateMessageRequest {
+            conversation_id,
+            message_id,
+            target_locale: target_locale.to_string(),
+        };
+        ApiClient::block_on(self.api.translate_message(&request))
+            .map(|response| response.translation)
+            .map_err(|e| match e.status() {
+                Some(429) => "Translation limit reached, try again later".to_string(),
+                Some(503) => "Translation isn't available on this server".to_string(),
+                Some(502) => "Translation service failed, try again".to_string(),
+                Some(413) => "Message is too long to translate".to_string(),
+                Some(410) => "Message was deleted".to_string(),
+                _ => describe(e),
+            })
+    }
 
-            let list_response = response
-                .json::<ListConversationsResponse>()
-                .await
-                .map_err(|e| format!("Failed to parse response: {}", e))?;
+    /// Get a conversation's newest messages, oldest first
+    pub fn get_latest_messages(&self, conversation_id: Uuid, limit: u32) -> Result<Vec<ChatMessage>, String> {
+        ApiClient::block_on(self.api.message_history(conversation_id, limit, None))
+            .map(|page| page.messages)
+            .map_err(describe)
+    }
 
-            Ok(list_response.conversations)
+    /// Versions of a message that edits replaced, oldest first
+    pub fn get_edit_history(&self, conversation_id: Uuid, message_id: Uuid) -> Result<Vec<MessageEdit>, String> {
+        ApiClient::block_on(self.api.edit_history(conversation_id, message_id))
+            .map(|response| response.edits)
+            .map_err(|e| match e.status() {
+                Some(403) => "You can't see this message's history".to_string(),
+                Some(410) => "Message was deleted".to_string(),
+                _ => describe(e),
+            })
+    }
+
+    /// Summarize messages `from..=to`, passing text to `on_text` as it arrives
+    ///
+    /// Returns `false` if there was nothing to summarize.
+    pub fn summarize_conversation(
+        &self,
+        conversation_id: Uuid,
+        from_message_id: Option<Uuid>,
+        to_message_id: Option<Uuid>,
+        on_text: impl FnMut(&str),
+    ) -> Result<bool, String> {
+        let request = SummarizeConversationRequest {
+            conversation_id,
+            from_message_id,
+            to_message_id,
+        };
+        ApiClient::block_on(self.api.summarize_conversation(&request, on_text)).map_err(|e| match e.status() {
+            Some(429) => "Summary limit reached, try again later".to_string(),
+            Some(503) => "Summaries aren't available on this server".to_string(),
+            Some(502) => "Summary service failed, try again".to_string(),
+            Some(413) => "Messages are too long to summarize".to_string(),
+            _ => describe(e),
         })
     }
 }
-
@@ -0,0 +1,123 @@
+//! Message History
+//!
+//! Pages older messages out of the local database when the message list is
+//! scrolled to the top. Like message search, pages load on a background
+//! worker thread that keeps the database open. Only one page loads at a time.
+
+use std::collections::HashSet;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+use uuid::Uuid;
+
+use crate::egui_app::local_db::messages::{MessageCursor, MessagePage};
+use crate::egui_app::local_db::LocalDatabase;
+use crate::shared::messaging::ChatMessage;
+
+/// Messages loaded per scroll to the top
+pub const PAGE_SIZE: u32 = 50;
+
+type PageRequest = (Uuid, Option<MessageCursor>);
+type PageResult = (Uuid, Result<MessagePage, String>);
+
+/// Older-message loading for the message list
+#[derive(Default)]
+pub struct MessageHistory {
+    /// Conversation whose page is loading
+    in_flight: Option<Uuid>,
+    /// Conversations with nothing older left to load
+    exhausted: HashSet<Uuid>,
+    pub error: Option<String>,
+    worker: Option<(Sender<PageRequest>, Receiver<PageResult>)>,
+}
+
+impl MessageHistory {
+    /// Whether a page is loading for the conversation
+    pub fn is_loading(&self, conversation_id: Uuid) -> bool {
+        self.in_flight == Some(conversation_id)
+    }
+
+    /// Whether older messages may still be available
+    pub fn has_more(&self, conversation_id: Uuid) -> bool {
+        !self.exhausted.contains(&conversation_id)
+    }
+
+    /// Start loading the page before `oldest` (the newest page if `None`)
+    ///
+    /// Does nothing while another page is loading or once the start of the
+    /// conversation has been reached.
+    pub fn load_before(&mut self, conversation_id: Uuid, oldest: Option<&ChatMessage>) {
+        if self.in_flight.is_some() || !self.has_more(conversation_id) {
+            return;
+        }
+        let request = (conversation_id, oldest.map(MessageCursor::of));
+        let (requests, _) = self.worker.get_or_insert_with(spawn_worker);
+        if requests.send(request).is_err() {
+            self.worker = None;
+            self.error = Some("Message history stopped".to_string());
+        } else {
+            self.in_flight = Some(conversation_id);
+        }
+    }
+
+    /// Collect a finished page, oldest message first
+    pub fn poll(&mut self) -> Option<(Uuid, Vec<ChatMessage>)> {
+        let (_, results) = self.worker.as_ref()?;
+        let (conversation_id, result) = results.try_recv().ok()?;
+        self.in_flight = None;
+        match result {
+            Ok(page) => {
+                if !page.has_more {
+                    self.exhausted.insert(conversation_id);
+                }
+                self.error = None;
+                Some((conversation_id, page.messages))
+            }
+            Err(e) => {
+                tracing::warn!("Failed to load older messages for {}: {}", conversation_id, e);
+                // Stop here rather than retrying every frame at the top
+                self.exhausted.insert(conversation_id);
+                self.error = Some(e);
+                None
+            }
+        }
+    }
+}
+
+/// Thread that opens the local database once and answers page requests in order
+fn spawn_worker() -> (Sender<PageRequest>, Receiver<PageResult>) {
+    let (request_tx, request_rx) = mpsc::channel::<PageRequest>();
+    let (result_tx, result_rx) = mpsc::channel();
+
+    thread::spawn(move || {
+        let rt = match tokio::runtime::Runtime::new() {
+            Ok(rt) => rt,
+            Err(e) => {
+                let error = format!("Failed to create runtime: {}", e);
+                for (conversation_id, _) in request_rx {
+                    if result_tx.send((conversation_id, Err(error.clone()))).is_err() {
+                        break;
+                    }
+                }
+                return;
+            }
+        };
+        rt.block_on(async {
+            let db = LocalDatabase::new().await.map_err(|e| format!("Failed to open local database: {}", e));
+            while let Ok((conversation_id, cursor)) = request_rx.recv() {
+                let result = match &db {
+                    Ok(db) => db
+                        .get_conversation_messages_before(&conversation_id, cursor.as_ref(), PAGE_SIZE)
+                        .await
+                        .map_err(|e| e.to_string()),
+                    Err(e) => Err(e.clone()),
+                };
+                if result_tx.send((conversation_id, result)).is_err() {
+                    break;
+                }
+            }
+        });
+    });
+
+    (request_tx, result_rx)
+}
@@ -5,13 +5,18 @@
 
 use eframe::egui;
 use std::sync::mpsc::channel;
-use super::state::MessagingState;
+use super::state::{merge_message, MessagingState, SummaryStatus, SummaryUpdate};
 use super::sidebar::render_sidebar;
 use super::chat_area::render_chat_area;
 use super::friend_api::FriendApiClient;
-use super::braid_sync::MessageSyncClient;
+use super::braid_sync::{HeartbeatPolicy, MessageSyncClient, ReconnectPolicy, SubscriptionStatus};
+use super::activity;
+use super::components::{conflict_dialog, edit_history};
 use crate::egui_app::config::Config;
+use crate::egui_app::local_db::LocalDatabase;
+use crate::egui_app::notifications::IncomingMessage;
 use crate::egui_app::theme::styles;
+use crate::shared::messaging::is_searchable;
 
 /// Sidebar width in pixels
 const SIDEBAR_WIDTH: f32 = 320.0;
@@ -20,6 +25,8 @@ const SIDEBAR_WIDTH: f32 = 320.0;
 pub fn render_messaging_view(ui: &mut egui::Ui, state: &mut MessagingState, config: &Config) {
     // Check for pending async operation results
     state.check_pending_operations();
+    state.low_data_mode = config.low_data_mode();
+    state.media.poll();
 
     // Initialize data on first render
     if !state.initialized {
@@ -36,13 +43,20 @@ pub fn render_messaging_view(ui: &mut egui::Ui, state: &mut MessagingState, conf
             );
             if let Some(ref mut client) = state.message_sync_client {
                 client.subscribe_to_conversation(conv_id);
+                client.subscribe_to_activity();
                 state.last_subscribed_conversation_id = Some(conv_id);
+                state.remote_activity.clear();
             } else {
                 tracing::error!("[BRAID] No message sync client available!");
             }
         }
 
         // Poll for incoming messages
+        let mut arrived = Vec::new();
+        let mut polled_status = None;
+        let mut delivery_receipts = Vec::new();
+        let mut read_receipts = Vec::new();
+        let sends_typing = state.sends_typing();
         if let Some(ref mut client) = state.message_sync_client {
             let incoming = client.poll_messages();
             if !incoming.is_empty() {
@@ -50,14 +64,54 @@ pub fn render_messaging_view(ui: &mut egui::Ui, state: &mut MessagingState, conf
                 for msg in incoming {
                     tracing::info!("[BRAID] UI updating with received message: id={}, sender={}, content='{}...', version={}, parents={:?}",
                                   msg.id, msg.sender_id, &msg.content[..msg.content.len().min(30)], msg.braid_version, msg.braid_parents);
-                    state.messages.entry(conv_id)
-                        .or_insert_with(Vec::new)
-                        .push(msg);
+                    // Our own echo means a send that looked failed went through
+                    state.failed_sends.remove(&msg.id);
+                    let messages = state.messages.entry(conv_id).or_insert_with(Vec::new);
+                    if merge_message(messages, msg.clone()) {
+                        arrived.push(msg);
+                    }
                     tracing::debug!(
                         "[BRAID] Message added to UI state, total messages in conversation: {}",
                         state.messages.get(&conv_id).map(|v| v.len()).unwrap_or(0)
                     );
                 }
+                // The server's sequence is the authoritative order, whatever
+                // order the messages reached us in
+                if let Some(messages) = state.messages.get_mut(&conv_id) {
+                    messages.sort_by(|a, b| a.display_cmp(b));
+                }
+            }
+
+            // Other participants' typing/recording/uploading in this conversation,
+            // ignoring our own echoes
+            let now = std::time::Instant::now();
+            for activity in client.poll_activity() {
+                let ours = activity.sender_id.is_some() && activity.sender_id == state.current_user_id;
+                if !ours && activity.conversation_id == Some(conv_id) {
+                    state.remote_activity.apply(activity, now);
+                }
+            }
+
+            // Let senders know their messages reached this device
+            for msg in arrived.iter().filter(|m| Some(m.sender_id) != state.current_user_id) {
+                client.mark_delivered(msg.conversation_id, msg.id);
+            }
+
+            // Delivery and read receipts for any conversation we have loaded
+            delivery_receipts = client.poll_delivery_receipts();
+            read_receipts = client.poll_read_receipts();
+
+            // Tell others what we're doing in the composer (debounced)
+            let current = activity::current_activity(
+                state.is_recording_audio,
+                state.is_uploading_file,
+                &state.message_input,
+            );
+            if let Some((kind, is_active)) = state.activity.update(current, now) {
+                // The server would drop it anyway
+                if let Some(user) = state.current_username.clone().filter(|_| sends_typing) {
+                    client.send_activity(conv_id, user, kind, is_active);
+                }
             }
 
             // Poll subscription status updates and reflect in UI state + log
@@ -68,15 +122,262 @@ pub fn render_messaging_view(ui: &mut egui::Ui, state: &mut MessagingState, conf
                     if state.subscription_log.len() > 200 { state.subscription_log.remove(0); }
                     state.last_subscription_status = Some(status.clone());
                 }
-                state.subscription_status = Some(status);
+                // Resubscribing waits for a fresh token
+                if status == SubscriptionStatus::Unauthorized {
+                    state.token_rejected = true;
+                }
+                state.subscription_status = Some(status.clone());
+                polled_status = Some(status);
             }
         }
+
+        // Applied once the client is no longer borrowed
+        for receipt in &delivery_receipts {
+            state.apply_delivery_receipt(receipt);
+        }
+        for receipt in &read_receipts {
+            state.apply_read_receipt(receipt);
+        }
+
+        // Catch up on anything missed while the subscription was down
+        if let Some(status) = polled_status {
+            state.track_subscription_status(conv_id, &status);
+        }
+
+        // Desktop notifications for messages the user isn't looking at
+        let window_focused = ui.ctx().input(|i| i.viewport().focused.unwrap_or(true));
+
+        // Messages in the open conversation count as read while the window has focus
+        if window_focused && state.is_online && state.message_sync_client.is_some() {
+            let read = state.take_unread_incoming(conv_id);
+            if let Some(client) = state.message_sync_client.as_ref().filter(|_| state.sends_read_receipts()) {
+                for message_id in read {
+                    client.mark_read(conv_id, message_id);
+                }
+            }
+        }
+        for msg in &arrived {
+            let sender_name = state.sender_name(msg.sender_id);
+            let incoming = IncomingMessage {
+                message: msg,
+                sender_name: &sender_name,
+                level: state.notification_level(msg.conversation_id),
+                focused: window_focused && state.selected_conversation_id == Some(msg.conversation_id),
+            };
+            state.notifications.message_received(
+                config,
+                &incoming,
+                state.current_user_id,
+                state.current_username.as_deref(),
+            );
+        }
     } else {
         tracing::debug!("[BRAID] No conversation selected");
     }
 
-    // Sync offline messages when online
-    state.sync_offline_messages();
+    // Contacts coming online or going offline, and the statuses they pick,
+    // whichever conversation is open
+    if let Some(ref mut client) = state.message_sync_client {
+        client.subscribe_to_activity();
+        client.subscribe_to_statuses();
+        let presence = client.poll_presence();
+        let statuses = client.poll_statuses();
+        for presence in &presence {
+            state.apply_presence(presence);
+        }
+        for status in &statuses {
+            state.apply_status(status);
+        }
+    }
+
+    // Conversations created, changed or left elsewhere, e.g. a new DM after
+    // a contact accepted our friend request
+    if let Some(ref mut client) = state.message_sync_client {
+        client.subscribe_to_conversation_events();
+        let events = client.poll_conversation_events();
+        for event in events {
+            state.apply_conversation_event(event);
+        }
+    }
+
+    // Push our status to the server for contacts to see
+    if let Some(request) = state.pending_status_update.take() {
+        let config_clone = config.clone();
+        std::thread::spawn(move || {
+            let client = FriendApiClient::new(config_clone);
+            if let Err(e) = client.set_status(&request) {
+                tracing::warn!("Failed to sync status: {}", e);
+            }
+        });
+    }
+
+    // Tell the server we're still here, and find out whether we're online
+    if state.heartbeat_due(std::time::Instant::now()) {
+        let config_clone = config.clone();
+        let sender = state.connectivity_sender();
+        std::thread::spawn(move || {
+            let client = FriendApiClient::new(config_clone);
+            let _ = sender.send(client.check_connectivity());
+        });
+    }
+
+    // Push manual-unread changes to the server
+    for (conversation_id, unread) in std::mem::take(&mut state.pending_unread_updates) {
+        let config_clone = config.clone();
+        std::thread::spawn(move || {
+            let client = FriendApiClient::new(config_clone);
+            if let Err(e) = client.set_conversation_unread(conversation_id, unread) {
+                tracing::warn!("Failed to sync unread marker for {}: {}", conversation_id, e);
+            }
+        });
+    }
+
+    // Push theme color changes to the server
+    for (conversation_id, color) in std::mem::take(&mut state.pending_theme_updates) {
+        let config_clone = config.clone();
+        std::thread::spawn(move || {
+            let client = FriendApiClient::new(config_clone);
+            if let Err(e) = client.set_conversation_theme(conversation_id, color) {
+                tracing::warn!("Failed to sync theme color for {}: {}", conversation_id, e);
+            }
+        });
+    }
+
+    // Push pins, then the pinned order, to the server in one go so the
+    // order applies to the new pins
+    let pin_updates = std::mem::take(&mut state.pending_pin_updates);
+    let pin_order = state.pending_pin_order.take();
+    if !pin_updates.is_empty() || pin_order.is_some() {
+        let config_clone = config.clone();
+        std::thread::spawn(move || {
+            let client = FriendApiClient::new(config_clone);
+            for (conversation_id, pinned) in pin_updates {
+                if let Err(e) = client.set_conversation_pinned(conversation_id, pinned) {
+                    tracing::warn!("Failed to sync pin for {}: {}", conversation_id, e);
+                }
+            }
+            if let Some(order) = pin_order {
+                if let Err(e) = client.reorder_pinned_conversations(order) {
+                    tracing::warn!("Failed to sync pinned conversation order: {}", e);
+                }
+            }
+        });
+    }
+
+    // Push group membership changes to the server
+    for (conversation_id, user_id, added) in std::mem::take(&mut state.pending_membership_updates) {
+        let config_clone = config.clone();
+        std::thread::spawn(move || {
+            let client = FriendApiClient::new(config_clone);
+            let result = if added {
+                client.add_conversation_participant(conversation_id, user_id)
+            } else {
+                client.remove_conversation_participant(conversation_id, user_id)
+            };
+            if let Err(e) = result {
+                tracing::warn!("Failed to sync membership of {} in {}: {}", user_id, conversation_id, e);
+            }
+        });
+    }
+
+    // Translate messages into the user's locale
+    for (conversation_id, message_id) in std::mem::take(&mut state.pending_translations) {
+        let config_clone = config.clone();
+        let sender = state.translation_sender();
+        std::thread::spawn(move || {
+            let client = FriendApiClient::new(config_clone.clone());
+            let result = client.translate_message(conversation_id, message_id, config_clone.locale());
+            let _ = sender.send((message_id, result));
+        });
+    }
+
+    // Stream a summary of unread messages into the summary panel
+    if let Some((conversation_id, from, to)) = state.pending_summary.take() {
+        let config_clone = config.clone();
+        let sender = state.summary_sender();
+        std::thread::spawn(move || {
+            let client = FriendApiClient::new(config_clone);
+            let result = client.summarize_conversation(conversation_id, Some(from), Some(to), |text| {
+                let _ = sender.send(SummaryUpdate::Chunk(conversation_id, text.to_string()));
+            });
+            let _ = sender.send(SummaryUpdate::Finished(conversation_id, result));
+        });
+    }
+
+    // Load a message's edit history into the history popup
+    if let Some((conversation_id, message_id)) = state.pending_edit_history.take() {
+        let config_clone = config.clone();
+        let sender = state.edit_history_sender();
+        std::thread::spawn(move || {
+            let client = FriendApiClient::new(config_clone);
+            let result = client.get_edit_history(conversation_id, message_id);
+            let _ = sender.send((message_id, result));
+        });
+    }
+
+    // PUT messages the conversation already shows as pending, in the order written
+    if let Some(client) = state.message_sync_client.as_ref().filter(|_| !state.pending_sends.is_empty()) {
+        let sender = client.sender();
+        let results = state.send_result_sender();
+        let messages = std::mem::take(&mut state.pending_sends);
+        std::thread::spawn(move || {
+            for message in messages {
+                let result = sender.send(message.conversation_id, message.id, &message.content, &message.message_type, None);
+                let _ = results.send((message.conversation_id, message.id, result));
+            }
+        });
+    }
+
+    // Reload a conversation's newest messages after a reconnect, on focus or on request
+    if let Some(conversation_id) = state.pending_resync.take() {
+        let config_clone = config.clone();
+        let sender = state.resync_sender();
+        std::thread::spawn(move || {
+            let client = FriendApiClient::new(config_clone.clone());
+            let result = client.get_latest_messages(conversation_id, config_clone.snapshot_limit());
+            let _ = sender.send((conversation_id, result));
+        });
+    }
+
+    // Write a conversation export chosen from the chat header menu
+    if let Some((conversation_id, format, path)) = state.pending_export.take() {
+        let sender = state.export_sender();
+        std::thread::spawn(move || {
+            let result = tokio::runtime::Runtime::new()
+                .map_err(|e| format!("Failed to create runtime: {}", e))
+                .and_then(|rt| {
+                    rt.block_on(async {
+                        let db = LocalDatabase::new().await.map_err(|e| format!("Failed to open local database: {}", e))?;
+                        db.export_conversation(&conversation_id, format).await.map_err(|e| e.to_string())
+                    })
+                })
+                .and_then(|contents| std::fs::write(&path, contents).map_err(|e| e.to_string()))
+                .map(|()| path);
+            let _ = sender.send(result);
+        });
+    }
+
+    // Push blocks and unblocks to the server
+    for (user_id, blocked) in std::mem::take(&mut state.pending_block_updates) {
+        let config_clone = config.clone();
+        std::thread::spawn(move || {
+            let client = FriendApiClient::new(config_clone);
+            if let Err(e) = client.set_user_blocked(user_id, blocked) {
+                tracing::warn!("Failed to sync block of {}: {}", user_id, e);
+            }
+        });
+    }
+
+    // Push the privacy setting to the server
+    if let Some(settings) = state.pending_privacy_update.take() {
+        let config_clone = config.clone();
+        std::thread::spawn(move || {
+            let client = FriendApiClient::new(config_clone);
+            if let Err(e) = client.set_privacy_settings(settings) {
+                tracing::warn!("Failed to sync privacy setting: {}", e);
+            }
+        });
+    }
 
     // Refresh friend requests when panel is shown
     if state.show_friend_requests_panel && state.pending_load_requests.is_none() {
@@ -141,7 +442,9 @@ fn load_initial_data(state: &mut MessagingState, config: &Config) {
     // Initialize message sync client
     if state.message_sync_client.is_none() {
         tracing::info!("[BRAID] Initializing message sync client");
-        state.message_sync_client = Some(MessageSyncClient::new(config.clone()));
+        let mut client = MessageSyncClient::new(config.clone(), ReconnectPolicy::default());
+        client.heartbeat = HeartbeatPolicy::from_env();
+        state.message_sync_client = Some(client);
         tracing::info!("[BRAID] Message sync client initialized successfully");
     } else {
         tracing::info!("[BRAID] Message sync client already exists");
@@ -157,6 +460,24 @@ fn load_initial_data(state: &mut MessagingState, config: &Config) {
     });
     state.pending_load_requests = Some(rx);
 
+    // Load the privacy setting
+    let config_clone = config.clone();
+    let (tx, rx) = channel();
+    std::thread::spawn(move || {
+        let client = FriendApiClient::new(config_clone);
+        let _ = tx.send(client.get_privacy_settings());
+    });
+    state.pending_load_privacy = Some(rx);
+
+    // Load blocked users
+    let config_clone = config.clone();
+    let (tx, rx) = channel();
+    std::thread::spawn(move || {
+        let client = FriendApiClient::new(config_clone);
+        let _ = tx.send(client.get_blocked_users());
+    });
+    state.pending_load_blocked = Some(rx);
+
     // Load contacts
     load_contacts(state, config);
     
@@ -165,12 +486,28 @@ fn load_initial_data(state: &mut MessagingState, config: &Config) {
 }
 
 /// Load or reload contacts
+///
+/// Their presence is read fresh right after, so the list starts out
+/// accurate instead of waiting for presence events.
 fn load_contacts(state: &mut MessagingState, config: &Config) {
     let config_clone = config.clone();
     let (tx, rx) = channel();
     std::thread::spawn(move || {
         let client = FriendApiClient::new(config_clone);
-        let result = client.get_contacts().map_err(|e| e.to_string());
+        let result = client.get_contacts().map(|mut contacts| {
+            let user_ids: Vec<_> = contacts.iter().map(|c| c.contact_user_id).collect();
+            match client.get_presence(&user_ids) {
+                Ok(presence) => {
+                    for p in presence {
+                        if let Some(contact) = contacts.iter_mut().find(|c| c.contact_user_id == p.user_id) {
+                            contact.is_online = p.is_online;
+                        }
+                    }
+                }
+                Err(e) => tracing::debug!("Failed to load contact presence: {}", e),
+            }
+            contacts
+        });
         let _ = tx.send(result);
     });
     state.pending_load_contacts = Some(rx);
@@ -196,6 +533,74 @@ fn render_modals(ui: &mut egui::Ui, state: &mut MessagingState, config: &Config)
     if state.show_add_friend_modal {
         render_add_friend_modal(ui, state, config);
     }
+
+    // New group modal
+    if state.show_new_group_modal {
+        render_new_group_modal(ui, state, config);
+    }
+
+    // Summary of unread messages
+    if state.summary.is_some() {
+        render_summary_panel(ui, state);
+    }
+
+    // Earlier versions of an edited message
+    if let Some(history) = state.edit_history.as_ref() {
+        let current = state
+            .messages
+            .get(&history.conversation_id)
+            .and_then(|messages| messages.iter().find(|m| m.id == history.message_id));
+        if !edit_history::render(ui.ctx(), history, current) {
+            state.edit_history = None;
+        }
+    }
+
+    // Merge conflicts, oldest first
+    if let Some(conflict) = state.pending_conflicts.first() {
+        if let Some(resolution) = conflict_dialog::render(ui.ctx(), conflict, state.pending_conflicts.len()) {
+            if let Err(e) = state.resolve_conflict(0, resolution) {
+                state.ui_error = Some(e);
+            }
+        }
+    }
+}
+
+/// Render the unread summary panel; closing it discards the summary
+fn render_summary_panel(ui: &mut egui::Ui, state: &mut MessagingState) {
+    let Some(summary) = state.summary.as_ref() else {
+        return;
+    };
+    let mut open = true;
+    egui::Window::new("Unread Summary")
+        .open(&mut open)
+        .collapsible(false)
+        .resizable(true)
+        .default_size(egui::vec2(360.0, 240.0))
+        .anchor(egui::Align2::RIGHT_TOP, [-10.0, 60.0])
+        .show(ui.ctx(), |ui| {
+            egui::ScrollArea::vertical().stick_to_bottom(true).show(ui, |ui| {
+                if !summary.text.is_empty() {
+                    ui.label(&summary.text);
+                }
+                match &summary.status {
+                    SummaryStatus::Streaming => {
+                        ui.spinner();
+                        // Keep drawing as text arrives
+                        ui.ctx().request_repaint();
+                    }
+                    SummaryStatus::Done => {}
+                    SummaryStatus::Empty => {
+                        ui.label(egui::RichText::new("Nothing unread to summarize").weak());
+                    }
+                    SummaryStatus::Failed(e) => {
+                        ui.colored_label(egui::Color32::RED, e);
+                    }
+                }
+            });
+        });
+    if !open {
+        state.summary = None;
+    }
 }
 
 /// Refresh friend requests
@@ -210,7 +615,86 @@ fn refresh_friend_requests(state: &mut MessagingState, config: &Config) {
     state.pending_load_requests = Some(rx);
 }
 
+/// Render the new group modal
+fn render_new_group_modal(ui: &mut egui::Ui, state: &mut MessagingState, config: &Config) {
+    egui::Window::new("New Group")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(ui.ctx(), |ui| {
+            ui.set_min_width(300.0);
+
+            ui.vertical(|ui| {
+                let enabled = !state.is_creating_group;
+
+                ui.label("Group name (optional):");
+                ui.add_space(4.0);
+                ui.add_enabled(enabled, egui::TextEdit::singleline(&mut state.new_group_name));
+                ui.add_space(8.0);
+
+                ui.label("Members:");
+                ui.add_space(4.0);
+                let mut toggled = None;
+                egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                    for contact in &state.contacts {
+                        let mut checked = state.new_group_members.contains(&contact.contact_user_id);
+                        let name = contact.display_name.as_ref().unwrap_or(&contact.username);
+                        if ui.add_enabled(enabled, egui::Checkbox::new(&mut checked, name.as_str())).changed() {
+                            toggled = Some(contact.contact_user_id);
+                        }
+                    }
+                });
+                if let Some(user_id) = toggled {
+                    state.toggle_new_group_member(user_id);
+                }
+                ui.add_space(8.0);
+
+                if let Some(ref error) = state.new_group_error {
+                    ui.colored_label(egui::Color32::RED, error);
+                    ui.add_space(8.0);
+                }
+
+                if state.is_creating_group {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Creating group...");
+                    });
+                } else {
+                    ui.horizontal(|ui| {
+                        if ui.button("Cancel").clicked() {
+                            state.close_new_group_modal();
+                        }
+
+                        if ui.button("Create").clicked() {
+                            if state.new_group_members.is_empty() {
+                                state.new_group_error = Some("Pick at least one contact".to_string());
+                            } else {
+                                state.is_creating_group = true;
+                                state.new_group_error = None;
+
+                                let members = state.new_group_members.clone();
+                                let name = Some(state.new_group_name.trim().to_string()).filter(|n| !n.is_empty());
+                                let config_clone = config.clone();
+                                let (tx, rx) = channel();
+
+                                std::thread::spawn(move || {
+                                    let client = FriendApiClient::new(config_clone);
+                                    let _ = tx.send(client.create_conversation(members, name));
+                                });
+
+                                state.pending_create_conversation = Some(rx);
+                            }
+                        }
+                    });
+                }
+            });
+        });
+}
+
 /// Render the add friend modal
+///
+/// Typing searches usernames and emails; picking a result sends the
+/// request to that user, otherwise the text must be an email address.
 fn render_add_friend_modal(ui: &mut egui::Ui, state: &mut MessagingState, config: &Config) {
     egui::Window::new("Add Friend")
         .collapsible(false)
@@ -220,11 +704,23 @@ fn render_add_friend_modal(ui: &mut egui::Ui, state: &mut MessagingState, config
             ui.set_min_width(300.0);
 
             ui.vertical(|ui| {
-                ui.label("Enter your friend's email address:");
+                ui.label("Search by username or email:");
                 ui.add_space(8.0);
 
                 let enabled = !state.is_sending_friend_request;
                 ui.add_enabled(enabled, egui::TextEdit::singleline(&mut state.add_friend_email));
+
+                // Editing the text after picking someone goes back to searching
+                if state.add_friend_selected.as_ref().is_some_and(|p| p.username != state.add_friend_email) {
+                    state.add_friend_selected = None;
+                }
+                if state.add_friend_selected.is_none() {
+                    state.add_friend_search.set_query(&state.add_friend_email);
+                    if state.add_friend_search.poll(config) {
+                        ui.ctx().request_repaint_after(std::time::Duration::from_millis(50));
+                    }
+                    render_add_friend_results(ui, state, enabled);
+                }
                 ui.add_space(8.0);
 
                 ui.label("Add a message (optional):");
@@ -254,23 +750,35 @@ fn render_add_friend_modal(ui: &mut egui::Ui, state: &mut MessagingState, config
                         }
 
                         if ui.button("Send Request").clicked() {
-                            if state.add_friend_email.is_empty() {
-                                state.add_friend_error = Some("Please enter an email address".to_string());
-                            } else if !state.add_friend_email.contains('@') {
-                                state.add_friend_error = Some("Please enter a valid email address".to_string());
+                            let to_user_id = state.add_friend_selected.as_ref().map(|p| p.user_id);
+                            if to_user_id.is_none() && state.add_friend_email.is_empty() {
+                                state.add_friend_error = Some("Please enter a username or email address".to_string());
+                            } else if to_user_id.is_none() && !state.add_friend_email.contains('@') {
+                                state.add_friend_error = Some("Pick someone from the results or enter an email address".to_string());
+                            } else if !state.is_online {
+                                // Queued requests go by user id; emails need the server to look up
+                                match to_user_id {
+                                    Some(user_id) => {
+                                        let message = Some(state.add_friend_message.trim().to_string()).filter(|m| !m.is_empty());
+                                        state.queue_friend_request(user_id, message);
+                                    }
+                                    None => {
+                                        state.add_friend_error = Some("You're offline - reconnect to send a request by email".to_string());
+                                    }
+                                }
                             } else {
                                 // Send the friend request
                                 state.is_sending_friend_request = true;
                                 state.add_friend_error = None;
                                 state.add_friend_success = None;
 
-                                let email = state.add_friend_email.clone();
+                                let email = if to_user_id.is_some() { String::new() } else { state.add_friend_email.clone() };
                                 let config_clone = config.clone();
                                 let (tx, rx) = channel();
 
                                 std::thread::spawn(move || {
                                     let client = FriendApiClient::new(config_clone);
-                                    let result = client.send_friend_request(&email);
+                                    let result = client.send_friend_request(&email, to_user_id);
                                     let mapped_result = match result {
                                         Ok(resp) if resp.success => Ok(()),
                                         Ok(resp) => Err(resp.error.unwrap_or("Unknown error".to_string())),
@@ -288,3 +796,42 @@ fn render_add_friend_modal(ui: &mut egui::Ui, state: &mut MessagingState, config
         });
 }
 
+/// Render users matching the add-friend search; clicking one picks them
+fn render_add_friend_results(ui: &mut egui::Ui, state: &mut MessagingState, enabled: bool) {
+    let search = &state.add_friend_search;
+    if search.is_searching() && search.results.is_empty() {
+        ui.horizontal(|ui| {
+            ui.spinner();
+            ui.label("Searching...");
+        });
+        return;
+    }
+    if let Some(ref error) = search.error {
+        ui.colored_label(egui::Color32::RED, error);
+        return;
+    }
+    if search.results.is_empty() {
+        if is_searchable(&state.add_friend_email) && !search.is_searching() {
+            ui.label("No matching users");
+        }
+        return;
+    }
+
+    let mut picked = None;
+    egui::ScrollArea::vertical()
+        .id_salt("add_friend_results")
+        .max_height(160.0)
+        .show(ui, |ui| {
+            for profile in &search.results {
+                let label = format!("{}  {}", profile.avatar_initial(), profile.username);
+                if ui.add_enabled(enabled, egui::Button::selectable(false, label)).clicked() {
+                    picked = Some(profile.clone());
+                }
+            }
+        });
+
+    if let Some(profile) = picked {
+        state.select_add_friend_result(profile);
+    }
+}
+
@@ -0,0 +1,230 @@
+//! Mention Autocomplete
+//!
+//! Typing `@` in the composer opens a popup of conversation participants
+//! filtered by what follows it. Matching is a fuzzy subsequence match on the
+//! username and display name. Choosing a candidate replaces the `@query` with
+//! `@username` and remembers who was picked; when the message is sent,
+//! [`serialize_mentions`] turns those into structured mention tokens (see
+//! [`crate::shared::messaging::mention`]).
+//!
+//! Cursor positions are character indices, matching egui's `CCursor`.
+
+use uuid::Uuid;
+
+use crate::shared::messaging::mention_token;
+
+/// Most candidates shown in the popup
+pub const MAX_CANDIDATES: usize = 5;
+
+/// A participant that can be mentioned
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MentionCandidate {
+    pub user_id: Uuid,
+    pub username: String,
+    pub display_name: Option<String>,
+}
+
+impl MentionCandidate {
+    /// Name shown in the popup
+    pub fn label(&self) -> String {
+        match &self.display_name {
+            Some(name) if name != &self.username => format!("{} (@{})", name, self.username),
+            _ => format!("@{}", self.username),
+        }
+    }
+}
+
+/// The `@query` being typed at the cursor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MentionQuery {
+    /// Character index of the `@`
+    pub start: usize,
+    /// Text between the `@` and the cursor
+    pub query: String,
+}
+
+/// The mention being typed just before `cursor`, if any
+///
+/// The `@` must start the text or follow whitespace, so email addresses don't
+/// trigger the popup, and the query ends at the first whitespace.
+pub fn active_query(text: &str, cursor: usize) -> Option<MentionQuery> {
+    let before: Vec<char> = text.chars().take(cursor).collect();
+    let at = before.iter().rposition(|c| *c == '@' || c.is_whitespace())?;
+    if before[at] != '@' || (at > 0 && !before[at - 1].is_whitespace()) {
+        return None;
+    }
+    Some(MentionQuery {
+        start: at,
+        query: before[at + 1..].iter().collect(),
+    })
+}
+
+/// Fuzzy subsequence score of `query` against `name`; higher is better
+///
+/// Every query character must appear in order. Matches at the start of the
+/// name and consecutive matches score higher.
+fn fuzzy_score(query: &str, name: &str) -> Option<i32> {
+    let name: Vec<char> = name.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut next = 0;
+    let mut previous: Option<usize> = None;
+
+    for q in query.to_lowercase().chars() {
+        let found = next + name[next..].iter().position(|c| *c == q)?;
+        score += match (found, previous) {
+            (0, _) => 10,
+            (i, Some(p)) if i == p + 1 => 5,
+            _ => 1,
+        };
+        previous = Some(found);
+        next = found + 1;
+    }
+
+    // Prefer shorter names when the match is equally good
+    Some(score * 100 - name.len() as i32)
+}
+
+/// Candidates matching `query`, best first, at most [`MAX_CANDIDATES`]
+pub fn match_candidates(query: &str, candidates: &[MentionCandidate]) -> Vec<MentionCandidate> {
+    let mut scored: Vec<(i32, &MentionCandidate)> = candidates
+        .iter()
+        .filter_map(|candidate| {
+            let by_username = fuzzy_score(query, &candidate.username);
+            let by_display = candidate.display_name.as_deref().and_then(|name| fuzzy_score(query, name));
+            by_username.max(by_display).map(|score| (score, candidate))
+        })
+        .collect();
+    scored.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.username.cmp(&b.1.username)));
+    scored.into_iter().take(MAX_CANDIDATES).map(|(_, c)| c.clone()).collect()
+}
+
+/// Replace the `@query` ending at `cursor` with `@username` of `candidate`
+///
+/// Returns the new text and the cursor position just after the mention and
+/// its trailing space.
+pub fn insert_mention(text: &str, query: &MentionQuery, cursor: usize, candidate: &MentionCandidate) -> (String, usize) {
+    let byte_index = |chars: usize| text.char_indices().nth(chars).map_or(text.len(), |(i, _)| i);
+    let (start, end) = (byte_index(query.start), byte_index(cursor));
+
+    let mention = format!("@{}", candidate.username);
+    let rest = &text[end..];
+    let separator = if rest.starts_with(char::is_whitespace) { "" } else { " " };
+
+    let new_text = format!("{}{}{}{}", &text[..start], mention, separator, rest);
+    let new_cursor = query.start + mention.chars().count() + 1;
+    (new_text, new_cursor)
+}
+
+/// Replace `@username` for each chosen mention with its structured token
+///
+/// Mentions the user deleted from the draft are simply not found; a plain
+/// `@name` that was never chosen from the popup is left as text.
+pub fn serialize_mentions(text: &str, chosen: &[MentionCandidate]) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut previous: Option<char> = None;
+    let mut rest = text;
+
+    while let Some(c) = rest.chars().next() {
+        let at_word_start = previous.is_none_or(char::is_whitespace);
+        if c == '@' && at_word_start {
+            let word_len = rest.find(|c: char| c.is_whitespace()).unwrap_or(rest.len());
+            let name = rest[1..word_len].trim_end_matches(|c: char| !c.is_alphanumeric() && c != '_');
+            if let Some(candidate) = chosen.iter().find(|m| !name.is_empty() && m.username == name) {
+                out.push_str(&mention_token(&candidate.username, candidate.user_id));
+                rest = &rest[1 + name.len()..];
+                previous = Some('@');
+                continue;
+            }
+        }
+        out.push(c);
+        previous = Some(c);
+        rest = &rest[c.len_utf8()..];
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::messaging::parse_mentions;
+
+    fn candidate(username: &str, display_name: Option<&str>) -> MentionCandidate {
+        MentionCandidate {
+            user_id: Uuid::new_v4(),
+            username: username.to_string(),
+            display_name: display_name.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_active_query() {
+        assert_eq!(active_query("hi @al", 6), Some(MentionQuery { start: 3, query: "al".to_string() }));
+        assert_eq!(active_query("@", 1), Some(MentionQuery { start: 0, query: String::new() }));
+        // Cursor moved back inside the query
+        assert_eq!(active_query("hi @alice", 5), Some(MentionQuery { start: 3, query: "a".to_string() }));
+        assert_eq!(active_query("hi @al ", 7), None);
+        assert_eq!(active_query("bob@example", 11), None);
+        assert_eq!(active_query("no mention", 10), None);
+    }
+
+    #[test]
+    fn test_fuzzy_matching_ranks_prefix_first() {
+        let candidates = vec![
+            candidate("malice", None),
+            candidate("alice", None),
+            candidate("bob", Some("Alan Bobson")),
+            candidate("carol", None),
+        ];
+
+        let names: Vec<String> = match_candidates("al", &candidates).into_iter().map(|c| c.username).collect();
+        assert_eq!(names, ["alice", "bob", "malice", "carol"]);
+
+        // Subsequence match
+        let names: Vec<String> = match_candidates("crl", &candidates).into_iter().map(|c| c.username).collect();
+        assert_eq!(names, ["carol"]);
+
+        assert!(match_candidates("zz", &candidates).is_empty());
+        assert_eq!(match_candidates("", &candidates).len(), 4);
+    }
+
+    #[test]
+    fn test_insert_mention_places_cursor_after_mention() {
+        let alice = candidate("alice", None);
+        let text = "hé @al see you";
+        let query = active_query(text, 6).unwrap();
+
+        let (new_text, cursor) = insert_mention(text, &query, 6, &alice);
+        assert_eq!(new_text, "hé @alice see you");
+        assert_eq!(cursor, 10);
+        assert_eq!(new_text.chars().nth(cursor - 1), Some(' '));
+
+        // At the end of the text a space is added
+        let (new_text, cursor) = insert_mention("@a", &active_query("@a", 2).unwrap(), 2, &alice);
+        assert_eq!(new_text, "@alice ");
+        assert_eq!(cursor, new_text.chars().count());
+        assert_eq!(active_query(&new_text, cursor), None);
+    }
+
+    #[test]
+    fn test_serialize_mentions() {
+        let alice = candidate("alice", None);
+        let bob = candidate("bob", None);
+        let chosen = vec![alice.clone(), bob.clone()];
+
+        let content = serialize_mentions("@alice, ask @bob and @carol (not bob@example.com or @bobby)", &chosen);
+        assert_eq!(
+            content,
+            format!(
+                "{}, ask {} and @carol (not bob@example.com or @bobby)",
+                mention_token("alice", alice.user_id),
+                mention_token("bob", bob.user_id)
+            )
+        );
+
+        let mentions = parse_mentions(&content);
+        assert_eq!(mentions.len(), 2);
+        assert_eq!(mentions[0].user_id, alice.user_id);
+        assert_eq!(mentions[1].user_id, bob.user_id);
+    }
+}
@@ -9,6 +9,12 @@ pub mod chat_area;
 pub mod components;
 pub mod braid_sync;
 pub mod friend_api;
+pub mod activity;
+pub mod commands;
+pub mod mentions;
+pub mod search;
+pub mod user_search;
+pub mod history;
 
 pub use state::MessagingState;
 pub use main_layout::render_messaging_view;
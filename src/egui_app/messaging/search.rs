@@ -0,0 +1,135 @@
+//! Message Search
+//!
+//! Searches message bodies in the local database as the user types in the
+//! sidebar search bar. Queries run on a background worker thread that keeps
+//! the database open; results for anything but the latest query are dropped.
+//! Typing is debounced so every keystroke doesn't start a search.
+
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::egui_app::local_db::search::MessageSearchHit;
+use crate::egui_app::local_db::LocalDatabase;
+
+/// Most message hits shown under the contacts
+pub const MAX_RESULTS: usize = 20;
+
+/// Pause in typing before a search starts
+pub const DEBOUNCE: Duration = Duration::from_millis(250);
+
+type SearchResult = (String, Result<Vec<MessageSearchHit>, String>);
+
+/// Message search driven by the sidebar query
+#[derive(Default)]
+pub struct MessageSearch {
+    /// Trimmed query the results should match
+    query: String,
+    /// When `query` last changed
+    changed_at: Option<Instant>,
+    /// Query sent to the worker and not yet answered
+    in_flight: Option<String>,
+    /// Hits for `query`
+    pub results: Vec<MessageSearchHit>,
+    pub error: Option<String>,
+    worker: Option<(Sender<String>, Receiver<SearchResult>)>,
+}
+
+impl MessageSearch {
+    /// Follow the search bar text; call every frame
+    pub fn set_query(&mut self, query: &str) {
+        let query = query.trim();
+        if query == self.query {
+            return;
+        }
+        self.query = query.to_string();
+        self.changed_at = Some(Instant::now());
+        if query.is_empty() {
+            self.results.clear();
+            self.error = None;
+        }
+    }
+
+    /// Whether a search for the current query hasn't finished yet
+    pub fn is_searching(&self) -> bool {
+        !self.query.is_empty() && (self.changed_at.is_some() || self.in_flight.is_some())
+    }
+
+    /// Collect finished searches and start a pending one once typing pauses
+    ///
+    /// Returns true while there is more to do, so the caller keeps repainting.
+    pub fn poll(&mut self) -> bool {
+        if let Some((_, results)) = &self.worker {
+            for (query, result) in results.try_iter() {
+                if self.in_flight.as_deref() == Some(query.as_str()) {
+                    self.in_flight = None;
+                }
+                if query != self.query {
+                    continue;
+                }
+                match result {
+                    Ok(hits) => {
+                        self.results = hits;
+                        self.error = None;
+                    }
+                    Err(e) => self.error = Some(e),
+                }
+            }
+        }
+
+        let Some(changed_at) = self.changed_at else {
+            return self.in_flight.is_some();
+        };
+        if self.query.is_empty() {
+            self.changed_at = None;
+            return false;
+        }
+        if changed_at.elapsed() >= DEBOUNCE && self.in_flight.is_none() {
+            let query = self.query.clone();
+            let (queries, _) = self.worker.get_or_insert_with(spawn_worker);
+            if queries.send(query.clone()).is_err() {
+                self.worker = None;
+                self.error = Some("Message search stopped".to_string());
+            } else {
+                self.in_flight = Some(query);
+            }
+            self.changed_at = None;
+        }
+        true
+    }
+}
+
+/// Thread that opens the local database once and answers queries in order
+fn spawn_worker() -> (Sender<String>, Receiver<SearchResult>) {
+    let (query_tx, query_rx) = mpsc::channel::<String>();
+    let (result_tx, result_rx) = mpsc::channel();
+
+    thread::spawn(move || {
+        let rt = match tokio::runtime::Runtime::new() {
+            Ok(rt) => rt,
+            Err(e) => {
+                let error = format!("Failed to create runtime: {}", e);
+                for query in query_rx {
+                    if result_tx.send((query, Err(error.clone()))).is_err() {
+                        break;
+                    }
+                }
+                return;
+            }
+        };
+        rt.block_on(async {
+            let db = LocalDatabase::new().await.map_err(|e| format!("Failed to open local database: {}", e));
+            while let Ok(query) = query_rx.recv() {
+                let result = match &db {
+                    Ok(db) => db.search_messages(&query, MAX_RESULTS).await.map_err(|e| e.to_string()),
+                    Err(e) => Err(e.clone()),
+                };
+                if result_tx.send((query, result)).is_err() {
+                    break;
+                }
+            }
+        });
+    });
+
+    (query_tx, result_rx)
+}
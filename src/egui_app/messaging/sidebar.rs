@@ -1,12 +1,13 @@
 //! Sidebar Component
 //!
-//! This module contains the sidebar with search bar, contact list, and add friend button.
+//! This module contains the sidebar with search bar, contact list, and add friend and new group buttons.
 
 use eframe::egui;
 use super::state::MessagingState;
 use super::components::{search_bar, contact_list, friend_requests_panel};
 use crate::egui_app::config::Config;
 use crate::egui_app::theme::styles;
+use crate::shared::messaging::MessagePrivacy;
 
 /// Render the sidebar
 pub fn render_sidebar(ui: &mut egui::Ui, state: &mut MessagingState, config: &Config) {
@@ -36,6 +37,44 @@ pub fn render_sidebar(ui: &mut egui::Ui, state: &mut MessagingState, config: &Co
                 if ui.button("➕").clicked() {
                     state.open_add_friend_modal();
                 }
+
+                // New group button
+                if ui.button("👥").on_hover_text("New group").clicked() {
+                    state.open_new_group_modal();
+                }
+
+                // Who can message me, and what I share
+                ui.menu_button("🛡", |ui| {
+                    ui.label("Who can message me");
+                    match state.privacy {
+                        Some(mut settings) => {
+                            for privacy in MessagePrivacy::ALL {
+                                if ui.radio(settings.message_privacy == privacy, privacy.label()).clicked() {
+                                    state.set_message_privacy(privacy);
+                                    ui.close();
+                                }
+                            }
+                            ui.separator();
+                            let mut changed = ui.checkbox(&mut settings.send_typing, "Send typing indicators").changed();
+                            changed |= ui
+                                .checkbox(&mut settings.send_presence, "Show when I'm online")
+                                .on_hover_text("Off, you appear offline and your status isn't shared")
+                                .changed();
+                            changed |= ui
+                                .checkbox(&mut settings.send_read_receipts, "Send read receipts")
+                                .on_hover_text("Off, you won't see others' read receipts either")
+                                .changed();
+                            if changed {
+                                state.set_privacy(settings);
+                            }
+                        }
+                        None => {
+                            ui.spinner();
+                        }
+                    }
+                })
+                .response
+                .on_hover_text("Who can message you and what activity you share");
             });
         });
         
@@ -56,7 +95,8 @@ pub fn render_sidebar(ui: &mut egui::Ui, state: &mut MessagingState, config: &Co
     egui::ScrollArea::vertical()
         .auto_shrink([false, false])
         .show(ui, |ui| {
-            contact_list::render(ui, state);
+            contact_list::render(ui, state, config);
+            search_bar::render_message_results(ui, state);
         });
 }
 
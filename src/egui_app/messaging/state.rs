@@ -2,11 +2,28 @@
 //!
 //! This module contains the state management for the messaging UI.
 
-use crate::shared::messaging::{Contact, ChatMessage, Conversation, FriendRequest};
-use std::collections::{HashMap, VecDeque};
-use std::sync::mpsc::Receiver;
+use crate::shared::messaging::{Contact, ChatMessage, Conversation, FriendRequest, MessageEdit, MessagePrivacy, MessageType, PrivacySettings, SetStatusRequest, ThemeColor, UserProfile, UserStatus};
+use crate::shared::{ConversationEvent, DeliveryReceiptEvent, PresenceEvent, ReadReceiptEvent, PRESENCE_HEARTBEAT_SECS};
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::mpsc::{channel, Receiver, Sender};
 use uuid::Uuid;
-use super::braid_sync::{MessageSyncClient, SubscriptionStatus};
+use super::braid_sync::{MessageSyncClient, SendError, SubscriptionStatus};
+use crate::egui_app::crdt::message_crdt::CompactionPolicy;
+use crate::egui_app::crdt::{message_crdt, ConversationCrdt, CrdtState, MergeResult, MessageCrdt, PresenceStatus, Resolution, UserStateCrdt};
+use crate::egui_app::deep_link::{DeepLink, NavigationResult};
+use crate::egui_app::local_db::export::ExportFormat;
+use crate::egui_app::media::MediaLoader;
+use crate::egui_app::notifications::{NotificationLevel, Notifications};
+use crate::egui_app::offline::Operation;
+use crate::egui_app::theme::colors::ConversationTheme;
+use super::activity::{ActivityTracker, RemoteActivity};
+use super::commands::CommandRegistry;
+use super::mentions::{MentionCandidate, MentionQuery};
+use super::search::MessageSearch;
+use super::user_search::UserSearch;
+use super::history::MessageHistory;
 // use crate::egui_app::config::Config; // Currently unused
 
 /// Pending API operation result types
@@ -14,6 +31,65 @@ pub type FriendRequestResult = Result<(), String>;
 pub type LoadRequestsResult = Result<Vec<FriendRequest>, String>;
 pub type LoadContactsResult = Result<Vec<Contact>, String>;
 pub type LoadConversationsResult = Result<Vec<Conversation>, String>;
+pub type LoadPrivacyResult = Result<PrivacySettings, String>;
+pub type LoadBlockedResult = Result<Vec<Uuid>, String>;
+pub type CreateConversationResult = Result<Conversation, String>;
+pub type TranslationResult = (Uuid, Result<String, String>);
+pub type EditHistoryResult = (Uuid, Result<Vec<MessageEdit>, String>);
+pub type ExportResult = Result<PathBuf, String>;
+pub type ResyncResult = (Uuid, Result<Vec<ChatMessage>, String>);
+pub type SendResult = (Uuid, Uuid, Result<String, SendError>);
+
+/// Progress of a streamed summary, reported by the background request
+#[derive(Debug, Clone, PartialEq)]
+pub enum SummaryUpdate {
+    /// More summary text for a conversation
+    Chunk(Uuid, String),
+    /// The stream ended; `Ok(false)` means there was nothing to summarize
+    Finished(Uuid, Result<bool, String>),
+}
+
+/// Where a conversation summary is up to
+#[derive(Debug, Clone, PartialEq)]
+pub enum SummaryStatus {
+    Streaming,
+    Done,
+    Empty,
+    Failed(String),
+}
+
+/// An AI summary of a conversation's unread messages; shown in a panel, never stored
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConversationSummary {
+    pub conversation_id: Uuid,
+    pub text: String,
+    pub status: SummaryStatus,
+}
+
+/// Prior versions of an edited message; shown in a popup, never stored
+#[derive(Debug, Clone, PartialEq)]
+pub struct EditHistory {
+    pub conversation_id: Uuid,
+    pub message_id: Uuid,
+    pub status: EditHistoryStatus,
+}
+
+/// Where loading an edit history is up to
+#[derive(Debug, Clone, PartialEq)]
+pub enum EditHistoryStatus {
+    Loading,
+    /// Replaced versions, oldest first
+    Loaded(Vec<MessageEdit>),
+    Failed(String),
+}
+
+/// A message's translation into the user's locale; shown inline, never sent
+#[derive(Debug, Clone, PartialEq)]
+pub enum Translation {
+    Pending,
+    Done(String),
+    Failed(String),
+}
 
 /// The main state for the messaging UI
 pub struct MessagingState {
@@ -42,16 +118,45 @@ pub struct MessagingState {
 
     /// Search query for contacts
     pub search_query: String,
+    /// Local full-text search over message bodies for `search_query`
+    pub message_search: MessageSearch,
     /// Message input text
     pub message_input: String,
+    /// `@mention` being typed in the composer, while the popup is open
+    pub mention_query: Option<MentionQuery>,
+    /// Highlighted row in the mention popup
+    pub mention_selected: usize,
+    /// Start of a mention query the user closed with Escape
+    pub mention_dismissed: Option<usize>,
+    /// Participants picked from the popup for the current draft
+    pub composer_mentions: Vec<MentionCandidate>,
+    /// Own message being edited in the composer, if any
+    pub editing_message_id: Option<Uuid>,
+    /// Slash commands available in the composer
+    pub commands: CommandRegistry,
+    /// Highlighted row in the command menu
+    pub command_selected: usize,
+    /// Command menu closed with Escape until the draft stops being a command name
+    pub command_menu_dismissed: bool,
 
     /// Add friend modal state
     pub show_add_friend_modal: bool,
     pub add_friend_email: String,
+    /// Users matching `add_friend_email` as it's typed
+    pub add_friend_search: UserSearch,
+    /// Search result picked to send the request to, instead of an email
+    pub add_friend_selected: Option<UserProfile>,
     pub add_friend_message: String,
     pub add_friend_error: Option<String>,
     pub add_friend_success: Option<String>,
 
+    /// New group modal state
+    pub show_new_group_modal: bool,
+    pub new_group_name: String,
+    pub new_group_members: Vec<Uuid>,
+    pub new_group_error: Option<String>,
+    pub is_creating_group: bool,
+
     /// Friend requests panel state
     pub show_friend_requests_panel: bool,
 
@@ -64,6 +169,8 @@ pub struct MessagingState {
     pub is_loading_messages: bool,
     pub is_sending_message: bool,
     pub is_sending_friend_request: bool,
+    /// Older messages loaded from the local database on scroll
+    pub history: MessageHistory,
 
     /// Pending async operation receivers
     pub pending_send_request: Option<Receiver<FriendRequestResult>>,
@@ -72,6 +179,9 @@ pub struct MessagingState {
     pub pending_load_requests: Option<Receiver<LoadRequestsResult>>,
     pub pending_load_contacts: Option<Receiver<LoadContactsResult>>,
     pub pending_load_conversations: Option<Receiver<LoadConversationsResult>>,
+    pub pending_load_privacy: Option<Receiver<LoadPrivacyResult>>,
+    pub pending_load_blocked: Option<Receiver<LoadBlockedResult>>,
+    pub pending_create_conversation: Option<Receiver<CreateConversationResult>>,
 
     /// Flag to trigger contacts reload on next frame
     pub should_reload_contacts: bool,
@@ -82,8 +192,16 @@ pub struct MessagingState {
     /// Whether initial data has been loaded
     pub initialized: bool,
 
-    /// Offline message queue for when network is unavailable
-    pub offline_queue: VecDeque<ChatMessage>,
+    /// Sends made while offline, waiting to be handed to the sync service's
+    /// persisted queue (see `AppState::check_sync`)
+    pub queued_operations: Vec<Operation>,
+    /// Messages already shown as pending, waiting to be PUT in the background
+    pub pending_sends: Vec<ChatMessage>,
+    /// Our messages the server didn't take; each can be retried
+    pub failed_sends: HashSet<Uuid>,
+    /// Background sends report the version the server gave each message here
+    send_result_sender: Sender<SendResult>,
+    send_result_receiver: Receiver<SendResult>,
 
     /// Network connectivity status
     pub is_online: bool,
@@ -106,6 +224,122 @@ pub struct MessagingState {
     pub subscription_log: Vec<String>,
     /// Remember last status to avoid duplicate log entries
     pub last_subscription_status: Option<SubscriptionStatus>,
+    /// The server rejected our token; the app refreshes it before the
+    /// subscription resumes
+    pub token_rejected: bool,
+
+    /// Deep link waiting for conversations to load
+    pub pending_deep_link: Option<DeepLink>,
+    /// Message the list should scroll to (set by deep links)
+    pub scroll_to_message_id: Option<Uuid>,
+    /// Top message in view while the list is scrolled back from the newest;
+    /// the list returns to it after a resync
+    pub scroll_anchor: Option<Uuid>,
+    /// Conversation that was open last session, selected once conversations load
+    pub restore_conversation_id: Option<Uuid>,
+
+    /// Mirrors `Config::low_data_mode`; views show placeholders instead of media
+    pub low_data_mode: bool,
+    /// Avatar/image/thumbnail downloads
+    pub media: MediaLoader,
+
+    /// Whether a voice message is being recorded
+    pub is_recording_audio: bool,
+    /// Whether an attachment upload is in progress
+    pub is_uploading_file: bool,
+    /// Debounces our outgoing typing/recording/uploading events
+    pub activity: ActivityTracker,
+    /// Other participants' activity in the selected conversation
+    pub remote_activity: RemoteActivity,
+
+    /// Manual-unread changes waiting to be sent to the server
+    pub pending_unread_updates: Vec<(Uuid, bool)>,
+    /// Theme color changes waiting to be sent to the server
+    pub pending_theme_updates: Vec<(Uuid, Option<ThemeColor>)>,
+    /// Pin/unpin changes waiting to be sent to the server
+    pub pending_pin_updates: Vec<(Uuid, bool)>,
+    /// New order of the pinned conversations, waiting to be sent to the server
+    pub pending_pin_order: Option<Vec<Uuid>>,
+    /// Participants added (`true`) or removed, waiting to be sent to the server
+    pub pending_membership_updates: Vec<(Uuid, Uuid, bool)>,
+
+    /// Translations shown under messages, by message id (kept for the session)
+    pub translations: HashMap<Uuid, Translation>,
+    /// Messages (conversation, message) waiting to be sent for translation
+    pub pending_translations: Vec<(Uuid, Uuid)>,
+    /// Background translation requests report back here
+    translation_sender: Sender<TranslationResult>,
+    translation_receiver: Receiver<TranslationResult>,
+
+    /// First message that was unread when each conversation was last opened
+    pub unread_since: HashMap<Uuid, Uuid>,
+    /// Summary shown in the summary panel, if any
+    pub summary: Option<ConversationSummary>,
+    /// Summary request (conversation, from, to) waiting to be sent
+    pub pending_summary: Option<(Uuid, Uuid, Uuid)>,
+    /// Background summary requests stream their text here
+    summary_sender: Sender<SummaryUpdate>,
+    summary_receiver: Receiver<SummaryUpdate>,
+
+    /// Edit history shown in the history popup, if any
+    pub edit_history: Option<EditHistory>,
+    /// Edit history request (conversation, message) waiting to be sent
+    pub pending_edit_history: Option<(Uuid, Uuid)>,
+    /// Background edit history requests report back here
+    edit_history_sender: Sender<EditHistoryResult>,
+    edit_history_receiver: Receiver<EditHistoryResult>,
+
+    /// Conversation whose messages are waiting to be reloaded from the server
+    pub pending_resync: Option<Uuid>,
+    /// The subscription dropped since it last connected; resync once it's back
+    resync_on_connect: bool,
+    /// Background resyncs report the server's messages here
+    resync_sender: Sender<ResyncResult>,
+    resync_receiver: Receiver<ResyncResult>,
+
+    /// Export (conversation, format, destination) waiting to be written
+    pub pending_export: Option<(Uuid, ExportFormat, PathBuf)>,
+    /// Background exports report the file written here
+    export_sender: Sender<ExportResult>,
+    export_receiver: Receiver<ExportResult>,
+
+    /// CRDT replicas of conversations' messages, by conversation
+    pub message_crdts: HashMap<Uuid, MessageCrdt>,
+    /// How far message CRDT operation logs are trimmed after each merge
+    pub crdt_compaction: CompactionPolicy,
+    /// CRDT replicas of conversations' metadata, by conversation
+    pub conversation_crdts: HashMap<Uuid, ConversationCrdt>,
+    /// Merge conflicts waiting for the user, oldest first
+    pub pending_conflicts: Vec<MergeResult>,
+    /// Merges anywhere in the app report conflicts here
+    conflict_sender: Sender<MergeResult>,
+    conflict_receiver: Receiver<MergeResult>,
+
+    /// Who can message us and which activity we share (`None` until loaded)
+    pub privacy: Option<PrivacySettings>,
+    /// Privacy settings waiting to be sent to the server
+    pub pending_privacy_update: Option<PrivacySettings>,
+    /// Users we've blocked, mirrored from the server; they're hidden
+    pub blocked_users: HashSet<Uuid>,
+    /// Blocks (`true`) and unblocks waiting to be sent to the server
+    pub pending_block_updates: Vec<(Uuid, bool)>,
+
+    /// Who's online and their chosen statuses, ours included
+    pub presence: UserStateCrdt,
+    /// Our status change waiting to be sent to the server
+    pub pending_status_update: Option<SetStatusRequest>,
+    /// When we last told the server we're online
+    last_heartbeat: Option<std::time::Instant>,
+    /// Whether heartbeats reach the server, reported by their threads
+    connectivity_sender: Sender<bool>,
+    connectivity_receiver: Receiver<bool>,
+    /// Whether the window had focus last frame
+    window_focused: bool,
+
+    /// Per-conversation notification levels (missing means `All`)
+    pub notification_levels: HashMap<Uuid, NotificationLevel>,
+    /// Desktop notifications for incoming messages
+    pub notifications: Notifications,
 }
 
 impl Default for MessagingState {
@@ -116,6 +350,14 @@ impl Default for MessagingState {
 
 impl MessagingState {
     pub fn new() -> Self {
+        let (conflict_sender, conflict_receiver) = channel();
+        let (translation_sender, translation_receiver) = channel();
+        let (summary_sender, summary_receiver) = channel();
+        let (edit_history_sender, edit_history_receiver) = channel();
+        let (resync_sender, resync_receiver) = channel();
+        let (send_result_sender, send_result_receiver) = channel();
+        let (export_sender, export_receiver) = channel();
+        let (connectivity_sender, connectivity_receiver) = channel();
         Self {
             current_user_id: None,
             current_username: None,
@@ -127,12 +369,28 @@ impl MessagingState {
             incoming_friend_requests: Vec::new(),
             outgoing_friend_requests: Vec::new(),
             search_query: String::new(),
+            message_search: MessageSearch::default(),
             message_input: String::new(),
+            mention_query: None,
+            mention_selected: 0,
+            mention_dismissed: None,
+            composer_mentions: Vec::new(),
+            editing_message_id: None,
+            commands: CommandRegistry::default(),
+            command_selected: 0,
+            command_menu_dismissed: false,
             show_add_friend_modal: false,
             add_friend_email: String::new(),
+            add_friend_search: UserSearch::default(),
+            add_friend_selected: None,
             add_friend_message: String::new(),
             add_friend_error: None,
             add_friend_success: None,
+            show_new_group_modal: false,
+            new_group_name: String::new(),
+            new_group_members: Vec::new(),
+            new_group_error: None,
+            is_creating_group: false,
             show_friend_requests_panel: false,
             show_chat_header_menu: false,
             is_loading_contacts: false,
@@ -140,16 +398,24 @@ impl MessagingState {
             is_loading_messages: false,
             is_sending_message: false,
             is_sending_friend_request: false,
+            history: MessageHistory::default(),
             pending_send_request: None,
             pending_accept_request: None,
             pending_reject_request: None,
             pending_load_requests: None,
             pending_load_contacts: None,
             pending_load_conversations: None,
+            pending_load_privacy: None,
+            pending_load_blocked: None,
+            pending_create_conversation: None,
             should_reload_contacts: false,
             contact_reload_frames: 0,
             initialized: false,
-            offline_queue: VecDeque::new(),
+            queued_operations: Vec::new(),
+            pending_sends: Vec::new(),
+            failed_sends: HashSet::new(),
+            send_result_sender,
+            send_result_receiver,
             is_online: true,
             last_sync_time: Some(std::time::Instant::now()),
             ui_error: None,
@@ -158,9 +424,68 @@ impl MessagingState {
             show_connection_log: false,
             subscription_log: Vec::new(),
             last_subscription_status: None,
+            token_rejected: false,
+            pending_deep_link: None,
+            scroll_to_message_id: None,
+            scroll_anchor: None,
+            restore_conversation_id: None,
+            low_data_mode: false,
+            media: MediaLoader::default(),
+            is_recording_audio: false,
+            is_uploading_file: false,
+            activity: ActivityTracker::new(),
+            remote_activity: RemoteActivity::new(),
+            pending_unread_updates: Vec::new(),
+            pending_theme_updates: Vec::new(),
+            pending_pin_updates: Vec::new(),
+            pending_pin_order: None,
+            pending_membership_updates: Vec::new(),
+            translations: HashMap::new(),
+            pending_translations: Vec::new(),
+            translation_sender,
+            translation_receiver,
+            unread_since: HashMap::new(),
+            summary: None,
+            pending_summary: None,
+            summary_sender,
+            summary_receiver,
+            edit_history: None,
+            pending_edit_history: None,
+            edit_history_sender,
+            edit_history_receiver,
+            pending_resync: None,
+            resync_on_connect: false,
+            resync_sender,
+            resync_receiver,
+            pending_export: None,
+            export_sender,
+            export_receiver,
+            presence: UserStateCrdt::new(0),
+            pending_status_update: None,
+            last_heartbeat: None,
+            connectivity_sender,
+            connectivity_receiver,
+            window_focused: true,
+            message_crdts: HashMap::new(),
+            crdt_compaction: CompactionPolicy::default(),
+            conversation_crdts: HashMap::new(),
+            pending_conflicts: Vec::new(),
+            conflict_sender,
+            conflict_receiver,
+            privacy: None,
+            pending_privacy_update: None,
+            blocked_users: HashSet::new(),
+            pending_block_updates: Vec::new(),
+            notification_levels: HashMap::new(),
+            notifications: Notifications::default(),
         }
     }
     
+    /// The first load of contacts and conversations has finished
+    pub fn initial_data_loaded(&self) -> bool {
+        self.initialized && !self.is_loading_contacts && !self.is_loading_conversations
+    }
+
     /// Get the currently selected conversation
     pub fn selected_conversation(&self) -> Option<&Conversation> {
         self.selected_conversation_id
@@ -173,32 +498,886 @@ impl MessagingState {
             .and_then(|id| self.messages.get(&id))
     }
     
-    /// Get filtered contacts based on search query
+    /// Get filtered contacts based on search query, in sidebar order
     pub fn filtered_contacts(&self) -> Vec<&Contact> {
         let query = self.search_query.to_lowercase().trim().to_string();
         
-        if query.is_empty() {
-            return self.contacts.iter().collect();
-        }
-        
-        self.contacts
+        let mut contacts: Vec<&Contact> = self.contacts
             .iter()
+            .filter(|c| !self.blocked_users.contains(&c.contact_user_id))
             .filter(|c| {
-                c.username.to_lowercase().contains(query.as_str())
+                query.is_empty()
+                    || c.username.to_lowercase().contains(query.as_str())
                     || c.email.to_lowercase().contains(query.as_str())
                     || c.display_name
                         .as_ref()
                         .map(|n| n.to_lowercase().contains(query.as_str()))
                         .unwrap_or(false)
             })
+            .collect();
+
+        // Each contact sits where their direct conversation does; contacts without one go last
+        let mut rank = HashMap::new();
+        for (i, conversation) in self.sidebar_conversations().iter().enumerate() {
+            if conversation.is_group() {
+                continue;
+            }
+            for participant in &conversation.participants {
+                rank.entry(*participant).or_insert(i);
+            }
+        }
+        contacts.sort_by_key(|c| rank.get(&c.contact_user_id).copied().unwrap_or(usize::MAX));
+        contacts
+    }
+
+    /// Conversations in sidebar order
+    ///
+    /// Pinned conversations come first in their manual order, then the rest
+    /// with the most recent activity first.
+    pub fn sidebar_conversations(&self) -> Vec<&Conversation> {
+        let mut conversations: Vec<&Conversation> = self.conversations.values().collect();
+        conversations.sort_by(|a, b| {
+            a.pin_cmp(b)
+                .then_with(|| self.last_activity(b).cmp(&self.last_activity(a)))
+                .then_with(|| a.id.cmp(&b.id))
+        });
+        conversations
+    }
+
+    /// Group conversations matching the search query, in sidebar order
+    pub fn filtered_groups(&self) -> Vec<&Conversation> {
+        let query = self.search_query.to_lowercase().trim().to_string();
+        self.sidebar_conversations()
+            .into_iter()
+            .filter(|c| c.is_group())
+            .filter(|c| query.is_empty() || c.display_title().to_lowercase().contains(query.as_str()))
             .collect()
     }
+
+    /// The one-to-one conversation with a contact, if there is one
+    pub fn direct_conversation_id(&self, contact_user_id: Uuid) -> Option<Uuid> {
+        self.conversations
+            .values()
+            .find(|c| !c.is_group() && c.has_participant(contact_user_id))
+            .map(|c| c.id)
+    }
+
+    /// Time of the latest message in a conversation, loaded or reported by the server
+    fn last_activity(&self, conversation: &Conversation) -> Option<DateTime<Utc>> {
+        let loaded = self.messages.get(&conversation.id).and_then(|m| m.last()).map(|m| m.timestamp);
+        conversation.last_message_time.max(loaded)
+    }
+
+    /// Pinned conversation IDs, top of the sidebar first
+    pub fn pinned_conversation_ids(&self) -> Vec<Uuid> {
+        let mut pinned: Vec<&Conversation> = self.conversations.values().filter(|c| c.pinned).collect();
+        pinned.sort_by(|a, b| a.pin_cmp(b).then_with(|| a.id.cmp(&b.id)));
+        pinned.iter().map(|c| c.id).collect()
+    }
+
+    /// Pin or unpin a conversation and queue the change for the server
+    ///
+    /// A newly pinned conversation goes below the other pinned ones.
+    pub fn set_pinned(&mut self, conversation_id: Uuid, pinned: bool) {
+        let next_order = self.conversations.values()
+            .filter(|c| c.pinned)
+            .map(|c| c.pin_order + 1)
+            .max()
+            .unwrap_or(0);
+        let Some(conversation) = self.conversations.get_mut(&conversation_id) else {
+            return;
+        };
+        if conversation.pinned == pinned {
+            return;
+        }
+        conversation.pinned = pinned;
+        if pinned {
+            conversation.pin_order = next_order;
+        }
+        self.pending_pin_updates.retain(|(id, _)| *id != conversation_id);
+        self.pending_pin_updates.push((conversation_id, pinned));
+    }
+
+    /// Add a contact to a conversation and queue the change for the server
+    pub fn add_participant(&mut self, conversation_id: Uuid, user_id: Uuid) {
+        let Some(conversation) = self.conversations.get_mut(&conversation_id) else {
+            return;
+        };
+        if conversation.has_participant(user_id) {
+            return;
+        }
+        conversation.participants.push(user_id);
+        if let Some(contact) = self.contacts.iter().find(|c| c.contact_user_id == user_id) {
+            conversation.member_usernames.push(contact.username.clone());
+        }
+        if let Some(crdt) = self.conversation_crdts.get_mut(&conversation_id) {
+            crdt.add_participant(user_id);
+        }
+        self.pending_membership_updates.push((conversation_id, user_id, true));
+    }
+
+    /// Leave a conversation and queue the change for the server
+    ///
+    /// The conversation disappears from the sidebar right away.
+    pub fn leave_conversation(&mut self, conversation_id: Uuid) {
+        let Some(user_id) = self.current_user_id else {
+            return;
+        };
+        if self.conversations.remove(&conversation_id).is_none() {
+            return;
+        }
+        self.messages.remove(&conversation_id);
+        if let Some(crdt) = self.conversation_crdts.get_mut(&conversation_id) {
+            crdt.remove_participant(user_id);
+        }
+        if self.selected_conversation_id == Some(conversation_id) {
+            self.selected_conversation_id = None;
+        }
+        self.pending_membership_updates.push((conversation_id, user_id, false));
+    }
+
+    /// Apply a conversation change from `/sync/conversations`
+    ///
+    /// The server doesn't track message previews, so an update keeps the
+    /// last message we already show.
+    pub fn apply_conversation_event(&mut self, event: ConversationEvent) {
+        match event {
+            ConversationEvent::Created { conversation } | ConversationEvent::Updated { conversation } => {
+                let mut conversation = conversation;
+                if let Some(existing) = self.conversations.get(&conversation.id) {
+                    if conversation.last_message.is_none() {
+                        conversation.last_message = existing.last_message.clone();
+                        conversation.last_message_preview = existing.last_message_preview.clone();
+                        conversation.last_message_time = existing.last_message_time;
+                    }
+                }
+                self.conversations.insert(conversation.id, conversation);
+            }
+            ConversationEvent::Deleted { conversation_id } => {
+                self.conversations.remove(&conversation_id);
+                self.messages.remove(&conversation_id);
+                if self.selected_conversation_id == Some(conversation_id) {
+                    self.selected_conversation_id = None;
+                }
+            }
+        }
+    }
+
+    /// Block a user and queue the block for the server
+    ///
+    /// They disappear from the contact list and friend requests right away,
+    /// and their direct conversation is closed if it was open.
+    pub fn block_user(&mut self, user_id: Uuid) {
+        if !self.blocked_users.insert(user_id) {
+            return;
+        }
+        self.incoming_friend_requests.retain(|r| r.from_user_id != user_id);
+        if self.selected_conversation_id.is_some() && self.selected_conversation_id == self.direct_conversation_id(user_id) {
+            self.selected_conversation_id = None;
+        }
+        self.pending_block_updates.push((user_id, true));
+    }
+
+    /// Unblock a user and queue the change for the server
+    pub fn unblock_user(&mut self, user_id: Uuid) {
+        if self.blocked_users.remove(&user_id) {
+            self.pending_block_updates.push((user_id, false));
+        }
+    }
+
+    /// Whether we've blocked a user
+    pub fn is_blocked(&self, user_id: Uuid) -> bool {
+        self.blocked_users.contains(&user_id)
+    }
+
+    /// Move a pinned conversation to another pinned one's position
+    ///
+    /// Used by drag-to-reorder in the sidebar; queues the new order for the server.
+    pub fn move_pinned(&mut self, conversation_id: Uuid, target_id: Uuid) {
+        let mut order = self.pinned_conversation_ids();
+        let from = order.iter().position(|id| *id == conversation_id);
+        let to = order.iter().position(|id| *id == target_id);
+        let (Some(from), Some(to)) = (from, to) else {
+            return;
+        };
+        if from == to {
+            return;
+        }
+        let moved = order.remove(from);
+        order.insert(to, moved);
+        for (position, id) in order.iter().enumerate() {
+            if let Some(conversation) = self.conversations.get_mut(id) {
+                conversation.pin_order = position as i32;
+            }
+        }
+        self.pending_pin_order = Some(order);
+    }
+
+    /// Channel for reporting merge conflicts from other threads
+    ///
+    /// Conflicts sent here show up in [`Self::pending_conflicts`] on the
+    /// next frame.
+    pub fn conflict_sender(&self) -> Sender<MergeResult> {
+        self.conflict_sender.clone()
+    }
+
+    /// Channel for reporting translation results from other threads
+    pub fn translation_sender(&self) -> Sender<TranslationResult> {
+        self.translation_sender.clone()
+    }
+
+    /// Ask for a message to be translated into the user's locale
+    ///
+    /// Translations are cached for the session, so asking again for a
+    /// message that is translated or in flight does nothing; a failed one
+    /// is retried.
+    pub fn request_translation(&mut self, conversation_id: Uuid, message_id: Uuid) {
+        if matches!(self.translations.get(&message_id), Some(Translation::Pending | Translation::Done(_))) {
+            return;
+        }
+        self.translations.insert(message_id, Translation::Pending);
+        self.pending_translations.push((conversation_id, message_id));
+    }
+
+    /// Channel for streaming summary text from other threads
+    pub fn summary_sender(&self) -> Sender<SummaryUpdate> {
+        self.summary_sender.clone()
+    }
+
+    /// Ask for a summary of the messages that were unread when the
+    /// conversation was opened, through to the newest
+    ///
+    /// Replaces any summary already shown.
+    pub fn request_unread_summary(&mut self, conversation_id: Uuid) {
+        let newest = self.messages.get(&conversation_id).and_then(|m| m.last()).map(|m| m.id);
+        let range = self.unread_since.get(&conversation_id).copied().zip(newest);
+        let status = if range.is_some() { SummaryStatus::Streaming } else { SummaryStatus::Empty };
+        self.summary = Some(ConversationSummary {
+            conversation_id,
+            text: String::new(),
+            status,
+        });
+        self.pending_summary = range.map(|(from, to)| (conversation_id, from, to));
+    }
+
+    /// Channel for reporting edit histories from other threads
+    pub fn edit_history_sender(&self) -> Sender<EditHistoryResult> {
+        self.edit_history_sender.clone()
+    }
+
+    /// Export a conversation's local history to `path`
+    pub fn export_conversation(&mut self, conversation_id: Uuid, format: ExportFormat, path: PathBuf) {
+        self.pending_export = Some((conversation_id, format, path));
+    }
+
+    /// Channel for reporting finished exports from other threads
+    pub fn export_sender(&self) -> Sender<ExportResult> {
+        self.export_sender.clone()
+    }
+
+    /// Open the history popup for an edited message and fetch its versions
+    ///
+    /// Replaces any history already shown.
+    pub fn show_edit_history(&mut self, conversation_id: Uuid, message_id: Uuid) {
+        self.edit_history = Some(EditHistory {
+            conversation_id,
+            message_id,
+            status: EditHistoryStatus::Loading,
+        });
+        self.pending_edit_history = Some((conversation_id, message_id));
+    }
+
+    /// Merge a remote replica into our message CRDT for its conversation
+    ///
+    /// Does nothing for conversations we don't keep a replica of. Merges
+    /// are when we learn how far other replicas have got, so the operation
+    /// log is compacted right after.
+    pub fn merge_message_crdt(&mut self, remote: &MessageCrdt) {
+        if let Some(local) = self.message_crdts.get_mut(&remote.conversation_id()) {
+            let result = local.merge(remote);
+            local.compact(&self.crdt_compaction);
+            self.report_conflict(result);
+        }
+    }
+
+    /// Merge a remote replica into our conversation CRDT
+    ///
+    /// Does nothing for conversations we don't keep a replica of.
+    pub fn merge_conversation_crdt(&mut self, remote: &ConversationCrdt) {
+        let conversation_id = remote.conversation_id();
+        let Some(local) = self.conversation_crdts.get_mut(&conversation_id) else {
+            return;
+        };
+        let result = local.merge(remote);
+        let members = local.participants();
+
+        // Keep the sidebar's member list in step with the replica
+        if let Some(conversation) = self.conversations.get_mut(&conversation_id) {
+            conversation.participants.retain(|id| members.contains(id));
+            for id in members {
+                if !conversation.participants.contains(&id) {
+                    conversation.participants.push(id);
+                }
+            }
+        }
+        self.report_conflict(result);
+    }
+
+    fn report_conflict(&self, result: MergeResult) {
+        if matches!(result, MergeResult::Conflict { .. }) {
+            let _ = self.conflict_sender.send(result);
+        }
+    }
+
+    /// Apply the user's choice to a pending conflict
+    ///
+    /// The conflict stays pending if the choice can't be applied.
+    pub fn resolve_conflict(&mut self, index: usize, resolution: Resolution) -> Result<(), String> {
+        let Some(MergeResult::Conflict { local_data, remote_data, .. }) = self.pending_conflicts.get(index) else {
+            return Err("No such conflict".to_string());
+        };
+
+        if resolution != Resolution::KeepMine {
+            match message_crdt::conflicting_entries(local_data, remote_data) {
+                Ok(entries) => {
+                    if let Some((_, ours, _)) = entries.first() {
+                        let conversation_id = ours.conversation_id;
+                        let crdt = self.message_crdts.get_mut(&conversation_id)
+                            .ok_or("This conversation's messages aren't loaded")?;
+                        let resolved = crdt.resolve_conflict(local_data, remote_data, resolution)?;
+                        let messages = self.messages.entry(conversation_id).or_default();
+                        for (original_id, entry) in resolved {
+                            let Some(index) = messages.iter().position(|m| m.id == original_id) else {
+                                continue;
+                            };
+                            if entry.id == original_id {
+                                messages[index].content = entry.content;
+                                messages[index].edit_version = entry.edit_version;
+                            } else {
+                                let mut copy = messages[index].clone();
+                                copy.id = entry.id;
+                                copy.content = entry.content;
+                                copy.braid_version = entry.braid_version;
+                                copy.edit_version = None;
+                                messages.insert(index + 1, copy);
+                            }
+                        }
+                    }
+                }
+                Err(_) => {
+                    let remote: ConversationCrdt = serde_json::from_slice(remote_data)
+                        .map_err(|_| "This conflict can't be resolved here".to_string())?;
+                    let crdt = self.conversation_crdts.get_mut(&remote.conversation_id())
+                        .ok_or("This conversation isn't loaded")?;
+                    crdt.resolve_conflict(remote_data, resolution)?;
+                }
+            }
+        }
+
+        self.pending_conflicts.remove(index);
+        Ok(())
+    }
+
+    /// Change who can message us and queue the change for the server
+    pub fn set_message_privacy(&mut self, message_privacy: MessagePrivacy) {
+        let settings = PrivacySettings { message_privacy, ..self.privacy.unwrap_or_default() };
+        self.set_privacy(settings);
+    }
+
+    /// Change our privacy settings and queue the change for the server
+    pub fn set_privacy(&mut self, settings: PrivacySettings) {
+        if self.privacy == Some(settings) {
+            return;
+        }
+        self.privacy = Some(settings);
+        self.pending_privacy_update = Some(settings);
+    }
+
+    /// Whether we share typing indicators
+    pub fn sends_typing(&self) -> bool {
+        self.privacy.is_none_or(|settings| settings.send_typing)
+    }
+
+    /// Whether we send read receipts, and so see other people's
+    pub fn sends_read_receipts(&self) -> bool {
+        self.privacy.is_none_or(|settings| settings.send_read_receipts)
+    }
     
     /// Select a conversation
     pub fn select_conversation(&mut self, conversation_id: Uuid) {
+        if self.selected_conversation_id != Some(conversation_id) {
+            // What's unread is worked out afresh each time it's opened
+            self.unread_since.remove(&conversation_id);
+            self.scroll_anchor = None;
+        }
         self.selected_conversation_id = Some(conversation_id);
+
+        // Opening a conversation clears the manual unread marker
+        if self.conversations.get(&conversation_id).is_some_and(|c| c.manually_unread) {
+            self.set_manually_unread(conversation_id, false);
+        }
+    }
+
+    /// Mark or unmark a conversation as unread and queue the change for the server
+    pub fn set_manually_unread(&mut self, conversation_id: Uuid, unread: bool) {
+        let Some(conversation) = self.conversations.get_mut(&conversation_id) else {
+            return;
+        };
+        conversation.manually_unread = unread;
+        self.pending_unread_updates.retain(|(id, _)| *id != conversation_id);
+        self.pending_unread_updates.push((conversation_id, unread));
+
+        // Marking the open conversation unread deselects it so the badge sticks
+        if unread && self.selected_conversation_id == Some(conversation_id) {
+            self.selected_conversation_id = None;
+        }
+    }
+
+    /// Set or clear a conversation's theme color and queue the change for the server
+    ///
+    /// The color is the user's own; the other participants don't see it.
+    pub fn set_theme_color(&mut self, conversation_id: Uuid, color: Option<ThemeColor>) {
+        let Some(conversation) = self.conversations.get_mut(&conversation_id) else {
+            return;
+        };
+        if conversation.theme_color == color {
+            return;
+        }
+        conversation.theme_color = color;
+        self.pending_theme_updates.retain(|(id, _)| *id != conversation_id);
+        self.pending_theme_updates.push((conversation_id, color));
+    }
+
+    /// Header and bubble colors for a conversation
+    pub fn conversation_theme(&self, conversation_id: Uuid) -> ConversationTheme {
+        ConversationTheme::for_color(self.conversations.get(&conversation_id).and_then(|c| c.theme_color))
+    }
+
+    /// Notification level for a conversation
+    pub fn notification_level(&self, conversation_id: Uuid) -> NotificationLevel {
+        self.notification_levels.get(&conversation_id).copied().unwrap_or_default()
+    }
+
+    /// Change a conversation's notification level
+    pub fn set_notification_level(&mut self, conversation_id: Uuid, level: NotificationLevel) {
+        if level == NotificationLevel::default() {
+            self.notification_levels.remove(&conversation_id);
+        } else {
+            self.notification_levels.insert(conversation_id, level);
+        }
+    }
+
+    /// Display name for a message sender
+    pub fn sender_name(&self, sender_id: Uuid) -> String {
+        self.contacts
+            .iter()
+            .find(|c| c.contact_user_id == sender_id)
+            .map(|c| c.display_name.clone().unwrap_or_else(|| c.username.clone()))
+            .unwrap_or_else(|| "New message".to_string())
+    }
+
+    /// Add a page of older messages above those already loaded
+    ///
+    /// Messages already present are skipped. Returns the message that was at
+    /// the top before, so the list can keep it in view.
+    pub fn prepend_older_messages(&mut self, conversation_id: Uuid, older: Vec<ChatMessage>) -> Option<Uuid> {
+        let messages = self.messages.entry(conversation_id).or_default();
+        let previous_top = messages.first().map(|m| m.id);
+        let mut page: Vec<ChatMessage> = older
+            .into_iter()
+            .filter(|m| !messages.iter().any(|existing| existing.id == m.id))
+            .collect();
+        if page.is_empty() {
+            return None;
+        }
+        page.append(messages);
+        *messages = page;
+        previous_top
+    }
+
+    /// Reload a conversation's newest messages from the server
+    pub fn request_resync(&mut self, conversation_id: Uuid) {
+        self.pending_resync = Some(conversation_id);
+    }
+
+    /// Channel for background resyncs to report back on
+    pub fn resync_sender(&self) -> Sender<ResyncResult> {
+        self.resync_sender.clone()
+    }
+
+    /// Follow the selected conversation's subscription status
+    ///
+    /// Messages may have been missed or changed while the stream was down
+    /// (it lagged behind, stalled or dropped), so coming back resyncs them.
+    pub fn track_subscription_status(&mut self, conversation_id: Uuid, status: &SubscriptionStatus) {
+        match status {
+            SubscriptionStatus::Retrying | SubscriptionStatus::Stalled | SubscriptionStatus::Error(_) => {
+                self.resync_on_connect = true;
+            }
+            SubscriptionStatus::Connected if self.resync_on_connect => {
+                self.resync_on_connect = false;
+                self.request_resync(conversation_id);
+            }
+            _ => {}
+        }
+    }
+
+    /// Replace a conversation's messages with the server's newest ones
+    ///
+    /// The server's copy wins for the span it covers: messages there that it
+    /// no longer has are dropped, unless we haven't sent them yet. Older
+    /// history loaded before that span is kept. The list keeps its place,
+    /// and the first unread message stays marked; if either was dropped, the
+    /// next message that's left takes over.
+    pub fn resync_messages(&mut self, conversation_id: Uuid, fresh: Vec<ChatMessage>) {
+        let previous = self.messages.remove(&conversation_id).unwrap_or_default();
+        let oldest_fresh = fresh.iter().min_by(|a, b| a.display_cmp(b)).cloned();
+
+        let mut messages = fresh;
+        for message in &mut messages {
+            // Receipts may have got here before the server's copy
+            if let Some(old) = previous.iter().find(|m| m.id == message.id) {
+                message.is_delivered |= old.is_delivered;
+                message.is_read |= old.is_read;
+            }
+        }
+        let kept: Vec<ChatMessage> = previous
+            .iter()
+            .filter(|m| !messages.iter().any(|fresh| fresh.id == m.id))
+            .filter(|m| !m.is_sent || oldest_fresh.as_ref().is_some_and(|oldest| m.display_cmp(oldest).is_lt()))
+            .cloned()
+            .collect();
+        messages.extend(kept);
+        messages.sort_by(|a, b| a.display_cmp(b));
+
+        // The message itself, or the first one after it that's still there
+        let survivor = |id: Uuid| {
+            previous
+                .iter()
+                .skip_while(|m| m.id != id)
+                .find(|m| messages.iter().any(|kept| kept.id == m.id))
+                .map(|m| m.id)
+        };
+        if self.selected_conversation_id == Some(conversation_id) {
+            self.scroll_anchor = self.scroll_anchor.and_then(survivor);
+            if let Some(anchor) = self.scroll_anchor {
+                self.scroll_to_message_id.get_or_insert(anchor);
+            }
+        }
+        if let Some(first_unread) = self.unread_since.get(&conversation_id).copied() {
+            match survivor(first_unread) {
+                Some(id) => self.unread_since.insert(conversation_id, id),
+                None => self.unread_since.remove(&conversation_id),
+            };
+        }
+
+        self.messages.insert(conversation_id, messages);
+    }
+
+    /// Mark other participants' unread messages in a conversation as read
+    ///
+    /// Returns their IDs so read receipts can be sent to the server. The
+    /// first of them is remembered so they can still be summarized.
+    pub fn take_unread_incoming(&mut self, conversation_id: Uuid) -> Vec<Uuid> {
+        let current_user_id = self.current_user_id;
+        let Some(messages) = self.messages.get_mut(&conversation_id) else {
+            return Vec::new();
+        };
+        let unread: Vec<Uuid> = messages
+            .iter_mut()
+            .filter(|m| !m.is_read && Some(m.sender_id) != current_user_id)
+            .map(|m| {
+                m.is_read = true;
+                m.id
+            })
+            .collect();
+        if let Some(&first) = unread.first() {
+            self.unread_since.entry(conversation_id).or_insert(first);
+        }
+        unread
+    }
+
+    /// Apply a delivery receipt from the server; returns whether a message changed
+    pub fn apply_delivery_receipt(&mut self, receipt: &DeliveryReceiptEvent) -> bool {
+        let message = self
+            .messages
+            .get_mut(&receipt.conversation_id)
+            .and_then(|messages| messages.iter_mut().find(|m| m.id == receipt.message_id));
+        match message {
+            Some(message) if !message.is_delivered => {
+                message.is_sent = true;
+                message.is_delivered = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Load one of our messages into the composer for editing
+    pub fn start_editing(&mut self, conversation_id: Uuid, message_id: Uuid) {
+        let Some(message) = self
+            .messages
+            .get(&conversation_id)
+            .and_then(|messages| messages.iter().find(|m| m.id == message_id))
+        else {
+            return;
+        };
+        if message.is_deleted || Some(message.sender_id) != self.current_user_id {
+            return;
+        }
+        self.message_input = message.content.clone();
+        self.composer_mentions.clear();
+        self.editing_message_id = Some(message_id);
+    }
+
+    /// Leave edit mode and clear the composer
+    pub fn cancel_editing(&mut self) {
+        if self.editing_message_id.take().is_some() {
+            self.message_input.clear();
+            self.composer_mentions.clear();
+        }
+    }
+
+    /// Apply an edit the server accepted to our copy of the message
+    pub fn apply_message_edit(&mut self, conversation_id: Uuid, message_id: Uuid, content: String, edit_version: String) {
+        if let Some(crdt) = self.message_crdts.get_mut(&conversation_id) {
+            crdt.edit_message(message_id, content.clone());
+        }
+        let message = self
+            .messages
+            .get_mut(&conversation_id)
+            .and_then(|messages| messages.iter_mut().find(|m| m.id == message_id));
+        if let Some(message) = message {
+            message.content = content;
+            message.edit_version = Some(edit_version);
+        }
+        // The translation was of the old text
+        self.translations.remove(&message_id);
+        // An open history is missing the version just replaced
+        if self.edit_history.as_ref().is_some_and(|h| h.message_id == message_id) {
+            self.show_edit_history(conversation_id, message_id);
+        }
+    }
+
+    /// Turn our copy of a deleted message into a tombstone
+    ///
+    /// The message stays in place, shown as deleted, so replies and read
+    /// state around it keep their context.
+    pub fn apply_message_delete(&mut self, conversation_id: Uuid, message_id: Uuid) {
+        if let Some(crdt) = self.message_crdts.get_mut(&conversation_id) {
+            crdt.delete_message(message_id);
+        }
+        let message = self
+            .messages
+            .get_mut(&conversation_id)
+            .and_then(|messages| messages.iter_mut().find(|m| m.id == message_id));
+        if let Some(message) = message {
+            message.is_deleted = true;
+            message.content.clear();
+            message.link_preview = None;
+        }
+        self.translations.remove(&message_id);
+        if self.editing_message_id == Some(message_id) {
+            self.cancel_editing();
+        }
+        // Deleted messages take their history with them
+        if self.edit_history.as_ref().is_some_and(|h| h.message_id == message_id) {
+            self.edit_history = None;
+        }
+    }
+
+    /// Apply a read receipt from the server; returns whether a message changed
+    ///
+    /// Ignored while we don't send read receipts ourselves.
+    pub fn apply_read_receipt(&mut self, receipt: &ReadReceiptEvent) -> bool {
+        if !self.sends_read_receipts() {
+            return false;
+        }
+        let message = self
+            .messages
+            .get_mut(&receipt.conversation_id)
+            .and_then(|messages| messages.iter_mut().find(|m| m.id == receipt.message_id));
+        match message {
+            Some(message) if !message.is_read => {
+                message.is_read = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Apply a presence change from the server
+    ///
+    /// The server forgets our status when it marks us offline, so coming
+    /// back online sends it again.
+    pub fn apply_presence(&mut self, presence: &PresenceEvent) {
+        if Some(presence.user_id) == self.current_user_id {
+            if presence.is_online {
+                self.resend_own_status();
+            }
+            return;
+        }
+        self.presence.set_connected(presence.user_id, presence.is_online);
+        if let Some(contact) = self.contacts.iter_mut().find(|c| c.contact_user_id == presence.user_id) {
+            contact.is_online = presence.is_online;
+        }
+    }
+
+    /// Whether a contact is online
+    pub fn is_contact_online(&self, user_id: Uuid) -> bool {
+        self.presence.is_online(&user_id)
+    }
+
+    /// A contact's status and status text
+    pub fn contact_status(&self, user_id: Uuid) -> (PresenceStatus, Option<&str>) {
+        let text = self.presence.get_presence(&user_id).and_then(|p| p.status_text());
+        (self.presence.status(&user_id), text)
+    }
+
+    /// Apply a contact's status from `/sync/presence`
+    pub fn apply_status(&mut self, status: &UserStatus) {
+        self.presence.merge_status(status);
+    }
+
+    /// Our own status and status text (`Online` until we pick one)
+    pub fn own_status(&self) -> (PresenceStatus, Option<&str>) {
+        match self.current_user_id.and_then(|id| self.presence.get_presence(&id)) {
+            Some(presence) => (*presence.status(), presence.status_text()),
+            None => (PresenceStatus::Online, None),
+        }
+    }
+
+    /// Set our status and status text, and queue them for our contacts
+    pub fn set_own_status(&mut self, status: PresenceStatus, status_text: Option<String>) -> Result<(), String> {
+        let request = SetStatusRequest { status, status_text }.normalized()?;
+        if let Some(user_id) = self.current_user_id {
+            self.presence.update_presence(user_id, request.status, request.status_text.clone());
+        }
+        self.pending_status_update = Some(request);
+        Ok(())
+    }
+
+    /// Queue our status again, unless it's the plain default
+    fn resend_own_status(&mut self) {
+        let (status, text) = self.own_status();
+        if status != PresenceStatus::Online || text.is_some() {
+            let status_text = text.map(str::to_string);
+            self.pending_status_update = Some(SetStatusRequest { status, status_text });
+        }
+    }
+
+    /// Channel for heartbeat threads to report whether the server was reachable
+    pub fn connectivity_sender(&self) -> Sender<bool> {
+        self.connectivity_sender.clone()
+    }
+
+    /// Track window focus; regaining it reconnects and resyncs right away
+    ///
+    /// While the app was in the background the OS may have suspended the
+    /// network, leaving subscriptions dead or deep in their backoff.
+    pub fn update_focus(&mut self, focused: bool) {
+        let regained = focused && !self.window_focused;
+        self.window_focused = focused;
+        if !regained {
+            return;
+        }
+        tracing::info!("[BRAID] Window focused, reconnecting and resyncing");
+        if let Some(ref mut client) = self.message_sync_client {
+            client.reconnect_now();
+        }
+        if let Some(conversation_id) = self.selected_conversation_id {
+            self.request_resync(conversation_id);
+        }
+        // The next heartbeat doubles as a connectivity check
+        self.last_heartbeat = None;
+        self.should_reload_contacts = true;
+    }
+
+    /// Whether it's time to send a presence heartbeat; if so, counts it as sent
+    pub fn heartbeat_due(&mut self, now: std::time::Instant) -> bool {
+        let interval = std::time::Duration::from_secs(PRESENCE_HEARTBEAT_SECS);
+        if self.last_heartbeat.is_some_and(|last| now.duration_since(last) < interval) {
+            return false;
+        }
+        self.last_heartbeat = Some(now);
+        true
+    }
+
+    /// Participants of a conversation that can be @mentioned
+    ///
+    /// Everyone but the current user; names come from contacts, and
+    /// participants who aren't contacts are left out since we don't know
+    /// their username.
+    pub fn mention_candidates(&self, conversation_id: Uuid) -> Vec<MentionCandidate> {
+        let Some(conversation) = self.conversations.get(&conversation_id) else {
+            return Vec::new();
+        };
+        conversation
+            .participants
+            .iter()
+            .filter(|id| Some(**id) != self.current_user_id)
+            .filter_map(|id| self.contacts.iter().find(|c| c.contact_user_id == *id))
+            .map(|c| MentionCandidate {
+                user_id: c.contact_user_id,
+                username: c.username.clone(),
+                display_name: c.display_name.clone(),
+            })
+            .collect()
+    }
+
+    /// Unread total for the taskbar/dock badge
+    ///
+    /// Manually-unread conversations count as one; muted conversations are left out.
+    pub fn total_unread(&self) -> u32 {
+        self.conversations
+            .values()
+            .filter(|c| self.notification_level(c.id) != NotificationLevel::Muted)
+            .map(|c| match (c.unread_count, c.manually_unread) {
+                (0, true) => 1,
+                (count, _) => count,
+            })
+            .sum()
+    }
+
+    /// Sidebar badge for a conversation
+    pub fn unread_badge(&self, conversation_id: Uuid) -> Option<String> {
+        self.conversations.get(&conversation_id).and_then(Conversation::unread_badge)
     }
     
+    /// Open the conversation (and message) a deep link points to
+    ///
+    /// Links opened before conversations have loaded are kept and retried
+    /// once the conversation list arrives.
+    pub fn navigate_to_message(&mut self, link: DeepLink) -> NavigationResult {
+        if self.conversations.is_empty() && (self.is_loading_conversations || !self.initialized) {
+            self.pending_deep_link = Some(link);
+            return NavigationResult::Deferred;
+        }
+
+        if !self.conversations.contains_key(&link.conversation_id) {
+            self.ui_error = Some("You are not a member of the linked conversation".to_string());
+            return NavigationResult::NotParticipant;
+        }
+
+        self.select_conversation(link.conversation_id);
+        self.scroll_to_message_id = None;
+
+        let Some(message_id) = link.message_id else {
+            return NavigationResult::Navigated;
+        };
+
+        // If history is already loaded, a missing message has been deleted
+        if let Some(messages) = self.messages.get(&link.conversation_id) {
+            if !messages.is_empty() && !messages.iter().any(|m| m.id == message_id) {
+                self.ui_error = Some("The linked message has been deleted".to_string());
+                return NavigationResult::MessageUnavailable;
+            }
+        }
+
+        self.scroll_to_message_id = Some(message_id);
+        NavigationResult::Navigated
+    }
+
     /// Clear the current selection
     pub fn clear_selection(&mut self) {
         self.selected_conversation_id = None;
@@ -208,17 +1387,50 @@ impl MessagingState {
     pub fn open_add_friend_modal(&mut self) {
         self.show_add_friend_modal = true;
         self.add_friend_email.clear();
+        self.add_friend_search = UserSearch::default();
+        self.add_friend_selected = None;
         self.add_friend_message.clear();
         self.add_friend_error = None;
         self.add_friend_success = None;
     }
 
+    /// Send the friend request to a user picked from the search results
+    pub fn select_add_friend_result(&mut self, profile: UserProfile) {
+        self.add_friend_email = profile.username.clone();
+        self.add_friend_selected = Some(profile);
+        self.add_friend_error = None;
+        self.add_friend_success = None;
+    }
+
     /// Close the add friend modal
     pub fn close_add_friend_modal(&mut self) {
         self.show_add_friend_modal = false;
         self.is_sending_friend_request = false;
     }
 
+    /// Open the new group modal
+    pub fn open_new_group_modal(&mut self) {
+        self.show_new_group_modal = true;
+        self.new_group_name.clear();
+        self.new_group_members.clear();
+        self.new_group_error = None;
+    }
+
+    /// Close the new group modal
+    pub fn close_new_group_modal(&mut self) {
+        self.show_new_group_modal = false;
+        self.is_creating_group = false;
+    }
+
+    /// Tick or untick a contact in the new group modal
+    pub fn toggle_new_group_member(&mut self, user_id: Uuid) {
+        if let Some(index) = self.new_group_members.iter().position(|id| *id == user_id) {
+            self.new_group_members.remove(index);
+        } else {
+            self.new_group_members.push(user_id);
+        }
+    }
+
     /// Toggle friend requests panel
     pub fn toggle_friend_requests_panel(&mut self) {
         self.show_friend_requests_panel = !self.show_friend_requests_panel;
@@ -231,6 +1443,77 @@ impl MessagingState {
 
     /// Check for pending async operation results
     pub fn check_pending_operations(&mut self) {
+        // Collect merge conflicts for the resolution dialog
+        self.pending_conflicts.extend(self.conflict_receiver.try_iter());
+
+        // Show finished translations, unless the message changed meanwhile
+        for (message_id, result) in self.translation_receiver.try_iter().collect::<Vec<_>>() {
+            if let Some(translation) = self.translations.get_mut(&message_id) {
+                *translation = match result {
+                    Ok(text) => Translation::Done(text),
+                    Err(e) => Translation::Failed(e),
+                };
+            }
+        }
+
+        // Show a loaded edit history, unless the popup was closed or replaced
+        for (message_id, result) in self.edit_history_receiver.try_iter().collect::<Vec<_>>() {
+            if let Some(history) = self.edit_history.as_mut().filter(|h| h.message_id == message_id) {
+                history.status = match result {
+                    Ok(edits) => EditHistoryStatus::Loaded(edits),
+                    Err(e) => EditHistoryStatus::Failed(e),
+                };
+            }
+        }
+
+        // Fresh messages from a resync, for any conversation still loaded
+        for (conversation_id, result) in self.resync_receiver.try_iter().collect::<Vec<_>>() {
+            match result {
+                Ok(messages) if self.messages.contains_key(&conversation_id) => {
+                    self.resync_messages(conversation_id, messages);
+                }
+                Ok(_) => {}
+                Err(e) => tracing::warn!("Failed to resync conversation {}: {}", conversation_id, e),
+            }
+        }
+
+        // Messages we showed as pending: the server took them, or they can be retried
+        for (conversation_id, message_id, result) in self.send_result_receiver.try_iter().collect::<Vec<_>>() {
+            match result {
+                Ok(version) => self.confirm_send(conversation_id, message_id, version),
+                Err(e) => self.fail_send(conversation_id, message_id, e),
+            }
+        }
+
+        // Exports run in the background; only failures need the user's attention
+        for result in self.export_receiver.try_iter().collect::<Vec<_>>() {
+            match result {
+                Ok(path) => tracing::info!("Exported conversation to {}", path.display()),
+                Err(e) => self.ui_error = Some(format!("Failed to export conversation: {}", e)),
+            }
+        }
+
+        // Heartbeats tell us whether we're online
+        if let Some(online) = self.connectivity_receiver.try_iter().last() {
+            self.set_online_status(online);
+        }
+
+        // Stream summary text into the panel, unless it was closed or replaced
+        for update in self.summary_receiver.try_iter().collect::<Vec<_>>() {
+            let conversation_id = match &update {
+                SummaryUpdate::Chunk(id, _) | SummaryUpdate::Finished(id, _) => *id,
+            };
+            let Some(summary) = self.summary.as_mut().filter(|s| s.conversation_id == conversation_id) else {
+                continue;
+            };
+            match update {
+                SummaryUpdate::Chunk(_, text) => summary.text.push_str(&text),
+                SummaryUpdate::Finished(_, Ok(true)) => summary.status = SummaryStatus::Done,
+                SummaryUpdate::Finished(_, Ok(false)) => summary.status = SummaryStatus::Empty,
+                SummaryUpdate::Finished(_, Err(e)) => summary.status = SummaryStatus::Failed(e),
+            }
+        }
+
         // Check send friend request result
         if let Some(ref rx) = self.pending_send_request {
             if let Ok(result) = rx.try_recv() {
@@ -249,6 +1532,25 @@ impl MessagingState {
             }
         }
 
+        // Check create conversation result
+        if let Some(ref rx) = self.pending_create_conversation {
+            if let Ok(result) = rx.try_recv() {
+                self.pending_create_conversation = None;
+                self.is_creating_group = false;
+                match result {
+                    Ok(conversation) => {
+                        let conversation_id = conversation.id;
+                        self.conversations.insert(conversation_id, conversation);
+                        self.close_new_group_modal();
+                        self.select_conversation(conversation_id);
+                    }
+                    Err(e) => {
+                        self.new_group_error = Some(e);
+                    }
+                }
+            }
+        }
+
         // Check accept friend request result
         if let Some((request_id, ref rx)) = self.pending_accept_request {
             if let Ok(result) = rx.try_recv() {
@@ -289,7 +1591,10 @@ impl MessagingState {
                 self.pending_load_requests = None;
                 match result {
                     Ok(requests) => {
-                        self.incoming_friend_requests = requests;
+                        self.incoming_friend_requests = requests
+                            .into_iter()
+                            .filter(|r| !self.blocked_users.contains(&r.from_user_id))
+                            .collect();
                     }
                     Err(e) => {
                         tracing::error!("Failed to load friend requests: {}", e);
@@ -298,6 +1603,38 @@ impl MessagingState {
             }
         }
 
+        // Check load blocked users result; blocks made while loading are kept
+        if let Some(ref rx) = self.pending_load_blocked {
+            if let Ok(result) = rx.try_recv() {
+                self.pending_load_blocked = None;
+                match result {
+                    Ok(user_ids) => {
+                        self.incoming_friend_requests.retain(|r| !user_ids.contains(&r.from_user_id));
+                        self.blocked_users.extend(user_ids);
+                    }
+                    Err(e) => {
+                        tracing::error!("Failed to load blocked users: {}", e);
+                    }
+                }
+            }
+        }
+
+        // Check load privacy setting result
+        if let Some(ref rx) = self.pending_load_privacy {
+            if let Ok(result) = rx.try_recv() {
+                self.pending_load_privacy = None;
+                match result {
+                    // A change made while loading wins
+                    Ok(settings) => {
+                        self.privacy.get_or_insert(settings);
+                    }
+                    Err(e) => {
+                        tracing::error!("Failed to load privacy setting: {}", e);
+                    }
+                }
+            }
+        }
+
         // Check load contacts result
         if let Some(ref rx) = self.pending_load_contacts {
             if let Ok(result) = rx.try_recv() {
@@ -305,6 +1642,9 @@ impl MessagingState {
                 self.is_loading_contacts = false;
                 match result {
                     Ok(contacts) => {
+                        for contact in &contacts {
+                            self.presence.set_connected(contact.contact_user_id, contact.is_online);
+                        }
                         self.contacts = contacts;
                     }
                     Err(e) => {
@@ -322,6 +1662,16 @@ impl MessagingState {
                 match result {
                     Ok(conversations) => {
                         self.conversations = conversations.into_iter().map(|c| (c.id, c)).collect();
+                        if let Some(link) = self.pending_deep_link.take() {
+                            let result = self.navigate_to_message(link);
+                            tracing::info!("[BRAID] Resolved pending deep link {}: {:?}", link, result);
+                        }
+                        // Reopen last session's conversation if it still exists
+                        if let Some(id) = self.restore_conversation_id.take() {
+                            if self.selected_conversation_id.is_none() && self.conversations.contains_key(&id) {
+                                self.select_conversation(id);
+                            }
+                        }
                         // Auto-select first conversation if none selected yet
                         if self.selected_conversation_id.is_none() {
                             if let Some((&first_id, _)) = self.conversations.iter().next() {
@@ -339,36 +1689,126 @@ impl MessagingState {
         }
     }
 
-    /// Queue a message for offline sending
-    pub fn queue_message_offline(&mut self, message: ChatMessage) {
-        tracing::info!("[BRAID] Queuing message offline: id={}, content={}", message.id, message.content);
-        self.offline_queue.push_back(message);
+    /// Send a message, showing it right away as pending
+    ///
+    /// The message is PUT in the background under the id it's shown with,
+    /// so the PUT's response and the subscription's echo both find it.
+    pub fn send_message(&mut self, conversation_id: Uuid, content: String, message_type: MessageType) -> Uuid {
+        let sender_id = self.current_user_id.unwrap_or_else(Uuid::nil);
+        let mut message = ChatMessage::new_text(conversation_id, sender_id, content, 0);
+        message.message_type = message_type;
+        message.braid_version = "pending".to_string();
+
+        self.messages.entry(conversation_id).or_default().push(message.clone());
+        self.pending_sends.push(message.clone());
+        message.id
+    }
+
+    /// Channel for background sends to report back on
+    pub fn send_result_sender(&self) -> Sender<SendResult> {
+        self.send_result_sender.clone()
     }
 
-    /// Sync offline messages when back online
-    pub fn sync_offline_messages(&mut self) {
-        if !self.is_online || self.offline_queue.is_empty() {
+    /// Try sending a failed message again
+    pub fn retry_send(&mut self, conversation_id: Uuid, message_id: Uuid) {
+        if !self.failed_sends.remove(&message_id) {
             return;
         }
+        let message = self
+            .messages
+            .get(&conversation_id)
+            .and_then(|messages| messages.iter().find(|m| m.id == message_id));
+        if let Some(message) = message {
+            self.pending_sends.push(message.clone());
+        }
+    }
 
-        tracing::info!("[BRAID] Syncing {} offline messages", self.offline_queue.len());
+    /// Try sending every failed message again, e.g. once we're back online
+    pub fn retry_failed_sends(&mut self) {
+        let failed = std::mem::take(&mut self.failed_sends);
+        let messages = self.messages.values().flatten().filter(|m| failed.contains(&m.id));
+        self.pending_sends.extend(messages.cloned());
+    }
 
-        while let Some(message) = self.offline_queue.pop_front() {
-            // Try to send the message
-            if let Some(ref mut client) = self.message_sync_client {
-                match client.send_message(message.conversation_id, message.content.clone(), None) {
-                    Ok((msg_id, version)) => {
-                        tracing::info!("[BRAID] Successfully synced offline message: id={}, version={}", msg_id, version);
-                        self.last_sync_time = Some(std::time::Instant::now());
-                    }
-                    Err(e) => {
-                        tracing::warn!("[BRAID] Failed to sync offline message: {}, re-queuing", e);
-                        self.offline_queue.push_front(message);
-                        break; // Stop trying to sync more messages
-                    }
-                }
-            }
+    /// The server took a message we showed as pending
+    fn confirm_send(&mut self, conversation_id: Uuid, message_id: Uuid, version: String) {
+        self.failed_sends.remove(&message_id);
+        let message = self
+            .messages
+            .get_mut(&conversation_id)
+            .and_then(|messages| messages.iter_mut().find(|m| m.id == message_id));
+        // The echo may have beaten the response here, with more to say
+        if let Some(message) = message.filter(|m| !m.is_sent) {
+            message.braid_version = version;
+            message.is_sent = true;
+        }
+    }
+
+    /// A message we showed as pending wasn't sent; flag it for a retry
+    fn fail_send(&mut self, conversation_id: Uuid, message_id: Uuid, error: SendError) {
+        tracing::warn!("[BRAID] Failed to send message {}: {}", message_id, error);
+        let unsent = self
+            .messages
+            .get(&conversation_id)
+            .and_then(|messages| messages.iter().find(|m| m.id == message_id))
+            .is_some_and(|m| !m.is_sent);
+        if !unsent {
+            return;
         }
+        self.failed_sends.insert(message_id);
+        self.ui_error = Some(match error {
+            SendError::Failed(e) if e.contains("401") || e.contains("UNAUTHORIZED") => {
+                "You are not authenticated. Please login in this window.".to_string()
+            }
+            SendError::Failed(e) if e.contains("403") || e.contains("FORBIDDEN") => {
+                "You are not a participant in this conversation.".to_string()
+            }
+            SendError::Failed(e) => format!("Failed to send message: {}", e),
+            limit_reached => limit_reached.to_string(),
+        });
+    }
+
+    /// Queue a send for the sync service, which sends it once we're back online
+    pub fn queue_operation(&mut self, operation: Operation) {
+        tracing::info!("[BRAID] Queuing {} {} until we're back online", operation.type_name(), operation.id());
+        self.queued_operations.push(operation);
+    }
+
+    /// Queue a message for offline sending
+    ///
+    /// It's sent under the id it's shown with, so the subscription's echo
+    /// marks it sent.
+    pub fn queue_message_offline(&mut self, message: ChatMessage) {
+        self.queue_operation(Operation::SendMessage {
+            id: message.id,
+            conversation_id: message.conversation_id,
+            content: message.content,
+            message_type: message.message_type,
+            timestamp: message.timestamp.to_rfc3339(),
+        });
+    }
+
+    /// Queue a friend request made while offline
+    pub fn queue_friend_request(&mut self, user_id: Uuid, message: Option<String>) {
+        self.queue_operation(Operation::SendFriendRequest {
+            id: Uuid::new_v4(),
+            user_id,
+            message,
+            timestamp: Utc::now().to_rfc3339(),
+        });
+        self.add_friend_success = Some("You're offline - the request will be sent when you reconnect".to_string());
+        self.add_friend_error = None;
+    }
+
+    /// Accept a friend request while offline; the contact shows up once the
+    /// queued accept reaches the server and contacts are reloaded
+    pub fn queue_accept_request(&mut self, request_id: Uuid) {
+        self.queue_operation(Operation::AcceptFriendRequest {
+            id: Uuid::new_v4(),
+            request_id,
+            timestamp: Utc::now().to_rfc3339(),
+        });
+        self.incoming_friend_requests.retain(|r| r.id != request_id);
     }
 
     /// Update network status
@@ -377,16 +1817,731 @@ impl MessagingState {
         self.is_online = online;
 
         if !was_online && online {
-            tracing::info!("[BRAID] Network connection restored, syncing offline messages");
-            self.sync_offline_messages();
+            tracing::info!("[BRAID] Network connection restored, retrying failed sends");
+            self.retry_failed_sends();
         } else if was_online && !online {
             tracing::warn!("[BRAID] Network connection lost, queuing messages offline");
         }
     }
+}
+
+/// Merge a message from the server into a conversation's messages
+///
+/// The same message can arrive more than once: our own come back over the
+/// subscription after the PUT returned, and edits (e.g. an attached link
+/// preview) are re-broadcasts under the same id. A known message is updated
+/// in place, keeping any receipts that got here first, unless it's an older
+/// version than the one we show. Returns whether the message is new.
+pub fn merge_message(messages: &mut Vec<ChatMessage>, mut incoming: ChatMessage) -> bool {
+    let Some(existing) = messages.iter_mut().find(|m| m.id == incoming.id) else {
+        messages.push(incoming);
+        return true;
+    };
+    incoming.is_delivered |= existing.is_delivered;
+    incoming.is_read |= existing.is_read;
+    incoming.sequence = incoming.sequence.or(existing.sequence);
 
-    /// Get the count of queued offline messages
-    pub fn offline_message_count(&self) -> usize {
-        self.offline_queue.len()
+    // The original arriving after we applied an edit to it
+    let stale = existing.is_edited() && !incoming.is_edited() && incoming.braid_version == existing.braid_version;
+    if stale {
+        existing.is_sent = true;
+        existing.is_delivered = incoming.is_delivered;
+        existing.is_read = incoming.is_read;
+        existing.sequence = incoming.sequence;
+    } else {
+        *existing = incoming;
     }
+    false
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::messaging::timestamp;
+
+    fn loaded_state() -> (MessagingState, Conversation, ChatMessage) {
+        let me = Uuid::new_v4();
+        let conversation = Conversation::new_direct(me, Uuid::new_v4());
+        let message = ChatMessage::new_text(conversation.id, me, "hello".to_string(), 1);
+
+        let mut state = MessagingState::new();
+        state.initialized = true;
+        state.conversations.insert(conversation.id, conversation.clone());
+        state.messages.insert(conversation.id, vec![message.clone()]);
+        (state, conversation, message)
+    }
+
+    #[test]
+    fn test_edit_and_delete_own_message() {
+        let (mut state, conversation, own) = loaded_state();
+        let incoming = ChatMessage::new_text(conversation.id, Uuid::new_v4(), "hi".to_string(), 2);
+        state.messages.get_mut(&conversation.id).unwrap().push(incoming.clone());
+        state.current_user_id = Some(own.sender_id);
+
+        // Only our own messages can be edited
+        state.start_editing(conversation.id, incoming.id);
+        assert_eq!(state.editing_message_id, None);
+        state.start_editing(conversation.id, own.id);
+        assert_eq!(state.editing_message_id, Some(own.id));
+        assert_eq!(state.message_input, "hello");
+
+        state.apply_message_edit(conversation.id, own.id, "hello there".to_string(), "edit-1".to_string());
+        let edited = &state.messages[&conversation.id][0];
+        assert_eq!(edited.content, "hello there");
+        assert!(edited.is_edited());
+        assert_eq!(edited.current_version(), "edit-1");
+
+        // Deleting keeps the message in place as a tombstone and leaves edit mode
+        state.apply_message_delete(conversation.id, own.id);
+        assert_eq!(state.messages[&conversation.id].len(), 2);
+        assert!(state.messages[&conversation.id][0].is_deleted);
+        assert!(state.messages[&conversation.id][0].content.is_empty());
+        assert_eq!(state.editing_message_id, None);
+        assert!(state.message_input.is_empty());
+    }
+
+    #[test]
+    fn test_read_receipts() {
+        let (mut state, conversation, own) = loaded_state();
+        state.current_user_id = Some(own.sender_id);
+        let incoming = ChatMessage::new_text(conversation.id, Uuid::new_v4(), "hi".to_string(), 2);
+        state.messages.get_mut(&conversation.id).unwrap().push(incoming.clone());
+
+        // Only other participants' messages are marked read, once
+        assert_eq!(state.take_unread_incoming(conversation.id), vec![incoming.id]);
+        assert!(state.take_unread_incoming(conversation.id).is_empty());
+
+        let receipt = ReadReceiptEvent {
+            conversation_id: conversation.id,
+            message_id: own.id,
+            reader_id: Uuid::new_v4(),
+            read_at: chrono::Utc::now().to_rfc3339(),
+        };
+
+        // Not sending read receipts means not seeing others'
+        let settings = PrivacySettings { send_read_receipts: false, ..PrivacySettings::default() };
+        state.set_privacy(settings);
+        assert_eq!(state.pending_privacy_update, Some(settings));
+        assert!(!state.apply_read_receipt(&receipt));
+        assert!(!state.messages[&conversation.id][0].is_read);
+
+        state.set_privacy(PrivacySettings::default());
+        assert!(state.apply_read_receipt(&receipt));
+        assert!(state.messages[&conversation.id][0].is_read);
+        assert!(!state.apply_read_receipt(&receipt));
+    }
+
+    #[test]
+    fn test_delivery_receipts() {
+        let (mut state, conversation, own) = loaded_state();
+        assert!(!state.messages[&conversation.id][0].is_sent);
+
+        let receipt = DeliveryReceiptEvent {
+            conversation_id: conversation.id,
+            message_id: own.id,
+            recipient_id: Uuid::new_v4(),
+            delivered_at: chrono::Utc::now().to_rfc3339(),
+        };
+        assert!(state.apply_delivery_receipt(&receipt));
+        let message = &state.messages[&conversation.id][0];
+        assert!(message.is_sent && message.is_delivered && !message.is_read);
+        assert!(!state.apply_delivery_receipt(&receipt));
+    }
+
+    #[test]
+    fn test_prepend_older_messages_keeps_order_and_skips_known() {
+        let (mut state, conversation, newest) = loaded_state();
+        let older: Vec<ChatMessage> = (0..3)
+            .map(|i| ChatMessage::new_text(conversation.id, Uuid::new_v4(), format!("old {}", i), i))
+            .collect();
+
+        let mut page = older.clone();
+        page.push(newest.clone());
+        assert_eq!(state.prepend_older_messages(conversation.id, page), Some(newest.id));
+
+        let ids: Vec<Uuid> = state.messages[&conversation.id].iter().map(|m| m.id).collect();
+        let mut expected: Vec<Uuid> = older.iter().map(|m| m.id).collect();
+        expected.push(newest.id);
+        assert_eq!(ids, expected);
+
+        assert_eq!(state.prepend_older_messages(conversation.id, older), None);
+        assert_eq!(state.messages[&conversation.id].len(), 4);
+    }
+
+    #[test]
+    fn test_navigate_to_message() {
+        let (mut state, conversation, message) = loaded_state();
+
+        let result = state.navigate_to_message(DeepLink::message(conversation.id, message.id));
+        assert_eq!(result, NavigationResult::Navigated);
+        assert_eq!(state.selected_conversation_id, Some(conversation.id));
+        assert_eq!(state.scroll_to_message_id, Some(message.id));
+    }
+
+    #[test]
+    fn test_mark_unread_shows_badge_until_opened() {
+        let (mut state, conversation, _) = loaded_state();
+        assert_eq!(state.unread_badge(conversation.id), None);
+
+        state.set_manually_unread(conversation.id, true);
+        assert_eq!(state.conversations[&conversation.id].unread_count, 0);
+        assert!(state.unread_badge(conversation.id).is_some());
+        assert_eq!(state.pending_unread_updates, vec![(conversation.id, true)]);
+
+        state.select_conversation(conversation.id);
+        assert_eq!(state.unread_badge(conversation.id), None);
+        assert_eq!(state.pending_unread_updates, vec![(conversation.id, false)]);
+    }
+
+    #[test]
+    fn test_theme_color_applies_to_rendering_and_round_trips() {
+        let (mut state, conversation, _) = loaded_state();
+        let other = Conversation::new_direct(Uuid::new_v4(), Uuid::new_v4());
+        state.conversations.insert(other.id, other.clone());
+        assert_eq!(state.conversation_theme(conversation.id), ConversationTheme::default());
+
+        let color = ThemeColor::new(0x1e, 0x63, 0x9e);
+        state.set_theme_color(conversation.id, Some(color));
+        let theme = state.conversation_theme(conversation.id);
+        assert_eq!(theme, ConversationTheme::for_color(Some(color)));
+        assert_ne!(theme.bubble_outgoing, ConversationTheme::default().bubble_outgoing);
+        assert_eq!(state.conversation_theme(other.id), ConversationTheme::default());
+        assert_eq!(state.pending_theme_updates, vec![(conversation.id, Some(color))]);
+
+        // What the server sends back on the next conversation load
+        let json = serde_json::to_string(&state.conversations[&conversation.id]).unwrap();
+        let reloaded: Conversation = serde_json::from_str(&json).unwrap();
+        assert_eq!(reloaded.theme_color, Some(color));
+        state.conversations.insert(reloaded.id, reloaded);
+        assert_eq!(state.conversation_theme(conversation.id), theme);
+
+        state.set_theme_color(conversation.id, None);
+        assert_eq!(state.conversation_theme(conversation.id), ConversationTheme::default());
+        assert_eq!(state.pending_theme_updates, vec![(conversation.id, None)]);
+    }
+
+    #[test]
+    fn test_pinned_conversations_sort_above_recent_ones() {
+        let mut state = MessagingState::new();
+        let conversation_at = |time: &str| {
+            let mut conversation = Conversation::new_direct(Uuid::new_v4(), Uuid::new_v4());
+            conversation.last_message_time = timestamp::parse(time);
+            conversation
+        };
+        let old = conversation_at("2024-01-01T09:00:00+00:00");
+        let recent = conversation_at("2024-03-01T09:00:00+00:00");
+        let quiet = conversation_at("2024-02-01T09:00:00+00:00");
+        for conversation in [&old, &recent, &quiet] {
+            state.conversations.insert(conversation.id, conversation.clone());
+        }
+        // A newly loaded message makes the quiet conversation the most recent
+        let message = ChatMessage {
+            timestamp: timestamp::parse("2024-04-01T09:00:00+00:00").unwrap(),
+            ..ChatMessage::new_text(quiet.id, Uuid::new_v4(), "hi".to_string(), 1)
+        };
+        state.messages.insert(quiet.id, vec![message]);
+        let order = |state: &MessagingState| state.sidebar_conversations().iter().map(|c| c.id).collect::<Vec<_>>();
+        assert_eq!(order(&state), vec![quiet.id, recent.id, old.id]);
+
+        state.set_pinned(old.id, true);
+        assert_eq!(order(&state), vec![old.id, quiet.id, recent.id]);
+        state.set_pinned(recent.id, true);
+        assert_eq!(order(&state), vec![old.id, recent.id, quiet.id]);
+        assert_eq!(state.pending_pin_updates, vec![(old.id, true), (recent.id, true)]);
+
+        // Drag the second pin above the first
+        state.move_pinned(recent.id, old.id);
+        assert_eq!(order(&state), vec![recent.id, old.id, quiet.id]);
+        assert_eq!(state.pending_pin_order, Some(vec![recent.id, old.id]));
+
+        state.set_pinned(recent.id, false);
+        assert_eq!(order(&state), vec![old.id, quiet.id, recent.id]);
+        assert_eq!(state.pending_pin_updates, vec![(old.id, true), (recent.id, false)]);
+    }
+
+    #[test]
+    fn test_conversation_events_update_the_sidebar() {
+        let (mut state, direct, _) = loaded_state();
+        let me = direct.participants[0];
+        state.current_user_id = Some(me);
+
+        let group = Conversation::new(vec![me, Uuid::new_v4()]);
+        state.apply_conversation_event(ConversationEvent::Created { conversation: group.clone() });
+        assert!(state.conversations.contains_key(&group.id));
+
+        let mut renamed = group.clone();
+        renamed.name = Some("Weekend plans".to_string());
+        renamed.participants.push(Uuid::new_v4());
+        state.apply_conversation_event(ConversationEvent::Updated { conversation: renamed });
+        assert_eq!(state.conversations[&group.id].participants.len(), 3);
+        assert_eq!(state.conversations[&group.id].display_title(), "Weekend plans");
+
+        // An update keeps the preview we already show
+        state.conversations.get_mut(&direct.id).unwrap().last_message_preview = "hello".to_string();
+        let mut from_server = direct.clone();
+        from_server.last_message = None;
+        from_server.last_message_preview = String::new();
+        state.apply_conversation_event(ConversationEvent::Updated { conversation: from_server });
+        assert_eq!(state.conversations[&direct.id].last_message_preview, "hello");
+
+        state.select_conversation(group.id);
+        state.apply_conversation_event(ConversationEvent::Deleted { conversation_id: group.id });
+        assert!(!state.conversations.contains_key(&group.id));
+        assert_eq!(state.selected_conversation_id, None);
+    }
+
+    #[test]
+    fn test_group_membership_changes_queue_for_server() {
+        let (mut state, direct, _) = loaded_state();
+        let me = direct.participants[0];
+        let friend = direct.participants[1];
+        let newcomer = Uuid::new_v4();
+        state.current_user_id = Some(me);
+
+        let group = Conversation::new(vec![me, friend, Uuid::new_v4()]);
+        state.conversations.insert(group.id, group.clone());
+        assert_eq!(state.direct_conversation_id(friend), Some(direct.id));
+        assert_eq!(state.filtered_groups().iter().map(|c| c.id).collect::<Vec<_>>(), vec![group.id]);
+
+        state.add_participant(group.id, newcomer);
+        state.add_participant(group.id, newcomer);
+        assert_eq!(state.conversations[&group.id].participants.len(), 4);
+
+        state.select_conversation(group.id);
+        state.leave_conversation(group.id);
+        assert!(!state.conversations.contains_key(&group.id));
+        assert_eq!(state.selected_conversation_id, None);
+        assert_eq!(
+            state.pending_membership_updates,
+            vec![(group.id, newcomer, true), (group.id, me, false)]
+        );
+    }
+
+    #[test]
+    fn test_translations_are_cached_until_the_message_changes() {
+        let (mut state, conversation, message) = loaded_state();
+
+        state.request_translation(conversation.id, message.id);
+        state.request_translation(conversation.id, message.id);
+        assert_eq!(state.pending_translations, vec![(conversation.id, message.id)]);
+        assert_eq!(state.translations.get(&message.id), Some(&Translation::Pending));
+
+        state.translation_sender().send((message.id, Ok("hallo".to_string()))).unwrap();
+        state.check_pending_operations();
+        assert_eq!(state.translations.get(&message.id), Some(&Translation::Done("hallo".to_string())));
+
+        // Cached: no new request
+        state.pending_translations.clear();
+        state.request_translation(conversation.id, message.id);
+        assert!(state.pending_translations.is_empty());
+
+        // An edit invalidates it
+        state.apply_message_edit(conversation.id, message.id, "hello again".to_string(), "edit-1".to_string());
+        assert_eq!(state.translations.get(&message.id), None);
+    }
+
+    #[test]
+    fn test_edit_history_popup_follows_the_message() {
+        let (mut state, conversation, message) = loaded_state();
+
+        state.show_edit_history(conversation.id, message.id);
+        assert_eq!(state.pending_edit_history, Some((conversation.id, message.id)));
+        let edit = MessageEdit {
+            version: message.current_version().to_string(),
+            content: message.content.clone(),
+            written_at: message.timestamp,
+            replaced_at: message.timestamp + chrono::Duration::minutes(5),
+        };
+        state.edit_history_sender().send((message.id, Ok(vec![edit.clone()]))).unwrap();
+        state.check_pending_operations();
+        let status = state.edit_history.as_ref().map(|h| h.status.clone());
+        assert_eq!(status, Some(EditHistoryStatus::Loaded(vec![edit])));
+
+        // Another edit reloads it; a delete closes it
+        state.pending_edit_history = None;
+        state.apply_message_edit(conversation.id, message.id, "hello again".to_string(), "edit-1".to_string());
+        assert_eq!(state.pending_edit_history, Some((conversation.id, message.id)));
+        assert_eq!(state.edit_history.as_ref().map(|h| &h.status), Some(&EditHistoryStatus::Loading));
+        state.apply_message_delete(conversation.id, message.id);
+        assert_eq!(state.edit_history, None);
+    }
+
+    #[test]
+    fn test_unread_summary_covers_messages_unread_when_opened() {
+        let (mut state, conversation, own) = loaded_state();
+        state.current_user_id = Some(own.sender_id);
+
+        // Nothing unread: nothing to ask for
+        state.select_conversation(conversation.id);
+        state.take_unread_incoming(conversation.id);
+        state.request_unread_summary(conversation.id);
+        assert_eq!(state.pending_summary, None);
+        assert_eq!(state.summary.as_ref().map(|s| &s.status), Some(&SummaryStatus::Empty));
+
+        let first = ChatMessage::new_text(conversation.id, Uuid::new_v4(), "one".to_string(), 2);
+        let second = ChatMessage::new_text(conversation.id, Uuid::new_v4(), "two".to_string(), 3);
+        state.messages.get_mut(&conversation.id).unwrap().extend([first.clone(), second.clone()]);
+        assert_eq!(state.take_unread_incoming(conversation.id), vec![first.id, second.id]);
+
+        state.request_unread_summary(conversation.id);
+        assert_eq!(state.pending_summary, Some((conversation.id, first.id, second.id)));
+
+        let sender = state.summary_sender();
+        sender.send(SummaryUpdate::Chunk(conversation.id, "[stub] ".to_string())).unwrap();
+        sender.send(SummaryUpdate::Chunk(conversation.id, "one, two".to_string())).unwrap();
+        sender.send(SummaryUpdate::Finished(conversation.id, Ok(true))).unwrap();
+        state.check_pending_operations();
+        let summary = state.summary.clone().unwrap();
+        assert_eq!(summary.text, "[stub] one, two");
+        assert_eq!(summary.status, SummaryStatus::Done);
+
+        // Reopening starts afresh
+        state.selected_conversation_id = None;
+        state.select_conversation(conversation.id);
+        assert!(!state.unread_since.contains_key(&conversation.id));
+    }
+
+    #[test]
+    fn test_presence_events_update_contacts() {
+        let (mut state, _, _) = loaded_state();
+        let friend = Uuid::new_v4();
+        assert!(!state.is_contact_online(friend));
+
+        let mut presence = PresenceEvent {
+            user_id: friend,
+            is_online: true,
+            last_seen: "2024-01-01T12:00:00Z".to_string(),
+        };
+        state.apply_presence(&presence);
+        assert!(state.is_contact_online(friend));
+
+        presence.is_online = false;
+        state.apply_presence(&presence);
+        assert!(!state.is_contact_online(friend));
+
+        // Heartbeats go out once per interval
+        let now = std::time::Instant::now();
+        assert!(state.heartbeat_due(now));
+        assert!(!state.heartbeat_due(now + std::time::Duration::from_secs(1)));
+        assert!(state.heartbeat_due(now + std::time::Duration::from_secs(PRESENCE_HEARTBEAT_SECS)));
+    }
+
+    #[test]
+    fn test_blocked_users_are_hidden() {
+        let (mut state, conversation, own) = loaded_state();
+        let me = own.sender_id;
+        let friend = *conversation.participants.iter().find(|id| **id != me).unwrap();
+        let contact: Contact = serde_json::from_value(serde_json::json!({
+            "id": Uuid::new_v4(),
+            "user_id": me,
+            "contact_user_id": friend,
+            "username": "friend",
+            "email": "friend@example.com",
+            "display_name": null,
+            "avatar_url": null,
+            "last_seen": "2024-01-01T12:00:00Z",
+            "is_online": false,
+            "created_at": "2024-01-01T12:00:00Z",
+        }))
+        .unwrap();
+        let request: FriendRequest = serde_json::from_value(serde_json::json!({
+            "id": Uuid::new_v4(),
+            "from_user_id": friend,
+            "to_user_id": me,
+            "from_username": "friend",
+            "from_email": "friend@example.com",
+            "to_email": "me@example.com",
+            "message": null,
+            "created_at": "2024-01-01T12:00:00Z",
+            "responded_at": null,
+        }))
+        .unwrap();
+        state.contacts.push(contact);
+        state.incoming_friend_requests.push(request);
+        state.selected_conversation_id = Some(conversation.id);
+
+        state.block_user(friend);
+        state.block_user(friend);
+        assert!(state.is_blocked(friend));
+        assert!(state.filtered_contacts().is_empty());
+        assert!(state.incoming_friend_requests.is_empty());
+        assert_eq!(state.selected_conversation_id, None);
+        assert_eq!(std::mem::take(&mut state.pending_block_updates), vec![(friend, true)]);
+
+        state.unblock_user(friend);
+        assert_eq!(state.filtered_contacts().len(), 1);
+        assert_eq!(state.pending_block_updates, vec![(friend, false)]);
+    }
+
+    #[test]
+    fn test_regaining_focus_triggers_resync() {
+        let (mut state, _, _) = loaded_state();
+        let now = std::time::Instant::now();
+        assert!(state.heartbeat_due(now));
+
+        // Staying focused or losing focus does nothing
+        state.update_focus(true);
+        state.update_focus(false);
+        assert!(!state.should_reload_contacts);
+        assert!(!state.heartbeat_due(now));
+
+        state.update_focus(true);
+        assert!(state.should_reload_contacts);
+        assert!(state.heartbeat_due(now));
+
+        // The connectivity check's result decides whether we're online
+        state.connectivity_sender().send(false).unwrap();
+        state.check_pending_operations();
+        assert!(!state.is_online);
+        state.connectivity_sender().send(true).unwrap();
+        state.check_pending_operations();
+        assert!(state.is_online);
+    }
+
+    /// Messages 1..=count as the server would send them
+    fn sequenced(conversation_id: Uuid, count: i64) -> Vec<ChatMessage> {
+        (1..=count)
+            .map(|n| {
+                let mut message = ChatMessage::new_text(conversation_id, Uuid::new_v4(), format!("#{}", n), n as u64);
+                message.is_sent = true;
+                message.sequence = Some(n);
+                message
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_resync_keeps_anchored_message_in_view() {
+        let (mut state, conversation, _) = loaded_state();
+        let history = sequenced(conversation.id, 10);
+        state.messages.insert(conversation.id, history.clone());
+        state.select_conversation(conversation.id);
+
+        // Scrolled back to #5, with #8 onwards unread
+        state.scroll_anchor = Some(history[4].id);
+        state.unread_since.insert(conversation.id, history[7].id);
+
+        // The server's newest six: #8 was deleted, #11 is new
+        let mut fresh: Vec<ChatMessage> = history[4..].iter().filter(|m| m.id != history[7].id).cloned().collect();
+        fresh.extend(sequenced(conversation.id, 11).pop());
+        fresh.reverse();
+        state.resync_sender().send((conversation.id, Ok(fresh))).unwrap();
+        state.check_pending_operations();
+
+        // Older history stays; the list goes back to #5 and #9 is the first unread
+        let messages = &state.messages[&conversation.id];
+        let contents: Vec<&str> = messages.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, ["#1", "#2", "#3", "#4", "#5", "#6", "#7", "#9", "#10", "#11"]);
+        assert_eq!(state.scroll_to_message_id, Some(history[4].id));
+        assert_eq!(state.unread_since.get(&conversation.id), Some(&history[8].id));
+    }
+
+    #[test]
+    fn test_resync_after_dropped_subscription() {
+        let (mut state, conversation, own) = loaded_state();
+        state.select_conversation(conversation.id);
+        state.track_subscription_status(conversation.id, &SubscriptionStatus::Connected);
+        assert_eq!(state.pending_resync, None);
+
+        state.track_subscription_status(conversation.id, &SubscriptionStatus::Retrying);
+        state.track_subscription_status(conversation.id, &SubscriptionStatus::Connecting);
+        assert_eq!(state.pending_resync, None);
+        state.track_subscription_status(conversation.id, &SubscriptionStatus::Connected);
+        assert_eq!(state.pending_resync, Some(conversation.id));
+
+        // Our unsent message survives a server that hasn't seen it, and
+        // being at the newest message stays that way
+        let fresh = sequenced(conversation.id, 2);
+        state.resync_messages(conversation.id, fresh.clone());
+        let ids: Vec<Uuid> = state.messages[&conversation.id].iter().map(|m| m.id).collect();
+        assert_eq!(ids, [fresh[0].id, fresh[1].id, own.id]);
+        assert_eq!(state.scroll_to_message_id, None);
+    }
+
+    #[test]
+    fn test_sent_message_shows_as_pending_until_confirmed() {
+        let (mut state, conversation, _) = loaded_state();
+        let id = state.send_message(conversation.id, "on my way".to_string(), MessageType::Text);
+        assert_eq!(state.pending_sends.len(), 1);
+        let shown = state.messages[&conversation.id].last().unwrap();
+        assert_eq!((shown.id, shown.is_sent), (id, false));
+        assert_eq!(message_crdt::MessageStatus::from(shown), message_crdt::MessageStatus::Pending);
+
+        state.pending_sends.clear();
+        state.send_result_sender().send((conversation.id, id, Ok("v7".to_string()))).unwrap();
+        state.check_pending_operations();
+        let sent = state.messages[&conversation.id].last().unwrap();
+        assert!(sent.is_sent);
+        assert_eq!(sent.braid_version, "v7");
+    }
+
+    #[test]
+    fn test_offline_sends_are_queued_for_sync() {
+        let (mut state, conversation, _) = loaded_state();
+        let message = ChatMessage::new_text(conversation.id, Uuid::new_v4(), "later".to_string(), 0);
+        state.queue_message_offline(message.clone());
+        state.queue_friend_request(Uuid::new_v4(), None);
+        state.queue_accept_request(Uuid::new_v4());
+
+        let queued = &state.queued_operations;
+        assert_eq!(
+            queued.iter().map(|op| op.type_name()).collect::<Vec<_>>(),
+            ["SendMessage", "SendFriendRequest", "AcceptFriendRequest"]
+        );
+        // Sent under the id it's shown with, so the echo confirms it
+        assert_eq!(queued[0].id(), message.id);
+        assert!(state.add_friend_success.is_some());
+    }
+
+    #[test]
+    fn test_failed_send_is_flagged_for_retry() {
+        let (mut state, conversation, _) = loaded_state();
+        let id = state.send_message(conversation.id, "hello?".to_string(), MessageType::Text);
+        state.pending_sends.clear();
+
+        let error = SendError::Failed("PUT failed: connection refused".to_string());
+        state.send_result_sender().send((conversation.id, id, Err(error))).unwrap();
+        state.check_pending_operations();
+        assert!(state.failed_sends.contains(&id));
+        assert!(state.ui_error.is_some());
+
+        // Retrying sends the same message, under the same id
+        state.retry_send(conversation.id, id);
+        assert!(state.failed_sends.is_empty());
+        assert_eq!(state.pending_sends.iter().map(|m| m.id).collect::<Vec<_>>(), [id]);
+
+        // Failures left over when we come back online are retried too
+        state.pending_sends.clear();
+        state.failed_sends.insert(id);
+        state.set_online_status(false);
+        state.set_online_status(true);
+        assert!(state.failed_sends.is_empty());
+        assert_eq!(state.pending_sends.len(), 1);
+    }
+
+    #[test]
+    fn test_message_arriving_twice_is_merged() {
+        let conversation_id = Uuid::new_v4();
+        let mut message = sequenced(conversation_id, 1).remove(0);
+        let mut messages = Vec::new();
+        assert!(merge_message(&mut messages, message.clone()));
+
+        // A receipt gets here before the subscription's copy of the same version
+        messages[0].is_delivered = true;
+        assert!(!merge_message(&mut messages, message.clone()));
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0].is_delivered);
+
+        // An edit replaces it; the original arriving late doesn't undo that
+        let mut edited = message.clone();
+        edited.content = "#1, edited".to_string();
+        edited.edit_version = Some("v2".to_string());
+        assert!(!merge_message(&mut messages, edited));
+        message.is_read = true;
+        assert!(!merge_message(&mut messages, message));
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].content, "#1, edited");
+        assert!(messages[0].is_delivered && messages[0].is_read);
+    }
+
+    #[test]
+    fn test_own_status_is_queued_and_resent_after_going_offline() {
+        let (mut state, _, own) = loaded_state();
+        state.current_user_id = Some(own.sender_id);
+        assert_eq!(state.own_status(), (PresenceStatus::Online, None));
+
+        assert!(state.set_own_status(PresenceStatus::Offline, None).is_err());
+        state.set_own_status(PresenceStatus::Away, Some(" lunch ".to_string())).unwrap();
+        assert_eq!(state.own_status(), (PresenceStatus::Away, Some("lunch")));
+        let sent = state.pending_status_update.take().unwrap();
+        assert_eq!(sent.status_text.as_deref(), Some("lunch"));
+
+        // Our own presence events don't mark us offline, and coming back resends
+        let mut presence = PresenceEvent {
+            user_id: own.sender_id,
+            is_online: false,
+            last_seen: "2024-01-01T12:00:00Z".to_string(),
+        };
+        state.apply_presence(&presence);
+        assert_eq!(state.own_status().0, PresenceStatus::Away);
+        assert!(state.pending_status_update.is_none());
+        presence.is_online = true;
+        state.apply_presence(&presence);
+        assert_eq!(state.pending_status_update, Some(sent));
+
+        // Contacts' statuses arrive over /sync/presence
+        let friend = Uuid::new_v4();
+        state.apply_status(&UserStatus {
+            user_id: friend,
+            status: PresenceStatus::DoNotDisturb,
+            status_text: Some("Focusing".to_string()),
+            updated_at: chrono::Utc::now().to_rfc3339(),
+        });
+        assert_eq!(state.contact_status(friend), (PresenceStatus::DoNotDisturb, Some("Focusing")));
+        assert!(state.is_contact_online(friend));
+    }
+
+    #[test]
+    fn test_total_unread_counts_manual_and_skips_muted() {
+        let (mut state, conversation, _) = loaded_state();
+        assert_eq!(state.total_unread(), 0);
+
+        let mut busy = Conversation::new_direct(Uuid::new_v4(), Uuid::new_v4());
+        busy.unread_count = 4;
+        state.conversations.insert(busy.id, busy.clone());
+        state.set_manually_unread(conversation.id, true);
+        assert_eq!(state.total_unread(), 5);
+
+        state.set_notification_level(busy.id, NotificationLevel::Muted);
+        assert_eq!(state.total_unread(), 1);
+    }
+
+    #[test]
+    fn test_navigate_to_foreign_or_deleted() {
+        let (mut state, conversation, _) = loaded_state();
+
+        let result = state.navigate_to_message(DeepLink::conversation(Uuid::new_v4()));
+        assert_eq!(result, NavigationResult::NotParticipant);
+        assert!(state.ui_error.is_some());
+        assert_eq!(state.selected_conversation_id, None);
+
+        let result = state.navigate_to_message(DeepLink::message(conversation.id, Uuid::new_v4()));
+        assert_eq!(result, NavigationResult::MessageUnavailable);
+        assert_eq!(state.scroll_to_message_id, None);
+    }
+
+    #[test]
+    fn test_navigate_before_load_is_deferred() {
+        let mut state = MessagingState::new();
+        let link = DeepLink::conversation(Uuid::new_v4());
+
+        assert_eq!(state.navigate_to_message(link), NavigationResult::Deferred);
+        assert_eq!(state.pending_deep_link, Some(link));
+    }
+
+    #[test]
+    fn test_conflicts_queue_and_resolve_into_messages() {
+        let (mut state, conversation, message) = loaded_state();
+        let mut sender = MessageCrdt::new(conversation.id, 1);
+        let entry = message_crdt::MessageEntry {
+            id: message.id,
+            ..sender.create_message("hello".to_string(), "text".to_string(), message.sender_id)
+        };
+        let mut ours = MessageCrdt::new(conversation.id, 2);
+        ours.add_received_message(entry.clone());
+        let mut theirs = MessageCrdt::new(conversation.id, 3);
+        theirs.add_received_message(message_crdt::MessageEntry { content: "hello!".to_string(), ..entry });
+        state.message_crdts.insert(conversation.id, ours);
+
+        state.merge_message_crdt(&theirs);
+        assert!(state.pending_conflicts.is_empty());
+        state.check_pending_operations();
+        assert_eq!(state.pending_conflicts.len(), 1);
+
+        state.resolve_conflict(0, Resolution::KeepBoth).unwrap();
+        assert!(state.pending_conflicts.is_empty());
+        let contents: Vec<_> = state.messages[&conversation.id].iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, vec!["hello", "hello!"]);
+    }
+}
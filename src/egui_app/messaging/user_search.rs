@@ -0,0 +1,116 @@
+//! User Search
+//!
+//! Looks up people to add as friends as the user types in the add-friend
+//! modal. Queries go to `GET /api/users/search` from a background worker
+//! thread; results for anything but the latest query are dropped. Typing
+//! is debounced so every keystroke doesn't hit the server.
+
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::egui_app::config::Config;
+use crate::shared::messaging::{is_searchable, UserProfile};
+use super::friend_api::FriendApiClient;
+
+/// Pause in typing before a search starts
+pub const DEBOUNCE: Duration = Duration::from_millis(300);
+
+type SearchResult = (String, Result<Vec<UserProfile>, String>);
+
+/// User search driven by the add-friend modal's input
+#[derive(Default)]
+pub struct UserSearch {
+    /// Trimmed query the results should match
+    query: String,
+    /// When `query` last changed
+    changed_at: Option<Instant>,
+    /// Query sent to the worker and not yet answered
+    in_flight: Option<String>,
+    /// Users matching `query`
+    pub results: Vec<UserProfile>,
+    pub error: Option<String>,
+    worker: Option<(Sender<String>, Receiver<SearchResult>)>,
+}
+
+impl UserSearch {
+    /// Follow the input text; call every frame
+    pub fn set_query(&mut self, query: &str) {
+        let query = query.trim();
+        if query == self.query {
+            return;
+        }
+        self.query = query.to_string();
+        self.changed_at = Some(Instant::now());
+        if !is_searchable(query) {
+            self.results.clear();
+            self.error = None;
+        }
+    }
+
+    /// Whether a search for the current query hasn't finished yet
+    pub fn is_searching(&self) -> bool {
+        is_searchable(&self.query) && (self.changed_at.is_some() || self.in_flight.is_some())
+    }
+
+    /// Collect finished searches and start a pending one once typing pauses
+    ///
+    /// Returns true while there is more to do, so the caller keeps repainting.
+    pub fn poll(&mut self, config: &Config) -> bool {
+        if let Some((_, results)) = &self.worker {
+            for (query, result) in results.try_iter() {
+                if self.in_flight.as_deref() == Some(query.as_str()) {
+                    self.in_flight = None;
+                }
+                if query != self.query {
+                    continue;
+                }
+                match result {
+                    Ok(users) => {
+                        self.results = users;
+                        self.error = None;
+                    }
+                    Err(e) => self.error = Some(e),
+                }
+            }
+        }
+
+        let Some(changed_at) = self.changed_at else {
+            return self.in_flight.is_some();
+        };
+        if !is_searchable(&self.query) {
+            self.changed_at = None;
+            return false;
+        }
+        if changed_at.elapsed() >= DEBOUNCE && self.in_flight.is_none() {
+            let query = self.query.clone();
+            let (queries, _) = self.worker.get_or_insert_with(|| spawn_worker(config.clone()));
+            if queries.send(query.clone()).is_err() {
+                self.worker = None;
+                self.error = Some("User search stopped".to_string());
+            } else {
+                self.in_flight = Some(query);
+            }
+            self.changed_at = None;
+        }
+        true
+    }
+}
+
+/// Thread that answers queries in order against the server
+fn spawn_worker(config: Config) -> (Sender<String>, Receiver<SearchResult>) {
+    let (query_tx, query_rx) = mpsc::channel::<String>();
+    let (result_tx, result_rx) = mpsc::channel();
+
+    thread::spawn(move || {
+        let client = FriendApiClient::new(config);
+        while let Ok(query) = query_rx.recv() {
+            let result = client.search_users(&query);
+            if result_tx.send((query, result)).is_err() {
+                break;
+            }
+        }
+    });
+
+    (query_tx, result_rx)
+}
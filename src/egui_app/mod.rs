@@ -9,9 +9,20 @@
 //!
 //! - **`config`** - Configuration management (server URL, token storage)
 //! - **`auth`** - Authentication UI and API client functions
+//! - **`api_client`** - Typed REST client shared by auth, contacts, friend
+//!   requests, conversations and messages
 //! - **`types`** - Shared types and app state enums
 //! - **`braid_client`** - Braid HTTP protocol client
 //! - **`local_db`** - Local SQLite database for offline functionality
+//! - **`offline`** - Persisted operation queue, retries and optimistic updates
+//! - **`sync`** - Background sync service that sends the queued operations
+//! - **`deep_link`** - `xfmail://` deep link parsing and OS handler registration
+//! - **`media`** - Background media loader (disabled in low data mode)
+//! - **`window_state`** - Persisted window geometry and last-open conversation
+//! - **`notifications`** - Desktop notifications for new messages
+//! - **`badge`** - Unread count on the taskbar/dock badge and window title
+//! - **`app_lock`** - PIN lock that hides content while keeping the session
+//! - **`diagnostics`** - Redacted diagnostic zip for bug reports
 //! - **`messaging_demo`** - Messaging demo placeholder
 //! - **`editing_demo`** - Editing demo placeholder
 //! - **`main`** - Main application entry point (binary)
@@ -24,8 +35,11 @@
 //! ├── main.rs         - Main application entry point
 //! ├── config.rs       - Configuration management
 //! ├── auth.rs         - Authentication UI and functions
+//! ├── api_client.rs   - Typed REST API client
+//! ├── error_text.rs   - Localized text for backend error codes
 //! ├── types.rs        - Shared types
 //! ├── braid_client.rs - Braid HTTP client
+//! ├── deep_link.rs    - Deep link parsing
 //! ├── messaging_demo.rs - Messaging demo placeholder
 //! └── editing_demo.rs   - Editing demo placeholder
 //! ```
@@ -39,9 +53,20 @@
 
 pub mod config;
 pub mod auth;
+pub mod api_client;
+pub mod error_text;
 pub mod types;
 pub mod braid_client;
 pub mod local_db;
+pub mod offline;
+pub mod sync;
+pub mod deep_link;
+pub mod media;
+pub mod window_state;
+pub mod notifications;
+pub mod badge;
+pub mod app_lock;
+pub mod diagnostics;
 pub mod messaging_demo;
 pub mod editing_demo;
 pub mod state;
@@ -54,8 +79,9 @@ mod crdt;
 
 // Re-export commonly used types
 pub use config::Config;
-pub use auth::{AuthState, login, signup, get_me};
+pub use auth::{AuthState, login, signup, get_me, refresh_token};
 pub use types::{AppView, UserInfo};
 pub use state::AppState;
+pub use deep_link::DeepLink;
 pub use debug::{DebugLogger, DebugLevel, DebugCategory};
 
@@ -0,0 +1,358 @@
+//! Desktop Notifications
+//!
+//! Shows an OS notification (and optionally plays a sound) when a message
+//! arrives in a conversation the user isn't looking at.
+//!
+//! A notification is shown only when:
+//!
+//! - notifications are enabled in [`Config`] and do-not-disturb (manual,
+//!   "until tomorrow", or the daily schedule in the configured timezone) is off
+//! - the conversation's [`NotificationLevel`] allows it (mentions-only
+//!   conversations notify on `@username`)
+//! - the message is from someone else and arrived after startup, so replayed
+//!   history never notifies
+//! - the conversation isn't focused (selected in a focused window)
+//!
+//! # OS Integration
+//!
+//! With the `desktop-notifications` feature, [`DesktopNotifier`] posts native
+//! notifications through `notify-rust`. Without it notifications are only
+//! logged.
+
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::egui_app::config::Config;
+use crate::shared::messaging::{display_text, mentions_username, ChatMessage, MessageType};
+
+/// Application name shown on notifications
+pub const APP_NAME: &str = "XFMail";
+
+/// Longest message body shown in a notification
+const BODY_PREVIEW_CHARS: usize = 120;
+
+/// Per-conversation notification setting
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum NotificationLevel {
+    /// Every new message
+    #[default]
+    All,
+    /// Only messages that mention the user
+    Mentions,
+    /// Never
+    Muted,
+}
+
+impl NotificationLevel {
+    pub const ALL: [NotificationLevel; 3] = [Self::All, Self::Mentions, Self::Muted];
+
+    /// Menu label
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::All => "All messages",
+            Self::Mentions => "Mentions only",
+            Self::Muted => "Muted",
+        }
+    }
+
+    /// Whether a message with `content` should notify `username`
+    pub fn allows(self, content: &str, username: Option<&str>) -> bool {
+        match self {
+            Self::All => true,
+            Self::Mentions => username.is_some_and(|name| mentions_username(content, name)),
+            Self::Muted => false,
+        }
+    }
+}
+
+/// A notification to show
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    pub conversation_id: Uuid,
+    pub title: String,
+    pub body: String,
+    /// Play the notification sound
+    pub sound: bool,
+}
+
+/// Delivers notifications to the user
+pub trait Notifier: Send {
+    fn notify(&self, notification: &Notification);
+}
+
+/// Native OS notifications
+#[derive(Debug, Default)]
+pub struct DesktopNotifier;
+
+impl Notifier for DesktopNotifier {
+    #[cfg(feature = "desktop-notifications")]
+    fn notify(&self, notification: &Notification) {
+        let mut native = notify_rust::Notification::new();
+        native.appname(APP_NAME).summary(&notification.title).body(&notification.body);
+        if notification.sound {
+            native.sound_name("message-new-instant");
+        }
+        if let Err(e) = native.show() {
+            tracing::warn!("Failed to show desktop notification: {}", e);
+        }
+    }
+
+    #[cfg(not(feature = "desktop-notifications"))]
+    fn notify(&self, notification: &Notification) {
+        tracing::debug!(
+            "Notification for {} (desktop notifications disabled): {}",
+            notification.conversation_id,
+            notification.title
+        );
+    }
+}
+
+/// An incoming message and the context needed to decide whether to notify
+#[derive(Debug, Clone, Copy)]
+pub struct IncomingMessage<'a> {
+    pub message: &'a ChatMessage,
+    /// Display name of the sender
+    pub sender_name: &'a str,
+    pub level: NotificationLevel,
+    /// Conversation is selected and the window has focus
+    pub focused: bool,
+}
+
+/// Decides which incoming messages notify and hands them to a [`Notifier`]
+pub struct Notifications {
+    notifier: Box<dyn Notifier>,
+    /// Messages older than this are history, not news
+    since: DateTime<Utc>,
+    /// Current time, for the do-not-disturb schedule
+    clock: fn() -> DateTime<Utc>,
+}
+
+impl fmt::Debug for Notifications {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Notifications").field("since", &self.since).finish_non_exhaustive()
+    }
+}
+
+impl Default for Notifications {
+    fn default() -> Self {
+        Self::new(Box::new(DesktopNotifier))
+    }
+}
+
+impl Notifications {
+    pub fn new(notifier: Box<dyn Notifier>) -> Self {
+        Self { notifier, since: Utc::now(), clock: Utc::now }
+    }
+
+    /// Use `clock` instead of the system time when checking do-not-disturb
+    pub fn with_clock(mut self, clock: fn() -> DateTime<Utc>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Only notify for messages sent at or after `since`
+    pub fn with_since(mut self, since: DateTime<Utc>) -> Self {
+        self.since = since;
+        self
+    }
+
+    /// Notification for `incoming`, if one should be shown
+    pub fn notification_for(
+        &self,
+        config: &Config,
+        incoming: &IncomingMessage<'_>,
+        current_user_id: Option<Uuid>,
+        current_username: Option<&str>,
+    ) -> Option<Notification> {
+        let message = incoming.message;
+        if !config.notifications_enabled() || config.do_not_disturb_at((self.clock)()) || incoming.focused {
+            return None;
+        }
+        if current_user_id == Some(message.sender_id) {
+            return None;
+        }
+        if !incoming.level.allows(&message.content, current_username) {
+            return None;
+        }
+        if message.timestamp < self.since {
+            return None;
+        }
+
+        let text = display_text(&message.content);
+        let body = match message.message_type {
+            MessageType::Action => format!("* {} {}", incoming.sender_name, text),
+            _ => text,
+        };
+
+        Some(Notification {
+            conversation_id: message.conversation_id,
+            title: incoming.sender_name.to_string(),
+            body: preview(&body),
+            sound: config.notification_sound(),
+        })
+    }
+
+    /// Show a notification for `incoming` if it warrants one; returns whether it did
+    pub fn message_received(
+        &self,
+        config: &Config,
+        incoming: &IncomingMessage<'_>,
+        current_user_id: Option<Uuid>,
+        current_username: Option<&str>,
+    ) -> bool {
+        match self.notification_for(config, incoming, current_user_id, current_username) {
+            Some(notification) => {
+                self.notifier.notify(&notification);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Message body trimmed for a notification
+fn preview(content: &str) -> String {
+    let content = content.trim();
+    match content.char_indices().nth(BODY_PREVIEW_CHARS) {
+        Some((end, _)) => format!("{}…", &content[..end]),
+        None => content.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::egui_app::config::DndSchedule;
+    use chrono::TimeZone;
+    use std::sync::{Arc, Mutex};
+
+    /// Records notifications instead of showing them
+    #[derive(Default, Clone)]
+    struct MockNotifier(Arc<Mutex<Vec<Notification>>>);
+
+    impl Notifier for MockNotifier {
+        fn notify(&self, notification: &Notification) {
+            self.0.lock().unwrap().push(notification.clone());
+        }
+    }
+
+    fn config() -> Config {
+        let mut config = Config::new();
+        config.set_notifications_enabled(true);
+        config.set_do_not_disturb(false);
+        config
+    }
+
+    fn message(content: &str) -> ChatMessage {
+        ChatMessage::new_text(Uuid::new_v4(), Uuid::new_v4(), content.to_string(), 0)
+    }
+
+    fn incoming(message: &ChatMessage, level: NotificationLevel, focused: bool) -> IncomingMessage<'_> {
+        IncomingMessage { message, sender_name: "Alice", level, focused }
+    }
+
+    fn notifications() -> (Notifications, MockNotifier) {
+        let mock = MockNotifier::default();
+        let since = Utc::now() - chrono::Duration::minutes(1);
+        (Notifications::new(Box::new(mock.clone())).with_since(since), mock)
+    }
+
+    #[test]
+    fn test_unfocused_unmuted_message_notifies() {
+        let (notifications, mock) = notifications();
+        let msg = message("hello there");
+
+        assert!(notifications.message_received(&config(), &incoming(&msg, NotificationLevel::All, false), None, None));
+
+        let shown = mock.0.lock().unwrap();
+        assert_eq!(shown.len(), 1);
+        assert_eq!(shown[0].conversation_id, msg.conversation_id);
+        assert_eq!(shown[0].title, "Alice");
+        assert_eq!(shown[0].body, "hello there");
+    }
+
+    #[test]
+    fn test_suppressed_cases() {
+        let (notifications, mock) = notifications();
+        let msg = message("hello");
+        let config = config();
+
+        // Focused, muted, or our own message
+        assert!(!notifications.message_received(&config, &incoming(&msg, NotificationLevel::All, true), None, None));
+        assert!(!notifications.message_received(&config, &incoming(&msg, NotificationLevel::Muted, false), None, None));
+        assert!(!notifications.message_received(
+            &config,
+            &incoming(&msg, NotificationLevel::All, false),
+            Some(msg.sender_id),
+            None
+        ));
+
+        // Do not disturb and the global toggle
+        let mut dnd = config.clone();
+        dnd.set_do_not_disturb(true);
+        assert!(!notifications.message_received(&dnd, &incoming(&msg, NotificationLevel::All, false), None, None));
+        let mut disabled = config.clone();
+        disabled.set_notifications_enabled(false);
+        assert!(!notifications.message_received(&disabled, &incoming(&msg, NotificationLevel::All, false), None, None));
+
+        // Replayed history
+        let mut old = message("from last week");
+        old.timestamp = Utc::now() - chrono::Duration::days(7);
+        assert!(!notifications.message_received(&config, &incoming(&old, NotificationLevel::All, false), None, None));
+
+        assert!(mock.0.lock().unwrap().is_empty());
+    }
+
+    fn late_evening() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 3, 1, 23, 0, 0).unwrap()
+    }
+
+    fn midday() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn test_dnd_schedule_suppresses_inside_window_only() {
+        let mut config = config();
+        config.set_timezone(chrono::FixedOffset::east_opt(0).unwrap());
+        config.set_dnd_schedule(Some(DndSchedule::default()));
+        let msg = message("hello");
+        let incoming = incoming(&msg, NotificationLevel::All, false);
+
+        let (inside, mock) = notifications();
+        let inside = inside.with_clock(late_evening);
+        assert!(!inside.message_received(&config, &incoming, None, None));
+        assert!(mock.0.lock().unwrap().is_empty());
+
+        let (outside, mock) = notifications();
+        let outside = outside.with_clock(midday);
+        assert!(outside.message_received(&config, &incoming, None, None));
+        assert_eq!(mock.0.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_mentions_only() {
+        let (notifications, mock) = notifications();
+        let config = config();
+        let plain = message("lunch?");
+        let mention = message("@bob lunch?");
+
+        let level = NotificationLevel::Mentions;
+        assert!(!notifications.message_received(&config, &incoming(&plain, level, false), None, Some("bob")));
+        assert!(notifications.message_received(&config, &incoming(&mention, level, false), None, Some("bob")));
+        assert_eq!(mock.0.lock().unwrap().len(), 1);
+
+        let structured = message(&format!("{} lunch?", crate::shared::messaging::mention_token("bob", Uuid::new_v4())));
+        assert!(notifications.message_received(&config, &incoming(&structured, level, false), None, Some("bob")));
+        assert_eq!(mock.0.lock().unwrap()[1].body, "@bob lunch?");
+    }
+
+    #[test]
+    fn test_preview_truncates() {
+        let long = "x".repeat(BODY_PREVIEW_CHARS + 10);
+        assert_eq!(preview(&long).chars().count(), BODY_PREVIEW_CHARS + 1);
+        assert_eq!(preview("  short  "), "short");
+    }
+}
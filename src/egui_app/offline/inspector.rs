@@ -0,0 +1,173 @@
+//! Queue Inspector
+//!
+//! Backs the advanced "Operation Queue" panel used to diagnose stuck syncs.
+//! Like message history, requests run on a background worker thread that
+//! keeps the local database open. Each request reloads the persisted queue,
+//! applies the action and sends back the full listing, so the panel always
+//! shows what is on disk.
+
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+use uuid::Uuid;
+
+use crate::egui_app::local_db::LocalDatabase;
+use crate::egui_app::offline::queue::{DeadLetter, OperationQueue, QueuedOperation};
+
+/// Something to do to the persisted queue before listing it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueueAction {
+    /// Just reload the listing
+    Refresh,
+    /// Put the operation back to pending
+    Retry(Uuid),
+    /// Drop the operation
+    Cancel(Uuid),
+    /// Raise the operation one priority level
+    BumpPriority(Uuid),
+    /// Put a dead-lettered operation back on the queue
+    RequeueDeadLetter(Uuid),
+    /// Put every dead-lettered message send back on the queue
+    RetryFailedMessages,
+}
+
+/// Queued operations and dead letters as read from disk
+struct Listing {
+    operations: Vec<QueuedOperation>,
+    dead_letters: Vec<DeadLetter>,
+}
+
+type ListResult = Result<Listing, String>;
+
+/// Listing and manual actions for the persisted operation queue
+#[derive(Default)]
+pub struct QueueInspector {
+    /// Operations from the last listing, in execution order
+    pub operations: Vec<QueuedOperation>,
+    /// Operations that used up their retries, oldest first
+    pub dead_letters: Vec<DeadLetter>,
+    pub error: Option<String>,
+    /// Requests sent but not answered yet
+    in_flight: usize,
+    worker: Option<(Sender<QueueAction>, Receiver<ListResult>)>,
+}
+
+impl QueueInspector {
+    /// Whether a request is still running
+    pub fn is_loading(&self) -> bool {
+        self.in_flight > 0
+    }
+
+    /// Load the listing once, for views that only need the failed-message count
+    pub fn ensure_loaded(&mut self) {
+        if self.worker.is_none() && self.error.is_none() {
+            self.send(QueueAction::Refresh);
+        }
+    }
+
+    /// Dead-lettered message sends
+    pub fn failed_messages(&self) -> usize {
+        self.dead_letters.iter().filter(|entry| entry.operation.is_message()).count()
+    }
+
+    /// Apply an action and reload the listing
+    pub fn send(&mut self, action: QueueAction) {
+        let (requests, _) = self.worker.get_or_insert_with(spawn_worker);
+        if requests.send(action).is_err() {
+            self.worker = None;
+            self.error = Some("Queue inspector stopped".to_string());
+        } else {
+            self.in_flight += 1;
+        }
+    }
+
+    /// Collect finished listings; returns true if the listing changed
+    pub fn poll(&mut self) -> bool {
+        let Some((_, results)) = self.worker.as_ref() else {
+            return false;
+        };
+        let mut changed = false;
+        while let Ok(result) = results.try_recv() {
+            self.in_flight = self.in_flight.saturating_sub(1);
+            match result {
+                Ok(listing) => {
+                    self.operations = listing.operations;
+                    self.dead_letters = listing.dead_letters;
+                    self.error = None;
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to inspect operation queue: {}", e);
+                    self.error = Some(e);
+                }
+            }
+            changed = true;
+        }
+        changed
+    }
+}
+
+/// Thread that opens the local database once and answers requests in order
+fn spawn_worker() -> (Sender<QueueAction>, Receiver<ListResult>) {
+    let (request_tx, request_rx) = mpsc::channel::<QueueAction>();
+    let (result_tx, result_rx) = mpsc::channel();
+
+    thread::spawn(move || {
+        let rt = match tokio::runtime::Runtime::new() {
+            Ok(rt) => rt,
+            Err(e) => {
+                let error = format!("Failed to create runtime: {}", e);
+                for _ in request_rx {
+                    if result_tx.send(Err(error.clone())).is_err() {
+                        break;
+                    }
+                }
+                return;
+            }
+        };
+        rt.block_on(async {
+            let db = LocalDatabase::new().await.map_err(|e| format!("Failed to open local database: {}", e));
+            while let Ok(action) = request_rx.recv() {
+                let result = match &db {
+                    Ok(db) => apply(db, action).await,
+                    Err(e) => Err(e.clone()),
+                };
+                if result_tx.send(result).is_err() {
+                    break;
+                }
+            }
+        });
+    });
+
+    (request_tx, result_rx)
+}
+
+async fn apply(db: &LocalDatabase, action: QueueAction) -> ListResult {
+    let queue = OperationQueue::load_from_db(db)
+        .await
+        .map_err(|e| format!("Failed to load operation queue: {}", e))?;
+
+    let found = match action {
+        QueueAction::Refresh => true,
+        QueueAction::Retry(id) => queue.requeue_operation(&id).await,
+        QueueAction::Cancel(id) => queue.cancel_operation(&id).await,
+        QueueAction::BumpPriority(id) => queue.bump_priority(&id).await.is_some(),
+        QueueAction::RequeueDeadLetter(id) => queue.requeue_dead_letter(&id).await,
+        QueueAction::RetryFailedMessages => {
+            let mut requeued = false;
+            for entry in queue.dead_letters().await {
+                if entry.operation.is_message() {
+                    requeued |= queue.requeue_dead_letter(&entry.operation.id()).await;
+                }
+            }
+            requeued
+        }
+    };
+    if !found {
+        tracing::debug!("{:?} ignored: operation already left the queue", action);
+    }
+
+    Ok(Listing {
+        operations: queue.list_all().await,
+        dead_letters: queue.dead_letters().await,
+    })
+}
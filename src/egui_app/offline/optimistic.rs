@@ -69,7 +69,7 @@ pub enum UiState {
     },
     /// Optimistically accepted friend request
     FriendRequestAccepted {
-        user_id: Uuid,
+        request_id: Uuid,
     },
     /// Optimistically sent friend request
     FriendRequestSent {
@@ -87,7 +87,7 @@ impl OptimisticManager {
 
     /// Apply an optimistic update
     pub async fn apply_update(&self, operation: Operation) {
-        let update_id = operation.id;
+        let update_id = operation.id();
         let applied_at = chrono::Utc::now().to_rfc3339();
 
         let ui_state = match &operation {
@@ -104,9 +104,9 @@ impl OptimisticManager {
                     username: username.clone(),
                 }
             }
-            Operation::AcceptFriendRequest { user_id, .. } => {
+            Operation::AcceptFriendRequest { request_id, .. } => {
                 UiState::FriendRequestAccepted {
-                    user_id: *user_id,
+                    request_id: *request_id,
                 }
             }
             Operation::SendFriendRequest { user_id, .. } => {
@@ -204,6 +204,7 @@ impl Default for OptimisticManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::shared::messaging::MessageType;
 
     #[tokio::test]
     async fn test_optimistic_manager_creation() {
@@ -219,6 +220,7 @@ mod tests {
             id: Uuid::new_v4(),
             conversation_id: Uuid::new_v4(),
             content: "Test message".to_string(),
+            message_type: MessageType::Text,
             timestamp: chrono::Utc::now().to_rfc3339(),
         };
 
@@ -227,7 +229,7 @@ mod tests {
         assert_eq!(manager.count_pending().await, 1);
 
         // Confirm operation
-        manager.confirm_operation(&operation.id).await;
+        manager.confirm_operation(&operation.id()).await;
         assert_eq!(manager.count_pending().await, 0);
     }
 
@@ -239,6 +241,7 @@ mod tests {
             id: Uuid::new_v4(),
             conversation_id: Uuid::new_v4(),
             content: "Test message".to_string(),
+            message_type: MessageType::Text,
             timestamp: chrono::Utc::now().to_rfc3339(),
         };
 
@@ -247,7 +250,7 @@ mod tests {
         assert_eq!(manager.count_pending().await, 1);
 
         // Rollback operation
-        manager.rollback_operation(&operation.id).await;
+        manager.rollback_operation(&operation.id()).await;
         assert_eq!(manager.count_pending().await, 0);
     }
 
@@ -260,6 +263,7 @@ mod tests {
             id: Uuid::new_v4(),
             conversation_id,
             content: "Test message".to_string(),
+            message_type: MessageType::Text,
             timestamp: chrono::Utc::now().to_rfc3339(),
         };
 
@@ -5,16 +5,20 @@
 //!
 //! ## Features
 //!
-//! - **Persistent Queue**: Operations survive app restarts
+//! - **Persistent Queue**: Operations survive app restarts (write-through to
+//!   the local database's `offline_queue` table, see [`OperationQueue::load_from_db`])
 //! - **Priority Support**: Different priority levels for operations
 //! - **Status Tracking**: Track operation execution status
 //! - **Batch Processing**: Process multiple operations efficiently
 //! - **Cleanup**: Remove old failed operations
+//! - **Dead Letters**: Operations that use up their retries are kept for a
+//!   manual retry instead of being cleaned up
 //!
 //! ## Usage
 //!
 //! ```rust,no_run
 //! use xfmail::egui_app::offline::queue::{OperationQueue, Operation};
+//! use xfmail::shared::messaging::MessageType;
 //!
 //! let mut queue = OperationQueue::new();
 //!
@@ -23,6 +27,7 @@
 //!     id: Uuid::new_v4(),
 //!     conversation_id: conversation_id,
 //!     content: "Hello offline!".to_string(),
+//!     message_type: MessageType::Text,
 //!     timestamp: chrono::Utc::now().to_rfc3339(),
 //! };
 //! queue.add_operation(operation).await;
@@ -35,7 +40,10 @@
 //! }
 //! ```
 
+use crate::egui_app::local_db::LocalDatabase;
+use crate::shared::messaging::MessageType;
 use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqlitePool};
 use std::collections::VecDeque;
 use tokio::sync::RwLock;
 use uuid::Uuid;
@@ -45,6 +53,10 @@ use uuid::Uuid;
 pub struct OperationQueue {
     /// Queued operations
     operations: RwLock<VecDeque<QueuedOperation>>,
+    /// Operations that used up their retries, oldest first
+    dead_letters: RwLock<Vec<DeadLetter>>,
+    /// Backing `offline_queue` table; `None` for an in-memory queue
+    pool: Option<SqlitePool>,
 }
 
 /// Queued operation with metadata
@@ -66,6 +78,19 @@ pub struct QueuedOperation {
     pub last_error: Option<String>,
 }
 
+/// Operation that used up its retries, kept so the user can send it again
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadLetter {
+    /// Operation details
+    pub operation: Operation,
+    /// Error from the final attempt
+    pub last_error: Option<String>,
+    /// Number of failed attempts
+    pub attempts: u32,
+    /// Timestamp when the operation was given up on
+    pub failed_at: String,
+}
+
 /// Operation types that can be queued
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Operation {
@@ -77,6 +102,9 @@ pub enum Operation {
         conversation_id: Uuid,
         /// Message content
         content: String,
+        /// Type of message; operations queued before it was recorded are text
+        #[serde(default)]
+        message_type: MessageType,
         /// Timestamp
         timestamp: String,
     },
@@ -142,11 +170,104 @@ pub enum Priority {
 }
 
 impl OperationQueue {
-    /// Create a new operation queue
+    /// Create a new in-memory operation queue
     pub fn new() -> Self {
         Self {
             operations: RwLock::new(VecDeque::new()),
+            dead_letters: RwLock::new(Vec::new()),
+            pool: None,
+        }
+    }
+
+    /// Rehydrate the queue from the local database
+    ///
+    /// Operations come back in priority order (FIFO within a priority) with
+    /// their status and retry count. Later changes are written through to
+    /// the same table.
+    pub async fn load_from_db(db: &LocalDatabase) -> sqlx::Result<Self> {
+        let pool = db.pool().clone();
+        let (operations, dead_letters) = Self::read_tables(&pool).await?;
+
+        Ok(Self {
+            operations: RwLock::new(operations),
+            dead_letters: RwLock::new(dead_letters),
+            pool: Some(pool),
+        })
+    }
+
+    /// Re-read the queue from the local database
+    ///
+    /// Picks up changes made through another handle on the same tables, such
+    /// as the queue panel's retries and cancels. Does nothing for an
+    /// in-memory queue.
+    pub async fn reload(&self) -> sqlx::Result<()> {
+        let Some(pool) = &self.pool else {
+            return Ok(());
+        };
+        let (operations, dead_letters) = Self::read_tables(pool).await?;
+        *self.operations.write().await = operations;
+        *self.dead_letters.write().await = dead_letters;
+        Ok(())
+    }
+
+    async fn read_tables(pool: &SqlitePool) -> sqlx::Result<(VecDeque<QueuedOperation>, Vec<DeadLetter>)> {
+        let rows = sqlx::query(
+            "SELECT data, status, priority, retry_count, created_at, last_attempt, error_message
+             FROM offline_queue
+             WHERE operation_type IN ('SendMessage', 'AddContact', 'SendFriendRequest', 'AcceptFriendRequest')
+             ORDER BY priority DESC, rowid ASC",
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let mut operations = VecDeque::with_capacity(rows.len());
+        for row in rows {
+            let data: String = row.try_get("data")?;
+            let operation = match serde_json::from_str::<Operation>(&data) {
+                Ok(operation) => operation,
+                Err(e) => {
+                    tracing::warn!("Skipping malformed queued operation: {}", e);
+                    continue;
+                }
+            };
+
+            operations.push_back(QueuedOperation {
+                operation,
+                status: OperationStatus::from_db(&row.try_get::<String, _>("status")?),
+                priority: Priority::from_db(row.try_get("priority")?),
+                retry_count: row.try_get::<i64, _>("retry_count")? as u32,
+                queued_at: row.try_get("created_at")?,
+                last_attempt: row.try_get("last_attempt")?,
+                last_error: row.try_get("error_message")?,
+            });
         }
+
+        let rows = sqlx::query(
+            "SELECT data, last_error, attempts, failed_at FROM dead_letter ORDER BY failed_at ASC, rowid ASC",
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let mut dead_letters = Vec::with_capacity(rows.len());
+        for row in rows {
+            let data: String = row.try_get("data")?;
+            let operation = match serde_json::from_str::<Operation>(&data) {
+                Ok(operation) => operation,
+                Err(e) => {
+                    tracing::warn!("Skipping malformed dead letter: {}", e);
+                    continue;
+                }
+            };
+
+            dead_letters.push(DeadLetter {
+                operation,
+                last_error: row.try_get("last_error")?,
+                attempts: row.try_get::<i64, _>("attempts")? as u32,
+                failed_at: row.try_get("failed_at")?,
+            });
+        }
+
+        Ok((operations, dead_letters))
     }
 
     /// Add an operation to the queue
@@ -161,13 +282,14 @@ impl OperationQueue {
             last_error: None,
         };
 
+        self.persist_insert(&queued_op).await;
         let mut operations = self.operations.write().await;
         operations.push_back(queued_op);
     }
 
     /// Add operation with specific priority
     pub async fn add_operation_with_priority(&self, operation: Operation, priority: Priority) {
-        let mut queued_op = QueuedOperation {
+        let queued_op = QueuedOperation {
             operation,
             status: OperationStatus::Pending,
             priority,
@@ -182,9 +304,10 @@ impl OperationQueue {
         // Insert based on priority (higher priority first)
         let insert_pos = operations
             .iter()
-            .position(|op| op.priority < priority)
+            .position(|op| op.priority < queued_op.priority)
             .unwrap_or(operations.len());
 
+        self.persist_insert(&queued_op).await;
         operations.insert(insert_pos, queued_op);
     }
 
@@ -208,12 +331,22 @@ impl OperationQueue {
             .collect()
     }
 
+    /// Look up a queued operation by ID
+    pub async fn get_operation(&self, operation_id: &Uuid) -> Option<QueuedOperation> {
+        let operations = self.operations.read().await;
+        operations
+            .iter()
+            .find(|op| op.operation.id() == *operation_id)
+            .cloned()
+    }
+
     /// Mark operation as in progress
     pub async fn start_operation(&self, operation_id: &Uuid) {
         let mut operations = self.operations.write().await;
         if let Some(op) = operations.iter_mut().find(|op| op.operation.id() == *operation_id) {
             op.status = OperationStatus::InProgress;
             op.last_attempt = Some(chrono::Utc::now().to_rfc3339());
+            self.persist_update(op).await;
         }
     }
 
@@ -222,6 +355,7 @@ impl OperationQueue {
         let mut operations = self.operations.write().await;
         operations.retain(|op| op.operation.id() != *operation_id);
         // Note: In a real implementation, completed operations might be archived
+        self.persist_delete(&[*operation_id]).await;
     }
 
     /// Mark operation as failed
@@ -231,6 +365,7 @@ impl OperationQueue {
             op.status = OperationStatus::Failed;
             op.last_error = Some(error);
             op.retry_count += 1;
+            self.persist_update(op).await;
         }
     }
 
@@ -240,7 +375,105 @@ impl OperationQueue {
         if let Some(op) = operations.iter_mut().find(|op| op.operation.id() == *operation_id) {
             op.status = OperationStatus::Retrying;
             op.retry_count += 1;
+            self.persist_update(op).await;
+        }
+    }
+
+    /// Every queued operation with its metadata, in execution order
+    pub async fn list_all(&self) -> Vec<QueuedOperation> {
+        let operations = self.operations.read().await;
+        operations.iter().cloned().collect()
+    }
+
+    /// Put an operation back to pending so the next sync picks it up
+    ///
+    /// Keeps the retry count and last error for diagnosis. Returns false if
+    /// the operation isn't queued.
+    pub async fn requeue_operation(&self, operation_id: &Uuid) -> bool {
+        let mut operations = self.operations.write().await;
+        let Some(op) = operations.iter_mut().find(|op| op.operation.id() == *operation_id) else {
+            return false;
+        };
+        op.status = OperationStatus::Pending;
+        self.persist_update(op).await;
+        true
+    }
+
+    /// Drop an operation without executing it
+    ///
+    /// Returns false if the operation isn't queued.
+    pub async fn cancel_operation(&self, operation_id: &Uuid) -> bool {
+        let mut operations = self.operations.write().await;
+        let before = operations.len();
+        operations.retain(|op| op.operation.id() != *operation_id);
+        if operations.len() == before {
+            return false;
         }
+        self.persist_delete(&[*operation_id]).await;
+        true
+    }
+
+    /// Raise an operation one priority level and move it ahead of lower
+    /// priority operations
+    ///
+    /// Returns the new priority, or `None` if the operation isn't queued.
+    pub async fn bump_priority(&self, operation_id: &Uuid) -> Option<Priority> {
+        let mut operations = self.operations.write().await;
+        let index = operations.iter().position(|op| op.operation.id() == *operation_id)?;
+        let mut op = operations.remove(index)?;
+        op.priority = op.priority.raised();
+
+        let insert_pos = operations
+            .iter()
+            .position(|other| other.priority < op.priority)
+            .unwrap_or(operations.len())
+            .min(index);
+
+        self.persist_update(&op).await;
+        let priority = op.priority.clone();
+        operations.insert(insert_pos, op);
+        Some(priority)
+    }
+
+    /// Move an operation that used up its retries to the dead-letter store
+    ///
+    /// Returns the new entry, or `None` if the operation isn't queued.
+    pub async fn dead_letter_operation(&self, operation_id: &Uuid) -> Option<DeadLetter> {
+        let mut operations = self.operations.write().await;
+        let index = operations.iter().position(|op| op.operation.id() == *operation_id)?;
+        let op = operations.remove(index)?;
+
+        let entry = DeadLetter {
+            operation: op.operation,
+            last_error: op.last_error,
+            attempts: op.retry_count,
+            failed_at: chrono::Utc::now().to_rfc3339(),
+        };
+        self.persist_dead_letter(&entry).await;
+        self.dead_letters.write().await.push(entry.clone());
+        Some(entry)
+    }
+
+    /// Operations that used up their retries, oldest first
+    pub async fn dead_letters(&self) -> Vec<DeadLetter> {
+        self.dead_letters.read().await.clone()
+    }
+
+    /// Put a dead-lettered operation back on the queue with a fresh retry budget
+    ///
+    /// Returns false if there is no dead letter with this ID.
+    pub async fn requeue_dead_letter(&self, operation_id: &Uuid) -> bool {
+        let mut dead_letters = self.dead_letters.write().await;
+        let Some(index) = dead_letters.iter().position(|entry| entry.operation.id() == *operation_id) else {
+            return false;
+        };
+        let entry = dead_letters.remove(index);
+        drop(dead_letters);
+
+        self.persist_delete_dead_letter(operation_id).await;
+        let priority = entry.operation.priority();
+        self.add_operation_with_priority(entry.operation, priority).await;
+        true
     }
 
     /// Get operation statistics
@@ -270,6 +503,7 @@ impl OperationQueue {
             completed,
             failed,
             retrying,
+            dead_letters: self.dead_letters.read().await.len(),
         }
     }
 
@@ -294,20 +528,146 @@ impl OperationQueue {
         let cutoff = chrono::Utc::now() - chrono::Duration::hours(max_age_hours);
 
         let mut operations = self.operations.write().await;
+        let mut removed = Vec::new();
         operations.retain(|op| {
             if op.status == OperationStatus::Failed {
                 if let Ok(queued_time) = chrono::DateTime::parse_from_rfc3339(&op.queued_at) {
-                    return queued_time > cutoff;
+                    if queued_time <= cutoff {
+                        removed.push(op.operation.id());
+                        return false;
+                    }
                 }
             }
             true
         });
+        self.persist_delete(&removed).await;
     }
 
     /// Clear all operations (for testing or reset)
     pub async fn clear(&self) {
         let mut operations = self.operations.write().await;
+        let ids: Vec<Uuid> = operations.iter().map(|op| op.operation.id()).collect();
         operations.clear();
+        self.persist_delete(&ids).await;
+    }
+
+    /// Write a new operation to the backing table
+    ///
+    /// Persistence failures are logged; the in-memory queue stays authoritative
+    /// for the running session.
+    async fn persist_insert(&self, op: &QueuedOperation) {
+        let Some(pool) = &self.pool else { return };
+        let data = match serde_json::to_string(&op.operation) {
+            Ok(data) => data,
+            Err(e) => {
+                tracing::warn!("Failed to serialize queued operation: {}", e);
+                return;
+            }
+        };
+
+        let result = sqlx::query(
+            "INSERT OR REPLACE INTO offline_queue (
+                id, operation_type, data, created_at, retry_count, last_attempt, error_message,
+                status, priority
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(op.operation.id().to_string())
+        .bind(op.operation.type_name())
+        .bind(data)
+        .bind(&op.queued_at)
+        .bind(op.retry_count as i64)
+        .bind(&op.last_attempt)
+        .bind(&op.last_error)
+        .bind(op.status.as_db())
+        .bind(op.priority.as_db())
+        .execute(pool)
+        .await;
+
+        if let Err(e) = result {
+            tracing::warn!("Failed to persist queued operation {}: {}", op.operation.id(), e);
+        }
+    }
+
+    /// Write an operation's status and priority columns to the backing table
+    async fn persist_update(&self, op: &QueuedOperation) {
+        let Some(pool) = &self.pool else { return };
+
+        let result = sqlx::query(
+            "UPDATE offline_queue SET status = ?, priority = ?, retry_count = ?, last_attempt = ?, error_message = ?
+             WHERE id = ?",
+        )
+        .bind(op.status.as_db())
+        .bind(op.priority.as_db())
+        .bind(op.retry_count as i64)
+        .bind(&op.last_attempt)
+        .bind(&op.last_error)
+        .bind(op.operation.id().to_string())
+        .execute(pool)
+        .await;
+
+        if let Err(e) = result {
+            tracing::warn!("Failed to update queued operation {}: {}", op.operation.id(), e);
+        }
+    }
+
+    /// Record a dead letter and drop its row from `offline_queue` in one transaction
+    async fn persist_dead_letter(&self, entry: &DeadLetter) {
+        let Some(pool) = &self.pool else { return };
+        if let Err(e) = Self::write_dead_letter(pool, entry).await {
+            tracing::warn!("Failed to persist dead letter {}: {}", entry.operation.id(), e);
+        }
+    }
+
+    async fn write_dead_letter(pool: &SqlitePool, entry: &DeadLetter) -> sqlx::Result<()> {
+        let data = serde_json::to_string(&entry.operation).map_err(|e| sqlx::Error::Encode(Box::new(e)))?;
+        let id = entry.operation.id().to_string();
+
+        let mut tx = pool.begin().await?;
+        sqlx::query(
+            "INSERT OR REPLACE INTO dead_letter (id, operation_type, data, last_error, attempts, failed_at)
+             VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&id)
+        .bind(entry.operation.type_name())
+        .bind(data)
+        .bind(&entry.last_error)
+        .bind(entry.attempts as i64)
+        .bind(&entry.failed_at)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query("DELETE FROM offline_queue WHERE id = ?")
+            .bind(&id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await
+    }
+
+    /// Remove a dead letter from the backing table
+    async fn persist_delete_dead_letter(&self, operation_id: &Uuid) {
+        let Some(pool) = &self.pool else { return };
+
+        if let Err(e) = sqlx::query("DELETE FROM dead_letter WHERE id = ?")
+            .bind(operation_id.to_string())
+            .execute(pool)
+            .await
+        {
+            tracing::warn!("Failed to delete dead letter {}: {}", operation_id, e);
+        }
+    }
+
+    /// Remove operations from the backing table
+    async fn persist_delete(&self, ids: &[Uuid]) {
+        let Some(pool) = &self.pool else { return };
+
+        for id in ids {
+            if let Err(e) = sqlx::query("DELETE FROM offline_queue WHERE id = ?")
+                .bind(id.to_string())
+                .execute(pool)
+                .await
+            {
+                tracing::warn!("Failed to delete queued operation {}: {}", id, e);
+            }
+        }
     }
 }
 
@@ -326,6 +686,8 @@ pub struct QueueStats {
     pub failed: usize,
     /// Operations being retried
     pub retrying: usize,
+    /// Operations that used up their retries
+    pub dead_letters: usize,
 }
 
 impl Operation {
@@ -339,6 +701,26 @@ impl Operation {
         }
     }
 
+    /// Whether this is a message send (kept flowing on slow connections)
+    pub fn is_message(&self) -> bool {
+        matches!(self, Operation::SendMessage { .. })
+    }
+
+    /// Approximate bytes put on the wire when executed
+    pub fn payload_size(&self) -> usize {
+        serde_json::to_vec(self).map(|bytes| bytes.len()).unwrap_or(0)
+    }
+
+    /// Variant name stored in `offline_queue.operation_type`
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Operation::SendMessage { .. } => "SendMessage",
+            Operation::AddContact { .. } => "AddContact",
+            Operation::SendFriendRequest { .. } => "SendFriendRequest",
+            Operation::AcceptFriendRequest { .. } => "AcceptFriendRequest",
+        }
+    }
+
     /// Get operation priority (default implementation)
     pub fn priority(&self) -> Priority {
         match self {
@@ -350,6 +732,57 @@ impl Operation {
     }
 }
 
+impl OperationStatus {
+    fn as_db(&self) -> &'static str {
+        match self {
+            OperationStatus::Pending => "Pending",
+            OperationStatus::InProgress => "InProgress",
+            OperationStatus::Completed => "Completed",
+            OperationStatus::Failed => "Failed",
+            OperationStatus::Retrying => "Retrying",
+        }
+    }
+
+    /// Parse a stored status; an operation interrupted mid-flight is pending again
+    fn from_db(value: &str) -> Self {
+        match value {
+            "Completed" => OperationStatus::Completed,
+            "Failed" => OperationStatus::Failed,
+            "Retrying" => OperationStatus::Retrying,
+            _ => OperationStatus::Pending,
+        }
+    }
+}
+
+impl Priority {
+    fn as_db(&self) -> i64 {
+        match self {
+            Priority::Low => 0,
+            Priority::Normal => 1,
+            Priority::High => 2,
+            Priority::Critical => 3,
+        }
+    }
+
+    fn from_db(value: i64) -> Self {
+        match value {
+            0 => Priority::Low,
+            2 => Priority::High,
+            3 => Priority::Critical,
+            _ => Priority::Normal,
+        }
+    }
+
+    /// The next level up; critical stays critical
+    pub fn raised(&self) -> Self {
+        match self {
+            Priority::Low => Priority::Normal,
+            Priority::Normal => Priority::High,
+            Priority::High | Priority::Critical => Priority::Critical,
+        }
+    }
+}
+
 impl Default for OperationQueue {
     fn default() -> Self {
         Self::new()
@@ -375,6 +808,7 @@ mod tests {
             id: Uuid::new_v4(),
             conversation_id: Uuid::new_v4(),
             content: "Test message".to_string(),
+            message_type: MessageType::Text,
             timestamp: chrono::Utc::now().to_rfc3339(),
         };
 
@@ -395,6 +829,7 @@ mod tests {
             id: Uuid::new_v4(),
             conversation_id: Uuid::new_v4(),
             content: "Test message".to_string(),
+            message_type: MessageType::Text,
             timestamp: chrono::Utc::now().to_rfc3339(),
         };
 
@@ -429,6 +864,7 @@ mod tests {
             id: Uuid::new_v4(),
             conversation_id: Uuid::new_v4(),
             content: "Urgent message".to_string(),
+            message_type: MessageType::Text,
             timestamp: chrono::Utc::now().to_rfc3339(),
         };
 
@@ -452,6 +888,7 @@ mod tests {
             id: Uuid::new_v4(),
             conversation_id: Uuid::new_v4(),
             content: "Test message".to_string(),
+            message_type: MessageType::Text,
             timestamp: chrono::Utc::now().to_rfc3339(),
         };
 
@@ -468,4 +905,180 @@ mod tests {
         queue.cleanup_failed_operations(0).await;
         assert_eq!(queue.count_failed().await, 0);
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_queue_survives_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = LocalDatabase::open(&dir.path().join("local.db"), None).await.unwrap();
+        let queue = OperationQueue::load_from_db(&db).await.unwrap();
+
+        let normal = Operation::AddContact {
+            id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            username: "bob".to_string(),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        };
+        let urgent = Operation::SendMessage {
+            id: Uuid::new_v4(),
+            conversation_id: Uuid::new_v4(),
+            content: "Queued while offline".to_string(),
+            message_type: MessageType::Text,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        };
+        let done = Operation::AcceptFriendRequest {
+            id: Uuid::new_v4(),
+            request_id: Uuid::new_v4(),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        };
+
+        queue.add_operation(normal.clone()).await;
+        queue.add_operation_with_priority(urgent.clone(), Priority::High).await;
+        queue.add_operation(done.clone()).await;
+        queue.fail_operation(&normal.id(), "Network error".to_string()).await;
+        queue.retry_operation(&normal.id()).await;
+        queue.complete_operation(&done.id()).await;
+
+        // Simulate a restart
+        drop(queue);
+        let reloaded = OperationQueue::load_from_db(&db).await.unwrap();
+
+        let stats = reloaded.get_stats().await;
+        assert_eq!(stats.total_operations, 2);
+
+        let urgent_op = reloaded.get_operation(&urgent.id()).await.unwrap();
+        assert_eq!(urgent_op.priority, Priority::High);
+        assert_eq!(urgent_op.status, OperationStatus::Pending);
+
+        let normal_op = reloaded.get_operation(&normal.id()).await.unwrap();
+        assert_eq!(normal_op.status, OperationStatus::Retrying);
+        assert_eq!(normal_op.retry_count, 2);
+        assert_eq!(normal_op.last_error.as_deref(), Some("Network error"));
+
+        // Priority order is preserved
+        let pending = reloaded.get_pending_operations().await;
+        assert_eq!(pending[0].operation.id(), urgent.id());
+    }
+
+    #[tokio::test]
+    async fn test_list_all_returns_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = LocalDatabase::open(&dir.path().join("local.db"), None).await.unwrap();
+        let queue = OperationQueue::load_from_db(&db).await.unwrap();
+
+        let stuck = Operation::SendMessage {
+            id: Uuid::new_v4(),
+            conversation_id: Uuid::new_v4(),
+            content: "Stuck message".to_string(),
+            message_type: MessageType::Text,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        };
+        let contact = Operation::AddContact {
+            id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            username: "bob".to_string(),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        };
+
+        queue.add_operation(stuck.clone()).await;
+        queue.add_operation(contact.clone()).await;
+        queue.fail_operation(&stuck.id(), "Timed out".to_string()).await;
+
+        let all = queue.list_all().await;
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].operation.type_name(), "SendMessage");
+        assert_eq!(all[0].status, OperationStatus::Failed);
+        assert_eq!(all[0].retry_count, 1);
+        assert_eq!(all[0].priority, Priority::Normal);
+        assert_eq!(all[0].last_error.as_deref(), Some("Timed out"));
+        assert_eq!(all[1].operation.type_name(), "AddContact");
+        assert_eq!(all[1].status, OperationStatus::Pending);
+        assert_eq!(all[1].last_error, None);
+
+        // Manual actions from the queue panel are written through
+        assert!(queue.requeue_operation(&stuck.id()).await);
+        assert_eq!(queue.bump_priority(&contact.id()).await, Some(Priority::High));
+        assert!(!queue.cancel_operation(&Uuid::new_v4()).await);
+
+        let reloaded = OperationQueue::load_from_db(&db).await.unwrap();
+        let all = reloaded.list_all().await;
+        assert_eq!(all[0].operation.id(), contact.id());
+        assert_eq!(all[0].priority, Priority::High);
+        assert_eq!(all[1].status, OperationStatus::Pending);
+        assert_eq!(all[1].retry_count, 1);
+
+        assert!(reloaded.cancel_operation(&stuck.id()).await);
+        assert_eq!(reloaded.list_all().await.len(), 1);
+
+        // Another handle on the same tables picks the cancel up on reload
+        assert_eq!(queue.list_all().await.len(), 2);
+        queue.reload().await.unwrap();
+        assert_eq!(queue.list_all().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_cleanup_failed_operations_deletes_rows() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = LocalDatabase::open(&dir.path().join("local.db"), None).await.unwrap();
+        let queue = OperationQueue::load_from_db(&db).await.unwrap();
+
+        let operation = Operation::SendMessage {
+            id: Uuid::new_v4(),
+            conversation_id: Uuid::new_v4(),
+            content: "Test message".to_string(),
+            message_type: MessageType::Text,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        };
+        queue.add_operation(operation.clone()).await;
+        queue.fail_operation(&operation.id(), "Error".to_string()).await;
+        queue.cleanup_failed_operations(0).await;
+
+        let reloaded = OperationQueue::load_from_db(&db).await.unwrap();
+        assert_eq!(reloaded.get_stats().await.total_operations, 0);
+    }
+
+    #[tokio::test]
+    async fn test_dead_letters_survive_reload_and_requeue() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = LocalDatabase::open(&dir.path().join("local.db"), None).await.unwrap();
+        let queue = OperationQueue::load_from_db(&db).await.unwrap();
+
+        let operation = Operation::SendMessage {
+            id: Uuid::new_v4(),
+            conversation_id: Uuid::new_v4(),
+            content: "Never delivered".to_string(),
+            message_type: MessageType::Text,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        };
+        queue.add_operation(operation.clone()).await;
+        for attempt in 1..=3 {
+            queue.fail_operation(&operation.id(), format!("Network error {}", attempt)).await;
+        }
+
+        let entry = queue.dead_letter_operation(&operation.id()).await.unwrap();
+        assert_eq!(entry.attempts, 3);
+        assert!(queue.dead_letter_operation(&operation.id()).await.is_none());
+
+        // Cleaning up failed operations no longer loses it
+        queue.cleanup_failed_operations(0).await;
+
+        let reloaded = OperationQueue::load_from_db(&db).await.unwrap();
+        let stats = reloaded.get_stats().await;
+        assert_eq!(stats.total_operations, 0);
+        assert_eq!(stats.dead_letters, 1);
+
+        let dead_letters = reloaded.dead_letters().await;
+        assert_eq!(dead_letters[0].operation.id(), operation.id());
+        assert_eq!(dead_letters[0].last_error.as_deref(), Some("Network error 3"));
+        assert_eq!(dead_letters[0].attempts, 3);
+
+        assert!(reloaded.requeue_dead_letter(&operation.id()).await);
+        assert!(!reloaded.requeue_dead_letter(&operation.id()).await);
+
+        let reloaded = OperationQueue::load_from_db(&db).await.unwrap();
+        assert!(reloaded.dead_letters().await.is_empty());
+        let requeued = reloaded.get_operation(&operation.id()).await.unwrap();
+        assert_eq!(requeued.status, OperationStatus::Pending);
+        assert_eq!(requeued.retry_count, 0);
+        assert_eq!(requeued.priority, Priority::High);
+    }
+}
@@ -30,6 +30,8 @@
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
 
 /// State reconciliation manager
 #[derive(Debug)]
@@ -39,14 +41,24 @@ pub struct ReconciliationManager {
 }
 
 /// Reconciliation strategy
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub enum ReconciliationStrategy {
     /// Last-write-wins strategy
     LastWriteWins,
     /// Merge strategy (combine changes)
     Merge,
     /// Custom reconciliation function
-    Custom(Box<dyn Fn(&ReconciliationInput) -> ReconciliationResult + Send + Sync>),
+    Custom(Arc<dyn Fn(&ReconciliationInput) -> ReconciliationResult + Send + Sync>),
+}
+
+impl fmt::Debug for ReconciliationStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReconciliationStrategy::LastWriteWins => f.write_str("LastWriteWins"),
+            ReconciliationStrategy::Merge => f.write_str("Merge"),
+            ReconciliationStrategy::Custom(_) => f.write_str("Custom(..)"),
+        }
+    }
 }
 
 /// Input for reconciliation process
@@ -5,28 +5,36 @@
 //!
 //! ## Features
 //!
-//! - **Exponential Backoff**: Gradually increase retry intervals
+//! - **Backoff Strategies**: Fixed, linear, exponential or Fibonacci intervals
+//!   under a configurable ceiling
 //! - **Jitter**: Add randomness to prevent thundering herd
-//! - **Max Retries**: Limit retry attempts to prevent infinite loops
-//! - **Circuit Breaker**: Temporarily disable failing operations
+//! - **Max Retries**: Operations that use up their attempts are permanently
+//!   failed instead of retried forever
 //!
 //! ## Usage
 //!
 //! ```rust,no_run
 //! use xfmail::egui_app::offline::retry::{RetryManager, BackoffStrategy};
 //!
-//! let mut retry_manager = RetryManager::new();
+//! let retry_manager = RetryManager::new_with(
+//!     BackoffStrategy::Fibonacci { base_interval: 2 },
+//!     5,
+//!     Duration::from_secs(120),
+//! );
 //!
 //! // Schedule retry for failed operation
-//! retry_manager.schedule_retry(operation).await;
+//! retry_manager.schedule_retry(operation, error).await;
 //!
-//! // Process retries
-//! retry_manager.process_retries().await;
+//! // Operations whose backoff window has elapsed
+//! let due = retry_manager.process_retries().await;
 //! ```
 
-use crate::egui_app::offline::queue::{Operation, QueuedOperation, OperationStatus};
+use crate::egui_app::offline::queue::Operation;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
 use uuid::Uuid;
 
@@ -35,8 +43,14 @@ use uuid::Uuid;
 pub struct RetryManager {
     /// Operations currently being retried
     retrying_operations: RwLock<HashMap<Uuid, RetryState>>,
+    /// Operations that used up `max_attempts`; never retried automatically
+    exhausted_operations: RwLock<HashMap<Uuid, RetryState>>,
     /// Backoff strategy
     backoff_strategy: BackoffStrategy,
+    /// Total tries an operation gets, counting the first one
+    max_attempts: u32,
+    /// Ceiling applied to every delay the strategy produces
+    max_delay: Duration,
 }
 
 /// Retry state for an operation
@@ -44,7 +58,7 @@ pub struct RetryManager {
 pub struct RetryState {
     /// Operation being retried
     pub operation: Operation,
-    /// Current retry attempt number
+    /// Number of failed attempts so far
     pub attempt: u32,
     /// Maximum retry attempts
     pub max_attempts: u32,
@@ -52,16 +66,36 @@ pub struct RetryState {
     pub next_retry_at: String,
     /// Last error message
     pub last_error: String,
+    /// Handed out by `process_retries` and not reported back yet
+    #[serde(default)]
+    pub in_flight: bool,
+}
+
+/// What `schedule_retry` did with a failed operation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RetryOutcome {
+    /// Retried once the backoff window has elapsed
+    Scheduled {
+        /// RFC 3339 time the operation becomes due
+        next_retry_at: String,
+    },
+    /// `max_attempts` reached; the operation is permanently failed
+    Exhausted,
 }
 
 /// Backoff strategy configuration
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub enum BackoffStrategy {
     /// Fixed interval between retries
     Fixed {
         /// Interval in seconds
         interval_seconds: u64,
     },
+    /// Delay grows by the same step after each failure
+    Linear {
+        /// Step in seconds
+        step_seconds: u64,
+    },
     /// Exponential backoff with jitter
     Exponential {
         /// Base interval in seconds
@@ -71,77 +105,192 @@ pub enum BackoffStrategy {
         /// Jitter factor (0.0 to 1.0)
         jitter: f64,
     },
+    /// Delay follows the Fibonacci sequence (1, 1, 2, 3, 5, ...) times the base
+    Fibonacci {
+        /// Base interval in seconds
+        base_interval: u64,
+    },
     /// Custom backoff function
-    Custom(Box<dyn Fn(u32) -> u64 + Send + Sync>),
+    Custom(Arc<dyn Fn(u32) -> u64 + Send + Sync>),
+}
+
+impl fmt::Debug for BackoffStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BackoffStrategy::Fixed { interval_seconds } => f
+                .debug_struct("Fixed")
+                .field("interval_seconds", interval_seconds)
+                .finish(),
+            BackoffStrategy::Linear { step_seconds } => f
+                .debug_struct("Linear")
+                .field("step_seconds", step_seconds)
+                .finish(),
+            BackoffStrategy::Exponential { base_interval, max_interval, jitter } => f
+                .debug_struct("Exponential")
+                .field("base_interval", base_interval)
+                .field("max_interval", max_interval)
+                .field("jitter", jitter)
+                .finish(),
+            BackoffStrategy::Fibonacci { base_interval } => f
+                .debug_struct("Fibonacci")
+                .field("base_interval", base_interval)
+                .finish(),
+            BackoffStrategy::Custom(_) => f.write_str("Custom(..)"),
+        }
+    }
+}
+
+impl Default for BackoffStrategy {
+    fn default() -> Self {
+        BackoffStrategy::Exponential {
+            base_interval: 1,
+            max_interval: 300, // 5 minutes
+            jitter: 0.1,
+        }
+    }
+}
+
+impl BackoffStrategy {
+    /// Seconds to wait after the `attempt`th failure, before any ceiling
+    fn delay_seconds(&self, attempt: u32) -> u64 {
+        let attempt = attempt.max(1);
+        match self {
+            BackoffStrategy::Fixed { interval_seconds } => *interval_seconds,
+            BackoffStrategy::Linear { step_seconds } => step_seconds.saturating_mul(attempt as u64),
+            BackoffStrategy::Exponential { base_interval, max_interval, jitter } => {
+                let factor = 2u64.checked_pow(attempt - 1).unwrap_or(u64::MAX);
+                let delay = base_interval.saturating_mul(factor).min(*max_interval);
+
+                // Add jitter
+                let jitter_amount = (delay as f64 * jitter.clamp(0.0, 1.0)) as u64;
+                if jitter_amount == 0 {
+                    delay
+                } else {
+                    delay.saturating_add((Uuid::new_v4().as_u128() % jitter_amount as u128) as u64)
+                }
+            }
+            BackoffStrategy::Fibonacci { base_interval } => {
+                let (mut current, mut next) = (1u64, 1u64);
+                for _ in 1..attempt {
+                    (current, next) = (next, current.saturating_add(next));
+                }
+                base_interval.saturating_mul(current)
+            }
+            BackoffStrategy::Custom(calc_fn) => calc_fn(attempt),
+        }
+    }
 }
 
 impl RetryManager {
-    /// Create a new retry manager
+    /// Create a retry manager with the default exponential backoff, five
+    /// attempts and a five minute ceiling
     pub fn new() -> Self {
+        Self::new_with(BackoffStrategy::default(), 5, Duration::from_secs(300))
+    }
+
+    /// Create a retry manager that gives each operation `max_attempts` tries
+    /// in total and never waits longer than `max_delay` between them
+    pub fn new_with(strategy: BackoffStrategy, max_attempts: u32, max_delay: Duration) -> Self {
         Self {
             retrying_operations: RwLock::new(HashMap::new()),
-            backoff_strategy: BackoffStrategy::Exponential {
-                base_interval: 1,
-                max_interval: 300, // 5 minutes
-                jitter: 0.1,
-            },
+            exhausted_operations: RwLock::new(HashMap::new()),
+            backoff_strategy: strategy,
+            max_attempts: max_attempts.max(1),
+            max_delay,
         }
     }
 
-    /// Schedule an operation for retry
-    pub async fn schedule_retry(&self, operation: Operation) {
+    /// Record a failed attempt and schedule the next one
+    ///
+    /// Once the operation has failed `max_attempts` times it is moved to the
+    /// permanently failed set instead.
+    pub async fn schedule_retry(&self, operation: Operation, error: String) -> RetryOutcome {
         let operation_id = operation.id();
-        let attempt = 1; // This would be tracked from the queued operation
-        let max_attempts = 5;
+        let mut operations = self.retrying_operations.write().await;
+        let attempt = operations.get(&operation_id).map_or(1, |state| state.attempt + 1);
+
+        if attempt >= self.max_attempts {
+            operations.remove(&operation_id);
+            let state = RetryState {
+                operation,
+                attempt,
+                max_attempts: self.max_attempts,
+                next_retry_at: chrono::Utc::now().to_rfc3339(),
+                last_error: error,
+                in_flight: false,
+            };
+            self.exhausted_operations.write().await.insert(operation_id, state);
+            return RetryOutcome::Exhausted;
+        }
 
         let next_retry_at = self.calculate_next_retry(attempt);
-
-        let retry_state = RetryState {
-            operation,
-            attempt,
-            max_attempts,
-            next_retry_at,
-            last_error: "Unknown error".to_string(), // Would be passed in
-        };
-
-        let mut operations = self.retrying_operations.write().await;
-        operations.insert(operation_id, retry_state);
+        operations.insert(
+            operation_id,
+            RetryState {
+                operation,
+                attempt,
+                max_attempts: self.max_attempts,
+                next_retry_at: next_retry_at.clone(),
+                last_error: error,
+                in_flight: false,
+            },
+        );
+        RetryOutcome::Scheduled { next_retry_at }
     }
 
-    /// Process pending retries
+    /// Operations whose backoff window has elapsed
+    ///
+    /// Each one is handed out once; report the result with `cancel_retry` on
+    /// success, `schedule_retry` on failure, or `release_retry` if it was
+    /// skipped.
     pub async fn process_retries(&self) -> Vec<Operation> {
         let now = chrono::Utc::now();
         let mut operations = self.retrying_operations.write().await;
 
         let mut ready_operations = Vec::new();
-
-        operations.retain(|_, state| {
-            if let Ok(retry_time) = chrono::DateTime::parse_from_rfc3339(&state.next_retry_at) {
-                if retry_time <= now {
-                    if state.attempt < state.max_attempts {
-                        ready_operations.push(state.operation.clone());
-                        // Schedule next retry
-                        state.attempt += 1;
-                        state.next_retry_at = self.calculate_next_retry(state.attempt);
-                        true // Keep in retry queue
-                    } else {
-                        false // Remove from retry queue (max attempts reached)
-                    }
-                } else {
-                    true // Keep waiting
-                }
-            } else {
-                false // Invalid timestamp, remove
+        for state in operations.values_mut() {
+            if state.in_flight {
+                continue;
+            }
+            let waiting = matches!(
+                chrono::DateTime::parse_from_rfc3339(&state.next_retry_at),
+                Ok(retry_time) if retry_time > now
+            );
+            if !waiting {
+                state.in_flight = true;
+                ready_operations.push(state.operation.clone());
             }
-        });
+        }
 
         ready_operations
     }
 
+    /// Make a handed-out operation due again without counting an attempt
+    pub async fn release_retry(&self, operation_id: &Uuid) {
+        let mut operations = self.retrying_operations.write().await;
+        if let Some(state) = operations.get_mut(operation_id) {
+            state.in_flight = false;
+        }
+    }
+
     /// Cancel retry for an operation
     pub async fn cancel_retry(&self, operation_id: &Uuid) {
         let mut operations = self.retrying_operations.write().await;
         operations.remove(operation_id);
+        drop(operations);
+        self.exhausted_operations.write().await.remove(operation_id);
+    }
+
+    /// Operations that used up their attempts
+    pub async fn permanently_failed(&self) -> Vec<RetryState> {
+        let operations = self.exhausted_operations.read().await;
+        operations.values().cloned().collect()
+    }
+
+    /// Whether an operation used up its attempts
+    pub async fn is_permanently_failed(&self, operation_id: &Uuid) -> bool {
+        let operations = self.exhausted_operations.read().await;
+        operations.contains_key(operation_id)
     }
 
     /// Get retry statistics
@@ -153,7 +302,7 @@ impl RetryManager {
         let mut max_attempts = 0;
 
         for state in operations.values() {
-            total_attempts += state.attempt;
+            total_attempts += state.attempt as usize;
             max_attempts = max_attempts.max(state.attempt);
         }
 
@@ -161,6 +310,7 @@ impl RetryManager {
             total_retrying,
             total_attempts,
             max_attempts,
+            permanently_failed: self.exhausted_operations.read().await.len(),
         }
     }
 
@@ -170,21 +320,14 @@ impl RetryManager {
         operations.len()
     }
 
+    /// Delay after the `attempt`th failure, capped at `max_delay`
+    pub fn delay_for_attempt(&self, attempt: u32) -> Duration {
+        Duration::from_secs(self.backoff_strategy.delay_seconds(attempt)).min(self.max_delay)
+    }
+
     /// Calculate next retry timestamp
     fn calculate_next_retry(&self, attempt: u32) -> String {
-        let delay_seconds = match &self.backoff_strategy {
-            BackoffStrategy::Fixed { interval_seconds } => *interval_seconds,
-            BackoffStrategy::Exponential { base_interval, max_interval, jitter } => {
-                let exponential_delay = base_interval * (2u64.pow(attempt.saturating_sub(1)));
-                let delay = exponential_delay.min(*max_interval);
-
-                // Add jitter
-                let jitter_amount = (delay as f64 * jitter) as u64;
-                delay + (rand::random::<u64>() % jitter_amount)
-            }
-            BackoffStrategy::Custom(calc_fn) => calc_fn(attempt),
-        };
-
+        let delay_seconds = self.delay_for_attempt(attempt).as_secs().min(i64::MAX as u64 / 1000);
         (chrono::Utc::now() + chrono::Duration::seconds(delay_seconds as i64)).to_rfc3339()
     }
 
@@ -203,6 +346,8 @@ pub struct RetryStats {
     pub total_attempts: usize,
     /// Maximum retry attempts for any single operation
     pub max_attempts: u32,
+    /// Operations that used up their attempts
+    pub permanently_failed: usize,
 }
 
 impl Default for RetryManager {
@@ -214,6 +359,7 @@ impl Default for RetryManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::shared::messaging::MessageType;
 
     #[tokio::test]
     async fn test_retry_manager_creation() {
@@ -229,11 +375,12 @@ mod tests {
             id: Uuid::new_v4(),
             conversation_id: Uuid::new_v4(),
             content: "Test message".to_string(),
+            message_type: MessageType::Text,
             timestamp: chrono::Utc::now().to_rfc3339(),
         };
 
         // Schedule retry
-        manager.schedule_retry(operation.clone()).await;
+        manager.schedule_retry(operation.clone(), "Network error".to_string()).await;
         assert_eq!(manager.count_retrying().await, 1);
 
         // Process retries (should not be ready yet)
@@ -265,13 +412,94 @@ mod tests {
             id: Uuid::new_v4(),
             conversation_id: Uuid::new_v4(),
             content: "Test message".to_string(),
+            message_type: MessageType::Text,
             timestamp: chrono::Utc::now().to_rfc3339(),
         };
 
-        manager.schedule_retry(operation).await;
+        manager.schedule_retry(operation, "Network error".to_string()).await;
 
         let stats = manager.get_stats().await;
         assert_eq!(stats.total_retrying, 1);
         assert!(stats.total_attempts >= 1);
     }
-}
\ No newline at end of file
+
+    fn message() -> Operation {
+        Operation::SendMessage {
+            id: Uuid::new_v4(),
+            conversation_id: Uuid::new_v4(),
+            content: "Test message".to_string(),
+            message_type: MessageType::Text,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+
+    fn delays(strategy: BackoffStrategy, max_delay: u64) -> Vec<u64> {
+        let manager = RetryManager::new_with(strategy, 10, Duration::from_secs(max_delay));
+        (1..=6).map(|attempt| manager.delay_for_attempt(attempt).as_secs()).collect()
+    }
+
+    #[test]
+    fn test_backoff_strategies() {
+        assert_eq!(delays(BackoffStrategy::Fixed { interval_seconds: 7 }, 300), vec![7; 6]);
+        assert_eq!(delays(BackoffStrategy::Linear { step_seconds: 2 }, 300), vec![2, 4, 6, 8, 10, 12]);
+        assert_eq!(
+            delays(BackoffStrategy::Exponential { base_interval: 1, max_interval: 300, jitter: 0.0 }, 300),
+            vec![1, 2, 4, 8, 16, 32]
+        );
+        assert_eq!(
+            delays(BackoffStrategy::Fibonacci { base_interval: 3 }, 300),
+            vec![3, 3, 6, 9, 15, 24]
+        );
+
+        // The manager's ceiling applies on top of the strategy's own
+        assert_eq!(
+            delays(BackoffStrategy::Exponential { base_interval: 1, max_interval: 300, jitter: 0.0 }, 10),
+            vec![1, 2, 4, 8, 10, 10]
+        );
+        assert_eq!(delays(BackoffStrategy::Custom(Arc::new(|attempt: u32| 100 * attempt as u64)), 250)[2], 250);
+    }
+
+    #[tokio::test]
+    async fn test_process_retries_waits_for_window() {
+        let waiting = RetryManager::new_with(BackoffStrategy::Fixed { interval_seconds: 60 }, 5, Duration::from_secs(300));
+        waiting.schedule_retry(message(), "Timed out".to_string()).await;
+        assert!(waiting.process_retries().await.is_empty());
+
+        let due = RetryManager::new_with(BackoffStrategy::Fixed { interval_seconds: 0 }, 5, Duration::from_secs(300));
+        let operation = message();
+        due.schedule_retry(operation.clone(), "Timed out".to_string()).await;
+
+        let ready = due.process_retries().await;
+        assert_eq!(ready.len(), 1);
+        assert_eq!(ready[0].id(), operation.id());
+
+        // Handed out once until the result is reported
+        assert!(due.process_retries().await.is_empty());
+        due.release_retry(&operation.id()).await;
+        assert_eq!(due.process_retries().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_exhausted_operations_are_permanently_failed() {
+        let manager = RetryManager::new_with(BackoffStrategy::Fixed { interval_seconds: 0 }, 3, Duration::from_secs(300));
+        let operation = message();
+
+        for attempt in 1..=2 {
+            let outcome = manager.schedule_retry(operation.clone(), format!("Error {}", attempt)).await;
+            assert!(matches!(outcome, RetryOutcome::Scheduled { .. }));
+            assert_eq!(manager.process_retries().await.len(), 1);
+        }
+        let outcome = manager.schedule_retry(operation.clone(), "Error 3".to_string()).await;
+        assert_eq!(outcome, RetryOutcome::Exhausted);
+
+        assert_eq!(manager.count_retrying().await, 0);
+        assert!(manager.process_retries().await.is_empty());
+        assert!(manager.is_permanently_failed(&operation.id()).await);
+
+        let failed = manager.permanently_failed().await;
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].attempt, 3);
+        assert_eq!(failed[0].last_error, "Error 3");
+        assert_eq!(manager.get_stats().await.permanently_failed, 1);
+    }
+}
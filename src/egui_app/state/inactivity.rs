@@ -0,0 +1,65 @@
+//! Inactivity tracking for auto-logout.
+//!
+//! On shared machines the session can be ended after a period without any
+//! user interaction (see [`Config::inactivity_timeout`]). The egui update
+//! loop reports interaction each frame; [`InactivityTimer::expired`] tells
+//! it when the idle period has run out.
+//!
+//! [`Config::inactivity_timeout`]: crate::egui_app::Config::inactivity_timeout
+
+use std::time::{Duration, Instant};
+
+/// Shown on the login screen after an automatic logout
+pub const SESSION_EXPIRED_MESSAGE: &str = "Session expired due to inactivity. Please log in again.";
+
+/// Time of the last user interaction.
+#[derive(Debug, Clone, Copy)]
+pub struct InactivityTimer {
+    last_activity: Instant,
+}
+
+impl Default for InactivityTimer {
+    fn default() -> Self {
+        Self::new(Instant::now())
+    }
+}
+
+impl InactivityTimer {
+    pub fn new(now: Instant) -> Self {
+        Self { last_activity: now }
+    }
+
+    /// Record user interaction at `now`
+    pub fn record_activity(&mut self, now: Instant) {
+        self.last_activity = now;
+    }
+
+    /// Time since the last interaction
+    pub fn idle_for(&self, now: Instant) -> Duration {
+        now.saturating_duration_since(self.last_activity)
+    }
+
+    /// Whether the idle time has reached `timeout`
+    pub fn expired(&self, timeout: Duration, now: Instant) -> bool {
+        self.idle_for(now) >= timeout
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_activity_resets_idle_time() {
+        let start = Instant::now();
+        let timeout = Duration::from_secs(300);
+        let mut timer = InactivityTimer::new(start);
+
+        assert!(!timer.expired(timeout, start + Duration::from_secs(299)));
+        assert!(timer.expired(timeout, start + timeout));
+
+        timer.record_activity(start + Duration::from_secs(200));
+        assert!(!timer.expired(timeout, start + timeout));
+        assert_eq!(timer.idle_for(start + timeout), Duration::from_secs(100));
+    }
+}
@@ -1,9 +1,27 @@
 use std::sync::mpsc::{channel, Receiver};
+use std::time::{Duration, Instant};
 
 use crate::egui_app::{
-    login, signup, AppView, AuthState, Config, DebugLogger, DebugCategory,
+    login, refresh_token, signup, AppView, AuthState, Config, DebugLogger, DebugCategory,
 };
+use crate::egui_app::auth::token_needs_refresh;
+use crate::egui_app::app_lock::AppLock;
+use crate::egui_app::diagnostics::{BundleExport, DiagnosticBundle};
+use crate::egui_app::local_db::LocalDatabase;
+use crate::egui_app::deep_link::{DeepLink, NavigationResult};
 use crate::egui_app::messaging::MessagingState;
+use crate::egui_app::offline::QueueInspector;
+use crate::egui_app::sync::{SyncCommand, SyncConfig, SyncWorker};
+use crate::shared::messaging::PresenceStatus;
+
+pub mod inactivity;
+pub mod onboarding;
+
+pub use inactivity::{InactivityTimer, SESSION_EXPIRED_MESSAGE};
+pub use onboarding::{Onboarding, OnboardingState, OnboardingStep};
+
+/// Minimum time between token refresh attempts
+const REFRESH_RETRY_INTERVAL: Duration = Duration::from_secs(10 * 60);
 
 /// Central application state shared across egui views.
 pub struct AppState {
@@ -16,6 +34,10 @@ pub struct AppState {
     pub confirm_password_input: String,
     pub is_signup_mode: bool,
     pub auth_result: Option<Receiver<Result<(String, crate::egui_app::UserInfo), String>>>,
+    /// In-flight token refresh
+    pub refresh_result: Option<Receiver<Result<String, String>>>,
+    /// When a refresh was last started
+    pub last_refresh_attempt: Option<Instant>,
     pub debug_logger: DebugLogger,
     pub debug_view_expanded: bool,
     pub debug_filter_category: Option<DebugCategory>,
@@ -26,6 +48,34 @@ pub struct AppState {
     pub is_online: bool,
     pub last_sync_time: Option<String>,
     pub pending_sync_operations: usize,
+    /// Background sync of queued operations, running while logged in
+    pub sync_worker: Option<SyncWorker>,
+
+    /// First-run onboarding progress
+    pub onboarding: Onboarding,
+
+    /// Last user interaction, for auto-logout
+    pub inactivity: InactivityTimer,
+
+    /// PIN lock over the UI
+    pub app_lock: AppLock,
+    /// PIN typed on the lock screen or in the PIN setup menu
+    pub pin_input: String,
+    /// Last lock/unlock error shown to the user
+    pub lock_error: Option<String>,
+
+    /// "Create diagnostic bundle" form
+    pub diagnostics: BundleExport,
+
+    /// Status text typed in the top bar's status picker
+    pub status_text_input: String,
+    /// Last status error shown to the user
+    pub status_error: Option<String>,
+
+    /// Advanced operation queue window is open
+    pub show_queue_panel: bool,
+    /// Persisted operation queue shown in that window
+    pub queue_inspector: QueueInspector,
 }
 
 impl AppState {
@@ -33,8 +83,11 @@ impl AppState {
         let debug_logger = DebugLogger::new(1000);
         debug_logger.info(DebugCategory::Other, "AppState initialized");
 
+        let config = Config::new();
+        let onboarding = Onboarding::new(&config);
+
         Self {
-            config: Config::new(),
+            config,
             auth_state: AuthState::new(),
             current_view: AppView::Auth,
             username_input: String::new(),
@@ -43,6 +96,8 @@ impl AppState {
             confirm_password_input: String::new(),
             is_signup_mode: false,
             auth_result: None,
+            refresh_result: None,
+            last_refresh_attempt: None,
             debug_logger,
             debug_view_expanded: false,
             debug_filter_category: None,
@@ -50,10 +105,23 @@ impl AppState {
             is_online: true, // Assume online by default
             last_sync_time: None,
             pending_sync_operations: 0,
+            sync_worker: None,
+            onboarding,
+            inactivity: InactivityTimer::default(),
+            app_lock: AppLock::load(),
+            pin_input: String::new(),
+            lock_error: None,
+            diagnostics: BundleExport::default(),
+            status_text_input: String::new(),
+            status_error: None,
+            show_queue_panel: false,
+            queue_inspector: QueueInspector::default(),
         }
     }
 
     pub fn check_auth_result(&mut self) {
+        self.check_token_refresh(chrono::Utc::now(), Instant::now());
+
         if let Some(ref rx) = self.auth_result {
             if let Ok(result) = rx.try_recv() {
                 self.auth_result = None;
@@ -63,13 +131,22 @@ impl AppState {
                     Ok((token, user)) => {
                         self.debug_logger.info(DebugCategory::Auth, format!("✓ Authentication successful: {}", user.email));
                         self.config.set_token(Some(token));
+                        self.sync_worker = Some(SyncWorker::spawn(SyncConfig::default(), self.config.clone()));
                         self.auth_state.authenticated = true;
                         self.auth_state.user = Some(user);
                         self.auth_state.error = None;
-                        self.current_view = AppView::Landing;
+                        self.inactivity.record_activity(Instant::now());
+                        // A deep link opened before login goes straight to messaging
+                        self.current_view = if self.messaging_state.pending_deep_link.is_some() {
+                            AppView::Messaging
+                        } else {
+                            AppView::Landing
+                        };
                         self.password_input.clear();
                         self.confirm_password_input.clear();
                         self.is_signup_mode = false;
+                        let step = self.onboarding.on_authenticated(&mut self.config);
+                        self.debug_logger.info(DebugCategory::Other, format!("Onboarding step: {:?}", step));
                     }
                     Err(e) => {
                         self.debug_logger.error(DebugCategory::Auth, format!("✗ Authentication failed: {}", e));
@@ -80,6 +157,61 @@ impl AppState {
         }
     }
 
+    /// Refresh the token in the background when it nears expiry or the
+    /// server rejected it, and apply the result once it arrives
+    ///
+    /// A rejected refresh means the session is gone, so the user is logged out
+    /// with an explanation instead of requests silently failing with 401.
+    pub fn check_token_refresh(&mut self, now: chrono::DateTime<chrono::Utc>, instant: Instant) {
+        if let Some(ref rx) = self.refresh_result {
+            if let Ok(result) = rx.try_recv() {
+                self.refresh_result = None;
+                match result {
+                    Ok(token) => {
+                        self.debug_logger.info(DebugCategory::Auth, "✓ Token refreshed");
+                        self.messaging_state.token_rejected = false;
+                        self.config.set_token(Some(token.clone()));
+                        if let Some(ref worker) = self.sync_worker {
+                            worker.send(SyncCommand::SetToken(Some(token.clone())));
+                        }
+                        if let Some(ref mut client) = self.messaging_state.message_sync_client {
+                            client.set_token(Some(token));
+                        }
+                    }
+                    Err(e) if e.contains("401") => {
+                        self.debug_logger.warn(DebugCategory::Auth, format!("Token refresh rejected: {}", e));
+                        self.logout();
+                        self.auth_state.set_error("Your session has expired. Please log in again.".to_string());
+                    }
+                    Err(e) => {
+                        self.debug_logger.warn(DebugCategory::Auth, format!("Token refresh failed, will retry: {}", e));
+                    }
+                }
+            }
+            return;
+        }
+
+        // A rejection (the subscription got a 401) can't wait for the retry interval
+        let rejected = std::mem::take(&mut self.messaging_state.token_rejected);
+        let due = self.auth_state.authenticated
+            && (rejected
+                || (self.config.get_token().is_some_and(|token| token_needs_refresh(token, now))
+                    && self
+                        .last_refresh_attempt
+                        .is_none_or(|last| instant.saturating_duration_since(last) >= REFRESH_RETRY_INTERVAL)));
+        if !due {
+            return;
+        }
+
+        self.last_refresh_attempt = Some(instant);
+        let config = self.config.clone();
+        let (tx, rx) = channel();
+        std::thread::spawn(move || {
+            let _ = tx.send(refresh_token(&config).map(|auth| auth.token));
+        });
+        self.refresh_result = Some(rx);
+    }
+
     pub fn handle_login(&mut self) {
         if self.username_input.is_empty() || self.password_input.is_empty() {
             self.auth_state
@@ -155,6 +287,225 @@ impl AppState {
         self.password_input.clear();
         self.confirm_password_input.clear();
         self.messaging_state = MessagingState::new();
+        self.sync_worker = None;
+        self.pending_sync_operations = 0;
+        self.refresh_result = None;
+        self.last_refresh_attempt = None;
+        self.app_lock.reset();
+        self.pin_input.clear();
+        self.lock_error = None;
+    }
+
+    /// Hide content behind the PIN, keeping the session and sync running
+    pub fn lock_app(&mut self) {
+        if !self.auth_state.authenticated {
+            return;
+        }
+        self.pin_input.clear();
+        self.lock_error = self.app_lock.lock().err().map(|e| e.to_string());
+    }
+
+    /// Try to unlock with the entered PIN
+    pub fn unlock_app(&mut self) -> bool {
+        let result = self.app_lock.unlock(&self.pin_input);
+        self.pin_input.clear();
+        match result {
+            Ok(()) => {
+                self.lock_error = None;
+                true
+            }
+            Err(e) => {
+                self.debug_logger.warn(DebugCategory::Auth, format!("Unlock failed: {}", e));
+                self.lock_error = Some(e.to_string());
+                false
+            }
+        }
+    }
+
+    /// Save the entered PIN
+    pub fn set_lock_pin(&mut self) {
+        self.lock_error = self.app_lock.set_pin(&self.pin_input).err().map(|e| e.to_string());
+        self.pin_input.clear();
+    }
+
+    /// Track user interaction and log out once the inactivity timeout passes
+    ///
+    /// `active` is whether the user interacted this frame. Logging out drops
+    /// the messaging state, which stops its background sync. Returns whether
+    /// the session expired.
+    pub fn check_inactivity(&mut self, active: bool, now: Instant) -> bool {
+        if active {
+            self.inactivity.record_activity(now);
+            return false;
+        }
+        let Some(timeout) = self.config.inactivity_timeout() else {
+            return false;
+        };
+        if !self.auth_state.authenticated || !self.inactivity.expired(timeout, now) {
+            return false;
+        }
+
+        self.debug_logger.info(
+            DebugCategory::Auth,
+            format!("Logging out after {}s of inactivity", self.inactivity.idle_for(now).as_secs()),
+        );
+        self.logout();
+        self.auth_state.set_error(SESSION_EXPIRED_MESSAGE.to_string());
+        true
+    }
+
+    /// Hand queued sends to the sync service, keep its view of connectivity
+    /// current and pick up its status for the top bar
+    pub fn check_sync(&mut self) {
+        self.is_online = self.messaging_state.is_online;
+        let Some(ref mut worker) = self.sync_worker else {
+            return;
+        };
+        worker.set_online(self.is_online);
+        for operation in std::mem::take(&mut self.messaging_state.queued_operations) {
+            if !worker.send(SyncCommand::Enqueue(operation.clone())) {
+                // The service never started; keep it rather than drop it
+                self.messaging_state.queued_operations.push(operation);
+            }
+        }
+        if worker.poll() {
+            if let Some(ref status) = worker.state {
+                self.pending_sync_operations = status.pending_operations;
+                self.last_sync_time = status.last_sync.clone();
+            }
+        }
+    }
+
+    /// Write a diagnostic bundle to the chosen path in the background
+    pub fn create_diagnostic_bundle(&mut self) {
+        if self.diagnostics.result.is_some() {
+            return;
+        }
+        let path = std::path::PathBuf::from(self.diagnostics.path.trim());
+        if path.as_os_str().is_empty() {
+            self.diagnostics.status = Some("Choose where to save the bundle".to_string());
+            return;
+        }
+
+        let messaging = &self.messaging_state;
+        let sync = format!(
+            "online: {}
+subscription: {:?}
+last sync: {}
+pending operations: {}",
+            self.is_online,
+            messaging.subscription_status,
+            messaging
+                .last_sync_time
+                .map_or("never".to_string(), |t| format!("{}s ago", t.elapsed().as_secs())),
+            self.pending_sync_operations,
+        );
+        let bundle = DiagnosticBundle::new(&self.config, self.diagnostics.include_sensitive)
+            .debug_logs(&self.debug_logger.get_entries())
+            .section("sync.txt", &sync)
+            .config(&self.config)
+            .connection_log(&messaging.subscription_log);
+
+        let (tx, rx) = channel();
+        std::thread::spawn(move || {
+            let stats = tokio::runtime::Runtime::new()
+                .map_err(|e| e.to_string())
+                .and_then(|rt| {
+                    rt.block_on(async {
+                        let db = LocalDatabase::new().await.map_err(|e| e.to_string())?;
+                        db.get_stats().await.map_err(|e| e.to_string())
+                    })
+                });
+            let bundle = match stats {
+                Ok(stats) => bundle.database_stats(&stats),
+                Err(e) => bundle.section("database_stats.txt", &format!("unavailable: {}", e)),
+            };
+            let result = bundle
+                .write_zip(&path)
+                .map(|()| path)
+                .map_err(|e| format!("Failed to write bundle: {}", e));
+            let _ = tx.send(result);
+        });
+        self.diagnostics.status = Some("Creating bundle…".to_string());
+        self.diagnostics.result = Some(rx);
+    }
+
+    /// Pick up a finished diagnostic bundle export
+    pub fn check_diagnostics_result(&mut self) {
+        let Some(ref rx) = self.diagnostics.result else {
+            return;
+        };
+        if let Ok(result) = rx.try_recv() {
+            self.diagnostics.result = None;
+            self.diagnostics.status = Some(match result {
+                Ok(path) => {
+                    self.debug_logger.info(DebugCategory::Other, format!("Diagnostic bundle saved to {}", path.display()));
+                    format!("Saved to {}", path.display())
+                }
+                Err(e) => {
+                    self.debug_logger.error(DebugCategory::Other, e.clone());
+                    e
+                }
+            });
+        }
+    }
+
+    /// Advance the onboarding flow (import finished, tips dismissed, ...)
+    pub fn advance_onboarding(&mut self) -> OnboardingStep {
+        let step = self.onboarding.advance(&mut self.config);
+        self.debug_logger.info(DebugCategory::Other, format!("Onboarding step: {:?}", step));
+        step
+    }
+
+    /// Apply onboarding progress saved by an earlier launch
+    pub fn restore_onboarding(&mut self, saved: &OnboardingState) {
+        self.config.set_onboarding_completed(saved.completed);
+        self.onboarding = Onboarding::new(&self.config);
+    }
+
+    /// Onboarding progress to save on exit
+    pub fn onboarding_state(&self) -> OnboardingState {
+        OnboardingState { completed: self.config.onboarding_completed() }
+    }
+
+    /// Move on to the tips once the first contacts and conversations load
+    pub fn check_onboarding(&mut self) {
+        if self.onboarding.step() == OnboardingStep::ImportServerData
+            && self.auth_state.authenticated
+            && self.messaging_state.initial_data_loaded()
+        {
+            self.advance_onboarding();
+        }
+    }
+
+    /// Open a deep link, switching to the messaging view
+    ///
+    /// Links opened before login are kept until authentication succeeds.
+    pub fn open_deep_link(&mut self, link: DeepLink) -> NavigationResult {
+        self.debug_logger.info(DebugCategory::Other, format!("Opening deep link: {}", link));
+
+        if !self.auth_state.authenticated {
+            self.messaging_state.pending_deep_link = Some(link);
+            return NavigationResult::Deferred;
+        }
+
+        self.current_view = AppView::Messaging;
+        let result = self.messaging_state.navigate_to_message(link);
+        if matches!(result, NavigationResult::NotParticipant | NavigationResult::MessageUnavailable) {
+            self.debug_logger.warn(DebugCategory::Other, format!("Deep link {} not opened: {:?}", link, result));
+        }
+        result
+    }
+
+    /// Set our status and status text; contacts see it on their next sync
+    pub fn set_status(&mut self, status: PresenceStatus, status_text: Option<String>) {
+        match self.messaging_state.set_own_status(status, status_text) {
+            Ok(()) => {
+                self.status_error = None;
+                self.debug_logger.info(DebugCategory::Other, format!("Status set to {}", status.label()));
+            }
+            Err(e) => self.status_error = Some(e),
+        }
     }
 
     pub fn toggle_auth_mode(&mut self) {
@@ -171,3 +522,80 @@ impl Default for AppState {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn logged_in_state(timeout: Option<Duration>, now: Instant) -> AppState {
+        let mut state = AppState::new();
+        state.config.set_token(Some("token".to_string()));
+        state.config.set_inactivity_timeout(timeout);
+        state.auth_state.authenticated = true;
+        state.current_view = AppView::Messaging;
+        state.inactivity = InactivityTimer::new(now);
+        state
+    }
+
+    #[test]
+    fn test_inactivity_past_timeout_logs_out() {
+        let start = Instant::now();
+        let timeout = Duration::from_secs(15 * 60);
+        let mut state = logged_in_state(Some(timeout), start);
+
+        // Interaction keeps the session alive
+        assert!(!state.check_inactivity(true, start + timeout));
+        assert!(!state.check_inactivity(false, start + timeout + Duration::from_secs(60)));
+        assert!(state.auth_state.authenticated);
+
+        assert!(state.check_inactivity(false, start + timeout * 2));
+        assert!(!state.auth_state.authenticated);
+        assert!(state.config.get_token().is_none());
+        assert_eq!(state.current_view, AppView::Auth);
+        assert_eq!(state.auth_state.error.as_deref(), Some(SESSION_EXPIRED_MESSAGE));
+    }
+
+    #[test]
+    fn test_rejected_token_is_refreshed_right_away() {
+        let start = Instant::now();
+        let mut state = logged_in_state(None, start);
+        state.last_refresh_attempt = Some(start);
+
+        // A fresh token inside the retry interval: nothing to do
+        state.check_token_refresh(chrono::Utc::now(), start);
+        assert!(state.refresh_result.is_none());
+
+        state.messaging_state.token_rejected = true;
+        state.check_token_refresh(chrono::Utc::now(), start);
+        assert!(state.refresh_result.is_some());
+        assert!(!state.messaging_state.token_rejected);
+    }
+
+    #[test]
+    fn test_onboarding_advances_after_import() {
+        let mut state = logged_in_state(None, Instant::now());
+        state.onboarding.on_authenticated(&mut state.config);
+        assert_eq!(state.onboarding.step(), OnboardingStep::ImportServerData);
+
+        // Nothing loaded yet
+        state.check_onboarding();
+        assert_eq!(state.onboarding.step(), OnboardingStep::ImportServerData);
+
+        state.messaging_state.initialized = true;
+        state.check_onboarding();
+        assert_eq!(state.onboarding.step(), OnboardingStep::ShowTips);
+
+        // Dismissing the tips finishes onboarding for good
+        state.advance_onboarding();
+        assert!(state.onboarding_state().completed);
+    }
+
+    #[test]
+    fn test_no_timeout_never_logs_out() {
+        let start = Instant::now();
+        let mut state = logged_in_state(None, start);
+
+        assert!(!state.check_inactivity(false, start + Duration::from_secs(24 * 60 * 60)));
+        assert!(state.auth_state.authenticated);
+    }
+}
@@ -0,0 +1,184 @@
+//! First-run onboarding state machine.
+//!
+//! Sequences the initial experience for a new install:
+//! authenticate → import server data → show onboarding tips.
+//! Returning users (onboarding flag set in [`Config`]) skip straight to
+//! [`OnboardingStep::Completed`]. The flag is kept between launches in
+//! [`OnboardingState`] (`<config>/xfmail/onboarding.json`), loaded at startup
+//! and saved on exit like the window state.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::egui_app::Config;
+
+/// A single step in the first-run flow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnboardingStep {
+    /// No token yet - user must log in or sign up
+    Authenticate,
+    /// Pull contacts, conversations and friend requests from the server
+    ImportServerData,
+    /// Show tips (e.g. how to add friends) before entering the app
+    ShowTips,
+    /// Onboarding finished or skipped
+    Completed,
+}
+
+impl OnboardingStep {
+    /// Determine the starting step for the given configuration.
+    pub fn initial(config: &Config) -> Self {
+        if config.onboarding_completed() {
+            OnboardingStep::Completed
+        } else if config.get_token().is_none() {
+            OnboardingStep::Authenticate
+        } else {
+            OnboardingStep::ImportServerData
+        }
+    }
+
+    /// The step that follows this one.
+    pub fn next(self) -> Self {
+        match self {
+            OnboardingStep::Authenticate => OnboardingStep::ImportServerData,
+            OnboardingStep::ImportServerData => OnboardingStep::ShowTips,
+            OnboardingStep::ShowTips | OnboardingStep::Completed => OnboardingStep::Completed,
+        }
+    }
+
+    pub fn is_completed(self) -> bool {
+        self == OnboardingStep::Completed
+    }
+}
+
+/// Tracks progress through the onboarding flow and persists completion in [`Config`].
+#[derive(Debug, Clone)]
+pub struct Onboarding {
+    step: OnboardingStep,
+}
+
+impl Onboarding {
+    pub fn new(config: &Config) -> Self {
+        Self { step: OnboardingStep::initial(config) }
+    }
+
+    pub fn step(&self) -> OnboardingStep {
+        self.step
+    }
+
+    /// Move to the next step, marking the config once the flow completes.
+    pub fn advance(&mut self, config: &mut Config) -> OnboardingStep {
+        self.step = self.step.next();
+        if self.step.is_completed() {
+            config.set_onboarding_completed(true);
+        }
+        self.step
+    }
+
+    /// Advance past `Authenticate` once a token has been obtained.
+    pub fn on_authenticated(&mut self, config: &mut Config) -> OnboardingStep {
+        if self.step == OnboardingStep::Authenticate {
+            self.advance(config);
+        }
+        self.step
+    }
+
+    /// Skip the remaining steps (e.g. user dismissed the tips).
+    pub fn skip(&mut self, config: &mut Config) {
+        self.step = OnboardingStep::Completed;
+        config.set_onboarding_completed(true);
+    }
+}
+
+/// Persisted onboarding progress
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OnboardingState {
+    /// The flow was finished or skipped on this install
+    pub completed: bool,
+}
+
+impl OnboardingState {
+    /// Default location of the state file
+    pub fn default_path() -> PathBuf {
+        let mut path = dirs::config_dir().unwrap_or_else(std::env::temp_dir);
+        path.push("xfmail");
+        path.push("onboarding.json");
+        path
+    }
+
+    /// Load from the default location, falling back to defaults
+    pub fn load() -> Self {
+        Self::load_from(&Self::default_path())
+    }
+
+    /// Load from `path`; a missing or unreadable file gives the defaults
+    pub fn load_from(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    /// Save to the default location
+    pub fn save(&self) -> std::io::Result<()> {
+        self.save_to(&Self::default_path())
+    }
+
+    /// Save to `path`, creating parent directories
+    pub fn save_to(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        std::fs::write(path, json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fresh_config_walks_all_steps() {
+        let mut config = Config::new();
+        let mut onboarding = Onboarding::new(&config);
+        assert_eq!(onboarding.step(), OnboardingStep::Authenticate);
+
+        config.set_token(Some("token".to_string()));
+        assert_eq!(onboarding.on_authenticated(&mut config), OnboardingStep::ImportServerData);
+        assert_eq!(onboarding.advance(&mut config), OnboardingStep::ShowTips);
+        assert!(!config.onboarding_completed());
+        assert_eq!(onboarding.advance(&mut config), OnboardingStep::Completed);
+        assert!(config.onboarding_completed());
+    }
+
+    #[test]
+    fn test_returning_user_skips_onboarding() {
+        let mut config = Config::new();
+        config.set_onboarding_completed(true);
+        let mut onboarding = Onboarding::new(&config);
+        assert_eq!(onboarding.step(), OnboardingStep::Completed);
+        assert_eq!(onboarding.on_authenticated(&mut config), OnboardingStep::Completed);
+    }
+
+    #[test]
+    fn test_existing_token_starts_at_import() {
+        let mut config = Config::new();
+        config.set_token(Some("token".to_string()));
+        assert_eq!(OnboardingStep::initial(&config), OnboardingStep::ImportServerData);
+    }
+
+    #[test]
+    fn test_completion_round_trips() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("onboarding.json");
+        assert_eq!(OnboardingState::load_from(&path), OnboardingState::default());
+
+        OnboardingState { completed: true }.save_to(&path).unwrap();
+        let mut config = Config::new();
+        config.set_onboarding_completed(OnboardingState::load_from(&path).completed);
+        assert_eq!(Onboarding::new(&config).step(), OnboardingStep::Completed);
+    }
+}
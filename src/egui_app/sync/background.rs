@@ -1,268 +0,0 @@
-//! # Background Sync Tasks
-//!
-//! Implements background synchronization workers that continuously sync
-//! local changes with remote servers while respecting system resources.
-//!
-//! ## Features
-//!
-//! - **Continuous Sync**: Always-on background synchronization
-//! - **Resource Aware**: Respects battery, network, and CPU constraints
-//! - **Priority Queues**: Handles urgent operations first
-//! - **Error Recovery**: Robust retry logic for failed operations
-//! - **Progress Tracking**: Real-time sync status updates
-//!
-//! ## Usage
-//!
-//! ```rust,no_run
-//! use xfmail::egui_app::sync::background::BackgroundSync;
-//!
-//! let mut bg_sync = BackgroundSync::new().await?;
-//! bg_sync.start().await?;
-//!
-//! // Monitor progress
-//! let progress = bg_sync.get_progress().await;
-//! ```
-//!
-//! ## Architecture
-//!
-//! The background sync system consists of:
-//! - **Sync Workers**: Dedicated threads for sync operations
-//! - **Queue Manager**: Prioritizes and schedules operations
-//! - **Resource Monitor**: Tracks system resource usage
-//! - **Error Handler**: Manages failed operations and retries
-
-use crate::egui_app::offline::queue::{OperationQueue, Operation, OperationStatus};
-use crate::egui_app::offline::retry::RetryManager;
-use crate::egui_app::config::Config;
-use std::sync::Arc;
-use tokio::sync::RwLock;
-
-/// Background synchronization manager
-#[derive(Debug)]
-pub struct BackgroundSync {
-    /// Operation queue
-    operation_queue: Arc<OperationQueue>,
-    /// Retry manager
-    retry_manager: Arc<RetryManager>,
-    /// Configuration
-    config: Config,
-    /// Sync workers
-    workers: Vec<tokio::task::JoinHandle<()>>,
-    /// Current sync progress
-    progress: Arc<RwLock<SyncProgress>>,
-    /// Whether sync is active
-    is_active: Arc<RwLock<bool>>,
-}
-
-/// Synchronization progress tracking
-#[derive(Debug, Clone)]
-pub struct SyncProgress {
-    /// Total operations to sync
-    pub total_operations: usize,
-    /// Completed operations
-    pub completed_operations: usize,
-    /// Failed operations
-    pub failed_operations: usize,
-    /// Current operation being processed
-    pub current_operation: Option<String>,
-    /// Estimated time remaining (seconds)
-    pub estimated_time_remaining: Option<u64>,
-}
-
-impl BackgroundSync {
-    /// Create a new background sync manager
-    pub async fn new() -> Result<Self, String> {
-        let operation_queue = Arc::new(OperationQueue::new());
-        let retry_manager = Arc::new(RetryManager::new());
-        let config = Config::new();
-
-        Ok(Self {
-            operation_queue,
-            retry_manager,
-            config,
-            workers: Vec::new(),
-            progress: Arc::new(RwLock::new(SyncProgress {
-                total_operations: 0,
-                completed_operations: 0,
-                failed_operations: 0,
-                current_operation: None,
-                estimated_time_remaining: None,
-            })),
-            is_active: Arc::new(RwLock::new(false)),
-        })
-    }
-
-    /// Start background synchronization
-    pub async fn start(&mut self) -> Result<(), String> {
-        if *self.is_active.read().await {
-            return Err("Background sync is already running".to_string());
-        }
-
-        *self.is_active.write().await = true;
-
-        // Start worker tasks
-        for i in 0..self.config.max_concurrent_sync_ops() {
-            let operation_queue = Arc::clone(&self.operation_queue);
-            let retry_manager = Arc::clone(&self.retry_manager);
-            let progress = Arc::clone(&self.progress);
-            let is_active = Arc::clone(&self.is_active);
-
-            let handle = tokio::spawn(async move {
-                Self::sync_worker(i, operation_queue, retry_manager, progress, is_active).await;
-            });
-
-            self.workers.push(handle);
-        }
-
-        Ok(())
-    }
-
-    /// Stop background synchronization
-    pub async fn stop(&mut self) -> Result<(), String> {
-        *self.is_active.write().await = false;
-
-        // Wait for workers to finish
-        for handle in self.workers.drain(..) {
-            let _ = handle.await;
-        }
-
-        Ok(())
-    }
-
-    /// Get current sync progress
-    pub async fn get_progress(&self) -> SyncProgress {
-        self.progress.read().await.clone()
-    }
-
-    /// Force immediate sync of all pending operations
-    pub async fn force_sync(&self) -> Result<(), String> {
-        let pending_ops = self.operation_queue.get_pending_operations().await;
-
-        for operation in pending_ops {
-            match self.execute_operation(&operation).await {
-                Ok(_) => {
-                    self.operation_queue.complete_operation(&operation.id()).await;
-                }
-                Err(e) => {
-                    self.operation_queue.fail_operation(&operation.id(), e.clone()).await;
-                    self.retry_manager.schedule_retry(operation).await;
-                }
-            }
-        }
-
-        Ok(())
-    }
-
-    /// Background sync worker
-    async fn sync_worker(
-        worker_id: usize,
-        operation_queue: Arc<OperationQueue>,
-        retry_manager: Arc<RetryManager>,
-        progress: Arc<RwLock<SyncProgress>>,
-        is_active: Arc<RwLock<bool>>,
-    ) {
-        tracing::info!("Starting sync worker {}", worker_id);
-
-        while *is_active.read().await {
-            // Get next operation to process
-            let operation = match operation_queue.get_next_operation().await {
-                Some(op) => op,
-                None => {
-                    // No operations available, wait before checking again
-                    tokio::time::sleep(std::time::Duration::from_secs(1)).await;
-                    continue;
-                }
-            };
-
-            // Update progress
-            {
-                let mut prog = progress.write().await;
-                prog.current_operation = Some(format!("Processing {}", operation.id()));
-            }
-
-            // Execute operation
-            match Self::execute_operation_static(&operation).await {
-                Ok(_) => {
-                    operation_queue.complete_operation(&operation.id()).await;
-
-                    let mut prog = progress.write().await;
-                    prog.completed_operations += 1;
-                }
-                Err(e) => {
-                    operation_queue.fail_operation(&operation.id(), e.clone()).await;
-                    retry_manager.schedule_retry(operation).await;
-
-                    let mut prog = progress.write().await;
-                    prog.failed_operations += 1;
-                }
-            }
-
-            // Small delay to prevent overwhelming the system
-            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
-        }
-
-        tracing::info!("Stopping sync worker {}", worker_id);
-    }
-
-    /// Execute a single operation
-    async fn execute_operation(&self, operation: &Operation) -> Result<(), String> {
-        Self::execute_operation_static(operation).await
-    }
-
-    /// Static version of execute_operation for use in workers
-    async fn execute_operation_static(operation: &Operation) -> Result<(), String> {
-        // Simulate network operation
-        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
-
-        // For demo purposes, randomly succeed/fail
-        if rand::random::<f32>() > 0.1 {
-            Ok(())
-        } else {
-            Err("Network error".to_string())
-        }
-    }
-}
-
-impl Drop for BackgroundSync {
-    fn drop(&mut self) {
-        // Stop all workers when dropped
-        let is_active = Arc::clone(&self.is_active);
-        tokio::spawn(async move {
-            *is_active.write().await = false;
-        });
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[tokio::test]
-    async fn test_background_sync_creation() {
-        let bg_sync = BackgroundSync::new().await;
-        assert!(bg_sync.is_ok());
-    }
-
-    #[tokio::test]
-    async fn test_background_sync_start_stop() {
-        let mut bg_sync = BackgroundSync::new().await.unwrap();
-
-        // Start sync
-        assert!(bg_sync.start().await.is_ok());
-
-        // Get initial progress
-        let progress = bg_sync.get_progress().await;
-        assert_eq!(progress.completed_operations, 0);
-
-        // Stop sync
-        assert!(bg_sync.stop().await.is_ok());
-    }
-
-    #[tokio::test]
-    async fn test_force_sync() {
-        let bg_sync = BackgroundSync::new().await.unwrap();
-
-        // Force sync (should succeed even with no operations)
-        assert!(bg_sync.force_sync().await.is_ok());
-    }
-}
\ No newline at end of file
@@ -0,0 +1,252 @@
+//! # Operation Executor
+//!
+//! Sends queued offline operations to the backend.
+//!
+//! ## Endpoints
+//!
+//! - **SendMessage**: `PUT /sync/conversations/{id}/messages/{message_id}` (Braid),
+//!   with the conversation's last known version as `Parents`
+//! - **SendFriendRequest** / **AddContact**: `POST /api/friends/request` by user ID
+//!   (contacts are created when the request is accepted)
+//! - **AcceptFriendRequest**: `POST /api/friends/respond`
+//!
+//! Failures come back as descriptive `Err(String)`s so the sync service's
+//! retry path can schedule another attempt.
+
+use crate::egui_app::api_client;
+use crate::egui_app::config::Config;
+use crate::egui_app::offline::queue::Operation;
+use crate::shared::messaging::{
+    MessageType, RespondFriendRequestRequest, RespondFriendRequestResponse, SendFriendRequestRequest,
+    SendFriendRequestResponse,
+};
+use reqwest::{Client, RequestBuilder, Response};
+use std::collections::HashMap;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+/// Executes queued operations against the backend
+#[derive(Debug)]
+pub struct OperationExecutor {
+    client: Client,
+    /// Server URL and credentials; the token is swapped on refresh
+    config: std::sync::RwLock<Config>,
+    /// Latest server version per conversation, used as `Parents` for the next send
+    versions: RwLock<HashMap<Uuid, String>>,
+}
+
+impl OperationExecutor {
+    pub fn new(client: Client, config: Config) -> Self {
+        Self {
+            client,
+            config: std::sync::RwLock::new(config),
+            versions: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Use a refreshed (or cleared) auth token for later requests
+    pub fn set_token(&self, token: Option<String>) {
+        self.config.write().unwrap().set_token(token);
+    }
+
+    fn config(&self) -> Config {
+        self.config.read().unwrap().clone()
+    }
+
+    /// Latest version the server returned for a conversation
+    pub async fn latest_version(&self, conversation_id: &Uuid) -> Option<String> {
+        self.versions.read().await.get(conversation_id).cloned()
+    }
+
+    /// Send one operation to the backend
+    pub async fn execute(&self, operation: &Operation) -> Result<(), String> {
+        match operation {
+            Operation::SendMessage { id, conversation_id, content, message_type, .. } => {
+                self.send_message(*id, *conversation_id, content, message_type).await
+            }
+            Operation::SendFriendRequest { user_id, message, .. } => {
+                self.send_friend_request(*user_id, message.clone()).await
+            }
+            Operation::AddContact { user_id, .. } => self.send_friend_request(*user_id, None).await,
+            Operation::AcceptFriendRequest { request_id, .. } => {
+                self.accept_friend_request(*request_id).await
+            }
+        }
+    }
+
+    /// PUT a message via Braid and record the returned version
+    async fn send_message(
+        &self,
+        message_id: Uuid,
+        conversation_id: Uuid,
+        content: &str,
+        message_type: &MessageType,
+    ) -> Result<(), String> {
+        let url = self.config().api_url(&format!(
+            "/sync/conversations/{}/messages/{}",
+            conversation_id, message_id
+        ));
+
+        let mut request = self.authorize(self.client.put(&url))?;
+        if let Some(parent) = self.latest_version(&conversation_id).await {
+            request = request.header("Parents", format!("\"{}\"", parent));
+        }
+
+        let body = serde_json::json!({
+            "content": content,
+            "message_type": message_type.to_string()
+        });
+        let response = Self::check(request.json(&body), "send message").await?;
+
+        let version = response
+            .headers()
+            .get("Version")
+            .and_then(|h| h.to_str().ok())
+            .and_then(|v| v.split(',').next())
+            .map(|v| v.trim().trim_matches('"').to_string())
+            .filter(|v| !v.is_empty());
+
+        if let Some(version) = version {
+            self.versions.write().await.insert(conversation_id, version);
+        }
+        Ok(())
+    }
+
+    async fn send_friend_request(&self, user_id: Uuid, message: Option<String>) -> Result<(), String> {
+        let url = self.config().api_url("/api/friends/request");
+        let request = SendFriendRequestRequest {
+            to_email: String::new(),
+            to_user_id: Some(user_id),
+            message,
+        };
+
+        let response = Self::check(self.authorize(self.client.post(&url))?.json(&request), "send friend request").await?;
+        let body: SendFriendRequestResponse = response
+            .json()
+            .await
+            .map_err(|e| format!("send friend request: invalid response: {}", e))?;
+
+        match body.error {
+            // Already done on a previous attempt; nothing left to retry
+            Some(error) if error == "Already friends" || error == "Friend request already pending" => Ok(()),
+            Some(error) if !body.success => Err(format!("send friend request: {}", error)),
+            _ => Ok(()),
+        }
+    }
+
+    async fn accept_friend_request(&self, request_id: Uuid) -> Result<(), String> {
+        let url = self.config().api_url("/api/friends/respond");
+        let request = RespondFriendRequestRequest { request_id, accept: true };
+
+        let response = Self::check(self.authorize(self.client.post(&url))?.json(&request), "accept friend request").await?;
+        let body: RespondFriendRequestResponse = response
+            .json()
+            .await
+            .map_err(|e| format!("accept friend request: invalid response: {}", e))?;
+
+        if body.success {
+            Ok(())
+        } else {
+            Err(format!(
+                "accept friend request: {}",
+                body.error.unwrap_or_else(|| "rejected by server".to_string())
+            ))
+        }
+    }
+
+    /// Add JWT or dev-bypass credentials
+    fn authorize(&self, request: RequestBuilder) -> Result<RequestBuilder, String> {
+        api_client::authorize(&self.config(), request).map_err(|e| e.to_string())
+    }
+
+    /// Turn transport errors and non-2xx statuses into descriptive errors
+    async fn check(request: RequestBuilder, action: &str) -> Result<Response, String> {
+        api_client::send(request).await.map_err(|e| format!("{}: {}", action, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::config::AppConfig;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    fn config(server_url: String) -> Config {
+        let mut config = Config::with_builder(AppConfig::builder().server_url(server_url)).unwrap();
+        config.set_token(Some("token".to_string()));
+        config
+    }
+
+    fn send(conversation_id: Uuid) -> Operation {
+        Operation::SendMessage {
+            id: Uuid::new_v4(),
+            conversation_id,
+            content: "hello".to_string(),
+            message_type: MessageType::Text,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+
+    /// Serve `responses` in order, returning the raw requests received
+    async fn serve(responses: Vec<&'static str>) -> (String, tokio::task::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let handle = tokio::spawn(async move {
+            let mut requests = Vec::new();
+            for response in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = vec![0u8; 8192];
+                let n = socket.read(&mut buf).await.unwrap();
+                requests.push(String::from_utf8_lossy(&buf[..n]).to_string());
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+            requests
+        });
+        (url, handle)
+    }
+
+    #[tokio::test]
+    async fn test_send_message_chains_parents() {
+        let ok = "HTTP/1.1 200 OK\r\nVersion: \"v1\"\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+        let (url, server) = serve(vec![ok, ok]).await;
+        let executor = OperationExecutor::new(Client::new(), config(url));
+        let conversation_id = Uuid::new_v4();
+
+        executor.execute(&send(conversation_id)).await.unwrap();
+        assert_eq!(executor.latest_version(&conversation_id).await.as_deref(), Some("v1"));
+        executor.execute(&send(conversation_id)).await.unwrap();
+
+        let requests = server.await.unwrap();
+        assert!(requests[0].starts_with("PUT /sync/conversations/"));
+        assert!(!requests[0].to_lowercase().contains("parents:"));
+        assert!(requests[1].to_lowercase().contains("parents: \"v1\""));
+    }
+
+    #[tokio::test]
+    async fn test_send_message_keeps_its_type() {
+        let ok = "HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+        let (url, server) = serve(vec![ok]).await;
+        let executor = OperationExecutor::new(Client::new(), config(url));
+
+        let mut action = send(Uuid::new_v4());
+        if let Operation::SendMessage { message_type, .. } = &mut action {
+            *message_type = MessageType::Action;
+        }
+        executor.execute(&action).await.unwrap();
+
+        let requests = server.await.unwrap();
+        assert!(requests[0].contains("\"message_type\":\"action\""));
+    }
+
+    #[tokio::test]
+    async fn test_http_failure_is_descriptive() {
+        let forbidden = "HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+        let (url, _server) = serve(vec![forbidden]).await;
+        let executor = OperationExecutor::new(Client::new(), config(url));
+
+        let error = executor.execute(&send(Uuid::new_v4())).await.unwrap_err();
+        assert!(error.contains("send message"), "{}", error);
+        assert!(error.contains("403"), "{}", error);
+    }
+}
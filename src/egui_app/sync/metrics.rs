@@ -8,10 +8,13 @@
 //! - **Error Tracking**: Failure rates and error patterns
 //! - **Bandwidth Usage**: Network usage monitoring
 //! - **User Experience**: Sync impact on application responsiveness
+//!
+//! Dead letters aren't counted here; the operation queue keeps them (see
+//! [`OperationQueue::dead_letters`](crate::egui_app::offline::OperationQueue::dead_letters)).
 
 use std::time::{Duration, Instant};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SyncMetrics {
     pub total_syncs: u64,
     pub successful_syncs: u64,
@@ -22,6 +25,12 @@ pub struct SyncMetrics {
     pub last_sync_start: Option<Instant>,
 }
 
+impl Default for SyncMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl SyncMetrics {
     pub fn new() -> Self {
         Self {
@@ -48,8 +57,9 @@ impl SyncMetrics {
             self.total_bytes_synced += bytes_synced;
 
             // Update rolling average
-            let total_duration = self.average_sync_duration * (self.successful_syncs - 1) + duration;
-            self.average_sync_duration = total_duration / self.successful_syncs;
+            let previous = self.average_sync_duration.as_secs_f64() * (self.successful_syncs - 1) as f64;
+            self.average_sync_duration =
+                Duration::from_secs_f64((previous + duration.as_secs_f64()) / self.successful_syncs as f64);
         }
     }
 
@@ -65,4 +75,4 @@ impl SyncMetrics {
             self.successful_syncs as f64 / self.total_syncs as f64
         }
     }
-}
\ No newline at end of file
+}
@@ -7,9 +7,10 @@
 //! ## Architecture
 //!
 //! The sync service coordinates multiple components:
-//! - **Background Tasks**: Continuous sync workers
+//! - **Background Task**: The sync loop started by [`SyncService::start`]
+//! - **Executor**: Sends queued operations to the backend
 //! - **Scheduler**: Intelligent sync scheduling
-//! - **Conflict Resolver**: Automatic conflict resolution
+//! - **Conflict Routing**: Settles reconciliation conflicts per [`ConflictStrategy`]
 //! - **Network Monitor**: Connectivity detection
 //! - **Sync State**: Comprehensive sync state tracking
 //! - **Metrics**: Performance monitoring and analytics
@@ -25,6 +26,9 @@
 //!
 //! ## Usage
 //!
+//! The egui app runs the service through a [`SyncWorker`], which owns the
+//! runtime; directly:
+//!
 //! ```rust,no_run
 //! use xfmail::egui_app::sync::{SyncService, SyncConfig};
 //!
@@ -35,6 +39,9 @@
 //! // Start background sync
 //! sync_service.start().await?;
 //!
+//! // Queue a send; it's persisted until it goes out or is dead-lettered
+//! sync_service.enqueue(operation).await;
+//!
 //! // Monitor sync status
 //! let status = sync_service.get_status().await;
 //! println!("Sync status: {:?}", status);
@@ -43,18 +50,32 @@
 //! sync_service.force_sync().await?;
 //! ```
 
-pub mod background;
 pub mod scheduler;
-pub mod conflict_resolver;
 pub mod network_monitor;
 pub mod sync_state;
 pub mod metrics;
+pub mod executor;
+pub mod worker;
 
 use crate::egui_app::local_db::LocalDatabase;
-use crate::egui_app::offline::{OperationQueue, RetryManager, ReconciliationManager};
+use crate::egui_app::offline::{BackoffStrategy, DeadLetter, OperationQueue, RetryManager, RetryOutcome, ReconciliationManager, ReconciliationResult};
+use crate::egui_app::offline::queue::Operation;
+use crate::egui_app::offline::reconciliation::{ConflictType, ReconciliationConflict, StateChange};
 use crate::egui_app::config::Config;
+use crate::egui_app::crdt::{serializer, ContactCrdt, ConversationCrdt, CrdtState, MergeResult, Merger, MessageCrdt};
+use executor::OperationExecutor;
+use metrics::SyncMetrics;
+use network_monitor::NetworkMonitor;
+use serde::de::DeserializeOwned;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, RwLock};
+use uuid::Uuid;
+
+pub use worker::{SyncCommand, SyncWorker};
+
+/// Longest wait between retries of a failed operation
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(300);
 
 /// Configuration for the sync service
 #[derive(Debug, Clone)]
@@ -67,6 +88,8 @@ pub struct SyncConfig {
     pub max_concurrent_ops: usize,
     /// Enable bandwidth-aware sync
     pub bandwidth_aware: bool,
+    /// Below this estimated bandwidth only message sends are synced
+    pub min_bulk_bandwidth_kbps: f64,
     /// Enable battery-aware sync
     pub battery_aware: bool,
     /// Maximum retry attempts for failed operations
@@ -82,6 +105,7 @@ impl Default for SyncConfig {
             sync_interval_seconds: 30,
             max_concurrent_ops: 5,
             bandwidth_aware: true,
+            min_bulk_bandwidth_kbps: 256.0,
             battery_aware: true,
             max_retry_attempts: 5,
             conflict_strategy: ConflictStrategy::AutoMerge,
@@ -89,6 +113,16 @@ impl Default for SyncConfig {
     }
 }
 
+/// What a sync cycle is allowed to send
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncScope {
+    /// Every pending operation
+    Full,
+    /// Only message sends; contact and other bulk operations wait for a
+    /// faster connection
+    MessagesOnly,
+}
+
 /// Conflict resolution strategies
 #[derive(Debug, Clone)]
 pub enum ConflictStrategy {
@@ -102,14 +136,58 @@ pub enum ConflictStrategy {
     Manual,
 }
 
+/// A reconciliation conflict parked for the user under [`ConflictStrategy::Manual`]
+/// (or when an automatic merge isn't possible)
+#[derive(Debug, Clone)]
+pub struct ConflictRecord {
+    /// Identifier passed to [`SyncService::resolve_conflict`]
+    pub id: Uuid,
+    /// Both sides of the conflict
+    pub conflict: ReconciliationConflict,
+    /// When the conflict was detected
+    pub detected_at: String,
+}
+
+/// The user's choice for a parked conflict
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictChoice {
+    /// Discard the remote change
+    KeepLocal,
+    /// Discard the local change
+    KeepRemote,
+    /// Merge both sides via the CRDT merger
+    Merge,
+}
+
+/// How a conflict was settled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictOutcome {
+    KeptLocal,
+    KeptRemote,
+    Merged,
+}
+
+/// Shared handles a sync cycle works with
+#[derive(Debug, Clone)]
+struct SyncContext {
+    operation_queue: Arc<OperationQueue>,
+    retry_manager: Arc<RetryManager>,
+    reconciliation_manager: Arc<ReconciliationManager>,
+    sync_state: Arc<RwLock<SyncState>>,
+    metrics: Arc<RwLock<SyncMetrics>>,
+    executor: Arc<OperationExecutor>,
+    network: Arc<RwLock<NetworkMonitor>>,
+    /// Held for a whole cycle so a forced sync and the background loop never
+    /// send the same operation twice
+    cycle: Arc<Mutex<()>>,
+}
+
 /// Main sync service coordinator
 #[derive(Debug)]
 pub struct SyncService {
     /// Service configuration
     config: SyncConfig,
-    /// Local database instance
-    local_db: Arc<LocalDatabase>,
-    /// Operation queue for offline operations
+    /// Operation queue for offline operations, persisted in the local database
     operation_queue: Arc<OperationQueue>,
     /// Retry manager for failed operations
     retry_manager: Arc<RetryManager>,
@@ -117,6 +195,14 @@ pub struct SyncService {
     reconciliation_manager: Arc<ReconciliationManager>,
     /// Current sync state
     sync_state: Arc<RwLock<SyncState>>,
+    /// Sync metrics
+    metrics: Arc<RwLock<SyncMetrics>>,
+    /// Sends operations to the backend
+    executor: Arc<OperationExecutor>,
+    /// Connectivity and bandwidth estimate
+    network: Arc<RwLock<NetworkMonitor>>,
+    /// Serializes sync cycles
+    cycle: Arc<Mutex<()>>,
     /// Background sync task handle
     background_task: Option<tokio::task::JoinHandle<()>>,
 }
@@ -136,8 +222,16 @@ pub struct SyncState {
     pub failed_operations: usize,
     /// Current network status
     pub network_status: NetworkStatus,
+    /// Estimated bandwidth from recent sync transfers, in Kbps
+    pub estimated_kbps: Option<f64>,
     /// Current sync errors
     pub errors: Vec<String>,
+    /// Number of operations that exhausted their retries
+    pub dead_letter_count: usize,
+    /// The operation queue's dead letters, oldest first
+    pub dead_letters: Vec<DeadLetter>,
+    /// Conflicts waiting for the user to pick a side
+    pub pending_conflicts: Vec<ConflictRecord>,
 }
 
 /// Network connectivity status
@@ -152,13 +246,37 @@ pub enum NetworkStatus {
 }
 
 impl SyncService {
-    /// Create a new sync service
+    /// Create a new sync service using the default app config
     pub async fn new(config: SyncConfig) -> Result<Self, String> {
-        let local_db = Arc::new(LocalDatabase::new().await
-            .map_err(|e| format!("Failed to initialize local database: {}", e))?);
+        Self::with_client(config, Config::default(), reqwest::Client::new()).await
+    }
+
+    /// Create a sync service that talks to the backend with `client`,
+    /// authenticating with `app_config`'s server URL and token
+    pub async fn with_client(
+        config: SyncConfig,
+        app_config: Config,
+        client: reqwest::Client,
+    ) -> Result<Self, String> {
+        let local_db = LocalDatabase::new().await
+            .map_err(|e| format!("Failed to initialize local database: {}", e))?;
+        Self::open(config, app_config, client, &local_db).await
+    }
 
-        let operation_queue = Arc::new(OperationQueue::new());
-        let retry_manager = Arc::new(RetryManager::new());
+    /// Create a sync service whose operation queue is kept in `local_db`
+    pub async fn open(
+        config: SyncConfig,
+        app_config: Config,
+        client: reqwest::Client,
+        local_db: &LocalDatabase,
+    ) -> Result<Self, String> {
+        let operation_queue = Arc::new(OperationQueue::load_from_db(local_db).await
+            .map_err(|e| format!("Failed to load operation queue: {}", e))?);
+        let retry_manager = Arc::new(RetryManager::new_with(
+            BackoffStrategy::default(),
+            config.max_retry_attempts,
+            MAX_RETRY_DELAY,
+        ));
         let reconciliation_manager = Arc::new(ReconciliationManager::new());
 
         let sync_state = Arc::new(RwLock::new(SyncState {
@@ -168,16 +286,25 @@ impl SyncService {
             pending_operations: 0,
             failed_operations: 0,
             network_status: NetworkStatus::Offline,
+            estimated_kbps: None,
             errors: Vec::new(),
+            dead_letter_count: 0,
+            dead_letters: Vec::new(),
+            pending_conflicts: Vec::new(),
         }));
+        let metrics = Arc::new(RwLock::new(SyncMetrics::new()));
+        let executor = Arc::new(OperationExecutor::new(client, app_config));
 
         Ok(Self {
             config,
-            local_db,
             operation_queue,
             retry_manager,
             reconciliation_manager,
             sync_state,
+            metrics,
+            executor,
+            network: Arc::new(RwLock::new(NetworkMonitor::new())),
+            cycle: Arc::new(Mutex::new(())),
             background_task: None,
         })
     }
@@ -188,13 +315,11 @@ impl SyncService {
             return Err("Sync service is already running".to_string());
         }
 
-        let operation_queue = Arc::clone(&self.operation_queue);
-        let retry_manager = Arc::clone(&self.retry_manager);
-        let sync_state = Arc::clone(&self.sync_state);
+        let context = self.context();
         let config = self.config.clone();
 
         let handle = tokio::spawn(async move {
-            Self::background_sync_loop(operation_queue, retry_manager, sync_state, config).await;
+            Self::background_sync_loop(context, config).await;
         });
 
         self.background_task = Some(handle);
@@ -214,18 +339,85 @@ impl SyncService {
         self.perform_sync().await
     }
 
+    /// Queue an operation for the next sync cycle
+    pub async fn enqueue(&self, operation: Operation) {
+        let priority = operation.priority();
+        self.operation_queue.add_operation_with_priority(operation, priority).await;
+        self.sync_state.write().await.pending_operations = self.operation_queue.count_pending().await;
+    }
+
+    /// Record whether the backend is reachable; nothing is sent while offline
+    pub async fn set_online(&self, online: bool) {
+        self.sync_state.write().await.network_status = if online {
+            NetworkStatus::Online
+        } else {
+            NetworkStatus::Offline
+        };
+    }
+
+    /// Authenticate later requests with a refreshed (or cleared) token
+    pub fn set_token(&self, token: Option<String>) {
+        self.executor.set_token(token);
+    }
+
     /// Get current sync status
     pub async fn get_status(&self) -> SyncState {
         self.sync_state.read().await.clone()
     }
 
+    /// Get current sync metrics
+    pub async fn get_metrics(&self) -> SyncMetrics {
+        self.metrics.read().await.clone()
+    }
+
+    /// Operations that exhausted their retries, oldest first
+    pub async fn get_dead_letters(&self) -> Vec<DeadLetter> {
+        self.operation_queue.dead_letters().await
+    }
+
+    /// Resolve a parked conflict with the user's choice
+    ///
+    /// A failed merge leaves the conflict pending.
+    pub async fn resolve_conflict(&self, id: Uuid, choice: ConflictChoice) -> Result<ConflictOutcome, String> {
+        let mut state = self.sync_state.write().await;
+        let index = state
+            .pending_conflicts
+            .iter()
+            .position(|record| record.id == id)
+            .ok_or_else(|| format!("No pending conflict {}", id))?;
+
+        let outcome = match choice {
+            ConflictChoice::KeepLocal => ConflictOutcome::KeptLocal,
+            ConflictChoice::KeepRemote => ConflictOutcome::KeptRemote,
+            ConflictChoice::Merge => {
+                if !Self::merge_conflict(&Merger::new(), &state.pending_conflicts[index].conflict) {
+                    return Err("Changes could not be merged; keep one side instead".to_string());
+                }
+                ConflictOutcome::Merged
+            }
+        };
+
+        let record = state.pending_conflicts.remove(index);
+        tracing::info!("Conflict {} ({}) resolved: {:?}", record.id, record.conflict.description, outcome);
+        Ok(outcome)
+    }
+
+    /// Handles shared with the background task
+    fn context(&self) -> SyncContext {
+        SyncContext {
+            operation_queue: Arc::clone(&self.operation_queue),
+            retry_manager: Arc::clone(&self.retry_manager),
+            reconciliation_manager: Arc::clone(&self.reconciliation_manager),
+            sync_state: Arc::clone(&self.sync_state),
+            metrics: Arc::clone(&self.metrics),
+            executor: Arc::clone(&self.executor),
+            network: Arc::clone(&self.network),
+            cycle: Arc::clone(&self.cycle),
+        }
+    }
+
     /// Background sync loop
-    async fn background_sync_loop(
-        operation_queue: Arc<OperationQueue>,
-        retry_manager: Arc<RetryManager>,
-        sync_state: Arc<RwLock<SyncState>>,
-        config: SyncConfig,
-    ) {
+    async fn background_sync_loop(context: SyncContext, config: SyncConfig) {
         let mut interval = tokio::time::interval(
             std::time::Duration::from_secs(config.sync_interval_seconds)
         );
@@ -233,40 +425,66 @@ impl SyncService {
         loop {
             interval.tick().await;
 
-            // Check if we should sync
-            if Self::should_perform_sync(&config, &sync_state).await {
-                if let Err(e) = Self::perform_sync_cycle(
-                    &operation_queue,
-                    &retry_manager,
-                    &sync_state,
-                    &config,
-                ).await {
+            // Check if (and how much) we should sync
+            if let Some(scope) = Self::should_perform_sync(&config, &context.sync_state).await {
+                if let Err(e) = Self::perform_sync_cycle(&context, &config, scope).await {
                     tracing::error!("Sync cycle failed: {}", e);
                 }
             }
         }
     }
 
-    /// Determine if sync should be performed
-    async fn should_perform_sync(config: &SyncConfig, sync_state: &Arc<RwLock<SyncState>>) -> bool {
+    /// Determine if sync should be performed, and what it may send
+    ///
+    /// With `bandwidth_aware`, an estimate below `min_bulk_bandwidth_kbps`
+    /// limits the cycle to message sends.
+    async fn should_perform_sync(config: &SyncConfig, sync_state: &RwLock<SyncState>) -> Option<SyncScope> {
         let state = sync_state.read().await;
 
         // Don't sync if offline
-        if matches!(state.network_status, NetworkStatus::Offline) {
-            return false;
+        if matches!(state.network_status, NetworkStatus::Offline) || !config.auto_sync {
+            return None;
         }
 
-        // Always sync if auto_sync is enabled
-        config.auto_sync
+        let slow = state
+            .estimated_kbps
+            .is_some_and(|kbps| kbps < config.min_bulk_bandwidth_kbps);
+        if config.bandwidth_aware && slow {
+            Some(SyncScope::MessagesOnly)
+        } else {
+            Some(SyncScope::Full)
+        }
     }
 
-    /// Perform a complete sync cycle
-    async fn perform_sync_cycle(
-        operation_queue: &Arc<OperationQueue>,
-        retry_manager: &Arc<RetryManager>,
-        sync_state: &Arc<RwLock<SyncState>>,
-        config: &SyncConfig,
+    /// Execute one operation, feeding its timing into the bandwidth estimate
+    async fn execute_measured(
+        executor: &OperationExecutor,
+        network: &RwLock<NetworkMonitor>,
+        sync_state: &RwLock<SyncState>,
+        operation: &Operation,
     ) -> Result<(), String> {
+        let started = Instant::now();
+        executor.execute(operation).await?;
+
+        let mut network = network.write().await;
+        network.record_transfer(operation.payload_size(), started.elapsed());
+        sync_state.write().await.estimated_kbps = network.estimated_kbps();
+        Ok(())
+    }
+
+    /// Perform a complete sync cycle
+    async fn perform_sync_cycle(context: &SyncContext, config: &SyncConfig, scope: SyncScope) -> Result<(), String> {
+        let SyncContext { operation_queue, retry_manager, sync_state, metrics, executor, network, .. } = context;
+        let _cycle = context.cycle.lock().await;
+        metrics.write().await.record_sync_start();
+
+        // Pick up retries and cancels made from the queue panel
+        if let Err(e) = operation_queue.reload().await {
+            metrics.write().await.record_sync_failure();
+            return Err(format!("Failed to reload operation queue: {}", e));
+        }
+        let mut bytes_synced = 0;
+
         // Update sync state
         {
             let mut state = sync_state.write().await;
@@ -274,8 +492,13 @@ impl SyncService {
             state.progress = 0.0;
         }
 
-        // Process pending operations
-        let pending_ops = operation_queue.get_pending_operations().await;
+        // Process pending operations; on a slow link non-message ops stay queued
+        let pending_ops: Vec<_> = operation_queue
+            .get_pending_operations()
+            .await
+            .into_iter()
+            .filter(|queued| scope == SyncScope::Full || queued.operation.is_message())
+            .collect();
         let total_ops = pending_ops.len();
 
         for (i, operation) in pending_ops.into_iter().enumerate() {
@@ -286,13 +509,16 @@ impl SyncService {
             }
 
             // Execute operation
-            match Self::execute_operation(&operation).await {
+            let operation = operation.operation;
+            match Self::execute_measured(executor, network, sync_state, &operation).await {
                 Ok(_) => {
+                    bytes_synced += operation.payload_size() as u64;
                     operation_queue.complete_operation(&operation.id()).await;
                 }
                 Err(e) => {
-                    operation_queue.fail_operation(&operation.id(), e.clone()).await;
-                    retry_manager.schedule_retry(operation).await;
+                    Self::handle_operation_failure(
+                        operation_queue, retry_manager, sync_state, &operation, e,
+                    ).await;
                 }
             }
         }
@@ -300,16 +526,39 @@ impl SyncService {
         // Process retries
         let retry_ops = retry_manager.process_retries().await;
         for operation in retry_ops {
-            match Self::execute_operation(&operation).await {
+            if operation_queue.get_operation(&operation.id()).await.is_none() {
+                // Cancelled or dead-lettered from the queue panel meanwhile
+                retry_manager.cancel_retry(&operation.id()).await;
+                continue;
+            }
+            if scope == SyncScope::MessagesOnly && !operation.is_message() {
+                // Still in the retry queue; try again on a faster connection
+                retry_manager.release_retry(&operation.id()).await;
+                continue;
+            }
+            match Self::execute_measured(executor, network, sync_state, &operation).await {
                 Ok(_) => {
+                    bytes_synced += operation.payload_size() as u64;
                     operation_queue.complete_operation(&operation.id()).await;
+                    retry_manager.cancel_retry(&operation.id()).await;
                 }
                 Err(e) => {
-                    operation_queue.fail_operation(&operation.id(), e).await;
+                    Self::handle_operation_failure(
+                        operation_queue, retry_manager, sync_state, &operation, e,
+                    ).await;
                 }
             }
         }
 
+        // Settle conflicts between local and remote state
+        match context.reconciliation_manager.reconcile().await {
+            ReconciliationResult::ConflictsFound(conflicts) => {
+                Self::route_conflicts(sync_state, &config.conflict_strategy, conflicts).await;
+            }
+            ReconciliationResult::Failed(e) => tracing::warn!("Reconciliation failed: {}", e),
+            ReconciliationResult::Success => {}
+        }
+
         // Update final state
         {
             let mut state = sync_state.write().await;
@@ -318,27 +567,122 @@ impl SyncService {
             state.last_sync = Some(chrono::Utc::now().to_rfc3339());
             state.pending_operations = operation_queue.count_pending().await;
             state.failed_operations = operation_queue.count_failed().await;
+            // Requeued from the queue panel, or dead-lettered this cycle
+            state.dead_letters = operation_queue.dead_letters().await;
+            state.dead_letter_count = state.dead_letters.len();
         }
+        metrics.write().await.record_sync_success(bytes_synced);
 
         Ok(())
     }
 
-    /// Execute a single operation
-    async fn execute_operation(operation: &crate::egui_app::offline::queue::Operation) -> Result<(), String> {
-        // This would implement the actual operation execution
-        // For now, simulate success
-        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
-        Ok(())
+    /// Record a failed attempt and either schedule a retry or dead-letter the
+    /// operation once the retry manager's `max_retry_attempts` is exhausted.
+    ///
+    /// Returns `true` if the operation was dead-lettered.
+    async fn handle_operation_failure(
+        operation_queue: &OperationQueue,
+        retry_manager: &RetryManager,
+        sync_state: &RwLock<SyncState>,
+        operation: &Operation,
+        error: String,
+    ) -> bool {
+        operation_queue.fail_operation(&operation.id(), error.clone()).await;
+
+        if let RetryOutcome::Scheduled { .. } = retry_manager.schedule_retry(operation.clone(), error).await {
+            return false;
+        }
+
+        if let Some(entry) = operation_queue.dead_letter_operation(&operation.id()).await {
+            tracing::warn!(
+                "Operation {} dead-lettered after {} attempts: {:?}",
+                operation.id(), entry.attempts, entry.last_error
+            );
+        }
+
+        let dead_letters = operation_queue.dead_letters().await;
+        let mut state = sync_state.write().await;
+        state.dead_letter_count = dead_letters.len();
+        state.dead_letters = dead_letters;
+        true
+    }
+
+    /// Apply `strategy` to each conflict, parking the ones it can't settle
+    async fn route_conflicts(
+        sync_state: &RwLock<SyncState>,
+        strategy: &ConflictStrategy,
+        conflicts: Vec<ReconciliationConflict>,
+    ) {
+        let merger = Merger::new();
+        for conflict in conflicts {
+            match Self::apply_strategy(strategy, &merger, &conflict) {
+                Some(outcome) => {
+                    tracing::info!("Conflict ({}) resolved automatically: {:?}", conflict.description, outcome);
+                }
+                None => {
+                    let record = ConflictRecord {
+                        id: Uuid::new_v4(),
+                        conflict,
+                        detected_at: chrono::Utc::now().to_rfc3339(),
+                    };
+                    tracing::info!("Conflict {} ({}) needs manual resolution", record.id, record.conflict.description);
+                    sync_state.write().await.pending_conflicts.push(record);
+                }
+            }
+        }
+    }
+
+    /// Settle a conflict per `strategy`; `None` means it needs the user
+    fn apply_strategy(
+        strategy: &ConflictStrategy,
+        merger: &Merger,
+        conflict: &ReconciliationConflict,
+    ) -> Option<ConflictOutcome> {
+        match strategy {
+            ConflictStrategy::PreferLocal => Some(ConflictOutcome::KeptLocal),
+            ConflictStrategy::PreferRemote => Some(ConflictOutcome::KeptRemote),
+            ConflictStrategy::AutoMerge => Self::merge_conflict(merger, conflict).then_some(ConflictOutcome::Merged),
+            ConflictStrategy::Manual => None,
+        }
+    }
+
+    /// Merge both sides of a conflict as CRDT states; `false` if they can't be
+    /// decoded or the merger reports a conflict
+    fn merge_conflict(merger: &Merger, conflict: &ReconciliationConflict) -> bool {
+        let (Ok(local), Ok(remote)) = (
+            serde_json::from_slice::<StateChange>(&conflict.local_data),
+            serde_json::from_slice::<StateChange>(&conflict.remote_data),
+        ) else {
+            return false;
+        };
+
+        match conflict.conflict_type {
+            ConflictType::ContactChange => {
+                Self::merge_states(&local, &remote, |l: &ContactCrdt, r| merger.merge_contacts(l, r))
+            }
+            ConflictType::ConversationMetadata | ConflictType::ParticipantChange => {
+                Self::merge_states(&local, &remote, |l: &ConversationCrdt, r| merger.merge_conversations(l, r))
+            }
+            ConflictType::MessageEdit => {
+                Self::merge_states(&local, &remote, |l: &MessageCrdt, r| merger.merge_messages(l, r))
+            }
+        }
+    }
+
+    fn merge_states<T: CrdtState + DeserializeOwned>(
+        local: &StateChange,
+        remote: &StateChange,
+        merge: impl Fn(&T, &T) -> MergeResult,
+    ) -> bool {
+        match (serializer::decode::<T>(&local.data), serializer::decode::<T>(&remote.data)) {
+            (Ok(local), Ok(remote)) => !matches!(merge(&local, &remote), MergeResult::Conflict { .. }),
+            _ => false,
+        }
     }
 
     /// Perform immediate sync
     async fn perform_sync(&self) -> Result<(), String> {
-        Self::perform_sync_cycle(
-            &self.operation_queue,
-            &self.retry_manager,
-            &self.sync_state,
-            &self.config,
-        ).await
+        Self::perform_sync_cycle(&self.context(), &self.config, SyncScope::Full).await
     }
 }
 
@@ -354,17 +698,25 @@ impl Drop for SyncService {
 mod tests {
     use super::*;
 
+    /// A service whose queue lives in a throwaway database
+    async fn service(config: SyncConfig) -> (tempfile::TempDir, SyncService) {
+        let dir = tempfile::tempdir().unwrap();
+        let db = LocalDatabase::open(&dir.path().join("local.db"), None).await.unwrap();
+        let service = SyncService::open(config, Config::default(), reqwest::Client::new(), &db).await.unwrap();
+        (dir, service)
+    }
+
     #[tokio::test]
     async fn test_sync_service_creation() {
-        let config = SyncConfig::default();
-        let service = SyncService::new(config).await;
+        let dir = tempfile::tempdir().unwrap();
+        let db = LocalDatabase::open(&dir.path().join("local.db"), None).await.unwrap();
+        let service = SyncService::open(SyncConfig::default(), Config::default(), reqwest::Client::new(), &db).await;
         assert!(service.is_ok());
     }
 
     #[tokio::test]
     async fn test_sync_service_start_stop() {
-        let config = SyncConfig::default();
-        let mut service = SyncService::new(config).await.unwrap();
+        let (_dir, mut service) = service(SyncConfig::default()).await;
 
         // Start service
         assert!(service.start().await.is_ok());
@@ -375,11 +727,136 @@ mod tests {
 
     #[tokio::test]
     async fn test_sync_status() {
-        let config = SyncConfig::default();
-        let service = SyncService::new(config).await.unwrap();
+        let (_dir, service) = service(SyncConfig::default()).await;
 
         let status = service.get_status().await;
         assert!(!status.is_syncing);
         assert_eq!(status.progress, 0.0);
+        assert_eq!(status.dead_letter_count, 0);
+    }
+
+    #[tokio::test]
+    async fn test_exhausted_retries_are_dead_lettered() {
+        let config = SyncConfig { max_retry_attempts: 3, ..SyncConfig::default() };
+        let operation_queue = OperationQueue::new();
+        let retry_manager = RetryManager::new_with(BackoffStrategy::default(), config.max_retry_attempts, MAX_RETRY_DELAY);
+        let sync_state = RwLock::new(SyncState {
+            is_syncing: false,
+            last_sync: None,
+            progress: 0.0,
+            pending_operations: 0,
+            failed_operations: 0,
+            network_status: NetworkStatus::Online,
+            estimated_kbps: None,
+            errors: Vec::new(),
+            dead_letter_count: 0,
+            dead_letters: Vec::new(),
+            pending_conflicts: Vec::new(),
+        });
+
+        let operation = Operation::SendMessage {
+            id: uuid::Uuid::new_v4(),
+            conversation_id: uuid::Uuid::new_v4(),
+            content: "Hello".to_string(),
+            message_type: crate::shared::messaging::MessageType::Text,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        };
+        operation_queue.add_operation(operation.clone()).await;
+
+        for attempt in 1..=3 {
+            let dead = SyncService::handle_operation_failure(
+                &operation_queue, &retry_manager, &sync_state,
+                &operation, format!("Network error {}", attempt),
+            ).await;
+            assert_eq!(dead, attempt == 3);
+        }
+
+        assert_eq!(retry_manager.count_retrying().await, 0);
+
+        let state = sync_state.read().await;
+        assert_eq!(state.dead_letter_count, 1);
+        assert_eq!(state.dead_letters.len(), 1);
+        assert_eq!(state.dead_letters[0].operation.id(), operation.id());
+        assert_eq!(state.dead_letters[0].last_error.as_deref(), Some("Network error 3"));
+        assert_eq!(state.dead_letters[0].attempts, 3);
+
+        // Kept for a manual retry rather than left to be cleaned up
+        assert!(operation_queue.get_operation(&operation.id()).await.is_none());
+        assert_eq!(operation_queue.get_stats().await.dead_letters, 1);
+    }
+
+    #[tokio::test]
+    async fn test_slow_link_limits_sync_to_messages() {
+        let config = SyncConfig::default();
+        let (_dir, service) = service(config.clone()).await;
+        service.sync_state.write().await.network_status = NetworkStatus::Online;
+
+        // No estimate yet: sync everything
+        assert_eq!(SyncService::should_perform_sync(&config, &service.sync_state).await, Some(SyncScope::Full));
+
+        service.sync_state.write().await.estimated_kbps = Some(config.min_bulk_bandwidth_kbps / 2.0);
+        assert_eq!(SyncService::should_perform_sync(&config, &service.sync_state).await, Some(SyncScope::MessagesOnly));
+
+        let unaware = SyncConfig { bandwidth_aware: false, ..config.clone() };
+        assert_eq!(SyncService::should_perform_sync(&unaware, &service.sync_state).await, Some(SyncScope::Full));
+
+        service.sync_state.write().await.network_status = NetworkStatus::Offline;
+        assert_eq!(SyncService::should_perform_sync(&config, &service.sync_state).await, None);
+    }
+
+    fn contact_conflict(local: &ContactCrdt, remote: &ContactCrdt) -> ReconciliationConflict {
+        let change = |crdt: &ContactCrdt| StateChange {
+            entity_type: "contact".to_string(),
+            entity_id: "contact1".to_string(),
+            change_type: "update".to_string(),
+            data: serde_json::to_vec(crdt).unwrap(),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        };
+        ReconciliationConflict {
+            conflict_type: ConflictType::ContactChange,
+            description: "Conflicting changes to contact1".to_string(),
+            local_data: serde_json::to_vec(&change(local)).unwrap(),
+            remote_data: serde_json::to_vec(&change(remote)).unwrap(),
+            resolution_options: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_strategies_settle_or_park_conflicts() {
+        let merger = Merger::new();
+        let mut remote = ContactCrdt::new(2);
+        remote.send_request(Uuid::new_v4());
+        let conflict = contact_conflict(&ContactCrdt::new(1), &remote);
+
+        let apply = |strategy| SyncService::apply_strategy(&strategy, &merger, &conflict);
+        assert_eq!(apply(ConflictStrategy::PreferLocal), Some(ConflictOutcome::KeptLocal));
+        assert_eq!(apply(ConflictStrategy::PreferRemote), Some(ConflictOutcome::KeptRemote));
+        assert_eq!(apply(ConflictStrategy::AutoMerge), Some(ConflictOutcome::Merged));
+        assert_eq!(apply(ConflictStrategy::Manual), None);
+
+        // Undecodable state can't be merged automatically
+        let garbled = ReconciliationConflict { local_data: b"not json".to_vec(), ..conflict.clone() };
+        assert_eq!(SyncService::apply_strategy(&ConflictStrategy::AutoMerge, &merger, &garbled), None);
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_manual_conflicts_are_parked_until_resolved() {
+        let (_dir, service) = service(SyncConfig::default()).await;
+        let conflict = contact_conflict(&ContactCrdt::new(1), &ContactCrdt::new(2));
+
+        SyncService::route_conflicts(&service.sync_state, &ConflictStrategy::Manual, vec![conflict.clone(), conflict]).await;
+        let pending = service.get_status().await.pending_conflicts;
+        assert_eq!(pending.len(), 2);
+
+        assert_eq!(
+            service.resolve_conflict(pending[0].id, ConflictChoice::KeepRemote).await,
+            Ok(ConflictOutcome::KeptRemote)
+        );
+        assert_eq!(
+            service.resolve_conflict(pending[1].id, ConflictChoice::Merge).await,
+            Ok(ConflictOutcome::Merged)
+        );
+        assert!(service.get_status().await.pending_conflicts.is_empty());
+        assert!(service.resolve_conflict(pending[0].id, ConflictChoice::KeepLocal).await.is_err());
+    }
+}
@@ -8,6 +8,18 @@
 //! - **Network Quality**: Bandwidth and latency assessment
 //! - **Adaptive Sync**: Adjust sync behavior based on network conditions
 //! - **Real-time Updates**: Live network status changes
+//!
+//! ## Bandwidth Estimation
+//!
+//! Each completed sync transfer is recorded as `(bytes, elapsed)`. The
+//! estimate is total bytes over total time across the most recent
+//! [`BANDWIDTH_SAMPLES`] transfers, so one slow request doesn't swing it.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Number of recent transfers the bandwidth estimate covers
+pub const BANDWIDTH_SAMPLES: usize = 10;
 
 #[derive(Debug, Clone)]
 pub enum NetworkStatus {
@@ -16,18 +28,100 @@ pub enum NetworkStatus {
     Offline,
 }
 
+/// Throughput estimate over recent transfers
+#[derive(Debug, Clone, Default)]
+pub struct BandwidthEstimator {
+    samples: VecDeque<(usize, Duration)>,
+}
+
+impl BandwidthEstimator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a completed transfer of `bytes` that took `elapsed`
+    pub fn record(&mut self, bytes: usize, elapsed: Duration) {
+        if elapsed.is_zero() {
+            return;
+        }
+        if self.samples.len() == BANDWIDTH_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back((bytes, elapsed));
+    }
+
+    /// Estimated throughput in kilobits per second, if anything was measured
+    pub fn estimate_kbps(&self) -> Option<f64> {
+        let bytes: usize = self.samples.iter().map(|(bytes, _)| bytes).sum();
+        let elapsed: Duration = self.samples.iter().map(|(_, elapsed)| *elapsed).sum();
+        if elapsed.is_zero() {
+            return None;
+        }
+        Some(bytes as f64 * 8.0 / 1000.0 / elapsed.as_secs_f64())
+    }
+}
+
+#[derive(Debug)]
 pub struct NetworkMonitor {
     current_status: NetworkStatus,
+    bandwidth: BandwidthEstimator,
+}
+
+impl Default for NetworkMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl NetworkMonitor {
     pub fn new() -> Self {
         Self {
             current_status: NetworkStatus::Offline,
+            bandwidth: BandwidthEstimator::new(),
         }
     }
 
     pub fn get_status(&self) -> NetworkStatus {
         self.current_status.clone()
     }
+
+    /// Record a completed sync transfer
+    pub fn record_transfer(&mut self, bytes: usize, elapsed: Duration) {
+        self.bandwidth.record(bytes, elapsed);
+    }
+
+    /// Estimated bandwidth in Kbps
+    pub fn estimated_kbps(&self) -> Option<f64> {
+        self.bandwidth.estimate_kbps()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_from_recent_transfers() {
+        let mut monitor = NetworkMonitor::new();
+        assert_eq!(monitor.estimated_kbps(), None);
+
+        // 2000 bytes in 1s + 2000 bytes in 3s = 32 kbit over 4s
+        monitor.record_transfer(2000, Duration::from_secs(1));
+        monitor.record_transfer(2000, Duration::from_secs(3));
+        assert_eq!(monitor.estimated_kbps(), Some(8.0));
+
+        // Zero-length timings are ignored
+        monitor.record_transfer(5000, Duration::ZERO);
+        assert_eq!(monitor.estimated_kbps(), Some(8.0));
+    }
+
+    #[test]
+    fn test_old_samples_age_out() {
+        let mut estimator = BandwidthEstimator::new();
+        estimator.record(1_000_000, Duration::from_secs(1));
+        for _ in 0..BANDWIDTH_SAMPLES {
+            estimator.record(125, Duration::from_secs(1));
+        }
+        assert_eq!(estimator.estimate_kbps(), Some(1.0));
+    }
 }
\ No newline at end of file
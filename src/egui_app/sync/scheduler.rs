@@ -27,6 +27,12 @@ pub struct SyncScheduler {
     is_active: RwLock<bool>,
 }
 
+impl Default for SyncScheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl SyncScheduler {
     /// Create a new sync scheduler
     pub fn new() -> Self {
@@ -92,7 +98,7 @@ impl SyncScheduler {
 
     /// Get time until next sync
     pub async fn time_until_next_sync(&self) -> Option<Duration> {
-        let last_sync = *self.last_sync.read().await?;
+        let last_sync = (*self.last_sync.read().await)?;
         let interval = *self.current_interval.read().await;
 
         let elapsed = last_sync.elapsed();
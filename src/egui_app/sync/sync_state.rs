@@ -17,7 +17,11 @@ pub struct SyncState {
     pub pending_operations: usize,
     pub failed_operations: usize,
     pub network_status: crate::egui_app::sync::network_monitor::NetworkStatus,
+    pub estimated_kbps: Option<f64>,
     pub errors: Vec<String>,
+    pub dead_letter_count: usize,
+    pub dead_letters: Vec<crate::egui_app::offline::DeadLetter>,
+    pub pending_conflicts: Vec<crate::egui_app::sync::ConflictRecord>,
 }
 
 impl Default for SyncState {
@@ -29,7 +33,11 @@ impl Default for SyncState {
             pending_operations: 0,
             failed_operations: 0,
             network_status: crate::egui_app::sync::network_monitor::NetworkStatus::Offline,
+            estimated_kbps: None,
             errors: Vec::new(),
+            dead_letter_count: 0,
+            dead_letters: Vec::new(),
+            pending_conflicts: Vec::new(),
         }
     }
-}
\ No newline at end of file
+}
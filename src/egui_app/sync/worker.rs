@@ -0,0 +1,132 @@
+//! # Sync Worker
+//!
+//! Runs the [`SyncService`] for the egui app. The UI thread can't await, so
+//! like the queue inspector the service lives on its own thread with a tokio
+//! runtime. The app sends it [`SyncCommand`]s and picks up a status snapshot
+//! each frame; dropping the worker stops the service.
+
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+
+use super::{SyncConfig, SyncService, SyncState};
+use crate::egui_app::config::Config;
+use crate::egui_app::offline::queue::Operation;
+
+/// How often the status snapshot is refreshed between commands
+const STATUS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Something for the sync service to do
+#[derive(Debug, Clone)]
+pub enum SyncCommand {
+    /// Persist an operation and send it on a later cycle
+    Enqueue(Operation),
+    /// Connectivity changed; coming online syncs right away
+    SetOnline(bool),
+    /// The auth token was refreshed
+    SetToken(Option<String>),
+}
+
+/// Handle on the sync service thread
+pub struct SyncWorker {
+    commands: UnboundedSender<SyncCommand>,
+    status: mpsc::Receiver<SyncState>,
+    /// Connectivity last passed on, so only changes are sent
+    online: Option<bool>,
+    /// Latest status from the service
+    pub state: Option<SyncState>,
+}
+
+impl SyncWorker {
+    /// Start the sync service, authenticated with `app_config`'s token
+    pub fn spawn(config: SyncConfig, app_config: Config) -> Self {
+        let (command_tx, mut command_rx) = unbounded_channel();
+        let (status_tx, status_rx) = mpsc::channel();
+
+        thread::spawn(move || {
+            let rt = match tokio::runtime::Runtime::new() {
+                Ok(rt) => rt,
+                Err(e) => {
+                    tracing::error!("Failed to create sync runtime: {}", e);
+                    return;
+                }
+            };
+            rt.block_on(async move {
+                let mut service = match SyncService::with_client(config, app_config, reqwest::Client::new()).await {
+                    Ok(service) => service,
+                    Err(e) => {
+                        tracing::error!("Failed to start sync service: {}", e);
+                        return;
+                    }
+                };
+                if let Err(e) = service.start().await {
+                    tracing::error!("Failed to start sync service: {}", e);
+                    return;
+                }
+
+                let mut report = tokio::time::interval(STATUS_INTERVAL);
+                loop {
+                    tokio::select! {
+                        command = command_rx.recv() => {
+                            // The app dropped the worker (logout or exit)
+                            let Some(command) = command else { break };
+                            apply(&service, command).await;
+                        }
+                        _ = report.tick() => {}
+                    }
+                    if status_tx.send(service.get_status().await).is_err() {
+                        break;
+                    }
+                }
+                let _ = service.stop().await;
+            });
+        });
+
+        Self {
+            commands: command_tx,
+            status: status_rx,
+            online: None,
+            state: None,
+        }
+    }
+
+    /// Pass a command to the service; false if it has stopped
+    pub fn send(&self, command: SyncCommand) -> bool {
+        self.commands.send(command).is_ok()
+    }
+
+    /// Tell the service about connectivity, if it changed
+    pub fn set_online(&mut self, online: bool) {
+        if self.online != Some(online) && self.send(SyncCommand::SetOnline(online)) {
+            self.online = Some(online);
+        }
+    }
+
+    /// Collect the latest status; returns true if it changed
+    pub fn poll(&mut self) -> bool {
+        match self.status.try_iter().last() {
+            Some(state) => {
+                self.state = Some(state);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+async fn apply(service: &SyncService, command: SyncCommand) {
+    match command {
+        SyncCommand::Enqueue(operation) => service.enqueue(operation).await,
+        SyncCommand::SetOnline(online) => {
+            service.set_online(online).await;
+            if online {
+                if let Err(e) = service.force_sync().await {
+                    tracing::warn!("Sync after reconnecting failed: {}", e);
+                }
+            }
+        }
+        SyncCommand::SetToken(token) => service.set_token(token),
+    }
+}
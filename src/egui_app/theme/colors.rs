@@ -4,6 +4,7 @@
 //! Colors are based on a warm brown/tan color scheme similar to classic Telegram themes.
 
 use eframe::egui::Color32;
+use crate::shared::messaging::{PresenceStatus, ThemeColor};
 
 /// Main sidebar background - Deep brown
 pub const SIDEBAR_BG: Color32 = Color32::from_rgb(0x2F, 0x1E, 0x1A);
@@ -56,6 +57,22 @@ pub const STATUS_ONLINE: Color32 = Color32::from_rgb(0x4C, 0xAF, 0x50);
 /// Offline status indicator - Gray
 pub const STATUS_OFFLINE: Color32 = Color32::from_rgb(0x9E, 0x9E, 0x9E);
 
+/// Away status indicator - Amber
+pub const STATUS_AWAY: Color32 = Color32::from_rgb(0xFF, 0xC1, 0x07);
+
+/// Do not disturb status indicator - Red
+pub const STATUS_DND: Color32 = Color32::from_rgb(0xE5, 0x39, 0x35);
+
+/// Indicator color for a presence status
+pub fn status_color(status: PresenceStatus) -> Color32 {
+    match status {
+        PresenceStatus::Online => STATUS_ONLINE,
+        PresenceStatus::Away => STATUS_AWAY,
+        PresenceStatus::DoNotDisturb => STATUS_DND,
+        PresenceStatus::Offline => STATUS_OFFLINE,
+    }
+}
+
 /// Success color - Green
 pub const SUCCESS: Color32 = Color32::from_rgb(0x4C, 0xAF, 0x50);
 
@@ -106,3 +123,60 @@ pub const INPUT_BG: Color32 = Color32::from_rgb(0xE6, 0xD7, 0xC7);
 
 /// Dark background for main areas
 pub const BG_DARK: Color32 = Color32::from_rgb(0x2F, 0x1E, 0x1A);
+
+/// Colors offered in the chat menu for per-conversation themes
+pub const CONVERSATION_PRESETS: &[(&str, ThemeColor)] = &[
+    ("Rust", ThemeColor::new(0xB7, 0x41, 0x0E)),
+    ("Forest", ThemeColor::new(0x2E, 0x7D, 0x32)),
+    ("Ocean", ThemeColor::new(0x1E, 0x63, 0x9E)),
+    ("Plum", ThemeColor::new(0x7B, 0x3F, 0x8C)),
+    ("Teal", ThemeColor::new(0x00, 0x79, 0x6B)),
+    ("Rose", ThemeColor::new(0xC2, 0x41, 0x6B)),
+    ("Slate", ThemeColor::new(0x4A, 0x5A, 0x6A)),
+    ("Gold", ThemeColor::new(0xB8, 0x86, 0x0B)),
+];
+
+/// Colors for one conversation's header and bubbles
+///
+/// Built from the user's chosen [`ThemeColor`], or the global theme when the
+/// conversation has none.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConversationTheme {
+    pub header_bg: Color32,
+    pub bubble_outgoing: Color32,
+    pub bubble_incoming: Color32,
+}
+
+impl Default for ConversationTheme {
+    fn default() -> Self {
+        Self {
+            header_bg: CHAT_HEADER_BG,
+            bubble_outgoing: BUBBLE_OUTGOING,
+            bubble_incoming: BUBBLE_INCOMING,
+        }
+    }
+}
+
+impl ConversationTheme {
+    /// Theme for a conversation's color, falling back to the global theme
+    ///
+    /// The header is darkened so light header text stays readable; bubbles
+    /// are tinted towards white so dark message text does.
+    pub fn for_color(color: Option<ThemeColor>) -> Self {
+        let Some(color) = color else {
+            return Self::default();
+        };
+        let base = Color32::from_rgb(color.r, color.g, color.b);
+        Self {
+            header_bg: mix(base, Color32::BLACK, 0.45),
+            bubble_outgoing: mix(base, Color32::WHITE, 0.55),
+            bubble_incoming: mix(base, Color32::WHITE, 0.8),
+        }
+    }
+}
+
+/// Blend `a` towards `b` by `t` (0.0 is `a`, 1.0 is `b`)
+fn mix(a: Color32, b: Color32, t: f32) -> Color32 {
+    let channel = |x: u8, y: u8| (x as f32 + (y as f32 - x as f32) * t).round() as u8;
+    Color32::from_rgb(channel(a.r(), b.r()), channel(a.g(), b.g()), channel(a.b(), b.b()))
+}
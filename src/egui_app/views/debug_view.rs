@@ -1,5 +1,6 @@
 use crate::egui_app::state::AppState;
 use crate::egui_app::debug::DebugCategory;
+use crate::egui_app::offline::{OperationStatus, Priority, QueueAction};
 
 pub fn render_debug_panel(ui: &mut egui::Ui, state: &mut AppState) {
     ui.vertical(|ui| {
@@ -95,3 +96,133 @@ pub fn render_debug_panel(ui: &mut egui::Ui, state: &mut AppState) {
         });
     });
 }
+
+/// Open the operation queue window and load the current queue
+pub fn open_queue_panel(state: &mut AppState) {
+    state.show_queue_panel = true;
+    state.queue_inspector.send(QueueAction::Refresh);
+}
+
+/// Advanced window listing the persisted operation queue, for diagnosing
+/// stuck syncs
+pub fn render_queue_panel(ctx: &egui::Context, state: &mut AppState) {
+    if !state.show_queue_panel {
+        return;
+    }
+    if state.queue_inspector.poll() {
+        state.debug_logger.debug(
+            DebugCategory::Sync,
+            format!("Operation queue: {} operations", state.queue_inspector.operations.len()),
+        );
+    }
+
+    let mut open = true;
+    let mut action = None;
+    egui::Window::new("⚙ Operation Queue")
+        .open(&mut open)
+        .default_width(640.0)
+        .show(ctx, |ui| {
+            let inspector = &state.queue_inspector;
+            ui.horizontal(|ui| {
+                if ui.add_enabled(!inspector.is_loading(), egui::Button::new("🔄 Refresh")).clicked() {
+                    action = Some(QueueAction::Refresh);
+                }
+                ui.label(format!("Operations: {}", inspector.operations.len()));
+                if inspector.is_loading() {
+                    ui.spinner();
+                }
+            });
+            if let Some(ref error) = inspector.error {
+                ui.colored_label(egui::Color32::RED, error);
+            }
+            ui.separator();
+
+            if !inspector.dead_letters.is_empty() {
+                ui.strong(format!("Failed after all retries: {}", inspector.dead_letters.len()));
+                egui::Grid::new("dead_letter_grid")
+                    .striped(true)
+                    .num_columns(4)
+                    .show(ui, |ui| {
+                        for entry in &inspector.dead_letters {
+                            let id = entry.operation.id();
+                            ui.label(entry.operation.type_name())
+                                .on_hover_text(format!("{}\nGave up {}", id, entry.failed_at));
+                            ui.label(format!("{} attempts", entry.attempts));
+                            match entry.last_error {
+                                Some(ref error) => ui.colored_label(egui::Color32::YELLOW, error),
+                                None => ui.label("—"),
+                            };
+                            if ui.add_enabled(!inspector.is_loading(), egui::Button::new("Retry")).clicked() {
+                                action = Some(QueueAction::RequeueDeadLetter(id));
+                            }
+                            ui.end_row();
+                        }
+                    });
+                ui.separator();
+            }
+
+            if inspector.operations.is_empty() {
+                ui.label("The queue is empty.");
+                return;
+            }
+
+            egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                egui::Grid::new("operation_queue_grid")
+                    .striped(true)
+                    .num_columns(6)
+                    .show(ui, |ui| {
+                        ui.strong("Type");
+                        ui.strong("Status");
+                        ui.strong("Retries");
+                        ui.strong("Priority");
+                        ui.strong("Last error");
+                        ui.strong("");
+                        ui.end_row();
+
+                        for op in &inspector.operations {
+                            let id = op.operation.id();
+                            ui.label(op.operation.type_name())
+                                .on_hover_text(format!("{}\nQueued {}", id, op.queued_at));
+                            ui.colored_label(status_color(&op.status), format!("{:?}", op.status));
+                            ui.label(op.retry_count.to_string());
+                            ui.label(format!("{:?}", op.priority));
+                            match op.last_error {
+                                Some(ref error) => ui.colored_label(egui::Color32::YELLOW, error),
+                                None => ui.label("—"),
+                            };
+                            ui.horizontal(|ui| {
+                                let idle = !inspector.is_loading();
+                                let can_retry = !matches!(op.status, OperationStatus::Pending | OperationStatus::InProgress);
+                                if ui.add_enabled(idle && can_retry, egui::Button::new("Retry")).clicked() {
+                                    action = Some(QueueAction::Retry(id));
+                                }
+                                if ui.add_enabled(idle, egui::Button::new("Cancel")).clicked() {
+                                    action = Some(QueueAction::Cancel(id));
+                                }
+                                let can_bump = op.priority != Priority::Critical;
+                                if ui.add_enabled(idle && can_bump, egui::Button::new("⬆ Priority")).clicked() {
+                                    action = Some(QueueAction::BumpPriority(id));
+                                }
+                            });
+                            ui.end_row();
+                        }
+                    });
+            });
+        });
+
+    if let Some(action) = action {
+        state.debug_logger.info(DebugCategory::Sync, format!("Operation queue action: {:?}", action));
+        state.queue_inspector.send(action);
+    }
+    state.show_queue_panel = open;
+}
+
+fn status_color(status: &OperationStatus) -> egui::Color32 {
+    match status {
+        OperationStatus::Pending => egui::Color32::LIGHT_GRAY,
+        OperationStatus::InProgress => egui::Color32::LIGHT_BLUE,
+        OperationStatus::Completed => egui::Color32::GREEN,
+        OperationStatus::Failed => egui::Color32::RED,
+        OperationStatus::Retrying => egui::Color32::YELLOW,
+    }
+}
@@ -0,0 +1,41 @@
+use eframe::egui;
+
+use crate::egui_app::state::AppState;
+use crate::egui_app::theme::colors;
+
+/// Full-window lock screen; nothing else is drawn while locked
+pub fn render(ctx: &egui::Context, state: &mut AppState) {
+    let frame = egui::Frame::default()
+        .fill(colors::BG_DARK)
+        .inner_margin(egui::Margin::same(0));
+
+    egui::CentralPanel::default().frame(frame).show(ctx, |ui| {
+        let available_rect = ui.available_rect_before_wrap();
+        ui.vertical_centered(|ui| {
+            ui.add_space((available_rect.height() - 200.0).max(0.0) / 2.0);
+
+            ui.label(egui::RichText::new("🔒 XFChat is locked").size(28.0).strong().color(colors::TEXT_LIGHT));
+            ui.add_space(20.0);
+
+            if let Some(ref error) = state.lock_error {
+                ui.label(egui::RichText::new(error).color(colors::ERROR));
+                ui.add_space(10.0);
+            }
+
+            let response = ui.add_sized(
+                [160.0, 28.0],
+                egui::TextEdit::singleline(&mut state.pin_input)
+                    .password(true)
+                    .hint_text("PIN")
+                    .text_color(colors::TEXT_LIGHT),
+            );
+            response.request_focus();
+            ui.add_space(10.0);
+
+            let submitted = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if ui.button("Unlock").clicked() || submitted {
+                state.unlock_app();
+            }
+        });
+    });
+}
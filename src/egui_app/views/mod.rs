@@ -1,11 +1,13 @@
 use eframe::egui;
 
 use crate::egui_app::AppView;
-use crate::egui_app::state::AppState;
+use crate::egui_app::state::{AppState, OnboardingStep};
 use crate::egui_app::theme::colors;
+use crate::shared::messaging::PresenceStatus;
 
 pub mod auth_view;
 pub mod landing_view;
+pub mod lock_view;
 pub mod xfmail_view;
 pub mod debug_view;
 
@@ -21,6 +23,24 @@ pub fn render_top_bar(ctx: &egui::Context, state: &mut AppState, frame: &mut efr
 
             ui.horizontal(|ui| {
                 ui.colored_label(colors::TEXT_LIGHT, egui::RichText::new("💬 XFChat").size(18.0).strong());
+                ui.menu_button(egui::RichText::new("Help").color(colors::TEXT_LIGHT), |ui| {
+                    ui.label("Diagnostic bundle for support");
+                    ui.add(egui::TextEdit::singleline(&mut state.diagnostics.path).desired_width(320.0));
+                    ui.checkbox(&mut state.diagnostics.include_sensitive, "Include tokens and email addresses")
+                        .on_hover_text("Only if support asks; these are redacted by default");
+                    let running = state.diagnostics.result.is_some();
+                    if ui.add_enabled(!running, egui::Button::new("Create diagnostic bundle")).clicked() {
+                        state.create_diagnostic_bundle();
+                    }
+                    if let Some(ref status) = state.diagnostics.status {
+                        ui.colored_label(colors::TEXT_SECONDARY, status);
+                    }
+                    ui.separator();
+                    if ui.button("Operation queue (advanced)…").clicked() {
+                        debug_view::open_queue_panel(state);
+                        ui.close();
+                    }
+                });
 
                 // Connectivity status indicator
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
@@ -50,6 +70,77 @@ pub fn render_top_bar(ctx: &egui::Context, state: &mut AppState, frame: &mut efr
                         if ui.button("Logout").clicked() {
                             state.logout();
                         }
+                        ui.menu_button(egui::RichText::new("🔒").color(colors::TEXT_LIGHT), |ui| {
+                            if ui.add_enabled(state.app_lock.has_pin(), egui::Button::new("Lock now")).clicked() {
+                                state.lock_app();
+                                ui.close();
+                            }
+                            let mut auto_lock = state.app_lock.auto_lock_on_minimize();
+                            if ui
+                                .add_enabled(state.app_lock.has_pin(), egui::Checkbox::new(&mut auto_lock, "Lock when minimized"))
+                                .changed()
+                            {
+                                state.lock_error = state.app_lock.set_auto_lock_on_minimize(auto_lock).err().map(|e| e.to_string());
+                            }
+                            ui.separator();
+                            ui.add(
+                                egui::TextEdit::singleline(&mut state.pin_input)
+                                    .password(true)
+                                    .hint_text("New PIN (4-8 digits)")
+                                    .desired_width(160.0),
+                            );
+                            let label = if state.app_lock.has_pin() { "Change PIN" } else { "Set PIN" };
+                            if ui.button(label).clicked() {
+                                state.set_lock_pin();
+                            }
+                            if let Some(ref error) = state.lock_error {
+                                ui.colored_label(colors::ERROR, error);
+                            }
+                        });
+
+                        let mut low_data_mode = state.config.low_data_mode();
+                        if ui
+                            .checkbox(&mut low_data_mode, egui::RichText::new("Low data").color(colors::TEXT_LIGHT))
+                            .on_hover_text("Skip avatars, images and previews; sync less often")
+                            .changed()
+                        {
+                            state.config.set_low_data_mode(low_data_mode);
+                        }
+                        ui.menu_button(egui::RichText::new("🔔").color(colors::TEXT_LIGHT), |ui| {
+                            let mut enabled = state.config.notifications_enabled();
+                            if ui.checkbox(&mut enabled, "Desktop notifications").changed() {
+                                state.config.set_notifications_enabled(enabled);
+                            }
+                            let mut sound = state.config.notification_sound();
+                            if ui.add_enabled(enabled, egui::Checkbox::new(&mut sound, "Sound")).changed() {
+                                state.config.set_notification_sound(sound);
+                            }
+                            ui.separator();
+                            let now = chrono::Utc::now();
+                            let paused_until = state.config.dnd_until().filter(|until| *until > now);
+                            let mut dnd = state.config.do_not_disturb() || paused_until.is_some();
+                            if ui.checkbox(&mut dnd, "Do not disturb").changed() {
+                                state.config.set_do_not_disturb(dnd);
+                            }
+                            if let Some(until) = paused_until {
+                                let local = until.with_timezone(&state.config.timezone());
+                                ui.colored_label(colors::TEXT_SECONDARY, format!("Paused until {}", local.format("%a %H:%M")));
+                            } else if ui.button("Pause until tomorrow").clicked() {
+                                state.config.dnd_until_tomorrow(now);
+                                ui.close();
+                            }
+                            let schedule = state.config.dnd_schedule().unwrap_or_default();
+                            let mut scheduled = state.config.dnd_schedule().is_some();
+                            let label = format!(
+                                "Every night ({}–{})",
+                                schedule.start.format("%H:%M"),
+                                schedule.end.format("%H:%M")
+                            );
+                            if ui.checkbox(&mut scheduled, label).changed() {
+                                state.config.set_dnd_schedule(scheduled.then_some(schedule));
+                            }
+                        });
+                        render_status_picker(ui, state);
                         if let Some(ref user) = state.auth_state.user {
                             ui.colored_label(colors::TEXT_LIGHT, format!("@{}", user.username));
                         }
@@ -59,6 +150,66 @@ pub fn render_top_bar(ctx: &egui::Context, state: &mut AppState, frame: &mut efr
         });
 }
 
+/// Menu for picking our status and status text
+fn render_status_picker(ui: &mut egui::Ui, state: &mut AppState) {
+    let (current, current_text) = state.messaging_state.own_status();
+    let current_text = current_text.map(str::to_string);
+    let title = egui::RichText::new(format!("● {}", current.label())).color(colors::status_color(current));
+    let response = ui.menu_button(title, |ui| {
+        for status in PresenceStatus::SELECTABLE {
+            let label = egui::RichText::new(format!("● {}", status.label())).color(colors::status_color(status));
+            if ui.selectable_label(status == current, label).clicked() {
+                state.set_status(status, current_text.clone());
+                ui.close();
+            }
+        }
+        ui.separator();
+        ui.add(
+            egui::TextEdit::singleline(&mut state.status_text_input)
+                .hint_text("What's your status?")
+                .desired_width(200.0),
+        );
+        ui.horizontal(|ui| {
+            if ui.button("Set").clicked() {
+                let text = Some(state.status_text_input.clone());
+                state.set_status(current, text);
+            }
+            if ui.add_enabled(current_text.is_some(), egui::Button::new("Clear")).clicked() {
+                state.status_text_input.clear();
+                state.set_status(current, None);
+            }
+        });
+        if let Some(ref error) = state.status_error {
+            ui.colored_label(colors::ERROR, error);
+        }
+    });
+    if let Some(text) = current_text {
+        response.response.on_hover_text(text);
+    }
+}
+
+/// First-run tips, shown once the initial import is done
+pub fn render_onboarding_tips(ctx: &egui::Context, state: &mut AppState) {
+    if state.onboarding.step() != OnboardingStep::ShowTips || !state.auth_state.authenticated {
+        return;
+    }
+
+    egui::Window::new("👋 Getting started")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(ctx, |ui| {
+            ui.label("• Add friends by username or email with the ➕ button in the sidebar");
+            ui.label("• Accept friend requests from 📬 to start chatting");
+            ui.label("• Messages you send offline are queued and sent when you reconnect");
+            ui.label("• Set a PIN from the 🔒 menu to lock the app");
+            ui.add_space(8.0);
+            if ui.button("Got it").clicked() {
+                state.advance_onboarding();
+            }
+        });
+}
+
 pub fn render_main_panel(ctx: &egui::Context, state: &mut AppState) {
     let frame = egui::Frame::default()
         .fill(colors::BG_DARK)
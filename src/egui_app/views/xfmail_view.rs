@@ -3,9 +3,12 @@ use eframe::egui;
 use crate::egui_app::AppView;
 use crate::egui_app::state::AppState;
 use crate::egui_app::messaging;
+use crate::egui_app::offline::QueueAction;
 
 /// Render the Telegram-style messaging view
 pub fn render_messaging(ui: &mut egui::Ui, state: &mut AppState) {
+    render_failed_messages_banner(ui, state);
+
     // Render the main messaging layout with config for API access
     messaging::main_layout::render_messaging_view(ui, &mut state.messaging_state, &state.config);
 }
@@ -26,3 +29,35 @@ pub fn render_xfcollab(ui: &mut egui::Ui, state: &mut AppState) {
     });
 }
 
+/// "N messages failed to send — tap to retry" while message sends sit in the
+/// dead-letter store
+fn render_failed_messages_banner(ui: &mut egui::Ui, state: &mut AppState) {
+    let inspector = &mut state.queue_inspector;
+    inspector.ensure_loaded();
+    inspector.poll();
+
+    let failed = inspector.failed_messages();
+    if failed == 0 {
+        return;
+    }
+
+    let noun = if failed == 1 { "message" } else { "messages" };
+    let response = egui::Frame::new()
+        .fill(egui::Color32::from_rgb(255, 238, 238))
+        .stroke(egui::Stroke::new(1.0, egui::Color32::from_rgb(200, 80, 80)))
+        .inner_margin(egui::Margin::symmetric(10, 8))
+        .show(ui, |ui| {
+            ui.set_width(ui.available_width());
+            ui.colored_label(
+                egui::Color32::from_rgb(160, 20, 20),
+                format!("{} {} failed to send — tap to retry", failed, noun),
+            );
+        })
+        .response
+        .interact(egui::Sense::click())
+        .on_hover_cursor(egui::CursorIcon::PointingHand);
+
+    if response.clicked() && !inspector.is_loading() {
+        inspector.send(QueueAction::RetryFailedMessages);
+    }
+}
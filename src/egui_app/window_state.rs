@@ -0,0 +1,216 @@
+//! Window State Persistence
+//!
+//! Remembers the native window's size, position, maximized state and the
+//! last-open conversation between launches. Stored as JSON in the platform
+//! config directory (`<config>/xfmail/window_state.json`).
+//!
+//! Positions are only restored when the window's title bar would land on the
+//! monitor it was saved on, so unplugging a second monitor can't strand the
+//! window off-screen.
+
+use std::path::{Path, PathBuf};
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Default inner size for a first launch
+pub const DEFAULT_SIZE: [f32; 2] = [1200.0, 800.0];
+
+/// Smallest inner size the window may have
+pub const MIN_SIZE: [f32; 2] = [800.0, 600.0];
+
+/// How much of the title bar must be on-screen to restore a position
+const VISIBLE_TITLE_BAR: [f32; 2] = [96.0, 32.0];
+
+/// Persisted window geometry and navigation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WindowState {
+    /// Inner size in points (the restored size when maximized)
+    pub size: [f32; 2],
+    /// Outer top-left position in points
+    pub position: Option<[f32; 2]>,
+    /// Whether the window was maximized
+    pub maximized: bool,
+    /// Size of the monitor the window was on
+    pub monitor_size: Option<[f32; 2]>,
+    /// Conversation open when the app closed
+    pub last_conversation_id: Option<Uuid>,
+}
+
+impl Default for WindowState {
+    fn default() -> Self {
+        Self {
+            size: DEFAULT_SIZE,
+            position: None,
+            maximized: false,
+            monitor_size: None,
+            last_conversation_id: None,
+        }
+    }
+}
+
+impl WindowState {
+    /// Default location of the state file
+    pub fn default_path() -> PathBuf {
+        let mut path = dirs::config_dir().unwrap_or_else(std::env::temp_dir);
+        path.push("xfmail");
+        path.push("window_state.json");
+        path
+    }
+
+    /// Load from the default location, falling back to defaults
+    pub fn load() -> Self {
+        Self::load_from(&Self::default_path())
+    }
+
+    /// Load from `path`; a missing or unreadable file gives the defaults
+    pub fn load_from(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    /// Save to the default location
+    pub fn save(&self) -> std::io::Result<()> {
+        self.save_to(&Self::default_path())
+    }
+
+    /// Save to `path`, creating parent directories
+    pub fn save_to(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        std::fs::write(path, json)
+    }
+
+    /// Inner size to open with, clamped to the minimum and the saved monitor
+    pub fn restored_size(&self) -> [f32; 2] {
+        let [mut width, mut height] = self.size;
+        if !width.is_finite() || !height.is_finite() {
+            return DEFAULT_SIZE;
+        }
+        if let Some([monitor_w, monitor_h]) = self.monitor_size {
+            width = width.min(monitor_w);
+            height = height.min(monitor_h);
+        }
+        [width.max(MIN_SIZE[0]), height.max(MIN_SIZE[1])]
+    }
+
+    /// Position to open at, or `None` if it would be off-screen
+    pub fn restored_position(&self) -> Option<[f32; 2]> {
+        let [x, y] = self.position?;
+        let [monitor_w, monitor_h] = self.monitor_size?;
+        if !x.is_finite() || !y.is_finite() {
+            return None;
+        }
+
+        // The title bar must be grabbable: its left part inside the monitor
+        let visible = x + VISIBLE_TITLE_BAR[0] <= monitor_w
+            && x + self.size[0] >= VISIBLE_TITLE_BAR[0]
+            && y >= 0.0
+            && y + VISIBLE_TITLE_BAR[1] <= monitor_h;
+        visible.then_some([x, y])
+    }
+
+    /// Record the current viewport
+    ///
+    /// Size and position are left alone while maximized so un-maximizing after
+    /// a restart returns to the previous geometry.
+    pub fn capture(&mut self, viewport: &egui::ViewportInfo) {
+        if let Some(maximized) = viewport.maximized {
+            self.maximized = maximized;
+        }
+        if let Some(monitor) = viewport.monitor_size {
+            self.monitor_size = Some([monitor.x, monitor.y]);
+        }
+        if self.maximized || viewport.minimized == Some(true) {
+            return;
+        }
+        if let Some(rect) = viewport.inner_rect {
+            self.size = [rect.width(), rect.height()];
+        }
+        if let Some(rect) = viewport.outer_rect {
+            self.position = Some([rect.min.x, rect.min.y]);
+        }
+    }
+
+    /// Viewport builder restoring this state
+    pub fn viewport_builder(&self) -> egui::ViewportBuilder {
+        let mut builder = egui::ViewportBuilder::default()
+            .with_inner_size(self.restored_size())
+            .with_min_inner_size(MIN_SIZE)
+            .with_maximized(self.maximized);
+        if let Some(position) = self.restored_position() {
+            builder = builder.with_position(position);
+        }
+        builder
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn saved() -> WindowState {
+        WindowState {
+            size: [1400.0, 900.0],
+            position: Some([120.0, 80.0]),
+            maximized: true,
+            monitor_size: Some([1920.0, 1080.0]),
+            last_conversation_id: Some(Uuid::new_v4()),
+        }
+    }
+
+    #[test]
+    fn test_geometry_round_trips() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("window_state.json");
+
+        let state = saved();
+        state.save_to(&path).unwrap();
+        let loaded = WindowState::load_from(&path);
+
+        assert_eq!(loaded, state);
+        assert_eq!(loaded.restored_size(), [1400.0, 900.0]);
+        assert_eq!(loaded.restored_position(), Some([120.0, 80.0]));
+    }
+
+    #[test]
+    fn test_missing_or_corrupt_file_gives_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("window_state.json");
+        assert_eq!(WindowState::load_from(&path), WindowState::default());
+
+        std::fs::write(&path, "not json").unwrap();
+        assert_eq!(WindowState::load_from(&path), WindowState::default());
+    }
+
+    #[test]
+    fn test_off_screen_position_is_not_restored() {
+        let mut state = saved();
+
+        // Was on a monitor to the right that is no longer there
+        state.position = Some([2500.0, 100.0]);
+        assert_eq!(state.restored_position(), None);
+
+        // Title bar above the top edge
+        state.position = Some([100.0, -200.0]);
+        assert_eq!(state.restored_position(), None);
+
+        // Unknown monitor
+        state.position = Some([100.0, 100.0]);
+        state.monitor_size = None;
+        assert_eq!(state.restored_position(), None);
+    }
+
+    #[test]
+    fn test_size_is_clamped() {
+        let mut state = saved();
+        state.size = [5000.0, 200.0];
+        assert_eq!(state.restored_size(), [1920.0, MIN_SIZE[1]]);
+    }
+}
@@ -53,9 +53,10 @@
 //!
 //! ```rust,no_run
 //! use xfmail::backend::server::init::create_app;
+//! use xfmail::backend::server::shutdown::Shutdown;
 //!
 //! # async fn example() {
-//! let app = create_app().await;
+//! let app = create_app(Shutdown::new()).await;
 //! // Use app with Axum server
 //! # }
 //! ```
@@ -8,6 +8,12 @@
 //! - `SerializationError` - JSON serialization/deserialization failures
 //! - `ValidationError` - Data validation failures
 //! - `MessageError` - Message-related errors
+//! - `NetworkError` - A request never got a response
+//! - `HttpError` - A request got a non-success HTTP status
+//!
+//! Error responses from the backend carry an [`ErrorBody`] whose
+//! [`ErrorCode`] tells failures with the same status apart, e.g. empty text
+//! from text that is too long.
 //!
 //! # Usage
 //!
@@ -21,6 +27,7 @@
 //! # Thread Safety
 //!
 //! All error types are `Send + Sync` and can be safely shared across thread boundaries.
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 /// Shared error types that can occur in both frontend and backend
@@ -48,6 +55,22 @@ pub enum SharedError {
         /// Human-readable error message
         message: String,
     },
+
+    /// Transport error (connection refused, timeout, ...)
+    #[error("Network error: {message}")]
+    NetworkError {
+        /// Human-readable error message
+        message: String,
+    },
+
+    /// Non-success HTTP response
+    #[error("HTTP {status}: {message}")]
+    HttpError {
+        /// HTTP status code
+        status: u16,
+        /// Response body, or the status text if it was empty
+        message: String,
+    },
 }
 
 impl SharedError {
@@ -72,6 +95,73 @@ impl SharedError {
             message: message.into(),
         }
     }
+
+    /// Create a new network error
+    pub fn network(message: impl Into<String>) -> Self {
+        Self::NetworkError {
+            message: message.into(),
+        }
+    }
+
+    /// Create a new HTTP error
+    pub fn http(status: u16, message: impl Into<String>) -> Self {
+        Self::HttpError {
+            status,
+            message: message.into(),
+        }
+    }
+
+    /// HTTP status code, for `HttpError`
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            Self::HttpError { status, .. } => Some(*status),
+            _ => None,
+        }
+    }
+}
+
+/// Machine-readable reason in an error response
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    /// The request body isn't a valid message
+    InvalidMessage,
+    EmptyText,
+    /// Text over the server's length limit
+    MessageTooLong,
+    /// Whole message over the server's size limit
+    MessageTooLarge,
+    EmptyAuthor,
+    AuthorTooLong,
+    /// A `Parents` version ID is malformed
+    InvalidParents,
+    Unauthorized,
+    /// Any other client error
+    BadRequest,
+    /// Any other server error
+    Internal,
+    /// A code this build doesn't know
+    #[serde(other)]
+    Unknown,
+}
+
+impl ErrorCode {
+    /// Generic code for a status that has no more specific one
+    pub fn for_status(status: u16) -> Self {
+        match status {
+            401 => Self::Unauthorized,
+            400..=499 => Self::BadRequest,
+            _ => Self::Internal,
+        }
+    }
+}
+
+/// JSON body of a backend error response
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorBody {
+    pub code: ErrorCode,
+    /// English description for logs; clients show their own text for `code`
+    pub message: String,
 }
 
 /// Helper trait for converting serialization errors
@@ -119,6 +209,27 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_http_error_status() {
+        let error = SharedError::http(404, "User not found");
+        assert_eq!(error.status(), Some(404));
+        assert_eq!(error.to_string(), "HTTP 404: User not found");
+        assert_eq!(SharedError::network("connection refused").status(), None);
+    }
+
+    #[test]
+    fn test_error_body_codes() {
+        let body: ErrorBody = serde_json::from_str(r#"{"code":"MESSAGE_TOO_LONG","message":"too long"}"#).unwrap();
+        assert_eq!(body.code, ErrorCode::MessageTooLong);
+
+        // Codes added later still parse
+        let body: ErrorBody = serde_json::from_str(r#"{"code":"SOMETHING_NEW","message":"?"}"#).unwrap();
+        assert_eq!(body.code, ErrorCode::Unknown);
+
+        assert_eq!(ErrorCode::for_status(401), ErrorCode::Unauthorized);
+        assert_eq!(ErrorCode::for_status(503), ErrorCode::Internal);
+    }
+
     #[test]
     fn test_error_display() {
         let error = SharedError::serialization("Test error");
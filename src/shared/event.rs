@@ -19,10 +19,237 @@ pub enum EventType {
     Status,
     /// Typing indicator event
     Typing,
+    /// A participant read a message
+    ReadReceipt,
+    /// A message reached a participant's device
+    DeliveryReceipt,
+    /// A user came online or went offline
+    Presence,
+    /// A conversation was created, changed or left, for one user
+    Conversation,
     /// Custom event type
     Custom(String),
 }
 
+impl EventType {
+    /// Whether events of this type are transient and must never be stored
+    ///
+    /// Ephemeral events only mean something to whoever is connected when
+    /// they're broadcast (typing, presence); replaying them later would be
+    /// wrong. Receipts are ephemeral too: the state they announce is stored
+    /// on the message itself. Custom events are ephemeral unless given a
+    /// variant of their own, so new kinds are never stored by accident.
+    pub fn is_ephemeral(&self) -> bool {
+        match self {
+            EventType::Message | EventType::Notification => false,
+            EventType::Status
+            | EventType::Typing
+            | EventType::ReadReceipt
+            | EventType::DeliveryReceipt
+            | EventType::Presence
+            | EventType::Conversation
+            | EventType::Custom(_) => true,
+        }
+    }
+
+    /// Name of the type on the wire, e.g. the SSE event name
+    pub fn name(&self) -> &str {
+        match self {
+            EventType::Message => "message",
+            EventType::Notification => "notification",
+            EventType::Status => "status",
+            EventType::Typing => "typing",
+            EventType::ReadReceipt => "read_receipt",
+            EventType::DeliveryReceipt => "delivery_receipt",
+            EventType::Presence => "presence",
+            EventType::Conversation => "conversation",
+            EventType::Custom(name) => name.as_str(),
+        }
+    }
+
+    /// Parse a `types` filter such as `typing,read_receipt`
+    ///
+    /// Unknown names are taken as custom types. `None` if the list names no
+    /// types at all, meaning every type.
+    pub fn parse_list(types: &str) -> Option<Vec<EventType>> {
+        let types: Vec<EventType> = types
+            .split(',')
+            .map(|s| s.trim().to_lowercase())
+            .filter(|s| !s.is_empty())
+            .map(|s| match s.as_str() {
+                "message" => EventType::Message,
+                "notification" => EventType::Notification,
+                "status" => EventType::Status,
+                "typing" => EventType::Typing,
+                "read_receipt" => EventType::ReadReceipt,
+                "delivery_receipt" => EventType::DeliveryReceipt,
+                "presence" => EventType::Presence,
+                "conversation" => EventType::Conversation,
+                _ => EventType::Custom(s),
+            })
+            .collect();
+        (!types.is_empty()).then_some(types)
+    }
+}
+
+/// What a user is doing in the composer, carried by typing events
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ActivityKind {
+    /// Typing a text message
+    #[default]
+    Typing,
+    /// Recording a voice message
+    RecordingAudio,
+    /// Uploading an attachment
+    UploadingFile,
+}
+
+impl ActivityKind {
+    /// Indicator text shown to other participants, e.g. "Alice is recording audio…"
+    pub fn indicator(&self, user: &str) -> String {
+        let verb = match self {
+            ActivityKind::Typing => "typing",
+            ActivityKind::RecordingAudio => "recording audio",
+            ActivityKind::UploadingFile => "uploading a file",
+        };
+        format!("{} is {}…", user, verb)
+    }
+}
+
+/// Payload of an [`EventType::Typing`] event
+///
+/// `kind` defaults to [`ActivityKind::Typing`] so payloads from older
+/// clients still parse.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ActivityEvent {
+    /// Username of the active user
+    pub user: String,
+    /// Whether the activity started (true) or stopped (false)
+    pub is_typing: bool,
+    /// Which activity this is
+    #[serde(default)]
+    pub kind: ActivityKind,
+    /// Conversation the activity belongs to, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conversation_id: Option<uuid::Uuid>,
+    /// Authenticated sender, set by the per-conversation typing endpoint
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sender_id: Option<uuid::Uuid>,
+    /// Seconds the indicator stays up without a refresh
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl_secs: Option<u64>,
+}
+
+impl ActivityEvent {
+    /// Parse the payload of a typing event; `None` for other event types
+    pub fn from_event(event: &RealtimeEvent) -> Option<Self> {
+        if event.event_type != EventType::Typing {
+            return None;
+        }
+        serde_json::from_value(event.payload.clone()).ok()
+    }
+}
+
+/// Payload of an [`EventType::ReadReceipt`] event
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ReadReceiptEvent {
+    pub conversation_id: uuid::Uuid,
+    pub message_id: uuid::Uuid,
+    /// Participant who read the message
+    pub reader_id: uuid::Uuid,
+    /// When the message was marked read (RFC3339)
+    pub read_at: String,
+}
+
+impl ReadReceiptEvent {
+    /// Parse the payload of a read-receipt event; `None` for other event types
+    pub fn from_event(event: &RealtimeEvent) -> Option<Self> {
+        if event.event_type != EventType::ReadReceipt {
+            return None;
+        }
+        serde_json::from_value(event.payload.clone()).ok()
+    }
+}
+
+/// Payload of an [`EventType::DeliveryReceipt`] event
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DeliveryReceiptEvent {
+    pub conversation_id: uuid::Uuid,
+    pub message_id: uuid::Uuid,
+    /// Participant whose device received the message
+    pub recipient_id: uuid::Uuid,
+    /// When the message was received (RFC3339)
+    pub delivered_at: String,
+}
+
+impl DeliveryReceiptEvent {
+    /// Parse the payload of a delivery-receipt event; `None` for other event types
+    pub fn from_event(event: &RealtimeEvent) -> Option<Self> {
+        if event.event_type != EventType::DeliveryReceipt {
+            return None;
+        }
+        serde_json::from_value(event.payload.clone()).ok()
+    }
+}
+
+/// How often clients send a presence heartbeat
+///
+/// The server treats a user as offline after missing a few of these.
+pub const PRESENCE_HEARTBEAT_SECS: u64 = 30;
+
+/// Payload of an [`EventType::Presence`] event
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PresenceEvent {
+    pub user_id: uuid::Uuid,
+    pub is_online: bool,
+    /// Last heartbeat from the user (RFC3339)
+    pub last_seen: String,
+}
+
+impl PresenceEvent {
+    /// Parse the payload of a presence event; `None` for other event types
+    pub fn from_event(event: &RealtimeEvent) -> Option<Self> {
+        if event.event_type != EventType::Presence {
+            return None;
+        }
+        serde_json::from_value(event.payload.clone()).ok()
+    }
+}
+
+/// Payload of an [`EventType::Conversation`] event
+///
+/// Each participant gets their own event, carrying the conversation as
+/// they see it (their unread count, pin, theme).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum ConversationEvent {
+    /// The user is in a new conversation (e.g. a DM after a friend accept)
+    Created { conversation: crate::shared::messaging::Conversation },
+    /// A conversation the user is in changed, e.g. its members
+    Updated { conversation: crate::shared::messaging::Conversation },
+    /// The user is no longer in the conversation
+    Deleted { conversation_id: uuid::Uuid },
+}
+
+impl ConversationEvent {
+    /// Parse the payload of a conversation event; `None` for other event types
+    pub fn from_event(event: &RealtimeEvent) -> Option<Self> {
+        if event.event_type != EventType::Conversation {
+            return None;
+        }
+        serde_json::from_value(event.payload.clone()).ok()
+    }
+
+    /// Conversation the event is about
+    pub fn conversation_id(&self) -> uuid::Uuid {
+        match self {
+            Self::Created { conversation } | Self::Updated { conversation } => conversation.id,
+            Self::Deleted { conversation_id } => *conversation_id,
+        }
+    }
+}
+
 /// Real-time event that can be broadcast to all subscribers
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct RealtimeEvent {
@@ -76,21 +303,75 @@ impl RealtimeEvent {
     
     /// Create a typing event
     pub fn typing(user: String, is_typing: bool) -> Self {
+        Self::activity(ActivityEvent {
+            user,
+            is_typing,
+            kind: ActivityKind::Typing,
+            conversation_id: None,
+            sender_id: None,
+            ttl_secs: None,
+        })
+    }
+
+    /// Create a typing event for any [`ActivityKind`]
+    pub fn activity(activity: ActivityEvent) -> Self {
         Self::new(
             EventType::Typing,
-            serde_json::json!({
-                "user": user,
-                "is_typing": is_typing,
-            }),
+            serde_json::to_value(activity).unwrap_or_default(),
         )
     }
     
+    /// Create a read-receipt event
+    pub fn read_receipt(receipt: ReadReceiptEvent) -> Self {
+        Self::new(
+            EventType::ReadReceipt,
+            serde_json::to_value(receipt).unwrap_or_default(),
+        )
+    }
+
+    /// Create a delivery-receipt event
+    pub fn delivery_receipt(receipt: DeliveryReceiptEvent) -> Self {
+        Self::new(
+            EventType::DeliveryReceipt,
+            serde_json::to_value(receipt).unwrap_or_default(),
+        )
+    }
+
+    /// Create a presence event
+    pub fn presence(presence: PresenceEvent) -> Self {
+        Self::new(
+            EventType::Presence,
+            serde_json::to_value(presence).unwrap_or_default(),
+        )
+    }
+
+    /// Create a conversation event
+    pub fn conversation(change: ConversationEvent) -> Self {
+        Self::new(
+            EventType::Conversation,
+            serde_json::to_value(change).unwrap_or_default(),
+        )
+    }
+
     /// Create a message event from a Message struct
     pub fn new_message_event(message: &crate::shared::message::Message) -> Self {
         let payload = serde_json::to_value(message).unwrap();
         Self::message(payload)
     }
     
+    /// Conversation the event belongs to, for payloads that carry one
+    pub fn conversation_id(&self) -> Option<uuid::Uuid> {
+        self.payload
+            .get("conversation_id")
+            .and_then(|id| id.as_str())
+            .and_then(|id| uuid::Uuid::parse_str(id).ok())
+    }
+
+    /// Whether this event must never be stored; see [`EventType::is_ephemeral`]
+    pub fn is_ephemeral(&self) -> bool {
+        self.event_type.is_ephemeral()
+    }
+
     /// Set the version ID
     pub fn with_version(mut self, version: String) -> Self {
         self.version = Some(version);
@@ -157,6 +438,110 @@ mod tests {
         assert_eq!(event.payload["is_typing"], true);
     }
 
+    #[test]
+    fn test_activity_kinds_round_trip_and_render() {
+        let cases = [
+            (ActivityKind::Typing, "Alice is typing…"),
+            (ActivityKind::RecordingAudio, "Alice is recording audio…"),
+            (ActivityKind::UploadingFile, "Alice is uploading a file…"),
+        ];
+        for (kind, expected) in cases {
+            let event = RealtimeEvent::activity(ActivityEvent {
+                user: "Alice".to_string(),
+                is_typing: true,
+                kind,
+                conversation_id: None,
+                sender_id: None,
+                ttl_secs: None,
+            });
+            let json = serde_json::to_string(&event).unwrap();
+            let decoded: RealtimeEvent = serde_json::from_str(&json).unwrap();
+            let activity = ActivityEvent::from_event(&decoded).unwrap();
+            assert_eq!(activity.kind, kind);
+            assert_eq!(activity.kind.indicator(&activity.user), expected);
+        }
+    }
+
+    #[test]
+    fn test_activity_kind_defaults_to_typing() {
+        let event = RealtimeEvent::new(
+            EventType::Typing,
+            serde_json::json!({"user": "Bob", "is_typing": true}),
+        );
+        let activity = ActivityEvent::from_event(&event).unwrap();
+        assert_eq!(activity.kind, ActivityKind::Typing);
+    }
+
+    #[test]
+    fn test_parse_type_list() {
+        assert_eq!(
+            EventType::parse_list("typing, Read_Receipt,poll"),
+            Some(vec![EventType::Typing, EventType::ReadReceipt, EventType::Custom("poll".to_string())])
+        );
+        assert_eq!(EventType::parse_list(" , "), None);
+        assert_eq!(EventType::DeliveryReceipt.name(), "delivery_receipt");
+    }
+
+    #[test]
+    fn test_delivery_receipt_is_not_a_read_receipt() {
+        let receipt = DeliveryReceiptEvent {
+            conversation_id: uuid::Uuid::new_v4(),
+            message_id: uuid::Uuid::new_v4(),
+            recipient_id: uuid::Uuid::new_v4(),
+            delivered_at: "2024-01-01T12:00:00Z".to_string(),
+        };
+        let event = RealtimeEvent::delivery_receipt(receipt.clone());
+        assert_eq!(serde_json::to_value(&event.event_type).unwrap(), "delivery_receipt");
+        assert_eq!(DeliveryReceiptEvent::from_event(&event), Some(receipt));
+        assert_eq!(ReadReceiptEvent::from_event(&event), None);
+    }
+
+    #[test]
+    fn test_read_receipt_round_trip() {
+        let receipt = ReadReceiptEvent {
+            conversation_id: uuid::Uuid::new_v4(),
+            message_id: uuid::Uuid::new_v4(),
+            reader_id: uuid::Uuid::new_v4(),
+            read_at: "2024-01-01T12:00:00Z".to_string(),
+        };
+        let event = RealtimeEvent::read_receipt(receipt.clone());
+        assert_eq!(serde_json::to_value(&event.event_type).unwrap(), "read_receipt");
+
+        let json = serde_json::to_string(&event).unwrap();
+        let decoded: RealtimeEvent = serde_json::from_str(&json).unwrap();
+        assert_eq!(ReadReceiptEvent::from_event(&decoded), Some(receipt));
+        assert_eq!(ActivityEvent::from_event(&decoded), None);
+    }
+
+    #[test]
+    fn test_presence_round_trip() {
+        let presence = PresenceEvent {
+            user_id: uuid::Uuid::new_v4(),
+            is_online: true,
+            last_seen: "2024-01-01T12:00:00Z".to_string(),
+        };
+        let event = RealtimeEvent::presence(presence.clone());
+        assert_eq!(serde_json::to_value(&event.event_type).unwrap(), "presence");
+
+        let json = serde_json::to_string(&event).unwrap();
+        let decoded: RealtimeEvent = serde_json::from_str(&json).unwrap();
+        assert_eq!(PresenceEvent::from_event(&decoded), Some(presence));
+        assert_eq!(ReadReceiptEvent::from_event(&decoded), None);
+    }
+
+    #[test]
+    fn test_only_messages_and_notifications_persist() {
+        assert!(!RealtimeEvent::message(serde_json::json!({})).is_ephemeral());
+        assert!(!RealtimeEvent::notification("Title".to_string(), "Body".to_string()).is_ephemeral());
+
+        assert!(RealtimeEvent::typing("user1".to_string(), true).is_ephemeral());
+        assert!(RealtimeEvent::status("online".to_string(), None).is_ephemeral());
+        assert!(EventType::Presence.is_ephemeral());
+        assert!(EventType::ReadReceipt.is_ephemeral());
+        assert!(EventType::DeliveryReceipt.is_ephemeral());
+        assert!(EventType::Custom("cursor".to_string()).is_ephemeral());
+    }
+
     #[test]
     fn test_event_with_version() {
         let event = RealtimeEvent::new(EventType::Message, serde_json::json!({}))
@@ -0,0 +1,99 @@
+//! Braid Merge Types
+//!
+//! A subscriber can name the merge type it understands in a `Merge-Type`
+//! request header (draft-toomim-httpbis-braid-http-04, Section 2.2). The
+//! server echoes the negotiated type back, or answers 406 Not Acceptable if
+//! it can't speak it.
+//!
+//! For the chat stream, `sync9` and `simpleton` receive the JSON message
+//! array on each update, while `diamond-types` receives diamond-types
+//! encoded patches containing only what changed since the previous update.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// Request/response header carrying the merge type
+pub const MERGE_TYPE_HEADER: &str = "Merge-Type";
+
+/// A merge type supported by the server
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MergeType {
+    Sync9,
+    Simpleton,
+    DiamondTypes,
+}
+
+impl MergeType {
+    /// Header value for this merge type
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Sync9 => "sync9",
+            Self::Simpleton => "simpleton",
+            Self::DiamondTypes => "diamond-types",
+        }
+    }
+
+    /// Whether updates are delivered as incremental patches
+    pub fn streams_patches(self) -> bool {
+        matches!(self, Self::DiamondTypes)
+    }
+}
+
+impl fmt::Display for MergeType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The requested merge type isn't supported
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnsupportedMergeType(pub String);
+
+impl fmt::Display for UnsupportedMergeType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unsupported merge type: {}", self.0)
+    }
+}
+
+impl std::error::Error for UnsupportedMergeType {}
+
+impl FromStr for MergeType {
+    type Err = UnsupportedMergeType;
+
+    /// Parse a header value; case-insensitive and tolerant of Structured
+    /// Headers quoting (`"sync9"`)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let value = s.trim().trim_matches('"').to_ascii_lowercase();
+        match value.as_str() {
+            "sync9" => Ok(Self::Sync9),
+            "simpleton" => Ok(Self::Simpleton),
+            "diamond-types" | "dt" => Ok(Self::DiamondTypes),
+            _ => Err(UnsupportedMergeType(s.trim().to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_merge_types() {
+        assert_eq!("sync9".parse(), Ok(MergeType::Sync9));
+        assert_eq!(" \"Simpleton\" ".parse(), Ok(MergeType::Simpleton));
+        assert_eq!("diamond-types".parse(), Ok(MergeType::DiamondTypes));
+        assert_eq!(
+            "ot".parse::<MergeType>(),
+            Err(UnsupportedMergeType("ot".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_round_trip_and_patches() {
+        for merge_type in [MergeType::Sync9, MergeType::Simpleton, MergeType::DiamondTypes] {
+            assert_eq!(merge_type.as_str().parse(), Ok(merge_type));
+        }
+        assert!(MergeType::DiamondTypes.streams_patches());
+        assert!(!MergeType::Simpleton.streams_patches());
+    }
+}
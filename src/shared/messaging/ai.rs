@@ -0,0 +1,89 @@
+//! AI Message Actions
+//!
+//! Request and response types for AI features: translating a single
+//! message and summarizing part of a conversation.
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Longest locale tag accepted, e.g. `zh-Hant-TW`
+const MAX_LOCALE_LEN: usize = 35;
+
+/// Request to translate a message for the requesting user
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranslateMessageRequest {
+    pub conversation_id: Uuid,
+    pub message_id: Uuid,
+    /// BCP 47 language tag to translate into, e.g. `de` or `pt-BR`
+    pub target_locale: String,
+}
+
+/// A translated message; only returned to the requester, never stored
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TranslateMessageResponse {
+    pub message_id: Uuid,
+    pub target_locale: String,
+    pub translation: String,
+}
+
+/// Request to summarize a range of a conversation's messages
+///
+/// Both ends are inclusive. The summary is streamed back as plain text;
+/// an empty range gets `204 No Content`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SummarizeConversationRequest {
+    pub conversation_id: Uuid,
+    /// First message to include; `None` starts at the oldest
+    #[serde(default)]
+    pub from_message_id: Option<Uuid>,
+    /// Last message to include; `None` runs to the newest
+    #[serde(default)]
+    pub to_message_id: Option<Uuid>,
+}
+
+/// Turn a locale into a BCP 47 tag
+///
+/// Accepts POSIX locales as found in `LANG` (`pt_BR.UTF-8` becomes `pt-BR`).
+/// Returns `None` for `C`/`POSIX` and anything that isn't a language tag.
+pub fn normalize_locale(locale: &str) -> Option<String> {
+    let tag = locale
+        .split(['.', '@'])
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .replace('_', "-");
+
+    if tag.is_empty() || tag.len() > MAX_LOCALE_LEN || tag.eq_ignore_ascii_case("c") || tag.eq_ignore_ascii_case("posix") {
+        return None;
+    }
+
+    let mut subtags = tag.split('-');
+    let language = subtags.next()?;
+    if !(2..=3).contains(&language.len()) || !language.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    if !subtags.all(|s| (1..=8).contains(&s.len()) && s.chars().all(|c| c.is_ascii_alphanumeric())) {
+        return None;
+    }
+
+    Some(tag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_locale() {
+        assert_eq!(normalize_locale("de").as_deref(), Some("de"));
+        assert_eq!(normalize_locale("pt_BR.UTF-8").as_deref(), Some("pt-BR"));
+        assert_eq!(normalize_locale("sr_RS@latin").as_deref(), Some("sr-RS"));
+        assert_eq!(normalize_locale("zh-Hant-TW").as_deref(), Some("zh-Hant-TW"));
+
+        assert_eq!(normalize_locale("C.UTF-8"), None);
+        assert_eq!(normalize_locale("POSIX"), None);
+        assert_eq!(normalize_locale(""), None);
+        assert_eq!(normalize_locale("english please"), None);
+        assert_eq!(normalize_locale("de-; drop table"), None);
+    }
+}
@@ -0,0 +1,39 @@
+//! Attachment Data Structures
+//!
+//! Request/response types for uploading and downloading message attachments
+//! and user avatars.
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Response after uploading an attachment
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct UploadAttachmentResponse {
+    /// Attachment ID (used in the download URL)
+    pub id: Uuid,
+    /// Server-relative download URL (`/api/attachments/{id}`)
+    pub url: String,
+    /// Size in bytes
+    pub size: u64,
+    /// MIME type as stored
+    pub mime_type: String,
+}
+
+/// Response after uploading an avatar
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct UploadAvatarResponse {
+    /// Server-relative URL of the new avatar, see [`avatar_url`]
+    pub url: String,
+    /// Size in bytes
+    pub size: u64,
+    /// MIME type as stored
+    pub mime_type: String,
+}
+
+/// Server-relative URL of a user's avatar
+///
+/// `version` changes whenever the avatar does, so clients can cache each
+/// URL indefinitely.
+pub fn avatar_url(user_id: Uuid, version: i64) -> String {
+    format!("/api/users/{}/avatar?v={}", user_id, version)
+}
@@ -5,7 +5,6 @@
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-#[cfg(feature = "ssr")]
 use chrono::{DateTime, Utc};
 
 /// Represents a contact (friend) in the messaging system
@@ -23,25 +22,18 @@ pub struct Contact {
     pub email: String,
     /// Optional display name
     pub display_name: Option<String>,
-    /// Optional avatar URL
+    /// Server-relative avatar URL, if the contact uploaded one
     pub avatar_url: Option<String>,
     /// Last seen timestamp
-    #[cfg(feature = "ssr")]
     pub last_seen: DateTime<Utc>,
-    #[cfg(not(feature = "ssr"))]
-    pub last_seen: String,
     /// Whether the contact is currently online
     pub is_online: bool,
     /// When the contact was added
-    #[cfg(feature = "ssr")]
     pub created_at: DateTime<Utc>,
-    #[cfg(not(feature = "ssr"))]
-    pub created_at: String,
 }
 
 impl Contact {
     /// Create a new contact
-    #[cfg(feature = "ssr")]
     pub fn new(
         user_id: Uuid,
         contact_user_id: Uuid,
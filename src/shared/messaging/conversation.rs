@@ -2,10 +2,13 @@
 //!
 //! Represents a conversation between two or more users.
 
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
 use uuid::Uuid;
 
 use super::message::ChatMessage;
+use super::timestamp;
 
 /// Represents a conversation between users
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -14,18 +17,38 @@ pub struct Conversation {
     pub id: Uuid,
     /// Participant user IDs
     pub participants: Vec<Uuid>,
-    /// Username of the other participant (for display in chat list)
+    /// Group name; `None` for direct chats and unnamed groups
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Username of the other participant in a direct chat (for display in chat list)
     pub other_username: Option<String>,
+    /// Usernames of the other members, for titling unnamed groups
+    #[serde(default)]
+    pub member_usernames: Vec<String>,
     /// Last message in the conversation (for preview)
     pub last_message: Option<ChatMessage>,
     /// Preview text of last message
     pub last_message_preview: String,
-    /// Timestamp of last message (RFC3339 string)
-    pub last_message_time: Option<String>,
+    /// Timestamp of last message
+    #[serde(default, with = "timestamp::option")]
+    pub last_message_time: Option<DateTime<Utc>>,
     /// Number of unread messages
     pub unread_count: u32,
-    /// When the conversation was created (RFC3339 string)
-    pub created_at: String,
+    /// Set by the user's "mark as unread" action; cleared when opened
+    #[serde(default)]
+    pub manually_unread: bool,
+    /// The user's own color for this chat; `None` uses the global theme
+    #[serde(default)]
+    pub theme_color: Option<ThemeColor>,
+    /// Pinned to the top of the user's sidebar
+    #[serde(default)]
+    pub pinned: bool,
+    /// Position among the user's pinned conversations (lowest first)
+    #[serde(default)]
+    pub pin_order: i32,
+    /// When the conversation was created
+    #[serde(with = "timestamp")]
+    pub created_at: DateTime<Utc>,
 }
 
 impl Conversation {
@@ -34,12 +57,18 @@ impl Conversation {
         Self {
             id: Uuid::new_v4(),
             participants,
+            name: None,
             other_username: None,
+            member_usernames: Vec::new(),
             last_message: None,
             last_message_preview: String::new(),
             last_message_time: None,
             unread_count: 0,
-            created_at: chrono::Utc::now().to_rfc3339(),
+            manually_unread: false,
+            theme_color: None,
+            pinned: false,
+            pin_order: 0,
+            created_at: Utc::now(),
         }
     }
 
@@ -51,10 +80,61 @@ impl Conversation {
     /// Update the last message
     pub fn update_last_message(&mut self, message: &ChatMessage, preview_len: usize) {
         self.last_message_preview = message.preview(preview_len);
-        self.last_message_time = Some(message.timestamp.clone());
+        self.last_message_time = Some(message.timestamp);
         self.last_message = Some(message.clone());
     }
 
+    /// Sidebar badge text, if any
+    ///
+    /// A manually-unread conversation always shows a badge, even with no
+    /// unread messages.
+    pub fn unread_badge(&self) -> Option<String> {
+        match (self.unread_count, self.manually_unread) {
+            (0, false) => None,
+            (0, true) => Some("•".to_string()),
+            (count, _) => Some(count.to_string()),
+        }
+    }
+
+    /// Compare sidebar positions by pinning alone
+    ///
+    /// Pinned conversations come first, in `pin_order`. Returns `Equal` for
+    /// two unpinned conversations, which are ordered by recency instead.
+    pub fn pin_cmp(&self, other: &Self) -> Ordering {
+        match (self.pinned, other.pinned) {
+            (true, true) => self.pin_order.cmp(&other.pin_order),
+            (true, false) => Ordering::Less,
+            (false, true) => Ordering::Greater,
+            (false, false) => Ordering::Equal,
+        }
+    }
+
+    /// Whether this is a group chat rather than a direct message
+    ///
+    /// Named conversations count as groups even while they have only two
+    /// members, so they keep their title when people leave.
+    pub fn is_group(&self) -> bool {
+        self.participants.len() > 2 || self.name.is_some()
+    }
+
+    /// Title shown in the sidebar and chat header
+    ///
+    /// Unnamed groups are titled after their members.
+    pub fn display_title(&self) -> String {
+        if let Some(name) = self.name.as_deref().filter(|n| !n.trim().is_empty()) {
+            return name.to_string();
+        }
+        if !self.is_group() {
+            if let Some(username) = &self.other_username {
+                return username.clone();
+            }
+        }
+        if self.member_usernames.is_empty() {
+            return "Group chat".to_string();
+        }
+        self.member_usernames.join(", ")
+    }
+
     /// Check if user is a participant
     pub fn has_participant(&self, user_id: Uuid) -> bool {
         self.participants.contains(&user_id)
@@ -69,6 +149,52 @@ impl Conversation {
     }
 }
 
+/// A per-conversation theme color, chosen by one user
+///
+/// Serialized as a `#rrggbb` string, which is also how it is stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct ThemeColor {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl ThemeColor {
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+
+    /// Parse a `#rrggbb` string
+    pub fn from_hex(hex: &str) -> Option<Self> {
+        let digits = hex.strip_prefix('#')?;
+        if digits.len() != 6 || !digits.is_ascii() {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(&digits[i..i + 2], 16).ok();
+        Some(Self::new(channel(0)?, channel(2)?, channel(4)?))
+    }
+
+    /// Format as a lowercase `#rrggbb` string
+    pub fn to_hex(self) -> String {
+        format!("#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+}
+
+impl TryFrom<String> for ThemeColor {
+    type Error = String;
+
+    fn try_from(hex: String) -> Result<Self, Self::Error> {
+        Self::from_hex(&hex).ok_or_else(|| format!("Invalid theme color: {}", hex))
+    }
+}
+
+impl From<ThemeColor> for String {
+    fn from(color: ThemeColor) -> Self {
+        color.to_hex()
+    }
+}
+
 /// Response for listing conversations
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ListConversationsResponse {
@@ -78,7 +204,37 @@ pub struct ListConversationsResponse {
 /// Request to create a conversation
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateConversationRequest {
+    /// Other members; the creator is added automatically
     pub participant_ids: Vec<Uuid>,
+    /// Group name; leave unset to title the group after its members
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+/// Request to set or clear the manual unread marker
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarkConversationUnreadRequest {
+    pub unread: bool,
+}
+
+/// Request to set or clear a conversation's theme color
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetConversationThemeRequest {
+    /// `None` resets the conversation to the global theme
+    pub color: Option<ThemeColor>,
+}
+
+/// Request to pin or unpin a conversation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PinConversationRequest {
+    pub pinned: bool,
+}
+
+/// Request to reorder the user's pinned conversations
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReorderPinnedConversationsRequest {
+    /// Pinned conversations, top of the sidebar first
+    pub conversation_ids: Vec<Uuid>,
 }
 
 /// Response after creating a conversation
@@ -89,3 +245,73 @@ pub struct CreateConversationResponse {
     pub error: Option<String>,
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manual_unread_shows_badge_without_unread_messages() {
+        let mut conversation = Conversation::new_direct(Uuid::new_v4(), Uuid::new_v4());
+        assert_eq!(conversation.unread_badge(), None);
+
+        conversation.manually_unread = true;
+        assert_eq!(conversation.unread_count, 0);
+        assert_eq!(conversation.unread_badge().as_deref(), Some("•"));
+
+        conversation.unread_count = 3;
+        assert_eq!(conversation.unread_badge().as_deref(), Some("3"));
+    }
+
+    #[test]
+    fn test_theme_color_hex_round_trip() {
+        let color = ThemeColor::new(0x1e, 0x90, 0xff);
+        assert_eq!(color.to_hex(), "#1e90ff");
+        assert_eq!(ThemeColor::from_hex("#1E90FF"), Some(color));
+
+        assert_eq!(ThemeColor::from_hex("1e90ff"), None);
+        assert_eq!(ThemeColor::from_hex("#1e90f"), None);
+        assert_eq!(ThemeColor::from_hex("#1e90fg"), None);
+
+        let json = serde_json::to_string(&SetConversationThemeRequest { color: Some(color) }).unwrap();
+        assert_eq!(json, r##"{"color":"#1e90ff"}"##);
+        let parsed: SetConversationThemeRequest = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.color, Some(color));
+        assert!(serde_json::from_str::<SetConversationThemeRequest>(r#"{"color":"blue"}"#).is_err());
+    }
+
+    #[test]
+    fn test_pin_cmp_orders_pinned_first() {
+        let mut first = Conversation::new_direct(Uuid::new_v4(), Uuid::new_v4());
+        let mut second = first.clone();
+        let unpinned = first.clone();
+        first.pinned = true;
+        first.pin_order = 0;
+        second.pinned = true;
+        second.pin_order = 1;
+
+        assert_eq!(first.pin_cmp(&second), Ordering::Less);
+        assert_eq!(second.pin_cmp(&unpinned), Ordering::Less);
+        assert_eq!(unpinned.pin_cmp(&first), Ordering::Greater);
+        assert_eq!(unpinned.pin_cmp(&unpinned.clone()), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_display_title_for_direct_and_group_chats() {
+        let mut direct = Conversation::new_direct(Uuid::new_v4(), Uuid::new_v4());
+        direct.other_username = Some("alice".to_string());
+        direct.member_usernames = vec!["alice".to_string()];
+        assert!(!direct.is_group());
+        assert_eq!(direct.display_title(), "alice");
+
+        let mut group = Conversation::new(vec![Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4()]);
+        assert!(group.is_group());
+        assert_eq!(group.display_title(), "Group chat");
+
+        group.member_usernames = vec!["alice".to_string(), "bob".to_string()];
+        assert_eq!(group.display_title(), "alice, bob");
+
+        group.name = Some("Weekend plans".to_string());
+        assert_eq!(group.display_title(), "Weekend plans");
+    }
+}
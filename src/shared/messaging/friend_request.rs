@@ -5,7 +5,6 @@
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-#[cfg(feature = "ssr")]
 use chrono::{DateTime, Utc};
 
 /// Status of a friend request
@@ -70,20 +69,13 @@ pub struct FriendRequest {
     #[serde(default)]
     pub status: FriendRequestStatus,
     /// When the request was created
-    #[cfg(feature = "ssr")]
     pub created_at: DateTime<Utc>,
-    #[cfg(not(feature = "ssr"))]
-    pub created_at: String,
     /// When the request was responded to
-    #[cfg(feature = "ssr")]
     pub responded_at: Option<DateTime<Utc>>,
-    #[cfg(not(feature = "ssr"))]
-    pub responded_at: Option<String>,
 }
 
 impl FriendRequest {
     /// Create a new friend request
-    #[cfg(feature = "ssr")]
     pub fn new(
         from_user_id: Uuid,
         to_user_id: Uuid,
@@ -116,7 +108,15 @@ impl FriendRequest {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SendFriendRequestRequest {
     /// Email of the user to send request to
+    #[serde(default)]
     pub to_email: String,
+    /// User ID to send the request to; takes precedence over `to_email`
+    /// (queued offline operations only know the user ID)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to_user_id: Option<Uuid>,
+    /// Optional note shown to the recipient
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
 }
 
 /// Response after sending a friend request
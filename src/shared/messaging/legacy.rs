@@ -0,0 +1,161 @@
+//! Legacy Message Adapter
+//!
+//! Conversions between the legacy `/chat` [`Message`] and the messaging
+//! system's [`ChatMessage`], so both paths can interoperate during migration.
+//!
+//! # Field Mapping
+//!
+//! | `Message`   | `ChatMessage`      | Notes                                        |
+//! |-------------|--------------------|----------------------------------------------|
+//! | `text`      | `content`          | 1:1                                          |
+//! | `author`    | `sender_id`        | author must be a UUID string to convert back |
+//! | `timestamp` | `timestamp`        | RFC3339 in both                              |
+//! | `version`   | `braid_version`    | empty `braid_version` maps to `None`         |
+//! | -           | `conversation_id`  | not present in `Message`; caller supplies it |
+//! | -           | `id`               | freshly generated when converting from legacy|
+//!
+//! Everything else (`is_read`, CRDT metadata, parents) has no legacy
+//! equivalent and is dropped or defaulted.
+//!
+//! # Usage
+//!
+//! ```rust
+//! use xfmail::shared::Message;
+//! use xfmail::shared::messaging::ChatMessage;
+//! use uuid::Uuid;
+//!
+//! let sender = Uuid::new_v4();
+//! let legacy = Message::new("hi".to_string(), sender.to_string());
+//! let chat = ChatMessage::try_from((Uuid::new_v4(), legacy)).unwrap();
+//! let back: Message = chat.into();
+//! assert_eq!(back.author, sender.to_string());
+//! ```
+
+use uuid::Uuid;
+
+use crate::shared::error::SharedError;
+use crate::shared::Message;
+
+use super::message::{ChatMessage, MessageType, VersionVector};
+use super::timestamp;
+
+impl From<&ChatMessage> for Message {
+    /// Lossy conversion: conversation, read/delivery state and CRDT metadata are dropped.
+    fn from(message: &ChatMessage) -> Self {
+        Message {
+            text: message.content.clone(),
+            author: message.sender_id.to_string(),
+            timestamp: message.timestamp.to_rfc3339(),
+            version: if message.braid_version.is_empty() {
+                None
+            } else {
+                Some(message.braid_version.clone())
+            },
+        }
+    }
+}
+
+impl From<ChatMessage> for Message {
+    fn from(message: ChatMessage) -> Self {
+        Message::from(&message)
+    }
+}
+
+impl TryFrom<(Uuid, Message)> for ChatMessage {
+    type Error = SharedError;
+
+    /// Convert a legacy message into a `ChatMessage` in `conversation_id`.
+    ///
+    /// Fails with a validation error if `author` is not a UUID or `text` is empty.
+    /// A `timestamp` that isn't RFC 3339 is replaced with the current time.
+    fn try_from((conversation_id, message): (Uuid, Message)) -> Result<Self, Self::Error> {
+        if conversation_id.is_nil() {
+            return Err(SharedError::validation("conversation_id", "Conversation ID is required"));
+        }
+
+        if message.text.is_empty() {
+            return Err(SharedError::validation("text", "Message text cannot be empty"));
+        }
+
+        let sender_id = Uuid::parse_str(&message.author).map_err(|_| {
+            SharedError::validation("author", format!("Author '{}' is not a user ID", message.author))
+        })?;
+
+        Ok(ChatMessage {
+            id: Uuid::new_v4(),
+            conversation_id,
+            sender_id,
+            content: message.text,
+            message_type: MessageType::Text,
+            timestamp: timestamp::parse(&message.timestamp).unwrap_or_else(chrono::Utc::now),
+            is_read: false,
+            is_delivered: false,
+            is_sent: true,
+            crdt_timestamp: 0,
+            braid_version: message.version.unwrap_or_default(),
+            braid_parents: Vec::new(),
+            version_vector: VersionVector::default(),
+            link_preview: None,
+            moderation_flag: None,
+            edit_version: None,
+            is_deleted: false,
+            sequence: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chat_message_to_legacy() {
+        let conversation_id = Uuid::new_v4();
+        let sender_id = Uuid::new_v4();
+        let chat = ChatMessage::new_text(conversation_id, sender_id, "Hello".to_string(), 7);
+
+        let legacy = Message::from(&chat);
+        assert_eq!(legacy.text, "Hello");
+        assert_eq!(legacy.author, sender_id.to_string());
+        assert_eq!(legacy.timestamp, chat.timestamp.to_rfc3339());
+        assert_eq!(legacy.version, Some(chat.braid_version.clone()));
+    }
+
+    #[test]
+    fn test_empty_braid_version_maps_to_none() {
+        let mut chat = ChatMessage::new_text(Uuid::new_v4(), Uuid::new_v4(), "Hi".to_string(), 0);
+        chat.braid_version.clear();
+        assert!(Message::from(chat).version.is_none());
+    }
+
+    #[test]
+    fn test_legacy_round_trip() {
+        let conversation_id = Uuid::new_v4();
+        let sender_id = Uuid::new_v4();
+        let legacy = Message::with_version("Hello".to_string(), sender_id.to_string(), "v1".to_string());
+
+        let chat = ChatMessage::try_from((conversation_id, legacy.clone())).unwrap();
+        assert_eq!(chat.conversation_id, conversation_id);
+        assert_eq!(chat.sender_id, sender_id);
+        assert_eq!(chat.braid_version, "v1");
+        assert_eq!(Message::from(chat), legacy);
+    }
+
+    #[test]
+    fn test_missing_conversation_id_is_rejected() {
+        let legacy = Message::new("Hello".to_string(), Uuid::new_v4().to_string());
+        match ChatMessage::try_from((Uuid::nil(), legacy)) {
+            Err(SharedError::ValidationError { field, .. }) => assert_eq!(field, "conversation_id"),
+            other => panic!("Expected ValidationError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_non_uuid_author_is_rejected() {
+        let legacy = Message::new("Hello".to_string(), "Alice".to_string());
+        match ChatMessage::try_from((Uuid::new_v4(), legacy)) {
+            Err(SharedError::ValidationError { field, .. }) => assert_eq!(field, "author"),
+            other => panic!("Expected ValidationError, got {:?}", other),
+        }
+    }
+}
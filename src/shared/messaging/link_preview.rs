@@ -0,0 +1,51 @@
+//! Link Preview Data Structures
+//!
+//! OpenGraph-style preview attached to messages that contain a URL, plus the
+//! URL extraction shared by the server (which fetches previews) and clients.
+
+use serde::{Deserialize, Serialize};
+
+/// Preview metadata for the first URL in a message
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LinkPreview {
+    /// The URL the preview was fetched for
+    pub url: String,
+    /// `og:title`, falling back to `<title>`
+    pub title: Option<String>,
+    /// `og:description`, falling back to `<meta name="description">`
+    pub description: Option<String>,
+    /// `og:image`, resolved against the page URL
+    pub image_url: Option<String>,
+    /// `og:site_name`
+    pub site_name: Option<String>,
+}
+
+impl LinkPreview {
+    /// A preview with no title or description is not worth rendering
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none() && self.description.is_none()
+    }
+}
+
+/// Extract `http(s)://` URLs from message text, in order of appearance
+pub fn extract_urls(text: &str) -> Vec<String> {
+    text.split_whitespace()
+        .map(|word| word.trim_start_matches(['(', '[', '<', '"', '\'']))
+        .filter(|word| word.starts_with("http://") || word.starts_with("https://"))
+        .map(|word| word.trim_end_matches(['.', ',', ';', ':', '!', '?', ')', ']', '>', '"', '\'']))
+        .filter(|url| url.len() > "https://".len())
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_urls() {
+        let urls = extract_urls("see https://example.com/a, and (http://foo.org/b). not ftp://x");
+        assert_eq!(urls, vec!["https://example.com/a", "http://foo.org/b"]);
+        assert!(extract_urls("no links here https://").is_empty());
+    }
+}
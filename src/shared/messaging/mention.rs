@@ -0,0 +1,102 @@
+//! Structured Mentions
+//!
+//! A mention is stored in message content as `@[username](user-id)`, so it
+//! keeps pointing at the right user even if a username is reused. Clients
+//! render it as `@username`; the server and notification code use
+//! [`parse_mentions`] to find who was mentioned.
+
+use std::ops::Range;
+
+use uuid::Uuid;
+
+/// A mention of a user within message content
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mention {
+    pub user_id: Uuid,
+    pub username: String,
+    /// Byte range of the token in the content
+    pub range: Range<usize>,
+}
+
+/// Token for mentioning `username`
+pub fn mention_token(username: &str, user_id: Uuid) -> String {
+    format!("@[{}]({})", username, user_id)
+}
+
+/// All well-formed mention tokens in `content`, in order
+pub fn parse_mentions(content: &str) -> Vec<Mention> {
+    let mut mentions = Vec::new();
+    let mut offset = 0;
+
+    while let Some(found) = content[offset..].find("@[") {
+        let start = offset + found;
+        offset = start + 2;
+
+        let rest = &content[start + 2..];
+        let Some(name_end) = rest.find("](") else { break };
+        let username = &rest[..name_end];
+        let after_name = &rest[name_end + 2..];
+        let Some(id_end) = after_name.find(')') else { break };
+        let Ok(user_id) = Uuid::parse_str(&after_name[..id_end]) else { continue };
+        if username.is_empty() || username.contains(char::is_whitespace) {
+            continue;
+        }
+
+        let end = start + 2 + name_end + 2 + id_end + 1;
+        mentions.push(Mention { user_id, username: username.to_string(), range: start..end });
+        offset = end;
+    }
+
+    mentions
+}
+
+/// Content with mention tokens shown as `@username`
+pub fn display_text(content: &str) -> String {
+    let mut text = String::with_capacity(content.len());
+    let mut last = 0;
+    for mention in parse_mentions(content) {
+        text.push_str(&content[last..mention.range.start]);
+        text.push('@');
+        text.push_str(&mention.username);
+        last = mention.range.end;
+    }
+    text.push_str(&content[last..]);
+    text
+}
+
+/// Whether `content` mentions `username`, either as a token or as plain `@username`
+pub fn mentions_username(content: &str, username: &str) -> bool {
+    parse_mentions(content).iter().any(|m| m.username == username)
+        || content
+            .split_whitespace()
+            .filter_map(|word| word.strip_prefix('@'))
+            .any(|word| word.trim_end_matches(|c: char| !c.is_alphanumeric() && c != '_') == username)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_display_mentions() {
+        let bob = Uuid::new_v4();
+        let content = format!("hi {}, see @[broken](nope) and {}!", mention_token("bob", bob), mention_token("amy_2", bob));
+
+        let mentions = parse_mentions(&content);
+        assert_eq!(mentions.len(), 2);
+        assert_eq!(mentions[0].username, "bob");
+        assert_eq!(mentions[0].user_id, bob);
+        assert_eq!(&content[mentions[0].range.clone()], mention_token("bob", bob));
+
+        assert_eq!(display_text(&content), "hi @bob, see @[broken](nope) and @amy_2!");
+    }
+
+    #[test]
+    fn test_mentions_username() {
+        let token = mention_token("bob", Uuid::new_v4());
+        assert!(mentions_username(&format!("{} lunch?", token), "bob"));
+        assert!(mentions_username("@bob, lunch?", "bob"));
+        assert!(!mentions_username("@bobby lunch?", "bob"));
+        assert!(!mentions_username("email bob@example.com", "bob"));
+    }
+}
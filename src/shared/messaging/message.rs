@@ -2,8 +2,11 @@
 //!
 //! Represents a message in a conversation.
 
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
+use super::link_preview::LinkPreview;
+use super::timestamp;
 
 /// Version vector for CRDT causal ordering
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
@@ -34,6 +37,9 @@ pub enum MessageType {
     },
     /// System message (e.g., "User joined")
     System,
+    /// Action/emote from `/me`; content is the action without the sender,
+    /// rendered as "* Alice waves"
+    Action,
 }
 
 impl Default for MessageType {
@@ -50,6 +56,7 @@ impl MessageType {
             MessageType::Image { .. } => "image".to_string(),
             MessageType::File { .. } => "file".to_string(),
             MessageType::System => "system".to_string(),
+            MessageType::Action => "action".to_string(),
         }
     }
 
@@ -69,6 +76,7 @@ impl MessageType {
                 url: String::new(),
             },
             "system" => MessageType::System,
+            "action" => MessageType::Action,
             _ => MessageType::Text,
         }
     }
@@ -88,12 +96,18 @@ pub struct ChatMessage {
     /// Type of message
     #[serde(default)]
     pub message_type: MessageType,
-    /// When the message was sent (RFC3339 string, unified across targets)
-    pub timestamp: String,
+    /// When the message was sent
+    #[serde(with = "timestamp")]
+    pub timestamp: DateTime<Utc>,
     /// Whether the message has been read by recipient
     pub is_read: bool,
-    /// Whether the message has been delivered
+    /// Whether the message has reached a recipient's device
     pub is_delivered: bool,
+    /// Whether the server accepted the message (persisted and broadcast);
+    /// false while it only exists locally. Messages from the server are
+    /// always sent, so this defaults to true.
+    #[serde(default = "sent_by_default")]
+    pub is_sent: bool,
     /// CRDT timestamp for ordering (Lamport-style)
     pub crdt_timestamp: u64,
     /// Braid version ID
@@ -103,6 +117,27 @@ pub struct ChatMessage {
     /// Version vector for causal ordering
     #[serde(default)]
     pub version_vector: VersionVector,
+    /// Preview for the first URL in the message, attached asynchronously by the server
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link_preview: Option<LinkPreview>,
+    /// Moderation marker (reason) set when the content filter flagged this message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub moderation_flag: Option<String>,
+    /// Version of the latest edit; `None` if the message was never edited
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub edit_version: Option<String>,
+    /// Tombstone: the message was deleted and its content cleared. Deleted
+    /// messages are kept so later versions still have their parents.
+    #[serde(default)]
+    pub is_deleted: bool,
+    /// Position in the conversation assigned by the server when it accepted
+    /// the message; the authoritative display order. `None` until then.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sequence: Option<i64>,
+}
+
+fn sent_by_default() -> bool {
+    true
 }
 
 impl ChatMessage {
@@ -119,16 +154,45 @@ impl ChatMessage {
             sender_id,
             content,
             message_type: MessageType::Text,
-            timestamp: chrono::Utc::now().to_rfc3339(),
+            timestamp: Utc::now(),
             is_read: false,
             is_delivered: false,
+            is_sent: false,
             crdt_timestamp,
             braid_version: Uuid::new_v4().to_string(),
             braid_parents: Vec::new(),
             version_vector: VersionVector::default(),
+            link_preview: None,
+            moderation_flag: None,
+            edit_version: None,
+            is_deleted: false,
+            sequence: None,
         }
     }
 
+    /// Display order within a conversation
+    ///
+    /// Messages the server has sequenced come first, in sequence order.
+    /// Messages not accepted yet (e.g. written offline) follow, in CRDT
+    /// causal order, which also breaks any tie between equal sequences.
+    pub fn display_cmp(&self, other: &Self) -> std::cmp::Ordering {
+        let unsequenced = |m: &Self| (m.sequence.is_none(), m.sequence);
+        unsequenced(self)
+            .cmp(&unsequenced(other))
+            .then_with(|| self.crdt_timestamp.cmp(&other.crdt_timestamp))
+            .then_with(|| self.id.cmp(&other.id))
+    }
+
+    /// Whether the message was edited after it was sent
+    pub fn is_edited(&self) -> bool {
+        self.edit_version.is_some()
+    }
+
+    /// Version an edit of this message must name as its parent
+    pub fn current_version(&self) -> &str {
+        self.edit_version.as_deref().unwrap_or(&self.braid_version)
+    }
+
     /// Get a preview of the message (first N characters)
     pub fn preview(&self, max_len: usize) -> String {
         if self.content.len() <= max_len {
@@ -171,5 +235,43 @@ pub struct ListMessagesRequest {
 pub struct ListMessagesResponse {
     pub messages: Vec<ChatMessage>,
     pub has_more: bool,
+    /// Effective page size applied by the server (after clamping)
+    #[serde(default)]
+    pub limit: u32,
+    /// Offset applied by the server
+    #[serde(default)]
+    pub offset: u32,
+}
+
+/// A page of older messages from `GET /sync/conversations/{id}/messages/history`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageHistoryPage {
+    /// Messages in the page, oldest first
+    pub messages: Vec<ChatMessage>,
+    /// Pass as `before` to fetch the next older page; `None` at the start
+    /// of the conversation
+    pub next_cursor: Option<Uuid>,
+}
+
+/// A version of a message that an edit replaced
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MessageEdit {
+    /// Braid version the content had
+    pub version: String,
+    pub content: String,
+    /// When this version was written
+    #[serde(with = "timestamp")]
+    pub written_at: DateTime<Utc>,
+    /// When the next edit replaced it
+    #[serde(with = "timestamp")]
+    pub replaced_at: DateTime<Utc>,
+}
+
+/// Response from `GET /sync/conversations/{id}/messages/{id}/history`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EditHistoryResponse {
+    pub message_id: Uuid,
+    /// Replaced versions, oldest first; the current one is the message itself
+    pub edits: Vec<MessageEdit>,
 }
 
@@ -2,6 +2,12 @@
 //!
 //! This module defines CRDT types for message ordering and conflict resolution.
 //! Uses Lamport timestamps for causal ordering of messages.
+//!
+//! Edits carry the version they were made from, so two edits made from the
+//! same version are detected as concurrent; the later timestamp wins the
+//! content either way, so every replica converges. Deletes leave a tombstone
+//! rather than removing the entry, keeping causal history intact, and a
+//! tombstone wins over any edit.
 
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
@@ -85,8 +91,11 @@ pub enum MessageOpType {
     /// Edit an existing message
     Edit {
         new_content: String,
+        /// Version the edit was made from: the send, or an earlier edit
+        #[serde(default)]
+        parent: Option<LamportTimestamp>,
     },
-    /// Delete a message
+    /// Delete a message, leaving a tombstone
     Delete,
     /// Mark message as read
     Read {
@@ -113,6 +122,27 @@ pub struct MessageEntry {
     pub timestamp: LamportTimestamp,
     pub is_deleted: bool,
     pub read_by: Vec<Uuid>,
+    /// Timestamp of the edit that produced `content`, if any
+    #[serde(default)]
+    pub edited_at: Option<LamportTimestamp>,
+}
+
+impl MessageEntry {
+    /// Version of the current content
+    pub fn version(&self) -> LamportTimestamp {
+        self.edited_at.unwrap_or(self.timestamp)
+    }
+}
+
+/// What applying an operation did
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApplyOutcome {
+    /// The operation changed the state
+    Applied,
+    /// Nothing to do: a duplicate, a stale edit, or an unknown message
+    Ignored,
+    /// An edit made concurrently with another; the later one's content is kept
+    Concurrent,
 }
 
 impl MessageState {
@@ -125,7 +155,7 @@ impl MessageState {
     }
 
     /// Apply an operation to the state
-    pub fn apply(&mut self, op: MessageOperation) {
+    pub fn apply(&mut self, op: MessageOperation) -> ApplyOutcome {
         // Update our timestamp
         self.current_timestamp = self.current_timestamp.update(&op.timestamp);
 
@@ -135,6 +165,9 @@ impl MessageState {
                 content,
                 message_type,
             } => {
+                if self.messages.iter().any(|m| m.id == op.message_id) {
+                    return ApplyOutcome::Ignored;
+                }
                 let entry = MessageEntry {
                     id: op.message_id,
                     sender_id,
@@ -143,6 +176,7 @@ impl MessageState {
                     timestamp: op.timestamp,
                     is_deleted: false,
                     read_by: Vec::new(),
+                    edited_at: None,
                 };
                 // Insert in sorted order by timestamp
                 let pos = self
@@ -150,25 +184,109 @@ impl MessageState {
                     .binary_search_by(|m| m.timestamp.cmp(&op.timestamp))
                     .unwrap_or_else(|p| p);
                 self.messages.insert(pos, entry);
+                ApplyOutcome::Applied
             }
-            MessageOpType::Edit { new_content } => {
-                if let Some(msg) = self.messages.iter_mut().find(|m| m.id == op.message_id) {
+            MessageOpType::Edit { new_content, parent } => {
+                let Some(msg) = self.messages.iter_mut().find(|m| m.id == op.message_id) else {
+                    return ApplyOutcome::Ignored;
+                };
+                if msg.is_deleted {
+                    return ApplyOutcome::Ignored;
+                }
+                let current = msg.version();
+                let concurrent = parent.is_some_and(|parent| parent != current);
+                if op.timestamp > current {
                     msg.content = new_content;
+                    msg.edited_at = Some(op.timestamp);
+                }
+                match (concurrent, op.timestamp > current) {
+                    (true, _) => ApplyOutcome::Concurrent,
+                    (false, true) => ApplyOutcome::Applied,
+                    (false, false) => ApplyOutcome::Ignored,
                 }
             }
             MessageOpType::Delete => {
-                if let Some(msg) = self.messages.iter_mut().find(|m| m.id == op.message_id) {
-                    msg.is_deleted = true;
+                match self.messages.iter_mut().find(|m| m.id == op.message_id) {
+                    Some(msg) if !msg.is_deleted => {
+                        msg.is_deleted = true;
+                        msg.content.clear();
+                        ApplyOutcome::Applied
+                    }
+                    _ => ApplyOutcome::Ignored,
                 }
             }
             MessageOpType::Read { reader_id } => {
-                if let Some(msg) = self.messages.iter_mut().find(|m| m.id == op.message_id) {
-                    if !msg.read_by.contains(&reader_id) {
+                match self.messages.iter_mut().find(|m| m.id == op.message_id) {
+                    Some(msg) if !msg.read_by.contains(&reader_id) => {
                         msg.read_by.push(reader_id);
+                        ApplyOutcome::Applied
                     }
+                    _ => ApplyOutcome::Ignored,
                 }
             }
         }
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn op(op_type: MessageOpType, counter: u64, node_id: Uuid, message_id: Uuid) -> MessageOperation {
+        MessageOperation {
+            op_type,
+            timestamp: LamportTimestamp::new(counter, node_id),
+            message_id,
+            conversation_id: Uuid::nil(),
+        }
+    }
+
+    fn edit(content: &str, parent: LamportTimestamp) -> MessageOpType {
+        MessageOpType::Edit { new_content: content.to_string(), parent: Some(parent) }
+    }
+
+    fn sent(node_id: Uuid, message_id: Uuid) -> MessageState {
+        let mut state = MessageState::new(node_id);
+        let send = MessageOpType::Send {
+            sender_id: node_id,
+            content: "hello".to_string(),
+            message_type: "text".to_string(),
+        };
+        state.apply(op(send, 1, node_id, message_id));
+        state
+    }
+
+    #[test]
+    fn test_concurrent_edits_converge() {
+        let (alice, bob, message_id) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let original = LamportTimestamp::new(1, alice);
+        let first = op(edit("first", original), 2, alice, message_id);
+        let second = op(edit("second", original), 3, bob, message_id);
+
+        let mut a = sent(alice, message_id);
+        assert_eq!(a.apply(first.clone()), ApplyOutcome::Applied);
+        assert_eq!(a.apply(second.clone()), ApplyOutcome::Concurrent);
+
+        let mut b = sent(alice, message_id);
+        assert_eq!(b.apply(second), ApplyOutcome::Applied);
+        assert_eq!(b.apply(first), ApplyOutcome::Concurrent);
+
+        assert_eq!(a.messages[0].content, "second");
+        assert_eq!(b.messages[0].content, "second");
+    }
+
+    #[test]
+    fn test_delete_leaves_tombstone_that_wins_over_edits() {
+        let (alice, message_id) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut state = sent(alice, message_id);
+
+        assert_eq!(state.apply(op(MessageOpType::Delete, 2, alice, message_id)), ApplyOutcome::Applied);
+        let edit_after = op(edit("too late", LamportTimestamp::new(1, alice)), 3, alice, message_id);
+        assert_eq!(state.apply(edit_after), ApplyOutcome::Ignored);
+
+        assert_eq!(state.messages.len(), 1);
+        assert!(state.messages[0].is_deleted);
+        assert!(state.messages[0].content.is_empty());
+    }
+}
+
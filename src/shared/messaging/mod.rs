@@ -6,6 +6,16 @@
 //! - `ChatMessage` - A message in a conversation
 //! - `Conversation` - A conversation between users
 //! - `FriendRequest` - A friend request between users
+//! - `LinkPreview` - OpenGraph preview for a URL in a message
+//! - `Mention` - A structured `@[username](user-id)` mention in message content
+//! - `MessagePrivacy` - Who can start a conversation with a user
+//! - `UserStatus` - A user's chosen availability and status text
+//! - `TranslateMessageRequest` - AI translation of a single message
+//! - `SummarizeConversationRequest` - AI summary of part of a conversation
+//! - `UserProfile` - What's public about a user, as found by user search
+//! - `UsageLimitExceeded` - Why a send was refused once a daily quota is used up
+//! - `legacy` - Conversions to/from the legacy `/chat` `Message`
+//! - `timestamp` - RFC 3339 serde format for timestamps in these types
 //!
 //! # Usage
 //!
@@ -18,16 +28,36 @@ pub mod message;
 pub mod conversation;
 pub mod friend_request;
 pub mod message_crdt;
+pub mod legacy;
+pub mod attachment;
+pub mod link_preview;
+pub mod mention;
+pub mod privacy;
+pub mod presence;
+pub mod ai;
+pub mod usage;
+pub mod user_search;
+pub mod timestamp;
 
 // Re-export all types
+pub use attachment::{avatar_url, UploadAttachmentResponse, UploadAvatarResponse};
+pub use link_preview::{extract_urls, LinkPreview};
+pub use mention::{display_text, mention_token, mentions_username, parse_mentions, Mention};
+pub use privacy::{BlockedUsersResponse, MessagePrivacy, PrivacySettings, BLOCKED_ERROR, PRIVACY_REJECTED_ERROR};
+pub use presence::{PresenceStatus, SetStatusRequest, UserStatus, MAX_PRESENCE_BATCH, MAX_STATUS_TEXT_CHARS};
+pub use ai::{normalize_locale, SummarizeConversationRequest, TranslateMessageRequest, TranslateMessageResponse};
+pub use usage::{UsageLimitExceeded, MESSAGE_QUOTA_ERROR};
+pub use user_search::{is_searchable, SearchUsersResponse, UserProfile, MAX_USER_SEARCH_RESULTS, MIN_USER_SEARCH_CHARS};
 pub use contact::{Contact, ListContactsResponse, GetContactResponse};
 pub use message::{
     ChatMessage, MessageType, SendMessageRequest, SendMessageResponse,
-    ListMessagesRequest, ListMessagesResponse,
+    ListMessagesRequest, ListMessagesResponse, MessageHistoryPage, MessageEdit, EditHistoryResponse,
 };
 pub use conversation::{
     Conversation, ListConversationsResponse, CreateConversationRequest,
-    CreateConversationResponse,
+    CreateConversationResponse, MarkConversationUnreadRequest,
+    SetConversationThemeRequest, ThemeColor, PinConversationRequest,
+    ReorderPinnedConversationsRequest,
 };
 pub use friend_request::{
     FriendRequest, FriendRequestStatus, SendFriendRequestRequest,
@@ -35,6 +65,6 @@ pub use friend_request::{
     RespondFriendRequestResponse, ListFriendRequestsResponse,
 };
 pub use message_crdt::{
-    LamportTimestamp, MessageOperation, MessageOpType, MessageState, MessageEntry,
+    ApplyOutcome, LamportTimestamp, MessageOperation, MessageOpType, MessageState, MessageEntry,
 };
 
@@ -0,0 +1,104 @@
+//! User Status
+//!
+//! The availability a user picks (online, away, do not disturb) and an
+//! optional status text, synced to their contacts through `/sync/presence`.
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Longest status text accepted, in characters
+pub const MAX_STATUS_TEXT_CHARS: usize = 140;
+
+/// Most users one `GET /api/presence/batch` may ask about
+pub const MAX_PRESENCE_BATCH: usize = 200;
+
+/// A user's availability
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum PresenceStatus {
+    #[default]
+    Online,
+    Away,
+    DoNotDisturb,
+    /// Not connected; never chosen, only reported
+    Offline,
+}
+
+impl PresenceStatus {
+    /// Statuses a user can pick
+    pub const SELECTABLE: [PresenceStatus; 3] = [
+        PresenceStatus::Online,
+        PresenceStatus::Away,
+        PresenceStatus::DoNotDisturb,
+    ];
+
+    /// Label for the status picker
+    pub fn label(&self) -> &'static str {
+        match self {
+            PresenceStatus::Online => "Online",
+            PresenceStatus::Away => "Away",
+            PresenceStatus::DoNotDisturb => "Do not disturb",
+            PresenceStatus::Offline => "Offline",
+        }
+    }
+}
+
+/// A user's current status; what `/sync/presence` streams
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct UserStatus {
+    pub user_id: Uuid,
+    pub status: PresenceStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status_text: Option<String>,
+    /// When the user set it (RFC3339); later wins
+    pub updated_at: String,
+}
+
+/// Body of `PUT /sync/presence`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SetStatusRequest {
+    pub status: PresenceStatus,
+    #[serde(default)]
+    pub status_text: Option<String>,
+}
+
+impl SetStatusRequest {
+    /// Trim the status text, dropping it if blank
+    ///
+    /// Fails if the status can't be picked or the text is too long.
+    pub fn normalized(self) -> Result<Self, String> {
+        if !PresenceStatus::SELECTABLE.contains(&self.status) {
+            return Err(format!("{} can't be set", self.status.label()));
+        }
+        let status_text = self.status_text.map(|t| t.trim().to_string()).filter(|t| !t.is_empty());
+        if status_text.as_ref().is_some_and(|t| t.chars().count() > MAX_STATUS_TEXT_CHARS) {
+            return Err(format!("Status text is limited to {} characters", MAX_STATUS_TEXT_CHARS));
+        }
+        Ok(Self { status: self.status, status_text })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_status_request_normalization() {
+        let request = SetStatusRequest { status: PresenceStatus::Away, status_text: Some("  lunch  ".to_string()) };
+        assert_eq!(request.normalized().unwrap().status_text.as_deref(), Some("lunch"));
+
+        let blank = SetStatusRequest { status: PresenceStatus::Online, status_text: Some("   ".to_string()) };
+        assert_eq!(blank.normalized().unwrap().status_text, None);
+
+        let offline = SetStatusRequest { status: PresenceStatus::Offline, status_text: None };
+        assert!(offline.normalized().is_err());
+
+        let long = SetStatusRequest { status: PresenceStatus::Online, status_text: Some("x".repeat(141)) };
+        assert!(long.normalized().is_err());
+    }
+
+    #[test]
+    fn test_status_serializes_snake_case() {
+        assert_eq!(serde_json::to_value(PresenceStatus::DoNotDisturb).unwrap(), "do_not_disturb");
+    }
+}
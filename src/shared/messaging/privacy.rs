@@ -0,0 +1,136 @@
+//! Privacy Settings
+//!
+//! Per-user settings for who may start a conversation with them and which
+//! activity they share, and the users they've blocked.
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Error code returned when the recipient doesn't accept the sender
+pub const PRIVACY_REJECTED_ERROR: &str = "recipient_not_accepting";
+
+/// Error code returned when the recipient has blocked the sender
+pub const BLOCKED_ERROR: &str = "recipient_blocked_sender";
+
+/// Who can send the user friend requests and first messages
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MessagePrivacy {
+    /// Anyone
+    #[default]
+    Everyone,
+    /// Only users already in the user's contacts
+    ContactsOnly,
+    /// No one; existing conversations continue as before
+    Nobody,
+}
+
+impl MessagePrivacy {
+    pub const ALL: [MessagePrivacy; 3] = [
+        MessagePrivacy::Everyone,
+        MessagePrivacy::ContactsOnly,
+        MessagePrivacy::Nobody,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MessagePrivacy::Everyone => "everyone",
+            MessagePrivacy::ContactsOnly => "contacts_only",
+            MessagePrivacy::Nobody => "nobody",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "everyone" => Some(MessagePrivacy::Everyone),
+            "contacts_only" => Some(MessagePrivacy::ContactsOnly),
+            "nobody" => Some(MessagePrivacy::Nobody),
+            _ => None,
+        }
+    }
+
+    /// Label for the settings menu
+    pub fn label(&self) -> &'static str {
+        match self {
+            MessagePrivacy::Everyone => "Everyone",
+            MessagePrivacy::ContactsOnly => "Contacts only",
+            MessagePrivacy::Nobody => "Nobody",
+        }
+    }
+
+    /// Whether a sender may start a conversation under this setting
+    pub fn allows(&self, sender_is_contact: bool) -> bool {
+        match self {
+            MessagePrivacy::Everyone => true,
+            MessagePrivacy::ContactsOnly => sender_is_contact,
+            MessagePrivacy::Nobody => false,
+        }
+    }
+}
+
+/// Request/response body for `/api/settings/privacy`
+///
+/// The activity flags are enforced by the server, which drops the user's
+/// indicators when they're off. Read receipts are reciprocal: a user who
+/// doesn't send them doesn't get anyone else's either.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PrivacySettings {
+    /// Who can message the user
+    pub message_privacy: MessagePrivacy,
+    /// Share typing, recording and uploading indicators
+    #[serde(default = "enabled")]
+    pub send_typing: bool,
+    /// Share online status and chosen status; off appears offline
+    #[serde(default = "enabled")]
+    pub send_presence: bool,
+    /// Send read receipts, and receive other users'
+    #[serde(default = "enabled")]
+    pub send_read_receipts: bool,
+}
+
+impl Default for PrivacySettings {
+    fn default() -> Self {
+        Self {
+            message_privacy: MessagePrivacy::default(),
+            send_typing: true,
+            send_presence: true,
+            send_read_receipts: true,
+        }
+    }
+}
+
+fn enabled() -> bool {
+    true
+}
+
+/// Response body for `GET /api/users/blocked`
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BlockedUsersResponse {
+    /// Blocked users, most recent first
+    pub user_ids: Vec<Uuid>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message_privacy_round_trip() {
+        for privacy in MessagePrivacy::ALL {
+            assert_eq!(MessagePrivacy::from_str(privacy.as_str()), Some(privacy));
+            let json = serde_json::to_string(&privacy).unwrap();
+            assert_eq!(json, format!("\"{}\"", privacy.as_str()));
+        }
+        assert!(MessagePrivacy::ContactsOnly.allows(true));
+        assert!(!MessagePrivacy::ContactsOnly.allows(false));
+        assert!(!MessagePrivacy::Nobody.allows(true));
+    }
+
+    #[test]
+    fn test_activity_sharing_defaults_on() {
+        // Older clients only send the message privacy
+        let settings: PrivacySettings = serde_json::from_str(r#"{"message_privacy":"nobody"}"#).unwrap();
+        assert_eq!(settings.message_privacy, MessagePrivacy::Nobody);
+        assert!(settings.send_typing && settings.send_presence && settings.send_read_receipts);
+    }
+}
@@ -0,0 +1,121 @@
+//! RFC 3339 Timestamps
+//!
+//! Serde helpers for `DateTime<Utc>` fields that have always been sent as
+//! [`DateTime::to_rfc3339`] strings (`2024-01-01T12:00:00+00:00`). Use with
+//! `#[serde(with = "timestamp")]` (or `timestamp::option`) to keep that wire
+//! format; chrono's own impl writes `Z` instead.
+//!
+//! Deserializing accepts any RFC 3339 offset and converts it to UTC.
+
+use chrono::{DateTime, Utc};
+use serde::{de, Deserialize, Deserializer, Serializer};
+
+/// Parse an RFC 3339 timestamp into UTC
+pub fn parse(s: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(s).ok().map(|dt| dt.with_timezone(&Utc))
+}
+
+pub fn serialize<S>(timestamp: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_str(&timestamp.to_rfc3339())
+}
+
+pub fn deserialize<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+    parse(&s).ok_or_else(|| de::Error::custom(format!("invalid RFC 3339 timestamp: {}", s)))
+}
+
+/// The same for `Option<DateTime<Utc>>`, with `None` as `null`
+pub mod option {
+    use super::*;
+
+    pub fn serialize<S>(timestamp: &Option<DateTime<Utc>>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match timestamp {
+            Some(timestamp) => super::serialize(timestamp, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match Option::<String>::deserialize(deserializer)? {
+            Some(s) => parse(&s)
+                .map(Some)
+                .ok_or_else(|| de::Error::custom(format!("invalid RFC 3339 timestamp: {}", s))),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shared::messaging::{ChatMessage, Conversation, MessageEdit};
+    use chrono::TimeZone;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_message_timestamp_keeps_wire_format() {
+        let mut message = ChatMessage::new_text(Uuid::new_v4(), Uuid::new_v4(), "hi".to_string(), 1);
+        message.timestamp = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+
+        let json = serde_json::to_value(&message).unwrap();
+        assert_eq!(json["timestamp"], "2024-01-01T12:00:00+00:00");
+
+        // Sub-second precision is written as before, too
+        let precise = message.timestamp + chrono::Duration::microseconds(123_456);
+        message.timestamp = precise;
+        let json = serde_json::to_value(&message).unwrap();
+        assert_eq!(json["timestamp"], precise.to_rfc3339());
+        assert_eq!(serde_json::from_value::<ChatMessage>(json).unwrap().timestamp, precise);
+    }
+
+    #[test]
+    fn test_older_timestamps_still_deserialize() {
+        let expected = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+        let message = serde_json::to_value(ChatMessage::new_text(Uuid::new_v4(), Uuid::new_v4(), String::new(), 1)).unwrap();
+
+        for old in ["2024-01-01T12:00:00Z", "2024-01-01T12:00:00+00:00", "2024-01-01T13:00:00+01:00"] {
+            let mut json = message.clone();
+            json["timestamp"] = old.into();
+            assert_eq!(serde_json::from_value::<ChatMessage>(json).unwrap().timestamp, expected, "{}", old);
+        }
+
+        let mut json = message;
+        json["timestamp"] = "yesterday".into();
+        assert!(serde_json::from_value::<ChatMessage>(json).is_err());
+    }
+
+    #[test]
+    fn test_optional_and_nested_timestamps() {
+        let created = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+        let mut conversation = Conversation::new(vec![Uuid::new_v4()]);
+        conversation.created_at = created;
+
+        let json = serde_json::to_value(&conversation).unwrap();
+        assert_eq!(json["created_at"], "2024-01-01T12:00:00+00:00");
+        assert!(json["last_message_time"].is_null());
+
+        conversation.last_message_time = Some(created);
+        let json = serde_json::to_string(&conversation).unwrap();
+        let parsed: Conversation = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.last_message_time, Some(created));
+
+        let edit: MessageEdit = serde_json::from_str(
+            r#"{"version":"v1","content":"a","written_at":"2024-01-01T12:00:00Z","replaced_at":"2024-01-01T12:05:00+00:00"}"#,
+        )
+        .unwrap();
+        assert_eq!(edit.written_at, created);
+        assert_eq!(serde_json::to_value(&edit).unwrap()["replaced_at"], "2024-01-01T12:05:00+00:00");
+    }
+}
@@ -0,0 +1,27 @@
+//! Usage Limits
+//!
+//! The body of the `429 Too Many Requests` a message PUT gets once the
+//! sender's daily quota is used up, so clients can tell it apart from a
+//! network failure and say when sending will work again.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::timestamp;
+
+/// Error code returned when the sender's daily message quota is used up
+pub const MESSAGE_QUOTA_ERROR: &str = "message_quota_exceeded";
+
+/// A usage limit the request would go over
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct UsageLimitExceeded {
+    /// Error code, e.g. [`MESSAGE_QUOTA_ERROR`]
+    pub error: String,
+    /// Allowed per period
+    pub limit: i64,
+    /// Used so far this period
+    pub used: i64,
+    /// When the period ends and the quota resets
+    #[serde(with = "timestamp")]
+    pub resets_at: DateTime<Utc>,
+}
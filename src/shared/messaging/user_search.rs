@@ -0,0 +1,53 @@
+//! User Search
+//!
+//! Public profiles returned when looking for people to add as friends.
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Most users one `GET /api/users/search` returns
+pub const MAX_USER_SEARCH_RESULTS: usize = 20;
+
+/// Shortest query that searches; anything shorter matches no one
+pub const MIN_USER_SEARCH_CHARS: usize = 2;
+
+/// What anyone may see about another user
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct UserProfile {
+    pub user_id: Uuid,
+    pub username: String,
+    /// Server-relative avatar URL, if the user uploaded one
+    pub avatar_url: Option<String>,
+}
+
+impl UserProfile {
+    /// Get avatar initial (first letter of username)
+    pub fn avatar_initial(&self) -> char {
+        self.username.chars().next().unwrap_or('?').to_ascii_uppercase()
+    }
+}
+
+/// Response type for a user search
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SearchUsersResponse {
+    /// Best matches first
+    pub users: Vec<UserProfile>,
+}
+
+/// Whether `query` is long enough to search for
+pub fn is_searchable(query: &str) -> bool {
+    query.trim().chars().count() >= MIN_USER_SEARCH_CHARS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_short_queries_are_not_searched() {
+        assert!(!is_searchable(""));
+        assert!(!is_searchable(" a "));
+        assert!(is_searchable("al"));
+        assert!(is_searchable("  bob@"));
+    }
+}
@@ -31,10 +31,22 @@ pub mod config;
 /// Messaging types for Telegram-style chat
 pub mod messaging;
 
+/// Braid Merge-Type negotiation
+pub mod merge_type;
+
+/// WebSocket frames
+pub mod socket;
+
+/// Opt-in reconnection telemetry
+pub mod telemetry;
+
 /// Re-export commonly used types for convenience
 pub use message::Message;
-pub use event::{RealtimeEvent, EventType};
-pub use error::SharedError;
+pub use event::{ActivityEvent, ActivityKind, ConversationEvent, DeliveryReceiptEvent, PresenceEvent, ReadReceiptEvent, RealtimeEvent, EventType, PRESENCE_HEARTBEAT_SECS};
+pub use error::{ErrorBody, ErrorCode, SharedError};
 pub use crdt::{CRDTOperation, DocumentState, CRDTPatch, ApplyOperationsRequest, ApplyOperationsResponse, DocumentMetadata};
 pub use config::{AppConfig, AppConfigBuilder, ConfigError};
+pub use merge_type::{MergeType, UnsupportedMergeType, MERGE_TYPE_HEADER};
+pub use socket::{ClientFrame, ServerFrame};
+pub use telemetry::{ConnectionReport, ConnectionTransport, DisconnectReason};
 
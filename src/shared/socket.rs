@@ -0,0 +1,76 @@
+//! WebSocket Frames
+//!
+//! The WebSocket endpoints (`GET /ws` and
+//! `GET /sync/conversations/{conversation_id}/ws`) carry the same data as
+//! the SSE streams plus the client's typing and receipt updates, so one
+//! connection does the work of a subscription and several POST/PUTs. Every
+//! text frame is one JSON object tagged by `type`.
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::shared::event::{ActivityKind, RealtimeEvent};
+use crate::shared::messaging::ChatMessage;
+
+/// Frame sent by the server
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum ServerFrame {
+    /// A new or changed message in the subscribed conversation
+    Message(ChatMessage),
+    /// A realtime event that passed the connection's `types` filter
+    Event(RealtimeEvent),
+    /// Answer to a [`ClientFrame::Ping`]
+    Pong { id: u64 },
+    /// A client frame was refused; the connection stays open
+    Error { message: String },
+}
+
+/// Frame sent by the client
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientFrame {
+    /// Composer activity, like `POST /sync/conversations/{id}/typing`
+    Typing {
+        conversation_id: Uuid,
+        /// Name shown in the indicator
+        user: String,
+        is_typing: bool,
+        #[serde(default)]
+        kind: ActivityKind,
+    },
+    /// Like `PUT .../messages/{message_id}/read`
+    Read { conversation_id: Uuid, message_id: Uuid },
+    /// Like `PUT .../messages/{message_id}/delivered`
+    Delivered { conversation_id: Uuid, message_id: Uuid },
+    /// Heartbeat; answered with a pong carrying the same id
+    Ping { id: u64 },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_frames_are_tagged_by_type() {
+        let frame = ClientFrame::Read { conversation_id: Uuid::nil(), message_id: Uuid::nil() };
+        let json = serde_json::to_value(&frame).unwrap();
+        assert_eq!(json["type"], "read");
+        assert_eq!(serde_json::from_value::<ClientFrame>(json).unwrap(), frame);
+
+        let typing: ClientFrame = serde_json::from_str(&format!(
+            r#"{{"type":"typing","conversation_id":"{}","user":"alice","is_typing":true}}"#,
+            Uuid::nil()
+        ))
+        .unwrap();
+        assert!(matches!(typing, ClientFrame::Typing { kind: ActivityKind::Typing, .. }));
+    }
+
+    #[test]
+    fn test_server_event_frame_round_trips() {
+        let frame = ServerFrame::Event(RealtimeEvent::typing("bob".to_string(), false));
+        let json = serde_json::to_string(&frame).unwrap();
+        assert!(json.starts_with(r#"{"type":"event","data":"#));
+        assert_eq!(serde_json::from_str::<ServerFrame>(&json).unwrap(), frame);
+    }
+}
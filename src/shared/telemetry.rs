@@ -0,0 +1,82 @@
+//! Connection Telemetry
+//!
+//! Clients that opt in report how their subscriptions recovered from a
+//! dropped connection to `POST /api/telemetry/connection`, so flaky networks
+//! show up in aggregate next to the server's own reconnect metrics. A report
+//! covers one outage, from the first failure until the client was connected
+//! again or gave up.
+//!
+//! Reports carry no user, conversation or network identifiers, only what
+//! the client's backoff did.
+
+use serde::{Deserialize, Serialize};
+
+/// Most reconnect attempts a report may claim
+pub const MAX_REPORTED_ATTEMPTS: u32 = 1_000;
+
+/// Most time spent backing off a report may claim (one day)
+pub const MAX_REPORTED_BACKOFF_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// How the subscription reached the server
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionTransport {
+    Sse,
+    #[serde(rename = "websocket")]
+    WebSocket,
+}
+
+impl ConnectionTransport {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Sse => "sse",
+            Self::WebSocket => "websocket",
+        }
+    }
+}
+
+/// Why a subscription had to reconnect
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DisconnectReason {
+    /// The connection couldn't be opened
+    Network,
+    /// The server answered with an error status
+    Http,
+    /// An open connection failed while reading or writing
+    Stream,
+    /// The server stopped answering heartbeats
+    Stalled,
+}
+
+impl DisconnectReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Network => "network",
+            Self::Http => "http",
+            Self::Stream => "stream",
+            Self::Stalled => "stalled",
+        }
+    }
+}
+
+/// One outage, as reported by a client
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConnectionReport {
+    pub transport: ConnectionTransport,
+    /// The failure that started the outage
+    pub reason: DisconnectReason,
+    /// Reconnect attempts made
+    pub attempts: u32,
+    /// Time spent waiting between attempts
+    pub backoff_ms: u64,
+    /// `false` if the client gave up
+    pub recovered: bool,
+}
+
+impl ConnectionReport {
+    /// Whether the numbers are within what a real client could report
+    pub fn is_plausible(&self) -> bool {
+        self.attempts <= MAX_REPORTED_ATTEMPTS && self.backoff_ms <= MAX_REPORTED_BACKOFF_MS
+    }
+}